@@ -3,8 +3,8 @@ mod options;
 use anyhow::Result;
 use log::{error, info};
 use movies_db::{
-    file_storage::FileStorage, Options as ServiceOptions, Service,
-    SqliteMoviesIndex as MoviesIndexImpl,
+    file_storage::FileStorage, CachedMoviesIndex, Options as ServiceOptions, Service,
+    SqliteMoviesIndex,
 };
 use options::Options;
 
@@ -29,7 +29,7 @@ fn initialize_logging(filter: LevelFilter) {
                 "{}:{} {} [{}] - {}",
                 record.file().unwrap_or("unknown"),
                 record.line().unwrap_or(0),
-                chrono::Local::now().format("%Y-%m-%dT%H:%M:%S"),
+                chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
                 record.level(),
                 record.args()
             )
@@ -43,9 +43,10 @@ async fn run_program() -> Result<()> {
     let options = parse_args()?;
     initialize_logging(LevelFilter::from(options.log_level));
 
-    let service_options: ServiceOptions = options.into();
+    let service_options = ServiceOptions::try_from(options)?;
 
-    let service: Service<MoviesIndexImpl, FileStorage> = Service::new(&service_options)?;
+    let service: Service<CachedMoviesIndex<SqliteMoviesIndex>, FileStorage> =
+        Service::new(&service_options)?;
     service.run().await?;
 
     Ok(())
@@ -1,9 +1,17 @@
+use std::net::{SocketAddr, ToSocketAddrs};
 use std::path::PathBuf;
 
-use clap::{Parser, ValueEnum};
+use anyhow::{anyhow, Result};
+use chrono::FixedOffset;
+
+use clap::{ArgAction, Parser, ValueEnum};
 use log::LevelFilter;
 
-use movies_db::Options as ServiceOptions;
+use movies_db::{
+    ffmpeg::default_max_ffmpeg_processes, DescriptionLengthPolicy, DownloadOffload,
+    FilenameTagsPolicy, LockGranularity, NoVideoStreamPolicy, Options as ServiceOptions,
+    PreviewBackpressurePolicy, ScreenshotMargin,
+};
 
 #[derive(ValueEnum, Clone, Copy, Debug)]
 pub enum LogLevel {
@@ -26,6 +34,85 @@ impl From<LogLevel> for LevelFilter {
     }
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum DescriptionPolicy {
+    Reject,
+    Truncate,
+}
+
+impl From<DescriptionPolicy> for DescriptionLengthPolicy {
+    fn from(value: DescriptionPolicy) -> Self {
+        match value {
+            DescriptionPolicy::Reject => DescriptionLengthPolicy::Reject,
+            DescriptionPolicy::Truncate => DescriptionLengthPolicy::Truncate,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum PreviewBackpressure {
+    Wait,
+    Drop,
+    Defer,
+}
+
+impl From<PreviewBackpressure> for PreviewBackpressurePolicy {
+    fn from(value: PreviewBackpressure) -> Self {
+        match value {
+            PreviewBackpressure::Wait => PreviewBackpressurePolicy::Wait,
+            PreviewBackpressure::Drop => PreviewBackpressurePolicy::Drop,
+            PreviewBackpressure::Defer => PreviewBackpressurePolicy::Defer,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum NoVideoStream {
+    FlagUnsupported,
+    Reject,
+}
+
+impl From<NoVideoStream> for NoVideoStreamPolicy {
+    fn from(value: NoVideoStream) -> Self {
+        match value {
+            NoVideoStream::FlagUnsupported => NoVideoStreamPolicy::FlagUnsupported,
+            NoVideoStream::Reject => NoVideoStreamPolicy::Reject,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum FilenameTags {
+    Disabled,
+    Suggest,
+    Apply,
+}
+
+impl From<FilenameTags> for FilenameTagsPolicy {
+    fn from(value: FilenameTags) -> Self {
+        match value {
+            FilenameTags::Disabled => FilenameTagsPolicy::Disabled,
+            FilenameTags::Suggest => FilenameTagsPolicy::Suggest,
+            FilenameTags::Apply => FilenameTagsPolicy::Apply,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum LockScope {
+    PerMovie,
+    Global,
+}
+
+impl From<LockScope> for LockGranularity {
+    fn from(value: LockScope) -> Self {
+        match value {
+            LockScope::PerMovie => LockGranularity::PerMovie,
+            LockScope::Global => LockGranularity::Global,
+        }
+    }
+}
+
 /// CLI interface to test different occlusion culler algorithms.
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
@@ -45,14 +132,417 @@ pub struct Options {
     /// The path to where ffmpeg and ffprobe are located
     #[arg(short, long, default_value = "/usr/bin/")]
     pub ffmpeg: PathBuf,
+
+    /// Whether the service fails to start if ffmpeg or ffprobe are not available
+    #[arg(long, default_value_t = true, action = ArgAction::Set)]
+    pub require_ffmpeg: bool,
+
+    /// The maximal number of queued preview requests
+    #[arg(long, default_value_t = 1024)]
+    pub preview_queue_capacity: usize,
+
+    /// The policy for preview requests if the preview queue is full
+    #[arg(long, value_enum, default_value_t = PreviewBackpressure::Wait)]
+    pub preview_backpressure: PreviewBackpressure,
+
+    /// The number of seconds after which clients should request deferred previews again
+    #[arg(long, default_value_t = 60)]
+    pub preview_retry_after: u32,
+
+    /// The policy for uploaded movie files without any video stream, e.g., audio-only files
+    #[arg(long, value_enum, default_value_t = NoVideoStream::FlagUnsupported)]
+    pub no_video_stream_policy: NoVideoStream,
+
+    /// The handling of the tags extracted from the filenames of uploaded movie files
+    #[arg(long, value_enum, default_value_t = FilenameTags::Disabled)]
+    pub filename_tags: FilenameTags,
+
+    /// An additional token extracted as tag from the filenames, can be given multiple times
+    #[arg(long = "filename-tag-token")]
+    pub filename_tag_tokens: Vec<String>,
+
+    /// The maximal number of concurrently running ffmpeg processes, defaults to the number of CPUs
+    #[arg(long)]
+    pub max_ffmpeg_processes: Option<usize>,
+
+    /// The number of seconds after which an ffmpeg or ffprobe process is killed, 0 disables the
+    /// timeout
+    #[arg(long, default_value_t = 600)]
+    pub ffmpeg_timeout: u64,
+
+    /// The size in bytes of the buffer used for writing uploaded data
+    #[arg(long, default_value_t = 1024 * 1024)]
+    pub upload_buffer_size: usize,
+
+    /// The maximal number of multipart fields processed per upload request
+    #[arg(long, default_value_t = 16)]
+    pub max_multipart_fields: usize,
+
+    /// The maximal size in bytes of an imported movie bundle, 0 disables the limit
+    #[arg(long, default_value_t = 64 * 1024 * 1024 * 1024)]
+    pub max_bundle_size: u64,
+
+    /// The maximal number of tags returned by the list of all tags, 0 disables the limit
+    #[arg(long, default_value_t = 10_000)]
+    pub max_tag_list_size: usize,
+
+    /// The maximal number of pooled connections to the SQLite database
+    #[arg(long, default_value_t = 8)]
+    pub db_pool_size: usize,
+
+    /// The maximal number of characters of a movie description
+    #[arg(long)]
+    pub max_description_length: Option<usize>,
+
+    /// The policy for descriptions exceeding the maximal description length
+    #[arg(long, value_enum, default_value_t = DescriptionPolicy::Reject)]
+    pub description_length_policy: DescriptionPolicy,
+
+    /// Reject any attempt to overwrite an already written movie file
+    #[arg(long)]
+    pub immutable_files: bool,
+
+    /// Keep the original casing of tags instead of converting them to lower case
+    #[arg(long)]
+    pub preserve_tag_case: bool,
+
+    /// The maximal number of movies kept in the read cache, 0 disables the cache
+    #[arg(long, default_value_t = 0)]
+    pub movie_cache_size: usize,
+
+    /// The size in bytes above which descriptions are stored separately
+    #[arg(long)]
+    pub description_offload_threshold: Option<usize>,
+
+    /// The duration in seconds for which clients may cache downloaded screenshots
+    #[arg(long, default_value_t = 7 * 24 * 60 * 60)]
+    pub screenshot_cache_max_age: u32,
+
+    /// The maximal total size in bytes of the screenshots kept in memory, 0 disables the cache
+    #[arg(long, default_value_t = 0)]
+    pub screenshot_memory_cache_size: usize,
+
+    /// The number of most recent movies whose screenshots are cached in memory on startup
+    #[arg(long, default_value_t = 100)]
+    pub screenshot_warm_up_count: usize,
+
+    /// The path to an image served for movies which have no screenshot yet
+    #[arg(long)]
+    pub placeholder_screenshot: Option<PathBuf>,
+
+    /// The secret for signing the time-limited URLs of shared movies
+    #[arg(long)]
+    pub share_secret: Option<String>,
+
+    /// An API key granting access to the mutating endpoints, can be given multiple times
+    #[arg(long = "api-key")]
+    pub api_keys: Vec<String>,
+
+    /// The UTC offset used for grouping movies by date, e.g., "+02:00"
+    #[arg(long)]
+    pub display_timezone: Option<FixedOffset>,
+
+    /// Transcode movies requested as MP4 on the fly if they are stored in another format
+    #[arg(long)]
+    pub transcode_on_the_fly: bool,
+
+    /// Compute perceptual hashes of new screenshots for finding near-duplicate movies
+    #[arg(long)]
+    pub compute_screenshot_hashes: bool,
+
+    /// The number of screenshots taken of each movie at evenly spaced time stamps
+    #[arg(long, default_value_t = 1)]
+    pub num_previews: usize,
+
+    /// The position of the main screenshot as fraction of the duration within (0, 1)
+    #[arg(long, default_value_t = 0.5)]
+    pub preview_position: f64,
+
+    /// The margin at the beginning of each movie skipped by the screenshots, either in seconds,
+    /// e.g., "90", or in percent of the duration, e.g., "5%"
+    #[arg(long, default_value = "0")]
+    pub screenshot_skip_intro: String,
+
+    /// The margin at the end of each movie skipped by the screenshots, either in seconds, e.g.,
+    /// "300", or in percent of the duration, e.g., "10%"
+    #[arg(long, default_value = "0")]
+    pub screenshot_skip_outro: String,
+
+    /// The number of replaced screenshots kept per movie, 0 disables the screenshot history
+    #[arg(long, default_value_t = 0)]
+    pub screenshot_history_size: usize,
+
+    /// Generate a short animated GIF preview of each movie in addition to its screenshots
+    #[arg(long)]
+    pub animated_preview: bool,
+
+    /// The number of seconds between two periodic flushes of buffered writes, 0 disables them
+    #[arg(long, default_value_t = 30)]
+    pub flush_interval: u64,
+
+    /// The number of seconds after which a download is aborted if the client pulls no data, 0
+    /// disables the timeout
+    #[arg(long, default_value_t = 300)]
+    pub download_idle_timeout: u64,
+
+    /// Log and aggregate the execution times of the index queries for diagnosing slow queries
+    #[arg(long)]
+    pub query_timing: bool,
+
+    /// The execution time in milliseconds from which on an index query is logged as slow
+    #[arg(long, default_value_t = 100)]
+    pub slow_query_threshold: u64,
+
+    /// Offload movie downloads onto nginx by an X-Accel-Redirect header to the given internal
+    /// location, under which nginx serves the root directory, e.g., "/protected"
+    #[arg(long)]
+    pub x_accel_redirect: Option<String>,
+
+    /// Offload movie downloads onto the reverse proxy by an X-Sendfile header
+    #[arg(long, conflicts_with = "x_accel_redirect")]
+    pub x_sendfile: bool,
+
+    /// The maximal number of recent activity events kept in memory
+    #[arg(long, default_value_t = 256)]
+    pub activity_log_capacity: usize,
+
+    /// The granularity of the locks that serialize the multi-step write operations on movies
+    #[arg(long, value_enum, default_value_t = LockScope::PerMovie)]
+    pub lock_granularity: LockScope,
+
+    /// The maximal number of concurrent uploads per client IP address, 0 disables the limit
+    #[arg(long, default_value_t = 0)]
+    pub max_uploads_per_client: usize,
+
+    /// The name of an additional library to serve, can be given multiple times
+    #[arg(long = "library")]
+    pub libraries: Vec<String>,
+}
+
+/// Parses the given address of the http server. Besides literal socket addresses, e.g.,
+/// "0.0.0.0:3030", hostnames are supported, e.g., "localhost:3030", which are resolved to their
+/// first socket address.
+///
+/// # Arguments
+/// * `address` - The address to parse, surrounding whitespace is ignored.
+fn parse_http_address(address: &str) -> Result<SocketAddr> {
+    let address = address.trim();
+    if let Ok(socket_addr) = address.parse::<SocketAddr>() {
+        return Ok(socket_addr);
+    }
+
+    let mut socket_addrs = address.to_socket_addrs().map_err(|err| {
+        anyhow!(
+            "Invalid address '{}', expected <host>:<port>: {}",
+            address,
+            err
+        )
+    })?;
+
+    socket_addrs
+        .next()
+        .ok_or_else(|| anyhow!("The address '{}' can't be resolved", address))
 }
 
-impl From<Options> for ServiceOptions {
-    fn from(options: Options) -> Self {
-        ServiceOptions {
+/// Parses the given screenshot margin, which is either given in seconds, e.g., "90", or in percent
+/// of the duration of the movie, e.g., "5%".
+///
+/// # Arguments
+/// * `margin` - The margin to parse, surrounding whitespace is ignored.
+fn parse_screenshot_margin(margin: &str) -> Result<ScreenshotMargin> {
+    let margin = margin.trim();
+    let invalid = || {
+        anyhow!(
+            "Invalid screenshot margin '{}', expected <seconds> or <percent>%",
+            margin
+        )
+    };
+
+    match margin.strip_suffix('%') {
+        Some(percent) => {
+            let percent: f64 = percent.trim().parse().map_err(|_| invalid())?;
+            if !(0.0..=100.0).contains(&percent) {
+                return Err(invalid());
+            }
+
+            Ok(ScreenshotMargin::Fraction(percent / 100.0))
+        }
+        None => {
+            let seconds: f64 = margin.parse().map_err(|_| invalid())?;
+            if !seconds.is_finite() || seconds < 0.0 {
+                return Err(invalid());
+            }
+
+            Ok(ScreenshotMargin::Seconds(seconds))
+        }
+    }
+}
+
+impl TryFrom<Options> for ServiceOptions {
+    type Error = anyhow::Error;
+
+    fn try_from(options: Options) -> Result<Self> {
+        Ok(ServiceOptions {
             root_dir: options.root_dir,
-            http_address: options.address.parse().unwrap(),
+            http_address: parse_http_address(&options.address)?,
             ffmpeg: options.ffmpeg,
+            require_ffmpeg: options.require_ffmpeg,
+            preview_queue_capacity: options.preview_queue_capacity,
+            preview_backpressure: options.preview_backpressure.into(),
+            preview_retry_after: options.preview_retry_after,
+            no_video_stream_policy: options.no_video_stream_policy.into(),
+            filename_tags_policy: options.filename_tags.into(),
+            filename_tag_tokens: options.filename_tag_tokens,
+            max_ffmpeg_processes: options
+                .max_ffmpeg_processes
+                .unwrap_or_else(default_max_ffmpeg_processes),
+            ffmpeg_timeout: options.ffmpeg_timeout,
+            upload_buffer_size: options.upload_buffer_size,
+            max_multipart_fields: options.max_multipart_fields,
+            max_bundle_size: options.max_bundle_size,
+            max_tag_list_size: options.max_tag_list_size,
+            db_pool_size: options.db_pool_size,
+            max_description_length: options.max_description_length,
+            description_length_policy: options.description_length_policy.into(),
+            immutable_files: options.immutable_files,
+            preserve_tag_case: options.preserve_tag_case,
+            movie_cache_size: options.movie_cache_size,
+            description_offload_threshold: options.description_offload_threshold,
+            screenshot_cache_max_age: options.screenshot_cache_max_age,
+            screenshot_memory_cache_size: options.screenshot_memory_cache_size,
+            screenshot_warm_up_count: options.screenshot_warm_up_count,
+            placeholder_screenshot: options.placeholder_screenshot,
+            share_secret: options.share_secret,
+            api_keys: options.api_keys,
+            display_timezone: options.display_timezone,
+            transcode_on_the_fly: options.transcode_on_the_fly,
+            compute_screenshot_hashes: options.compute_screenshot_hashes,
+            num_previews: options.num_previews,
+            preview_position: options.preview_position,
+            screenshot_skip_intro: parse_screenshot_margin(&options.screenshot_skip_intro)?,
+            screenshot_skip_outro: parse_screenshot_margin(&options.screenshot_skip_outro)?,
+            screenshot_history_size: options.screenshot_history_size,
+            animated_preview: options.animated_preview,
+            flush_interval: options.flush_interval,
+            download_idle_timeout: options.download_idle_timeout,
+            query_timing: options.query_timing,
+            slow_query_threshold: options.slow_query_threshold,
+            download_offload: match (options.x_accel_redirect, options.x_sendfile) {
+                (Some(location), _) => DownloadOffload::XAccelRedirect { location },
+                (None, true) => DownloadOffload::XSendfile,
+                (None, false) => DownloadOffload::Disabled,
+            },
+            activity_log_capacity: options.activity_log_capacity,
+            lock_granularity: options.lock_granularity.into(),
+            max_uploads_per_client: options.max_uploads_per_client,
+            libraries: options.libraries,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Parses the options from the given additional arguments.
+    fn parse_options(args: &[&str]) -> Options {
+        let mut all_args = vec!["movies-db-cli", "--root-dir", "/data"];
+        all_args.extend_from_slice(args);
+
+        Options::parse_from(all_args)
+    }
+
+    #[test]
+    fn test_parse_socket_address() {
+        let options = parse_options(&["--address", " 127.0.0.1:8080 "]);
+        let service_options = ServiceOptions::try_from(options).unwrap();
+        assert_eq!(
+            service_options.http_address,
+            SocketAddr::from(([127, 0, 0, 1], 8080))
+        );
+
+        assert_eq!(
+            parse_http_address("[::1]:3030").unwrap(),
+            "[::1]:3030".parse::<SocketAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_hostname_address() {
+        let socket_addr = parse_http_address("localhost:3030").unwrap();
+        assert!(socket_addr.ip().is_loopback());
+        assert_eq!(socket_addr.port(), 3030);
+    }
+
+    #[test]
+    fn test_parse_malformed_address() {
+        for address in ["127.0.0.1", "127.0.0.1:port", "localhost", ""] {
+            let options = parse_options(&["--address", address]);
+            assert!(
+                ServiceOptions::try_from(options).is_err(),
+                "Address {:?} must be rejected",
+                address
+            );
         }
     }
+
+    #[test]
+    fn test_parse_screenshot_margin() {
+        assert_eq!(
+            parse_screenshot_margin("90").unwrap(),
+            ScreenshotMargin::Seconds(90.0)
+        );
+        assert_eq!(
+            parse_screenshot_margin(" 12.5 ").unwrap(),
+            ScreenshotMargin::Seconds(12.5)
+        );
+        assert_eq!(
+            parse_screenshot_margin("5%").unwrap(),
+            ScreenshotMargin::Fraction(0.05)
+        );
+
+        for margin in ["-1", "inf", "NaN", "101%", "-5%", "%", "ten", ""] {
+            assert!(
+                parse_screenshot_margin(margin).is_err(),
+                "Margin {:?} must be rejected",
+                margin
+            );
+        }
+
+        let options = parse_options(&[]);
+        let service_options = ServiceOptions::try_from(options).unwrap();
+        assert_eq!(
+            service_options.screenshot_skip_intro,
+            ScreenshotMargin::default()
+        );
+    }
+
+    #[test]
+    fn test_download_offload() {
+        let offload = |args: &[&str]| {
+            ServiceOptions::try_from(parse_options(args))
+                .unwrap()
+                .download_offload
+        };
+
+        assert_eq!(offload(&[]), DownloadOffload::Disabled);
+        assert_eq!(
+            offload(&["--x-accel-redirect", "/protected"]),
+            DownloadOffload::XAccelRedirect {
+                location: "/protected".to_owned()
+            }
+        );
+        assert_eq!(offload(&["--x-sendfile"]), DownloadOffload::XSendfile);
+
+        // only one kind of offloading can be enabled
+        assert!(Options::try_parse_from([
+            "movies-db-cli",
+            "--root-dir",
+            "/data",
+            "--x-accel-redirect",
+            "/protected",
+            "--x-sendfile"
+        ])
+        .is_err());
+    }
 }
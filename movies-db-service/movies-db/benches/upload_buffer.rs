@@ -0,0 +1,61 @@
+//! Measures the write throughput of uploads with and without the upload buffer by writing
+//! 512 MiB in 4 KiB chunks, i.e., the size of typical multipart chunks, to the file storage.
+//!
+//! Run with `cargo bench --bench upload_buffer`.
+
+use std::time::Instant;
+
+use movies_db::{
+    file_storage::FileStorage, generate_movie_id, MovieDataType, MovieStorage, Options,
+};
+use tempdir::TempDir;
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+
+const TOTAL_SIZE: usize = 512 * 1024 * 1024;
+const CHUNK_SIZE: usize = 4 * 1024;
+
+/// Writes `TOTAL_SIZE` bytes in chunks of `CHUNK_SIZE` bytes to the given writer.
+async fn write_chunks<W: AsyncWrite + Unpin>(mut writer: W) {
+    let chunk = vec![0x42u8; CHUNK_SIZE];
+    for _ in 0..TOTAL_SIZE / CHUNK_SIZE {
+        writer.write_all(&chunk).await.unwrap();
+    }
+    writer.flush().await.unwrap();
+}
+
+#[tokio::main]
+async fn main() {
+    let root_dir = TempDir::new("movies-db-bench").unwrap();
+    let options = Options {
+        root_dir: root_dir.path().to_path_buf(),
+        ..Default::default()
+    };
+
+    let storage = FileStorage::new(&options).unwrap();
+
+    for buffer_size in [0, options.upload_buffer_size] {
+        let writer = storage
+            .write_movie_data(
+                generate_movie_id(),
+                MovieDataType::MovieData {
+                    ext: "mp4".to_owned(),
+                },
+            )
+            .await
+            .unwrap();
+
+        let start = Instant::now();
+        if buffer_size == 0 {
+            write_chunks(writer).await;
+        } else {
+            write_chunks(BufWriter::with_capacity(buffer_size, writer)).await;
+        }
+        let elapsed = start.elapsed().as_secs_f64();
+
+        println!(
+            "buffer size {:>8} bytes: {:.0} MiB/s",
+            buffer_size,
+            (TOTAL_SIZE / (1024 * 1024)) as f64 / elapsed
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use async_trait::async_trait;
+
+use crate::{
+    lru::Lru, AnimatedPreviewInfo, Error, IndexBatch, Movie, MovieDetailed, MovieFileInfo, MovieId,
+    MovieSearchQuery, MoviesIndex, Options, PreviewState, QueryStats, ScreenshotInfo, SearchFacets,
+    SubtitleInfo, VideoMetadata,
+};
+
+/// A movies index wrapping another index with a read-through cache for `get_movie`.
+/// Any mutation of a movie invalidates its cache entry.
+/// The size of the cache is configured by `Options::movie_cache_size`, where a size of zero
+/// disables the cache.
+pub struct CachedMoviesIndex<I: MoviesIndex> {
+    index: I,
+    cache: Mutex<Lru<MovieId, MovieDetailed>>,
+}
+
+impl<I: MoviesIndex> CachedMoviesIndex<I> {
+    /// Removes the movie for the given ID from the cache.
+    ///
+    /// # Arguments
+    /// * `id` - The ID of the movie to invalidate.
+    fn invalidate(&self, id: &MovieId) {
+        self.cache.lock().unwrap().remove(id);
+    }
+}
+
+#[async_trait]
+impl<I: MoviesIndex> MoviesIndex for CachedMoviesIndex<I> {
+    fn new(options: &Options) -> Result<Self, Error> {
+        Ok(Self {
+            index: I::new(options)?,
+            cache: Mutex::new(Lru::new(options.movie_cache_size)),
+        })
+    }
+
+    async fn add_movie(&mut self, movie: Movie) -> Result<MovieId, Error> {
+        self.index.add_movie(movie).await
+    }
+
+    async fn add_movie_with_id(&mut self, id: MovieId, movie: Movie) -> Result<MovieId, Error> {
+        self.invalidate(&id);
+        self.index.add_movie_with_id(id, movie).await
+    }
+
+    async fn add_movies(&mut self, movies: Vec<Movie>) -> Result<Vec<MovieId>, Error> {
+        self.index.add_movies(movies).await
+    }
+
+    async fn get_movie(&self, id: &MovieId) -> Result<MovieDetailed, Error> {
+        if let Some(movie) = self.cache.lock().unwrap().get(id) {
+            return Ok(movie.clone());
+        }
+
+        // mutations require exclusive access, s.t. the movie can't change in between
+        let movie = self.index.get_movie(id).await?;
+        // each movie weighs one, s.t. the size of the cache bounds the number of movies
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(id.clone(), movie.clone(), 1);
+
+        Ok(movie)
+    }
+
+    async fn movie_exists(&self, id: &MovieId) -> Result<bool, Error> {
+        if self.cache.lock().unwrap().contains(id) {
+            return Ok(true);
+        }
+
+        self.index.movie_exists(id).await
+    }
+
+    async fn update_movie_file_info(
+        &mut self,
+        id: &MovieId,
+        movie_file_info: MovieFileInfo,
+    ) -> Result<(), Error> {
+        self.invalidate(id);
+        self.index.update_movie_file_info(id, movie_file_info).await
+    }
+
+    async fn remove_movie_file_info(&mut self, id: &MovieId) -> Result<(), Error> {
+        self.invalidate(id);
+        self.index.remove_movie_file_info(id).await
+    }
+
+    async fn update_screenshot_info(
+        &mut self,
+        id: &MovieId,
+        screenshot_info: ScreenshotInfo,
+    ) -> Result<(), Error> {
+        self.invalidate(id);
+        self.index.update_screenshot_info(id, screenshot_info).await
+    }
+
+    async fn update_video_metadata(
+        &mut self,
+        id: &MovieId,
+        video_metadata: VideoMetadata,
+    ) -> Result<(), Error> {
+        self.invalidate(id);
+        self.index.update_video_metadata(id, video_metadata).await
+    }
+
+    async fn update_animated_preview_info(
+        &mut self,
+        id: &MovieId,
+        animated_preview_info: AnimatedPreviewInfo,
+    ) -> Result<(), Error> {
+        self.invalidate(id);
+        self.index
+            .update_animated_preview_info(id, animated_preview_info)
+            .await
+    }
+
+    async fn add_subtitle_info(
+        &mut self,
+        id: &MovieId,
+        subtitle_info: SubtitleInfo,
+    ) -> Result<(), Error> {
+        self.invalidate(id);
+        self.index.add_subtitle_info(id, subtitle_info).await
+    }
+
+    async fn get_subtitles(&self, id: &MovieId) -> Result<Vec<SubtitleInfo>, Error> {
+        self.index.get_subtitles(id).await
+    }
+
+    async fn update_preview_state(
+        &mut self,
+        id: &MovieId,
+        preview_state: PreviewState,
+    ) -> Result<(), Error> {
+        self.invalidate(id);
+        self.index.update_preview_state(id, preview_state).await
+    }
+
+    async fn touch_movie(&mut self, id: &MovieId) -> Result<(), Error> {
+        self.invalidate(id);
+        self.index.touch_movie(id).await
+    }
+
+    async fn set_featured(&mut self, id: &MovieId, featured: bool) -> Result<(), Error> {
+        self.invalidate(id);
+        self.index.set_featured(id, featured).await
+    }
+
+    async fn add_tags(&mut self, id: &MovieId, tags: &[String]) -> Result<(), Error> {
+        self.invalidate(id);
+        self.index.add_tags(id, tags).await
+    }
+
+    async fn update_movie(&mut self, id: &MovieId, movie: Movie) -> Result<(), Error> {
+        self.invalidate(id);
+        self.index.update_movie(id, movie).await
+    }
+
+    async fn apply_batch(&mut self, batch: IndexBatch) -> Result<(), Error> {
+        for edit in batch.edits() {
+            self.invalidate(edit.id());
+        }
+
+        self.index.apply_batch(batch).await
+    }
+
+    async fn rename_tag(&mut self, from: &str, to: &str) -> Result<usize, Error> {
+        // the rename may touch any movie
+        self.cache.lock().unwrap().clear();
+        self.index.rename_tag(from, to).await
+    }
+
+    async fn rename_tags(
+        &mut self,
+        renames: &BTreeMap<String, String>,
+    ) -> Result<BTreeMap<String, usize>, Error> {
+        // the renames may touch any movie
+        self.cache.lock().unwrap().clear();
+        self.index.rename_tags(renames).await
+    }
+
+    async fn remove_movie(&mut self, id: &MovieId) -> Result<(), Error> {
+        self.invalidate(id);
+        self.index.remove_movie(id).await
+    }
+
+    async fn search_movies(&self, query: MovieSearchQuery) -> Result<Vec<MovieId>, Error> {
+        self.index.search_movies(query).await
+    }
+
+    async fn search_movies_count(&self, query: MovieSearchQuery) -> Result<usize, Error> {
+        self.index.search_movies_count(query).await
+    }
+
+    async fn get_tag_list_with_count(&self) -> Result<Vec<(String, usize)>, Error> {
+        self.index.get_tag_list_with_count().await
+    }
+
+    async fn get_top_tags(&self, limit: usize) -> Result<Vec<(String, usize)>, Error> {
+        self.index.get_top_tags(limit).await
+    }
+
+    async fn search_tags(&self, prefix: &str, limit: usize) -> Result<Vec<(String, usize)>, Error> {
+        self.index.search_tags(prefix, limit).await
+    }
+
+    async fn suggest_tags(&self, tags: &[String]) -> Result<Vec<(String, usize)>, Error> {
+        self.index.suggest_tags(tags).await
+    }
+
+    async fn get_year_histogram(&self) -> Result<Vec<(u16, usize)>, Error> {
+        self.index.get_year_histogram().await
+    }
+
+    async fn get_search_facets(&self, query: MovieSearchQuery) -> Result<SearchFacets, Error> {
+        self.index.get_search_facets(query).await
+    }
+
+    async fn update_screenshot_hash(&mut self, id: &MovieId, hash: u64) -> Result<(), Error> {
+        self.index.update_screenshot_hash(id, hash).await
+    }
+
+    async fn get_screenshot_hashes(&self) -> Result<Vec<(MovieId, u64)>, Error> {
+        self.index.get_screenshot_hashes().await
+    }
+
+    fn get_query_stats(&self) -> Option<BTreeMap<String, QueryStats>> {
+        self.index.get_query_stats()
+    }
+
+    async fn get_letter_offset(
+        &self,
+        query: MovieSearchQuery,
+        letter: char,
+    ) -> Result<usize, Error> {
+        self.index.get_letter_offset(query, letter).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::SimpleMoviesIndex;
+
+    fn create_index(movie_cache_size: usize) -> CachedMoviesIndex<SimpleMoviesIndex> {
+        let mut options = Options::default();
+        options.movie_cache_size = movie_cache_size;
+
+        CachedMoviesIndex::new(&options).unwrap()
+    }
+
+    fn create_movie(title: &str) -> Movie {
+        Movie {
+            title: title.to_owned(),
+            description: String::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    fn is_cached(index: &CachedMoviesIndex<SimpleMoviesIndex>, id: &MovieId) -> bool {
+        index.cache.lock().unwrap().contains(id)
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_on_update() {
+        let mut index = create_index(8);
+
+        let id = index.add_movie(create_movie("Das Boot")).await.unwrap();
+        assert!(!is_cached(&index, &id));
+
+        let movie = index.get_movie(&id).await.unwrap();
+        assert!(movie.screenshot_file_info.is_none());
+        assert!(is_cached(&index, &id));
+
+        let screenshot_info = ScreenshotInfo {
+            mime_type: "image/png".to_owned(),
+            extension: "png".to_owned(),
+            count: 1,
+        };
+        index
+            .update_screenshot_info(&id, screenshot_info.clone())
+            .await
+            .unwrap();
+        assert!(!is_cached(&index, &id));
+
+        // the cache is filled again with the updated movie
+        let movie = index.get_movie(&id).await.unwrap();
+        assert_eq!(movie.screenshot_file_info, Some(screenshot_info.clone()));
+        let movie = index.get_movie(&id).await.unwrap();
+        assert_eq!(movie.screenshot_file_info, Some(screenshot_info));
+
+        index.remove_movie(&id).await.unwrap();
+        assert!(matches!(
+            index.get_movie(&id).await,
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_least_recently_used_eviction() {
+        let mut index = create_index(2);
+
+        let boot = index.add_movie(create_movie("Das Boot")).await.unwrap();
+        let alien = index.add_movie(create_movie("Alien")).await.unwrap();
+        let heat = index.add_movie(create_movie("Heat")).await.unwrap();
+
+        index.get_movie(&boot).await.unwrap();
+        index.get_movie(&alien).await.unwrap();
+        index.get_movie(&boot).await.unwrap();
+
+        // alien is the least recently used movie
+        index.get_movie(&heat).await.unwrap();
+        assert!(is_cached(&index, &boot));
+        assert!(!is_cached(&index, &alien));
+        assert!(is_cached(&index, &heat));
+    }
+
+    #[tokio::test]
+    async fn test_disabled_cache() {
+        let mut index = create_index(0);
+
+        let id = index.add_movie(create_movie("Das Boot")).await.unwrap();
+        assert_eq!(index.get_movie(&id).await.unwrap().movie.title, "Das Boot");
+        assert!(!is_cached(&index, &id));
+    }
+}
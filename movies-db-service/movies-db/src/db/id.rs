@@ -2,7 +2,40 @@ use uuid::Uuid;
 
 pub type MovieId = String;
 
+/// The maximal length of a movie ID.
+pub const MAX_MOVIE_ID_LENGTH: usize = 64;
+
 /// Generates and returns a new movie random ID
 pub fn generate_movie_id() -> MovieId {
     Uuid::new_v4().to_string()
 }
+
+/// Returns true if the given movie ID is valid, i.e., it is not empty, not longer than
+/// `MAX_MOVIE_ID_LENGTH` and only consists of ASCII alphanumeric characters, '-' and '_'.
+/// This makes sure that the ID can safely be used as part of a file path.
+///
+/// # Arguments
+/// * `id` - The movie ID to check.
+pub fn is_valid_movie_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_MOVIE_ID_LENGTH
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_movie_id() {
+        assert!(is_valid_movie_id(&generate_movie_id()));
+        assert!(is_valid_movie_id("imdb_tt0082096"));
+
+        assert!(!is_valid_movie_id(""));
+        assert!(!is_valid_movie_id("../movies.db"));
+        assert!(!is_valid_movie_id("foo bar"));
+        assert!(!is_valid_movie_id(&"a".repeat(MAX_MOVIE_ID_LENGTH + 1)));
+    }
+}
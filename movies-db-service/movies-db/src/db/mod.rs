@@ -1,9 +1,15 @@
+mod cached_movies_index;
 mod id;
 mod movies_index;
+mod query_timing;
 mod simple_movies_index;
 mod sqlite_movies_index;
+mod tag_renames;
 
+pub use cached_movies_index::*;
 pub use id::*;
 pub use movies_index::*;
+pub use query_timing::*;
 pub use simple_movies_index::*;
 pub use sqlite_movies_index::*;
+pub use tag_renames::*;
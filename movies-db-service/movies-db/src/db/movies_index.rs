@@ -1,10 +1,16 @@
-use crate::{Error, MovieId, Options};
+use std::collections::BTreeMap;
 
-use chrono::{DateTime, Utc};
+use crate::{DescriptionLengthPolicy, Error, MovieId, Options, QueryStats};
+
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
 
 use async_trait::async_trait;
 
+/// The ellipsis appended to truncated descriptions.
+const ELLIPSIS: char = '…';
+
 /// A single entry in the movie database.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Movie {
@@ -20,45 +26,159 @@ pub struct Movie {
     pub tags: Vec<String>,
 }
 
+impl Movie {
+    /// Makes sure that the description does not exceed the given maximal number of characters,
+    /// where a character is a grapheme cluster. Depending on the policy, a too long description
+    /// is either rejected or truncated s.t. together with the appended ellipsis it fits into the
+    /// limit.
+    ///
+    /// # Arguments
+    /// `max_length` - The maximal number of characters of the description.
+    /// `policy` - The policy for descriptions exceeding the limit.
+    pub fn limit_description(
+        &mut self,
+        max_length: usize,
+        policy: DescriptionLengthPolicy,
+    ) -> Result<(), Error> {
+        let length = self.description.graphemes(true).count();
+        if length <= max_length {
+            return Ok(());
+        }
+
+        match policy {
+            DescriptionLengthPolicy::Reject => Err(Error::InvalidArgument(format!(
+                "Description has {} characters, but at most {} are allowed",
+                length, max_length
+            ))),
+            DescriptionLengthPolicy::Truncate => {
+                let mut description: String = self
+                    .description
+                    .graphemes(true)
+                    .take(max_length.saturating_sub(1))
+                    .collect();
+
+                if max_length > 0 {
+                    description.push(ELLIPSIS);
+                }
+
+                self.description = description;
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The state of the preview generation of a movie.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum PreviewState {
+    /// The preview generation is queued or in progress.
+    Pending,
+
+    /// The preview has been generated.
+    Ready,
+
+    /// The preview generation failed, e.g., as the movie file is corrupt. The preview isn't
+    /// requested again automatically.
+    Failed { reason: String },
+
+    /// No previews can be generated for the movie, e.g., as the storage has no file paths.
+    Unsupported,
+}
+
+/// The kind of a job currently processing a movie.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    /// The preview of the movie is queued or being generated.
+    Preview,
+
+    /// The movie file is being uploaded.
+    Upload,
+
+    /// The movie file is being transcoded while it is streamed.
+    Transcode,
+
+    /// Derived data of the movie is being regenerated, e.g., its thumbnails.
+    Reprocess,
+}
+
 /// A single movie entry with timestamp.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MovieDetailed {
     pub movie: Movie,
     pub movie_file_info: Option<MovieFileInfo>,
     pub screenshot_file_info: Option<ScreenshotInfo>,
+
+    /// The time at which the movie has been added, which is always stored and serialized in UTC,
+    /// i.e., with a `Z` suffix.
     pub date: DateTime<Utc>,
+
+    /// The state of the preview generation, None if no preview has been requested yet.
+    #[serde(default)]
+    pub preview_state: Option<PreviewState>,
+
+    /// True if the movie is featured, e.g., as a staff pick, s.t. it can be pinned to the top of
+    /// the search results.
+    #[serde(default)]
+    pub featured: bool,
+
+    /// True if the movie is currently being processed, e.g., its preview is being generated.
+    /// The index doesn't track the jobs, s.t. this is only set in the responses of the service.
+    #[serde(default)]
+    pub processing: bool,
+
+    /// The kinds of the jobs currently processing the movie.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub active_jobs: Vec<JobKind>,
+
+    /// The metadata of the video stream of the movie file, None if it hasn't been extracted.
+    #[serde(default)]
+    pub video_metadata: Option<VideoMetadata>,
+
+    /// The info of the animated preview of the movie, None if it hasn't been generated.
+    #[serde(default)]
+    pub animated_preview_info: Option<AnimatedPreviewInfo>,
+
+    /// The sorted languages of the available subtitles, e.g., ["de", "en"].
+    #[serde(default)]
+    pub subtitle_languages: Vec<String>,
 }
 
 /// The sorting order for the movies.
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize, Default)]
 pub enum SortingField {
     #[serde(rename(serialize = "title", deserialize = "title"))]
     Title,
 
+    #[default]
     #[serde(rename(serialize = "date", deserialize = "date"))]
     Date,
 }
 
-impl Default for SortingField {
-    fn default() -> Self {
-        Self::Date
-    }
-}
-
 /// The sorting order for the movies.
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize, Default)]
 pub enum SortingOrder {
     #[serde(rename(serialize = "ascending", deserialize = "ascending"))]
     Ascending,
 
+    #[default]
     #[serde(rename(serialize = "descending", deserialize = "descending"))]
     Descending,
 }
 
-impl Default for SortingOrder {
-    fn default() -> Self {
-        Self::Descending
-    }
+/// The way the tags of a query must match the tags of a movie.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize, Default)]
+pub enum TagMatchMode {
+    /// The movie must match all tags of the query.
+    #[default]
+    #[serde(rename(serialize = "all", deserialize = "all"))]
+    All,
+
+    /// The movie must match at least one tag of the query.
+    #[serde(rename(serialize = "any", deserialize = "any"))]
+    Any,
 }
 
 /// The file info for a stored movie file.
@@ -69,6 +189,10 @@ pub struct MovieFileInfo {
 
     // the mime type of the movie file, e.g., "video/mp4"
     pub mime_type: String,
+
+    /// the name of the uploaded file as provided by the client, which is only kept for display
+    #[serde(default)]
+    pub original_filename: Option<String>,
 }
 
 /// The screenshot info for a stored movie file.
@@ -79,6 +203,78 @@ pub struct ScreenshotInfo {
 
     // the mime type of the screenshot file, e.g., "image/png"
     pub mime_type: String,
+
+    /// the number of screenshots taken of the movie, where the first one is the main screenshot
+    #[serde(default = "ScreenshotInfo::default_count")]
+    pub count: u32,
+}
+
+impl ScreenshotInfo {
+    fn default_count() -> u32 {
+        1
+    }
+}
+
+/// The info of the stored animated preview of a movie, i.e., a short looping clip of the movie.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnimatedPreviewInfo {
+    /// the extension of the animated preview file in lower case, e.g., "gif"
+    pub extension: String,
+
+    // the mime type of the animated preview file, e.g., "image/gif"
+    pub mime_type: String,
+}
+
+/// The info of a stored subtitle file of a movie.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SubtitleInfo {
+    /// the language of the subtitles, e.g., "en" or "pt-BR"
+    pub language: String,
+
+    /// the mime type of the subtitle file, e.g., "text/vtt"
+    pub mime_type: String,
+
+    /// the extension of the subtitle file in lower case, e.g., "vtt"
+    pub extension: String,
+}
+
+/// The technical metadata of the video stream of a stored movie file.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct VideoMetadata {
+    /// the width of the video in pixels
+    pub width: u32,
+
+    /// the height of the video in pixels
+    pub height: u32,
+
+    /// the name of the video codec, e.g., "h264"
+    pub codec: String,
+
+    /// the bitrate of the video in bits per second, if known
+    #[serde(default)]
+    pub bitrate: Option<u64>,
+
+    /// the number of frames per second, if known
+    #[serde(default)]
+    pub frame_rate: Option<f64>,
+
+    /// the duration of the movie in seconds, if known
+    #[serde(default)]
+    pub duration: Option<f64>,
+}
+
+/// The facets of a set of movies, i.e., the values of the movies with their number of
+/// occurrences, each ordered descending by the number of occurrences and ascending by the value.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SearchFacets {
+    /// The tags of the movies.
+    pub tags: Vec<(String, usize)>,
+
+    /// The years in which the movies were added, referring to the display timezone.
+    pub years: Vec<(u16, usize)>,
+
+    /// The file extensions of the movie files.
+    pub formats: Vec<(String, usize)>,
 }
 
 /// A query for searching movies in the database.
@@ -97,10 +293,66 @@ pub struct MovieSearchQuery {
     /// Wildcards are supported, e.g., *foo* will match any movie whose title contains "foo".
     pub title: Option<String>,
 
+    /// Optionally, a search string for the description of the movie with the same wildcard
+    /// semantics as the title, e.g., *alien* will match any movie whose description contains
+    /// "alien".
+    pub description: Option<String>,
+
+    /// Optionally, the point in time at or after which the movie must have been added.
+    #[serde(default)]
+    pub added_after: Option<DateTime<Utc>>,
+
+    /// Optionally, the point in time before which the movie must have been added.
+    #[serde(default)]
+    pub added_before: Option<DateTime<Utc>>,
+
+    /// Optionally, a time window relative to now within which the movie must have been added,
+    /// e.g., "24h" or "7d". The service resolves the window into `added_after` before the index
+    /// is searched. See `parse_relative_duration` for the supported units.
+    #[serde(default)]
+    pub added_within: Option<String>,
+
     /// A sorted list of lower case tags that must match the movie.
     #[serde(default)]
     pub tags: Vec<String>,
 
+    /// Defines whether the movie must match all or only any of the tags.
+    #[serde(default)]
+    pub tag_match_mode: TagMatchMode,
+
+    /// A list of tags that must not match the movie, e.g., to search for all sci-fi movies that
+    /// are not a tv show. The excluded tags are compared case-insensitively and always have to
+    /// match exactly.
+    #[serde(default)]
+    pub exclude_tags: Vec<String>,
+
+    /// If true, the tags of the query are prefixes, e.g., "brit" matches the tag "british".
+    /// Otherwise, the tags must match exactly.
+    #[serde(default)]
+    pub tag_prefix_match: bool,
+
+    /// If true, tags matching no known tag are replaced by the closest known tag within a small
+    /// edit distance, e.g., "germeny" matches "germany". The tags are corrected by the service
+    /// before the index is searched.
+    #[serde(default)]
+    pub tag_fuzzy_match: bool,
+
+    /// If true, the featured movies come first, followed by the remaining movies. Both parts are
+    /// sorted as requested.
+    #[serde(default)]
+    pub featured_first: bool,
+
+    /// Optionally, whether the movie must have subtitles. Together with `subtitle_lang`, it
+    /// refers to the subtitles of that language, e.g., false and "en" match all movies without
+    /// English subtitles.
+    #[serde(default)]
+    pub has_subtitles: Option<bool>,
+
+    /// Optionally, the language in which the movie must have subtitles, e.g., "en". The
+    /// language is compared case-insensitively.
+    #[serde(default)]
+    pub subtitle_lang: Option<String>,
+
     /// Optionally, the start index of the movies to return.
     pub start_index: Option<usize>,
 
@@ -108,6 +360,194 @@ pub struct MovieSearchQuery {
     pub num_results: Option<usize>,
 }
 
+impl MovieSearchQuery {
+    /// Returns the subtitle filter of the query, if any, i.e., whether the movie must have
+    /// subtitles and optionally their language. Only giving a language requires subtitles in
+    /// that language.
+    pub fn get_subtitle_filter(&self) -> Option<(bool, Option<String>)> {
+        match (self.has_subtitles, self.subtitle_lang.clone()) {
+            (None, None) => None,
+            (has_subtitles, language) => Some((has_subtitles.unwrap_or(true), language)),
+        }
+    }
+
+    /// Checks that the date range of the query isn't inverted, i.e., that the movies mustn't
+    /// have been added after a later point in time than the one before which they were added.
+    pub fn check_date_range(&self) -> Result<(), Error> {
+        match (self.added_after, self.added_before) {
+            (Some(added_after), Some(added_before)) if added_after > added_before => {
+                Err(Error::InvalidArgument(format!(
+                    "The start {} of the date range is after its end {}",
+                    added_after.to_rfc3339(),
+                    added_before.to_rfc3339()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Resolves the relative time window of the query, if any, into the point in time at or after
+    /// which the movie must have been added. If the query already defines such a point in time,
+    /// the later one of both is kept.
+    ///
+    /// # Arguments
+    /// * `now` - The point in time the window is relative to.
+    pub fn resolve_added_within(&mut self, now: DateTime<Utc>) -> Result<(), Error> {
+        let added_within = match self.added_within.take() {
+            Some(added_within) => added_within,
+            None => return Ok(()),
+        };
+
+        // reject windows reaching further back than a date can represent
+        let cutoff = now
+            .checked_sub_signed(parse_relative_duration(&added_within)?)
+            .ok_or_else(|| {
+                Error::InvalidArgument(format!(
+                    "The time window '{}' reaches too far back",
+                    added_within
+                ))
+            })?;
+        self.added_after = Some(match self.added_after {
+            Some(added_after) => added_after.max(cutoff),
+            None => cutoff,
+        });
+
+        Ok(())
+    }
+}
+
+/// Parses a relative duration consisting of a positive number and a unit, e.g., "90m", "24h",
+/// "7d" or "2w". The supported units are seconds (s), minutes (m), hours (h), days (d) and
+/// weeks (w).
+///
+/// # Arguments
+/// * `duration` - The duration to parse.
+pub fn parse_relative_duration(duration: &str) -> Result<Duration, Error> {
+    let invalid = || {
+        Error::InvalidArgument(format!(
+            "Invalid relative duration '{}', expected a positive number followed by s, m, h, d or w",
+            duration
+        ))
+    };
+
+    let trimmed = duration.trim();
+    let unit = trimmed.chars().last().ok_or_else(invalid)?;
+    let number = &trimmed[..trimmed.len() - unit.len_utf8()];
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let number: i64 = number.parse().map_err(|_| invalid())?;
+    if number == 0 {
+        return Err(invalid());
+    }
+
+    let seconds_per_unit: i64 = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+
+    number
+        .checked_mul(seconds_per_unit)
+        .and_then(Duration::try_seconds)
+        .ok_or_else(invalid)
+}
+
+/// A single edit of a movie, which is applied as part of an [`IndexBatch`].
+#[derive(Clone, Debug)]
+pub enum MovieEdit {
+    /// Replaces the title, the description and the tags of the movie.
+    Update { id: MovieId, movie: Movie },
+
+    /// Adds the given tags to the movie, tags the movie already has are skipped.
+    AddTags { id: MovieId, tags: Vec<String> },
+
+    /// Sets or clears the featured flag of the movie.
+    SetFeatured { id: MovieId, featured: bool },
+
+    /// Sets the date at which the movie has been added to now.
+    Touch { id: MovieId },
+}
+
+impl MovieEdit {
+    /// Returns the id of the edited movie.
+    pub fn id(&self) -> &MovieId {
+        match self {
+            MovieEdit::Update { id, .. } => id,
+            MovieEdit::AddTags { id, .. } => id,
+            MovieEdit::SetFeatured { id, .. } => id,
+            MovieEdit::Touch { id } => id,
+        }
+    }
+}
+
+/// A batch of movie edits, which are applied atomically in the given order, i.e., either all of
+/// them or, if one of them fails, none of them.
+#[derive(Clone, Debug, Default)]
+pub struct IndexBatch {
+    edits: Vec<MovieEdit>,
+}
+
+impl IndexBatch {
+    /// Creates a new empty batch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the replacement of the title, the description and the tags of the given movie.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to update.
+    /// * `movie` - The new title, description and tags of the movie.
+    pub fn update_movie(mut self, id: MovieId, movie: Movie) -> Self {
+        self.edits.push(MovieEdit::Update { id, movie });
+        self
+    }
+
+    /// Adds the given tags to the given movie.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    /// * `tags` - The tags to add.
+    pub fn add_tags(mut self, id: MovieId, tags: Vec<String>) -> Self {
+        self.edits.push(MovieEdit::AddTags { id, tags });
+        self
+    }
+
+    /// Sets or clears the featured flag of the given movie.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    /// * `featured` - True to feature the movie and false to clear the flag.
+    pub fn set_featured(mut self, id: MovieId, featured: bool) -> Self {
+        self.edits.push(MovieEdit::SetFeatured { id, featured });
+        self
+    }
+
+    /// Sets the date at which the given movie has been added to now.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    pub fn touch_movie(mut self, id: MovieId) -> Self {
+        self.edits.push(MovieEdit::Touch { id });
+        self
+    }
+
+    /// Returns the edits of the batch in the order in which they are applied.
+    pub fn edits(&self) -> &[MovieEdit] {
+        &self.edits
+    }
+
+    /// Consumes the batch and returns its edits in the order in which they are applied.
+    pub fn into_edits(self) -> Vec<MovieEdit> {
+        self.edits
+    }
+}
+
 /// The movies index manages a list of all movies in the database.
 /// Additionally, it provides methods for managing and searching movies.
 #[async_trait]
@@ -126,12 +566,34 @@ pub trait MoviesIndex: Send + Sync {
     /// `movie` - The movie to add to the index
     async fn add_movie(&mut self, movie: Movie) -> Result<MovieId, Error>;
 
+    /// Adds a new movie with the given ID to the index.
+    /// Fails if the ID is invalid or if there is already a movie with the given ID.
+    ///
+    /// # Arguments
+    /// `id` - The ID for the new movie.
+    /// `movie` - The movie to add to the index
+    async fn add_movie_with_id(&mut self, id: MovieId, movie: Movie) -> Result<MovieId, Error>;
+
+    /// Adds the given movies to the index at once and returns their IDs in the same order.
+    /// Either all movies are added or, e.g., if a movie has no title, none of them.
+    ///
+    /// # Arguments
+    /// `movies` - The movies to add to the index
+    async fn add_movies(&mut self, movies: Vec<Movie>) -> Result<Vec<MovieId>, Error>;
+
     /// Returns the the movie for the given ID.
     ///
     /// # Arguments
     /// `id` - The ID of the movie to return.
     async fn get_movie(&self, id: &MovieId) -> Result<MovieDetailed, Error>;
 
+    /// Returns true if there is a movie with the given ID. In contrast to `get_movie`, the
+    /// details of the movie aren't loaded.
+    ///
+    /// # Arguments
+    /// `id` - The ID of the movie to check.
+    async fn movie_exists(&self, id: &MovieId) -> Result<bool, Error>;
+
     /// Updates the movie file info for the given ID.
     ///
     /// # Arguments
@@ -143,6 +605,12 @@ pub trait MoviesIndex: Send + Sync {
         movie_file_info: MovieFileInfo,
     ) -> Result<(), Error>;
 
+    /// Removes the movie file info for the given ID, e.g., if the movie file no longer exists.
+    ///
+    /// # Arguments
+    /// `id` - The ID of the movie to update.
+    async fn remove_movie_file_info(&mut self, id: &MovieId) -> Result<(), Error>;
+
     /// Updates the screenshot info for the given ID.
     ///
     /// # Arguments
@@ -154,6 +622,115 @@ pub trait MoviesIndex: Send + Sync {
         screenshot_info: ScreenshotInfo,
     ) -> Result<(), Error>;
 
+    /// Updates the metadata of the video stream of the movie file for the given ID. The metadata
+    /// is dropped as soon as the movie file info is removed.
+    ///
+    /// # Arguments
+    /// `id` - The ID of the movie to update.
+    /// `video_metadata` - The new metadata of the video stream.
+    async fn update_video_metadata(
+        &mut self,
+        id: &MovieId,
+        video_metadata: VideoMetadata,
+    ) -> Result<(), Error>;
+
+    /// Updates the info of the animated preview for the given ID.
+    ///
+    /// # Arguments
+    /// `id` - The ID of the movie to update.
+    /// `animated_preview_info` - The new info of the animated preview.
+    async fn update_animated_preview_info(
+        &mut self,
+        id: &MovieId,
+        animated_preview_info: AnimatedPreviewInfo,
+    ) -> Result<(), Error>;
+
+    /// Adds the info of a subtitle file for the given ID. An existing subtitle of the same
+    /// language is replaced.
+    ///
+    /// # Arguments
+    /// `id` - The ID of the movie to update.
+    /// `subtitle_info` - The info of the subtitle file.
+    async fn add_subtitle_info(
+        &mut self,
+        id: &MovieId,
+        subtitle_info: SubtitleInfo,
+    ) -> Result<(), Error>;
+
+    /// Returns the infos of all subtitle files for the given ID, sorted by their language.
+    ///
+    /// # Arguments
+    /// `id` - The ID of the movie.
+    async fn get_subtitles(&self, id: &MovieId) -> Result<Vec<SubtitleInfo>, Error>;
+
+    /// Updates the state of the preview generation for the given ID.
+    ///
+    /// # Arguments
+    /// `id` - The ID of the movie to update.
+    /// `preview_state` - The new state of the preview generation.
+    async fn update_preview_state(
+        &mut self,
+        id: &MovieId,
+        preview_state: PreviewState,
+    ) -> Result<(), Error>;
+
+    /// Sets the date the movie was added to the current time, i.e., moves the movie to the front
+    /// of the recently added movies.
+    ///
+    /// # Arguments
+    /// `id` - The ID of the movie to touch.
+    async fn touch_movie(&mut self, id: &MovieId) -> Result<(), Error>;
+
+    /// Marks the movie for the given ID as featured or not featured.
+    ///
+    /// # Arguments
+    /// `id` - The ID of the movie to update.
+    /// `featured` - True if the movie is featured.
+    async fn set_featured(&mut self, id: &MovieId, featured: bool) -> Result<(), Error>;
+
+    /// Adds the given tags to the movie for the given ID. Tags the movie already has, compared
+    /// case-insensitively, are skipped.
+    ///
+    /// # Arguments
+    /// `id` - The ID of the movie to update.
+    /// `tags` - The tags to add.
+    async fn add_tags(&mut self, id: &MovieId, tags: &[String]) -> Result<(), Error>;
+
+    /// Renames the given tag of all movies, where the tags are compared case-insensitively.
+    /// Movies already having the new tag keep it only once. Returns the number of movies having
+    /// the old tag before the rename.
+    ///
+    /// # Arguments
+    /// `from` - The tag to rename.
+    /// `to` - The new name of the tag.
+    async fn rename_tag(&mut self, from: &str, to: &str) -> Result<usize, Error>;
+
+    /// Renames the tags of all movies atomically according to the given renames, which map the
+    /// old tags onto the new tags and are resolved by `resolve_tag_renames`. Movies already
+    /// having the new tag keep it only once. Returns the number of movies having each old tag
+    /// before the renames, keyed by the lower case old tags.
+    ///
+    /// # Arguments
+    /// `renames` - The renames mapping the old tags onto the new tags.
+    async fn rename_tags(
+        &mut self,
+        renames: &BTreeMap<String, String>,
+    ) -> Result<BTreeMap<String, usize>, Error>;
+
+    /// Replaces the title, the description and the tags of the movie for the given ID.
+    ///
+    /// # Arguments
+    /// `id` - The ID of the movie to update.
+    /// `movie` - The new title, description and tags of the movie.
+    async fn update_movie(&mut self, id: &MovieId, movie: Movie) -> Result<(), Error>;
+
+    /// Applies the edits of the given batch in their order within a single transaction. If any
+    /// of the edits fails, none of them is applied and the error of the failed edit is returned.
+    ///
+    /// # Arguments
+    /// `batch` - The batch of edits to apply.
+    async fn apply_batch(&mut self, batch: IndexBatch) -> Result<(), Error>;
+
     /// Removes the movie for the given ID.
     ///
     /// # Arguments
@@ -166,8 +743,82 @@ pub trait MoviesIndex: Send + Sync {
     /// `query` - The query to search for.
     async fn search_movies(&self, query: MovieSearchQuery) -> Result<Vec<MovieId>, Error>;
 
+    /// Returns the total number of movies matching the given query, i.e., the pagination of the
+    /// query is ignored.
+    ///
+    /// # Arguments
+    /// `query` - The query to count the matching movies for.
+    async fn search_movies_count(&self, query: MovieSearchQuery) -> Result<usize, Error>;
+
     /// Returns a list of all tags with the number of movies associated with each tag.
     async fn get_tag_list_with_count(&self) -> Result<Vec<(String, usize)>, Error>;
+
+    /// Returns the given number of most common tags with the number of movies associated with
+    /// each tag, ordered in the same way as `get_tag_list_with_count`.
+    ///
+    /// # Arguments
+    /// `limit` - The maximal number of tags to return.
+    async fn get_top_tags(&self, limit: usize) -> Result<Vec<(String, usize)>, Error>;
+
+    /// Returns the most common tags starting with the given prefix, e.g., for autocompleting
+    /// the tags in an input box. The prefix is compared case-insensitively and the tags are
+    /// ordered in the same way as `get_tag_list_with_count`.
+    ///
+    /// # Arguments
+    /// `prefix` - The prefix of the tags.
+    /// `limit` - The maximal number of tags to return.
+    async fn search_tags(&self, prefix: &str, limit: usize) -> Result<Vec<(String, usize)>, Error>;
+
+    /// Returns a list of tag suggestions for the given tags. The suggestions are the tags of the
+    /// movies having at least one of the given tags, ranked by the number of such movies.
+    /// The given tags themselves are not part of the suggestions.
+    ///
+    /// # Arguments
+    /// `tags` - The tags to get suggestions for.
+    async fn suggest_tags(&self, tags: &[String]) -> Result<Vec<(String, usize)>, Error>;
+
+    /// Returns the years in which movies have been added together with the number of movies
+    /// added in each year, ordered ascending by the year. The years refer to the configured
+    /// display timezone.
+    async fn get_year_histogram(&self) -> Result<Vec<(u16, usize)>, Error>;
+
+    /// Returns the facets of all movies matching the given query regardless of its pagination,
+    /// i.e., the counts of their tags, years and file formats.
+    ///
+    /// # Arguments
+    /// `query` - The query to compute the facets for.
+    async fn get_search_facets(&self, query: MovieSearchQuery) -> Result<SearchFacets, Error>;
+
+    /// Stores the perceptual hash of the screenshot for the given ID. The hash is dropped as
+    /// soon as the screenshot info changes.
+    ///
+    /// # Arguments
+    /// `id` - The ID of the movie to update.
+    /// `hash` - The perceptual hash of the screenshot.
+    async fn update_screenshot_hash(&mut self, id: &MovieId, hash: u64) -> Result<(), Error>;
+
+    /// Returns the perceptual hashes of the screenshots of all movies having one, ordered by
+    /// the movie ID.
+    async fn get_screenshot_hashes(&self) -> Result<Vec<(MovieId, u64)>, Error>;
+
+    /// Returns the aggregated execution times of the queries per kind of query, if the index
+    /// collects them, i.e., if query timing is enabled and supported by the index.
+    fn get_query_stats(&self) -> Option<BTreeMap<String, QueryStats>>;
+
+    /// Returns the number of movies matching the given query, which are sorted by their title
+    /// before the first movie whose title starts with the given letter. That is, the offset at
+    /// which the movies starting with the letter begin in the search results. If no title starts
+    /// with the letter, the offset is the position at which such a title would be inserted.
+    /// The sorting field, the start index and the number of results of the query are ignored.
+    ///
+    /// # Arguments
+    /// `query` - The query to search for.
+    /// `letter` - The letter to get the offset for.
+    async fn get_letter_offset(
+        &self,
+        query: MovieSearchQuery,
+        letter: char,
+    ) -> Result<usize, Error>;
 }
 
 #[cfg(test)]
@@ -181,7 +832,8 @@ mod test {
                 "sorting_field": "title",
                 "sorting_order": "ascending",
                 "title": "foo",
-                "tags": ["bar", "baz"]
+                "tags": ["bar", "baz"],
+                "tag_match_mode": "any"
             }
         "#;
 
@@ -189,6 +841,7 @@ mod test {
 
         assert_eq!(query.title, Some("foo".to_string()));
         assert_eq!(query.tags, vec!["bar".to_string(), "baz".to_string()]);
+        assert_eq!(query.tag_match_mode, TagMatchMode::Any);
         assert_eq!(query.sorting_field, SortingField::Title);
         assert_eq!(query.sorting_order, SortingOrder::Ascending);
 
@@ -204,7 +857,184 @@ mod test {
 
         assert_eq!(query.title, Some("foo".to_string()));
         assert!(query.tags.is_empty());
+        assert_eq!(query.tag_match_mode, TagMatchMode::All);
         assert_eq!(query.sorting_field, SortingField::Date);
         assert_eq!(query.sorting_order, SortingOrder::Descending);
     }
+
+    #[test]
+    fn test_query_date_range_serialization() {
+        let query_string = r#"
+            {
+                "added_after": "2023-01-01T00:00:00Z",
+                "added_before": "2023-07-01T14:00:00+02:00"
+            }
+        "#;
+
+        let query = serde_json::from_str::<MovieSearchQuery>(query_string).unwrap();
+        let added_after: DateTime<Utc> = "2023-01-01T00:00:00Z".parse().unwrap();
+        let added_before: DateTime<Utc> = "2023-07-01T12:00:00Z".parse().unwrap();
+        assert_eq!(query.added_after, Some(added_after));
+        assert_eq!(query.added_before, Some(added_before));
+        assert!(query.check_date_range().is_ok());
+
+        // the dates survive a round trip through JSON and the query string
+        let json = serde_json::to_string(&query).unwrap();
+        let query = serde_json::from_str::<MovieSearchQuery>(&json).unwrap();
+        assert_eq!(query.added_after, Some(added_after));
+        assert_eq!(query.added_before, Some(added_before));
+
+        let query_string = serde_qs::to_string(&query).unwrap();
+        let query = serde_qs::from_str::<MovieSearchQuery>(&query_string).unwrap();
+        assert_eq!(query.added_after, Some(added_after));
+        assert_eq!(query.added_before, Some(added_before));
+
+        // both dates are optional
+        let query = serde_json::from_str::<MovieSearchQuery>("{}").unwrap();
+        assert_eq!(query.added_after, None);
+        assert_eq!(query.added_before, None);
+        assert!(query.check_date_range().is_ok());
+
+        // an inverted range is rejected
+        let query = MovieSearchQuery {
+            added_after: Some(added_before),
+            added_before: Some(added_after),
+            ..Default::default()
+        };
+        assert!(matches!(
+            query.check_date_range(),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_relative_duration() {
+        assert_eq!(
+            parse_relative_duration("45s").unwrap(),
+            Duration::seconds(45)
+        );
+        assert_eq!(
+            parse_relative_duration("90m").unwrap(),
+            Duration::minutes(90)
+        );
+        assert_eq!(parse_relative_duration("24h").unwrap(), Duration::hours(24));
+        assert_eq!(parse_relative_duration(" 7d ").unwrap(), Duration::days(7));
+        assert_eq!(parse_relative_duration("30d").unwrap(), Duration::days(30));
+        assert_eq!(parse_relative_duration("2w").unwrap(), Duration::weeks(2));
+
+        for duration in [
+            "",
+            "d",
+            "7",
+            "0d",
+            "-7d",
+            "+7d",
+            "7 d",
+            "7D",
+            "7y",
+            "1.5h",
+            "7dd",
+            "99999999999999999w",
+        ] {
+            assert!(
+                matches!(
+                    parse_relative_duration(duration),
+                    Err(Error::InvalidArgument(_))
+                ),
+                "{}",
+                duration
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_added_within() {
+        let now: DateTime<Utc> = "2023-07-08T12:00:00Z".parse().unwrap();
+
+        let mut query =
+            serde_json::from_str::<MovieSearchQuery>(r#"{"added_within": "7d"}"#).unwrap();
+        query.resolve_added_within(now).unwrap();
+        assert_eq!(query.added_within, None);
+        assert_eq!(
+            query.added_after,
+            Some("2023-07-01T12:00:00Z".parse().unwrap())
+        );
+
+        // the later of both cutoffs is kept
+        let added_after: DateTime<Utc> = "2023-07-08T00:00:00Z".parse().unwrap();
+        let mut query = MovieSearchQuery {
+            added_after: Some(added_after),
+            added_within: Some("24h".to_owned()),
+            ..Default::default()
+        };
+        query.resolve_added_within(now).unwrap();
+        assert_eq!(query.added_after, Some(added_after));
+
+        // a query without a window is kept as is
+        let mut query = MovieSearchQuery::default();
+        query.resolve_added_within(now).unwrap();
+        assert_eq!(query.added_after, None);
+
+        for added_within in ["soon", "9999999999w"] {
+            let mut query = MovieSearchQuery {
+                added_within: Some(added_within.to_owned()),
+                ..Default::default()
+            };
+            assert!(matches!(
+                query.resolve_added_within(now),
+                Err(Error::InvalidArgument(_))
+            ));
+        }
+    }
+
+    fn create_movie(description: &str) -> Movie {
+        Movie {
+            title: "Das Boot".to_owned(),
+            description: description.to_owned(),
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_limit_description_reject() {
+        // exactly at the limit
+        let mut movie = create_movie("Ein U-Boot");
+        movie
+            .limit_description(10, DescriptionLengthPolicy::Reject)
+            .unwrap();
+        assert_eq!(movie.description, "Ein U-Boot");
+
+        // one character above the limit
+        let mut movie = create_movie("Ein U-Boot!");
+        assert!(matches!(
+            movie.limit_description(10, DescriptionLengthPolicy::Reject),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert_eq!(movie.description, "Ein U-Boot!");
+    }
+
+    #[test]
+    fn test_limit_description_truncate() {
+        // exactly at the limit
+        let mut movie = create_movie("Ein U-Boot");
+        movie
+            .limit_description(10, DescriptionLengthPolicy::Truncate)
+            .unwrap();
+        assert_eq!(movie.description, "Ein U-Boot");
+
+        // one character above the limit
+        let mut movie = create_movie("Ein U-Boot!");
+        movie
+            .limit_description(10, DescriptionLengthPolicy::Truncate)
+            .unwrap();
+        assert_eq!(movie.description, "Ein U-Boo…");
+        assert_eq!(movie.description.graphemes(true).count(), 10);
+
+        // grapheme clusters consisting of multiple code points are not split
+        let mut movie = create_movie("Cafe\u{301}s and more");
+        movie
+            .limit_description(5, DescriptionLengthPolicy::Truncate)
+            .unwrap();
+        assert_eq!(movie.description, "Cafe\u{301}…");
+    }
 }
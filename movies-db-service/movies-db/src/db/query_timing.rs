@@ -0,0 +1,137 @@
+use std::{
+    collections::BTreeMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+/// The aggregated execution times of a single kind of index query.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QueryStats {
+    /// The number of executed queries.
+    pub count: u64,
+
+    /// The total execution time of all queries in microseconds.
+    pub total_micros: u64,
+
+    /// The longest execution time of a single query in microseconds.
+    pub max_micros: u64,
+
+    /// The number of queries which took at least the slow query threshold.
+    pub slow_count: u64,
+}
+
+/// Collects the execution times of the index queries for diagnosing slow queries. The execution
+/// time of a query includes waiting for the database connection, s.t. lock contention shows up
+/// as well.
+pub struct QueryTimings {
+    /// The execution time from which on a query is reported as slow.
+    slow_threshold: Duration,
+
+    /// The aggregated execution times per kind of query.
+    stats: Mutex<BTreeMap<&'static str, QueryStats>>,
+}
+
+impl QueryTimings {
+    /// Creates new empty query timings.
+    ///
+    /// # Arguments
+    /// * `slow_threshold` - The execution time from which on a query is reported as slow.
+    pub fn new(slow_threshold: Duration) -> Self {
+        Self {
+            slow_threshold,
+            stats: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Starts measuring the execution time of the given query, which is recorded as soon as the
+    /// returned timer is dropped.
+    ///
+    /// # Arguments
+    /// * `query` - The kind of the query, e.g., "search_movies".
+    pub fn start(&self, query: &'static str) -> QueryTimer<'_> {
+        QueryTimer {
+            timings: self,
+            query,
+            start: Instant::now(),
+        }
+    }
+
+    /// Records the given execution time of the given query.
+    ///
+    /// # Arguments
+    /// * `query` - The kind of the query.
+    /// * `elapsed` - The execution time of the query.
+    fn record(&self, query: &'static str, elapsed: Duration) {
+        let slow = elapsed >= self.slow_threshold;
+        if slow {
+            warn!("Slow index query '{}' took {:?}", query, elapsed);
+        } else {
+            debug!("Index query '{}' took {:?}", query, elapsed);
+        }
+
+        let micros = elapsed.as_micros() as u64;
+        let mut stats = self.stats.lock().unwrap();
+        let stats = stats.entry(query).or_default();
+        stats.count += 1;
+        stats.total_micros += micros;
+        stats.max_micros = stats.max_micros.max(micros);
+        if slow {
+            stats.slow_count += 1;
+        }
+    }
+
+    /// Returns the aggregated execution times per kind of query.
+    pub fn get_stats(&self) -> BTreeMap<String, QueryStats> {
+        self.stats
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(query, stats)| (query.to_string(), stats.clone()))
+            .collect()
+    }
+}
+
+/// Measures the execution time of a single query until it is dropped.
+pub struct QueryTimer<'a> {
+    timings: &'a QueryTimings,
+    query: &'static str,
+    start: Instant,
+}
+
+impl Drop for QueryTimer<'_> {
+    fn drop(&mut self) {
+        self.timings.record(self.query, self.start.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_query_timings() {
+        let timings = QueryTimings::new(Duration::from_millis(20));
+
+        drop(timings.start("get_movie"));
+        {
+            let _timer = timings.start("search_movies");
+            std::thread::sleep(Duration::from_millis(25));
+        }
+        drop(timings.start("search_movies"));
+
+        let stats = timings.get_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats["get_movie"].count, 1);
+        assert_eq!(stats["get_movie"].slow_count, 0);
+
+        // only the query exceeding the threshold is reported as slow
+        let search_stats = &stats["search_movies"];
+        assert_eq!(search_stats.count, 2);
+        assert_eq!(search_stats.slow_count, 1);
+        assert!(search_stats.max_micros >= 25_000);
+        assert!(search_stats.total_micros >= search_stats.max_micros);
+    }
+}
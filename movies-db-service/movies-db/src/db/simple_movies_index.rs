@@ -1,42 +1,112 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+};
 
-use chrono::DateTime;
+use chrono::{DateTime, Datelike, FixedOffset};
 use log::{error, info};
 use wildmatch::WildMatch;
 
 use async_trait::async_trait;
 
 use crate::{
-    generate_movie_id, Error, Movie, MovieDetailed, MovieFileInfo, MovieId, MovieSearchQuery,
-    MoviesIndex, Options, ScreenshotInfo, SortingField, SortingOrder,
+    generate_movie_id, is_valid_movie_id, resolve_tag_renames, AnimatedPreviewInfo, Error,
+    IndexBatch, Movie, MovieDetailed, MovieEdit, MovieFileInfo, MovieId, MovieSearchQuery,
+    MoviesIndex, Options, PreviewState, QueryStats, ScreenshotInfo, SearchFacets, SortingField,
+    SortingOrder, SubtitleInfo, TagMatchMode, VideoMetadata,
 };
 
 /// A very simple and naive in-memory implementation of the movies index.
 pub struct SimpleMoviesIndex {
     movies: HashMap<MovieId, MovieDetailed>,
+
+    /// If true, the tags of the movies keep their original casing.
+    preserve_tag_case: bool,
+
+    /// The timezone for grouping movies by date.
+    display_timezone: FixedOffset,
+
+    /// The perceptual hashes of the screenshots.
+    screenshot_hashes: HashMap<MovieId, u64>,
+
+    /// The infos of the subtitle files of the movies, keyed by their language.
+    subtitles: HashMap<MovieId, BTreeMap<String, SubtitleInfo>>,
 }
 
 impl SimpleMoviesIndex {
-    /// Processes the given tags by converting them to lower case and sorting them.
+    /// Processes the given tags by converting them to lower case, sorting them and removing
+    /// duplicates.
     ///
     /// # Arguments
     /// `tags` - The tags to process.
-    fn process_tags(tags: &mut [String]) {
+    fn process_tags(tags: &mut Vec<String>) {
         tags.iter_mut().for_each(|tag| *tag = tag.to_lowercase());
         tags.sort();
+        tags.dedup();
+    }
+
+    /// Processes the tags of a movie. The tags are sorted and de-duplicated by their lower case
+    /// variant and are converted to lower case, unless the original casing is preserved. In the
+    /// latter case, the first casing of a duplicated tag is kept.
+    ///
+    /// # Arguments
+    /// `tags` - The tags to process.
+    fn process_movie_tags(&self, tags: &mut Vec<String>) {
+        if self.preserve_tag_case {
+            tags.sort_by_cached_key(|tag| tag.to_lowercase());
+            tags.dedup_by(|tag, kept| tag.to_lowercase() == kept.to_lowercase());
+        } else {
+            Self::process_tags(tags);
+        }
+    }
+
+    /// Counts the given tags case-insensitively and returns them ordered descending by their
+    /// count and ascending by their lower case variant.
+    ///
+    /// # Arguments
+    /// `tags` - The tags to count.
+    fn count_tags<'a>(tags: impl Iterator<Item = &'a String>) -> Vec<(String, usize)> {
+        // maps the lower case tag to the displayed tag and its count
+        let mut tag_map: HashMap<String, (&String, usize)> = HashMap::new();
+
+        for tag in tags {
+            let entry = tag_map.entry(tag.to_lowercase()).or_insert((tag, 0));
+            entry.0 = entry.0.min(tag);
+            entry.1 += 1;
+        }
+
+        let mut tag_list: Vec<(String, &String, usize)> = tag_map
+            .into_iter()
+            .map(|(key, (tag, count))| (key, tag, count))
+            .collect();
+        tag_list.sort_unstable_by(|(lhs_key, _, lhs), (rhs_key, _, rhs)| {
+            rhs.cmp(lhs).then_with(|| lhs_key.cmp(rhs_key))
+        });
+
+        tag_list
+            .into_iter()
+            .map(|(_, tag, count)| (tag.clone(), count))
+            .collect()
     }
 }
 
 #[async_trait]
 impl MoviesIndex for SimpleMoviesIndex {
-    fn new(_: &Options) -> Result<Self, Error> {
+    fn new(options: &Options) -> Result<Self, Error> {
         Ok(Self {
             movies: HashMap::new(),
+            preserve_tag_case: options.preserve_tag_case,
+            display_timezone: options.get_display_timezone(),
+            screenshot_hashes: HashMap::new(),
+            subtitles: HashMap::new(),
         })
     }
 
     async fn add_movie(&mut self, movie: Movie) -> Result<MovieId, Error> {
-        let id = generate_movie_id();
+        self.add_movie_with_id(generate_movie_id(), movie).await
+    }
+
+    async fn add_movie_with_id(&mut self, id: MovieId, movie: Movie) -> Result<MovieId, Error> {
         info!("Adding movie {} with id {}", movie.title, id);
 
         // check if movie has title
@@ -47,25 +117,59 @@ impl MoviesIndex for SimpleMoviesIndex {
             ));
         }
 
-        assert!(
-            self.movies.get(&id).is_none(),
-            "Movie with id {} already exists",
-            id
-        );
+        // check if the id is valid and not yet taken
+        if !is_valid_movie_id(&id) {
+            error!("Invalid movie id {}", id);
+            return Err(Error::InvalidArgument(format!("Invalid movie id {}", id)));
+        }
+
+        if self.movies.contains_key(&id) {
+            error!("Movie with id {} already exists", id);
+            return Err(Error::AlreadyExists(format!(
+                "Movie with id {} already exists",
+                id
+            )));
+        }
 
         let mut movie_with_date = MovieDetailed {
             movie,
             movie_file_info: None,
             screenshot_file_info: None,
             date: chrono::Utc::now(),
+            preview_state: None,
+            featured: false,
+            processing: false,
+            active_jobs: Vec::new(),
+            video_metadata: None,
+            animated_preview_info: None,
+            subtitle_languages: Vec::new(),
         };
-        Self::process_tags(&mut movie_with_date.movie.tags);
+        self.process_movie_tags(&mut movie_with_date.movie.tags);
 
         self.movies.insert(id.clone(), movie_with_date);
 
         Ok(id)
     }
 
+    async fn add_movies(&mut self, movies: Vec<Movie>) -> Result<Vec<MovieId>, Error> {
+        info!("Adding {} movies", movies.len());
+
+        // the generated ids are unique, s.t. only a missing title can make adding a movie fail
+        if movies.iter().any(|movie| movie.title.is_empty()) {
+            error!("Movie has no title");
+            return Err(Error::InvalidArgument(
+                "Movie title must not be empty".to_string(),
+            ));
+        }
+
+        let mut ids = Vec::with_capacity(movies.len());
+        for movie in movies {
+            ids.push(self.add_movie(movie).await?);
+        }
+
+        Ok(ids)
+    }
+
     async fn get_movie(&self, id: &MovieId) -> Result<MovieDetailed, Error> {
         info!("Getting movie with id {}", id);
 
@@ -78,6 +182,10 @@ impl MoviesIndex for SimpleMoviesIndex {
         }
     }
 
+    async fn movie_exists(&self, id: &MovieId) -> Result<bool, Error> {
+        Ok(self.movies.contains_key(id))
+    }
+
     async fn update_movie_file_info(
         &mut self,
         id: &MovieId,
@@ -97,6 +205,22 @@ impl MoviesIndex for SimpleMoviesIndex {
         }
     }
 
+    async fn remove_movie_file_info(&mut self, id: &MovieId) -> Result<(), Error> {
+        info!("Removing movie file info for movie with id {}", id);
+
+        match self.movies.get_mut(id) {
+            Some(movie) => {
+                movie.movie_file_info = None;
+                movie.video_metadata = None;
+                Ok(())
+            }
+            None => {
+                error!("Movie with id {} not found", id);
+                Err(Error::NotFound(format!("Movie with id {} not found", id)))
+            }
+        }
+    }
+
     async fn update_screenshot_info(
         &mut self,
         id: &MovieId,
@@ -107,6 +231,216 @@ impl MoviesIndex for SimpleMoviesIndex {
         match self.movies.get_mut(id) {
             Some(movie) => {
                 movie.screenshot_file_info = Some(screenshot_info);
+                self.screenshot_hashes.remove(id);
+                Ok(())
+            }
+            None => {
+                error!("Movie with id {} not found", id);
+                Err(Error::NotFound(format!("Movie with id {} not found", id)))
+            }
+        }
+    }
+
+    async fn update_video_metadata(
+        &mut self,
+        id: &MovieId,
+        video_metadata: VideoMetadata,
+    ) -> Result<(), Error> {
+        info!("Updating video metadata for movie with id {}", id);
+
+        match self.movies.get_mut(id) {
+            Some(movie) => {
+                movie.video_metadata = Some(video_metadata);
+                Ok(())
+            }
+            None => {
+                error!("Movie with id {} not found", id);
+                Err(Error::NotFound(format!("Movie with id {} not found", id)))
+            }
+        }
+    }
+
+    async fn update_animated_preview_info(
+        &mut self,
+        id: &MovieId,
+        animated_preview_info: AnimatedPreviewInfo,
+    ) -> Result<(), Error> {
+        info!("Updating animated preview info for movie with id {}", id);
+
+        match self.movies.get_mut(id) {
+            Some(movie) => {
+                movie.animated_preview_info = Some(animated_preview_info);
+                Ok(())
+            }
+            None => {
+                error!("Movie with id {} not found", id);
+                Err(Error::NotFound(format!("Movie with id {} not found", id)))
+            }
+        }
+    }
+
+    async fn add_subtitle_info(
+        &mut self,
+        id: &MovieId,
+        subtitle_info: SubtitleInfo,
+    ) -> Result<(), Error> {
+        info!(
+            "Adding {} subtitles for movie with id {}",
+            subtitle_info.language, id
+        );
+
+        match self.movies.get_mut(id) {
+            Some(movie) => {
+                let subtitles = self.subtitles.entry(id.clone()).or_default();
+                subtitles.insert(subtitle_info.language.clone(), subtitle_info);
+                movie.subtitle_languages = subtitles.keys().cloned().collect();
+                Ok(())
+            }
+            None => {
+                error!("Movie with id {} not found", id);
+                Err(Error::NotFound(format!("Movie with id {} not found", id)))
+            }
+        }
+    }
+
+    async fn get_subtitles(&self, id: &MovieId) -> Result<Vec<SubtitleInfo>, Error> {
+        if !self.movies.contains_key(id) {
+            error!("Movie with id {} not found", id);
+            return Err(Error::NotFound(format!("Movie with id {} not found", id)));
+        }
+
+        Ok(self
+            .subtitles
+            .get(id)
+            .map(|subtitles| subtitles.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn update_preview_state(
+        &mut self,
+        id: &MovieId,
+        preview_state: PreviewState,
+    ) -> Result<(), Error> {
+        info!("Updating preview state for movie with id {}", id);
+
+        match self.movies.get_mut(id) {
+            Some(movie) => {
+                movie.preview_state = Some(preview_state);
+                Ok(())
+            }
+            None => {
+                error!("Movie with id {} not found", id);
+                Err(Error::NotFound(format!("Movie with id {} not found", id)))
+            }
+        }
+    }
+
+    async fn add_tags(&mut self, id: &MovieId, tags: &[String]) -> Result<(), Error> {
+        info!("Adding tags {:?} to movie with id {}", tags, id);
+
+        let preserve_tag_case = self.preserve_tag_case;
+        let movie = match self.movies.get_mut(id) {
+            Some(movie) => &mut movie.movie,
+            None => {
+                error!("Movie with id {} not found", id);
+                return Err(Error::NotFound(format!("Movie with id {} not found", id)));
+            }
+        };
+
+        for tag in tags {
+            let lower_tag = tag.to_lowercase();
+            if movie
+                .tags
+                .iter()
+                .any(|movie_tag| movie_tag.to_lowercase() == lower_tag)
+            {
+                continue;
+            }
+
+            movie.tags.push(if preserve_tag_case {
+                tag.clone()
+            } else {
+                lower_tag
+            });
+        }
+
+        // keep the tags sorted by their lower case variant
+        movie.tags.sort_by_cached_key(|tag| tag.to_lowercase());
+
+        Ok(())
+    }
+
+    async fn set_featured(&mut self, id: &MovieId, featured: bool) -> Result<(), Error> {
+        info!(
+            "Setting featured flag of movie with id {} to {}",
+            id, featured
+        );
+
+        match self.movies.get_mut(id) {
+            Some(movie) => {
+                movie.featured = featured;
+                Ok(())
+            }
+            None => {
+                error!("Movie with id {} not found", id);
+                Err(Error::NotFound(format!("Movie with id {} not found", id)))
+            }
+        }
+    }
+
+    async fn touch_movie(&mut self, id: &MovieId) -> Result<(), Error> {
+        info!("Touching movie with id {}", id);
+
+        match self.movies.get_mut(id) {
+            Some(movie) => {
+                movie.date = chrono::Utc::now();
+                Ok(())
+            }
+            None => {
+                error!("Movie with id {} not found", id);
+                Err(Error::NotFound(format!("Movie with id {} not found", id)))
+            }
+        }
+    }
+
+    async fn apply_batch(&mut self, batch: IndexBatch) -> Result<(), Error> {
+        info!("Applying batch of {} edits", batch.edits().len());
+
+        // the edits only change the movies, which are restored if any of the edits fails
+        let snapshot = self.movies.clone();
+        for edit in batch.into_edits() {
+            let result = match edit {
+                MovieEdit::Update { id, movie } => self.update_movie(&id, movie).await,
+                MovieEdit::AddTags { id, tags } => self.add_tags(&id, &tags).await,
+                MovieEdit::SetFeatured { id, featured } => self.set_featured(&id, featured).await,
+                MovieEdit::Touch { id } => self.touch_movie(&id).await,
+            };
+
+            if let Err(err) = result {
+                self.movies = snapshot;
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn update_movie(&mut self, id: &MovieId, mut movie: Movie) -> Result<(), Error> {
+        info!("Updating movie {} with id {}", movie.title, id);
+
+        // check if movie has title
+        if movie.title.is_empty() {
+            error!("Movie has no title");
+            return Err(Error::InvalidArgument(
+                "Movie title must not be empty".to_string(),
+            ));
+        }
+
+        self.process_movie_tags(&mut movie.tags);
+
+        match self.movies.get_mut(id) {
+            Some(movie_detailed) => {
+                movie_detailed.movie = movie;
                 Ok(())
             }
             None => {
@@ -116,11 +450,60 @@ impl MoviesIndex for SimpleMoviesIndex {
         }
     }
 
+    async fn rename_tag(&mut self, from: &str, to: &str) -> Result<usize, Error> {
+        // a single rename can't form a chain or a cycle
+        let renames = BTreeMap::from([(from.to_owned(), to.to_owned())]);
+        let counts = self.rename_tags(&renames).await?;
+
+        Ok(counts.into_values().sum())
+    }
+
+    async fn rename_tags(
+        &mut self,
+        renames: &BTreeMap<String, String>,
+    ) -> Result<BTreeMap<String, usize>, Error> {
+        info!("Renaming tags {:?}", renames);
+
+        let renames = resolve_tag_renames(renames)?;
+        let mut counts: BTreeMap<String, usize> =
+            renames.keys().map(|old_tag| (old_tag.clone(), 0)).collect();
+
+        let preserve_tag_case = self.preserve_tag_case;
+        for movie in self.movies.values_mut() {
+            let tags = &mut movie.movie.tags;
+            let mut renamed = false;
+            for tag in tags.iter_mut() {
+                let lower_tag = tag.to_lowercase();
+                if let Some(new_tag) = renames.get(&lower_tag) {
+                    *counts.get_mut(&lower_tag).unwrap() += 1;
+                    *tag = if preserve_tag_case {
+                        new_tag.clone()
+                    } else {
+                        new_tag.to_lowercase()
+                    };
+                    renamed = true;
+                }
+            }
+
+            // renamed tags may collide with the other tags of the movie
+            if renamed {
+                tags.sort_by_cached_key(|tag| tag.to_lowercase());
+                tags.dedup_by(|lhs, rhs| lhs.to_lowercase() == rhs.to_lowercase());
+            }
+        }
+
+        Ok(counts)
+    }
+
     async fn remove_movie(&mut self, id: &MovieId) -> Result<(), Error> {
         info!("Removing movie with id {}", id);
 
         match self.movies.remove(id) {
-            Some(_) => Ok(()),
+            Some(_) => {
+                self.screenshot_hashes.remove(id);
+                self.subtitles.remove(id);
+                Ok(())
+            }
             None => {
                 error!("Movie with id {} not found", id);
                 Err(Error::NotFound(format!("Movie with id {} not found", id)))
@@ -130,13 +513,24 @@ impl MoviesIndex for SimpleMoviesIndex {
 
     async fn search_movies(&self, mut query: MovieSearchQuery) -> Result<Vec<MovieId>, Error> {
         info!("Searching movies with query {:?}", query);
+        query.check_date_range()?;
         Self::process_tags(&mut query.tags);
 
         // get sorted movie ids
-        let in_movie_ids = self.get_movies_sorted(query.sorting_field, query.sorting_order);
+        let mut in_movie_ids = self.get_movies_sorted(query.sorting_field, query.sorting_order);
+
+        // the sort is stable, s.t. the featured and the remaining movies keep their order
+        if query.featured_first {
+            in_movie_ids.sort_by_key(|id| !self.is_featured(id));
+        }
+
+        let subtitle_filter = query.get_subtitle_filter();
 
         // create wildcard query if provided
         let title_query: Option<WildMatch> = query.title.map(|s| WildMatch::new(&s));
+        let description_query: Option<WildMatch> = query.description.map(|s| WildMatch::new(&s));
+        let mut exclude_tags = query.exclude_tags;
+        Self::process_tags(&mut exclude_tags);
 
         let start_index = query.start_index.unwrap_or(0);
         let end_index = match query.num_results {
@@ -155,6 +549,17 @@ impl MoviesIndex for SimpleMoviesIndex {
                 }
             };
 
+            // skip movies added outside of the date range
+            if query
+                .added_after
+                .is_some_and(|date| movie_with_date.date < date)
+                || query
+                    .added_before
+                    .is_some_and(|date| movie_with_date.date >= date)
+            {
+                continue;
+            }
+
             let movie = &movie_with_date.movie;
 
             // if a title query is available and the movie title does not match, skip
@@ -164,15 +569,59 @@ impl MoviesIndex for SimpleMoviesIndex {
                 }
             }
 
-            // check that all tags match
-            if !query
-                .tags
-                .iter()
-                .all(|tag| movie.tags.binary_search(tag).is_ok())
-            {
+            // same for the description
+            if let Some(ref description_query) = description_query {
+                if !description_query.matches(&movie.description) {
+                    continue;
+                }
+            }
+
+            // check that all or any tags match, the movie tags are sorted by their lower case
+            // variant
+            let matches_tag = |tag: &String| {
+                if query.tag_prefix_match {
+                    movie
+                        .tags
+                        .iter()
+                        .any(|movie_tag| movie_tag.to_lowercase().starts_with(tag.as_str()))
+                } else {
+                    movie
+                        .tags
+                        .binary_search_by(|movie_tag| movie_tag.to_lowercase().cmp(tag))
+                        .is_ok()
+                }
+            };
+            let tags_match = match query.tag_match_mode {
+                TagMatchMode::All => query.tags.iter().all(matches_tag),
+                TagMatchMode::Any => query.tags.is_empty() || query.tags.iter().any(matches_tag),
+            };
+            if !tags_match {
                 continue;
             }
 
+            // skip movies having any of the excluded tags
+            if movie.tags.iter().any(|movie_tag| {
+                exclude_tags
+                    .binary_search(&movie_tag.to_lowercase())
+                    .is_ok()
+            }) {
+                continue;
+            }
+
+            // skip movies with or without the requested subtitles
+            if let Some((has_subtitles, ref language)) = subtitle_filter {
+                let languages = &movie_with_date.subtitle_languages;
+                let found = match language {
+                    Some(language) => languages
+                        .iter()
+                        .any(|lang| lang.eq_ignore_ascii_case(language)),
+                    None => !languages.is_empty(),
+                };
+                if found != has_subtitles {
+                    continue;
+                }
+            }
+
             // add movie id if index is within range
             if num_hits >= start_index && end_index > num_hits {
                 movie_ids.push(id.clone());
@@ -189,47 +638,209 @@ impl MoviesIndex for SimpleMoviesIndex {
     async fn get_tag_list_with_count(&self) -> Result<Vec<(String, usize)>, Error> {
         info!("Getting tag list with count");
 
-        let mut tag_map: HashMap<String, usize> = HashMap::new();
-
-        for movie in self.movies.values() {
-            for tag in movie.movie.tags.iter() {
-                let count = tag_map.entry(tag.clone()).or_insert(0);
-                *count += 1;
-            }
-        }
+        Ok(Self::count_tags(
+            self.movies
+                .values()
+                .flat_map(|movie| movie.movie.tags.iter()),
+        ))
+    }
 
-        let mut tag_list: Vec<(String, usize)> = tag_map.into_iter().collect();
-        tag_list.sort_unstable_by(|(_, lhs), (_, rhs)| rhs.cmp(lhs));
+    async fn get_top_tags(&self, limit: usize) -> Result<Vec<(String, usize)>, Error> {
+        let mut tags = self.get_tag_list_with_count().await?;
+        tags.truncate(limit);
 
-        Ok(tag_list)
+        Ok(tags)
     }
-}
 
-impl SimpleMoviesIndex {
-    /// Returns a list of all movies sorted according to the given sorting parameter.
-    ///
-    /// # Arguments
-    /// * `field` - The field by which the movies should be sorted.
-    /// * `order` - The order in which the movies should be sorted.
-    fn get_movies_sorted(&self, field: SortingField, order: SortingOrder) -> Vec<MovieId> {
-        match field {
-            SortingField::Title => self.get_movies_sorted_by_title(order),
-            SortingField::Date => self.get_movies_sorted_by_date(order),
-        }
+    async fn search_tags(&self, prefix: &str, limit: usize) -> Result<Vec<(String, usize)>, Error> {
+        let prefix = prefix.to_lowercase();
+
+        let mut tags = Self::count_tags(
+            self.movies
+                .values()
+                .flat_map(|movie| movie.movie.tags.iter())
+                .filter(|tag| tag.to_lowercase().starts_with(&prefix)),
+        );
+        tags.truncate(limit);
+
+        Ok(tags)
     }
 
-    /// Returns a list of all movies sorted by their title.
-    ///
-    /// # Arguments
-    /// * `order` - The order in which the movies should be sorted.
-    fn get_movies_sorted_by_title(&self, order: SortingOrder) -> Vec<MovieId> {
-        let mut movies: Vec<(MovieId, String)> = self
+    async fn suggest_tags(&self, tags: &[String]) -> Result<Vec<(String, usize)>, Error> {
+        let mut tags = tags.to_vec();
+        Self::process_tags(&mut tags);
+
+        let is_input_tag = |tag: &String| tags.contains(&tag.to_lowercase());
+
+        let co_occurring_tags = self
             .movies
-            .iter()
-            .map(|(id, movie)| (id.clone(), movie.movie.title.clone()))
-            .collect();
+            .values()
+            .map(|movie| &movie.movie.tags)
+            .filter(|movie_tags| movie_tags.iter().any(is_input_tag))
+            .flat_map(|movie_tags| movie_tags.iter().filter(|tag| !is_input_tag(tag)));
 
-        movies.sort_unstable_by(|(_, lhs), (_, rhs)| lhs.cmp(rhs));
+        Ok(Self::count_tags(co_occurring_tags))
+    }
+
+    async fn get_year_histogram(&self) -> Result<Vec<(u16, usize)>, Error> {
+        let mut histogram: BTreeMap<u16, usize> = BTreeMap::new();
+        for movie in self.movies.values() {
+            let year = movie.date.with_timezone(&self.display_timezone).year();
+            *histogram.entry(year as u16).or_insert(0) += 1;
+        }
+
+        Ok(histogram.into_iter().collect())
+    }
+
+    async fn get_search_facets(&self, query: MovieSearchQuery) -> Result<SearchFacets, Error> {
+        let query = MovieSearchQuery {
+            start_index: None,
+            num_results: None,
+            ..query
+        };
+
+        let movie_ids = self.search_movies(query).await?;
+        let movies: Vec<&MovieDetailed> = movie_ids
+            .iter()
+            .filter_map(|id| self.movies.get(id))
+            .collect();
+
+        let mut years: BTreeMap<u16, usize> = BTreeMap::new();
+        let mut formats: BTreeMap<String, usize> = BTreeMap::new();
+        for movie in movies.iter() {
+            let year = movie.date.with_timezone(&self.display_timezone).year();
+            *years.entry(year as u16).or_insert(0) += 1;
+
+            if let Some(info) = &movie.movie_file_info {
+                *formats.entry(info.extension.clone()).or_insert(0) += 1;
+            }
+        }
+
+        // the sort is stable, s.t. equally often values keep their ascending order
+        let mut years: Vec<(u16, usize)> = years.into_iter().collect();
+        years.sort_by_key(|(_, count)| Reverse(*count));
+        let mut formats: Vec<(String, usize)> = formats.into_iter().collect();
+        formats.sort_by_key(|(_, count)| Reverse(*count));
+
+        Ok(SearchFacets {
+            tags: Self::count_tags(movies.iter().flat_map(|movie| movie.movie.tags.iter())),
+            years,
+            formats,
+        })
+    }
+
+    async fn update_screenshot_hash(&mut self, id: &MovieId, hash: u64) -> Result<(), Error> {
+        if !self.movies.contains_key(id) {
+            error!("Movie with id {} not found", id);
+            return Err(Error::NotFound(format!("Movie with id {} not found", id)));
+        }
+
+        self.screenshot_hashes.insert(id.clone(), hash);
+
+        Ok(())
+    }
+
+    async fn get_screenshot_hashes(&self) -> Result<Vec<(MovieId, u64)>, Error> {
+        let mut hashes: Vec<(MovieId, u64)> = self
+            .screenshot_hashes
+            .iter()
+            .map(|(id, hash)| (id.clone(), *hash))
+            .collect();
+        hashes.sort();
+
+        Ok(hashes)
+    }
+
+    fn get_query_stats(&self) -> Option<BTreeMap<String, QueryStats>> {
+        // the in-memory queries are not worth timing
+        None
+    }
+
+    async fn search_movies_count(&self, query: MovieSearchQuery) -> Result<usize, Error> {
+        let query = MovieSearchQuery {
+            start_index: None,
+            num_results: None,
+            ..query
+        };
+
+        Ok(self.search_movies(query).await?.len())
+    }
+
+    async fn get_letter_offset(
+        &self,
+        query: MovieSearchQuery,
+        letter: char,
+    ) -> Result<usize, Error> {
+        let sorting_order = query.sorting_order;
+        let featured_first = query.featured_first;
+        let query = MovieSearchQuery {
+            sorting_field: SortingField::Title,
+            start_index: None,
+            num_results: None,
+            ..query
+        };
+
+        let letter_string = letter.to_string();
+        let mut offset = 0usize;
+        for id in self.search_movies(query).await? {
+            let title = match self.movies.get(&id) {
+                Some(movie_with_date) => &movie_with_date.movie.title,
+                None => continue,
+            };
+
+            // the featured movies precede all others, if they come first, and in descending
+            // order, the titles starting with the letter are preceded by the greater titles not
+            // starting with the letter
+            let is_before = featured_first && self.is_featured(&id)
+                || match sorting_order {
+                    SortingOrder::Ascending => title.as_str() < letter_string.as_str(),
+                    SortingOrder::Descending => {
+                        title.as_str() > letter_string.as_str() && !title.starts_with(letter)
+                    }
+                };
+
+            if is_before {
+                offset += 1;
+            }
+        }
+
+        Ok(offset)
+    }
+}
+
+impl SimpleMoviesIndex {
+    /// Returns true if the movie with the given id is featured.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    fn is_featured(&self, id: &MovieId) -> bool {
+        self.movies.get(id).is_some_and(|movie| movie.featured)
+    }
+
+    /// Returns a list of all movies sorted according to the given sorting parameter.
+    ///
+    /// # Arguments
+    /// * `field` - The field by which the movies should be sorted.
+    /// * `order` - The order in which the movies should be sorted.
+    fn get_movies_sorted(&self, field: SortingField, order: SortingOrder) -> Vec<MovieId> {
+        match field {
+            SortingField::Title => self.get_movies_sorted_by_title(order),
+            SortingField::Date => self.get_movies_sorted_by_date(order),
+        }
+    }
+
+    /// Returns a list of all movies sorted by their title.
+    ///
+    /// # Arguments
+    /// * `order` - The order in which the movies should be sorted.
+    fn get_movies_sorted_by_title(&self, order: SortingOrder) -> Vec<MovieId> {
+        let mut movies: Vec<(MovieId, String)> = self
+            .movies
+            .iter()
+            .map(|(id, movie)| (id.clone(), movie.movie.title.clone()))
+            .collect();
+
+        movies.sort_unstable_by(|(_, lhs), (_, rhs)| lhs.cmp(rhs));
 
         if order == SortingOrder::Ascending {
             movies.iter().map(|(id, _)| id.clone()).collect()
@@ -245,7 +856,7 @@ impl SimpleMoviesIndex {
             .map(|(id, movie)| (id.clone(), movie.date))
             .collect();
 
-        movies.sort_unstable_by(|(_, lhs), (_, rhs)| lhs.cmp(rhs));
+        movies.sort_unstable_by_key(|(_, date)| *date);
 
         if order == SortingOrder::Ascending {
             movies.iter().map(|(id, _)| id.clone()).collect()
@@ -259,80 +870,773 @@ impl SimpleMoviesIndex {
 mod test {
     use super::*;
 
-    fn create_test_movies() -> Vec<Movie> {
-        vec![Movie { title: "Doctor Who".to_owned(), description: "The Doctor, a Time Lord from the race whose home planet is Gallifrey, travels through time and space in their ship the TARDIS (an acronym for Time and Relative Dimension In Space) with numerous companions.".to_owned(), tags: vec!["Sci-Fi".to_owned(), "time travel".to_owned(), "british".to_owned(), "tv show".to_owned()] },
-        Movie { title: "The X-Files".to_owned(), description: "Two F.B.I. Agents, Fox Mulder the believer and Dana Scully the skeptic, investigate the strange and unexplained, while hidden forces work to impede their efforts.".to_owned(), tags: vec!["crime".to_owned(), "drama".to_owned(), "mystery".to_owned(), "usa".to_owned(), "tv show".to_owned()] },
-        Movie { title: "E.T. the Extra-Terrestrial".to_owned(), description: "A troubled child summons the courage to help a friendly alien escape from Earth and return to his home planet.".to_owned(), tags: vec!["adventure".to_owned(), "family".to_owned(), "Sci-Fi".to_owned(), "usa".to_owned(), "movie".to_owned()] },
-        Movie { title: "Das Boot".to_owned(), description: "A German U-boat stalks the frigid waters of the North Atlantic as its young crew experience the sheer terror and claustrophobic life of a submariner in World War II.".to_owned(), tags: vec!["drama".to_owned(), "war".to_owned(), "germany".to_owned(), "movie".to_owned()] }]
+    fn create_test_movies() -> Vec<Movie> {
+        vec![Movie { title: "Doctor Who".to_owned(), description: "The Doctor, a Time Lord from the race whose home planet is Gallifrey, travels through time and space in their ship the TARDIS (an acronym for Time and Relative Dimension In Space) with numerous companions.".to_owned(), tags: vec!["Sci-Fi".to_owned(), "time travel".to_owned(), "british".to_owned(), "tv show".to_owned()] },
+        Movie { title: "The X-Files".to_owned(), description: "Two F.B.I. Agents, Fox Mulder the believer and Dana Scully the skeptic, investigate the strange and unexplained, while hidden forces work to impede their efforts.".to_owned(), tags: vec!["crime".to_owned(), "drama".to_owned(), "mystery".to_owned(), "usa".to_owned(), "tv show".to_owned()] },
+        Movie { title: "E.T. the Extra-Terrestrial".to_owned(), description: "A troubled child summons the courage to help a friendly alien escape from Earth and return to his home planet.".to_owned(), tags: vec!["adventure".to_owned(), "family".to_owned(), "Sci-Fi".to_owned(), "usa".to_owned(), "movie".to_owned()] },
+        Movie { title: "Das Boot".to_owned(), description: "A German U-boat stalks the frigid waters of the North Atlantic as its young crew experience the sheer terror and claustrophobic life of a submariner in World War II.".to_owned(), tags: vec!["drama".to_owned(), "war".to_owned(), "germany".to_owned(), "movie".to_owned()] }]
+    }
+
+    fn create_tag_suggestion_movies() -> Vec<Movie> {
+        let movie = |title: &str, tags: &[&str]| Movie {
+            title: title.to_owned(),
+            description: String::new(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        };
+
+        vec![
+            movie("Alien", &["Sci-Fi", "space", "aliens"]),
+            movie("Interstellar", &["Sci-Fi", "space"]),
+            movie("Galaxy Quest", &["Sci-Fi", "space", "aliens", "comedy"]),
+            movie("Cosmos", &["space", "documentary"]),
+            movie("Das Boot", &["drama", "war"]),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_add_movie() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+
+        let movies = create_test_movies();
+
+        for movie in movies {
+            let ret = index.add_movie(movie.clone()).await;
+            assert!(ret.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_movie_with_id() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+        let movies = create_test_movies();
+
+        // supply a fresh id
+        let id = index
+            .add_movie_with_id("doctor-who".to_owned(), movies[0].clone())
+            .await
+            .unwrap();
+        assert_eq!(id, "doctor-who");
+        assert_eq!(
+            index.get_movie(&id).await.unwrap().movie.title,
+            "Doctor Who"
+        );
+
+        // supply a colliding id
+        assert!(matches!(
+            index
+                .add_movie_with_id("doctor-who".to_owned(), movies[1].clone())
+                .await,
+            Err(Error::AlreadyExists(_))
+        ));
+        assert_eq!(
+            index.get_movie(&id).await.unwrap().movie.title,
+            "Doctor Who"
+        );
+
+        // supply an invalid id
+        assert!(matches!(
+            index
+                .add_movie_with_id("../x-files".to_owned(), movies[1].clone())
+                .await,
+            Err(Error::InvalidArgument(_))
+        ));
+
+        // omit the id
+        let id = index.add_movie(movies[1].clone()).await.unwrap();
+        assert_ne!(id, "doctor-who");
+        assert_eq!(
+            index.get_movie(&id).await.unwrap().movie.title,
+            "The X-Files"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_movie() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+        let movies = create_test_movies();
+
+        let mut movie_ids: Vec<MovieId> = Vec::with_capacity(movies.len());
+        for movie in movies.iter() {
+            let id = index.add_movie(movie.clone()).await.unwrap();
+            movie_ids.push(id);
+        }
+
+        for (id, movie) in movie_ids.iter().zip(movies.iter()) {
+            let db_movie = index.get_movie(id).await.unwrap();
+
+            assert_eq!(movie.title, db_movie.movie.title);
+            assert_eq!(movie.description, db_movie.movie.description);
+
+            let mut ref_tags = movie.tags.clone();
+            SimpleMoviesIndex::process_tags(&mut ref_tags);
+            assert_eq!(ref_tags, db_movie.movie.tags);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_movie_exists() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+
+        let mut movie_ids: Vec<MovieId> = Vec::new();
+        for movie in create_test_movies().into_iter() {
+            movie_ids.push(index.add_movie(movie).await.unwrap());
+        }
+
+        for id in movie_ids.iter() {
+            assert!(index.movie_exists(id).await.unwrap());
+        }
+        assert!(!index.movie_exists(&generate_movie_id()).await.unwrap());
+
+        index.remove_movie(&movie_ids[0]).await.unwrap();
+        assert!(!index.movie_exists(&movie_ids[0]).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_video_metadata() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+
+        let video_metadata = VideoMetadata {
+            width: 1920,
+            height: 1080,
+            codec: "h264".to_owned(),
+            bitrate: Some(4_500_000),
+            frame_rate: Some(25.0),
+            duration: Some(5940.5),
+        };
+
+        let id = index
+            .add_movie(create_test_movies()[0].clone())
+            .await
+            .unwrap();
+        assert_eq!(index.get_movie(&id).await.unwrap().video_metadata, None);
+
+        index
+            .update_video_metadata(&id, video_metadata.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            index.get_movie(&id).await.unwrap().video_metadata,
+            Some(video_metadata.clone())
+        );
+
+        // the metadata belongs to the movie file
+        index.remove_movie_file_info(&id).await.unwrap();
+        assert_eq!(index.get_movie(&id).await.unwrap().video_metadata, None);
+
+        let ret = index
+            .update_video_metadata(&generate_movie_id(), video_metadata)
+            .await;
+        assert!(matches!(ret, Err(Error::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_remove_movie() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+        let movies = create_test_movies();
+
+        let mut movie_ids: Vec<MovieId> = Vec::with_capacity(movies.len());
+        for movie in movies.iter() {
+            let id = index.add_movie(movie.clone()).await.unwrap();
+            movie_ids.push(id);
+        }
+
+        movie_ids.sort();
+
+        index.remove_movie(&movie_ids[0]).await.unwrap();
+        assert!(index.remove_movie(&movie_ids[0]).await.is_err());
+
+        let movie_ids = &movie_ids[1..];
+        let mut listed_movie_ids = index.search_movies(Default::default()).await.unwrap();
+        listed_movie_ids.sort();
+
+        assert_eq!(movie_ids, listed_movie_ids);
+    }
+
+    #[tokio::test]
+    async fn test_touch_movie() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+        let movies = create_test_movies();
+
+        let mut movie_ids: Vec<MovieId> = Vec::with_capacity(movies.len());
+        for movie in movies.iter() {
+            let id = index.add_movie(movie.clone()).await.unwrap();
+            movie_ids.push(id);
+        }
+
+        // the most recently added movie comes first
+        let listed_movie_ids = index.search_movies(Default::default()).await.unwrap();
+        assert_eq!(&listed_movie_ids[0], movie_ids.last().unwrap());
+
+        // touching the first movie moves it to the front
+        index.touch_movie(&movie_ids[0]).await.unwrap();
+        let listed_movie_ids = index.search_movies(Default::default()).await.unwrap();
+        assert_eq!(listed_movie_ids[0], movie_ids[0]);
+        assert_eq!(listed_movie_ids.len(), movie_ids.len());
+
+        assert!(matches!(
+            index.touch_movie(&"unknown".to_owned()).await,
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_apply_batch() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+        let movies = create_test_movies();
+        let id = index.add_movie(movies[0].clone()).await.unwrap();
+        let other_id = index.add_movie(movies[1].clone()).await.unwrap();
+        let before = index.get_movie(&id).await.unwrap();
+
+        // a batch with a failing edit leaves the movie entirely unchanged
+        let batch = IndexBatch::new()
+            .update_movie(
+                id.clone(),
+                Movie {
+                    title: "Doctor Who (2005)".to_owned(),
+                    description: "The revived series.".to_owned(),
+                    tags: vec!["british".to_owned()],
+                },
+            )
+            .add_tags(id.clone(), vec!["classic".to_owned()])
+            .set_featured(id.clone(), true)
+            .touch_movie(id.clone())
+            .set_featured(generate_movie_id(), true);
+        let ret = index.apply_batch(batch).await;
+        assert!(matches!(ret, Err(Error::NotFound(_))));
+
+        let after = index.get_movie(&id).await.unwrap();
+        assert_eq!(after.movie.title, before.movie.title);
+        assert_eq!(after.movie.description, before.movie.description);
+        assert_eq!(after.movie.tags, before.movie.tags);
+        assert_eq!(after.featured, before.featured);
+        assert_eq!(after.date, before.date);
+
+        // a successful batch applies all edits in their order
+        let batch = IndexBatch::new()
+            .update_movie(
+                id.clone(),
+                Movie {
+                    title: "Doctor Who (2005)".to_owned(),
+                    description: "The revived series.".to_owned(),
+                    tags: vec!["british".to_owned()],
+                },
+            )
+            .add_tags(id.clone(), vec!["classic".to_owned()])
+            .set_featured(other_id.clone(), true);
+        index.apply_batch(batch).await.unwrap();
+
+        let movie = index.get_movie(&id).await.unwrap();
+        assert_eq!(movie.movie.title, "Doctor Who (2005)");
+        assert_eq!(movie.movie.tags, ["british", "classic"]);
+        assert!(index.get_movie(&other_id).await.unwrap().featured);
+    }
+
+    #[tokio::test]
+    async fn test_update_movie() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+        let movies = create_test_movies();
+        let id = index.add_movie(movies[0].clone()).await.unwrap();
+
+        index
+            .update_movie(
+                &id,
+                Movie {
+                    title: "Doctor Who (2005)".to_owned(),
+                    description: "The revived series.".to_owned(),
+                    tags: vec!["TV Show".to_owned(), "british".to_owned()],
+                },
+            )
+            .await
+            .unwrap();
+
+        let movie = index.get_movie(&id).await.unwrap();
+        assert_eq!(movie.movie.title, "Doctor Who (2005)");
+        assert_eq!(movie.movie.description, "The revived series.");
+        assert_eq!(movie.movie.tags, ["british", "tv show"]);
+
+        // the search reflects the new title and tags
+        let mut query: MovieSearchQuery = Default::default();
+        query.title = Some("*(2005)".to_owned());
+        assert_eq!(
+            index.search_movies(query).await.unwrap(),
+            std::slice::from_ref(&id)
+        );
+        let mut query: MovieSearchQuery = Default::default();
+        query.tags = vec!["sci-fi".to_owned()];
+        assert!(index.search_movies(query).await.unwrap().is_empty());
+
+        let mut movie = movies[1].clone();
+        assert!(matches!(
+            index
+                .update_movie(&"unknown".to_owned(), movie.clone())
+                .await,
+            Err(Error::NotFound(_))
+        ));
+        movie.title.clear();
+        assert!(matches!(
+            index.update_movie(&id, movie).await,
+            Err(Error::InvalidArgument(_))
+        ));
+        assert_eq!(
+            index.get_movie(&id).await.unwrap().movie.title,
+            "Doctor Who (2005)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_tag() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+
+        let mut movie_ids: Vec<MovieId> = Vec::new();
+        for movie in create_test_movies() {
+            movie_ids.push(index.add_movie(movie).await.unwrap());
+        }
+
+        let tags = |movie: MovieDetailed| -> Vec<String> {
+            let mut tags = movie.movie.tags;
+            tags.sort();
+            tags
+        };
+
+        assert_eq!(index.rename_tag("Drama", "thriller").await.unwrap(), 2);
+        assert_eq!(
+            tags(index.get_movie(&movie_ids[3]).await.unwrap()),
+            ["germany", "movie", "thriller", "war"]
+        );
+
+        // renaming onto an existing tag of the movie merges both tags
+        assert_eq!(index.rename_tag("crime", "mystery").await.unwrap(), 1);
+        assert_eq!(
+            tags(index.get_movie(&movie_ids[1]).await.unwrap()),
+            ["mystery", "thriller", "tv show", "usa"]
+        );
+
+        // unknown tags affect no movie
+        assert_eq!(index.rename_tag("western", "cowboys").await.unwrap(), 0);
+        assert!(matches!(
+            index.rename_tag("war", "").await,
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rename_tags() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+
+        let mut movie_ids: Vec<MovieId> = Vec::new();
+        for movie in create_test_movies() {
+            movie_ids.push(index.add_movie(movie).await.unwrap());
+        }
+
+        let renames = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+            pairs
+                .iter()
+                .map(|(old_tag, new_tag)| (old_tag.to_string(), new_tag.to_string()))
+                .collect()
+        };
+        let tags = |movie: MovieDetailed| -> Vec<String> {
+            let mut tags = movie.movie.tags;
+            tags.sort();
+            tags
+        };
+
+        // a simple batch, which follows the chain from "tv" to "series"
+        let counts = index
+            .rename_tags(&renames(&[
+                ("Sci-Fi", "science fiction"),
+                ("tv show", "tv"),
+                ("tv", "series"),
+            ]))
+            .await
+            .unwrap();
+        assert_eq!(
+            counts,
+            BTreeMap::from([
+                ("sci-fi".to_owned(), 2),
+                ("tv".to_owned(), 0),
+                ("tv show".to_owned(), 2)
+            ])
+        );
+        assert_eq!(
+            tags(index.get_movie(&movie_ids[0]).await.unwrap()),
+            ["british", "science fiction", "series", "time travel"]
+        );
+
+        // renaming onto an existing tag merges both tags
+        let counts = index
+            .rename_tags(&renames(&[("crime", "drama")]))
+            .await
+            .unwrap();
+        assert_eq!(counts, BTreeMap::from([("crime".to_owned(), 1)]));
+        assert_eq!(
+            tags(index.get_movie(&movie_ids[1]).await.unwrap()),
+            ["drama", "mystery", "series", "usa"]
+        );
+
+        // cycles are rejected without renaming any tag
+        assert!(matches!(
+            index
+                .rename_tags(&renames(&[("usa", "america"), ("america", "usa")]))
+                .await,
+            Err(Error::InvalidArgument(_))
+        ));
+        assert_eq!(
+            tags(index.get_movie(&movie_ids[2]).await.unwrap()),
+            ["adventure", "family", "movie", "science fiction", "usa"]
+        );
+
+        let tag_list = index.get_tag_list_with_count().await.unwrap();
+        assert!(tag_list.contains(&("drama".to_owned(), 2)));
+        assert!(tag_list.contains(&("science fiction".to_owned(), 2)));
+        assert!(!tag_list
+            .iter()
+            .any(|(tag, _)| tag == "crime" || tag == "sci-fi"));
+    }
+
+    #[tokio::test]
+    async fn test_featured_first() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+
+        let mut movie_ids: Vec<MovieId> = Vec::new();
+        for movie in create_test_movies() {
+            movie_ids.push(index.add_movie(movie).await.unwrap());
+        }
+
+        // feature "The X-Files" and "E.T. the Extra-Terrestrial"
+        index.set_featured(&movie_ids[1], true).await.unwrap();
+        index.set_featured(&movie_ids[2], true).await.unwrap();
+        index.set_featured(&movie_ids[2], true).await.unwrap();
+        assert!(index.get_movie(&movie_ids[1]).await.unwrap().featured);
+        assert!(!index.get_movie(&movie_ids[0]).await.unwrap().featured);
+
+        // the featured movies lead the results and both parts keep their order
+        let mut query: MovieSearchQuery = Default::default();
+        query.sorting_field = SortingField::Title;
+        query.sorting_order = SortingOrder::Ascending;
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap()).await,
+            [
+                "Das Boot",
+                "Doctor Who",
+                "E.T. the Extra-Terrestrial",
+                "The X-Files"
+            ]
+        );
+        query.featured_first = true;
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap()).await,
+            [
+                "E.T. the Extra-Terrestrial",
+                "The X-Files",
+                "Das Boot",
+                "Doctor Who"
+            ]
+        );
+        assert_eq!(
+            index.get_letter_offset(query.clone(), 'D').await.unwrap(),
+            2
+        );
+
+        query.sorting_order = SortingOrder::Descending;
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap()).await,
+            [
+                "The X-Files",
+                "E.T. the Extra-Terrestrial",
+                "Doctor Who",
+                "Das Boot"
+            ]
+        );
+
+        // the pagination and the filters apply to the reordered results
+        query.sorting_order = SortingOrder::Ascending;
+        query.start_index = Some(1);
+        query.num_results = Some(2);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap()).await,
+            ["The X-Files", "Das Boot"]
+        );
+        query.start_index = None;
+        query.num_results = None;
+        query.tags = vec!["movie".to_owned()];
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap()).await,
+            ["E.T. the Extra-Terrestrial", "Das Boot"]
+        );
+
+        // unfeatured movies are sorted as usual
+        index.set_featured(&movie_ids[2], false).await.unwrap();
+        assert!(!index.get_movie(&movie_ids[2]).await.unwrap().featured);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Das Boot", "E.T. the Extra-Terrestrial"]
+        );
+
+        assert!(matches!(
+            index.set_featured(&"unknown".to_owned(), true).await,
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    async fn movie_ids_to_titles(index: &SimpleMoviesIndex, movie_ids: &[MovieId]) -> Vec<String> {
+        let mut movie_titles: Vec<String> = Vec::with_capacity(movie_ids.len());
+
+        for movie_id in movie_ids {
+            let movie = index.get_movie(movie_id).await.unwrap();
+            movie_titles.push(movie.movie.title.clone());
+        }
+
+        movie_titles
+    }
+
+    #[tokio::test]
+    async fn test_get_year_histogram() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+        assert!(index.get_year_histogram().await.unwrap().is_empty());
+
+        for (title, year) in [
+            ("Das Boot", 2019),
+            ("Alien", 2021),
+            ("Heat", 2019),
+            ("Apollo 13", 2023),
+        ] {
+            let id = index
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: String::new(),
+                    tags: Vec::new(),
+                })
+                .await
+                .unwrap();
+
+            let movie = index.movies.get_mut(&id).unwrap();
+            movie.date = movie.date.with_year(year).unwrap();
+        }
+
+        assert_eq!(
+            index.get_year_histogram().await.unwrap(),
+            [(2019, 2), (2021, 1), (2023, 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_search_facets() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+
+        for (title, tags, year, ext) in [
+            ("Alien", vec!["sci-fi", "horror"], 2019, Some("mkv")),
+            ("Interstellar", vec!["sci-fi", "space"], 2021, Some("mp4")),
+            ("Galaxy Quest", vec!["sci-fi", "comedy"], 2019, Some("mp4")),
+            ("Arrival", vec!["sci-fi"], 2023, None),
+            ("Das Boot", vec!["drama", "war"], 2021, Some("avi")),
+        ] {
+            let id = index
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: String::new(),
+                    tags: tags.iter().map(|t| t.to_string()).collect(),
+                })
+                .await
+                .unwrap();
+
+            let movie = index.movies.get_mut(&id).unwrap();
+            movie.date = movie.date.with_year(year).unwrap();
+
+            if let Some(ext) = ext {
+                index
+                    .update_movie_file_info(
+                        &id,
+                        MovieFileInfo {
+                            extension: ext.to_owned(),
+                            mime_type: format!("video/{}", ext),
+                            original_filename: None,
+                        },
+                    )
+                    .await
+                    .unwrap();
+            }
+        }
+
+        // the facets ignore the pagination of the query
+        let query = MovieSearchQuery {
+            tags: vec!["sci-fi".to_owned()],
+            start_index: Some(1),
+            num_results: Some(1),
+            ..Default::default()
+        };
+        let tag = |tag: &str, count: usize| (tag.to_owned(), count);
+        assert_eq!(
+            index.get_search_facets(query).await.unwrap(),
+            SearchFacets {
+                tags: vec![
+                    tag("sci-fi", 4),
+                    tag("comedy", 1),
+                    tag("horror", 1),
+                    tag("space", 1)
+                ],
+                years: vec![(2019, 2), (2021, 1), (2023, 1)],
+                formats: vec![tag("mp4", 2), tag("mkv", 1)],
+            }
+        );
+
+        let query = MovieSearchQuery {
+            title: Some("Nothing".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            index.get_search_facets(query).await.unwrap(),
+            SearchFacets::default()
+        );
     }
 
     #[tokio::test]
-    async fn test_add_movie() {
-        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
-
-        let movies = create_test_movies();
+    async fn test_duplicate_tags() {
+        for preserve_tag_case in [false, true] {
+            let mut options = Options::default();
+            options.preserve_tag_case = preserve_tag_case;
+            let mut index = SimpleMoviesIndex::new(&options).unwrap();
+
+            let id = index
+                .add_movie(Movie {
+                    title: "Das Boot".to_owned(),
+                    description: String::new(),
+                    tags: vec!["war".to_owned(), "War".to_owned(), "war".to_owned()],
+                })
+                .await
+                .unwrap();
+            index
+                .add_tags(&id, &["WAR".to_owned(), "drama".to_owned()])
+                .await
+                .unwrap();
 
-        for movie in movies {
-            let ret = index.add_movie(movie.clone()).await;
-            assert!(ret.is_ok());
+            // the first casing of a duplicated tag is kept
+            let movie = index.get_movie(&id).await.unwrap();
+            assert_eq!(movie.movie.tags, ["drama", "war"]);
+            assert_eq!(
+                index.get_tag_list_with_count().await.unwrap(),
+                [("drama".to_owned(), 1), ("war".to_owned(), 1)]
+            );
         }
     }
 
     #[tokio::test]
-    async fn test_get_movie() {
-        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
-        let movies = create_test_movies();
-
-        let mut movie_ids: Vec<MovieId> = Vec::with_capacity(movies.len());
-        for movie in movies.iter() {
-            let id = index.add_movie(movie.clone()).await.unwrap();
-            movie_ids.push(id);
-        }
+    async fn test_preserve_tag_case() {
+        let mut options = Options::default();
+        options.preserve_tag_case = true;
+        let mut index = SimpleMoviesIndex::new(&options).unwrap();
+
+        let apollo = index
+            .add_movie(Movie {
+                title: "Apollo 13".to_owned(),
+                description: String::new(),
+                tags: vec!["space".to_owned(), "NASA".to_owned()],
+            })
+            .await
+            .unwrap();
+        index
+            .add_movie(Movie {
+                title: "The Right Stuff".to_owned(),
+                description: String::new(),
+                tags: vec!["nasa".to_owned(), "Drama".to_owned()],
+            })
+            .await
+            .unwrap();
+
+        // the displayed tags keep their casing
+        let movie = index.get_movie(&apollo).await.unwrap();
+        assert_eq!(movie.movie.tags, ["NASA", "space"]);
+
+        // searching is case-insensitive
+        let query = MovieSearchQuery {
+            tags: vec!["nasa".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(index.search_movies(query).await.unwrap().len(), 2);
 
-        for (id, movie) in movie_ids.iter().zip(movies.iter()) {
-            let db_movie = index.get_movie(id).await.unwrap();
+        let query = MovieSearchQuery {
+            tags: vec!["Space".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(index.search_movies(query).await.unwrap(), [apollo]);
 
-            assert_eq!(movie.title, db_movie.movie.title);
-            assert_eq!(movie.description, db_movie.movie.description);
+        let tags = index.get_tag_list_with_count().await.unwrap();
+        assert_eq!(
+            tags,
+            [
+                ("NASA".to_owned(), 2),
+                ("Drama".to_owned(), 1),
+                ("space".to_owned(), 1)
+            ]
+        );
 
-            let mut ref_tags = movie.tags.clone();
-            SimpleMoviesIndex::process_tags(&mut ref_tags);
-            assert_eq!(ref_tags, db_movie.movie.tags);
-        }
+        let suggestions = index.suggest_tags(&["drama".to_owned()]).await.unwrap();
+        assert_eq!(suggestions, [("nasa".to_owned(), 1)]);
     }
 
     #[tokio::test]
-    async fn test_remove_movie() {
+    async fn test_suggest_tags() {
         let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
-        let movies = create_test_movies();
 
-        let mut movie_ids: Vec<MovieId> = Vec::with_capacity(movies.len());
-        for movie in movies.iter() {
-            let id = index.add_movie(movie.clone()).await.unwrap();
-            movie_ids.push(id);
+        for movie in create_tag_suggestion_movies() {
+            index.add_movie(movie).await.unwrap();
         }
 
-        movie_ids.sort();
+        let top_tags = index.get_top_tags(3).await.unwrap();
+        assert_eq!(
+            top_tags,
+            [
+                ("space".to_owned(), 4),
+                ("sci-fi".to_owned(), 3),
+                ("aliens".to_owned(), 2)
+            ]
+        );
+        assert_eq!(index.get_top_tags(100).await.unwrap().len(), 7);
+        assert!(index.get_top_tags(0).await.unwrap().is_empty());
 
-        index.remove_movie(&movie_ids[0]).await.unwrap();
-        assert!(index.remove_movie(&movie_ids[0]).await.is_err());
+        let suggestions = index.suggest_tags(&["Sci-Fi".to_owned()]).await.unwrap();
+        assert_eq!(
+            suggestions,
+            [
+                ("space".to_owned(), 3),
+                ("aliens".to_owned(), 2),
+                ("comedy".to_owned(), 1)
+            ]
+        );
 
-        let movie_ids = &movie_ids[1..];
-        let mut listed_movie_ids = index.search_movies(Default::default()).await.unwrap();
-        listed_movie_ids.sort();
+        let suggestions = index
+            .suggest_tags(&["sci-fi".to_owned(), "space".to_owned()])
+            .await
+            .unwrap();
+        assert_eq!(
+            suggestions,
+            [
+                ("aliens".to_owned(), 2),
+                ("comedy".to_owned(), 1),
+                ("documentary".to_owned(), 1)
+            ]
+        );
 
-        assert_eq!(movie_ids, listed_movie_ids);
+        assert!(index.suggest_tags(&[]).await.unwrap().is_empty());
     }
 
-    async fn movie_ids_to_titles(index: &SimpleMoviesIndex, movie_ids: &[MovieId]) -> Vec<String> {
-        let mut movie_titles: Vec<String> = Vec::with_capacity(movie_ids.len());
+    #[tokio::test]
+    async fn test_search_tags() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
 
-        for movie_id in movie_ids {
-            let movie = index.get_movie(movie_id).await.unwrap();
-            movie_titles.push(movie.movie.title.clone());
+        for movie in create_tag_suggestion_movies() {
+            index.add_movie(movie).await.unwrap();
         }
 
-        movie_titles
+        assert_eq!(
+            index.search_tags("S", 10).await.unwrap(),
+            [("space".to_owned(), 4), ("sci-fi".to_owned(), 3)]
+        );
+        assert_eq!(
+            index.search_tags("d", 1).await.unwrap(),
+            [("documentary".to_owned(), 1)]
+        );
+        assert_eq!(index.search_tags("", 100).await.unwrap().len(), 7);
+        assert!(index.search_tags("space", 0).await.unwrap().is_empty());
     }
 
     #[tokio::test]
@@ -399,7 +1703,18 @@ mod test {
             sorting_field: Default::default(),
             sorting_order: Default::default(),
             title: Some("Boot".to_owned()),
+            description: None,
+            added_after: None,
+            added_before: None,
+            added_within: None,
             tags: vec![],
+            tag_match_mode: TagMatchMode::All,
+            exclude_tags: vec![],
+            tag_prefix_match: false,
+            tag_fuzzy_match: false,
+            featured_first: false,
+            has_subtitles: None,
+            subtitle_lang: None,
             start_index: None,
             num_results: None,
         };
@@ -408,7 +1723,18 @@ mod test {
             sorting_field: Default::default(),
             sorting_order: Default::default(),
             title: Some("*Boot".to_owned()),
+            description: None,
+            added_after: None,
+            added_before: None,
+            added_within: None,
             tags: vec![],
+            tag_match_mode: TagMatchMode::All,
+            exclude_tags: vec![],
+            tag_prefix_match: false,
+            tag_fuzzy_match: false,
+            featured_first: false,
+            has_subtitles: None,
+            subtitle_lang: None,
             start_index: None,
             num_results: None,
         };
@@ -422,7 +1748,18 @@ mod test {
             sorting_field: SortingField::Title,
             sorting_order: SortingOrder::Ascending,
             title: None,
+            description: None,
+            added_after: None,
+            added_before: None,
+            added_within: None,
             tags: vec![],
+            tag_match_mode: TagMatchMode::All,
+            exclude_tags: vec![],
+            tag_prefix_match: false,
+            tag_fuzzy_match: false,
+            featured_first: false,
+            has_subtitles: None,
+            subtitle_lang: None,
             start_index: Some(0),
             num_results: Some(1),
         };
@@ -434,7 +1771,18 @@ mod test {
             sorting_field: SortingField::Title,
             sorting_order: SortingOrder::Ascending,
             title: None,
+            description: None,
+            added_after: None,
+            added_before: None,
+            added_within: None,
             tags: vec![],
+            tag_match_mode: TagMatchMode::All,
+            exclude_tags: vec![],
+            tag_prefix_match: false,
+            tag_fuzzy_match: false,
+            featured_first: false,
+            has_subtitles: None,
+            subtitle_lang: None,
             start_index: Some(1),
             num_results: Some(2),
         };
@@ -442,6 +1790,336 @@ mod test {
             movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
             ["Doctor Who", "E.T. the Extra-Terrestrial"]
         );
+
+        // test query 5: Tag prefixes only match if prefix matching is enabled
+        let mut query: MovieSearchQuery = Default::default();
+        query.tags = vec!["Brit".to_owned()];
+        assert!(index.search_movies(query.clone()).await.unwrap().is_empty());
+        query.tag_prefix_match = true;
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Doctor Who"]
+        );
+
+        let mut query: MovieSearchQuery = Default::default();
+        query.tags = vec!["sci".to_owned()];
+        query.tag_prefix_match = true;
+        query.sorting_field = SortingField::Title;
+        query.sorting_order = SortingOrder::Ascending;
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Doctor Who", "E.T. the Extra-Terrestrial"]
+        );
+
+        // wildcards within the prefix are matched literally
+        let mut query: MovieSearchQuery = Default::default();
+        query.tags = vec!["%".to_owned()];
+        query.tag_prefix_match = true;
+        assert!(index.search_movies(query).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tag_match_mode() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+        for movie in create_test_movies() {
+            index.add_movie(movie).await.unwrap();
+        }
+
+        let search = |tags: &[&str], tag_prefix_match: bool, tag_match_mode: TagMatchMode| {
+            MovieSearchQuery {
+                sorting_field: SortingField::Title,
+                sorting_order: SortingOrder::Ascending,
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                tag_prefix_match,
+                tag_match_mode,
+                ..Default::default()
+            }
+        };
+
+        // exact tags
+        let query = search(&["drama", "movie"], false, TagMatchMode::All);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Das Boot"]
+        );
+        let query = search(&["drama", "movie"], false, TagMatchMode::Any);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Das Boot", "E.T. the Extra-Terrestrial", "The X-Files"]
+        );
+
+        // tag prefixes, where each prefix must match on its own
+        let query = search(&["sci", "us"], true, TagMatchMode::All);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["E.T. the Extra-Terrestrial"]
+        );
+        let query = search(&["sci", "us"], true, TagMatchMode::Any);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Doctor Who", "E.T. the Extra-Terrestrial", "The X-Files"]
+        );
+        let query = search(&["ti", "tv"], true, TagMatchMode::All);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Doctor Who"]
+        );
+
+        // a single tag behaves the same in both modes
+        for tag_match_mode in [TagMatchMode::All, TagMatchMode::Any] {
+            let query = search(&["war"], false, tag_match_mode);
+            assert_eq!(
+                movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+                ["Das Boot"]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_movies_count() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+        for movie in create_test_movies() {
+            index.add_movie(movie).await.unwrap();
+        }
+
+        let search = |tags: &[&str], tag_match_mode: TagMatchMode| MovieSearchQuery {
+            sorting_field: SortingField::Title,
+            sorting_order: SortingOrder::Ascending,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            tag_match_mode,
+            start_index: Some(1),
+            num_results: Some(1),
+            ..Default::default()
+        };
+
+        // the count ignores the pagination of the query
+        for (tags, tag_match_mode, count) in [
+            (vec![], TagMatchMode::All, 4),
+            (vec!["drama", "movie"], TagMatchMode::All, 1),
+            (vec!["drama", "movie"], TagMatchMode::Any, 3),
+            (vec!["western"], TagMatchMode::Any, 0),
+        ] {
+            let query = search(&tags, tag_match_mode);
+            assert_eq!(
+                index.search_movies_count(query.clone()).await.unwrap(),
+                count,
+                "{:?}",
+                tags
+            );
+            assert_eq!(
+                index.search_movies(query).await.unwrap().len(),
+                usize::from(count > 1)
+            );
+        }
+
+        let mut query = search(&[], TagMatchMode::All);
+        query.title = Some("D*".to_owned());
+        assert_eq!(index.search_movies_count(query).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_exclude_tags() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+        for movie in create_test_movies() {
+            index.add_movie(movie).await.unwrap();
+        }
+
+        let search = |tags: &[&str], exclude_tags: &[&str]| MovieSearchQuery {
+            sorting_field: SortingField::Title,
+            sorting_order: SortingOrder::Ascending,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            exclude_tags: exclude_tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        };
+
+        // the excluded tags are compared case-insensitively
+        let query = search(&["sci-fi"], &["TV Show"]);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["E.T. the Extra-Terrestrial"]
+        );
+
+        // without included tags, all other movies are returned
+        let query = search(&[], &["usa", "war"]);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Doctor Who"]
+        );
+
+        // the exclusion composes with matching any tag and the title
+        let mut query = search(&["drama", "sci-fi"], &["usa"]);
+        query.tag_match_mode = TagMatchMode::Any;
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap()).await,
+            ["Das Boot", "Doctor Who"]
+        );
+        query.title = Some("Das*".to_owned());
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Das Boot"]
+        );
+        let mut query = search(&[], &["movie"]);
+        query.title = Some("E.T.*".to_owned());
+        assert!(index.search_movies(query).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_subtitles() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+        let mut ids = Vec::new();
+        for movie in create_test_movies() {
+            ids.push(index.add_movie(movie).await.unwrap());
+        }
+
+        // Doctor Who has English and German subtitles, Das Boot only German ones
+        let subtitle = |language: &str| SubtitleInfo {
+            language: language.to_owned(),
+            mime_type: "text/vtt".to_owned(),
+            extension: "vtt".to_owned(),
+        };
+        for (id, language) in [(&ids[0], "en"), (&ids[0], "de"), (&ids[3], "de")] {
+            index
+                .add_subtitle_info(id, subtitle(language))
+                .await
+                .unwrap();
+        }
+
+        let search = |has_subtitles: Option<bool>, subtitle_lang: Option<&str>| MovieSearchQuery {
+            sorting_field: SortingField::Title,
+            sorting_order: SortingOrder::Ascending,
+            has_subtitles,
+            subtitle_lang: subtitle_lang.map(|lang| lang.to_owned()),
+            ..Default::default()
+        };
+
+        for (query, titles) in [
+            (search(Some(true), None), vec!["Das Boot", "Doctor Who"]),
+            (
+                search(Some(false), None),
+                vec!["E.T. the Extra-Terrestrial", "The X-Files"],
+            ),
+            // the language is compared case-insensitively
+            (search(None, Some("EN")), vec!["Doctor Who"]),
+            (
+                search(Some(true), Some("de")),
+                vec!["Das Boot", "Doctor Who"],
+            ),
+            (
+                search(Some(false), Some("en")),
+                vec!["Das Boot", "E.T. the Extra-Terrestrial", "The X-Files"],
+            ),
+            (search(None, Some("fr")), vec![]),
+        ] {
+            assert_eq!(
+                index.search_movies_count(query.clone()).await.unwrap(),
+                titles.len()
+            );
+            assert_eq!(
+                movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+                titles
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_date_range() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+        for (title, date) in [
+            ("Das Boot", "2019-03-01T12:00:00+00:00"),
+            ("Alien", "2021-12-31T23:59:59.250+00:00"),
+            ("Heat", "2022-06-15T08:30:00+00:00"),
+            ("Apollo 13", "2023-01-01T00:00:00+00:00"),
+        ] {
+            let id = index
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: String::new(),
+                    tags: Vec::new(),
+                })
+                .await
+                .unwrap();
+
+            index.movies.get_mut(&id).unwrap().date = date.parse().unwrap();
+        }
+
+        let search = |added_after: Option<&str>, added_before: Option<&str>| MovieSearchQuery {
+            sorting_field: SortingField::Title,
+            sorting_order: SortingOrder::Ascending,
+            added_after: added_after.map(|date| date.parse().unwrap()),
+            added_before: added_before.map(|date| date.parse().unwrap()),
+            ..Default::default()
+        };
+
+        let query = search(Some("2021-01-01T00:00:00Z"), None);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Alien", "Apollo 13", "Heat"]
+        );
+
+        let query = search(None, Some("2022-01-01T00:00:00Z"));
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Alien", "Das Boot"]
+        );
+
+        // the start of the range is inclusive, whereas its end is exclusive
+        let query = search(Some("2021-12-31T23:59:59Z"), Some("2023-01-01T00:00:00Z"));
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Alien", "Heat"]
+        );
+        let query = search(Some("2022-06-15T10:30:00+02:00"), None);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Apollo 13", "Heat"]
+        );
+
+        // an inverted range is rejected
+        let query = search(Some("2023-01-01T00:00:00Z"), Some("2021-01-01T00:00:00Z"));
+        assert!(matches!(
+            index.search_movies(query).await,
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_search_description() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+        for movie in create_test_movies() {
+            index.add_movie(movie).await.unwrap();
+        }
+
+        let search = |title: Option<&str>, description: Option<&str>| {
+            let mut query: MovieSearchQuery = Default::default();
+            query.sorting_field = SortingField::Title;
+            query.sorting_order = SortingOrder::Ascending;
+            query.title = title.map(|s| s.to_owned());
+            query.description = description.map(|s| s.to_owned());
+            query
+        };
+
+        // description only
+        let query = search(None, Some("*home planet*"));
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Doctor Who", "E.T. the Extra-Terrestrial"]
+        );
+
+        // title only
+        let query = search(Some("Doctor*"), None);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Doctor Who"]
+        );
+
+        // both title and description must match
+        let query = search(Some("E.T.*"), Some("*home planet*"));
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["E.T. the Extra-Terrestrial"]
+        );
+        let query = search(Some("Doctor*"), Some("*alien*"));
+        assert!(index.search_movies(query).await.unwrap().is_empty());
     }
 
     #[tokio::test]
@@ -459,7 +2137,7 @@ mod test {
 
         // make sure non of the added movies has a file info
         for movie_id in movie_ids.iter() {
-            let movie = index.get_movie(&movie_id).await.unwrap();
+            let movie = index.get_movie(movie_id).await.unwrap();
             assert!(movie.movie_file_info.is_none());
         }
 
@@ -470,10 +2148,12 @@ mod test {
             MovieFileInfo {
                 extension: ".mp4".to_owned(),
                 mime_type: "video/mp4".to_owned(),
+                original_filename: None,
             },
             MovieFileInfo {
                 extension: ".wmv".to_owned(),
                 mime_type: "video/x-ms-wmv".to_owned(),
+                original_filename: None,
             },
         ];
 
@@ -512,7 +2192,7 @@ mod test {
 
         // make sure non of the added movies has a screenshot info
         for movie_id in movie_ids.iter() {
-            let movie = index.get_movie(&movie_id).await.unwrap();
+            let movie = index.get_movie(movie_id).await.unwrap();
             assert!(movie.screenshot_file_info.is_none());
         }
 
@@ -523,10 +2203,12 @@ mod test {
             ScreenshotInfo {
                 extension: ".png".to_owned(),
                 mime_type: "video/png".to_owned(),
+                count: 1,
             },
             ScreenshotInfo {
                 extension: ".jpeg".to_owned(),
                 mime_type: "image/jpeg".to_owned(),
+                count: 1,
             },
         ];
 
@@ -549,4 +2231,71 @@ mod test {
             assert!(movie.screenshot_file_info.is_none());
         }
     }
+
+    #[tokio::test]
+    async fn test_get_letter_offset() {
+        let mut index = SimpleMoviesIndex::new(&Options::default()).unwrap();
+
+        let movie = |title: &str, tags: &[&str]| Movie {
+            title: title.to_owned(),
+            description: String::new(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        };
+        for movie in [
+            movie("Alien", &["space"]),
+            movie("Cosmos", &["space"]),
+            movie("Das Boot", &["war"]),
+            movie("Doctor Who", &["space"]),
+            movie("Heat", &[]),
+        ] {
+            index.add_movie(movie).await.unwrap();
+        }
+
+        // the offset points at the first title starting with the letter
+        let mut query: MovieSearchQuery = Default::default();
+        query.sorting_field = SortingField::Title;
+        query.sorting_order = SortingOrder::Ascending;
+        let offset = index.get_letter_offset(query.clone(), 'D').await.unwrap();
+        assert_eq!(offset, 2);
+        let movie_ids = index.search_movies(query.clone()).await.unwrap();
+        assert_eq!(
+            movie_ids_to_titles(&index, &movie_ids[offset..offset + 1]).await,
+            ["Das Boot"]
+        );
+
+        // without a matching title, the offset is the position of the next title
+        assert_eq!(
+            index.get_letter_offset(query.clone(), 'E').await.unwrap(),
+            4
+        );
+        assert_eq!(
+            index.get_letter_offset(query.clone(), 'Z').await.unwrap(),
+            5
+        );
+
+        // the pagination of the query is ignored
+        query.start_index = Some(3);
+        query.num_results = Some(1);
+        assert_eq!(
+            index.get_letter_offset(query.clone(), 'D').await.unwrap(),
+            2
+        );
+
+        // in descending order, the titles starting with the letter follow the greater titles
+        query.sorting_order = SortingOrder::Descending;
+        query.start_index = None;
+        query.num_results = None;
+        let offset = index.get_letter_offset(query.clone(), 'D').await.unwrap();
+        assert_eq!(offset, 1);
+        let movie_ids = index.search_movies(query.clone()).await.unwrap();
+        assert_eq!(
+            movie_ids_to_titles(&index, &movie_ids[offset..offset + 1]).await,
+            ["Doctor Who"]
+        );
+
+        // the filters of the query are applied
+        query.sorting_order = SortingOrder::Ascending;
+        query.tags = vec!["space".to_owned()];
+        assert_eq!(index.get_letter_offset(query, 'D').await.unwrap(), 2);
+    }
 }
@@ -1,26 +1,143 @@
-use std::fs::create_dir_all;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::create_dir_all,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use chrono::{DateTime, Utc};
-use itertools::Itertools;
+use chrono::{DateTime, FixedOffset, Utc};
 use log::{debug, error, info};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::{Connection, Result};
-use tokio::sync::Mutex;
 
 use async_trait::async_trait;
 
 use crate::{
-    generate_movie_id, Error, Movie, MovieDetailed, MovieFileInfo, MovieId, MovieSearchQuery,
-    MoviesIndex, Options, ScreenshotInfo, SortingField, SortingOrder,
+    generate_movie_id, is_valid_movie_id, resolve_tag_renames, AnimatedPreviewInfo, Error,
+    IndexBatch, Movie, MovieDetailed, MovieEdit, MovieFileInfo, MovieId, MovieSearchQuery,
+    MoviesIndex, Options, PreviewState, QueryStats, QueryTimer, QueryTimings, ScreenshotInfo,
+    SearchFacets, SortingField, SortingOrder, SubtitleInfo, TagMatchMode, VideoMetadata,
 };
 
+/// The time a connection waits for a locked database before failing.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The movies index stored in an SQLite database. The queries are blocking and hence run on the
+/// blocking thread pool of the runtime, s.t. they don't stall the async workers. All clones share
+/// the same pool of connections.
+#[derive(Clone)]
 pub struct SqliteMoviesIndex {
-    /// The connection to the database.
-    connection: Mutex<Connection>,
+    /// The pool of connections to the database. In WAL mode, reads run concurrently to each
+    /// other and to the single write.
+    pool: Pool<SqliteConnectionManager>,
+
+    /// The lock serializing the writes, s.t. concurrent writes wait for each other instead of
+    /// failing on a busy database.
+    write_lock: Arc<Mutex<()>>,
+
+    /// If true, the tags of the movies keep their original casing.
+    preserve_tag_case: bool,
+
+    /// Optionally, the size in bytes above which descriptions are stored in a separate table.
+    description_offload_threshold: Option<usize>,
+
+    /// The timezone for grouping movies by date.
+    display_timezone: FixedOffset,
+
+    /// Optionally, the execution times of the queries, which are only collected on demand.
+    query_timings: Option<Arc<QueryTimings>>,
 }
 
 impl SqliteMoviesIndex {
+    /// Starts measuring the execution time of the given query, if query timing is enabled.
+    ///
+    /// # Arguments
+    /// * `query` - The kind of the query, e.g., "search_movies".
+    fn time_query(&self, query: &'static str) -> Option<QueryTimer<'_>> {
+        self.query_timings
+            .as_ref()
+            .map(|query_timings| query_timings.start(query))
+    }
+
+    /// Runs the given reading function with a pooled connection on the blocking thread pool and
+    /// returns its result. The function gets a clone of the index for accessing its settings.
+    ///
+    /// # Arguments
+    /// * `f` - The function running the queries.
+    async fn run_blocking<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Self, &mut Connection) -> Result<T, Error> + Send + 'static,
+    {
+        self.run_pooled(false, f).await
+    }
+
+    /// Same as `run_blocking`, but for functions writing to the database, which run one at a
+    /// time.
+    ///
+    /// # Arguments
+    /// * `f` - The function running the queries.
+    async fn run_blocking_write<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Self, &mut Connection) -> Result<T, Error> + Send + 'static,
+    {
+        self.run_pooled(true, f).await
+    }
+
+    /// Runs the given function with a pooled connection on the blocking thread pool.
+    ///
+    /// # Arguments
+    /// * `write` - True if the function writes to the database.
+    /// * `f` - The function running the queries.
+    async fn run_pooled<T, F>(&self, write: bool, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Self, &mut Connection) -> Result<T, Error> + Send + 'static,
+    {
+        let index = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let _write_guard = if write {
+                Some(index.write_lock.lock().map_err(|_| {
+                    Error::Internal("The write lock of the database is poisoned".to_owned())
+                })?)
+            } else {
+                None
+            };
+
+            let mut connection = index.pool.get()?;
+            f(&index, &mut connection)
+        })
+        .await
+        .map_err(|e| Error::Internal(format!("Failed to run the database query: {}", e)))?
+    }
+
+    /// Creates the pool of connections to the given database. Each connection uses the WAL
+    /// journal, s.t. reads don't block each other, and waits for a locked database.
+    ///
+    /// # Arguments
+    /// * `sqlite_path` - The path to the database file.
+    /// * `pool_size` - The maximal number of connections.
+    fn create_pool(
+        sqlite_path: &Path,
+        pool_size: usize,
+    ) -> Result<Pool<SqliteConnectionManager>, r2d2::Error> {
+        let manager = SqliteConnectionManager::file(sqlite_path).with_init(|connection| {
+            connection.busy_timeout(BUSY_TIMEOUT)?;
+            connection.execute_batch("PRAGMA journal_mode=WAL;")
+        });
+
+        Pool::builder()
+            .max_size(u32::try_from(pool_size.max(1)).unwrap_or(u32::MAX))
+            .build(manager)
+    }
+
     fn create_tables(connection: &Connection) -> Result<(), rusqlite::Error> {
         info!("Create the tables...");
+
+        // the dates are stored in UTC in RFC 3339 format
         connection.execute(
             "CREATE TABLE IF NOT EXISTS movies (
                 id TEXT PRIMARY KEY,
@@ -34,7 +151,30 @@ impl SqliteMoviesIndex {
         connection.execute(
             "CREATE TABLE IF NOT EXISTS tags (
                 id TEXT NOT NULL,
-                tag TEXT NOT NULL
+                tag TEXT NOT NULL,
+                display_tag TEXT
+            )",
+            (),
+        )?;
+
+        // databases created before the display casing of tags existed lack the column
+        Self::add_missing_column(connection, "tags", "display_tag", "TEXT")?;
+
+        // databases created before the tags were unique per movie may contain duplicates, which
+        // are removed before the unique index can be created
+        connection.execute(
+            "DELETE FROM tags WHERE rowid NOT IN (SELECT MIN(rowid) FROM tags GROUP BY id, tag)",
+            (),
+        )?;
+        connection.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS tags_id_tag ON tags (id, tag)",
+            (),
+        )?;
+
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS descriptions (
+                id TEXT PRIMARY KEY,
+                text TEXT NOT NULL
             )",
             (),
         )?;
@@ -43,190 +183,221 @@ impl SqliteMoviesIndex {
             "CREATE TABLE IF NOT EXISTS file_infos (
                 id TEXT PRIMARY KEY,
                 mime_type TEXT NOT NULL,
-                extension TEXT NOT NULL
+                extension TEXT NOT NULL,
+                original_filename TEXT
             )",
             (),
         )?;
 
+        // databases created before the original filenames were kept lack the column
+        Self::add_missing_column(connection, "file_infos", "original_filename", "TEXT")?;
+
         connection.execute(
             "CREATE TABLE IF NOT EXISTS screenshot_infos (
                 id TEXT PRIMARY KEY,
                 mime_type TEXT NOT NULL,
-                extension TEXT NOT NULL
+                extension TEXT NOT NULL,
+                count INTEGER
             )",
             (),
         )?;
 
-        info!("Create the tables...DONE");
-
-        Ok(())
-    }
-
-    async fn search_movies_impl(&self, query: MovieSearchQuery) -> Result<Vec<MovieId>, Error> {
-        let query_string = if query.tags.is_empty() {
-            self.create_search_movies_no_tags_query_string(&query)
-        } else {
-            self.create_search_movies_with_tags_query_string(&query)
-        };
-
-        let connection = self.connection.lock().await;
-
-        let mut stmt = connection.prepare(&query_string)?;
-
-        let rows = stmt.query_map([], |row| {
-            let id: MovieId = row.get(0)?;
-
-            Ok(id)
-        })?;
+        // databases created before multiple screenshots were taken lack the column
+        Self::add_missing_column(connection, "screenshot_infos", "count", "INTEGER")?;
 
-        let mut ids = Vec::new();
-        for row in rows {
-            ids.push(row?);
-        }
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS preview_states (
+                id TEXT PRIMARY KEY,
+                state TEXT NOT NULL,
+                reason TEXT
+            )",
+            (),
+        )?;
 
-        Ok(ids)
-    }
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS screenshot_hashes (
+                id TEXT PRIMARY KEY,
+                hash INTEGER NOT NULL
+            )",
+            (),
+        )?;
 
-    fn create_search_movies_no_tags_query_string(&self, query: &MovieSearchQuery) -> String {
-        // search query without tags:
-        // SELECT m.id FROM movies m WHERE m.title LIKE '%Das%' ORDER BY title DESC LIMIT 10 OFFSET 0
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS video_metadata (
+                id TEXT PRIMARY KEY,
+                width INTEGER NOT NULL,
+                height INTEGER NOT NULL,
+                codec TEXT NOT NULL,
+                bitrate INTEGER,
+                frame_rate REAL,
+                duration REAL
+            )",
+            (),
+        )?;
 
-        let mut query_string = "SELECT m.id FROM movies m".to_owned();
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS animated_preview_infos (
+                id TEXT PRIMARY KEY,
+                mime_type TEXT NOT NULL,
+                extension TEXT NOT NULL
+            )",
+            (),
+        )?;
 
-        // check for where clause
-        match &query.title {
-            Some(title) => {
-                let title = title.replace('*', "%");
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS subtitle_infos (
+                id TEXT NOT NULL,
+                language TEXT NOT NULL,
+                mime_type TEXT NOT NULL,
+                extension TEXT NOT NULL,
+                PRIMARY KEY (id, language)
+            )",
+            (),
+        )?;
 
-                query_string.push_str(" WHERE m.title LIKE '");
-                query_string.push_str(&title);
-                query_string.push('\'');
-            }
-            None => {}
-        }
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS featured_movies (
+                id TEXT PRIMARY KEY
+            )",
+            (),
+        )?;
 
-        query_string.push_str(&Self::create_order_and_limit_string(query));
+        info!("Create the tables...DONE");
 
-        query_string
+        Ok(())
     }
 
-    fn create_search_movies_with_tags_query_string(&self, query: &MovieSearchQuery) -> String {
-        // search query without tags:
-        // SELECT m.id FROM movies m, tags t WHERE m.title LIKE '%Das%' AND m.id = t.id AND t.tag IN ('war','germany') GROUP BY m.id ORDER BY title DESC LIMIT 10 OFFSET 0
-        let mut query_string = "SELECT m.id FROM movies m, tags t WHERE m.id = t.id ".to_owned();
-
-        // create WHERE clause for the tags
-        let tags = query
-            .tags
-            .iter()
-            .map(|tag| format!("'{}'", tag.to_lowercase()))
-            .join(",");
-        let tags = "(".to_owned() + &tags + ")";
-        query_string.push_str(" AND t.tag IN ");
-        query_string.push_str(&tags);
-
-        // add WHERE clause for the title if available
-        match &query.title {
-            Some(title) => {
-                let title = title.replace('*', "%");
-
-                query_string.push_str(" AND m.title LIKE '");
-                query_string.push_str(&title);
-                query_string.push('\'');
+    /// Returns the tags starting with the given prefix with the number of movies associated with
+    /// each tag, ordered descending by their count and ascending by the tag.
+    ///
+    /// # Arguments
+    /// * `prefix` - The lower case prefix of the tags, an empty prefix matches all tags.
+    /// * `limit` - The maximal number of tags to return, a negative limit returns all tags.
+    async fn query_tag_counts(
+        &self,
+        prefix: &str,
+        limit: i64,
+    ) -> Result<Vec<(String, usize)>, Error> {
+        let pattern = Self::escape_like_pattern(prefix) + "%";
+
+        self.run_blocking(move |_, connection| {
+            let mut stmt = connection.prepare(
+                "SELECT MIN(COALESCE(display_tag, tag)), COUNT(*) FROM tags WHERE tag LIKE ?1 ESCAPE '\\' GROUP BY tag ORDER BY COUNT(*) DESC, tag ASC LIMIT ?2",
+            )?;
+
+            let rows = stmt.query_map((&pattern, limit), |row| {
+                let tag: String = row.get(0)?;
+                let count: usize = row.get(1)?;
+
+                Ok((tag, count))
+            })?;
+
+            let mut tags: Vec<(String, usize)> = Vec::new();
+            for row in rows {
+                tags.push(row?);
             }
-            None => {}
-        }
-
-        // add GROUP BY clause
-        query_string.push_str(" GROUP BY m.id ");
-
-        query_string.push_str(&Self::create_order_and_limit_string(query));
 
-        query_string
+            Ok(tags)
+        })
+        .await
     }
 
-    /// Creates the ORDER and LIMIT string based on the provided query.
+    /// Runs the given statement selecting values together with their counts and returns them.
     ///
     /// # Arguments
-    /// * `query` - The query to create the ORDER and LIMIT string for.
-    fn create_order_and_limit_string(query: &MovieSearchQuery) -> String {
-        let mut order_and_limit = String::new();
-
-        // field
-        let field = match query.sorting_field {
-            SortingField::Title => "m.title",
-            SortingField::Date => "m.date_added",
-        };
-
-        // order
-        let order = match query.sorting_order {
-            SortingOrder::Ascending => "ASC",
-            SortingOrder::Descending => "DESC",
-        };
-
-        order_and_limit.push_str(&format!(" ORDER BY {} {} ", field, order));
+    /// * `connection` - The connection to run the statement on.
+    /// * `query_string` - The statement selecting the values and their counts.
+    /// * `params` - The values of the `?` placeholders of the statement.
+    fn query_value_counts<T: rusqlite::types::FromSql>(
+        connection: &Connection,
+        query_string: &str,
+        params: &[String],
+    ) -> Result<Vec<(T, usize)>, Error> {
+        let mut stmt = connection.prepare(query_string)?;
+
+        let rows = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| {
+            let value: T = row.get(0)?;
+            let count: usize = row.get(1)?;
 
-        // limit
-        if let Some(limit) = query.num_results {
-            order_and_limit.push_str(&format!(" LIMIT {} ", limit));
-        } else {
-            order_and_limit.push_str(" LIMIT -1 ");
-        }
+            Ok((value, count))
+        })?;
 
-        // offset
-        if let Some(offset) = query.start_index {
-            order_and_limit.push_str(&format!(" OFFSET {} ", offset));
-        } else {
-            order_and_limit.push_str(" OFFSET 0 ");
+        let mut counts = Vec::new();
+        for row in rows {
+            counts.push(row?);
         }
 
-        order_and_limit
+        Ok(counts)
     }
-}
 
-#[async_trait]
-impl MoviesIndex for SqliteMoviesIndex {
-    fn new(options: &Options) -> Result<Self, Error>
-    where
-        Self: Sized,
-    {
-        if let Err(err) = create_dir_all(&options.root_dir) {
-            error!("Failed to create the root directory: {}", err);
-            return Err(err.into());
+    /// Parses the preview state stored in the preview_states table.
+    ///
+    /// # Arguments
+    /// * `state` - The name of the state.
+    /// * `reason` - The reason of a failed preview generation.
+    fn parse_preview_state(state: &str, reason: Option<String>) -> Result<PreviewState, Error> {
+        match state {
+            "pending" => Ok(PreviewState::Pending),
+            "ready" => Ok(PreviewState::Ready),
+            "failed" => Ok(PreviewState::Failed {
+                reason: reason.unwrap_or_default(),
+            }),
+            "unsupported" => Ok(PreviewState::Unsupported),
+            _ => Err(Error::Internal(format!(
+                "Unknown preview state '{}'",
+                state
+            ))),
         }
+    }
 
-        let mut sqlite_path = options.root_dir.clone();
-        sqlite_path.push("movies.db");
-
-        debug!("SQLite database path: {}", sqlite_path.display());
+    /// Adds the given nullable column to the given table, if the column doesn't exist yet.
+    ///
+    /// # Arguments
+    /// * `connection` - The connection to the database.
+    /// * `table` - The name of the table.
+    /// * `column` - The name of the column to add.
+    /// * `column_type` - The type of the column to add, e.g., "TEXT".
+    fn add_missing_column(
+        connection: &Connection,
+        table: &str,
+        column: &str,
+        column_type: &str,
+    ) -> Result<(), rusqlite::Error> {
+        let has_column: bool = connection.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+            (table, column),
+            |row| row.get(0),
+        )?;
 
-        if sqlite_path.exists() {
-            info!("Found existing movies.db");
+        if !has_column {
+            info!("Add the {} column to the {} table...", column, table);
+            connection.execute(
+                &format!(
+                    "ALTER TABLE {} ADD COLUMN {} {}",
+                    table, column, column_type
+                ),
+                (),
+            )?;
         }
 
-        match Connection::open(sqlite_path) {
-            Err(err) => {
-                error!("Failed to open the SQLite database: {}", err);
-                Err(Error::IO(format!("Failed to open SQLite DB{}", err)))
-            }
-            Ok(connection) => {
-                if let Err(err) = Self::create_tables(&connection) {
-                    error!("Failed to create the tables: {}", err);
-                    return Err(Error::Internal(format!(
-                        "Failed to create the tables: {}",
-                        err
-                    )));
-                }
-
-                let connection = Mutex::new(connection);
-
-                Ok(Self { connection })
-            }
-        }
+        Ok(())
     }
 
-    async fn add_movie(&mut self, movie: Movie) -> Result<MovieId, Error> {
-        let id = generate_movie_id();
+    /// Inserts the given movie with the given id using the given connection. Fails if the movie
+    /// has no title or if the id is invalid or already taken.
+    ///
+    /// # Arguments
+    /// * `connection` - The connection to insert the movie with.
+    /// * `id` - The ID for the new movie.
+    /// * `movie` - The movie to insert.
+    /// * `date` - The date at which the movie has been added in RFC 3339 format.
+    fn insert_movie(
+        &self,
+        connection: &Connection,
+        id: &MovieId,
+        movie: Movie,
+        date: &str,
+    ) -> Result<(), Error> {
         info!("Adding movie {} with id {}", movie.title, id);
 
         // check if movie has title
@@ -237,419 +408,3378 @@ impl MoviesIndex for SqliteMoviesIndex {
             ));
         }
 
-        let date = chrono::Utc::now().to_rfc3339();
+        // check if the id is valid
+        if !is_valid_movie_id(id) {
+            error!("Invalid movie id {}", id);
+            return Err(Error::InvalidArgument(format!("Invalid movie id {}", id)));
+        }
 
-        let connection = self.connection.lock().await;
+        // check if the id is not yet taken
+        let mut stmt = connection.prepare("SELECT COUNT(*) FROM movies WHERE id=:id")?;
+        let count: usize = stmt.query_row(&[(":id", id)], |row| row.get(0))?;
+        if count > 0 {
+            error!("Movie with id {} already exists", id);
+            return Err(Error::AlreadyExists(format!(
+                "Movie with id {} already exists",
+                id
+            )));
+        }
 
         // insert movie details
+        let description_preview = self.insert_description(connection, id, &movie.description)?;
         connection.execute(
             "INSERT INTO movies (id, title, description, date_added) VALUES (?1, ?2, ?3, ?4)",
-            (&id, &movie.title, &movie.description, &date),
+            (id, &movie.title, description_preview, date),
         )?;
 
-        // insert tags
-        let mut stmt = connection.prepare("INSERT INTO tags (id, tag) VALUES (?1, ?2)")?;
-        for tag in movie.tags {
-            stmt.execute((&id, &tag.to_lowercase()))?;
-        }
+        self.insert_tags(connection, id, movie.tags)?;
 
-        Ok(id)
+        Ok(())
     }
 
-    async fn get_movie(&self, id: &MovieId) -> Result<MovieDetailed, Error> {
-        info!("Getting movie with id {}", id);
-
-        let connection = self.connection.lock().await;
+    /// Replaces the title, the description and the tags of the movie with the given id.
+    ///
+    /// # Arguments
+    /// * `connection` - The connection to update the movie with.
+    /// * `id` - The id of the movie to update.
+    /// * `movie` - The new title, description and tags of the movie.
+    fn update_movie_with(
+        &self,
+        connection: &Connection,
+        id: &MovieId,
+        movie: Movie,
+    ) -> Result<(), Error> {
+        info!("Updating movie {} with id {}", movie.title, id);
 
-        // get the movie details
-        let mut stmt =
-            connection.prepare("SELECT title, description, date_added FROM movies WHERE id=:id")?;
-        let mut rows = stmt.query_map(&[(":id", &id)], |row| {
-            let title: String = row.get(0)?;
-            let description: String = row.get(1)?;
-            let date: String = row.get(2)?;
+        // check if movie has title
+        if movie.title.is_empty() {
+            error!("Movie has no title");
+            return Err(Error::InvalidArgument(
+                "Movie title must not be empty".to_string(),
+            ));
+        }
 
-            Ok((title, description, date))
-        })?;
+        // replace the separately stored description
+        connection.execute("DELETE FROM descriptions WHERE id=:id", &[(":id", &id)])?;
+        let description_preview = self.insert_description(connection, id, &movie.description)?;
 
-        let row = match rows.next() {
-            None => {
-                error!("No movie with id {} found", id);
-                return Err(Error::NotFound(format!("No movie with id {} found", id)));
-            }
-            Some(row) => row?,
-        };
+        // update movie details, stop if there was no movie with the given id
+        if connection.execute(
+            "UPDATE movies SET title = ?1, description = ?2 WHERE id = ?3",
+            (&movie.title, description_preview, id),
+        )? == 0
+        {
+            error!("No movie with id {} found", id);
+            return Err(Error::NotFound(format!("No movie with id {} found", id)));
+        }
 
-        let title = row.0;
-        let description = row.1;
-        let date_added: DateTime<Utc> = match row.2.parse() {
-            Err(err) => {
-                error!("Failed to parse date: {}", err);
-                return Err(Error::Internal(format!("Failed to parse date: {}", err)));
-            }
-            Ok(date) => date,
-        };
+        // replace tags
+        connection.execute("DELETE FROM tags WHERE id=:id", &[(":id", &id)])?;
+        self.insert_tags(connection, id, movie.tags)?;
 
-        // get the tags
-        let mut stmt = connection.prepare("SELECT tag FROM tags WHERE id=:id ORDER BY tag")?;
-        let rows = stmt.query_map(&[(":id", &id)], |row| {
-            let tag: String = row.get(0)?;
+        Ok(())
+    }
 
-            Ok(tag)
-        })?;
+    /// Adds the given tags to the movie with the given id, skipping the tags it already has.
+    ///
+    /// # Arguments
+    /// * `connection` - The connection to add the tags with.
+    /// * `id` - The id of the movie.
+    /// * `tags` - The tags to add.
+    fn add_tags_with(
+        &self,
+        connection: &Connection,
+        id: &MovieId,
+        tags: &[String],
+    ) -> Result<(), Error> {
+        info!("Adding tags {:?} to movie with id {}", tags, id);
 
-        let mut tags: Vec<String> = Vec::new();
-        for row in rows {
-            tags.push(row?);
+        let count: usize =
+            connection.query_row("SELECT COUNT(*) FROM movies WHERE id = ?1", [id], |row| {
+                row.get(0)
+            })?;
+        if count == 0 {
+            error!("No movie with id {} found", id);
+            return Err(Error::NotFound(format!("No movie with id {} found", id)));
         }
 
-        // get movie file info, if available
-        let mut stmt =
-            connection.prepare("SELECT mime_type, extension FROM file_infos WHERE id=:id")?;
-        let mut rows = stmt.query_map(&[(":id", &id)], |row| {
-            let mime_type: String = row.get(0)?;
-            let extension: String = row.get(1)?;
+        // the tags are matched by their lower case variant, s.t. existing tags are skipped
+        let mut stmt = connection
+            .prepare("INSERT OR IGNORE INTO tags (id, tag, display_tag) VALUES (?1, ?2, ?3)")?;
+        for tag in tags {
+            let lower_tag = tag.to_lowercase();
+            let display_tag = if self.preserve_tag_case {
+                tag.clone()
+            } else {
+                lower_tag.clone()
+            };
+
+            stmt.execute((id, &lower_tag, &display_tag))?;
+        }
 
-            Ok((mime_type, extension))
-        })?;
+        Ok(())
+    }
 
-        let movie_file_info = match rows.next() {
-            None => None,
-            Some(row) => {
-                let (mime_type, extension) = row?;
+    /// Sets or clears the featured flag of the movie with the given id.
+    ///
+    /// # Arguments
+    /// * `connection` - The connection to update the flag with.
+    /// * `id` - The id of the movie.
+    /// * `featured` - True to feature the movie and false to clear the flag.
+    fn set_featured_with(
+        connection: &Connection,
+        id: &MovieId,
+        featured: bool,
+    ) -> Result<(), Error> {
+        info!(
+            "Setting featured flag of movie with id {} to {}",
+            id, featured
+        );
 
-                Some(MovieFileInfo {
-                    mime_type,
-                    extension,
-                })
-            }
-        };
+        let count: usize =
+            connection.query_row("SELECT COUNT(*) FROM movies WHERE id = ?1", [id], |row| {
+                row.get(0)
+            })?;
+        if count == 0 {
+            error!("No movie with id {} found", id);
+            return Err(Error::NotFound(format!("No movie with id {} found", id)));
+        }
 
-        // get movie screenshot info, if available
-        let mut stmt =
-            connection.prepare("SELECT mime_type, extension FROM screenshot_infos WHERE id=:id")?;
-        let mut rows = stmt.query_map(&[(":id", &id)], |row| {
-            let mime_type: String = row.get(0)?;
-            let extension: String = row.get(1)?;
+        if featured {
+            connection.execute(
+                "INSERT OR IGNORE INTO featured_movies (id) VALUES (?1)",
+                [id],
+            )?;
+        } else {
+            connection.execute("DELETE FROM featured_movies WHERE id = ?1", [id])?;
+        }
 
-            Ok((mime_type, extension))
-        })?;
+        Ok(())
+    }
 
-        let screenshot_file_info = match rows.next() {
-            None => None,
-            Some(row) => {
-                let (mime_type, extension) = row?;
+    /// Sets the date at which the movie with the given id has been added to now.
+    ///
+    /// # Arguments
+    /// * `connection` - The connection to update the date with.
+    /// * `id` - The id of the movie.
+    fn touch_movie_with(connection: &Connection, id: &MovieId) -> Result<(), Error> {
+        info!("Touching movie with id {}", id);
 
-                Some(ScreenshotInfo {
-                    mime_type,
-                    extension,
-                })
+        let date = chrono::Utc::now().to_rfc3339();
+
+        if connection.execute(
+            "UPDATE movies SET date_added = ?1 WHERE id = ?2",
+            (&date, &id),
+        )? == 0
+        {
+            error!("No movie with id {} found", id);
+            return Err(Error::NotFound(format!("No movie with id {} found", id)));
+        }
+
+        Ok(())
+    }
+
+    /// Stores the given description of the movie with the given id separately if it is large and
+    /// returns the part of it to keep in the movies table, i.e., its preview or the whole
+    /// description.
+    ///
+    /// # Arguments
+    /// * `connection` - The connection to the database.
+    /// * `id` - The id of the movie.
+    /// * `description` - The description of the movie.
+    fn insert_description<'a>(
+        &self,
+        connection: &Connection,
+        id: &MovieId,
+        description: &'a str,
+    ) -> Result<&'a str, Error> {
+        match self.description_offload_threshold {
+            Some(threshold) if description.len() > threshold => {
+                connection.execute(
+                    "INSERT INTO descriptions (id, text) VALUES (?1, ?2)",
+                    (id, description),
+                )?;
+
+                Ok(Self::create_description_preview(description, threshold))
+            }
+            _ => Ok(description),
+        }
+    }
+
+    /// Inserts the given tags of the movie with the given id.
+    ///
+    /// # Arguments
+    /// * `connection` - The connection to the database.
+    /// * `id` - The id of the movie.
+    /// * `tags` - The tags of the movie.
+    fn insert_tags(
+        &self,
+        connection: &Connection,
+        id: &MovieId,
+        tags: Vec<String>,
+    ) -> Result<(), Error> {
+        // the tags are matched by their lower case variant, s.t. duplicates are skipped and the
+        // first casing of a tag is kept
+        let mut stmt = connection
+            .prepare("INSERT OR IGNORE INTO tags (id, tag, display_tag) VALUES (?1, ?2, ?3)")?;
+        for tag in tags {
+            let lower_tag = tag.to_lowercase();
+            let display_tag = if self.preserve_tag_case {
+                tag
+            } else {
+                lower_tag.clone()
+            };
+
+            stmt.execute((id, &lower_tag, &display_tag))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the preview of the given description, i.e., its longest prefix with at most the
+    /// given number of bytes that doesn't split a character.
+    ///
+    /// # Arguments
+    /// * `description` - The description to return the preview for.
+    /// * `max_bytes` - The maximal number of bytes of the preview.
+    fn create_description_preview(description: &str, max_bytes: usize) -> &str {
+        let mut end = max_bytes.min(description.len());
+        while !description.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        &description[..end]
+    }
+
+    async fn search_movies_impl(&self, query: MovieSearchQuery) -> Result<Vec<MovieId>, Error> {
+        let (query_string, params) = Self::create_search_movies_query(&query);
+
+        self.run_blocking(move |_, connection| {
+            let mut stmt = connection.prepare(&query_string)?;
+
+            let rows = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| {
+                let id: MovieId = row.get(0)?;
+
+                Ok(id)
+            })?;
+
+            let mut ids = Vec::new();
+            for row in rows {
+                ids.push(row?);
+            }
+
+            Ok(ids)
+        })
+        .await
+    }
+
+    /// Creates the statement searching the ids of the movies matching the given query together
+    /// with the values of its `?` placeholders, s.t. no user string is pasted into the SQL.
+    ///
+    /// # Arguments
+    /// * `query` - The query to create the statement for.
+    fn create_search_movies_query(query: &MovieSearchQuery) -> (String, Vec<String>) {
+        if query.tags.is_empty() {
+            Self::create_search_movies_no_tags_query(query)
+        } else {
+            Self::create_search_movies_with_tags_query(query)
+        }
+    }
+
+    fn create_search_movies_no_tags_query(query: &MovieSearchQuery) -> (String, Vec<String>) {
+        // search query without tags:
+        // SELECT m.id FROM movies m WHERE m.title LIKE ? ESCAPE '\' ORDER BY title DESC LIMIT 10 OFFSET 0
+
+        let mut query_string = "SELECT m.id FROM movies m".to_owned();
+        let mut params = Vec::new();
+
+        // check for where clause
+        let conditions = Self::create_filter_conditions(query, &mut params);
+        if !conditions.is_empty() {
+            query_string.push_str(" WHERE ");
+            query_string.push_str(&conditions.join(" AND "));
+        }
+
+        query_string.push_str(&Self::create_order_and_limit_string(query));
+
+        (query_string, params)
+    }
+
+    fn create_search_movies_with_tags_query(query: &MovieSearchQuery) -> (String, Vec<String>) {
+        // search query with all tags matching:
+        // SELECT m.id FROM movies m, tags t WHERE m.id = t.id AND t.tag IN (?,?) AND m.title LIKE ? ESCAPE '\' GROUP BY m.id HAVING COUNT(DISTINCT t.tag) = 2 ORDER BY title DESC LIMIT 10 OFFSET 0
+        // search query with any tag matching:
+        // SELECT DISTINCT m.id FROM movies m, tags t WHERE m.id = t.id AND t.tag IN (?,?) AND m.title LIKE ? ESCAPE '\' ORDER BY title DESC LIMIT 10 OFFSET 0
+        let tags: BTreeSet<String> = query.tags.iter().map(|tag| tag.to_lowercase()).collect();
+        let mut query_string = match query.tag_match_mode {
+            TagMatchMode::All => "SELECT m.id",
+            TagMatchMode::Any => "SELECT DISTINCT m.id",
+        }
+        .to_owned();
+        query_string.push_str(" FROM movies m, tags t WHERE m.id = t.id ");
+        let mut params = Vec::new();
+
+        // create WHERE clause for the tags
+        let patterns: Vec<String> = if query.tag_prefix_match {
+            tags.iter()
+                .map(|tag| Self::escape_like_pattern(tag) + "%")
+                .collect()
+        } else {
+            Vec::new()
+        };
+        if query.tag_prefix_match {
+            let conditions = vec!["t.tag LIKE ? ESCAPE '\\'"; patterns.len()].join(" OR ");
+            query_string.push_str(" AND (");
+            query_string.push_str(&conditions);
+            query_string.push(')');
+
+            params.extend(patterns.iter().cloned());
+        } else {
+            let placeholders = vec!["?"; tags.len()].join(",");
+            query_string.push_str(" AND t.tag IN (");
+            query_string.push_str(&placeholders);
+            query_string.push(')');
+
+            params.extend(tags.iter().cloned());
+        }
+
+        // add WHERE clauses for the remaining filters if available
+        for condition in Self::create_filter_conditions(query, &mut params) {
+            query_string.push_str(" AND ");
+            query_string.push_str(&condition);
+        }
+
+        // add GROUP BY and HAVING clauses, s.t. only movies matching all tags remain
+        if query.tag_match_mode == TagMatchMode::All {
+            query_string.push_str(" GROUP BY m.id HAVING ");
+            if query.tag_prefix_match {
+                // a single tag of the movie may match several prefixes, hence each prefix is
+                // checked on its own
+                let conditions =
+                    vec!["MAX(t.tag LIKE ? ESCAPE '\\')"; patterns.len()].join(" AND ");
+                query_string.push_str(&conditions);
+                params.extend(patterns);
+            } else {
+                query_string.push_str(&format!("COUNT(DISTINCT t.tag) = {}", tags.len()));
+            }
+        }
+
+        query_string.push_str(&Self::create_order_and_limit_string(query));
+
+        (query_string, params)
+    }
+
+    /// Creates the conditions for the title, the description, the date range, the excluded tags
+    /// and the subtitles of the given query and appends their values to the given parameters. Offloaded
+    /// descriptions are matched in full and not only their preview.
+    ///
+    /// # Arguments
+    /// * `query` - The query to create the conditions for.
+    /// * `params` - The parameters to which the values are appended.
+    fn create_filter_conditions(query: &MovieSearchQuery, params: &mut Vec<String>) -> Vec<String> {
+        let mut conditions = Vec::new();
+
+        if let Some(title) = &query.title {
+            conditions.push("m.title LIKE ? ESCAPE '\\'".to_owned());
+            params.push(Self::create_wildcard_pattern(title));
+        }
+
+        if let Some(description) = &query.description {
+            conditions.push(
+                "COALESCE((SELECT d.text FROM descriptions d WHERE d.id = m.id), m.description) \
+                LIKE ? ESCAPE '\\'"
+                    .to_owned(),
+            );
+            params.push(Self::create_wildcard_pattern(description));
+        }
+
+        // the dates are compared as julian days, since the stored dates differ in the number of
+        // fractional digits of their seconds, s.t. they can't be compared as strings
+        if let Some(added_after) = &query.added_after {
+            conditions.push("julianday(m.date_added) >= julianday(?)".to_owned());
+            params.push(added_after.to_rfc3339());
+        }
+
+        if let Some(added_before) = &query.added_before {
+            conditions.push("julianday(m.date_added) < julianday(?)".to_owned());
+            params.push(added_before.to_rfc3339());
+        }
+
+        // the excluded tags are matched by their lower case variant
+        let exclude_tags: BTreeSet<String> = query
+            .exclude_tags
+            .iter()
+            .map(|tag| tag.to_lowercase())
+            .collect();
+        if !exclude_tags.is_empty() {
+            let placeholders = vec!["?"; exclude_tags.len()].join(",");
+            conditions.push(format!(
+                "m.id NOT IN (SELECT id FROM tags WHERE tag IN ({}))",
+                placeholders
+            ));
+            params.extend(exclude_tags);
+        }
+
+        if let Some((has_subtitles, language)) = query.get_subtitle_filter() {
+            let negation = if has_subtitles { "" } else { "NOT " };
+            match language {
+                Some(language) => {
+                    conditions.push(format!(
+                        "m.id {}IN (SELECT id FROM subtitle_infos WHERE language = ? COLLATE NOCASE)",
+                        negation
+                    ));
+                    params.push(language);
+                }
+                None => conditions.push(format!(
+                    "m.id {}IN (SELECT id FROM subtitle_infos)",
+                    negation
+                )),
+            }
+        }
+
+        conditions
+    }
+
+    /// Creates the LIKE pattern for the given title or description query, where `*` is the
+    /// wildcard and all other characters are matched literally.
+    ///
+    /// # Arguments
+    /// * `text` - The title or description query.
+    fn create_wildcard_pattern(text: &str) -> String {
+        Self::escape_like_pattern(text).replace('*', "%")
+    }
+
+    /// Escapes the given text for its literal use in a LIKE pattern with a backslash as escape
+    /// character.
+    ///
+    /// # Arguments
+    /// * `text` - The text to escape.
+    fn escape_like_pattern(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            if matches!(c, '\\' | '%' | '_') {
+                escaped.push('\\');
             }
+            escaped.push(c);
+        }
+
+        escaped
+    }
+
+    /// Creates the ORDER and LIMIT string based on the provided query.
+    ///
+    /// # Arguments
+    /// * `query` - The query to create the ORDER and LIMIT string for.
+    fn create_order_and_limit_string(query: &MovieSearchQuery) -> String {
+        let mut order_and_limit = String::new();
+
+        // field
+        let field = match query.sorting_field {
+            SortingField::Title => "m.title",
+            SortingField::Date => "m.date_added",
         };
 
-        let movie = Movie {
-            title,
-            description,
-            tags,
+        // order
+        let order = match query.sorting_order {
+            SortingOrder::Ascending => "ASC",
+            SortingOrder::Descending => "DESC",
         };
 
-        Ok(MovieDetailed {
-            movie,
-            date: date_added,
-            movie_file_info,
-            screenshot_file_info,
-        })
+        // the featured movies come first, if requested
+        if query.featured_first {
+            order_and_limit.push_str(&format!(
+                " ORDER BY m.id IN (SELECT id FROM featured_movies) DESC, {} {} ",
+                field, order
+            ));
+        } else {
+            order_and_limit.push_str(&format!(" ORDER BY {} {} ", field, order));
+        }
+
+        // limit
+        if let Some(limit) = query.num_results {
+            order_and_limit.push_str(&format!(" LIMIT {} ", limit));
+        } else {
+            order_and_limit.push_str(" LIMIT -1 ");
+        }
+
+        // offset
+        if let Some(offset) = query.start_index {
+            order_and_limit.push_str(&format!(" OFFSET {} ", offset));
+        } else {
+            order_and_limit.push_str(" OFFSET 0 ");
+        }
+
+        order_and_limit
     }
+}
 
-    async fn remove_movie(&mut self, id: &MovieId) -> Result<(), Error> {
-        let connection = self.connection.lock().await;
+#[async_trait]
+impl MoviesIndex for SqliteMoviesIndex {
+    fn new(options: &Options) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        if let Err(err) = create_dir_all(&options.root_dir) {
+            error!("Failed to create the root directory: {}", err);
+            return Err(err.into());
+        }
 
-        // delete movie details, stop if there was no movie with the given id
-        if connection.execute("DELETE FROM movies WHERE id=:id", &[(":id", &id)])? == 0 {
-            error!("No movie with id {} found", id);
-            return Err(Error::NotFound(format!("No movie with id {} found", id)));
+        let mut sqlite_path = options.root_dir.clone();
+        sqlite_path.push("movies.db");
+
+        debug!("SQLite database path: {}", sqlite_path.display());
+
+        if sqlite_path.exists() {
+            info!("Found existing movies.db");
         }
 
-        // delete tags
-        connection.execute("DELETE FROM tags WHERE id=:id", &[(":id", &id)])?;
+        match Self::create_pool(&sqlite_path, options.db_pool_size) {
+            Err(err) => {
+                error!("Failed to open the SQLite database: {}", err);
+                Err(Error::IO(format!("Failed to open SQLite DB{}", err)))
+            }
+            Ok(pool) => {
+                if let Err(err) = Self::create_tables(&*pool.get()?) {
+                    error!("Failed to create the tables: {}", err);
+                    return Err(Error::Internal(format!(
+                        "Failed to create the tables: {}",
+                        err
+                    )));
+                }
+
+                Ok(Self {
+                    pool,
+                    write_lock: Arc::new(Mutex::new(())),
+                    preserve_tag_case: options.preserve_tag_case,
+                    description_offload_threshold: options.description_offload_threshold,
+                    display_timezone: options.get_display_timezone(),
+                    query_timings: options.query_timing.then(|| {
+                        Arc::new(QueryTimings::new(Duration::from_millis(
+                            options.slow_query_threshold,
+                        )))
+                    }),
+                })
+            }
+        }
+    }
+
+    async fn add_movie(&mut self, movie: Movie) -> Result<MovieId, Error> {
+        self.add_movie_with_id(generate_movie_id(), movie).await
+    }
 
-        // delete file info
-        connection.execute("DELETE FROM file_infos WHERE id=:id", &[(":id", &id)])?;
+    async fn add_movie_with_id(&mut self, id: MovieId, movie: Movie) -> Result<MovieId, Error> {
+        let date = chrono::Utc::now().to_rfc3339();
 
-        // delete screenshot info
-        connection.execute("DELETE FROM screenshot_infos WHERE id=:id", &[(":id", &id)])?;
+        // the description, the movie and its tags are inserted within a single transaction, s.t. a
+        // failure doesn't leave an orphaned description behind
+        self.run_blocking_write(move |index, connection| {
+            let transaction = connection.transaction()?;
+            index.insert_movie(&transaction, &id, movie, &date)?;
+            transaction.commit()?;
 
-        Ok(())
+            Ok(id)
+        })
+        .await
     }
 
-    async fn update_movie_file_info(
-        &mut self,
-        id: &MovieId,
-        movie_file_info: MovieFileInfo,
-    ) -> Result<(), Error> {
-        let connection = self.connection.lock().await;
+    async fn add_movies(&mut self, movies: Vec<Movie>) -> Result<Vec<MovieId>, Error> {
+        info!("Adding {} movies", movies.len());
 
-        connection.execute(
-            "INSERT OR REPLACE INTO file_infos (id, mime_type, extension) VALUES (?1, ?2, ?3)",
-            (&id, &movie_file_info.mime_type, &movie_file_info.extension),
-        )?;
+        let date = chrono::Utc::now().to_rfc3339();
 
-        Ok(())
+        // all movies are inserted within a single transaction, which is rolled back on failure
+        self.run_blocking_write(move |index, connection| {
+            let transaction = connection.transaction()?;
+
+            let mut ids = Vec::with_capacity(movies.len());
+            for movie in movies {
+                let id = generate_movie_id();
+                index.insert_movie(&transaction, &id, movie, &date)?;
+                ids.push(id);
+            }
+
+            transaction.commit()?;
+
+            Ok(ids)
+        })
+        .await
     }
 
-    async fn update_screenshot_info(
-        &mut self,
-        id: &MovieId,
-        screenshot_info: ScreenshotInfo,
-    ) -> Result<(), Error> {
-        let connection = self.connection.lock().await;
+    async fn get_movie(&self, id: &MovieId) -> Result<MovieDetailed, Error> {
+        info!("Getting movie with id {}", id);
+        let _timer = self.time_query("get_movie");
+
+        let id = id.clone();
+        self.run_blocking(move |_, connection| {
+            let id = &id;
+
+            // get the movie details
+            let mut stmt = connection.prepare(
+                "SELECT m.title, COALESCE(d.text, m.description), m.date_added FROM movies m
+                LEFT JOIN descriptions d ON m.id = d.id WHERE m.id=:id",
+            )?;
+            let mut rows = stmt.query_map(&[(":id", &id)], |row| {
+                let title: String = row.get(0)?;
+                let description: String = row.get(1)?;
+                let date: String = row.get(2)?;
+
+                Ok((title, description, date))
+            })?;
+
+            let row = match rows.next() {
+                None => {
+                    error!("No movie with id {} found", id);
+                    return Err(Error::NotFound(format!("No movie with id {} found", id)));
+                }
+                Some(row) => row?,
+            };
+
+            let title = row.0;
+            let description = row.1;
+            let date_added: DateTime<Utc> = match row.2.parse() {
+                Err(err) => {
+                    error!("Failed to parse date: {}", err);
+                    return Err(Error::Internal(format!("Failed to parse date: {}", err)));
+                }
+                Ok(date) => date,
+            };
 
-        connection.execute(
-            "INSERT OR REPLACE INTO screenshot_infos (id, mime_type, extension) VALUES (?1, ?2, ?3)",
-            (&id, &screenshot_info.mime_type, &screenshot_info.extension),
-        )?;
+            // get the tags
+            let mut stmt = connection
+                .prepare("SELECT COALESCE(display_tag, tag) FROM tags WHERE id=:id ORDER BY tag")?;
+            let rows = stmt.query_map(&[(":id", &id)], |row| {
+                let tag: String = row.get(0)?;
 
-        Ok(())
+                Ok(tag)
+            })?;
+
+            let mut tags: Vec<String> = Vec::new();
+            for row in rows {
+                tags.push(row?);
+            }
+
+            // get movie file info, if available
+            let mut stmt = connection.prepare(
+                "SELECT mime_type, extension, original_filename FROM file_infos WHERE id=:id",
+            )?;
+            let mut rows = stmt.query_map(&[(":id", &id)], |row| {
+                let mime_type: String = row.get(0)?;
+                let extension: String = row.get(1)?;
+                let original_filename: Option<String> = row.get(2)?;
+
+                Ok((mime_type, extension, original_filename))
+            })?;
+
+            let movie_file_info = match rows.next() {
+                None => None,
+                Some(row) => {
+                    let (mime_type, extension, original_filename) = row?;
+
+                    Some(MovieFileInfo {
+                        mime_type,
+                        extension,
+                        original_filename,
+                    })
+                }
+            };
+
+            // get movie screenshot info, if available
+            let mut stmt = connection
+                .prepare("SELECT mime_type, extension, count FROM screenshot_infos WHERE id=:id")?;
+            let mut rows = stmt.query_map(&[(":id", &id)], |row| {
+                let mime_type: String = row.get(0)?;
+                let extension: String = row.get(1)?;
+                let count: Option<u32> = row.get(2)?;
+
+                Ok((mime_type, extension, count))
+            })?;
+
+            let screenshot_file_info = match rows.next() {
+                None => None,
+                Some(row) => {
+                    let (mime_type, extension, count) = row?;
+
+                    // screenshots stored before multiple screenshots were taken have no count
+                    Some(ScreenshotInfo {
+                        mime_type,
+                        extension,
+                        count: count.unwrap_or(1),
+                    })
+                }
+            };
+
+            // get preview state, if available
+            let mut stmt =
+                connection.prepare("SELECT state, reason FROM preview_states WHERE id=:id")?;
+            let mut rows = stmt.query_map(&[(":id", &id)], |row| {
+                let state: String = row.get(0)?;
+                let reason: Option<String> = row.get(1)?;
+
+                Ok((state, reason))
+            })?;
+
+            let preview_state = match rows.next() {
+                None => None,
+                Some(row) => {
+                    let (state, reason) = row?;
+                    Some(Self::parse_preview_state(&state, reason)?)
+                }
+            };
+
+            // get video metadata, if available
+            let mut stmt = connection.prepare(
+                "SELECT width, height, codec, bitrate, frame_rate, duration FROM video_metadata WHERE id=:id",
+            )?;
+            let mut rows = stmt.query_map(&[(":id", &id)], |row| {
+                Ok(VideoMetadata {
+                    width: row.get(0)?,
+                    height: row.get(1)?,
+                    codec: row.get(2)?,
+                    bitrate: row.get(3)?,
+                    frame_rate: row.get(4)?,
+                    duration: row.get(5)?,
+                })
+            })?;
+            let video_metadata = match rows.next() {
+                None => None,
+                Some(row) => Some(row?),
+            };
+
+            // get animated preview info, if available
+            let mut stmt = connection
+                .prepare("SELECT mime_type, extension FROM animated_preview_infos WHERE id=:id")?;
+            let mut rows = stmt.query_map(&[(":id", &id)], |row| {
+                Ok(AnimatedPreviewInfo {
+                    mime_type: row.get(0)?,
+                    extension: row.get(1)?,
+                })
+            })?;
+            let animated_preview_info = match rows.next() {
+                None => None,
+                Some(row) => Some(row?),
+            };
+
+            // get the languages of the subtitles
+            let mut stmt = connection
+                .prepare("SELECT language FROM subtitle_infos WHERE id=:id ORDER BY language")?;
+            let subtitle_languages = stmt
+                .query_map(&[(":id", &id)], |row| row.get(0))?
+                .collect::<Result<Vec<String>, _>>()?;
+
+            let featured: bool = connection.query_row(
+                "SELECT EXISTS (SELECT 1 FROM featured_movies WHERE id = ?1)",
+                [id],
+                |row| row.get(0),
+            )?;
+
+            let movie = Movie {
+                title,
+                description,
+                tags,
+            };
+
+            Ok(MovieDetailed {
+                movie,
+                date: date_added,
+                movie_file_info,
+                screenshot_file_info,
+                preview_state,
+                featured,
+                processing: false,
+                active_jobs: Vec::new(),
+                video_metadata,
+                animated_preview_info,
+                subtitle_languages,
+            })
+        })
+        .await
     }
 
-    async fn search_movies(&self, query: MovieSearchQuery) -> Result<Vec<MovieId>, Error> {
-        self.search_movies_impl(query).await
+    async fn movie_exists(&self, id: &MovieId) -> Result<bool, Error> {
+        let _timer = self.time_query("movie_exists");
+
+        let id = id.clone();
+        self.run_blocking(move |_, connection| {
+            let id = &id;
+
+            let mut stmt = connection.prepare("SELECT 1 FROM movies WHERE id=?1 LIMIT 1")?;
+
+            Ok(stmt.exists([id])?)
+        })
+        .await
     }
 
-    async fn get_tag_list_with_count(&self) -> Result<Vec<(String, usize)>, Error> {
-        let connection = self.connection.lock().await;
+    async fn update_movie(&mut self, id: &MovieId, movie: Movie) -> Result<(), Error> {
+        let id = id.clone();
 
-        let mut stmt = connection.prepare(
-            "SELECT tag, COUNT(*) FROM tags GROUP BY tag ORDER BY COUNT(*) DESC, tag ASC",
-        )?;
+        // the movie is updated within a single transaction, which is rolled back on failure
+        self.run_blocking_write(move |index, connection| {
+            let id = &id;
 
-        let rows = stmt.query_map([], |row| {
-            let tag: String = row.get(0)?;
-            let count: usize = row.get(1)?;
+            let transaction = connection.transaction()?;
+            index.update_movie_with(&transaction, id, movie)?;
+            transaction.commit()?;
 
-            Ok((tag, count))
-        })?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn apply_batch(&mut self, batch: IndexBatch) -> Result<(), Error> {
+        info!("Applying batch of {} edits", batch.edits().len());
+
+        // the transaction is rolled back when dropped without being committed
+        self.run_blocking_write(move |index, connection| {
+            let transaction = connection.transaction()?;
+            for edit in batch.into_edits() {
+                match edit {
+                    MovieEdit::Update { id, movie } => {
+                        index.update_movie_with(&transaction, &id, movie)?
+                    }
+                    MovieEdit::AddTags { id, tags } => {
+                        index.add_tags_with(&transaction, &id, &tags)?
+                    }
+                    MovieEdit::SetFeatured { id, featured } => {
+                        Self::set_featured_with(&transaction, &id, featured)?
+                    }
+                    MovieEdit::Touch { id } => Self::touch_movie_with(&transaction, &id)?,
+                }
+            }
+            transaction.commit()?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn rename_tag(&mut self, from: &str, to: &str) -> Result<usize, Error> {
+        // a single rename can't form a chain or a cycle
+        let renames = BTreeMap::from([(from.to_owned(), to.to_owned())]);
+        let counts = self.rename_tags(&renames).await?;
+
+        Ok(counts.into_values().sum())
+    }
+
+    async fn rename_tags(
+        &mut self,
+        renames: &BTreeMap<String, String>,
+    ) -> Result<BTreeMap<String, usize>, Error> {
+        info!("Renaming tags {:?}", renames);
+
+        let renames = resolve_tag_renames(renames)?;
+
+        // all renames are applied within a single transaction, which is rolled back on failure
+        self.run_blocking_write(move |index, connection| {
+            let transaction = connection.transaction()?;
+
+            // the movies are counted before any rename, s.t. the counts don't depend on the order
+            let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+            for old_tag in renames.keys() {
+                let count: usize = transaction.query_row(
+                    "SELECT COUNT(DISTINCT id) FROM tags WHERE tag = ?1",
+                    [old_tag],
+                    |row| row.get(0),
+                )?;
+                counts.insert(old_tag.clone(), count);
+            }
+
+            for (old_tag, new_tag) in renames.iter() {
+                let lower_tag = new_tag.to_lowercase();
+                let display_tag = if index.preserve_tag_case {
+                    new_tag.clone()
+                } else {
+                    lower_tag.clone()
+                };
+
+                // movies already having the new tag keep it only once
+                transaction.execute(
+                    "DELETE FROM tags WHERE tag = ?1 AND ?1 != ?2 AND id IN (SELECT id FROM tags WHERE tag = ?2)",
+                    (old_tag, &lower_tag),
+                )?;
+                transaction.execute(
+                    "UPDATE tags SET tag = ?1, display_tag = ?2 WHERE tag = ?3",
+                    (&lower_tag, &display_tag, old_tag),
+                )?;
+            }
+
+            transaction.commit()?;
+
+            Ok(counts)
+        })
+        .await
+    }
+
+    async fn remove_movie(&mut self, id: &MovieId) -> Result<(), Error> {
+        let id = id.clone();
+        self.run_blocking_write(move |_, connection| {
+            let id = &id;
+
+            // delete movie details, stop if there was no movie with the given id
+            if connection.execute("DELETE FROM movies WHERE id=:id", &[(":id", &id)])? == 0 {
+                error!("No movie with id {} found", id);
+                return Err(Error::NotFound(format!("No movie with id {} found", id)));
+            }
+
+            // delete the separately stored description
+            connection.execute("DELETE FROM descriptions WHERE id=:id", &[(":id", &id)])?;
+
+            // delete tags
+            connection.execute("DELETE FROM tags WHERE id=:id", &[(":id", &id)])?;
+
+            // delete file info
+            connection.execute("DELETE FROM file_infos WHERE id=:id", &[(":id", &id)])?;
+
+            // delete screenshot info
+            connection.execute("DELETE FROM screenshot_infos WHERE id=:id", &[(":id", &id)])?;
+
+            // delete preview state
+            connection.execute("DELETE FROM preview_states WHERE id=:id", &[(":id", &id)])?;
+
+            // delete screenshot hash
+            connection.execute(
+                "DELETE FROM screenshot_hashes WHERE id=:id",
+                &[(":id", &id)],
+            )?;
+
+            // delete video metadata
+            connection.execute("DELETE FROM video_metadata WHERE id=:id", &[(":id", &id)])?;
+
+            // delete animated preview info
+            connection.execute(
+                "DELETE FROM animated_preview_infos WHERE id=:id",
+                &[(":id", &id)],
+            )?;
+
+            // delete subtitle infos
+            connection.execute("DELETE FROM subtitle_infos WHERE id=:id", &[(":id", &id)])?;
+
+            // delete featured flag
+            connection.execute("DELETE FROM featured_movies WHERE id=:id", &[(":id", &id)])?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn add_tags(&mut self, id: &MovieId, tags: &[String]) -> Result<(), Error> {
+        let id = id.clone();
+        let tags = tags.to_vec();
+        self.run_blocking_write(move |index, connection| {
+            index.add_tags_with(connection, &id, &tags)
+        })
+        .await
+    }
+
+    async fn set_featured(&mut self, id: &MovieId, featured: bool) -> Result<(), Error> {
+        let id = id.clone();
+        self.run_blocking_write(move |_, connection| {
+            Self::set_featured_with(connection, &id, featured)
+        })
+        .await
+    }
+
+    async fn touch_movie(&mut self, id: &MovieId) -> Result<(), Error> {
+        let id = id.clone();
+        self.run_blocking_write(move |_, connection| Self::touch_movie_with(connection, &id))
+            .await
+    }
+
+    async fn update_movie_file_info(
+        &mut self,
+        id: &MovieId,
+        movie_file_info: MovieFileInfo,
+    ) -> Result<(), Error> {
+        let id = id.clone();
+        self.run_blocking_write(move |_, connection| {
+            let id = &id;
+
+            connection.execute(
+                "INSERT OR REPLACE INTO file_infos (id, mime_type, extension, original_filename)
+                    VALUES (?1, ?2, ?3, ?4)",
+                (
+                    &id,
+                    &movie_file_info.mime_type,
+                    &movie_file_info.extension,
+                    &movie_file_info.original_filename,
+                ),
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn remove_movie_file_info(&mut self, id: &MovieId) -> Result<(), Error> {
+        let id = id.clone();
+        self.run_blocking_write(move |_, connection| {
+            let id = &id;
+
+            connection.execute("DELETE FROM file_infos WHERE id=:id", &[(":id", &id)])?;
+
+            // the video metadata belongs to the removed movie file
+            connection.execute("DELETE FROM video_metadata WHERE id=:id", &[(":id", &id)])?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn update_video_metadata(
+        &mut self,
+        id: &MovieId,
+        video_metadata: VideoMetadata,
+    ) -> Result<(), Error> {
+        let id = id.clone();
+        self.run_blocking_write(move |_, connection| {
+            let id = &id;
+
+            connection.execute(
+                "INSERT OR REPLACE INTO video_metadata (id, width, height, codec, bitrate, frame_rate, duration)
+                    SELECT id, ?2, ?3, ?4, ?5, ?6, ?7 FROM movies WHERE id = ?1",
+                (
+                    &id,
+                    video_metadata.width,
+                    video_metadata.height,
+                    &video_metadata.codec,
+                    video_metadata.bitrate,
+                    video_metadata.frame_rate,
+                    video_metadata.duration,
+                ),
+            )?;
+
+            if connection.changes() == 0 {
+                error!("No movie with id {} found", id);
+                return Err(Error::NotFound(format!("No movie with id {} found", id)));
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn update_animated_preview_info(
+        &mut self,
+        id: &MovieId,
+        animated_preview_info: AnimatedPreviewInfo,
+    ) -> Result<(), Error> {
+        let id = id.clone();
+        self.run_blocking_write(move |_, connection| {
+            let id = &id;
+
+            connection.execute(
+                "INSERT OR REPLACE INTO animated_preview_infos (id, mime_type, extension)
+                    SELECT id, ?2, ?3 FROM movies WHERE id = ?1",
+                (
+                    &id,
+                    &animated_preview_info.mime_type,
+                    &animated_preview_info.extension,
+                ),
+            )?;
+
+            if connection.changes() == 0 {
+                error!("No movie with id {} found", id);
+                return Err(Error::NotFound(format!("No movie with id {} found", id)));
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn add_subtitle_info(
+        &mut self,
+        id: &MovieId,
+        subtitle_info: SubtitleInfo,
+    ) -> Result<(), Error> {
+        let id = id.clone();
+        self.run_blocking_write(move |_, connection| {
+            let id = &id;
+
+            connection.execute(
+                "INSERT OR REPLACE INTO subtitle_infos (id, language, mime_type, extension)
+                    SELECT id, ?2, ?3, ?4 FROM movies WHERE id = ?1",
+                (
+                    &id,
+                    &subtitle_info.language,
+                    &subtitle_info.mime_type,
+                    &subtitle_info.extension,
+                ),
+            )?;
+
+            if connection.changes() == 0 {
+                error!("No movie with id {} found", id);
+                return Err(Error::NotFound(format!("No movie with id {} found", id)));
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_subtitles(&self, id: &MovieId) -> Result<Vec<SubtitleInfo>, Error> {
+        let id = id.clone();
+        self.run_blocking(move |_, connection| {
+            let id = &id;
+
+            let mut stmt = connection.prepare("SELECT 1 FROM movies WHERE id=?1 LIMIT 1")?;
+            if !stmt.exists([id])? {
+                error!("No movie with id {} found", id);
+                return Err(Error::NotFound(format!("No movie with id {} found", id)));
+            }
+
+            let mut stmt = connection.prepare(
+                "SELECT language, mime_type, extension FROM subtitle_infos WHERE id=:id ORDER BY language",
+            )?;
+            let subtitles = stmt
+                .query_map(&[(":id", &id)], |row| {
+                    Ok(SubtitleInfo {
+                        language: row.get(0)?,
+                        mime_type: row.get(1)?,
+                        extension: row.get(2)?,
+                    })
+                })?
+                .collect::<Result<Vec<SubtitleInfo>, _>>()?;
+
+            Ok(subtitles)
+        })
+        .await
+    }
+
+    async fn update_screenshot_info(
+        &mut self,
+        id: &MovieId,
+        screenshot_info: ScreenshotInfo,
+    ) -> Result<(), Error> {
+        let id = id.clone();
+        self.run_blocking_write(move |_, connection| {
+            let id = &id;
+
+            connection.execute(
+                "INSERT OR REPLACE INTO screenshot_infos (id, mime_type, extension, count) VALUES (?1, ?2, ?3, ?4)",
+                (
+                    &id,
+                    &screenshot_info.mime_type,
+                    &screenshot_info.extension,
+                    screenshot_info.count,
+                ),
+            )?;
+
+            // the hash belongs to the previous screenshot
+            connection.execute(
+                "DELETE FROM screenshot_hashes WHERE id=:id",
+                &[(":id", &id)],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn update_preview_state(
+        &mut self,
+        id: &MovieId,
+        preview_state: PreviewState,
+    ) -> Result<(), Error> {
+        let id = id.clone();
+        self.run_blocking_write(move |_, connection| {
+            let id = &id;
+
+            let (state, reason) = match &preview_state {
+                PreviewState::Pending => ("pending", None),
+                PreviewState::Ready => ("ready", None),
+                PreviewState::Failed { reason } => ("failed", Some(reason)),
+                PreviewState::Unsupported => ("unsupported", None),
+            };
+
+            connection.execute(
+                "INSERT OR REPLACE INTO preview_states (id, state, reason) VALUES (?1, ?2, ?3)",
+                (&id, state, reason),
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn search_movies(&self, query: MovieSearchQuery) -> Result<Vec<MovieId>, Error> {
+        query.check_date_range()?;
+        let _timer = self.time_query("search_movies");
+        self.search_movies_impl(query).await
+    }
+
+    async fn search_movies_count(&self, query: MovieSearchQuery) -> Result<usize, Error> {
+        query.check_date_range()?;
+        let _timer = self.time_query("search_movies_count");
+        let query = MovieSearchQuery {
+            start_index: None,
+            num_results: None,
+            ..query
+        };
+
+        let (ids_query_string, params) = Self::create_search_movies_query(&query);
+        let query_string = format!("SELECT COUNT(*) FROM ({})", ids_query_string);
+
+        self.run_blocking(move |_, connection| {
+            let count: usize = connection.query_row(
+                &query_string,
+                rusqlite::params_from_iter(params.iter()),
+                |row| row.get(0),
+            )?;
+
+            Ok(count)
+        })
+        .await
+    }
+
+    fn get_query_stats(&self) -> Option<BTreeMap<String, QueryStats>> {
+        self.query_timings
+            .as_ref()
+            .map(|query_timings| query_timings.get_stats())
+    }
+
+    async fn get_letter_offset(
+        &self,
+        query: MovieSearchQuery,
+        letter: char,
+    ) -> Result<usize, Error> {
+        query.check_date_range()?;
+        let query = MovieSearchQuery {
+            sorting_field: SortingField::Title,
+            start_index: None,
+            num_results: None,
+            ..query
+        };
+
+        let (ids_query_string, mut params) = Self::create_search_movies_query(&query);
+
+        // in descending order, the titles starting with the letter are preceded by the greater
+        // titles not starting with the letter
+        let letter = letter.to_string();
+        let title_condition = match query.sorting_order {
+            SortingOrder::Ascending => {
+                params.push(letter);
+                "title < ?"
+            }
+            SortingOrder::Descending => {
+                params.push(letter.clone());
+                params.push(letter);
+                "title > ? AND substr(title, 1, 1) != ?"
+            }
+        };
+
+        // the featured movies precede all others, if they come first
+        let featured_condition = if query.featured_first {
+            "id IN (SELECT id FROM featured_movies) OR "
+        } else {
+            ""
+        };
+
+        let query_string = format!(
+            "SELECT COUNT(*) FROM movies WHERE id IN ({}) AND ({}{})",
+            ids_query_string, featured_condition, title_condition
+        );
+
+        self.run_blocking(move |_, connection| {
+            let offset: usize = connection.query_row(
+                &query_string,
+                rusqlite::params_from_iter(params.iter()),
+                |row| row.get(0),
+            )?;
+
+            Ok(offset)
+        })
+        .await
+    }
+
+    async fn get_tag_list_with_count(&self) -> Result<Vec<(String, usize)>, Error> {
+        let _timer = self.time_query("get_tag_list");
+
+        // a negative limit means no limit in SQLite
+        self.query_tag_counts("", -1).await
+    }
+
+    async fn get_top_tags(&self, limit: usize) -> Result<Vec<(String, usize)>, Error> {
+        let _timer = self.time_query("get_top_tags");
+        self.query_tag_counts("", i64::try_from(limit).unwrap_or(i64::MAX))
+            .await
+    }
+
+    async fn search_tags(&self, prefix: &str, limit: usize) -> Result<Vec<(String, usize)>, Error> {
+        let _timer = self.time_query("search_tags");
+        self.query_tag_counts(
+            &prefix.to_lowercase(),
+            i64::try_from(limit).unwrap_or(i64::MAX),
+        )
+        .await
+    }
+
+    async fn get_year_histogram(&self) -> Result<Vec<(u16, usize)>, Error> {
+        self.run_blocking(move |index, connection| {
+            // the dates are stored in UTC and shifted into the display timezone
+            let modifier = format!("{:+} seconds", index.display_timezone.local_minus_utc());
+            let mut stmt = connection.prepare(
+                "SELECT CAST(strftime('%Y', date_added, ?1) AS INTEGER) AS year, COUNT(*)
+                FROM movies GROUP BY year ORDER BY year ASC",
+            )?;
+
+            let rows = stmt.query_map([&modifier], |row| {
+                let year: u16 = row.get(0)?;
+                let count: usize = row.get(1)?;
+
+                Ok((year, count))
+            })?;
+
+            let mut histogram: Vec<(u16, usize)> = Vec::new();
+            for row in rows {
+                histogram.push(row?);
+            }
+
+            Ok(histogram)
+        })
+        .await
+    }
+
+    async fn get_search_facets(&self, query: MovieSearchQuery) -> Result<SearchFacets, Error> {
+        query.check_date_range()?;
+        let _timer = self.time_query("get_search_facets");
+        let query = MovieSearchQuery {
+            start_index: None,
+            num_results: None,
+            ..query
+        };
+
+        let (ids_query_string, params) = Self::create_search_movies_query(&query);
+
+        self.run_blocking(move |index, connection| {
+            let tags = Self::query_value_counts(
+                connection,
+                &format!(
+                    "SELECT MIN(COALESCE(display_tag, tag)), COUNT(*) FROM tags WHERE id IN ({})
+                    GROUP BY tag ORDER BY COUNT(*) DESC, tag ASC",
+                    ids_query_string
+                ),
+                &params,
+            )?;
+
+            // the dates are stored in UTC and shifted into the display timezone
+            let mut year_params = vec![format!(
+                "{:+} seconds",
+                index.display_timezone.local_minus_utc()
+            )];
+            year_params.extend(params.iter().cloned());
+            let years = Self::query_value_counts(
+                connection,
+                &format!(
+                    "SELECT CAST(strftime('%Y', date_added, ?) AS INTEGER) AS year, COUNT(*)
+                    FROM movies WHERE id IN ({}) GROUP BY year ORDER BY COUNT(*) DESC, year ASC",
+                    ids_query_string
+                ),
+                &year_params,
+            )?;
+
+            let formats = Self::query_value_counts(
+                connection,
+                &format!(
+                    "SELECT extension, COUNT(*) FROM file_infos WHERE id IN ({})
+                    GROUP BY extension ORDER BY COUNT(*) DESC, extension ASC",
+                    ids_query_string
+                ),
+                &params,
+            )?;
+
+            Ok(SearchFacets {
+                tags,
+                years,
+                formats,
+            })
+        })
+        .await
+    }
+
+    async fn suggest_tags(&self, tags: &[String]) -> Result<Vec<(String, usize)>, Error> {
+        if tags.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let tags: Vec<String> = tags.iter().map(|tag| tag.to_lowercase()).collect();
+        let placeholders = vec!["?"; tags.len()].join(", ");
+
+        self.run_blocking(move |_, connection| {
+            let mut stmt = connection.prepare(&format!(
+                "SELECT MIN(COALESCE(t2.display_tag, t2.tag)), COUNT(DISTINCT t2.id) AS count FROM tags t1
+                INNER JOIN tags t2 ON t1.id = t2.id
+                WHERE t1.tag IN ({placeholders}) AND t2.tag NOT IN ({placeholders})
+                GROUP BY t2.tag ORDER BY count DESC, t2.tag ASC"
+            ))?;
+
+            let rows = stmt.query_map(
+                rusqlite::params_from_iter(tags.iter().chain(tags.iter())),
+                |row| {
+                    let tag: String = row.get(0)?;
+                    let count: usize = row.get(1)?;
+
+                    Ok((tag, count))
+                },
+            )?;
+
+            let mut suggestions: Vec<(String, usize)> = Vec::new();
+            for row in rows {
+                suggestions.push(row?);
+            }
+
+            Ok(suggestions)
+        })
+        .await
+    }
+
+    async fn update_screenshot_hash(&mut self, id: &MovieId, hash: u64) -> Result<(), Error> {
+        let id = id.clone();
+        self.run_blocking_write(move |_, connection| {
+            let id = &id;
+
+            // SQLite only supports signed 64-bit integers, s.t. the bits are stored as i64
+            connection.execute(
+                "INSERT OR REPLACE INTO screenshot_hashes (id, hash)
+                    SELECT id, ?2 FROM movies WHERE id = ?1",
+                (&id, hash as i64),
+            )?;
+
+            if connection.changes() == 0 {
+                error!("No movie with id {} found", id);
+                return Err(Error::NotFound(format!("No movie with id {} found", id)));
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_screenshot_hashes(&self) -> Result<Vec<(MovieId, u64)>, Error> {
+        self.run_blocking(move |_, connection| {
+            let mut stmt =
+                connection.prepare("SELECT id, hash FROM screenshot_hashes ORDER BY id")?;
+            let rows = stmt.query_map([], |row| {
+                let id: MovieId = row.get(0)?;
+                let hash: i64 = row.get(1)?;
+
+                Ok((id, hash as u64))
+            })?;
+
+            let mut hashes: Vec<(MovieId, u64)> = Vec::new();
+            for row in rows {
+                hashes.push(row?);
+            }
+
+            Ok(hashes)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tempdir::TempDir;
+
+    use crate::Movie;
+
+    use super::*;
+
+    fn create_test_movies() -> Vec<Movie> {
+        vec![Movie { title: "Doctor Who".to_owned(), description: "The Doctor, a Time Lord from the race whose home planet is Gallifrey, travels through time and space in their ship the TARDIS (an acronym for Time and Relative Dimension In Space) with numerous companions.".to_owned(), tags: vec!["Sci-Fi".to_owned(), "time travel".to_owned(), "british".to_owned(), "tv show".to_owned()] },
+        Movie { title: "The X-Files".to_owned(), description: "Two F.B.I. Agents, Fox Mulder the believer and Dana Scully the skeptic, investigate the strange and unexplained, while hidden forces work to impede their efforts.".to_owned(), tags: vec!["crime".to_owned(), "drama".to_owned(), "mystery".to_owned(), "usa".to_owned(), "tv show".to_owned()] },
+        Movie { title: "E.T. the Extra-Terrestrial".to_owned(), description: "A troubled child summons the courage to help a friendly alien escape from Earth and return to his home planet.".to_owned(), tags: vec!["adventure".to_owned(), "family".to_owned(), "Sci-Fi".to_owned(), "usa".to_owned(), "movie".to_owned()] },
+        Movie { title: "Das Boot".to_owned(), description: "A German U-boat stalks the frigid waters of the North Atlantic as its young crew experience the sheer terror and claustrophobic life of a submariner in World War II.".to_owned(), tags: vec!["drama".to_owned(), "war".to_owned(), "germany".to_owned(), "movie".to_owned()] }]
+    }
+
+    fn create_tag_suggestion_movies() -> Vec<Movie> {
+        let movie = |title: &str, tags: &[&str]| Movie {
+            title: title.to_owned(),
+            description: String::new(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        };
+
+        vec![
+            movie("Alien", &["Sci-Fi", "space", "aliens"]),
+            movie("Interstellar", &["Sci-Fi", "space"]),
+            movie("Galaxy Quest", &["Sci-Fi", "space", "aliens", "comedy"]),
+            movie("Cosmos", &["space", "documentary"]),
+            movie("Das Boot", &["drama", "war"]),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_add_movie() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let movies = create_test_movies();
+
+        for movie in movies {
+            let ret = index.add_movie(movie.clone()).await;
+            assert!(ret.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_movie_with_id() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+        let movies = create_test_movies();
+
+        // supply a fresh id
+        let id = index
+            .add_movie_with_id("doctor-who".to_owned(), movies[0].clone())
+            .await
+            .unwrap();
+        assert_eq!(id, "doctor-who");
+        assert_eq!(
+            index.get_movie(&id).await.unwrap().movie.title,
+            "Doctor Who"
+        );
+
+        // supply a colliding id
+        assert!(matches!(
+            index
+                .add_movie_with_id("doctor-who".to_owned(), movies[1].clone())
+                .await,
+            Err(Error::AlreadyExists(_))
+        ));
+        assert_eq!(
+            index.get_movie(&id).await.unwrap().movie.title,
+            "Doctor Who"
+        );
+
+        // supply an invalid id
+        assert!(matches!(
+            index
+                .add_movie_with_id("../x-files".to_owned(), movies[1].clone())
+                .await,
+            Err(Error::InvalidArgument(_))
+        ));
+
+        // omit the id
+        let id = index.add_movie(movies[1].clone()).await.unwrap();
+        assert_ne!(id, "doctor-who");
+        assert_eq!(
+            index.get_movie(&id).await.unwrap().movie.title,
+            "The X-Files"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_movies() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+        let movies = create_test_movies();
+
+        let ids = index.add_movies(movies.clone()).await.unwrap();
+        assert_eq!(ids.len(), movies.len());
+        for (id, movie) in ids.iter().zip(movies.iter()) {
+            let stored = index.get_movie(id).await.unwrap().movie;
+            assert_eq!(stored.title, movie.title);
+            assert_eq!(stored.description, movie.description);
+        }
+
+        // a single invalid movie rolls back the whole batch
+        let mut invalid_movies = movies.clone();
+        invalid_movies[1].title = String::new();
+        assert!(matches!(
+            index.add_movies(invalid_movies).await,
+            Err(Error::InvalidArgument(_))
+        ));
+
+        let query = MovieSearchQuery::default();
+        assert_eq!(index.search_movies(query).await.unwrap().len(), ids.len());
+    }
+
+    #[tokio::test]
+    async fn test_get_movie() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let movies = create_test_movies();
+
+        let mut movie_ids: Vec<MovieId> = Vec::with_capacity(movies.len());
+        for movie in movies.iter() {
+            let id = index.add_movie(movie.clone()).await.unwrap();
+            movie_ids.push(id);
+        }
+
+        for (id, movie) in movie_ids.iter().zip(movies.iter()) {
+            let db_movie = index.get_movie(id).await;
+            assert!(db_movie.is_ok());
+            let db_movie = db_movie.unwrap();
+
+            assert_eq!(movie.title, db_movie.movie.title);
+            assert_eq!(movie.description, db_movie.movie.description);
+
+            let mut ref_tags = movie.tags.clone();
+            ref_tags
+                .iter_mut()
+                .for_each(|tag| *tag = tag.to_lowercase());
+            ref_tags.sort();
+            assert_eq!(ref_tags, db_movie.movie.tags);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_movie_exists() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let mut movie_ids: Vec<MovieId> = Vec::new();
+        for movie in create_test_movies().into_iter() {
+            movie_ids.push(index.add_movie(movie).await.unwrap());
+        }
+
+        for id in movie_ids.iter() {
+            assert!(index.movie_exists(id).await.unwrap());
+        }
+        assert!(!index.movie_exists(&generate_movie_id()).await.unwrap());
+
+        index.remove_movie(&movie_ids[0]).await.unwrap();
+        assert!(!index.movie_exists(&movie_ids[0]).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_video_metadata() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let video_metadata = VideoMetadata {
+            width: 1920,
+            height: 1080,
+            codec: "h264".to_owned(),
+            bitrate: Some(4_500_000),
+            frame_rate: Some(25.0),
+            duration: Some(5940.5),
+        };
+
+        let id = index
+            .add_movie(create_test_movies()[0].clone())
+            .await
+            .unwrap();
+        assert_eq!(index.get_movie(&id).await.unwrap().video_metadata, None);
+
+        index
+            .update_video_metadata(&id, video_metadata.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            index.get_movie(&id).await.unwrap().video_metadata,
+            Some(video_metadata.clone())
+        );
+
+        // the metadata belongs to the movie file
+        index.remove_movie_file_info(&id).await.unwrap();
+        assert_eq!(index.get_movie(&id).await.unwrap().video_metadata, None);
+
+        let ret = index
+            .update_video_metadata(&generate_movie_id(), video_metadata)
+            .await;
+        assert!(matches!(ret, Err(Error::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_animated_preview_info() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let animated_preview_info = AnimatedPreviewInfo {
+            extension: "gif".to_owned(),
+            mime_type: "image/gif".to_owned(),
+        };
+
+        let id = index
+            .add_movie(create_test_movies()[0].clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            index.get_movie(&id).await.unwrap().animated_preview_info,
+            None
+        );
+
+        index
+            .update_animated_preview_info(&id, animated_preview_info.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            index.get_movie(&id).await.unwrap().animated_preview_info,
+            Some(animated_preview_info.clone())
+        );
+
+        let ret = index
+            .update_animated_preview_info(&generate_movie_id(), animated_preview_info)
+            .await;
+        assert!(matches!(ret, Err(Error::NotFound(_))));
+
+        // the info is removed together with the movie
+        index.remove_movie(&id).await.unwrap();
+        let count: usize = index
+            .pool
+            .get()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM animated_preview_infos", (), |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_apply_batch() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let movies = create_test_movies();
+        let id = index.add_movie(movies[0].clone()).await.unwrap();
+        let other_id = index.add_movie(movies[1].clone()).await.unwrap();
+        let before = index.get_movie(&id).await.unwrap();
+
+        // a batch with a failing edit leaves the movie entirely unchanged
+        let batch = IndexBatch::new()
+            .update_movie(
+                id.clone(),
+                Movie {
+                    title: "Doctor Who (2005)".to_owned(),
+                    description: "The revived series.".to_owned(),
+                    tags: vec!["british".to_owned()],
+                },
+            )
+            .add_tags(id.clone(), vec!["classic".to_owned()])
+            .set_featured(id.clone(), true)
+            .touch_movie(id.clone())
+            .set_featured(generate_movie_id(), true);
+        let ret = index.apply_batch(batch).await;
+        assert!(matches!(ret, Err(Error::NotFound(_))));
+
+        let after = index.get_movie(&id).await.unwrap();
+        assert_eq!(after.movie.title, before.movie.title);
+        assert_eq!(after.movie.description, before.movie.description);
+        assert_eq!(after.movie.tags, before.movie.tags);
+        assert_eq!(after.featured, before.featured);
+        assert_eq!(after.date, before.date);
+
+        // a successful batch applies all edits in their order
+        let batch = IndexBatch::new()
+            .update_movie(
+                id.clone(),
+                Movie {
+                    title: "Doctor Who (2005)".to_owned(),
+                    description: "The revived series.".to_owned(),
+                    tags: vec!["british".to_owned()],
+                },
+            )
+            .add_tags(id.clone(), vec!["classic".to_owned()])
+            .set_featured(other_id.clone(), true);
+        index.apply_batch(batch).await.unwrap();
+
+        let movie = index.get_movie(&id).await.unwrap();
+        assert_eq!(movie.movie.title, "Doctor Who (2005)");
+        assert_eq!(movie.movie.tags, ["british", "classic"]);
+        assert!(index.get_movie(&other_id).await.unwrap().featured);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_queries() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let id = index
+            .add_movie(create_test_movies()[0].clone())
+            .await
+            .unwrap();
+
+        // a long running write holds the write lock on another thread
+        let write_lock = index.write_lock.clone();
+        let (locked_sender, locked_receiver) = std::sync::mpsc::channel();
+        let slow_write = std::thread::spawn(move || {
+            let _write_guard = write_lock.lock().unwrap();
+            locked_sender.send(()).unwrap();
+            std::thread::sleep(Duration::from_millis(200));
+        });
+        locked_receiver.recv().unwrap();
+
+        // the waiting writes don't block the runtime, s.t. other tasks keep running meanwhile
+        let ticks = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let ticker = {
+            let ticks = ticks.clone();
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    ticks.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                }
+            })
+        };
+
+        let writes = futures::future::join_all((0..8).map(|i| {
+            let mut index = index.clone();
+            let id = id.clone();
+            async move { index.add_tags(&id, &[format!("tag{}", i)]).await }
+        }))
+        .await;
+        ticker.abort();
+        slow_write.join().unwrap();
+
+        for write in writes {
+            write.unwrap();
+        }
+        let tags = index.get_movie(&id).await.unwrap().movie.tags;
+        assert!((0..8).all(|i| tags.contains(&format!("tag{}", i))));
+        assert!(ticks.load(std::sync::atomic::Ordering::SeqCst) >= 5);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_parallel_searches() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.db_pool_size = 4;
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        for movie in create_test_movies() {
+            index.add_movie(movie).await.unwrap();
+        }
+        let num_movies = create_test_movies().len();
+
+        // an open read transaction on one connection neither blocks the other reads nor a write
+        let connection = index.pool.get().unwrap();
+        connection
+            .execute_batch("BEGIN; SELECT COUNT(*) FROM movies;")
+            .unwrap();
+
+        let searches = (0..16)
+            .map(|_| {
+                let index = index.clone();
+                tokio::spawn(async move { index.search_movies(MovieSearchQuery::default()).await })
+            })
+            .collect::<Vec<_>>();
+        for search in searches {
+            assert_eq!(search.await.unwrap().unwrap().len(), num_movies);
+        }
+
+        index
+            .add_movie(create_test_movies()[0].clone())
+            .await
+            .unwrap();
+
+        connection.execute_batch("COMMIT;").unwrap();
+        drop(connection);
+        assert_eq!(
+            index
+                .search_movies(MovieSearchQuery::default())
+                .await
+                .unwrap()
+                .len(),
+            num_movies + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subtitle_infos() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let subtitle = |language: &str, extension: &str| SubtitleInfo {
+            language: language.to_owned(),
+            mime_type: "text/vtt".to_owned(),
+            extension: extension.to_owned(),
+        };
+
+        let id = index
+            .add_movie(create_test_movies()[0].clone())
+            .await
+            .unwrap();
+        assert!(index.get_subtitles(&id).await.unwrap().is_empty());
+        assert!(index
+            .get_movie(&id)
+            .await
+            .unwrap()
+            .subtitle_languages
+            .is_empty());
+
+        index
+            .add_subtitle_info(&id, subtitle("en", "vtt"))
+            .await
+            .unwrap();
+        index
+            .add_subtitle_info(&id, subtitle("de", "vtt"))
+            .await
+            .unwrap();
+
+        // a subtitle of the same language replaces the existing one
+        index
+            .add_subtitle_info(&id, subtitle("en", "srt"))
+            .await
+            .unwrap();
+        assert_eq!(
+            index.get_subtitles(&id).await.unwrap(),
+            vec![subtitle("de", "vtt"), subtitle("en", "srt")]
+        );
+        assert_eq!(
+            index.get_movie(&id).await.unwrap().subtitle_languages,
+            vec!["de".to_owned(), "en".to_owned()]
+        );
+
+        let ret = index
+            .add_subtitle_info(&generate_movie_id(), subtitle("en", "vtt"))
+            .await;
+        assert!(matches!(ret, Err(Error::NotFound(_))));
+        let ret = index.get_subtitles(&generate_movie_id()).await;
+        assert!(matches!(ret, Err(Error::NotFound(_))));
+
+        // the subtitles are removed together with the movie
+        index.remove_movie(&id).await.unwrap();
+        let count: usize = index
+            .pool
+            .get()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM subtitle_infos", (), |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_remove_movie() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let movies = create_test_movies();
+
+        let mut movie_ids: Vec<MovieId> = Vec::with_capacity(movies.len());
+        for movie in movies.iter() {
+            let id = index.add_movie(movie.clone()).await.unwrap();
+            movie_ids.push(id);
+        }
+
+        movie_ids.sort();
+
+        index.remove_movie(&movie_ids[0]).await.unwrap();
+        assert!(index.remove_movie(&movie_ids[0]).await.is_err());
+
+        let movie_ids = &movie_ids[1..];
+        let mut listed_movie_ids = index.search_movies(Default::default()).await.unwrap();
+        listed_movie_ids.sort();
+
+        assert_eq!(movie_ids, listed_movie_ids);
+    }
+
+    #[tokio::test]
+    async fn test_touch_movie() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+        let movies = create_test_movies();
+
+        let mut movie_ids: Vec<MovieId> = Vec::with_capacity(movies.len());
+        for movie in movies.iter() {
+            let id = index.add_movie(movie.clone()).await.unwrap();
+            movie_ids.push(id);
+        }
+
+        // the most recently added movie comes first
+        let listed_movie_ids = index.search_movies(Default::default()).await.unwrap();
+        assert_eq!(&listed_movie_ids[0], movie_ids.last().unwrap());
+
+        // touching the first movie moves it to the front
+        index.touch_movie(&movie_ids[0]).await.unwrap();
+        let listed_movie_ids = index.search_movies(Default::default()).await.unwrap();
+        assert_eq!(listed_movie_ids[0], movie_ids[0]);
+        assert_eq!(listed_movie_ids.len(), movie_ids.len());
+
+        assert!(matches!(
+            index.touch_movie(&"unknown".to_owned()).await,
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_suggest_tags() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        for movie in create_tag_suggestion_movies() {
+            index.add_movie(movie).await.unwrap();
+        }
+
+        let top_tags = index.get_top_tags(3).await.unwrap();
+        assert_eq!(
+            top_tags,
+            [
+                ("space".to_owned(), 4),
+                ("sci-fi".to_owned(), 3),
+                ("aliens".to_owned(), 2)
+            ]
+        );
+        assert_eq!(index.get_top_tags(100).await.unwrap().len(), 7);
+        assert!(index.get_top_tags(0).await.unwrap().is_empty());
+
+        let suggestions = index.suggest_tags(&["Sci-Fi".to_owned()]).await.unwrap();
+        assert_eq!(
+            suggestions,
+            [
+                ("space".to_owned(), 3),
+                ("aliens".to_owned(), 2),
+                ("comedy".to_owned(), 1)
+            ]
+        );
+
+        let suggestions = index
+            .suggest_tags(&["sci-fi".to_owned(), "space".to_owned()])
+            .await
+            .unwrap();
+        assert_eq!(
+            suggestions,
+            [
+                ("aliens".to_owned(), 2),
+                ("comedy".to_owned(), 1),
+                ("documentary".to_owned(), 1)
+            ]
+        );
+
+        assert!(index.suggest_tags(&[]).await.unwrap().is_empty());
+        assert!(index
+            .suggest_tags(&["unknown".to_owned()])
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_tags() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        for movie in create_tag_suggestion_movies() {
+            index.add_movie(movie).await.unwrap();
+        }
+
+        assert_eq!(
+            index.search_tags("S", 10).await.unwrap(),
+            [("space".to_owned(), 4), ("sci-fi".to_owned(), 3)]
+        );
+        assert_eq!(
+            index.search_tags("d", 1).await.unwrap(),
+            [("documentary".to_owned(), 1)]
+        );
+        assert_eq!(index.search_tags("", 100).await.unwrap().len(), 7);
+        assert!(index.search_tags("sci_", 10).await.unwrap().is_empty());
+        assert!(index.search_tags("space", 0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_offload_large_description() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.description_offload_threshold = Some(64);
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let transcript = "Periskop ausfahren! Äußerste Kraft voraus. ".repeat(4096);
+        let id = index
+            .add_movie(Movie {
+                title: "Das Boot".to_owned(),
+                description: transcript.clone(),
+                tags: Vec::new(),
+            })
+            .await
+            .unwrap();
+        let short_id = index
+            .add_movie(Movie {
+                title: "Alien".to_owned(),
+                description: "In space no one can hear you scream.".to_owned(),
+                tags: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            index.get_movie(&id).await.unwrap().movie.description,
+            transcript
+        );
+        assert_eq!(
+            index.get_movie(&short_id).await.unwrap().movie.description,
+            "In space no one can hear you scream."
+        );
+
+        // the movies table only contains a preview of the description
+        {
+            let connection = index.pool.get().unwrap();
+            let preview: String = connection
+                .query_row(
+                    "SELECT description FROM movies WHERE id=:id",
+                    &[(":id", &id)],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert!(preview.len() <= 64);
+            assert!(transcript.starts_with(&preview));
+
+            let count: usize = connection
+                .query_row("SELECT COUNT(*) FROM descriptions", (), |row| row.get(0))
+                .unwrap();
+            assert_eq!(count, 1);
+        }
+
+        index.remove_movie(&id).await.unwrap();
+        let connection = index.pool.get().unwrap();
+        let count: usize = connection
+            .query_row("SELECT COUNT(*) FROM descriptions", (), |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_update_movie() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.description_offload_threshold = Some(64);
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let movies = create_test_movies();
+        let id = index.add_movie(movies[0].clone()).await.unwrap();
+        let other_id = index.add_movie(movies[1].clone()).await.unwrap();
+
+        // the large description is replaced by a short one
+        index
+            .update_movie(
+                &id,
+                Movie {
+                    title: "Doctor Who (2005)".to_owned(),
+                    description: "The revived series.".to_owned(),
+                    tags: vec!["TV Show".to_owned(), "british".to_owned()],
+                },
+            )
+            .await
+            .unwrap();
+
+        let movie = index.get_movie(&id).await.unwrap();
+        assert_eq!(movie.movie.title, "Doctor Who (2005)");
+        assert_eq!(movie.movie.description, "The revived series.");
+        assert_eq!(movie.movie.tags, ["british", "tv show"]);
+        assert_eq!(
+            index.get_movie(&other_id).await.unwrap().movie.title,
+            movies[1].title
+        );
+
+        // the search reflects the new title and tags
+        let mut query: MovieSearchQuery = Default::default();
+        query.title = Some("*(2005)".to_owned());
+        assert_eq!(
+            index.search_movies(query).await.unwrap(),
+            std::slice::from_ref(&id)
+        );
+        let mut query: MovieSearchQuery = Default::default();
+        query.tags = vec!["sci-fi".to_owned()];
+        assert!(index.search_movies(query).await.unwrap().is_empty());
+
+        {
+            let connection = index.pool.get().unwrap();
+            let count: usize = connection
+                .query_row("SELECT COUNT(*) FROM descriptions", (), |row| row.get(0))
+                .unwrap();
+            assert_eq!(count, 1);
+        }
+
+        // failed updates leave the movie unchanged
+        let mut movie = movies[1].clone();
+        assert!(matches!(
+            index
+                .update_movie(&"unknown".to_owned(), movie.clone())
+                .await,
+            Err(Error::NotFound(_))
+        ));
+        movie.title.clear();
+        assert!(matches!(
+            index.update_movie(&id, movie).await,
+            Err(Error::InvalidArgument(_))
+        ));
+        assert_eq!(
+            index.get_movie(&id).await.unwrap().movie.title,
+            "Doctor Who (2005)"
+        );
+
+        let connection = index.pool.get().unwrap();
+        let count: usize = connection
+            .query_row("SELECT COUNT(*) FROM descriptions", (), |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_year_histogram() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+        assert!(index.get_year_histogram().await.unwrap().is_empty());
+
+        for (title, date) in [
+            ("Das Boot", "2019-03-01T12:00:00+00:00"),
+            ("Alien", "2021-12-31T23:59:59+00:00"),
+            ("Heat", "2019-11-11T08:30:00+00:00"),
+            ("Apollo 13", "2023-01-01T00:00:00+00:00"),
+        ] {
+            let id = index
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: String::new(),
+                    tags: Vec::new(),
+                })
+                .await
+                .unwrap();
+
+            index
+                .pool
+                .get()
+                .unwrap()
+                .execute("UPDATE movies SET date_added=?1 WHERE id=?2", (date, &id))
+                .unwrap();
+        }
+
+        assert_eq!(
+            index.get_year_histogram().await.unwrap(),
+            [(2019, 2), (2021, 1), (2023, 1)]
+        );
+
+        // the years refer to the display timezone
+        index.display_timezone = FixedOffset::east_opt(3600).unwrap();
+        assert_eq!(
+            index.get_year_histogram().await.unwrap(),
+            [(2019, 2), (2022, 1), (2023, 1)]
+        );
+
+        index.display_timezone = FixedOffset::west_opt(3600).unwrap();
+        assert_eq!(
+            index.get_year_histogram().await.unwrap(),
+            [(2019, 2), (2021, 1), (2022, 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_search_facets() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        for (title, tags, date, ext) in [
+            (
+                "Alien",
+                vec!["sci-fi", "horror"],
+                "2019-03-01T12:00:00+00:00",
+                Some("mkv"),
+            ),
+            (
+                "Interstellar",
+                vec!["Sci-Fi", "space"],
+                "2021-12-31T23:59:59+00:00",
+                Some("mp4"),
+            ),
+            (
+                "Galaxy Quest",
+                vec!["sci-fi", "comedy"],
+                "2019-11-11T08:30:00+00:00",
+                Some("mp4"),
+            ),
+            ("Arrival", vec!["sci-fi"], "2023-01-01T00:00:00+00:00", None),
+            (
+                "Das Boot",
+                vec!["drama", "war"],
+                "2021-06-01T00:00:00+00:00",
+                Some("avi"),
+            ),
+        ] {
+            let id = index
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: String::new(),
+                    tags: tags.iter().map(|t| t.to_string()).collect(),
+                })
+                .await
+                .unwrap();
+
+            index
+                .pool
+                .get()
+                .unwrap()
+                .execute("UPDATE movies SET date_added=?1 WHERE id=?2", (date, &id))
+                .unwrap();
+
+            if let Some(ext) = ext {
+                index
+                    .update_movie_file_info(
+                        &id,
+                        MovieFileInfo {
+                            extension: ext.to_owned(),
+                            mime_type: format!("video/{}", ext),
+                            original_filename: None,
+                        },
+                    )
+                    .await
+                    .unwrap();
+            }
+        }
+
+        // the facets ignore the pagination of the query
+        let query = MovieSearchQuery {
+            tags: vec!["sci-fi".to_owned()],
+            start_index: Some(1),
+            num_results: Some(1),
+            ..Default::default()
+        };
+        let tag = |tag: &str, count: usize| (tag.to_owned(), count);
+        assert_eq!(
+            index.get_search_facets(query.clone()).await.unwrap(),
+            SearchFacets {
+                tags: vec![
+                    tag("sci-fi", 4),
+                    tag("comedy", 1),
+                    tag("horror", 1),
+                    tag("space", 1)
+                ],
+                years: vec![(2019, 2), (2021, 1), (2023, 1)],
+                formats: vec![tag("mp4", 2), tag("mkv", 1)],
+            }
+        );
+
+        // the years refer to the display timezone
+        index.display_timezone = FixedOffset::east_opt(3600).unwrap();
+        assert_eq!(
+            index.get_search_facets(query).await.unwrap().years,
+            [(2019, 2), (2022, 1), (2023, 1)]
+        );
+
+        let query = MovieSearchQuery {
+            title: Some("Nothing".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            index.get_search_facets(query).await.unwrap(),
+            SearchFacets::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_tags() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let id = index
+            .add_movie(Movie {
+                title: "Das Boot".to_owned(),
+                description: String::new(),
+                tags: vec!["war".to_owned(), "War".to_owned(), "war".to_owned()],
+            })
+            .await
+            .unwrap();
+        index
+            .add_tags(&id, &["WAR".to_owned(), "drama".to_owned()])
+            .await
+            .unwrap();
+
+        let movie = index.get_movie(&id).await.unwrap();
+        assert_eq!(movie.movie.tags, ["drama", "war"]);
+        assert_eq!(
+            index.get_tag_list_with_count().await.unwrap(),
+            [("drama".to_owned(), 1), ("war".to_owned(), 1)]
+        );
+
+        // duplicates of databases created before the tags were unique are removed on startup
+        {
+            let connection = index.pool.get().unwrap();
+            connection.execute("DROP INDEX tags_id_tag", ()).unwrap();
+            connection
+                .execute(
+                    "INSERT INTO tags (id, tag, display_tag) VALUES (?1, 'war', 'war')",
+                    [&id],
+                )
+                .unwrap();
+        }
+        drop(index);
+
+        let index = SqliteMoviesIndex::new(&options).unwrap();
+        let movie = index.get_movie(&id).await.unwrap();
+        assert_eq!(movie.movie.tags, ["drama", "war"]);
+        assert_eq!(
+            index.get_tag_list_with_count().await.unwrap(),
+            [("drama".to_owned(), 1), ("war".to_owned(), 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_preserve_tag_case() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.preserve_tag_case = true;
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let apollo = index
+            .add_movie(Movie {
+                title: "Apollo 13".to_owned(),
+                description: String::new(),
+                tags: vec!["space".to_owned(), "NASA".to_owned()],
+            })
+            .await
+            .unwrap();
+        index
+            .add_movie(Movie {
+                title: "The Right Stuff".to_owned(),
+                description: String::new(),
+                tags: vec!["nasa".to_owned(), "Drama".to_owned()],
+            })
+            .await
+            .unwrap();
+
+        // the displayed tags keep their casing
+        let movie = index.get_movie(&apollo).await.unwrap();
+        assert_eq!(movie.movie.tags, ["NASA", "space"]);
+
+        // searching is case-insensitive
+        let query = MovieSearchQuery {
+            tags: vec!["nasa".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(index.search_movies(query).await.unwrap().len(), 2);
+
+        let query = MovieSearchQuery {
+            tags: vec!["Space".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(index.search_movies(query).await.unwrap(), [apollo]);
+
+        let tags = index.get_tag_list_with_count().await.unwrap();
+        assert_eq!(
+            tags,
+            [
+                ("NASA".to_owned(), 2),
+                ("Drama".to_owned(), 1),
+                ("space".to_owned(), 1)
+            ]
+        );
+
+        let suggestions = index.suggest_tags(&["drama".to_owned()]).await.unwrap();
+        assert_eq!(suggestions, [("nasa".to_owned(), 1)]);
+    }
+
+    async fn movie_ids_to_titles(index: &SqliteMoviesIndex, movie_ids: &[MovieId]) -> Vec<String> {
+        let mut movie_titles: Vec<String> = Vec::with_capacity(movie_ids.len());
+
+        for movie_id in movie_ids {
+            let movie = index.get_movie(movie_id).await.unwrap();
+            movie_titles.push(movie.movie.title.clone());
+        }
+
+        movie_titles
+    }
+
+    #[tokio::test]
+    async fn test_query_movies() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let movies = create_test_movies();
+
+        for movie in movies.iter() {
+            let ret = index.add_movie(movie.clone()).await;
+            assert!(ret.is_ok());
+        }
+
+        // test query 1A: Search all movies (ascending order by title)
+        let mut query: MovieSearchQuery = Default::default();
+        query.sorting_field = SortingField::Title;
+        query.sorting_order = SortingOrder::Ascending;
+        let movie_title: Vec<String> =
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await;
+
+        assert_eq!(
+            movie_title,
+            vec![
+                "Das Boot",
+                "Doctor Who",
+                "E.T. the Extra-Terrestrial",
+                "The X-Files",
+            ]
+        );
+
+        // test query 1B: Search all movies (descending order by title)
+        let mut query: MovieSearchQuery = Default::default();
+        query.sorting_field = SortingField::Title;
+        query.sorting_order = SortingOrder::Descending;
+        let movie_title: Vec<String> =
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await;
+
+        assert_eq!(
+            movie_title,
+            vec![
+                "The X-Files",
+                "E.T. the Extra-Terrestrial",
+                "Doctor Who",
+                "Das Boot",
+            ]
+        );
+
+        // // test query 2: Search only science fiction movies
+        let mut query: MovieSearchQuery = Default::default();
+        query.tags = vec!["Sci-Fi".to_owned()];
+        query.sorting_field = SortingField::Title;
+        query.sorting_order = SortingOrder::Ascending;
+        let search_result = index.search_movies(query).await.unwrap();
+        let title_list: Vec<String> = movie_ids_to_titles(&index, &search_result).await;
+        assert_eq!(
+            title_list,
+            vec![
+                "Doctor Who".to_owned(),
+                "E.T. the Extra-Terrestrial".to_owned()
+            ]
+        );
+
+        // test query 3: Search 'Das Boot'
+        let query = MovieSearchQuery {
+            sorting_field: Default::default(),
+            sorting_order: Default::default(),
+            title: Some("Boot".to_owned()),
+            description: None,
+            added_after: None,
+            added_before: None,
+            added_within: None,
+            tags: vec![],
+            tag_match_mode: TagMatchMode::All,
+            exclude_tags: vec![],
+            tag_prefix_match: false,
+            tag_fuzzy_match: false,
+            featured_first: false,
+            has_subtitles: None,
+            subtitle_lang: None,
+            start_index: None,
+            num_results: None,
+        };
+        assert_eq!(index.search_movies(query).await.unwrap().len(), 0);
+        let query = MovieSearchQuery {
+            sorting_field: Default::default(),
+            sorting_order: Default::default(),
+            title: Some("*Boot".to_owned()),
+            description: None,
+            added_after: None,
+            added_before: None,
+            added_within: None,
+            tags: vec![],
+            tag_match_mode: TagMatchMode::All,
+            exclude_tags: vec![],
+            tag_prefix_match: false,
+            tag_fuzzy_match: false,
+            featured_first: false,
+            has_subtitles: None,
+            subtitle_lang: None,
+            start_index: None,
+            num_results: None,
+        };
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Das Boot"]
+        );
+
+        // test query 4: Limited ranges
+        let query = MovieSearchQuery {
+            sorting_field: SortingField::Title,
+            sorting_order: SortingOrder::Ascending,
+            title: None,
+            description: None,
+            added_after: None,
+            added_before: None,
+            added_within: None,
+            tags: vec![],
+            tag_match_mode: TagMatchMode::All,
+            exclude_tags: vec![],
+            tag_prefix_match: false,
+            tag_fuzzy_match: false,
+            featured_first: false,
+            has_subtitles: None,
+            subtitle_lang: None,
+            start_index: Some(0),
+            num_results: Some(1),
+        };
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Das Boot"]
+        );
+        let query = MovieSearchQuery {
+            sorting_field: SortingField::Title,
+            sorting_order: SortingOrder::Ascending,
+            title: None,
+            description: None,
+            added_after: None,
+            added_before: None,
+            added_within: None,
+            tags: vec![],
+            tag_match_mode: TagMatchMode::All,
+            exclude_tags: vec![],
+            tag_prefix_match: false,
+            tag_fuzzy_match: false,
+            featured_first: false,
+            has_subtitles: None,
+            subtitle_lang: None,
+            start_index: Some(1),
+            num_results: Some(2),
+        };
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Doctor Who", "E.T. the Extra-Terrestrial"]
+        );
+
+        // test query 5: Tag prefixes only match if prefix matching is enabled
+        let mut query: MovieSearchQuery = Default::default();
+        query.tags = vec!["Brit".to_owned()];
+        assert!(index.search_movies(query.clone()).await.unwrap().is_empty());
+        query.tag_prefix_match = true;
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Doctor Who"]
+        );
+
+        let mut query: MovieSearchQuery = Default::default();
+        query.tags = vec!["sci".to_owned()];
+        query.tag_prefix_match = true;
+        query.sorting_field = SortingField::Title;
+        query.sorting_order = SortingOrder::Ascending;
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Doctor Who", "E.T. the Extra-Terrestrial"]
+        );
+
+        // wildcards within the prefix are matched literally
+        let mut query: MovieSearchQuery = Default::default();
+        query.tags = vec!["%".to_owned()];
+        query.tag_prefix_match = true;
+        assert!(index.search_movies(query).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_query_movies_with_quotes() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        for movie in create_test_movies() {
+            index.add_movie(movie).await.unwrap();
+        }
+        index
+            .add_movie(Movie {
+                title: "Schindler's List".to_owned(),
+                description: String::new(),
+                tags: vec!["drama".to_owned(), "director's cut".to_owned()],
+            })
+            .await
+            .unwrap();
+
+        // quotes in the title and the tags are matched literally
+        let mut query: MovieSearchQuery = Default::default();
+        query.title = Some("*'s*".to_owned());
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap()).await,
+            ["Schindler's List"]
+        );
+        query.tags = vec!["Director's Cut".to_owned()];
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap()).await,
+            ["Schindler's List"]
+        );
+        query.tag_prefix_match = true;
+        query.tags = vec!["director'".to_owned()];
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Schindler's List"]
+        );
+
+        // injected SQL is matched literally as well
+        let mut query: MovieSearchQuery = Default::default();
+        query.title = Some("'; DROP TABLE movies;--".to_owned());
+        assert!(index.search_movies(query).await.unwrap().is_empty());
+        let mut query: MovieSearchQuery = Default::default();
+        query.tags = vec!["drama') OR ('1'='1".to_owned()];
+        assert!(index.search_movies(query).await.unwrap().is_empty());
+
+        // literal wildcards in the title only match themselves
+        let mut query: MovieSearchQuery = Default::default();
+        query.title = Some("%".to_owned());
+        assert!(index.search_movies(query).await.unwrap().is_empty());
+        let mut query: MovieSearchQuery = Default::default();
+        query.title = Some("Das_Boot".to_owned());
+        assert!(index.search_movies(query).await.unwrap().is_empty());
+
+        // the letter offset is based on the same query
+        let mut query: MovieSearchQuery = Default::default();
+        query.title = Some("*'*".to_owned());
+        query.sorting_field = SortingField::Title;
+        assert_eq!(index.get_letter_offset(query, 'S').await.unwrap(), 0);
+
+        assert_eq!(
+            index
+                .search_movies(MovieSearchQuery::default())
+                .await
+                .unwrap()
+                .len(),
+            5
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_all_tags() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        // the tag sets overlap, but only some movies have all of the searched tags
+        for (title, tags) in [
+            ("Both", vec!["sci-fi", "war"]),
+            ("Sci-Fi only", vec!["sci-fi", "space"]),
+            ("War only", vec!["war", "drama"]),
+            ("All", vec!["sci-fi", "war", "drama"]),
+        ] {
+            index
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: String::new(),
+                    tags: tags.into_iter().map(|tag| tag.to_owned()).collect(),
+                })
+                .await
+                .unwrap();
+        }
+
+        // duplicate tags in different cases count once
+        for tags in [vec!["sci-fi", "war"], vec!["Sci-Fi", "war", "WAR"]] {
+            let query = MovieSearchQuery {
+                sorting_field: SortingField::Title,
+                sorting_order: SortingOrder::Ascending,
+                tags: tags.into_iter().map(|tag| tag.to_owned()).collect(),
+                ..Default::default()
+            };
+            assert_eq!(
+                movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap())
+                    .await,
+                ["All", "Both"]
+            );
+            assert_eq!(index.search_movies_count(query).await.unwrap(), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tag_match_mode() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        for movie in create_test_movies() {
+            index.add_movie(movie).await.unwrap();
+        }
+
+        let search = |tags: &[&str], tag_prefix_match: bool, tag_match_mode: TagMatchMode| {
+            MovieSearchQuery {
+                sorting_field: SortingField::Title,
+                sorting_order: SortingOrder::Ascending,
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                tag_prefix_match,
+                tag_match_mode,
+                ..Default::default()
+            }
+        };
+
+        // exact tags
+        let query = search(&["drama", "movie"], false, TagMatchMode::All);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Das Boot"]
+        );
+        let query = search(&["drama", "movie"], false, TagMatchMode::Any);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Das Boot", "E.T. the Extra-Terrestrial", "The X-Files"]
+        );
+
+        // tag prefixes, where each prefix must match on its own
+        let query = search(&["sci", "us"], true, TagMatchMode::All);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["E.T. the Extra-Terrestrial"]
+        );
+        let query = search(&["sci", "us"], true, TagMatchMode::Any);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Doctor Who", "E.T. the Extra-Terrestrial", "The X-Files"]
+        );
+        let query = search(&["ti", "tv"], true, TagMatchMode::All);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Doctor Who"]
+        );
+
+        // a single tag behaves the same in both modes
+        for tag_match_mode in [TagMatchMode::All, TagMatchMode::Any] {
+            let query = search(&["war"], false, tag_match_mode);
+            assert_eq!(
+                movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+                ["Das Boot"]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_movies_count() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        for movie in create_test_movies() {
+            index.add_movie(movie).await.unwrap();
+        }
+
+        let search = |tags: &[&str], tag_match_mode: TagMatchMode| MovieSearchQuery {
+            sorting_field: SortingField::Title,
+            sorting_order: SortingOrder::Ascending,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            tag_match_mode,
+            start_index: Some(1),
+            num_results: Some(1),
+            ..Default::default()
+        };
+
+        // the count ignores the pagination of the query
+        for (tags, tag_match_mode, count) in [
+            (vec![], TagMatchMode::All, 4),
+            (vec!["drama", "movie"], TagMatchMode::All, 1),
+            (vec!["drama", "movie"], TagMatchMode::Any, 3),
+            (vec!["western"], TagMatchMode::Any, 0),
+        ] {
+            let query = search(&tags, tag_match_mode);
+            assert_eq!(
+                index.search_movies_count(query.clone()).await.unwrap(),
+                count,
+                "{:?}",
+                tags
+            );
+            assert_eq!(
+                index.search_movies(query).await.unwrap().len(),
+                usize::from(count > 1)
+            );
+        }
+
+        let mut query = search(&[], TagMatchMode::All);
+        query.title = Some("D*".to_owned());
+        assert_eq!(index.search_movies_count(query).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_exclude_tags() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        for movie in create_test_movies() {
+            index.add_movie(movie).await.unwrap();
+        }
+
+        let search = |tags: &[&str], exclude_tags: &[&str]| MovieSearchQuery {
+            sorting_field: SortingField::Title,
+            sorting_order: SortingOrder::Ascending,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            exclude_tags: exclude_tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        };
+
+        // the excluded tags are compared case-insensitively
+        let query = search(&["sci-fi"], &["TV Show"]);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["E.T. the Extra-Terrestrial"]
+        );
+
+        // without included tags, all other movies are returned
+        let query = search(&[], &["usa", "war"]);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Doctor Who"]
+        );
+
+        // the exclusion composes with matching any tag and the title
+        let mut query = search(&["drama", "sci-fi"], &["usa"]);
+        query.tag_match_mode = TagMatchMode::Any;
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap()).await,
+            ["Das Boot", "Doctor Who"]
+        );
+        query.title = Some("Das*".to_owned());
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Das Boot"]
+        );
+        let mut query = search(&[], &["movie"]);
+        query.title = Some("E.T.*".to_owned());
+        assert!(index.search_movies(query).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_subtitles() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+        let mut ids = Vec::new();
+        for movie in create_test_movies() {
+            ids.push(index.add_movie(movie).await.unwrap());
+        }
+
+        // Doctor Who has English and German subtitles, Das Boot only German ones
+        let subtitle = |language: &str| SubtitleInfo {
+            language: language.to_owned(),
+            mime_type: "text/vtt".to_owned(),
+            extension: "vtt".to_owned(),
+        };
+        for (id, language) in [(&ids[0], "en"), (&ids[0], "de"), (&ids[3], "de")] {
+            index
+                .add_subtitle_info(id, subtitle(language))
+                .await
+                .unwrap();
+        }
+
+        let search = |has_subtitles: Option<bool>, subtitle_lang: Option<&str>| MovieSearchQuery {
+            sorting_field: SortingField::Title,
+            sorting_order: SortingOrder::Ascending,
+            has_subtitles,
+            subtitle_lang: subtitle_lang.map(|lang| lang.to_owned()),
+            ..Default::default()
+        };
+
+        for (query, titles) in [
+            (search(Some(true), None), vec!["Das Boot", "Doctor Who"]),
+            (
+                search(Some(false), None),
+                vec!["E.T. the Extra-Terrestrial", "The X-Files"],
+            ),
+            // the language is compared case-insensitively
+            (search(None, Some("EN")), vec!["Doctor Who"]),
+            (
+                search(Some(true), Some("de")),
+                vec!["Das Boot", "Doctor Who"],
+            ),
+            (
+                search(Some(false), Some("en")),
+                vec!["Das Boot", "E.T. the Extra-Terrestrial", "The X-Files"],
+            ),
+            (search(None, Some("fr")), vec![]),
+        ] {
+            assert_eq!(
+                index.search_movies_count(query.clone()).await.unwrap(),
+                titles.len()
+            );
+            assert_eq!(
+                movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+                titles
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_date_range() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        // the stored dates differ in the number of fractional digits of their seconds
+        for (title, date) in [
+            ("Das Boot", "2019-03-01T12:00:00+00:00"),
+            ("Alien", "2021-12-31T23:59:59.250+00:00"),
+            ("Heat", "2022-06-15T08:30:00+00:00"),
+            ("Apollo 13", "2023-01-01T00:00:00+00:00"),
+        ] {
+            let id = index
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: String::new(),
+                    tags: Vec::new(),
+                })
+                .await
+                .unwrap();
+
+            index
+                .pool
+                .get()
+                .unwrap()
+                .execute("UPDATE movies SET date_added=?1 WHERE id=?2", (date, &id))
+                .unwrap();
+        }
+
+        let search = |added_after: Option<&str>, added_before: Option<&str>| MovieSearchQuery {
+            sorting_field: SortingField::Title,
+            sorting_order: SortingOrder::Ascending,
+            added_after: added_after.map(|date| date.parse().unwrap()),
+            added_before: added_before.map(|date| date.parse().unwrap()),
+            ..Default::default()
+        };
+
+        let query = search(Some("2021-01-01T00:00:00Z"), None);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Alien", "Apollo 13", "Heat"]
+        );
+
+        let query = search(None, Some("2022-01-01T00:00:00Z"));
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Alien", "Das Boot"]
+        );
+
+        // the start of the range is inclusive, whereas its end is exclusive
+        let query = search(Some("2021-12-31T23:59:59Z"), Some("2023-01-01T00:00:00Z"));
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Alien", "Heat"]
+        );
+        let query = search(Some("2022-06-15T10:30:00+02:00"), None);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Apollo 13", "Heat"]
+        );
+
+        // an inverted range is rejected
+        let query = search(Some("2023-01-01T00:00:00Z"), Some("2021-01-01T00:00:00Z"));
+        assert!(matches!(
+            index.search_movies(query).await,
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_search_description() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.description_offload_threshold = Some(64);
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
 
-        let mut tags: Vec<(String, usize)> = Vec::new();
-        for row in rows {
-            tags.push(row?);
+        for movie in create_test_movies() {
+            index.add_movie(movie).await.unwrap();
         }
 
-        Ok(tags)
-    }
-}
+        let search = |title: Option<&str>, description: Option<&str>| {
+            let mut query: MovieSearchQuery = Default::default();
+            query.sorting_field = SortingField::Title;
+            query.sorting_order = SortingOrder::Ascending;
+            query.title = title.map(|s| s.to_owned());
+            query.description = description.map(|s| s.to_owned());
+            query
+        };
 
-#[cfg(test)]
-mod test {
-    use tempdir::TempDir;
+        // description only, the matched text is beyond the preview of the offloaded descriptions
+        let query = search(None, Some("*home planet*"));
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Doctor Who", "E.T. the Extra-Terrestrial"]
+        );
 
-    use crate::Movie;
+        // title only
+        let query = search(Some("Doctor*"), None);
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["Doctor Who"]
+        );
 
-    use super::*;
+        // both title and description must match, also in combination with tags
+        let mut query = search(Some("E.T.*"), Some("*home planet*"));
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap()).await,
+            ["E.T. the Extra-Terrestrial"]
+        );
+        query.tags = vec!["sci-fi".to_owned()];
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
+            ["E.T. the Extra-Terrestrial"]
+        );
+        let query = search(Some("Doctor*"), Some("*alien*"));
+        assert!(index.search_movies(query).await.unwrap().is_empty());
 
-    fn create_test_movies() -> Vec<Movie> {
-        vec![Movie { title: "Doctor Who".to_owned(), description: "The Doctor, a Time Lord from the race whose home planet is Gallifrey, travels through time and space in their ship the TARDIS (an acronym for Time and Relative Dimension In Space) with numerous companions.".to_owned(), tags: vec!["Sci-Fi".to_owned(), "time travel".to_owned(), "british".to_owned(), "tv show".to_owned()] },
-        Movie { title: "The X-Files".to_owned(), description: "Two F.B.I. Agents, Fox Mulder the believer and Dana Scully the skeptic, investigate the strange and unexplained, while hidden forces work to impede their efforts.".to_owned(), tags: vec!["crime".to_owned(), "drama".to_owned(), "mystery".to_owned(), "usa".to_owned(), "tv show".to_owned()] },
-        Movie { title: "E.T. the Extra-Terrestrial".to_owned(), description: "A troubled child summons the courage to help a friendly alien escape from Earth and return to his home planet.".to_owned(), tags: vec!["adventure".to_owned(), "family".to_owned(), "Sci-Fi".to_owned(), "usa".to_owned(), "movie".to_owned()] },
-        Movie { title: "Das Boot".to_owned(), description: "A German U-boat stalks the frigid waters of the North Atlantic as its young crew experience the sheer terror and claustrophobic life of a submariner in World War II.".to_owned(), tags: vec!["drama".to_owned(), "war".to_owned(), "germany".to_owned(), "movie".to_owned()] }]
+        // literal wildcards in the description only match themselves
+        let query = search(None, Some("*100%*"));
+        assert!(index.search_movies(query).await.unwrap().is_empty());
     }
 
     #[tokio::test]
-    async fn test_add_movie() {
+    async fn test_query_timing() {
         let root_dir = TempDir::new("movies-db").unwrap();
         let mut options = Options::default();
         options.root_dir = root_dir.path().to_path_buf();
+
+        // without query timing, no stats are collected
+        let index = SqliteMoviesIndex::new(&options).unwrap();
+        assert!(index.get_query_stats().is_none());
+        drop(index);
+
+        // every query exceeds a zero threshold and is reported as slow
+        options.query_timing = true;
+        options.slow_query_threshold = 0;
         let mut index = SqliteMoviesIndex::new(&options).unwrap();
+        let id = index
+            .add_movie(create_test_movies()[0].clone())
+            .await
+            .unwrap();
 
-        let movies = create_test_movies();
+        index.get_movie(&id).await.unwrap();
+        index.search_movies(Default::default()).await.unwrap();
+        index.search_movies(Default::default()).await.unwrap();
+        index.get_tag_list_with_count().await.unwrap();
 
-        for movie in movies {
-            let ret = index.add_movie(movie.clone()).await;
-            assert!(ret.is_ok());
-        }
+        let stats = index.get_query_stats().unwrap();
+        assert_eq!(
+            stats.keys().collect::<Vec<_>>(),
+            ["get_movie", "get_tag_list", "search_movies"]
+        );
+        assert_eq!(stats["get_movie"].count, 1);
+        assert_eq!(stats["get_movie"].slow_count, 1);
+        assert_eq!(stats["search_movies"].count, 2);
+        assert_eq!(stats["search_movies"].slow_count, 2);
+        assert!(stats["search_movies"].total_micros >= stats["search_movies"].max_micros);
     }
 
     #[tokio::test]
-    async fn test_get_movie() {
+    async fn test_rename_tag() {
         let root_dir = TempDir::new("movies-db").unwrap();
         let mut options = Options::default();
         options.root_dir = root_dir.path().to_path_buf();
         let mut index = SqliteMoviesIndex::new(&options).unwrap();
 
-        let movies = create_test_movies();
-
-        let mut movie_ids: Vec<MovieId> = Vec::with_capacity(movies.len());
-        for movie in movies.iter() {
-            let id = index.add_movie(movie.clone()).await.unwrap();
-            movie_ids.push(id);
+        let mut movie_ids: Vec<MovieId> = Vec::new();
+        for movie in create_test_movies() {
+            movie_ids.push(index.add_movie(movie).await.unwrap());
         }
 
-        for (id, movie) in movie_ids.iter().zip(movies.iter()) {
-            let db_movie = index.get_movie(id).await;
-            assert!(db_movie.is_ok());
-            let db_movie = db_movie.unwrap();
+        let tags = |movie: MovieDetailed| -> Vec<String> {
+            let mut tags = movie.movie.tags;
+            tags.sort();
+            tags
+        };
 
-            assert_eq!(movie.title, db_movie.movie.title);
-            assert_eq!(movie.description, db_movie.movie.description);
+        assert_eq!(index.rename_tag("Drama", "thriller").await.unwrap(), 2);
+        assert_eq!(
+            tags(index.get_movie(&movie_ids[3]).await.unwrap()),
+            ["germany", "movie", "thriller", "war"]
+        );
 
-            let mut ref_tags = movie.tags.clone();
-            ref_tags
-                .iter_mut()
-                .for_each(|tag| *tag = tag.to_lowercase());
-            ref_tags.sort();
-            assert_eq!(ref_tags, db_movie.movie.tags);
-        }
+        // renaming onto an existing tag of the movie merges both tags
+        assert_eq!(index.rename_tag("crime", "mystery").await.unwrap(), 1);
+        assert_eq!(
+            tags(index.get_movie(&movie_ids[1]).await.unwrap()),
+            ["mystery", "thriller", "tv show", "usa"]
+        );
+
+        // unknown tags affect no movie
+        assert_eq!(index.rename_tag("western", "cowboys").await.unwrap(), 0);
+        assert!(matches!(
+            index.rename_tag("war", "").await,
+            Err(Error::InvalidArgument(_))
+        ));
     }
 
     #[tokio::test]
-    async fn test_remove_movie() {
+    async fn test_rename_tags() {
         let root_dir = TempDir::new("movies-db").unwrap();
         let mut options = Options::default();
         options.root_dir = root_dir.path().to_path_buf();
         let mut index = SqliteMoviesIndex::new(&options).unwrap();
 
-        let movies = create_test_movies();
-
-        let mut movie_ids: Vec<MovieId> = Vec::with_capacity(movies.len());
-        for movie in movies.iter() {
-            let id = index.add_movie(movie.clone()).await.unwrap();
-            movie_ids.push(id);
+        let mut movie_ids: Vec<MovieId> = Vec::new();
+        for movie in create_test_movies() {
+            movie_ids.push(index.add_movie(movie).await.unwrap());
         }
 
-        movie_ids.sort();
-
-        index.remove_movie(&movie_ids[0]).await.unwrap();
-        assert!(index.remove_movie(&movie_ids[0]).await.is_err());
-
-        let movie_ids = &movie_ids[1..];
-        let mut listed_movie_ids = index.search_movies(Default::default()).await.unwrap();
-        listed_movie_ids.sort();
+        let renames = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+            pairs
+                .iter()
+                .map(|(old_tag, new_tag)| (old_tag.to_string(), new_tag.to_string()))
+                .collect()
+        };
+        let tags = |movie: MovieDetailed| -> Vec<String> {
+            let mut tags = movie.movie.tags;
+            tags.sort();
+            tags
+        };
 
-        assert_eq!(movie_ids, listed_movie_ids);
-    }
+        // a simple batch, which follows the chain from "tv" to "series"
+        let counts = index
+            .rename_tags(&renames(&[
+                ("Sci-Fi", "science fiction"),
+                ("tv show", "tv"),
+                ("tv", "series"),
+            ]))
+            .await
+            .unwrap();
+        assert_eq!(
+            counts,
+            BTreeMap::from([
+                ("sci-fi".to_owned(), 2),
+                ("tv".to_owned(), 0),
+                ("tv show".to_owned(), 2)
+            ])
+        );
+        assert_eq!(
+            tags(index.get_movie(&movie_ids[0]).await.unwrap()),
+            ["british", "science fiction", "series", "time travel"]
+        );
 
-    async fn movie_ids_to_titles(index: &SqliteMoviesIndex, movie_ids: &[MovieId]) -> Vec<String> {
-        let mut movie_titles: Vec<String> = Vec::with_capacity(movie_ids.len());
+        // renaming onto an existing tag merges both tags
+        let counts = index
+            .rename_tags(&renames(&[("crime", "drama")]))
+            .await
+            .unwrap();
+        assert_eq!(counts, BTreeMap::from([("crime".to_owned(), 1)]));
+        assert_eq!(
+            tags(index.get_movie(&movie_ids[1]).await.unwrap()),
+            ["drama", "mystery", "series", "usa"]
+        );
 
-        for movie_id in movie_ids {
-            let movie = index.get_movie(movie_id).await.unwrap();
-            movie_titles.push(movie.movie.title.clone());
-        }
+        // cycles are rejected without renaming any tag
+        assert!(matches!(
+            index
+                .rename_tags(&renames(&[("usa", "america"), ("america", "usa")]))
+                .await,
+            Err(Error::InvalidArgument(_))
+        ));
+        assert_eq!(
+            tags(index.get_movie(&movie_ids[2]).await.unwrap()),
+            ["adventure", "family", "movie", "science fiction", "usa"]
+        );
 
-        movie_titles
+        let tag_list = index.get_tag_list_with_count().await.unwrap();
+        assert!(tag_list.contains(&("drama".to_owned(), 2)));
+        assert!(tag_list.contains(&("science fiction".to_owned(), 2)));
+        assert!(!tag_list
+            .iter()
+            .any(|(tag, _)| tag == "crime" || tag == "sci-fi"));
     }
 
     #[tokio::test]
-    async fn test_query_movies() {
+    async fn test_featured_first() {
         let root_dir = TempDir::new("movies-db").unwrap();
         let mut options = Options::default();
         options.root_dir = root_dir.path().to_path_buf();
         let mut index = SqliteMoviesIndex::new(&options).unwrap();
 
-        let movies = create_test_movies();
-
-        for movie in movies.iter() {
-            let ret = index.add_movie(movie.clone()).await;
-            assert!(ret.is_ok());
+        let mut movie_ids: Vec<MovieId> = Vec::new();
+        for movie in create_test_movies() {
+            movie_ids.push(index.add_movie(movie).await.unwrap());
         }
 
-        // test query 1A: Search all movies (ascending order by title)
+        // feature "The X-Files" and "E.T. the Extra-Terrestrial"
+        index.set_featured(&movie_ids[1], true).await.unwrap();
+        index.set_featured(&movie_ids[2], true).await.unwrap();
+        index.set_featured(&movie_ids[2], true).await.unwrap();
+        assert!(index.get_movie(&movie_ids[1]).await.unwrap().featured);
+        assert!(!index.get_movie(&movie_ids[0]).await.unwrap().featured);
+
+        // the featured movies lead the results and both parts keep their order
         let mut query: MovieSearchQuery = Default::default();
         query.sorting_field = SortingField::Title;
         query.sorting_order = SortingOrder::Ascending;
-        let movie_title: Vec<String> =
-            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await;
-
         assert_eq!(
-            movie_title,
-            vec![
+            movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap()).await,
+            [
                 "Das Boot",
                 "Doctor Who",
+                "E.T. the Extra-Terrestrial",
+                "The X-Files"
+            ]
+        );
+        query.featured_first = true;
+        assert_eq!(
+            movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap()).await,
+            [
                 "E.T. the Extra-Terrestrial",
                 "The X-Files",
+                "Das Boot",
+                "Doctor Who"
             ]
         );
+        assert_eq!(
+            index.get_letter_offset(query.clone(), 'D').await.unwrap(),
+            2
+        );
 
-        // test query 1B: Search all movies (descending order by title)
-        let mut query: MovieSearchQuery = Default::default();
-        query.sorting_field = SortingField::Title;
         query.sorting_order = SortingOrder::Descending;
-        let movie_title: Vec<String> =
-            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await;
-
         assert_eq!(
-            movie_title,
-            vec![
+            movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap()).await,
+            [
                 "The X-Files",
                 "E.T. the Extra-Terrestrial",
                 "Doctor Who",
-                "Das Boot",
+                "Das Boot"
             ]
         );
 
-        // // test query 2: Search only science fiction movies
-        let mut query: MovieSearchQuery = Default::default();
-        query.tags = vec!["Sci-Fi".to_owned()];
-        query.sorting_field = SortingField::Title;
+        // the pagination and the filters apply to the reordered results
         query.sorting_order = SortingOrder::Ascending;
-        let search_result = index.search_movies(query).await.unwrap();
-        let title_list: Vec<String> = movie_ids_to_titles(&index, &search_result).await;
+        query.start_index = Some(1);
+        query.num_results = Some(2);
         assert_eq!(
-            title_list,
-            vec![
-                "Doctor Who".to_owned(),
-                "E.T. the Extra-Terrestrial".to_owned()
-            ]
+            movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap()).await,
+            ["The X-Files", "Das Boot"]
         );
-
-        // test query 3: Search 'Das Boot'
-        let query = MovieSearchQuery {
-            sorting_field: Default::default(),
-            sorting_order: Default::default(),
-            title: Some("Boot".to_owned()),
-            tags: vec![],
-            start_index: None,
-            num_results: None,
-        };
-        assert_eq!(index.search_movies(query).await.unwrap().len(), 0);
-        let query = MovieSearchQuery {
-            sorting_field: Default::default(),
-            sorting_order: Default::default(),
-            title: Some("*Boot".to_owned()),
-            tags: vec![],
-            start_index: None,
-            num_results: None,
-        };
+        query.start_index = None;
+        query.num_results = None;
+        query.tags = vec!["movie".to_owned()];
         assert_eq!(
-            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
-            ["Das Boot"]
+            movie_ids_to_titles(&index, &index.search_movies(query.clone()).await.unwrap()).await,
+            ["E.T. the Extra-Terrestrial", "Das Boot"]
         );
 
-        // test query 4: Limited ranges
-        let query = MovieSearchQuery {
-            sorting_field: SortingField::Title,
-            sorting_order: SortingOrder::Ascending,
-            title: None,
-            tags: vec![],
-            start_index: Some(0),
-            num_results: Some(1),
-        };
-        assert_eq!(
-            movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
-            ["Das Boot"]
-        );
-        let query = MovieSearchQuery {
-            sorting_field: SortingField::Title,
-            sorting_order: SortingOrder::Ascending,
-            title: None,
-            tags: vec![],
-            start_index: Some(1),
-            num_results: Some(2),
-        };
+        // unfeatured movies are sorted as usual
+        index.set_featured(&movie_ids[2], false).await.unwrap();
+        assert!(!index.get_movie(&movie_ids[2]).await.unwrap().featured);
         assert_eq!(
             movie_ids_to_titles(&index, &index.search_movies(query).await.unwrap()).await,
-            ["Doctor Who", "E.T. the Extra-Terrestrial"]
+            ["Das Boot", "E.T. the Extra-Terrestrial"]
         );
+
+        assert!(matches!(
+            index.set_featured(&"unknown".to_owned(), true).await,
+            Err(Error::NotFound(_))
+        ));
     }
 
     #[tokio::test]
@@ -670,7 +3800,7 @@ mod test {
 
         // make sure non of the added movies has a file info
         for movie_id in movie_ids.iter() {
-            let movie = index.get_movie(&movie_id).await.unwrap();
+            let movie = index.get_movie(movie_id).await.unwrap();
             assert!(movie.movie_file_info.is_none());
         }
 
@@ -681,10 +3811,12 @@ mod test {
             MovieFileInfo {
                 extension: ".mp4".to_owned(),
                 mime_type: "video/mp4".to_owned(),
+                original_filename: Some("Das Boot (1981).mp4".to_owned()),
             },
             MovieFileInfo {
                 extension: ".wmv".to_owned(),
                 mime_type: "video/x-ms-wmv".to_owned(),
+                original_filename: None,
             },
         ];
 
@@ -726,7 +3858,7 @@ mod test {
 
         // make sure non of the added movies has a screenshot info
         for movie_id in movie_ids.iter() {
-            let movie = index.get_movie(&movie_id).await.unwrap();
+            let movie = index.get_movie(movie_id).await.unwrap();
             assert!(movie.screenshot_file_info.is_none());
         }
 
@@ -737,10 +3869,12 @@ mod test {
             ScreenshotInfo {
                 extension: ".png".to_owned(),
                 mime_type: "video/png".to_owned(),
+                count: 1,
             },
             ScreenshotInfo {
                 extension: ".jpeg".to_owned(),
                 mime_type: "image/jpeg".to_owned(),
+                count: 5,
             },
         ];
 
@@ -763,4 +3897,179 @@ mod test {
             assert!(movie.screenshot_file_info.is_none());
         }
     }
+
+    #[tokio::test]
+    async fn test_screenshot_hashes() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let mut movie_ids: Vec<MovieId> = Vec::new();
+        for movie in create_test_movies().into_iter().take(3) {
+            movie_ids.push(index.add_movie(movie).await.unwrap());
+        }
+        movie_ids.sort();
+
+        // hashes with the highest bit set must survive the round trip
+        index
+            .update_screenshot_hash(&movie_ids[0], u64::MAX)
+            .await
+            .unwrap();
+        index
+            .update_screenshot_hash(&movie_ids[1], 0x0123_4567_89ab_cdef)
+            .await
+            .unwrap();
+        index
+            .update_screenshot_hash(&movie_ids[2], 0)
+            .await
+            .unwrap();
+        assert_eq!(
+            index.get_screenshot_hashes().await.unwrap(),
+            vec![
+                (movie_ids[0].clone(), u64::MAX),
+                (movie_ids[1].clone(), 0x0123_4567_89ab_cdef),
+                (movie_ids[2].clone(), 0),
+            ]
+        );
+
+        // a new screenshot or removing the movie drops the hash
+        index
+            .update_screenshot_info(
+                &movie_ids[1],
+                ScreenshotInfo {
+                    extension: "png".to_owned(),
+                    mime_type: "image/png".to_owned(),
+                    count: 1,
+                },
+            )
+            .await
+            .unwrap();
+        index.remove_movie(&movie_ids[2]).await.unwrap();
+        assert_eq!(
+            index.get_screenshot_hashes().await.unwrap(),
+            vec![(movie_ids[0].clone(), u64::MAX)]
+        );
+
+        // unknown movies are rejected
+        let ret = index.update_screenshot_hash(&movie_ids[2], 0).await;
+        assert!(matches!(ret, Err(Error::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_preview_state() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let id = index
+            .add_movie(create_test_movies()[0].clone())
+            .await
+            .unwrap();
+        assert!(index.get_movie(&id).await.unwrap().preview_state.is_none());
+
+        let states = [
+            PreviewState::Pending,
+            PreviewState::Failed {
+                reason: "Invalid data found when processing input".to_owned(),
+            },
+            PreviewState::Unsupported,
+            PreviewState::Ready,
+        ];
+        for state in states {
+            index
+                .update_preview_state(&id, state.clone())
+                .await
+                .unwrap();
+            assert_eq!(
+                index.get_movie(&id).await.unwrap().preview_state,
+                Some(state)
+            );
+        }
+
+        // the state survives reopening the index
+        drop(index);
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+        assert_eq!(
+            index.get_movie(&id).await.unwrap().preview_state,
+            Some(PreviewState::Ready)
+        );
+
+        // a movie added again with the same id starts without a state
+        let movie = index.get_movie(&id).await.unwrap().movie;
+        index.remove_movie(&id).await.unwrap();
+        index.add_movie_with_id(id.clone(), movie).await.unwrap();
+        assert!(index.get_movie(&id).await.unwrap().preview_state.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_letter_offset() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        let mut index = SqliteMoviesIndex::new(&options).unwrap();
+
+        let movie = |title: &str, tags: &[&str]| Movie {
+            title: title.to_owned(),
+            description: String::new(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        };
+        for movie in [
+            movie("Alien", &["space"]),
+            movie("Cosmos", &["space"]),
+            movie("Das Boot", &["war"]),
+            movie("Doctor Who", &["space"]),
+            movie("Heat", &[]),
+        ] {
+            index.add_movie(movie).await.unwrap();
+        }
+
+        // the offset points at the first title starting with the letter
+        let mut query: MovieSearchQuery = Default::default();
+        query.sorting_field = SortingField::Title;
+        query.sorting_order = SortingOrder::Ascending;
+        let offset = index.get_letter_offset(query.clone(), 'D').await.unwrap();
+        assert_eq!(offset, 2);
+        let movie_ids = index.search_movies(query.clone()).await.unwrap();
+        assert_eq!(
+            movie_ids_to_titles(&index, &movie_ids[offset..offset + 1]).await,
+            ["Das Boot"]
+        );
+
+        // without a matching title, the offset is the position of the next title
+        assert_eq!(
+            index.get_letter_offset(query.clone(), 'E').await.unwrap(),
+            4
+        );
+        assert_eq!(
+            index.get_letter_offset(query.clone(), 'Z').await.unwrap(),
+            5
+        );
+
+        // the pagination of the query is ignored
+        query.start_index = Some(3);
+        query.num_results = Some(1);
+        assert_eq!(
+            index.get_letter_offset(query.clone(), 'D').await.unwrap(),
+            2
+        );
+
+        // in descending order, the titles starting with the letter follow the greater titles
+        query.sorting_order = SortingOrder::Descending;
+        query.start_index = None;
+        query.num_results = None;
+        let offset = index.get_letter_offset(query.clone(), 'D').await.unwrap();
+        assert_eq!(offset, 1);
+        let movie_ids = index.search_movies(query.clone()).await.unwrap();
+        assert_eq!(
+            movie_ids_to_titles(&index, &movie_ids[offset..offset + 1]).await,
+            ["Doctor Who"]
+        );
+
+        // the filters of the query are applied
+        query.sorting_order = SortingOrder::Ascending;
+        query.tags = vec!["space".to_owned()];
+        assert_eq!(index.get_letter_offset(query, 'D').await.unwrap(), 2);
+    }
 }
@@ -0,0 +1,119 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::Error;
+
+/// Resolves the given batch of tag renames, which maps old tags onto new tags, into the final
+/// tag of each old tag. Tags are compared case-insensitively, i.e., the resolved renames are
+/// keyed by the lower case old tags, whereas the new tags keep their casing.
+/// Chains are followed to their end, e.g., "a" -> "b" and "b" -> "c" renames both "a" and "b"
+/// into "c". Cycles, e.g., "a" -> "b" and "b" -> "a", are rejected, as are empty tags and old
+/// tags given more than once.
+///
+/// # Arguments
+/// * `renames` - The renames mapping the old tags onto the new tags.
+pub fn resolve_tag_renames(
+    renames: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, Error> {
+    let mut direct: BTreeMap<String, String> = BTreeMap::new();
+    for (old_tag, new_tag) in renames.iter() {
+        let old_tag = old_tag.trim().to_lowercase();
+        let new_tag = new_tag.trim();
+        if old_tag.is_empty() || new_tag.is_empty() {
+            return Err(Error::InvalidArgument(
+                "Renamed tags must not be empty".to_owned(),
+            ));
+        }
+
+        if direct.insert(old_tag.clone(), new_tag.to_owned()).is_some() {
+            return Err(Error::InvalidArgument(format!(
+                "Tag '{}' is renamed more than once",
+                old_tag
+            )));
+        }
+    }
+
+    let mut resolved: BTreeMap<String, String> = BTreeMap::new();
+    for (old_tag, new_tag) in direct.iter() {
+        let mut visited: BTreeSet<&str> = BTreeSet::new();
+        visited.insert(old_tag);
+
+        let mut current = old_tag.as_str();
+        let mut target = new_tag;
+        loop {
+            // renaming a tag onto itself only changes its casing and ends the chain
+            let key = target.to_lowercase();
+            if key == current {
+                break;
+            }
+
+            match direct.get_key_value(&key) {
+                None => break,
+                Some((next, _)) if visited.contains(next.as_str()) => {
+                    return Err(Error::InvalidArgument(format!(
+                        "The renames of tag '{}' form a cycle",
+                        old_tag
+                    )));
+                }
+                Some((next, next_target)) => {
+                    visited.insert(next);
+                    current = next;
+                    target = next_target;
+                }
+            }
+        }
+
+        resolved.insert(old_tag.clone(), target.clone());
+    }
+
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Creates the renames from the given pairs of old and new tags.
+    fn renames(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(old_tag, new_tag)| (old_tag.to_string(), new_tag.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_tag_renames() {
+        let resolved = resolve_tag_renames(&renames(&[
+            ("Sci-Fi", "science fiction"),
+            ("Scifi", "sci-fi"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            resolved,
+            renames(&[("sci-fi", "science fiction"), ("scifi", "science fiction")])
+        );
+
+        // renaming a tag onto itself changes its casing
+        let resolved =
+            resolve_tag_renames(&renames(&[("usa", "USA"), ("america", "usa")])).unwrap();
+        assert_eq!(resolved, renames(&[("america", "USA"), ("usa", "USA")]));
+
+        // cycles are rejected
+        for pairs in [
+            vec![("a", "b"), ("b", "a")],
+            vec![("a", "b"), ("b", "c"), ("c", "A")],
+        ] {
+            let ret = resolve_tag_renames(&renames(&pairs));
+            assert!(matches!(ret, Err(Error::InvalidArgument(_))), "{:?}", pairs);
+        }
+
+        // empty and ambiguous renames are rejected
+        for pairs in [
+            vec![("", "a")],
+            vec![("a", " ")],
+            vec![("drama", "comedy"), ("Drama", "thriller")],
+        ] {
+            let ret = resolve_tag_renames(&renames(&pairs));
+            assert!(matches!(ret, Err(Error::InvalidArgument(_))), "{:?}", pairs);
+        }
+    }
+}
@@ -16,11 +16,28 @@ quick_error! {
         NotFound(err: String) {
             display("{}", err)
         }
+        AlreadyExists(err: String) {
+            display("{}", err)
+        }
+        PermissionDenied(err: String) {
+            display("{}", err)
+        }
+        InsufficientStorage(err: String) {
+            display("{}", err)
+        }
+        PayloadTooLarge(err: String) {
+            display("{}", err)
+        }
     }
 }
 
 impl From<io::Error> for Error {
     fn from(error: io::Error) -> Self {
+        // a full disk is reported separately, s.t. clients can tell it apart from other failures
+        if error.kind() == io::ErrorKind::StorageFull {
+            return Error::InsufficientStorage(format!("{}", error));
+        }
+
         Error::IO(format!("{}", error))
     }
 }
@@ -30,3 +47,33 @@ impl From<rusqlite::Error> for Error {
         Error::Internal(format!("SQLite Error: {}", error))
     }
 }
+
+impl From<r2d2::Error> for Error {
+    fn from(error: r2d2::Error) -> Self {
+        Error::Internal(format!("SQLite Pool Error: {}", error))
+    }
+}
+
+impl From<async_zip::error::ZipError> for Error {
+    fn from(error: async_zip::error::ZipError) -> Self {
+        Error::Internal(format!("ZIP Error: {}", error))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Internal(format!("JSON Error: {}", error))
+    }
+}
+
+impl From<prometheus::Error> for Error {
+    fn from(error: prometheus::Error) -> Self {
+        Error::Internal(format!("Prometheus Error: {}", error))
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(error: image::ImageError) -> Self {
+        Error::Internal(format!("Image Error: {}", error))
+    }
+}
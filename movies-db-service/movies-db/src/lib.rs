@@ -1,5 +1,9 @@
+// the tests start from the default options and adjust them field by field
+#![cfg_attr(test, allow(clippy::field_reassign_with_default))]
+
 mod db;
 mod error;
+mod lru;
 mod options;
 mod service;
 mod storage;
@@ -0,0 +1,194 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+};
+
+/// A cached value together with its weight and the time of its last use.
+struct LruEntry<V> {
+    /// The cached value.
+    value: V,
+
+    /// The weight of the value, e.g., its size in bytes.
+    weight: usize,
+
+    /// The time of the last use.
+    used: u64,
+}
+
+/// A least recently used cache bounded by the total weight of its values, where e.g. a weight
+/// of one per value bounds the number of values and the size of each value bounds their total
+/// size. A maximal weight of zero disables the cache.
+pub(crate) struct Lru<K, V> {
+    /// The maximal total weight of the cached values.
+    max_weight: usize,
+
+    /// The total weight of the cached values.
+    weight: usize,
+
+    /// The cached values by their keys.
+    entries: HashMap<K, LruEntry<V>>,
+
+    /// The keys of the cached values ordered by the time of their last use.
+    last_used: BTreeMap<u64, K>,
+
+    /// The logical clock for tracking the time of the last use.
+    clock: u64,
+}
+
+impl<K: Eq + Hash + Clone, V> Lru<K, V> {
+    /// Creates a new empty cache.
+    ///
+    /// # Arguments
+    /// * `max_weight` - The maximal total weight of the cached values.
+    pub fn new(max_weight: usize) -> Self {
+        Self {
+            max_weight,
+            weight: 0,
+            entries: HashMap::new(),
+            last_used: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Returns the maximal total weight of the cached values.
+    pub fn max_weight(&self) -> usize {
+        self.max_weight
+    }
+
+    /// Returns the next time of the logical clock.
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// Returns the cached value for the given key, if any, and marks it as recently used.
+    ///
+    /// # Arguments
+    /// * `key` - The key of the value.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let clock = self.tick();
+
+        let entry = self.entries.get_mut(key)?;
+        let used = std::mem::replace(&mut entry.used, clock);
+        self.last_used.remove(&used);
+        self.last_used.insert(clock, key.clone());
+
+        Some(&entry.value)
+    }
+
+    /// Returns true if a value is cached for the given key, without marking it as used.
+    ///
+    /// # Arguments
+    /// * `key` - The key of the value.
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Inserts the given value, replacing any previous value of the key, and evicts the least
+    /// recently used values until the cache fits. Values exceeding the maximal weight on their
+    /// own are not cached.
+    ///
+    /// # Arguments
+    /// * `key` - The key of the value.
+    /// * `value` - The value to insert.
+    /// * `weight` - The weight of the value.
+    pub fn insert(&mut self, key: K, value: V, weight: usize) {
+        self.remove(&key);
+        if weight > self.max_weight || self.max_weight == 0 {
+            return;
+        }
+
+        while self.weight + weight > self.max_weight {
+            match self.last_used.pop_first() {
+                Some((_, evicted_key)) => {
+                    if let Some(evicted) = self.entries.remove(&evicted_key) {
+                        self.weight -= evicted.weight;
+                    }
+                }
+                None => break,
+            }
+        }
+
+        let used = self.tick();
+        self.weight += weight;
+        self.last_used.insert(used, key.clone());
+        self.entries.insert(
+            key,
+            LruEntry {
+                value,
+                weight,
+                used,
+            },
+        );
+    }
+
+    /// Removes the value for the given key, if any.
+    ///
+    /// # Arguments
+    /// * `key` - The key of the value.
+    pub fn remove(&mut self, key: &K) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.last_used.remove(&entry.used);
+            self.weight -= entry.weight;
+        }
+    }
+
+    /// Removes all values from the cache.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.last_used.clear();
+        self.weight = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lru() {
+        let mut lru = Lru::new(3);
+
+        lru.insert("a", 1, 1);
+        lru.insert("b", 2, 1);
+        lru.insert("c", 3, 1);
+        assert_eq!(lru.get(&"a"), Some(&1));
+
+        // inserting d evicts b, which has been used least recently
+        lru.insert("d", 4, 1);
+        assert!(!lru.contains(&"b"));
+        assert!(lru.contains(&"a"));
+        assert!(lru.contains(&"c"));
+        assert_eq!(lru.get(&"a"), Some(&1));
+
+        // a heavier value evicts as many values as needed
+        lru.insert("e", 5, 2);
+        assert!(!lru.contains(&"c"));
+        assert!(!lru.contains(&"d"));
+        assert_eq!(lru.get(&"a"), Some(&1));
+        assert_eq!(lru.get(&"e"), Some(&5));
+
+        // replacing a value updates the total weight
+        lru.insert("e", 6, 1);
+        lru.insert("f", 7, 1);
+        assert!(lru.contains(&"a"));
+        assert_eq!(lru.get(&"e"), Some(&6));
+
+        // too heavy values are not cached
+        lru.insert("g", 8, 4);
+        assert!(!lru.contains(&"g"));
+
+        lru.remove(&"f");
+        assert!(!lru.contains(&"f"));
+
+        lru.clear();
+        assert!(!lru.contains(&"a"));
+        lru.insert("a", 1, 3);
+        assert!(lru.contains(&"a"));
+
+        // a cache without weight caches nothing
+        let mut lru = Lru::new(0);
+        lru.insert("a", 1, 0);
+        assert!(lru.get(&"a").is_none());
+    }
+}
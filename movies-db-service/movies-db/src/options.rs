@@ -1,5 +1,131 @@
 use std::{net::SocketAddr, path::PathBuf};
 
+use chrono::FixedOffset;
+
+use crate::{ffmpeg::default_max_ffmpeg_processes, is_valid_movie_id, Error};
+
+/// The name of the default library, whose data is located directly in the root directory.
+pub const DEFAULT_LIBRARY: &str = "default";
+
+/// The name of the directory in the root directory containing the data of the named libraries.
+/// The name contains a '.', s.t. it can't collide with the data directory of a movie.
+const LIBRARIES_DIR_NAME: &str = "libraries.d";
+
+/// The policy for descriptions exceeding the maximal description length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DescriptionLengthPolicy {
+    /// Movies with a too long description are rejected.
+    #[default]
+    Reject,
+
+    /// Too long descriptions are truncated and an ellipsis is appended.
+    Truncate,
+}
+
+/// The policy for preview requests if the queue of preview requests is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PreviewBackpressurePolicy {
+    /// Wait until the request can be queued, which slows down the uploads.
+    #[default]
+    Wait,
+
+    /// Drop the request, the missing preview is requested again on the next startup.
+    Drop,
+
+    /// Drop the request, but let the upload respond with 503 Service Unavailable and a
+    /// Retry-After header, s.t. the client can request the preview again later. The uploaded
+    /// movie file is stored nevertheless.
+    Defer,
+}
+
+/// The policy for uploaded movie files without any video stream, e.g., audio-only files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoVideoStreamPolicy {
+    /// The movie file is stored, but its preview is flagged as unsupported.
+    #[default]
+    FlagUnsupported,
+
+    /// The upload is rejected with 415 Unsupported Media Type. Requires ffmpeg, without it all
+    /// uploads are accepted.
+    Reject,
+}
+
+/// The handling of the tags extracted from the filenames of uploaded movie files, e.g., the year
+/// and the resolution of "Alien.1979.BluRay.1080p.mkv".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilenameTagsPolicy {
+    /// No tags are extracted from the filenames.
+    #[default]
+    Disabled,
+
+    /// The extracted tags, which the movie doesn't have yet, are returned as suggestions in the
+    /// response of the upload.
+    Suggest,
+
+    /// The extracted tags are added to the movie.
+    Apply,
+}
+
+/// The granularity of the locks that serialize the multi-step write operations on movies, e.g.,
+/// uploads. The single mutations of the index are serialized regardless of the granularity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LockGranularity {
+    /// Write operations on different movies proceed concurrently.
+    #[default]
+    PerMovie,
+
+    /// All write operations are serialized.
+    Global,
+}
+
+/// The offloading of movie downloads onto a reverse proxy in front of the service, which serves
+/// the movie files directly instead of streaming them through the service.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum DownloadOffload {
+    /// The movie files are streamed by the service.
+    #[default]
+    Disabled,
+
+    /// The response carries an `X-Accel-Redirect` header for nginx with the path of the movie
+    /// file relative to the root directory, prefixed by the given internal location, e.g.,
+    /// "/protected", under which nginx serves the root directory.
+    XAccelRedirect { location: String },
+
+    /// The response carries an `X-Sendfile` header, e.g., for Apache or lighttpd, with the
+    /// absolute path of the movie file.
+    XSendfile,
+}
+
+/// A margin at the beginning or the end of a movie, which is excluded from the screenshots, e.g.,
+/// to skip the intro and the credits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScreenshotMargin {
+    /// The margin in seconds.
+    Seconds(f64),
+
+    /// The margin as fraction of the duration of the movie, e.g., 0.1 for 10%.
+    Fraction(f64),
+}
+
+impl Default for ScreenshotMargin {
+    fn default() -> Self {
+        Self::Seconds(0.0)
+    }
+}
+
+impl ScreenshotMargin {
+    /// Returns the margin in seconds for a movie with the given duration.
+    ///
+    /// # Arguments
+    /// * `duration` - The duration of the movie in seconds.
+    pub fn to_seconds(&self, duration: f64) -> f64 {
+        match *self {
+            Self::Seconds(seconds) => seconds,
+            Self::Fraction(fraction) => fraction * duration,
+        }
+    }
+}
+
 /// The options for the service
 #[derive(Debug, Clone)]
 pub struct Options {
@@ -10,6 +136,182 @@ pub struct Options {
 
     /// The path to where ffmpeg and ffprobe are located
     pub ffmpeg: PathBuf,
+
+    /// If true, the service fails to start if ffmpeg or ffprobe are not available. Otherwise, the
+    /// service starts without generating previews.
+    pub require_ffmpeg: bool,
+
+    /// The maximal number of queued preview requests.
+    pub preview_queue_capacity: usize,
+
+    /// The policy for preview requests if the queue of preview requests is full.
+    pub preview_backpressure: PreviewBackpressurePolicy,
+
+    /// The number of seconds after which clients should request deferred previews again.
+    pub preview_retry_after: u32,
+
+    /// The policy for uploaded movie files without any video stream.
+    pub no_video_stream_policy: NoVideoStreamPolicy,
+
+    /// The handling of the tags extracted from the filenames of uploaded movie files.
+    pub filename_tags_policy: FilenameTagsPolicy,
+
+    /// The tokens extracted as tags from the filenames of uploaded movie files in addition to the
+    /// year and the resolution, e.g., "bluray". The tokens are matched case-insensitively.
+    pub filename_tag_tokens: Vec<String>,
+
+    /// The maximal number of concurrently running ffmpeg and ffprobe processes.
+    pub max_ffmpeg_processes: usize,
+
+    /// The number of seconds after which an ffmpeg or ffprobe process is killed, s.t. a corrupt
+    /// movie file can't hang the preview generation forever. 0 disables the timeout.
+    pub ffmpeg_timeout: u64,
+
+    /// The size in bytes of the buffer used for writing uploaded data to the storage.
+    /// Multipart chunks are often small, s.t. buffering them reduces the number of writes.
+    /// Defaults to 1 MiB.
+    pub upload_buffer_size: usize,
+
+    /// The maximal number of multipart fields processed per upload request. Requests with more
+    /// fields are rejected.
+    pub max_multipart_fields: usize,
+
+    /// The maximal size in bytes of an imported movie bundle, which is spooled to a temporary
+    /// file before the import, and of its data once decompressed. Larger bundles are rejected.
+    /// 0 disables the limit.
+    pub max_bundle_size: u64,
+
+    /// The maximal number of tags returned by the list of all tags, s.t. huge vocabularies don't
+    /// produce huge responses. Truncated lists are marked by the `X-Truncated` header, while the
+    /// top tags and the tag suggestions remain available. 0 disables the limit.
+    pub max_tag_list_size: usize,
+
+    /// The maximal number of pooled connections to the SQLite database, i.e., the number of
+    /// queries running in parallel.
+    pub db_pool_size: usize,
+
+    /// Optionally, the maximal number of characters (grapheme clusters) of a movie description.
+    pub max_description_length: Option<usize>,
+
+    /// The policy for descriptions exceeding the maximal description length.
+    pub description_length_policy: DescriptionLengthPolicy,
+
+    /// If true, movie files are write-once, i.e., once the upload of a movie file has completed,
+    /// any attempt to overwrite it is rejected. The metadata of the movies remains editable.
+    pub immutable_files: bool,
+
+    /// If true, tags keep their original casing when displayed. Tags are matched
+    /// case-insensitively either way. Otherwise, tags are converted to lower case.
+    pub preserve_tag_case: bool,
+
+    /// The maximal number of movies kept in the read-through cache of the movies index.
+    /// A size of zero disables the cache.
+    pub movie_cache_size: usize,
+
+    /// Optionally, the size in bytes above which descriptions are stored separately from the
+    /// remaining movie details, s.t. listing and searching movies stays fast.
+    pub description_offload_threshold: Option<usize>,
+
+    /// The duration in seconds for which clients may cache downloaded screenshots.
+    pub screenshot_cache_max_age: u32,
+
+    /// The maximal total size in bytes of the main screenshots kept in memory, s.t. they are
+    /// served without reading the storage. A size of zero disables the cache.
+    pub screenshot_memory_cache_size: usize,
+
+    /// The number of most recently added movies whose screenshots are loaded into the in-memory
+    /// screenshot cache on startup, s.t. the first page renders without reading the storage.
+    pub screenshot_warm_up_count: usize,
+
+    /// Optionally, the path to an image served instead of the screenshot of movies which have no
+    /// screenshot yet. If not set, requesting such a screenshot fails.
+    pub placeholder_screenshot: Option<PathBuf>,
+
+    /// Optionally, the secret for signing the URLs of shared movies. A valid signature grants
+    /// access to the downloads of the shared movie until it expires. If not set, movies can't be
+    /// shared. The signature is checked in place of the `Authorizer`, not of the API keys, which
+    /// only protect the mutating endpoints, s.t. the downloads stay readable without a signature
+    /// unless an `Authorizer` denies them.
+    pub share_secret: Option<String>,
+
+    /// The API keys granting access to the mutating endpoints, i.e., all requests except GET,
+    /// HEAD and OPTIONS, which must pass one of them in the `X-API-Key` header. Reading endpoints
+    /// stay open. If empty, no key is required.
+    pub api_keys: Vec<String>,
+
+    /// Optionally, the timezone used by human-facing endpoints which group movies by date, e.g.,
+    /// the number of movies added per year. Timestamps are always stored and returned in UTC,
+    /// independent of this option. Defaults to UTC.
+    pub display_timezone: Option<FixedOffset>,
+
+    /// If true, movies requested in another format than their stored format are transcoded
+    /// into MP4 while they are streamed. Transcoded streams don't support range requests.
+    pub transcode_on_the_fly: bool,
+
+    /// If true, a perceptual hash is computed for each new screenshot, which allows finding
+    /// movies with near-duplicate screenshots.
+    pub compute_screenshot_hashes: bool,
+
+    /// The number of screenshots taken of each movie at evenly spaced time stamps, e.g., at 10%,
+    /// 30%, 50%, 70% and 90% of the movie for five screenshots. The main screenshot is taken at
+    /// the preview position instead of the screenshot closest to the middle.
+    pub num_previews: usize,
+
+    /// The position of the main screenshot as fraction of the duration of each movie within
+    /// (0, 1), e.g., 0.25 to avoid black intro frames. Defaults to the middle of the movie.
+    pub preview_position: f64,
+
+    /// The margin at the beginning of each movie, which is skipped when taking screenshots, s.t.
+    /// they don't show the intro. The screenshots are evenly spaced within the remaining range.
+    pub screenshot_skip_intro: ScreenshotMargin,
+
+    /// The margin at the end of each movie, which is skipped when taking screenshots, s.t. they
+    /// don't show the credits.
+    pub screenshot_skip_outro: ScreenshotMargin,
+
+    /// The number of replaced screenshots kept per movie, s.t. a previous screenshot can be
+    /// restored, e.g., if an earlier automatically chosen frame was preferred. 0 disables the
+    /// history.
+    pub screenshot_history_size: usize,
+
+    /// If true, an animated preview, i.e., a short looping GIF clip starting at the preview
+    /// position, is generated in addition to the screenshots of each movie.
+    pub animated_preview: bool,
+
+    /// The number of seconds between two periodic flushes of buffered writes, 0 disables the
+    /// periodic flushes. Buffered writes are always flushed during the graceful shutdown.
+    pub flush_interval: u64,
+
+    /// The number of seconds after which a download is aborted if the client pulls no data,
+    /// s.t. stalled clients don't hold files and transcodes forever. 0 disables the timeout.
+    pub download_idle_timeout: u64,
+
+    /// If true, the execution times of the index queries, e.g., searches, movie lookups and tag
+    /// lists, are logged and aggregated for diagnosing slow queries. Only supported by the
+    /// SQLite index.
+    pub query_timing: bool,
+
+    /// The execution time in milliseconds from which on an index query is logged as slow, if
+    /// query timing is enabled.
+    pub slow_query_threshold: u64,
+
+    /// The offloading of movie downloads onto a reverse proxy, which only applies to storages
+    /// providing file paths. Other downloads, e.g., transcoded movies, are always streamed.
+    pub download_offload: DownloadOffload,
+
+    /// The maximal number of recent activity events kept in memory.
+    pub activity_log_capacity: usize,
+
+    /// The granularity of the locks that serialize the multi-step write operations on movies.
+    pub lock_granularity: LockGranularity,
+
+    /// The maximal number of concurrent uploads per client IP address, 0 disables the limit.
+    /// Excess uploads are rejected with 429 Too Many Requests.
+    pub max_uploads_per_client: usize,
+
+    /// The names of the libraries served in addition to the default library. Each library has its
+    /// own index and storage.
+    pub libraries: Vec<String>,
 }
 
 impl Default for Options {
@@ -18,6 +320,132 @@ impl Default for Options {
             root_dir: PathBuf::from("./"),
             http_address: SocketAddr::from(([127, 0, 0, 1], 3030)),
             ffmpeg: PathBuf::from("/usr/bin/"),
+            require_ffmpeg: true,
+            preview_queue_capacity: 1024,
+            preview_backpressure: PreviewBackpressurePolicy::Wait,
+            preview_retry_after: 60,
+            no_video_stream_policy: NoVideoStreamPolicy::FlagUnsupported,
+            filename_tags_policy: FilenameTagsPolicy::Disabled,
+            filename_tag_tokens: Vec::new(),
+            max_ffmpeg_processes: default_max_ffmpeg_processes(),
+            ffmpeg_timeout: 600,
+            upload_buffer_size: 1024 * 1024,
+            max_multipart_fields: 16,
+            max_bundle_size: 64 * 1024 * 1024 * 1024,
+            max_tag_list_size: 10_000,
+            db_pool_size: 8,
+            max_description_length: None,
+            description_length_policy: DescriptionLengthPolicy::Reject,
+            immutable_files: false,
+            preserve_tag_case: false,
+            movie_cache_size: 0,
+            description_offload_threshold: None,
+            screenshot_cache_max_age: 7 * 24 * 60 * 60,
+            screenshot_memory_cache_size: 0,
+            screenshot_warm_up_count: 100,
+            placeholder_screenshot: None,
+            share_secret: None,
+            api_keys: Vec::new(),
+            display_timezone: None,
+            transcode_on_the_fly: false,
+            compute_screenshot_hashes: false,
+            num_previews: 1,
+            preview_position: 0.5,
+            screenshot_skip_intro: ScreenshotMargin::default(),
+            screenshot_skip_outro: ScreenshotMargin::default(),
+            screenshot_history_size: 0,
+            animated_preview: false,
+            flush_interval: 30,
+            download_idle_timeout: 300,
+            query_timing: false,
+            slow_query_threshold: 100,
+            download_offload: DownloadOffload::Disabled,
+            activity_log_capacity: 256,
+            lock_granularity: LockGranularity::PerMovie,
+            max_uploads_per_client: 0,
+            libraries: Vec::new(),
+        }
+    }
+}
+
+impl Options {
+    /// Returns the options for the library with the given name, i.e., the options with the root
+    /// directory of the library. The default library is located directly in the root directory.
+    ///
+    /// # Arguments
+    /// * `library` - The name of the library.
+    pub fn for_library(&self, library: &str) -> Result<Options, Error> {
+        if library == DEFAULT_LIBRARY {
+            return Ok(self.clone());
+        }
+
+        // library names follow the same rules as movie IDs, s.t. they are safe directory names
+        if !is_valid_movie_id(library) {
+            return Err(Error::InvalidArgument(format!(
+                "Invalid library name '{}'",
+                library
+            )));
+        }
+
+        let mut options = self.clone();
+        options.root_dir = self.root_dir.join(LIBRARIES_DIR_NAME).join(library);
+        options.libraries.clear();
+
+        // the proxy serves the whole root directory, s.t. the location of the library is nested
+        if let DownloadOffload::XAccelRedirect { location } = &mut options.download_offload {
+            *location = format!(
+                "{}/{}/{}",
+                location.trim_end_matches('/'),
+                LIBRARIES_DIR_NAME,
+                library
+            );
         }
+
+        Ok(options)
+    }
+
+    /// Returns the timezone used by human-facing endpoints grouping movies by date.
+    pub fn get_display_timezone(&self) -> FixedOffset {
+        self.display_timezone
+            .unwrap_or(FixedOffset::east_opt(0).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_for_library() {
+        let options = Options {
+            root_dir: PathBuf::from("/data"),
+            libraries: vec!["home-videos".to_owned()],
+            download_offload: DownloadOffload::XAccelRedirect {
+                location: "/protected/".to_owned(),
+            },
+            ..Default::default()
+        };
+
+        let default_options = options.for_library(DEFAULT_LIBRARY).unwrap();
+        assert_eq!(default_options.root_dir, PathBuf::from("/data"));
+
+        let library_options = options.for_library("home-videos").unwrap();
+        assert_eq!(
+            library_options.root_dir,
+            PathBuf::from("/data/libraries.d/home-videos")
+        );
+        assert!(library_options.libraries.is_empty());
+        assert_eq!(
+            library_options.download_offload,
+            DownloadOffload::XAccelRedirect {
+                location: "/protected/libraries.d/home-videos".to_owned()
+            }
+        );
+
+        assert!(matches!(
+            options.for_library("../movies"),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(options.for_library("").is_err());
     }
 }
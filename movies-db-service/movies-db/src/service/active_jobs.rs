@@ -0,0 +1,97 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::{JobKind, MovieId};
+
+/// The jobs currently processing movies, e.g., uploads and transcodes. Each job is tracked until
+/// its guard is dropped.
+#[derive(Debug, Clone, Default)]
+pub struct ActiveJobs {
+    /// The number of running jobs per movie and kind.
+    jobs: Arc<Mutex<HashMap<(MovieId, JobKind), usize>>>,
+}
+
+/// A running job, which is tracked as long as the guard is alive.
+#[derive(Debug)]
+pub struct ActiveJobGuard {
+    jobs: ActiveJobs,
+    key: (MovieId, JobKind),
+}
+
+impl ActiveJobs {
+    /// Marks a job of the given kind processing the given movie as running until the returned
+    /// guard is dropped.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the processed movie.
+    /// * `kind` - The kind of the job.
+    pub fn start(&self, id: &MovieId, kind: JobKind) -> ActiveJobGuard {
+        let key = (id.clone(), kind);
+        *self.jobs.lock().unwrap().entry(key.clone()).or_insert(0) += 1;
+
+        ActiveJobGuard {
+            jobs: self.clone(),
+            key,
+        }
+    }
+
+    /// Returns the kinds of the jobs currently processing the given movie, ordered by the kind.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    pub fn get(&self, id: &MovieId) -> Vec<JobKind> {
+        let mut kinds: Vec<JobKind> = self
+            .jobs
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|(job_id, _)| job_id == id)
+            .map(|(_, kind)| *kind)
+            .collect();
+        kinds.sort();
+
+        kinds
+    }
+}
+
+impl Drop for ActiveJobGuard {
+    fn drop(&mut self) {
+        let mut jobs = self.jobs.jobs.lock().unwrap();
+        if let Some(count) = jobs.get_mut(&self.key) {
+            *count -= 1;
+            if *count == 0 {
+                jobs.remove(&self.key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_active_jobs() {
+        let jobs = ActiveJobs::default();
+        let id = "alien".to_owned();
+        let other_id = "heat".to_owned();
+
+        let transcode = jobs.start(&id, JobKind::Transcode);
+        let second_transcode = jobs.start(&id, JobKind::Transcode);
+        let upload = jobs.start(&id, JobKind::Upload);
+        let _other_upload = jobs.start(&other_id, JobKind::Upload);
+        assert_eq!(jobs.get(&id), [JobKind::Upload, JobKind::Transcode]);
+
+        // the job is tracked until its last guard is dropped
+        drop(transcode);
+        assert_eq!(jobs.get(&id), [JobKind::Upload, JobKind::Transcode]);
+        drop(second_transcode);
+        assert_eq!(jobs.get(&id), [JobKind::Upload]);
+        drop(upload);
+        assert!(jobs.get(&id).is_empty());
+
+        assert_eq!(jobs.get(&other_id), [JobKind::Upload]);
+    }
+}
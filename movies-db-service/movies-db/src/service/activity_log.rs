@@ -0,0 +1,147 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::MovieId;
+
+/// The number of events buffered for live subscribers that have not yet received them.
+const LIVE_EVENTS_CAPACITY: usize = 64;
+
+/// The kind of an activity event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    MovieAdded,
+    MovieDeleted,
+    MovieUploaded,
+    PreviewGenerated,
+    Error,
+}
+
+/// A significant event of the service, e.g., a movie has been added.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityEvent {
+    /// The time at which the event happened.
+    pub time: DateTime<Utc>,
+
+    /// The kind of the event.
+    pub kind: ActivityKind,
+
+    /// The id of the movie the event refers to, if any.
+    pub movie_id: Option<MovieId>,
+
+    /// A human readable description of the event.
+    pub message: String,
+}
+
+/// A bounded in-memory log of the most recent activity events, which additionally forwards all
+/// new events to live subscribers. It is shared between all components of a library.
+#[derive(Clone, Debug)]
+pub struct ActivityLog {
+    /// The maximal number of kept events.
+    capacity: usize,
+
+    /// The most recent events, ordered from the oldest to the newest.
+    events: Arc<Mutex<VecDeque<ActivityEvent>>>,
+
+    /// The channel for forwarding new events to live subscribers.
+    live_events: broadcast::Sender<ActivityEvent>,
+}
+
+impl ActivityLog {
+    /// Creates a new empty activity log.
+    ///
+    /// # Arguments
+    /// * `capacity` - The maximal number of kept events.
+    pub fn new(capacity: usize) -> Self {
+        let (live_events, _) = broadcast::channel(LIVE_EVENTS_CAPACITY);
+
+        Self {
+            capacity,
+            events: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            live_events,
+        }
+    }
+
+    /// Records a new event. If the log is full, the oldest event is dropped.
+    ///
+    /// # Arguments
+    /// * `kind` - The kind of the event.
+    /// * `movie_id` - The id of the movie the event refers to, if any.
+    /// * `message` - A human readable description of the event.
+    pub fn record(&self, kind: ActivityKind, movie_id: Option<&MovieId>, message: String) {
+        let event = ActivityEvent {
+            time: Utc::now(),
+            kind,
+            movie_id: movie_id.cloned(),
+            message,
+        };
+
+        if self.capacity > 0 {
+            let mut events = self.events.lock().unwrap();
+            if events.len() >= self.capacity {
+                events.pop_front();
+            }
+
+            events.push_back(event.clone());
+        }
+
+        // sending only fails if there are no live subscribers
+        let _ = self.live_events.send(event);
+    }
+
+    /// Returns the most recent events, starting with the newest one.
+    ///
+    /// # Arguments
+    /// * `limit` - The maximal number of events to return.
+    pub fn get_recent(&self, limit: usize) -> Vec<ActivityEvent> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns a receiver for all events recorded from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<ActivityEvent> {
+        self.live_events.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_activity_log() {
+        let log = ActivityLog::new(2);
+        let mut live_events = log.subscribe();
+
+        let boot = "das-boot".to_owned();
+        log.record(ActivityKind::MovieAdded, Some(&boot), "added".to_owned());
+        log.record(
+            ActivityKind::MovieUploaded,
+            Some(&boot),
+            "uploaded".to_owned(),
+        );
+        log.record(ActivityKind::Error, None, "failed".to_owned());
+
+        // only the most recent events are kept
+        let kinds: Vec<ActivityKind> = log.get_recent(10).iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, [ActivityKind::Error, ActivityKind::MovieUploaded]);
+        assert_eq!(log.get_recent(1)[0].message, "failed");
+
+        // live subscribers receive all events
+        let event = live_events.recv().await.unwrap();
+        assert_eq!(event.kind, ActivityKind::MovieAdded);
+        assert_eq!(event.movie_id, Some(boot));
+    }
+}
@@ -0,0 +1,117 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::Method,
+    middleware::Next,
+    web, HttpResponse,
+};
+use serde::Serialize;
+
+use super::share_token::constant_time_eq;
+
+/// The header carrying the API key of a request.
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+/// The body of the responses to requests rejected due to a missing or wrong API key.
+#[derive(Debug, Serialize)]
+struct ApiKeyError {
+    error: String,
+}
+
+/// The API keys granting access to the mutating endpoints, i.e., all requests except GET, HEAD
+/// and OPTIONS. Reading endpoints stay open. Without any keys, the authentication is disabled.
+#[derive(Debug, Default, Clone)]
+pub struct ApiKeys {
+    keys: Vec<String>,
+}
+
+impl ApiKeys {
+    /// Creates new API keys, empty keys are ignored.
+    ///
+    /// # Arguments
+    /// * `keys` - The keys granting access to the mutating endpoints.
+    pub fn new(keys: &[String]) -> Self {
+        Self {
+            keys: keys.iter().filter(|key| !key.is_empty()).cloned().collect(),
+        }
+    }
+
+    /// Returns true if requests with the given method require an API key. Only the reading
+    /// methods are allowed without a key, s.t. new mutating methods are protected as well.
+    ///
+    /// # Arguments
+    /// * `method` - The method of the request.
+    pub fn is_required(&self, method: &Method) -> bool {
+        !self.keys.is_empty() && ![Method::GET, Method::HEAD, Method::OPTIONS].contains(method)
+    }
+
+    /// Returns true if the given key is one of the API keys. All keys are compared, s.t. the
+    /// comparison does not reveal which key matched.
+    ///
+    /// # Arguments
+    /// * `key` - The key to check.
+    pub fn is_valid(&self, key: &str) -> bool {
+        self.keys.iter().fold(false, |valid, expected| {
+            constant_time_eq(expected.as_bytes(), key.as_bytes()) | valid
+        })
+    }
+}
+
+/// Middleware rejecting mutating requests without a valid API key in the `X-API-Key` header
+/// with 401 Unauthorized, based on the API keys registered as app data.
+///
+/// # Arguments
+/// * `req` - The request to check.
+/// * `next` - The next service in the chain.
+pub async fn require_api_key(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    if let Some(api_keys) = req.app_data::<web::Data<ApiKeys>>() {
+        if api_keys.is_required(req.method()) {
+            let error = match req.headers().get(API_KEY_HEADER) {
+                None => Some("Missing API key"),
+                Some(key) => match key.to_str() {
+                    Ok(key) if api_keys.is_valid(key) => None,
+                    _ => Some("Invalid API key"),
+                },
+            };
+
+            if let Some(error) = error {
+                let response = HttpResponse::Unauthorized().json(ApiKeyError {
+                    error: error.to_owned(),
+                });
+
+                return Ok(req.into_response(response).map_into_right_body());
+            }
+        }
+    }
+
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_api_keys() {
+        let api_keys = ApiKeys::new(&["first".to_owned(), String::new(), "second".to_owned()]);
+        assert!(api_keys.is_valid("first"));
+        assert!(api_keys.is_valid("second"));
+        assert!(!api_keys.is_valid("firs"));
+        assert!(!api_keys.is_valid(""));
+
+        assert!(api_keys.is_required(&Method::POST));
+        assert!(api_keys.is_required(&Method::PUT));
+        assert!(api_keys.is_required(&Method::DELETE));
+        assert!(api_keys.is_required(&Method::PATCH));
+        assert!(!api_keys.is_required(&Method::GET));
+        assert!(!api_keys.is_required(&Method::HEAD));
+        assert!(!api_keys.is_required(&Method::OPTIONS));
+
+        // without keys, no request requires a key
+        let api_keys = ApiKeys::new(&[String::new()]);
+        assert!(!api_keys.is_required(&Method::POST));
+    }
+}
@@ -0,0 +1,66 @@
+use std::future::{ready, Ready};
+use std::net::SocketAddr;
+
+use actix_web::dev::Payload;
+use actix_web::http::header::HeaderMap;
+use actix_web::{FromRequest, HttpRequest};
+
+use crate::MovieId;
+
+/// The operations on a single movie, which are subject to authorization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Reading the movie, e.g., its metadata, its movie file or its screenshot.
+    Read,
+
+    /// Changing the movie, e.g., uploading its movie file or requesting a new preview.
+    Update,
+
+    /// Deleting the movie.
+    Delete,
+}
+
+/// The context of a request, on which the authorization decisions are based.
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    /// The headers of the request, e.g., carrying the credentials of the user.
+    pub headers: HeaderMap,
+
+    /// The address of the client, if known.
+    pub peer_addr: Option<SocketAddr>,
+}
+
+impl FromRequest for RequestContext {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(Self {
+            headers: req.headers().clone(),
+            peer_addr: req.peer_addr(),
+        }))
+    }
+}
+
+/// The hook deciding whether a request may operate on a movie. It is invoked before each
+/// operation on a single movie, s.t. deployments can plug in their own access control, e.g.,
+/// private and shared movies, without the service baking in a specific model.
+pub trait Authorizer: Send + Sync {
+    /// Returns true if the request may perform the given action on the given movie.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context of the request.
+    /// * `id` - The id of the movie.
+    /// * `action` - The action to perform.
+    fn can_access(&self, ctx: &RequestContext, id: &MovieId, action: Action) -> bool;
+}
+
+/// The default authorizer, which allows every request.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AllowAll;
+
+impl Authorizer for AllowAll {
+    fn can_access(&self, _ctx: &RequestContext, _id: &MovieId, _action: Action) -> bool {
+        true
+    }
+}
@@ -0,0 +1,730 @@
+use std::path::Path;
+
+use async_compression::tokio::{bufread::GzipDecoder, write::GzipEncoder};
+use async_zip::{
+    tokio::{read::seek::ZipFileReader, write::ZipFileWriter},
+    Compression, ZipEntryBuilder,
+};
+use futures::AsyncReadExt;
+use log::{debug, error, trace};
+use tokio::io::{
+    AsyncBufRead, AsyncRead, AsyncReadExt as _, AsyncSeek, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio_util::compat::{FuturesAsyncReadCompatExt, TokioAsyncReadCompatExt};
+
+use crate::{Error, MovieDetailed};
+
+/// The name of the metadata entry inside of a movie bundle.
+pub const METADATA_ENTRY_NAME: &str = "metadata.json";
+
+/// The name prefix of the movie file entry inside of a movie bundle.
+pub const MOVIE_ENTRY_PREFIX: &str = "movie";
+
+/// The name prefix of the screenshot entry inside of a movie bundle.
+pub const SCREENSHOT_ENTRY_PREFIX: &str = "screenshot";
+
+/// The magic bytes at the start of every gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The size of the buffer used for decompressing bundles.
+const DECOMPRESS_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The maximal decompressed size of the metadata entry of a bundle in bytes. The metadata is kept
+/// in memory, s.t. a small compressed entry must not expand into a huge one.
+const MAX_METADATA_SIZE: u64 = 16 * 1024 * 1024;
+
+/// The compression of a downloaded bundle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BundleCompression {
+    /// The bundle is sent as plain ZIP file.
+    None,
+
+    /// The bundle is sent as gzip-compressed ZIP file, i.e., as `.zip.gz` file.
+    GzipFile,
+
+    /// The bundle is sent as ZIP file with gzip content encoding, which the client decodes
+    /// transparently.
+    GzipEncoding,
+}
+
+/// Writes the given movie as ZIP bundle into the given writer. The bundle consists of the
+/// `metadata.json` and, if available, the movie file and the screenshot.
+/// The data is streamed into the writer, i.e., the movie data is never fully kept in memory.
+///
+/// # Arguments
+/// * `writer` - The writer to write the bundle to.
+/// * `movie` - The movie whose metadata will be written to the bundle.
+/// * `movie_data` - Optionally, the reader onto the movie file.
+/// * `screenshot_data` - Optionally, the reader onto the screenshot file.
+pub async fn write_bundle<W, R>(
+    writer: W,
+    movie: &MovieDetailed,
+    movie_data: Option<R>,
+    screenshot_data: Option<R>,
+) -> Result<W, Error>
+where
+    W: AsyncWrite + Unpin,
+    R: AsyncRead + Unpin,
+{
+    let mut zip = ZipFileWriter::with_tokio(writer);
+
+    // write the metadata
+    trace!("Write bundle entry '{}'...", METADATA_ENTRY_NAME);
+    let metadata = serde_json::to_vec_pretty(movie)?;
+    let entry = ZipEntryBuilder::new(METADATA_ENTRY_NAME.into(), Compression::Stored);
+    zip.write_entry_whole(entry, &metadata).await?;
+
+    // write the movie file
+    if let (Some(info), Some(reader)) = (&movie.movie_file_info, movie_data) {
+        let name = format!("{}.{}", MOVIE_ENTRY_PREFIX, info.extension);
+        write_stream_entry(&mut zip, &name, reader).await?;
+    }
+
+    // write the screenshot file
+    if let (Some(info), Some(reader)) = (&movie.screenshot_file_info, screenshot_data) {
+        let name = format!("{}.{}", SCREENSHOT_ENTRY_PREFIX, info.extension);
+        write_stream_entry(&mut zip, &name, reader).await?;
+    }
+
+    let writer = zip.close().await?.into_inner();
+
+    debug!("Wrote bundle for movie '{}'", movie.movie.title);
+
+    Ok(writer)
+}
+
+/// Writes the given movie as gzip-compressed ZIP bundle into the given writer, see
+/// [`write_bundle`]. The data is compressed while being streamed, s.t. the memory stays bounded.
+///
+/// # Arguments
+/// * `writer` - The writer to write the compressed bundle to.
+/// * `movie` - The movie whose metadata will be written to the bundle.
+/// * `movie_data` - Optionally, the reader onto the movie file.
+/// * `screenshot_data` - Optionally, the reader onto the screenshot file.
+pub async fn write_compressed_bundle<W, R>(
+    writer: W,
+    movie: &MovieDetailed,
+    movie_data: Option<R>,
+    screenshot_data: Option<R>,
+) -> Result<W, Error>
+where
+    W: AsyncWrite + Unpin,
+    R: AsyncRead + Unpin,
+{
+    let mut encoder =
+        write_bundle(GzipEncoder::new(writer), movie, movie_data, screenshot_data).await?;
+
+    // finishes the gzip stream by writing its trailer
+    encoder.shutdown().await?;
+
+    Ok(encoder.into_inner())
+}
+
+/// Returns true if the file at the given path is gzip-compressed.
+///
+/// # Arguments
+/// * `path` - The path to the file to check.
+pub async fn is_gzip_file(path: &Path) -> Result<bool, Error> {
+    let mut file = tokio::fs::File::open(path).await?;
+
+    let mut magic = [0u8; 2];
+    match file.read_exact(&mut magic).await {
+        Ok(_) => Ok(magic == GZIP_MAGIC),
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Decompresses the gzip-compressed file at the given source path into the given destination
+/// path. The data is decompressed while being streamed, s.t. the memory stays bounded.
+/// Decompressing more than the given maximal size fails, s.t. a small file can't expand into a
+/// huge one and fill up the disk.
+///
+/// # Arguments
+/// * `src` - The path to the gzip-compressed file.
+/// * `dst` - The path to write the decompressed data to.
+/// * `max_size` - The maximal size of the decompressed data in bytes, 0 disables the limit.
+pub async fn decompress_file(src: &Path, dst: &Path, max_size: u64) -> Result<(), Error> {
+    let max_size = match max_size {
+        0 => u64::MAX,
+        max_size => max_size,
+    };
+
+    // reading one byte more than allowed tells a too large file apart from one of maximal size
+    let src_file = tokio::fs::File::open(src).await?;
+    let mut decoder = GzipDecoder::new(BufReader::new(src_file)).take(max_size.saturating_add(1));
+    let mut dst_file = tokio::fs::File::create(dst).await?;
+
+    // errors of reading and writing are told apart, s.t. e.g. a full disk isn't reported as
+    // invalid bundle
+    let mut buffer = vec![0u8; DECOMPRESS_BUFFER_SIZE];
+    let mut size: u64 = 0;
+    loop {
+        let n = decoder.read(&mut buffer).await.map_err(|err| {
+            error!("Failed to decompress bundle: {}", err);
+            Error::InvalidArgument(format!("Bundle is not a valid gzip file: {}", err))
+        })?;
+        if n == 0 {
+            break;
+        }
+
+        size += n as u64;
+        if size > max_size {
+            error!("Decompressed bundle exceeds {} bytes", max_size);
+            return Err(Error::PayloadTooLarge(format!(
+                "Decompressed bundle exceeds the maximal size of {} bytes",
+                max_size
+            )));
+        }
+
+        dst_file.write_all(&buffer[..n]).await?;
+    }
+    dst_file.flush().await?;
+
+    Ok(())
+}
+
+/// Returns true if the given value of an `Accept-Encoding` header accepts gzip, i.e., it lists
+/// `gzip` or `*` without a quality of zero.
+///
+/// # Arguments
+/// * `accept_encoding` - The value of the `Accept-Encoding` header.
+pub fn accepts_gzip(accept_encoding: &str) -> bool {
+    accept_encoding.split(',').any(|item| {
+        let mut parts = item.split(';').map(str::trim);
+        let coding = parts.next().unwrap_or_default();
+        if !coding.eq_ignore_ascii_case("gzip") && coding != "*" {
+            return false;
+        }
+
+        // the quality defaults to 1, whereas a quality of 0 rejects the coding
+        parts
+            .filter_map(|param| param.strip_prefix("q="))
+            .all(|quality| quality.parse::<f32>().is_ok_and(|q| q > 0.0))
+    })
+}
+
+/// Reader for movie bundles as written by [`write_bundle`].
+pub struct BundleReader<R>
+where
+    R: AsyncBufRead + AsyncSeek + Unpin,
+{
+    zip: ZipFileReader<R>,
+    movie: MovieDetailed,
+    movie_entry: Option<usize>,
+    screenshot_entry: Option<usize>,
+
+    /// The maximal decompressed size of the movie and the screenshot entry, 0 disables the limit.
+    max_entry_size: u64,
+}
+
+impl<R> BundleReader<R>
+where
+    R: AsyncBufRead + AsyncSeek + Unpin,
+{
+    /// Opens the bundle from the given reader and parses its metadata. Fails if the bundle is not
+    /// a valid ZIP file or if any of the entries referenced by the metadata are missing.
+    ///
+    /// # Arguments
+    /// * `reader` - The reader onto the bundle data.
+    pub async fn new(reader: R) -> Result<Self, Error> {
+        let mut zip = ZipFileReader::with_tokio(reader).await.map_err(|err| {
+            error!("Failed to open bundle: {}", err);
+            Error::InvalidArgument(format!("Bundle is not a valid ZIP file: {}", err))
+        })?;
+
+        // parse the metadata
+        let metadata_entry = Self::find_entry(&zip, METADATA_ENTRY_NAME).ok_or_else(|| {
+            error!("Bundle has no metadata");
+            Error::InvalidArgument(format!("Bundle has no '{}' entry", METADATA_ENTRY_NAME))
+        })?;
+
+        // reading one byte more than allowed tells a too large entry apart from one of maximal size
+        let mut metadata = Vec::new();
+        zip.reader_without_entry(metadata_entry)
+            .await?
+            .take(MAX_METADATA_SIZE + 1)
+            .read_to_end(&mut metadata)
+            .await?;
+        if metadata.len() as u64 > MAX_METADATA_SIZE {
+            error!("Bundle metadata exceeds {} bytes", MAX_METADATA_SIZE);
+            return Err(Error::PayloadTooLarge(format!(
+                "'{}' exceeds the maximal size of {} bytes",
+                METADATA_ENTRY_NAME, MAX_METADATA_SIZE
+            )));
+        }
+
+        let movie: MovieDetailed = serde_json::from_slice(&metadata).map_err(|err| {
+            error!("Failed to parse bundle metadata: {}", err);
+            Error::InvalidArgument(format!("Invalid '{}': {}", METADATA_ENTRY_NAME, err))
+        })?;
+
+        // find the movie and screenshot entries referenced by the metadata
+        let movie_entry = match &movie.movie_file_info {
+            Some(info) => {
+                let name = format!("{}.{}", MOVIE_ENTRY_PREFIX, info.extension);
+                Some(Self::find_required_entry(&zip, &name)?)
+            }
+            None => None,
+        };
+
+        let screenshot_entry = match &movie.screenshot_file_info {
+            Some(info) => {
+                let name = format!("{}.{}", SCREENSHOT_ENTRY_PREFIX, info.extension);
+                Some(Self::find_required_entry(&zip, &name)?)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            zip,
+            movie,
+            movie_entry,
+            screenshot_entry,
+            max_entry_size: 0,
+        })
+    }
+
+    /// Returns the reader with the given maximal decompressed size of the movie and the
+    /// screenshot entry. Copying a larger entry fails, s.t. a small bundle can't expand into a
+    /// huge file and fill up the disk.
+    ///
+    /// # Arguments
+    /// * `max_entry_size` - The maximal size of an entry in bytes, 0 disables the limit.
+    pub fn with_max_entry_size(mut self, max_entry_size: u64) -> Self {
+        self.max_entry_size = max_entry_size;
+        self
+    }
+
+    /// Returns the movie metadata stored in the bundle.
+    pub fn metadata(&self) -> &MovieDetailed {
+        &self.movie
+    }
+
+    /// Copies the movie file of the bundle into the given writer. Returns false if the bundle has
+    /// no movie file.
+    ///
+    /// # Arguments
+    /// * `writer` - The writer to copy the movie file into.
+    pub async fn copy_movie_data<W>(&mut self, writer: &mut W) -> Result<bool, Error>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        match self.movie_entry {
+            Some(index) => {
+                self.copy_entry(index, writer).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Copies the screenshot of the bundle into the given writer. Returns false if the bundle has
+    /// no screenshot.
+    ///
+    /// # Arguments
+    /// * `writer` - The writer to copy the screenshot into.
+    pub async fn copy_screenshot_data<W>(&mut self, writer: &mut W) -> Result<bool, Error>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        match self.screenshot_entry {
+            Some(index) => {
+                self.copy_entry(index, writer).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Copies the data of the entry with the given index into the given writer.
+    ///
+    /// # Arguments
+    /// * `index` - The index of the entry to copy.
+    /// * `writer` - The writer to copy the entry data into.
+    async fn copy_entry<W>(&mut self, index: usize, writer: &mut W) -> Result<(), Error>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let max_size = match self.max_entry_size {
+            0 => u64::MAX,
+            max_size => max_size,
+        };
+
+        // reading one byte more than allowed tells a too large entry apart from one of maximal size
+        let entry = self.zip.reader_without_entry(index).await?;
+        let mut entry = entry.compat().take(max_size.saturating_add(1));
+        let size = tokio::io::copy(&mut entry, writer).await?;
+        if size > max_size {
+            error!("Bundle entry exceeds {} bytes", max_size);
+            return Err(Error::PayloadTooLarge(format!(
+                "Bundle entry exceeds the maximal size of {} bytes",
+                max_size
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the index of the entry with the given name, if present.
+    ///
+    /// # Arguments
+    /// * `zip` - The ZIP file reader.
+    /// * `name` - The name of the entry to find.
+    fn find_entry(zip: &ZipFileReader<R>, name: &str) -> Option<usize> {
+        zip.file()
+            .entries()
+            .iter()
+            .position(|entry| entry.filename().as_str().ok() == Some(name))
+    }
+
+    /// Returns the index of the entry with the given name or an error if the entry is missing.
+    ///
+    /// # Arguments
+    /// * `zip` - The ZIP file reader.
+    /// * `name` - The name of the entry to find.
+    fn find_required_entry(zip: &ZipFileReader<R>, name: &str) -> Result<usize, Error> {
+        Self::find_entry(zip, name).ok_or_else(|| {
+            error!("Bundle is missing entry '{}'", name);
+            Error::InvalidArgument(format!("Incomplete bundle, missing entry '{}'", name))
+        })
+    }
+}
+
+/// Creates a file name for a bundle based on the given movie title. All characters that are not
+/// safe to be used in a file name are replaced by an underscore.
+///
+/// # Arguments
+/// * `title` - The title of the movie.
+pub fn create_bundle_filename(title: &str) -> String {
+    let name: String = title
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    let name = name.trim_matches(|c| c == '.' || c == ' ');
+
+    if name.is_empty() {
+        "movie.zip".to_owned()
+    } else {
+        format!("{}.zip", name)
+    }
+}
+
+/// Streams the data of the given reader as a new entry into the ZIP file.
+///
+/// # Arguments
+/// * `zip` - The ZIP file writer.
+/// * `name` - The name of the entry.
+/// * `reader` - The reader whose data will be written into the entry.
+async fn write_stream_entry<W, R>(
+    zip: &mut ZipFileWriter<W>,
+    name: &str,
+    reader: R,
+) -> Result<(), Error>
+where
+    W: AsyncWrite + Unpin,
+    R: AsyncRead + Unpin,
+{
+    trace!("Write bundle entry '{}'...", name);
+
+    // movies and screenshots are already compressed, so we just store them
+    let entry = ZipEntryBuilder::new(name.into(), Compression::Stored);
+    let mut entry_writer = zip.write_entry_stream(entry).await?;
+
+    futures::io::copy(&mut reader.compat(), &mut entry_writer).await?;
+    entry_writer.close().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use tempdir::TempDir;
+
+    use crate::{Movie, MovieFileInfo, PreviewState, ScreenshotInfo};
+
+    use super::*;
+
+    fn create_test_movie() -> MovieDetailed {
+        MovieDetailed {
+            movie: Movie {
+                title: "Das Boot".to_owned(),
+                description: "A German U-boat stalks the frigid waters of the North Atlantic."
+                    .to_owned(),
+                tags: vec!["drama".to_owned(), "war".to_owned()],
+            },
+            movie_file_info: Some(MovieFileInfo {
+                extension: "mp4".to_owned(),
+                mime_type: "video/mp4".to_owned(),
+                original_filename: None,
+            }),
+            screenshot_file_info: Some(ScreenshotInfo {
+                extension: "png".to_owned(),
+                mime_type: "image/png".to_owned(),
+                count: 1,
+            }),
+            date: chrono::Utc::now(),
+            preview_state: Some(PreviewState::Ready),
+            featured: false,
+            processing: false,
+            active_jobs: Vec::new(),
+            video_metadata: None,
+            animated_preview_info: None,
+            subtitle_languages: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_bundle() {
+        let movie = create_test_movie();
+        let movie_data = b"Movie Data!\n".to_vec();
+        let screenshot_data = b"Screenshot Data!\n".to_vec();
+
+        let bundle = write_bundle(
+            Vec::new(),
+            &movie,
+            Some(Cursor::new(movie_data.clone())),
+            Some(Cursor::new(screenshot_data.clone())),
+        )
+        .await
+        .unwrap();
+
+        let mut reader = ZipFileReader::with_tokio(Cursor::new(bundle))
+            .await
+            .unwrap();
+
+        let names: Vec<String> = reader
+            .file()
+            .entries()
+            .iter()
+            .map(|e| e.filename().as_str().unwrap().to_owned())
+            .collect();
+        assert_eq!(names, ["metadata.json", "movie.mp4", "screenshot.png"]);
+
+        // read all entries
+        let mut entries: Vec<Vec<u8>> = Vec::new();
+        for index in 0..names.len() {
+            let mut data = Vec::new();
+            let mut entry = reader.reader_without_entry(index).await.unwrap();
+            entry.read_to_end(&mut data).await.unwrap();
+            entries.push(data);
+        }
+
+        // check the metadata
+        let metadata: MovieDetailed = serde_json::from_slice(&entries[0]).unwrap();
+        assert_eq!(metadata.movie.title, movie.movie.title);
+        assert_eq!(metadata.movie.description, movie.movie.description);
+        assert_eq!(metadata.movie.tags, movie.movie.tags);
+        assert_eq!(metadata.movie_file_info, movie.movie_file_info);
+        assert_eq!(metadata.screenshot_file_info, movie.screenshot_file_info);
+        assert_eq!(metadata.date, movie.date);
+
+        // check the movie and screenshot data
+        assert_eq!(entries[1], movie_data);
+        assert_eq!(entries[2], screenshot_data);
+    }
+
+    #[tokio::test]
+    async fn test_write_bundle_without_files() {
+        let mut movie = create_test_movie();
+        movie.movie_file_info = None;
+        movie.screenshot_file_info = None;
+
+        let bundle = write_bundle::<_, Cursor<Vec<u8>>>(Vec::new(), &movie, None, None)
+            .await
+            .unwrap();
+
+        let reader = ZipFileReader::with_tokio(Cursor::new(bundle))
+            .await
+            .unwrap();
+        let entries = reader.file().entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].filename().as_str().unwrap(), METADATA_ENTRY_NAME);
+    }
+
+    #[tokio::test]
+    async fn test_read_bundle() {
+        let movie = create_test_movie();
+        let movie_data = b"Movie Data!\n".to_vec();
+        let screenshot_data = b"Screenshot Data!\n".to_vec();
+
+        let bundle = write_bundle(
+            Vec::new(),
+            &movie,
+            Some(Cursor::new(movie_data.clone())),
+            Some(Cursor::new(screenshot_data.clone())),
+        )
+        .await
+        .unwrap();
+
+        let mut reader = BundleReader::new(Cursor::new(bundle)).await.unwrap();
+
+        let metadata = reader.metadata();
+        assert_eq!(metadata.movie.title, movie.movie.title);
+        assert_eq!(metadata.movie.description, movie.movie.description);
+        assert_eq!(metadata.movie.tags, movie.movie.tags);
+        assert_eq!(metadata.movie_file_info, movie.movie_file_info);
+        assert_eq!(metadata.screenshot_file_info, movie.screenshot_file_info);
+
+        let mut data = Vec::new();
+        assert!(reader.copy_movie_data(&mut data).await.unwrap());
+        assert_eq!(data, movie_data);
+
+        let mut data = Vec::new();
+        assert!(reader.copy_screenshot_data(&mut data).await.unwrap());
+        assert_eq!(data, screenshot_data);
+    }
+
+    #[tokio::test]
+    async fn test_read_invalid_bundle() {
+        // not a zip file at all
+        let ret = BundleReader::new(Cursor::new(b"Hello, world!\n".to_vec())).await;
+        assert!(matches!(ret, Err(Error::InvalidArgument(_))));
+
+        // the metadata references a movie file, but the bundle does not contain it
+        let movie = create_test_movie();
+        let bundle = write_bundle::<_, Cursor<Vec<u8>>>(Vec::new(), &movie, None, None)
+            .await
+            .unwrap();
+        let ret = BundleReader::new(Cursor::new(bundle)).await;
+        assert!(matches!(ret, Err(Error::InvalidArgument(_))));
+
+        // the metadata is missing
+        let mut zip = ZipFileWriter::with_tokio(Vec::new());
+        let entry = ZipEntryBuilder::new("movie.mp4".into(), Compression::Stored);
+        zip.write_entry_whole(entry, b"Movie Data!\n")
+            .await
+            .unwrap();
+        let bundle = zip.close().await.unwrap().into_inner();
+        let ret = BundleReader::new(Cursor::new(bundle)).await;
+        assert!(matches!(ret, Err(Error::InvalidArgument(_))));
+    }
+
+    #[tokio::test]
+    async fn test_read_too_large_bundle() {
+        let movie = create_test_movie();
+        let movie_data = b"Movie Data!\n".to_vec();
+
+        let bundle = write_bundle(
+            Vec::new(),
+            &movie,
+            Some(Cursor::new(movie_data.clone())),
+            Some(Cursor::new(b"Screenshot!\n".to_vec())),
+        )
+        .await
+        .unwrap();
+
+        // the entries may have exactly the maximal size, but not more
+        let mut reader = BundleReader::new(Cursor::new(bundle.clone()))
+            .await
+            .unwrap()
+            .with_max_entry_size(movie_data.len() as u64);
+        let mut data = Vec::new();
+        assert!(reader.copy_movie_data(&mut data).await.unwrap());
+        assert_eq!(data, movie_data);
+
+        let mut reader = BundleReader::new(Cursor::new(bundle))
+            .await
+            .unwrap()
+            .with_max_entry_size(movie_data.len() as u64 - 1);
+        let ret = reader.copy_movie_data(&mut Vec::new()).await;
+        assert!(matches!(ret, Err(Error::PayloadTooLarge(_))));
+
+        // the metadata is kept in memory and thus bounded as well
+        let mut zip = ZipFileWriter::with_tokio(Vec::new());
+        let entry = ZipEntryBuilder::new(METADATA_ENTRY_NAME.into(), Compression::Stored);
+        zip.write_entry_whole(entry, &vec![b' '; MAX_METADATA_SIZE as usize + 1])
+            .await
+            .unwrap();
+        let bundle = zip.close().await.unwrap().into_inner();
+        let ret = BundleReader::new(Cursor::new(bundle)).await;
+        assert!(matches!(ret, Err(Error::PayloadTooLarge(_))));
+    }
+
+    #[tokio::test]
+    async fn test_compressed_bundle() {
+        let movie = create_test_movie();
+        let movie_data = b"Movie Data!\n".to_vec();
+        let screenshot_data = b"Screenshot Data!\n".to_vec();
+
+        let bundle = write_compressed_bundle(
+            Vec::new(),
+            &movie,
+            Some(Cursor::new(movie_data.clone())),
+            Some(Cursor::new(screenshot_data.clone())),
+        )
+        .await
+        .unwrap();
+        assert_eq!(bundle[..2], GZIP_MAGIC);
+
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let compressed_path = root_dir.path().join("bundle.zip.gz");
+        let bundle_path = root_dir.path().join("bundle.zip");
+        tokio::fs::write(&compressed_path, &bundle).await.unwrap();
+        assert!(is_gzip_file(&compressed_path).await.unwrap());
+
+        decompress_file(&compressed_path, &bundle_path, 0)
+            .await
+            .unwrap();
+        assert!(!is_gzip_file(&bundle_path).await.unwrap());
+
+        // the decompressed bundle may have exactly the maximal size, but not more
+        let size = tokio::fs::metadata(&bundle_path).await.unwrap().len();
+        let limited_path = root_dir.path().join("limited.zip");
+        decompress_file(&compressed_path, &limited_path, size)
+            .await
+            .unwrap();
+        let ret = decompress_file(&compressed_path, &limited_path, size - 1).await;
+        assert!(matches!(ret, Err(Error::PayloadTooLarge(_))));
+
+        let bundle = tokio::fs::read(&bundle_path).await.unwrap();
+        let mut reader = BundleReader::new(Cursor::new(bundle)).await.unwrap();
+        assert_eq!(reader.metadata().movie.title, movie.movie.title);
+
+        let mut data = Vec::new();
+        assert!(reader.copy_movie_data(&mut data).await.unwrap());
+        assert_eq!(data, movie_data);
+
+        // a truncated gzip stream is rejected
+        tokio::fs::write(&compressed_path, &GZIP_MAGIC)
+            .await
+            .unwrap();
+        let ret = decompress_file(&compressed_path, &bundle_path, 0).await;
+        assert!(matches!(ret, Err(Error::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn test_accepts_gzip() {
+        assert!(accepts_gzip("gzip"));
+        assert!(accepts_gzip("deflate, GZIP;q=0.5, br"));
+        assert!(accepts_gzip("*"));
+        assert!(!accepts_gzip(""));
+        assert!(!accepts_gzip("br, deflate"));
+        assert!(!accepts_gzip("gzip;q=0"));
+        assert!(!accepts_gzip("gzip; q=0.0, identity"));
+    }
+
+    #[test]
+    fn test_create_bundle_filename() {
+        assert_eq!(create_bundle_filename("Das Boot"), "Das Boot.zip");
+        assert_eq!(
+            create_bundle_filename("E.T. the Extra-Terrestrial"),
+            "E.T. the Extra-Terrestrial.zip"
+        );
+        assert_eq!(
+            create_bundle_filename("AC/DC: \"Live\""),
+            "AC_DC_ _Live_.zip"
+        );
+        assert_eq!(create_bundle_filename(" .. "), "movie.zip");
+    }
+}
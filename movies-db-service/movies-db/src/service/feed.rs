@@ -0,0 +1,94 @@
+use crate::{MovieDetailed, MovieId};
+
+/// The title of the channel of the movie feeds.
+const FEED_TITLE: &str = "Movies";
+
+/// The description of the channel of the movie feeds.
+const FEED_DESCRIPTION: &str = "The recently added movies";
+
+/// Escapes the given text for its use as text content or attribute value in XML.
+///
+/// # Arguments
+/// * `text` - The text to escape.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // control characters are not allowed in XML 1.0
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Creates an RSS 2.0 feed of the given movies, which keeps their order. Each item links to the
+/// details of the movie and, if available, encloses its screenshot.
+///
+/// # Arguments
+/// * `movies_url` - The URL of the movie endpoints, e.g., `http://localhost:3030/api/v1/movie`.
+/// * `movies` - The movies of the feed together with their ids.
+pub fn create_rss_feed(movies_url: &str, movies: &[(MovieId, MovieDetailed)]) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\">\n<channel>\n");
+    xml.push_str(&format!("<title>{}</title>\n", FEED_TITLE));
+    xml.push_str(&format!("<link>{}</link>\n", escape_xml(movies_url)));
+    xml.push_str(&format!(
+        "<description>{}</description>\n",
+        FEED_DESCRIPTION
+    ));
+
+    for (id, movie) in movies.iter() {
+        let link = escape_xml(&format!("{}?id={}", movies_url, id));
+
+        xml.push_str("<item>\n");
+        xml.push_str(&format!(
+            "<title>{}</title>\n",
+            escape_xml(&movie.movie.title)
+        ));
+        xml.push_str(&format!(
+            "<description>{}</description>\n",
+            escape_xml(&movie.movie.description)
+        ));
+        xml.push_str(&format!("<link>{}</link>\n", link));
+        xml.push_str(&format!("<guid isPermaLink=\"true\">{}</guid>\n", link));
+        xml.push_str(&format!("<pubDate>{}</pubDate>\n", movie.date.to_rfc2822()));
+
+        // the size of the screenshot is unknown without reading it, which RSS denotes by 0
+        if let Some(screenshot) = &movie.screenshot_file_info {
+            xml.push_str(&format!(
+                "<enclosure url=\"{}\" length=\"0\" type=\"{}\"/>\n",
+                escape_xml(&format!("{}/screenshot?id={}", movies_url, id)),
+                escape_xml(&screenshot.mime_type)
+            ));
+        }
+
+        xml.push_str("</item>\n");
+    }
+
+    xml.push_str("</channel>\n</rss>\n");
+
+    xml
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(escape_xml("Das Boot"), "Das Boot");
+        assert_eq!(
+            escape_xml("Tom & Jerry <\"Pilot\"> Schindler's"),
+            "Tom &amp; Jerry &lt;&quot;Pilot&quot;&gt; Schindler&apos;s"
+        );
+        assert_eq!(escape_xml("a\u{1}b\nc"), "ab\nc");
+    }
+}
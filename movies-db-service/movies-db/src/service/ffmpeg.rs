@@ -1,13 +1,231 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    process::{Output, Stdio},
+    sync::Arc,
+    time::Duration,
+};
 
+use futures::{Stream, StreamExt};
 use log::{info, trace};
-use tokio::process::Command;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    process::{Child, ChildStdout, Command},
+    sync::{OwnedSemaphorePermit, Semaphore, SemaphorePermit},
+};
+use tokio_util::{bytes::Bytes, io::ReaderStream};
 
-use crate::Error;
+use crate::{Error, VideoMetadata};
 
+/// The ffmpeg and ffprobe binaries. All clones share the same budget of concurrently running
+/// processes, which every invocation must acquire before spawning a process.
+///
+/// All arguments passed to ffmpeg and ffprobe are fixed, only the path of the movie file and the
+/// numeric timestamp of a screenshot vary. The movie file is always passed as an absolute path,
+/// s.t. neither its name nor its extension can be interpreted as an option or a protocol. The
+/// binaries are executed directly, i.e., without a shell.
+///
+/// Every invocation, except for the streamed transcoding, is bounded by an optional timeout,
+/// s.t. a corrupt file can't hang a process forever. Processes exceeding the timeout are killed.
+#[derive(Clone)]
 pub struct FFMpeg {
     ffmpeg_bin_path: PathBuf,
     ffprobe_bin_path: PathBuf,
+    processes: Arc<Semaphore>,
+    timeout: Option<Duration>,
+}
+
+/// Returns the default maximal number of concurrently running ffmpeg processes, i.e., the number
+/// of available CPUs.
+pub fn default_max_ffmpeg_processes() -> usize {
+    std::thread::available_parallelism()
+        .map(|num| num.get())
+        .unwrap_or(1)
+}
+
+/// The version info of the ffmpeg and ffprobe binaries.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FFMpegVersions {
+    /// The first line of the version info of ffmpeg.
+    pub ffmpeg: String,
+
+    /// The first line of the version info of ffprobe.
+    pub ffprobe: String,
+}
+
+/// Creates the argument for passing the given movie file to ffmpeg or ffprobe. The path is made
+/// absolute, s.t. it always starts with a separator and cannot be mistaken for an option, e.g.,
+/// `-i`, or for a protocol, e.g., `concat:`.
+///
+/// # Arguments
+/// * `movie_file` - The path to the movie file.
+fn create_input_arg(movie_file: &Path) -> Result<PathBuf, Error> {
+    std::path::absolute(movie_file).map_err(|e| {
+        Error::InvalidArgument(format!(
+            "Invalid movie file path '{}': {}",
+            movie_file.display(),
+            e
+        ))
+    })
+}
+
+/// Creates the argument for passing the given timestamp to ffmpeg. Only finite and non-negative
+/// timestamps are accepted.
+///
+/// # Arguments
+/// * `timestamp` - The timestamp in seconds.
+fn create_timestamp_arg(timestamp: f64) -> Result<String, Error> {
+    if !timestamp.is_finite() || timestamp < 0.0 {
+        return Err(Error::InvalidArgument(format!(
+            "Invalid screenshot timestamp '{}'",
+            timestamp
+        )));
+    }
+
+    Ok(timestamp.to_string())
+}
+
+/// The filter for creating an animated preview. The clip is reduced to a low frame rate and
+/// width, and a palette is generated from the clip itself, s.t. the GIF keeps its colors.
+const ANIMATED_PREVIEW_FILTER: &str =
+    "fps=10,scale=320:-2:flags=lanczos,split[s0][s1];[s0]palettegen[p];[s1][p]paletteuse";
+
+/// Checks that the given fraction of the duration of a movie lies within (0, 1), i.e., it
+/// addresses a frame strictly between the start and the end of the movie.
+///
+/// # Arguments
+/// * `fraction` - The fraction to check.
+pub fn check_screenshot_fraction(fraction: f64) -> Result<(), Error> {
+    if fraction > 0.0 && fraction < 1.0 {
+        Ok(())
+    } else {
+        Err(Error::InvalidArgument(format!(
+            "Invalid screenshot position '{}', expected a fraction within (0, 1)",
+            fraction
+        )))
+    }
+}
+
+/// The output of ffprobe listing the streams and the format of a movie file.
+#[derive(Debug, Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+
+    #[serde(default)]
+    format: Option<ProbeFormat>,
+}
+
+/// A single stream reported by ffprobe. ffprobe reports numbers with a fraction as strings.
+#[derive(Debug, Deserialize)]
+struct ProbeStream {
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    bit_rate: Option<String>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    duration: Option<String>,
+
+    #[serde(default)]
+    disposition: ProbeDisposition,
+}
+
+/// The disposition of a stream reported by ffprobe.
+#[derive(Debug, Default, Deserialize)]
+struct ProbeDisposition {
+    /// 1 if the stream is an attached picture, e.g., a cover art.
+    #[serde(default)]
+    attached_pic: u8,
+}
+
+/// The format of a movie file reported by ffprobe.
+#[derive(Debug, Deserialize)]
+struct ProbeFormat {
+    bit_rate: Option<String>,
+    duration: Option<String>,
+}
+
+/// Parses a frame rate reported by ffprobe, e.g., "30000/1001". ffprobe reports "0/0" if the
+/// frame rate is unknown.
+///
+/// # Arguments
+/// * `frame_rate` - The frame rate as fraction.
+fn parse_frame_rate(frame_rate: &str) -> Option<f64> {
+    let (numerator, denominator) = frame_rate.split_once('/')?;
+    let numerator: f64 = numerator.trim().parse().ok()?;
+    let denominator: f64 = denominator.trim().parse().ok()?;
+
+    let frame_rate = numerator / denominator;
+    (frame_rate.is_finite() && frame_rate > 0.0).then_some(frame_rate)
+}
+
+/// Parses the JSON output of ffprobe into the metadata of the first video stream. Attached
+/// pictures don't count as video streams. The bitrate and the duration of the stream fall back
+/// to the ones of the format, as some containers, e.g., MKV, only report them for the format.
+///
+/// # Arguments
+/// * `output` - The JSON output of ffprobe.
+fn parse_video_metadata(output: &[u8]) -> Result<VideoMetadata, Error> {
+    let output: ProbeOutput = serde_json::from_slice(output)
+        .map_err(|e| Error::Internal(format!("Failed to parse ffprobe output: {}", e)))?;
+
+    let stream = output
+        .streams
+        .into_iter()
+        .find(|stream| {
+            stream.codec_type.as_deref() == Some("video") && stream.disposition.attached_pic == 0
+        })
+        .ok_or_else(|| Error::InvalidArgument("The movie file has no video stream".to_owned()))?;
+
+    let (width, height) = match (stream.width, stream.height) {
+        (Some(width), Some(height)) => (width, height),
+        _ => {
+            return Err(Error::Internal(
+                "ffprobe reported no resolution of the video stream".to_owned(),
+            ))
+        }
+    };
+
+    let format = output.format;
+    let bitrate = stream
+        .bit_rate
+        .or_else(|| format.as_ref().and_then(|f| f.bit_rate.clone()))
+        .and_then(|bit_rate| bit_rate.parse::<u64>().ok());
+    let duration = stream
+        .duration
+        .or_else(|| format.as_ref().and_then(|f| f.duration.clone()))
+        .and_then(|duration| duration.parse::<f64>().ok());
+    let frame_rate = stream
+        .avg_frame_rate
+        .as_deref()
+        .and_then(parse_frame_rate)
+        .or_else(|| stream.r_frame_rate.as_deref().and_then(parse_frame_rate));
+
+    Ok(VideoMetadata {
+        width,
+        height,
+        codec: stream.codec_name.unwrap_or_default(),
+        bitrate,
+        frame_rate,
+        duration,
+    })
+}
+
+/// The state of a running transcoding, which is kept alive as long as its output is streamed.
+struct Transcoding {
+    /// The output of ffmpeg.
+    output: ReaderStream<ChildStdout>,
+
+    /// The ffmpeg process, which is killed if the stream is dropped early.
+    child: Child,
+
+    /// The permit of the ffmpeg process.
+    _process: OwnedSemaphorePermit,
+
+    /// True if the output has been streamed completely.
+    done: bool,
 }
 
 /// creates and returns the path to the ffmpeg binary.
@@ -33,52 +251,170 @@ fn create_ffprobe_bin_path(ffprobe_dir: &Path) -> PathBuf {
 }
 
 impl FFMpeg {
+    /// Creates a new instance of ffmpeg without checking the binaries.
+    ///
+    /// # Arguments
+    /// * `root_dir` - The path to the directory where the ffmpeg and ffprobe binaries are located.
+    fn from_root_dir(root_dir: &Path) -> Self {
+        Self {
+            ffmpeg_bin_path: create_ffmpeg_bin_path(root_dir),
+            ffprobe_bin_path: create_ffprobe_bin_path(root_dir),
+            processes: Arc::new(Semaphore::new(default_max_ffmpeg_processes())),
+            timeout: None,
+        }
+    }
+
     /// Creates a new instance of ffmpeg.
     ///
     /// # Arguments
     /// * `root_dir` - The path to the directory where the ffmpeg and ffprobe binaries are located.
     pub async fn new(root_dir: &Path) -> Result<Self, Error> {
-        let ffmpeg_bin_path = create_ffmpeg_bin_path(root_dir);
-        let ffprobe_bin_path = create_ffprobe_bin_path(root_dir);
+        let (ffmpeg, _) = Self::new_with_versions(root_dir).await?;
 
-        Self::check_bin(&ffmpeg_bin_path, "ffmpeg").await?;
-        Self::check_bin(&ffmpeg_bin_path, "ffprobe").await?;
+        Ok(ffmpeg)
+    }
 
-        Ok(Self {
-            ffmpeg_bin_path,
-            ffprobe_bin_path,
-        })
+    /// Creates a new instance of ffmpeg and returns it together with the version info of the
+    /// binaries, which have been probed for checking them.
+    ///
+    /// # Arguments
+    /// * `root_dir` - The path to the directory where the ffmpeg and ffprobe binaries are located.
+    pub async fn new_with_versions(root_dir: &Path) -> Result<(Self, FFMpegVersions), Error> {
+        let ffmpeg = Self::from_root_dir(root_dir);
+        let versions = ffmpeg.get_versions().await?;
+
+        Ok((ffmpeg, versions))
     }
 
-    /// Retur ns the duration of the given movie file in seconds.
-    pub async fn get_movie_duration(&self, movie_file: &Path) -> Result<f64, Error> {
-        trace!("get_movie_duration: movie_file={}", movie_file.display());
-        let output = Command::new(&self.ffprobe_bin_path)
-            .arg("-v")
-            .arg("error")
-            .arg("-show_entries")
-            .arg("format=duration")
-            .arg("-of")
-            .arg("default=noprint_wrappers=1:nokey=1")
-            .arg(movie_file)
-            .output()
-            .await
+    /// Returns the instance with the given maximal number of concurrently running ffmpeg and
+    /// ffprobe processes, which is shared by all its clones.
+    ///
+    /// # Arguments
+    /// * `max_processes` - The maximal number of concurrently running processes.
+    pub fn with_max_processes(mut self, max_processes: usize) -> Self {
+        self.processes = Arc::new(Semaphore::new(max_processes.max(1)));
+        self
+    }
+
+    /// Returns the instance with the given timeout of the ffmpeg and ffprobe invocations. The
+    /// streamed transcoding isn't bounded by the timeout, as its duration depends on the client.
+    ///
+    /// # Arguments
+    /// * `timeout` - The maximal duration of an invocation, none disables the timeout.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Runs the given future, which waits for the given binary, within the timeout.
+    ///
+    /// # Arguments
+    /// * `bin` - The path to the binary the future waits for.
+    /// * `future` - The future to run.
+    async fn with_process_timeout<T, F: std::future::Future<Output = T>>(
+        &self,
+        bin: &Path,
+        future: F,
+    ) -> Result<T, Error> {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, future).await.map_err(|_| {
+                Error::Internal(format!(
+                    "Execution of binary '{}' timed out after {:?}",
+                    bin.display(),
+                    timeout
+                ))
+            }),
+            None => Ok(future.await),
+        }
+    }
+
+    /// Executes the given command of the given binary and returns its output if it succeeds.
+    /// The process is killed if it exceeds the timeout.
+    ///
+    /// # Arguments
+    /// * `command` - The command to execute.
+    /// * `name` - The name of the binary, i.e., ffmpeg or ffprobe.
+    /// * `bin` - The path to the binary.
+    async fn run_command(
+        &self,
+        command: &mut Command,
+        name: &str,
+        bin: &Path,
+    ) -> Result<Output, Error> {
+        // dropping the pending output on a timeout kills the process
+        let output = self
+            .with_process_timeout(bin, command.kill_on_drop(true).output())
+            .await?
             .map_err(|e| {
                 Error::Internal(format!(
-                    "Failed to execute ffprobe binary '{}': {}",
-                    self.ffprobe_bin_path.display(),
+                    "Failed to execute {} binary '{}': {}",
+                    name,
+                    bin.display(),
                     e
                 ))
             })?;
 
         if !output.status.success() {
             return Err(Error::Internal(format!(
-                "Failed to execute ffprobe binary '{}': {}",
-                self.ffprobe_bin_path.display(),
+                "Failed to execute {} binary '{}': {}",
+                name,
+                bin.display(),
                 String::from_utf8_lossy(&output.stderr)
             )));
         }
 
+        Ok(output)
+    }
+
+    /// Waits until another ffmpeg or ffprobe process may be spawned. The process must not outlive
+    /// the returned permit.
+    async fn acquire_process(&self) -> Result<SemaphorePermit<'_>, Error> {
+        self.processes
+            .acquire()
+            .await
+            .map_err(|e| Error::Internal(format!("Failed to acquire ffmpeg process: {}", e)))
+    }
+
+    /// Re-probes the ffmpeg and ffprobe binaries in the given directory and returns their
+    /// current version info. Allows to detect updated binaries without restarting the service.
+    ///
+    /// # Arguments
+    /// * `root_dir` - The path to the directory where the ffmpeg and ffprobe binaries are located.
+    pub async fn recheck(root_dir: &Path) -> Result<FFMpegVersions, Error> {
+        Self::from_root_dir(root_dir).get_versions().await
+    }
+
+    /// Probes the ffmpeg and ffprobe binaries and returns their version info.
+    pub async fn get_versions(&self) -> Result<FFMpegVersions, Error> {
+        let _process = self.acquire_process().await?;
+
+        let ffmpeg = self.check_bin(&self.ffmpeg_bin_path, "ffmpeg").await?;
+        let ffprobe = self.check_bin(&self.ffprobe_bin_path, "ffprobe").await?;
+
+        Ok(FFMpegVersions { ffmpeg, ffprobe })
+    }
+
+    /// Returns the duration of the given movie file in seconds.
+    ///
+    /// # Arguments
+    /// * `movie_file` - The path to the movie file.
+    pub async fn get_movie_duration(&self, movie_file: &Path) -> Result<f64, Error> {
+        trace!("get_movie_duration: movie_file={}", movie_file.display());
+        let input = create_input_arg(movie_file)?;
+        let _process = self.acquire_process().await?;
+        let mut command = Command::new(&self.ffprobe_bin_path);
+        command
+            .arg("-v")
+            .arg("error")
+            .arg("-show_entries")
+            .arg("format=duration")
+            .arg("-of")
+            .arg("default=noprint_wrappers=1:nokey=1")
+            .arg(input);
+        let output = self
+            .run_command(&mut command, "ffprobe", &self.ffprobe_bin_path)
+            .await?;
+
         let duration = String::from_utf8_lossy(&output.stdout)
             .trim()
             .parse::<f64>()
@@ -93,6 +429,59 @@ impl FFMpeg {
         Ok(duration)
     }
 
+    /// Returns the metadata of the video stream of the given movie file, i.e., its resolution,
+    /// codec, bitrate, frame rate and duration.
+    ///
+    /// # Arguments
+    /// * `movie_file` - The path to the movie file.
+    pub async fn get_movie_metadata(&self, movie_file: &Path) -> Result<VideoMetadata, Error> {
+        trace!("get_movie_metadata: movie_file={}", movie_file.display());
+        let input = create_input_arg(movie_file)?;
+        let _process = self.acquire_process().await?;
+        let mut command = Command::new(&self.ffprobe_bin_path);
+        command
+            .arg("-v")
+            .arg("error")
+            .arg("-show_streams")
+            .arg("-show_format")
+            .arg("-of")
+            .arg("json")
+            .arg(input);
+        let output = self
+            .run_command(&mut command, "ffprobe", &self.ffprobe_bin_path)
+            .await?;
+
+        parse_video_metadata(&output.stdout)
+    }
+
+    /// Returns true if the given movie file has at least one video stream. Attached pictures,
+    /// e.g., the cover art of an audio file, don't count as video streams.
+    ///
+    /// # Arguments
+    /// * `movie_file` - The path to the movie file.
+    pub async fn has_video_stream(&self, movie_file: &Path) -> Result<bool, Error> {
+        trace!("has_video_stream: movie_file={}", movie_file.display());
+        let input = create_input_arg(movie_file)?;
+        let _process = self.acquire_process().await?;
+        let mut command = Command::new(&self.ffprobe_bin_path);
+        command
+            .arg("-v")
+            .arg("error")
+            .arg("-select_streams")
+            .arg("V")
+            .arg("-show_entries")
+            .arg("stream=index")
+            .arg("-of")
+            .arg("csv=p=0")
+            .arg(input);
+        let output = self
+            .run_command(&mut command, "ffprobe", &self.ffprobe_bin_path)
+            .await?;
+
+        // every selected video stream is reported by its index on a separate line
+        Ok(!String::from_utf8_lossy(&output.stdout).trim().is_empty())
+    }
+
     /// Creates a screenshot of the given movie file at the given timestamp.
     ///
     /// # Arguments
@@ -103,24 +492,98 @@ impl FFMpeg {
         movie_file: &Path,
         timestamp: f64,
     ) -> Result<Vec<u8>, Error> {
-        // trigger ffmpeg to create a screenshot of the given movie file at the given timestamp
-        // and return the screenshot data in png format
-        let output = Command::new(&self.ffmpeg_bin_path)
+        let mut command = self.create_screenshot_command(movie_file, timestamp)?;
+        let _process = self.acquire_process().await?;
+        let output = self
+            .run_command(&mut command, "ffmpeg", &self.ffmpeg_bin_path)
+            .await?;
+
+        Ok(output.stdout)
+    }
+
+    /// Creates an animated preview of the given movie file, i.e., a short looping GIF clip
+    /// starting at the given timestamp.
+    ///
+    /// # Arguments
+    /// * `movie_file` - The path to the movie file.
+    /// * `start` - The timestamp in seconds at which the clip starts.
+    /// * `duration` - The duration of the clip in seconds.
+    pub async fn create_animated_preview(
+        &self,
+        movie_file: &Path,
+        start: f64,
+        duration: f64,
+    ) -> Result<Vec<u8>, Error> {
+        if !duration.is_finite() || duration <= 0.0 {
+            return Err(Error::InvalidArgument(format!(
+                "Invalid animated preview duration '{}'",
+                duration
+            )));
+        }
+
+        let mut command = Command::new(&self.ffmpeg_bin_path);
+        command
             .arg("-ss")
-            .arg(timestamp.to_string())
+            .arg(create_timestamp_arg(start)?)
+            .arg("-t")
+            .arg(duration.to_string())
             .arg("-i")
-            .arg(movie_file)
-            .arg("-vframes")
-            .arg("1")
-            .arg("-q:v")
-            .arg("2")
-            .arg("-c:v")
-            .arg("png")
+            .arg(create_input_arg(movie_file)?)
+            .arg("-vf")
+            .arg(ANIMATED_PREVIEW_FILTER)
+            .arg("-loop")
+            .arg("0")
             .arg("-f")
-            .arg("image2pipe")
-            .arg("-")
-            .output()
+            .arg("gif")
+            .arg("-");
+
+        let _process = self.acquire_process().await?;
+        let output = self
+            .run_command(&mut command, "ffmpeg", &self.ffmpeg_bin_path)
+            .await?;
+
+        Ok(output.stdout)
+    }
+
+    /// Creates a screenshot of the given movie file at the given fraction of its duration, e.g.,
+    /// 0.25 for the frame after the first quarter of the movie.
+    ///
+    /// # Arguments
+    /// * `movie_file` - The path to the movie file.
+    /// * `fraction` - The fraction of the duration within (0, 1) at which to create the screenshot.
+    pub async fn create_screenshot_at_fraction(
+        &self,
+        movie_file: &Path,
+        fraction: f64,
+    ) -> Result<Vec<u8>, Error> {
+        check_screenshot_fraction(fraction)?;
+
+        let duration = self.get_movie_duration(movie_file).await?;
+        self.create_screenshot(movie_file, duration * fraction)
             .await
+    }
+
+    /// Creates a screenshot of the given movie file at the given timestamp and streams the
+    /// screenshot data directly into the given writer, i.e., without buffering the whole
+    /// screenshot in memory. Returns the number of written bytes.
+    ///
+    /// # Arguments
+    /// * `movie_file` - The path to the movie file.
+    /// * `timestamp` - The timestamp in seconds at which to create the screenshot.
+    /// * `writer` - The writer to stream the screenshot data in png format into.
+    pub async fn write_screenshot<W: AsyncWrite + Unpin>(
+        &self,
+        movie_file: &Path,
+        timestamp: f64,
+        writer: &mut W,
+    ) -> Result<u64, Error> {
+        let mut command = self.create_screenshot_command(movie_file, timestamp)?;
+        let _process = self.acquire_process().await?;
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
             .map_err(|e| {
                 Error::Internal(format!(
                     "Failed to execute ffmpeg binary '{}': {}",
@@ -129,42 +592,180 @@ impl FFMpeg {
                 ))
             })?;
 
-        if !output.status.success() {
+        let mut stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| Error::Internal("Failed to capture the output of ffmpeg".to_owned()))?;
+        let mut stderr = child.stderr.take().ok_or_else(|| {
+            Error::Internal("Failed to capture the error output of ffmpeg".to_owned())
+        })?;
+
+        // stderr must be drained concurrently, otherwise ffmpeg may block on a full pipe
+        let mut error_output = Vec::new();
+        let (written, status) = self
+            .with_process_timeout(&self.ffmpeg_bin_path, async {
+                let (written, _) = tokio::join!(
+                    async {
+                        let written = tokio::io::copy(&mut stdout, &mut *writer).await;
+
+                        // nobody reads the output anymore, s.t. ffmpeg would block on the full
+                        // pipe until the timeout, so it is killed right away
+                        if written.is_err() {
+                            let _ = child.start_kill();
+                        }
+
+                        written
+                    },
+                    stderr.read_to_end(&mut error_output)
+                );
+
+                (written, child.wait().await)
+            })
+            .await?;
+
+        // the failed write is the cause, if the process has been killed because of it
+        let written = written?;
+        let status = status?;
+        if !status.success() {
             return Err(Error::Internal(format!(
                 "Failed to execute ffmpeg binary '{}': {}",
                 self.ffmpeg_bin_path.display(),
-                String::from_utf8_lossy(&output.stderr)
+                String::from_utf8_lossy(&error_output)
             )));
         }
 
-        Ok(output.stdout)
+        writer.flush().await?;
+
+        Ok(written)
     }
 
-    /// Checks either ffmpeg or ffprobe binary.
+    /// Transcodes the given movie file into a fragmented MP4 and returns the stream of the
+    /// transcoded data. The data is produced while it is streamed, s.t. the total size isn't
+    /// known in advance and seeking within the stream isn't possible. If ffmpeg fails, the stream
+    /// ends with an error.
     ///
     /// # Arguments
-    /// * `bin` - The path to the binary to check.
-    /// * `name` - The name of the binary to check.
-    async fn check_bin(bin: &Path, name: &str) -> Result<(), Error> {
-        let output = Command::new(bin)
-            .arg("-version")
-            .output()
+    /// * `movie_file` - The path to the movie file.
+    pub async fn transcode_to_mp4(
+        &self,
+        movie_file: &Path,
+    ) -> Result<impl Stream<Item = std::io::Result<Bytes>>, Error> {
+        trace!("transcode_to_mp4: movie_file={}", movie_file.display());
+        let input = create_input_arg(movie_file)?;
+
+        // the permit is owned by the stream, as the process runs as long as it is streamed
+        let process = self
+            .processes
+            .clone()
+            .acquire_owned()
             .await
+            .map_err(|e| Error::Internal(format!("Failed to acquire ffmpeg process: {}", e)))?;
+
+        // the error output is discarded, otherwise ffmpeg may block on a full pipe
+        let mut child = Command::new(&self.ffmpeg_bin_path)
+            .arg("-v")
+            .arg("error")
+            .arg("-i")
+            .arg(input)
+            .arg("-c:v")
+            .arg("libx264")
+            .arg("-preset")
+            .arg("veryfast")
+            .arg("-c:a")
+            .arg("aac")
+            .arg("-movflags")
+            .arg("frag_keyframe+empty_moov+default_base_moof")
+            .arg("-f")
+            .arg("mp4")
+            .arg("-")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
             .map_err(|e| {
                 Error::Internal(format!(
                     "Failed to execute ffmpeg binary '{}': {}",
-                    bin.display(),
+                    self.ffmpeg_bin_path.display(),
                     e
                 ))
             })?;
 
-        if !output.status.success() {
-            return Err(Error::Internal(format!(
-                "Failed to execute ffmpeg binary '{}': {}",
-                bin.display(),
-                String::from_utf8_lossy(&output.stderr)
-            )));
-        }
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| Error::Internal("Failed to capture the output of ffmpeg".to_owned()))?;
+
+        let transcoding = Transcoding {
+            output: ReaderStream::new(stdout),
+            child,
+            _process: process,
+            done: false,
+        };
+
+        Ok(futures::stream::unfold(
+            transcoding,
+            |mut transcoding| async move {
+                if transcoding.done {
+                    return None;
+                }
+
+                if let Some(chunk) = transcoding.output.next().await {
+                    return Some((chunk, transcoding));
+                }
+
+                // the output is complete, report if ffmpeg failed
+                transcoding.done = true;
+                match transcoding.child.wait().await {
+                    Ok(status) if status.success() => None,
+                    Ok(status) => Some((
+                        Err(std::io::Error::other(format!("ffmpeg failed: {}", status))),
+                        transcoding,
+                    )),
+                    Err(err) => Some((Err(err), transcoding)),
+                }
+            },
+        ))
+    }
+
+    /// Creates the ffmpeg command for creating a screenshot of the given movie file at the given
+    /// timestamp, which writes the screenshot data in png format to stdout.
+    ///
+    /// # Arguments
+    /// * `movie_file` - The path to the movie file.
+    /// * `timestamp` - The timestamp in seconds at which to create the screenshot.
+    fn create_screenshot_command(
+        &self,
+        movie_file: &Path,
+        timestamp: f64,
+    ) -> Result<Command, Error> {
+        let mut command = Command::new(&self.ffmpeg_bin_path);
+        command
+            .arg("-ss")
+            .arg(create_timestamp_arg(timestamp)?)
+            .arg("-i")
+            .arg(create_input_arg(movie_file)?)
+            .arg("-vframes")
+            .arg("1")
+            .arg("-q:v")
+            .arg("2")
+            .arg("-c:v")
+            .arg("png")
+            .arg("-f")
+            .arg("image2pipe")
+            .arg("-");
+
+        Ok(command)
+    }
+
+    /// Checks either ffmpeg or ffprobe binary and returns the first line of its version info.
+    ///
+    /// # Arguments
+    /// * `bin` - The path to the binary to check.
+    /// * `name` - The name of the binary to check.
+    async fn check_bin(&self, bin: &Path, name: &str) -> Result<String, Error> {
+        let output = self
+            .run_command(Command::new(bin).arg("-version"), name, bin)
+            .await?;
 
         // extract first line of version info
         let output = String::from_utf8_lossy(&output.stdout);
@@ -172,6 +773,369 @@ impl FFMpeg {
 
         info!("{} Version Info: {}", name, output);
 
-        Ok(())
+        Ok(output.to_owned())
+    }
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod test {
+    use std::os::unix::fs::PermissionsExt;
+
+    use tempdir::TempDir;
+
+    use super::*;
+
+    /// The name of the file that is created if two ffprobe processes overlap.
+    const OVERLAP_FILE_NAME: &str = "overlap";
+
+    /// The check of the fake binaries for answering the version probe.
+    const VERSION_CHECK: &str =
+        "if [ \"$1\" = \"-version\" ]; then echo \"version test\"; exit 0; fi";
+
+    /// Writes a fake binary with the given name and script into the given directory.
+    fn write_fake_binary(dir: &Path, name: &str, script: &str) {
+        let file_path = dir.join(name);
+        let script = format!("#!/bin/sh\n{}\n{}\n", VERSION_CHECK, script);
+        std::fs::write(&file_path, script).unwrap();
+        std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    /// A writer failing on every write, e.g., like a file on a full disk.
+    struct FailingWriter;
+
+    impl AsyncWrite for FailingWriter {
+        fn poll_write(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            _buf: &[u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            std::task::Poll::Ready(Err(std::io::Error::other("write failed")))
+        }
+
+        fn poll_flush(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Writes fake ffmpeg and ffprobe binaries into the given directory. The fake ffprobe takes a
+    /// while and records whether it ran concurrently to another ffprobe process.
+    fn write_fake_binaries(dir: &Path) {
+        let ffprobe_script = format!(
+            "mkdir '{running}' 2>/dev/null || touch '{overlap}'\nsleep 0.1\nrmdir '{running}'\necho 10.0",
+            running = dir.join("running").display(),
+            overlap = dir.join(OVERLAP_FILE_NAME).display()
+        );
+
+        write_fake_binary(dir, "ffmpeg", "");
+        write_fake_binary(dir, "ffprobe", &ffprobe_script);
+    }
+
+    /// Writes fake ffmpeg and ffprobe binaries into the given directory, which record their
+    /// arguments line by line into the files `<name>.args`.
+    fn write_recording_binaries(dir: &Path) {
+        for (name, output) in [("ffmpeg", "png"), ("ffprobe", "10.0")] {
+            let script = format!(
+                "printf '%s\\n' \"$@\" > '{}'\necho {}",
+                dir.join(format!("{}.args", name)).display(),
+                output
+            );
+            write_fake_binary(dir, name, &script);
+        }
+    }
+
+    /// Returns the arguments recorded by the fake binary with the given name.
+    fn read_recorded_args(dir: &Path, name: &str) -> Vec<String> {
+        std::fs::read_to_string(dir.join(format!("{}.args", name)))
+            .unwrap()
+            .lines()
+            .map(|line| line.to_owned())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_missing_ffprobe() {
+        let bin_dir = TempDir::new("movies-db").unwrap();
+        write_fake_binary(bin_dir.path(), "ffmpeg", "");
+
+        // the error names the missing ffprobe binary instead of the present ffmpeg binary
+        match FFMpeg::new(bin_dir.path()).await {
+            Ok(_) => panic!("Creating ffmpeg without ffprobe must fail"),
+            Err(err) => {
+                let ffprobe_bin_path = create_ffprobe_bin_path(bin_dir.path());
+                assert!(err
+                    .to_string()
+                    .contains(&format!("ffprobe binary '{}'", ffprobe_bin_path.display())));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_new_with_versions() {
+        let bin_dir = TempDir::new("movies-db").unwrap();
+        write_fake_binaries(bin_dir.path());
+
+        let (_, versions) = FFMpeg::new_with_versions(bin_dir.path()).await.unwrap();
+        assert_eq!(versions.ffmpeg, "version test");
+        assert_eq!(versions.ffprobe, "version test");
+    }
+
+    #[tokio::test]
+    async fn test_write_screenshot_failed_write() {
+        let bin_dir = TempDir::new("movies-db").unwrap();
+        write_fake_binaries(bin_dir.path());
+
+        // the fake ffmpeg writes an endless screenshot, i.e., it only stops once it is killed
+        write_fake_binary(bin_dir.path(), "ffmpeg", "exec yes");
+
+        let ffmpeg = FFMpeg::new(bin_dir.path()).await.unwrap();
+        let movie_file = bin_dir.path().join("movie.mp4");
+
+        let ret = tokio::time::timeout(
+            Duration::from_secs(10),
+            ffmpeg.write_screenshot(&movie_file, 1.0, &mut FailingWriter),
+        )
+        .await
+        .expect("ffmpeg has not been killed after the failed write");
+        assert!(matches!(ret, Err(Error::IO(_))));
+    }
+
+    #[tokio::test]
+    async fn test_max_processes() {
+        let bin_dir = TempDir::new("movies-db").unwrap();
+        write_fake_binaries(bin_dir.path());
+
+        let ffmpeg = FFMpeg::new(bin_dir.path())
+            .await
+            .unwrap()
+            .with_max_processes(1);
+        let movie_file = bin_dir.path().join("movie.mp4");
+
+        // the clones share the limit, s.t. the calls are serialized
+        let calls = (0..4).map(|_| {
+            let ffmpeg = ffmpeg.clone();
+            let movie_file = movie_file.clone();
+            tokio::spawn(async move { ffmpeg.get_movie_duration(&movie_file).await })
+        });
+        for duration in futures::future::join_all(calls).await {
+            assert_eq!(duration.unwrap().unwrap(), 10.0);
+        }
+
+        assert!(!bin_dir.path().join(OVERLAP_FILE_NAME).exists());
+        assert_eq!(ffmpeg.processes.available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let bin_dir = TempDir::new("movies-db").unwrap();
+        let finished_file = bin_dir.path().join("finished");
+        let script = format!("sleep 1\ntouch '{}'", finished_file.display());
+        write_fake_binary(bin_dir.path(), "ffmpeg", &script);
+        write_fake_binary(bin_dir.path(), "ffprobe", &script);
+
+        let ffmpeg = FFMpeg::new(bin_dir.path())
+            .await
+            .unwrap()
+            .with_timeout(Some(Duration::from_millis(100)));
+        let movie_file = bin_dir.path().join("movie.mp4");
+
+        let start = std::time::Instant::now();
+        let ret = ffmpeg.get_movie_duration(&movie_file).await;
+        assert!(matches!(ret, Err(Error::Internal(_))));
+        let ret = ffmpeg.create_screenshot(&movie_file, 1.0).await;
+        assert!(matches!(ret, Err(Error::Internal(_))));
+        let mut screenshot = Vec::new();
+        let ret = ffmpeg
+            .write_screenshot(&movie_file, 1.0, &mut screenshot)
+            .await;
+        assert!(matches!(ret, Err(Error::Internal(_))));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // the killed processes never finish their script
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!finished_file.exists());
+        assert_eq!(
+            ffmpeg.processes.available_permits(),
+            default_max_ffmpeg_processes()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_arguments_cannot_be_injected() {
+        let bin_dir = TempDir::new("movies-db").unwrap();
+        write_recording_binaries(bin_dir.path());
+        let ffmpeg = FFMpeg::new(bin_dir.path()).await.unwrap();
+
+        // a movie file whose name and extension contain shell-special characters and options
+        let movie_file = PathBuf::from("-i x; rm -rf ~ `id` $(id) | movie.mp4 -f null");
+        let input = std::env::current_dir()
+            .unwrap()
+            .join(&movie_file)
+            .display()
+            .to_string();
+
+        assert_eq!(ffmpeg.get_movie_duration(&movie_file).await.unwrap(), 10.0);
+        assert_eq!(
+            read_recorded_args(bin_dir.path(), "ffprobe"),
+            [
+                "-v",
+                "error",
+                "-show_entries",
+                "format=duration",
+                "-of",
+                "default=noprint_wrappers=1:nokey=1",
+                input.as_str()
+            ]
+        );
+
+        assert!(ffmpeg.has_video_stream(&movie_file).await.unwrap());
+        assert_eq!(
+            read_recorded_args(bin_dir.path(), "ffprobe"),
+            [
+                "-v",
+                "error",
+                "-select_streams",
+                "V",
+                "-show_entries",
+                "stream=index",
+                "-of",
+                "csv=p=0",
+                input.as_str()
+            ]
+        );
+
+        let screenshot = ffmpeg.create_screenshot(&movie_file, 1.5).await.unwrap();
+        assert_eq!(screenshot, b"png\n");
+        assert_eq!(
+            read_recorded_args(bin_dir.path(), "ffmpeg"),
+            [
+                "-ss",
+                "1.5",
+                "-i",
+                input.as_str(),
+                "-vframes",
+                "1",
+                "-q:v",
+                "2",
+                "-c:v",
+                "png",
+                "-f",
+                "image2pipe",
+                "-"
+            ]
+        );
+
+        // only finite and non-negative timestamps are passed on
+        for timestamp in [f64::NAN, f64::INFINITY, -1.0] {
+            let ret = ffmpeg.create_screenshot(&movie_file, timestamp).await;
+            assert!(matches!(ret, Err(Error::InvalidArgument(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_screenshot_at_fraction() {
+        let bin_dir = TempDir::new("movies-db").unwrap();
+        write_recording_binaries(bin_dir.path());
+        let ffmpeg = FFMpeg::new(bin_dir.path()).await.unwrap();
+        let movie_file = bin_dir.path().join("movie.mp4");
+
+        // the fake ffprobe reports a duration of 10 seconds
+        let screenshot = ffmpeg
+            .create_screenshot_at_fraction(&movie_file, 0.25)
+            .await
+            .unwrap();
+        assert_eq!(screenshot, b"png\n");
+        assert_eq!(
+            read_recorded_args(bin_dir.path(), "ffmpeg")[..2],
+            ["-ss", "2.5"]
+        );
+
+        // only fractions strictly between the start and the end of the movie are accepted
+        for fraction in [0.0, 1.0, -0.5, 1.5, f64::NAN, f64::INFINITY] {
+            let ret = ffmpeg
+                .create_screenshot_at_fraction(&movie_file, fraction)
+                .await;
+            assert!(matches!(ret, Err(Error::InvalidArgument(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_animated_preview() {
+        let bin_dir = TempDir::new("movies-db").unwrap();
+        write_recording_binaries(bin_dir.path());
+        let ffmpeg = FFMpeg::new(bin_dir.path()).await.unwrap();
+        let movie_file = bin_dir.path().join("movie.mp4");
+
+        let preview = ffmpeg
+            .create_animated_preview(&movie_file, 5.0, 3.0)
+            .await
+            .unwrap();
+        assert_eq!(preview, b"png\n");
+
+        let args = read_recorded_args(bin_dir.path(), "ffmpeg");
+        assert_eq!(args[..4], ["-ss", "5", "-t", "3"]);
+        assert_eq!(args[args.len() - 3..], ["-f", "gif", "-"]);
+        assert!(args.iter().any(|arg| arg.contains("palettegen")));
+
+        // only finite and positive durations and valid start timestamps are passed on
+        for (start, duration) in [(0.0, 0.0), (0.0, -1.0), (0.0, f64::NAN), (-1.0, 3.0)] {
+            let ret = ffmpeg
+                .create_animated_preview(&movie_file, start, duration)
+                .await;
+            assert!(matches!(ret, Err(Error::InvalidArgument(_))));
+        }
+    }
+
+    #[test]
+    fn test_parse_video_metadata() {
+        // the cover art is skipped and the bitrate falls back to the one of the format
+        let output = br#"{
+            "streams": [
+                {"codec_type": "audio", "codec_name": "aac", "bit_rate": "128000"},
+                {
+                    "codec_type": "video", "codec_name": "mjpeg", "width": 600, "height": 600,
+                    "disposition": {"attached_pic": 1}
+                },
+                {
+                    "codec_type": "video", "codec_name": "h264", "width": 1920,
+                    "height": 1080, "avg_frame_rate": "30000/1001", "r_frame_rate": "30/1",
+                    "duration": "5940.500000", "disposition": {"attached_pic": 0}
+                }
+            ],
+            "format": {"duration": "5941.000000", "bit_rate": "4500000"}
+        }"#;
+        let metadata = parse_video_metadata(output).unwrap();
+        assert_eq!(metadata.width, 1920);
+        assert_eq!(metadata.height, 1080);
+        assert_eq!(metadata.codec, "h264");
+        assert_eq!(metadata.bitrate, Some(4_500_000));
+        assert!((metadata.frame_rate.unwrap() - 29.97).abs() < 0.01);
+        assert_eq!(metadata.duration, Some(5940.5));
+
+        // unknown frame rates are reported as 0/0
+        assert_eq!(parse_frame_rate("0/0"), None);
+        assert_eq!(parse_frame_rate("25/1"), Some(25.0));
+        assert_eq!(parse_frame_rate("25"), None);
+
+        // audio-only files and invalid output are rejected
+        let output = br#"{"streams": [{"codec_type": "audio", "codec_name": "mp3"}]}"#;
+        assert!(matches!(
+            parse_video_metadata(output),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            parse_video_metadata(b"10.0"),
+            Err(Error::Internal(_))
+        ));
     }
 }
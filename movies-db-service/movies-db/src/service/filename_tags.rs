@@ -0,0 +1,114 @@
+use std::path::Path;
+
+/// The resolution tokens recognized in filenames.
+const RESOLUTION_TOKENS: &[&str] = &[
+    "480p", "576p", "720p", "1080p", "1440p", "2160p", "4320p", "4k", "8k",
+];
+
+/// The range of the years recognized in filenames.
+const YEAR_RANGE: std::ops::RangeInclusive<u16> = 1880..=2099;
+
+/// The metadata extracted from the filename of an uploaded movie file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilenameTags {
+    /// The year of the movie, if the filename contains one.
+    pub year: Option<u16>,
+
+    /// The resolution of the movie, e.g., "1080p", if the filename contains one.
+    pub resolution: Option<String>,
+
+    /// The configured additional tokens found in the filename, in lower case.
+    pub tokens: Vec<String>,
+}
+
+impl FilenameTags {
+    /// Returns the extracted metadata as candidate tags, i.e., the year, the resolution and the
+    /// additional tokens.
+    pub fn into_tags(self) -> Vec<String> {
+        let mut tags = Vec::with_capacity(self.tokens.len() + 2);
+        tags.extend(self.year.map(|year| year.to_string()));
+        tags.extend(self.resolution);
+        tags.extend(self.tokens);
+
+        tags
+    }
+}
+
+/// Extracts the year, the resolution and the given additional tokens from the given filename,
+/// e.g., "Alien.1979.BluRay.1080p.mkv". The filename without its extension is split into tokens
+/// at every non-alphanumeric character and the tokens are matched case-insensitively.
+/// A year is a token of four digits in a plausible range, where the last one wins s.t. titles
+/// starting with a number, e.g., "2001.A.Space.Odyssey.1968", are handled.
+///
+/// # Arguments
+/// * `filename` - The filename of the uploaded movie file.
+/// * `extra_tokens` - The additional tokens to extract, e.g., "bluray".
+pub fn extract_filename_tags(filename: &str, extra_tokens: &[String]) -> FilenameTags {
+    let stem = Path::new(filename)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(filename);
+
+    let mut result = FilenameTags::default();
+    for token in stem
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+    {
+        let token = token.to_lowercase();
+
+        if token.len() == 4 && token.chars().all(|c| c.is_ascii_digit()) {
+            if let Ok(year) = token.parse::<u16>() {
+                if YEAR_RANGE.contains(&year) {
+                    result.year = Some(year);
+                    continue;
+                }
+            }
+        }
+
+        if result.resolution.is_none() && RESOLUTION_TOKENS.contains(&token.as_str()) {
+            result.resolution = Some(token);
+            continue;
+        }
+
+        if !result.tokens.contains(&token)
+            && extra_tokens
+                .iter()
+                .any(|extra_token| extra_token.to_lowercase() == token)
+        {
+            result.tokens.push(token);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_extract_filename_tags() {
+        let extra_tokens = vec!["BluRay".to_owned(), "x264".to_owned(), "hdr".to_owned()];
+
+        let tags = extract_filename_tags("Alien.1979.BluRay.1080p.x264.mkv", &extra_tokens);
+        assert_eq!(tags.year, Some(1979));
+        assert_eq!(tags.resolution.as_deref(), Some("1080p"));
+        assert_eq!(tags.tokens, ["bluray", "x264"]);
+        assert_eq!(tags.into_tags(), ["1979", "1080p", "bluray", "x264"]);
+
+        // the last year wins, s.t. a number in the title is not taken for the year
+        let tags = extract_filename_tags("2001 A Space Odyssey (1968) [4K HDR].mp4", &extra_tokens);
+        assert_eq!(tags.year, Some(1968));
+        assert_eq!(tags.resolution.as_deref(), Some("4k"));
+        assert_eq!(tags.tokens, ["hdr"]);
+
+        // implausible years and unknown tokens are ignored
+        let tags = extract_filename_tags("das_boot-0042-directors_cut.avi", &extra_tokens);
+        assert_eq!(tags, FilenameTags::default());
+        assert!(tags.into_tags().is_empty());
+
+        // without additional tokens, only the year and the resolution are extracted
+        let tags = extract_filename_tags("Heat.1995.720p.BluRay.mkv", &[]);
+        assert_eq!(tags.into_tags(), ["1995", "720p"]);
+    }
+}
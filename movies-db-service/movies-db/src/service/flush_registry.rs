@@ -0,0 +1,140 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use log::{debug, error};
+use tokio_util::sync::CancellationToken;
+
+use crate::Error;
+
+/// A subsystem that buffers writes, e.g., batched counters, and persists them on request.
+#[async_trait]
+pub trait Flushable: Send + Sync {
+    /// Returns the name of the subsystem used for logging.
+    fn name(&self) -> &str;
+
+    /// Persists all buffered writes.
+    async fn flush(&self) -> Result<(), Error>;
+}
+
+/// The registry of all subsystems with buffered writes. The buffered writes are flushed
+/// periodically as a backstop and once more during the graceful shutdown, s.t. no write is lost
+/// when the service stops.
+#[derive(Clone, Default)]
+pub struct FlushRegistry {
+    flushables: Arc<Mutex<Vec<Arc<dyn Flushable>>>>,
+}
+
+impl FlushRegistry {
+    /// Registers the given subsystem, s.t. its buffered writes are flushed together with all
+    /// other registered subsystems.
+    ///
+    /// # Arguments
+    /// * `flushable` - The subsystem to register.
+    pub fn register(&self, flushable: Arc<dyn Flushable>) {
+        debug!("Registering '{}' for flushing", flushable.name());
+        self.flushables.lock().unwrap().push(flushable);
+    }
+
+    /// Flushes the buffered writes of all registered subsystems. A failing subsystem doesn't
+    /// prevent flushing the others, but its error is returned.
+    pub async fn flush_all(&self) -> Result<(), Error> {
+        // the list is copied, s.t. the lock isn't held while flushing
+        let flushables: Vec<Arc<dyn Flushable>> = self.flushables.lock().unwrap().clone();
+
+        let mut result = Ok(());
+        for flushable in flushables {
+            if let Err(err) = flushable.flush().await {
+                error!("Failed to flush '{}': {}", flushable.name(), err);
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Flushes all registered subsystems in the given interval until the given token is
+    /// cancelled.
+    ///
+    /// # Arguments
+    /// * `interval` - The interval between two flushes.
+    /// * `cancellation` - The token for stopping the periodic flushes.
+    pub async fn run_periodic_flush(&self, interval: Duration, cancellation: CancellationToken) {
+        let mut ticker = tokio::time::interval(interval);
+
+        // the first tick completes immediately, but there is nothing to flush yet
+        ticker.tick().await;
+
+        loop {
+            tokio::select! {
+                _ = cancellation.cancelled() => break,
+                _ = ticker.tick() => {
+                    // errors are already logged and the next flush tries again
+                    let _ = self.flush_all().await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+
+    /// A counter that buffers its increments until they are flushed.
+    #[derive(Default)]
+    pub(crate) struct BatchedCounter {
+        pub(crate) pending: AtomicU64,
+        pub(crate) persisted: AtomicU64,
+    }
+
+    #[async_trait]
+    impl Flushable for BatchedCounter {
+        fn name(&self) -> &str {
+            "batched counter"
+        }
+
+        async fn flush(&self) -> Result<(), Error> {
+            let pending = self.pending.swap(0, Ordering::SeqCst);
+            self.persisted.fetch_add(pending, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_periodic_flush() {
+        let registry = FlushRegistry::default();
+        let counter = Arc::new(BatchedCounter::default());
+        registry.register(counter.clone());
+
+        let cancellation = CancellationToken::new();
+        let handle = {
+            let registry = registry.clone();
+            let cancellation = cancellation.clone();
+            tokio::spawn(async move {
+                registry
+                    .run_periodic_flush(Duration::from_millis(10), cancellation)
+                    .await
+            })
+        };
+
+        counter.pending.fetch_add(3, Ordering::SeqCst);
+        for _ in 0..100 {
+            if counter.persisted.load(Ordering::SeqCst) == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(counter.persisted.load(Ordering::SeqCst), 3);
+        assert_eq!(counter.pending.load(Ordering::SeqCst), 0);
+
+        cancellation.cancel();
+        handle.await.unwrap();
+    }
+}
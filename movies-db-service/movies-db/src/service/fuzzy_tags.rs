@@ -0,0 +1,111 @@
+use std::cmp::Reverse;
+
+/// The maximal number of edits between a tag and its correction.
+const MAX_EDIT_DISTANCE: usize = 2;
+
+/// The number of characters of a tag per allowed edit, s.t. short tags, e.g., "war", are not
+/// corrected into unrelated ones, e.g., "usa".
+const CHARS_PER_EDIT: usize = 4;
+
+/// Returns the Levenshtein distance between the given strings, i.e., the minimal number of
+/// inserted, deleted or substituted characters to turn one into the other.
+///
+/// # Arguments
+/// * `a` - The first string.
+/// * `b` - The second string.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+
+    // the distances between the processed prefix of a and all prefixes of b
+    let mut distances: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = distances[0];
+        distances[0] = i + 1;
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != *b_char);
+            diagonal = distances[j + 1];
+            distances[j + 1] = substitution.min(distances[j] + 1).min(distances[j + 1] + 1);
+        }
+    }
+
+    distances[b.len()]
+}
+
+/// Returns the maximal edit distance for correcting the given tag, which grows with its length.
+///
+/// # Arguments
+/// * `tag` - The tag to correct.
+pub fn max_edit_distance(tag: &str) -> usize {
+    (tag.chars().count() / CHARS_PER_EDIT).min(MAX_EDIT_DISTANCE)
+}
+
+/// Returns the known tag closest to the given tag within its maximal edit distance, if any.
+/// Equally close tags are ordered by their count and then alphabetically.
+///
+/// # Arguments
+/// * `tag` - The lower case tag to correct.
+/// * `known_tags` - The lower case known tags together with their counts.
+pub fn correct_tag<'a>(tag: &str, known_tags: &'a [(String, usize)]) -> Option<&'a str> {
+    let max_distance = max_edit_distance(tag);
+
+    known_tags
+        .iter()
+        .map(|(known_tag, count)| (edit_distance(tag, known_tag), Reverse(*count), known_tag))
+        .filter(|(distance, _, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, _, known_tag)| known_tag.as_str())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("", "war"), 3);
+        assert_eq!(edit_distance("germany", "germany"), 0);
+        assert_eq!(edit_distance("germeny", "germany"), 1);
+        assert_eq!(edit_distance("sciencefiction", "science fiction"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("über", "uber"), 1);
+    }
+
+    #[test]
+    fn test_correct_tag() {
+        let known_tags: Vec<(String, usize)> = [
+            ("science fiction", 3),
+            ("germany", 2),
+            ("drama", 4),
+            ("dramedy", 1),
+            ("usa", 5),
+            ("war", 1),
+        ]
+        .iter()
+        .map(|(tag, count)| (tag.to_string(), *count))
+        .collect();
+
+        assert_eq!(
+            correct_tag("sciencefiction", &known_tags),
+            Some("science fiction")
+        );
+        assert_eq!(correct_tag("germeny", &known_tags), Some("germany"));
+
+        // short tags allow fewer edits, s.t. they are not corrected into unrelated tags
+        assert_eq!(max_edit_distance("war"), 0);
+        assert_eq!(max_edit_distance("drama"), 1);
+        assert_eq!(max_edit_distance("sciencefiction"), 2);
+        assert_eq!(correct_tag("wat", &known_tags), None);
+        assert_eq!(correct_tag("drame", &known_tags), Some("drama"));
+        assert_eq!(correct_tag("dramxdy", &known_tags), Some("dramedy"));
+        assert_eq!(correct_tag("grmny", &known_tags), None);
+        assert_eq!(correct_tag("fiction", &known_tags), None);
+
+        // equally close tags are ordered by their count and then alphabetically
+        let known_tags = vec![("horror".to_owned(), 1), ("terror".to_owned(), 3)];
+        assert_eq!(correct_tag("herror", &known_tags), Some("terror"));
+        let known_tags = vec![("horror".to_owned(), 2), ("terror".to_owned(), 2)];
+        assert_eq!(correct_tag("herror", &known_tags), Some("horror"));
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    cell::RefCell,
+    io,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::Stream;
+use log::warn;
+use tokio::time::Instant;
+
+/// A download stream, which is aborted if the client pulls no data within the idle timeout.
+/// A stalled client doesn't poll the stream anymore, hence a separate watchdog task drops the
+/// wrapped stream on timeout, s.t. its file handle or transcode is freed right away. The next
+/// poll, if any, fails with a timeout error.
+pub struct IdleTimeoutStream<S> {
+    state: Rc<RefCell<IdleState<S>>>,
+}
+
+/// The state shared between the stream and its watchdog.
+struct IdleState<S> {
+    /// The wrapped stream, which is none once it is finished or aborted.
+    stream: Option<Pin<Box<S>>>,
+
+    /// The point in time the client pulled data for the last time.
+    last_pull: Instant,
+
+    /// True if the stream has been aborted and the timeout is not yet reported.
+    timed_out: bool,
+}
+
+impl<S> IdleTimeoutStream<S>
+where
+    S: Stream + 'static,
+{
+    /// Wraps the given stream and spawns its watchdog onto the current local task set, e.g., the
+    /// actix worker serving the request.
+    ///
+    /// # Arguments
+    /// * `stream` - The stream to wrap.
+    /// * `timeout` - The idle timeout, none disables the timeout.
+    pub fn new(stream: S, timeout: Option<Duration>) -> Self {
+        let state = Rc::new(RefCell::new(IdleState {
+            stream: Some(Box::pin(stream)),
+            last_pull: Instant::now(),
+            timed_out: false,
+        }));
+
+        if let Some(timeout) = timeout {
+            let state = Rc::downgrade(&state);
+            actix_web::rt::spawn(async move {
+                let mut deadline = Instant::now() + timeout;
+                loop {
+                    tokio::time::sleep_until(deadline).await;
+
+                    // the stream is gone once the response has been dropped
+                    let state = match state.upgrade() {
+                        Some(state) => state,
+                        None => break,
+                    };
+                    let mut state = state.borrow_mut();
+                    if state.stream.is_none() {
+                        break;
+                    }
+
+                    deadline = state.last_pull + timeout;
+                    if deadline <= Instant::now() {
+                        warn!("Aborting download idle for more than {:?}", timeout);
+                        state.stream = None;
+                        state.timed_out = true;
+                        break;
+                    }
+                }
+            });
+        }
+
+        Self { state }
+    }
+}
+
+impl<S, T> Stream for IdleTimeoutStream<S>
+where
+    S: Stream<Item = io::Result<T>>,
+{
+    type Item = io::Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.state.borrow_mut();
+        let state = &mut *state;
+        state.last_pull = Instant::now();
+
+        let item = match state.stream.as_mut() {
+            Some(stream) => match stream.as_mut().poll_next(cx) {
+                Poll::Ready(item) => item,
+                Poll::Pending => return Poll::Pending,
+            },
+            None if state.timed_out => {
+                state.timed_out = false;
+                return Poll::Ready(Some(Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "The download has been idle for too long",
+                ))));
+            }
+            None => return Poll::Ready(None),
+        };
+
+        // release the wrapped stream as soon as it is finished
+        if item.is_none() {
+            state.stream = None;
+        }
+
+        Poll::Ready(item)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use futures::StreamExt;
+
+    use super::*;
+
+    /// Sets the flag when it is dropped.
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Returns an endless stream of chunks and the flag which is set once the stream is dropped.
+    fn create_stream() -> (impl Stream<Item = io::Result<Vec<u8>>>, Arc<AtomicBool>) {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let flag = DropFlag(dropped.clone());
+        let stream = futures::stream::repeat(vec![0u8; 16]).map(move |chunk| {
+            let _flag = &flag;
+            Ok(chunk)
+        });
+
+        (stream, dropped)
+    }
+
+    #[actix_web::test]
+    async fn test_idle_timeout() {
+        let timeout = Duration::from_millis(50);
+
+        // a stalled consumer gets the stream dropped after the timeout
+        let (stream, dropped) = create_stream();
+        let mut stream = IdleTimeoutStream::new(stream, Some(timeout));
+        assert!(stream.next().await.unwrap().is_ok());
+        tokio::time::sleep(timeout * 3).await;
+        assert!(dropped.load(Ordering::SeqCst));
+
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(stream.next().await.is_none());
+
+        // a consumer pulling steadily keeps the stream alive
+        let (stream, dropped) = create_stream();
+        let mut stream = IdleTimeoutStream::new(stream, Some(timeout));
+        for _ in 0..6 {
+            assert!(stream.next().await.unwrap().is_ok());
+            tokio::time::sleep(timeout / 5).await;
+        }
+        assert!(!dropped.load(Ordering::SeqCst));
+
+        // without timeout, the stream is never aborted
+        let (stream, dropped) = create_stream();
+        let mut stream = IdleTimeoutStream::new(stream, None);
+        assert!(stream.next().await.unwrap().is_ok());
+        tokio::time::sleep(timeout * 3).await;
+        assert!(!dropped.load(Ordering::SeqCst));
+        assert!(stream.next().await.unwrap().is_ok());
+    }
+}
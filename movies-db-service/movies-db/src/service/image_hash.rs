@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use image::{imageops::FilterType, DynamicImage};
+use log::debug;
+use tokio::sync::RwLock;
+
+use crate::{Error, MovieId, MoviesIndex};
+
+/// The width of the grayscale image the difference hash is computed from. Each row yields one
+/// bit less than its width, i.e., 8 bits per row.
+const HASH_IMAGE_WIDTH: u32 = 9;
+
+/// The height of the grayscale image the difference hash is computed from.
+const HASH_IMAGE_HEIGHT: u32 = 8;
+
+/// Computes the perceptual difference hash (dHash) of the given image data. The image is scaled
+/// down to 9x8 grayscale pixels and each bit of the hash tells whether a pixel is brighter than
+/// its right neighbour. Thus, visually similar images, e.g., different encodes of the same
+/// frame, have hashes with a small Hamming distance.
+///
+/// # Arguments
+/// * `data` - The encoded image data, e.g., in png format.
+pub fn compute_dhash(data: &[u8]) -> Result<u64, Error> {
+    let image = image::load_from_memory(data)?;
+
+    Ok(compute_image_dhash(&image))
+}
+
+/// Computes the perceptual difference hash of the given image.
+///
+/// # Arguments
+/// * `image` - The image to compute the hash for.
+fn compute_image_dhash(image: &DynamicImage) -> u64 {
+    let pixels = image
+        .resize_exact(HASH_IMAGE_WIDTH, HASH_IMAGE_HEIGHT, FilterType::Triangle)
+        .into_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..HASH_IMAGE_HEIGHT {
+        for x in 0..HASH_IMAGE_WIDTH - 1 {
+            let left = pixels.get_pixel(x, y)[0];
+            let right = pixels.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+
+    hash
+}
+
+/// Returns the number of differing bits of the given hashes.
+///
+/// # Arguments
+/// * `lhs` - The first hash.
+/// * `rhs` - The second hash.
+pub fn hamming_distance(lhs: u64, rhs: u64) -> u32 {
+    (lhs ^ rhs).count_ones()
+}
+
+/// Clusters the given movies by the similarity of their screenshot hashes. Two movies belong to
+/// the same cluster if their hashes are within the given Hamming distance, either directly or
+/// transitively via other movies. Only clusters with at least two movies are returned.
+///
+/// # Arguments
+/// * `hashes` - The movies together with their screenshot hashes.
+/// * `threshold` - The maximal Hamming distance of similar hashes.
+pub fn cluster_similar(hashes: &[(MovieId, u64)], threshold: u32) -> Vec<Vec<MovieId>> {
+    // union-find forest, where each movie points to its parent and the roots denote the clusters
+    let mut parents: Vec<usize> = (0..hashes.len()).collect();
+
+    for i in 0..hashes.len() {
+        for j in (i + 1)..hashes.len() {
+            if hamming_distance(hashes[i].1, hashes[j].1) <= threshold {
+                let (root_i, root_j) = (find_root(&mut parents, i), find_root(&mut parents, j));
+                if root_i != root_j {
+                    parents[root_j] = root_i;
+                }
+            }
+        }
+    }
+
+    let mut clusters: HashMap<usize, Vec<MovieId>> = HashMap::new();
+    for (i, (id, _)) in hashes.iter().enumerate() {
+        let root = find_root(&mut parents, i);
+        clusters.entry(root).or_default().push(id.clone());
+    }
+
+    let mut result: Vec<Vec<MovieId>> = clusters
+        .into_values()
+        .filter(|ids| ids.len() > 1)
+        .map(|mut ids| {
+            ids.sort();
+            ids
+        })
+        .collect();
+
+    result.sort();
+
+    result
+}
+
+/// Returns the root of the tree of the given node in the given union-find forest and compresses
+/// the path to the root, s.t. subsequent lookups are fast.
+///
+/// # Arguments
+/// * `parents` - The parent of each node, where the roots are their own parents.
+/// * `node` - The node to find the root for.
+fn find_root(parents: &mut [usize], node: usize) -> usize {
+    let mut root = node;
+    while parents[root] != root {
+        root = parents[root];
+    }
+
+    let mut node = node;
+    while parents[node] != root {
+        let parent = parents[node];
+        parents[node] = root;
+        node = parent;
+    }
+
+    root
+}
+
+/// Computes the perceptual hash of the given screenshot and stores it in the index.
+///
+/// # Arguments
+/// * `index` - The movies index to store the hash in.
+/// * `id` - The id of the movie.
+/// * `data` - The encoded screenshot data.
+pub async fn store_screenshot_hash<I: MoviesIndex>(
+    index: &Arc<RwLock<I>>,
+    id: &MovieId,
+    data: Vec<u8>,
+) -> Result<(), Error> {
+    // decoding and scaling the screenshot is CPU bound and must not block the async runtime
+    let hash = tokio::task::spawn_blocking(move || compute_dhash(&data))
+        .await
+        .map_err(|err| Error::Internal(format!("Failed to compute screenshot hash: {}", err)))??;
+    debug!("Screenshot hash of movie {}: {:016x}", id, hash);
+
+    index.write().await.update_screenshot_hash(id, hash).await
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use image::{codecs::jpeg::JpegEncoder, ImageFormat, Rgb, RgbImage};
+
+    use super::*;
+
+    /// Creates a test frame with a diagonal gradient and a bright square.
+    fn create_frame(width: u32, height: u32, mirrored: bool) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| {
+            let x = if mirrored { width - 1 - x } else { x };
+            let value = ((x + y) * 255 / (width + height)) as u8;
+            if x > width / 4 && x < width / 2 && y > height / 4 && y < height / 2 {
+                Rgb([255, 255, 255])
+            } else {
+                Rgb([value, value / 2, 255 - value])
+            }
+        })
+    }
+
+    /// Encodes the given frame in png format.
+    fn encode_png(frame: &RgbImage) -> Vec<u8> {
+        let mut data = Vec::new();
+        frame
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    /// Encodes the given frame in jpeg format with a low quality.
+    fn encode_jpeg(frame: &RgbImage) -> Vec<u8> {
+        let mut data = Vec::new();
+        JpegEncoder::new_with_quality(&mut data, 30)
+            .encode_image(frame)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        assert_eq!(hamming_distance(0, 0), 0);
+        assert_eq!(hamming_distance(0b1011, 0b0001), 2);
+        assert_eq!(hamming_distance(0, u64::MAX), 64);
+    }
+
+    #[test]
+    fn test_cluster_similar_transitive() {
+        // "a" and "c" differ by two bits, but are linked via "b"
+        let hashes = [
+            ("d".to_owned(), 0xf0),
+            ("c".to_owned(), 0b11),
+            ("e".to_owned(), 0xf1),
+            ("a".to_owned(), 0b00),
+            ("b".to_owned(), 0b01),
+            ("f".to_owned(), 0xff00),
+        ];
+        assert_eq!(
+            cluster_similar(&hashes, 1),
+            [
+                vec!["a".to_owned(), "b".to_owned(), "c".to_owned()],
+                vec!["d".to_owned(), "e".to_owned()],
+            ]
+        );
+        assert!(cluster_similar(&[], 1).is_empty());
+    }
+
+    #[test]
+    fn test_similar_encodes() {
+        // two encodes of the same frame in different formats and resolutions
+        let frame = create_frame(640, 360, false);
+        let png = compute_dhash(&encode_png(&frame)).unwrap();
+        let jpeg = compute_dhash(&encode_jpeg(&image::imageops::resize(
+            &frame,
+            320,
+            180,
+            FilterType::Triangle,
+        )))
+        .unwrap();
+
+        // another frame
+        let other = compute_dhash(&encode_png(&create_frame(640, 360, true))).unwrap();
+
+        assert!(hamming_distance(png, jpeg) <= 5);
+        assert!(hamming_distance(png, other) > 20);
+
+        let hashes = [
+            ("das-boot".to_owned(), png),
+            ("alien".to_owned(), other),
+            ("das-boot-hd".to_owned(), jpeg),
+        ];
+        assert_eq!(
+            cluster_similar(&hashes, 10),
+            [["das-boot".to_owned(), "das-boot-hd".to_owned()]]
+        );
+        assert!(cluster_similar(&hashes, 0).len() <= 1);
+        assert_eq!(
+            cluster_similar(&hashes, 64),
+            [[
+                "alien".to_owned(),
+                "das-boot".to_owned(),
+                "das-boot-hd".to_owned()
+            ]]
+        );
+
+        assert!(compute_dhash(b"not an image").is_err());
+    }
+}
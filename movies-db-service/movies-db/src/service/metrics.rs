@@ -0,0 +1,222 @@
+use actix_web::{
+    body::{BodySize, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header, StatusCode},
+    middleware::Next,
+    web,
+};
+use prometheus::{
+    exponential_buckets, Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+use crate::Error;
+
+/// The route label of the requests not matching any route.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// The metrics of the service for monitoring, e.g., alerting when the preview generation falls
+/// behind the uploads. The metrics are exposed in the Prometheus text format.
+pub struct Metrics {
+    registry: Registry,
+
+    /// The number of handled requests per route and method.
+    requests: IntCounterVec,
+
+    /// The number of failed requests per route and method, i.e., with a 4xx or 5xx status.
+    errors: IntCounterVec,
+
+    /// The sizes of the uploaded and downloaded files in bytes.
+    transfer_bytes: HistogramVec,
+
+    /// The number of queued or in-flight preview requests per library.
+    preview_queue_length: IntGaugeVec,
+}
+
+impl Metrics {
+    /// Creates new metrics, which are all zero.
+    pub fn new() -> Result<Self, Error> {
+        let requests = IntCounterVec::new(
+            Opts::new(
+                "movies_db_http_requests_total",
+                "The number of handled requests",
+            ),
+            &["route", "method"],
+        )?;
+        let errors = IntCounterVec::new(
+            Opts::new(
+                "movies_db_http_errors_total",
+                "The number of requests answered with an error status",
+            ),
+            &["route", "method"],
+        )?;
+
+        // from 1 KiB up to 16 GiB
+        let transfer_bytes = HistogramVec::new(
+            HistogramOpts::new(
+                "movies_db_transfer_bytes",
+                "The sizes of the uploaded and downloaded files in bytes",
+            )
+            .buckets(exponential_buckets(1024.0, 4.0, 13)?),
+            &["direction"],
+        )?;
+        let preview_queue_length = IntGaugeVec::new(
+            Opts::new(
+                "movies_db_preview_queue_length",
+                "The number of queued or in-flight preview requests",
+            ),
+            &["library"],
+        )?;
+
+        let registry = Registry::new();
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(errors.clone()))?;
+        registry.register(Box::new(transfer_bytes.clone()))?;
+        registry.register(Box::new(preview_queue_length.clone()))?;
+
+        Ok(Self {
+            registry,
+            requests,
+            errors,
+            transfer_bytes,
+            preview_queue_length,
+        })
+    }
+
+    /// Returns the gauge for the length of the preview queue of the given library.
+    ///
+    /// # Arguments
+    /// * `library` - The name of the library.
+    pub fn preview_queue_length(&self, library: &str) -> IntGauge {
+        self.preview_queue_length.with_label_values(&[library])
+    }
+
+    /// Records a handled request.
+    ///
+    /// # Arguments
+    /// * `route` - The pattern of the matched route, e.g., `/api/v1/movie`.
+    /// * `method` - The method of the request.
+    /// * `status` - The status of the response.
+    pub fn record_request(&self, route: &str, method: &str, status: StatusCode) {
+        self.requests.with_label_values(&[route, method]).inc();
+        if status.is_client_error() || status.is_server_error() {
+            self.errors.with_label_values(&[route, method]).inc();
+        }
+    }
+
+    /// Records the size of an uploaded file.
+    ///
+    /// # Arguments
+    /// * `bytes` - The size of the upload in bytes.
+    pub fn record_upload(&self, bytes: u64) {
+        self.transfer_bytes
+            .with_label_values(&["upload"])
+            .observe(bytes as f64);
+    }
+
+    /// Records the size of a downloaded file.
+    ///
+    /// # Arguments
+    /// * `bytes` - The size of the download in bytes.
+    pub fn record_download(&self, bytes: u64) {
+        self.transfer_bytes
+            .with_label_values(&["download"])
+            .observe(bytes as f64);
+    }
+
+    /// Returns the metrics in the Prometheus text format.
+    pub fn encode(&self) -> Result<String, Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        String::from_utf8(buffer).map_err(|err| Error::Internal(err.to_string()))
+    }
+}
+
+/// Returns the value of the given header as number, if present and valid.
+///
+/// # Arguments
+/// * `headers` - The headers to read the value from.
+/// * `name` - The name of the header.
+fn get_header_number(headers: &header::HeaderMap, name: header::HeaderName) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.parse().ok()
+}
+
+/// Middleware recording each request in the metrics registered as app data. Uploads are
+/// multipart requests, whose size is taken from their `Content-Length`. Downloads are responses
+/// with a known size, except for JSON responses, s.t. streamed responses like bundles are not
+/// recorded.
+///
+/// # Arguments
+/// * `req` - The request to record.
+/// * `next` - The next service in the chain.
+pub async fn record_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let metrics = req.app_data::<web::Data<Metrics>>().cloned();
+    let method = req.method().clone();
+
+    let is_multipart = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/"));
+    let upload_size = if is_multipart {
+        get_header_number(req.headers(), header::CONTENT_LENGTH)
+    } else {
+        None
+    };
+
+    let res = next.call(req).await?;
+
+    if let Some(metrics) = metrics {
+        let route = res.request().match_pattern();
+        metrics.record_request(
+            route.as_deref().unwrap_or(UNMATCHED_ROUTE),
+            method.as_str(),
+            res.status(),
+        );
+
+        if let Some(bytes) = upload_size.filter(|_| res.status().is_success()) {
+            metrics.record_upload(bytes);
+        }
+
+        let is_json = res
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/json"));
+        if let BodySize::Sized(bytes) = res.response().body().size() {
+            if res.status().is_success() && !is_json && bytes > 0 {
+                metrics.record_download(bytes);
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_metrics() {
+        let metrics = Metrics::new().unwrap();
+
+        metrics.record_request("/api/v1/movie", "GET", StatusCode::OK);
+        metrics.record_request("/api/v1/movie", "GET", StatusCode::NOT_FOUND);
+        metrics.record_upload(4096);
+        metrics.preview_queue_length("default").set(3);
+
+        let text = metrics.encode().unwrap();
+        assert!(text
+            .contains("movies_db_http_requests_total{method=\"GET\",route=\"/api/v1/movie\"} 2"));
+        assert!(
+            text.contains("movies_db_http_errors_total{method=\"GET\",route=\"/api/v1/movie\"} 1")
+        );
+        assert!(text.contains("movies_db_transfer_bytes_count{direction=\"upload\"} 1"));
+        assert!(text.contains("movies_db_preview_queue_length{library=\"default\"} 3"));
+    }
+}
@@ -1,6 +1,27 @@
+mod active_jobs;
+mod activity_log;
+mod api_keys;
+mod authorizer;
+mod bundle;
+mod feed;
 pub mod ffmpeg;
+mod filename_tags;
+mod flush_registry;
+mod fuzzy_tags;
+mod idle_timeout;
+mod image_hash;
+mod metrics;
+mod movie_locks;
 mod preview_generator;
+mod screenshot_cache;
+mod screenshot_resizer;
 mod service_handler;
 mod service_impl;
+mod share_token;
+mod startup_report;
+mod upload_limits;
 
+pub use authorizer::{Action, AllowAll, Authorizer, RequestContext};
+pub use flush_registry::{FlushRegistry, Flushable};
 pub use service_impl::*;
+pub use startup_report::{StartupCheck, StartupReport};
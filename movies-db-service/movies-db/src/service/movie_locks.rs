@@ -0,0 +1,120 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+use crate::{LockGranularity, MovieId};
+
+/// The key of the single lock used for all movies with a global lock granularity.
+const GLOBAL_LOCK_KEY: &str = "";
+
+/// A map of locks for serializing the multi-step write operations on the same movie, e.g., an
+/// upload storing the movie file and updating the index afterwards, while such operations on
+/// different movies may proceed concurrently. The locks don't replace the lock of the index, i.e.,
+/// the single index mutations are still serialized across all movies, but keep the slow steps,
+/// e.g., streaming the movie data, of different movies from waiting for each other. With a global
+/// lock granularity, all movies share the same lock.
+pub struct MovieLocks {
+    /// The granularity of the locks.
+    granularity: LockGranularity,
+
+    /// The locks of the movies. Locks which are not held anymore are removed lazily.
+    locks: Mutex<HashMap<MovieId, Arc<AsyncMutex<()>>>>,
+}
+
+impl MovieLocks {
+    /// Creates a new empty map of movie locks.
+    ///
+    /// # Arguments
+    /// * `granularity` - The granularity of the locks.
+    pub fn new(granularity: LockGranularity) -> Self {
+        Self {
+            granularity,
+            locks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Acquires the lock for the given movie and waits until it is available. The lock is held
+    /// until the returned guard is dropped.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to lock.
+    pub async fn lock(&self, id: &MovieId) -> OwnedMutexGuard<()> {
+        let key = match self.granularity {
+            LockGranularity::Global => GLOBAL_LOCK_KEY,
+            LockGranularity::PerMovie => id.as_str(),
+        };
+
+        let lock = {
+            let mut locks = self.locks.lock().unwrap();
+            match locks.get(key) {
+                Some(lock) => lock.clone(),
+                None => {
+                    // only the map itself refers to locks which are neither held nor awaited
+                    locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+
+                    let lock = Arc::new(AsyncMutex::new(()));
+                    locks.insert(key.to_owned(), lock.clone());
+                    lock
+                }
+            }
+        };
+
+        lock.lock_owned().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Returns true if the lock for the given movie can be acquired within a short time.
+    async fn can_lock(locks: &MovieLocks, id: &str) -> bool {
+        tokio::time::timeout(Duration::from_millis(50), locks.lock(&id.to_owned()))
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_per_movie_locks() {
+        let locks = Arc::new(MovieLocks::new(LockGranularity::PerMovie));
+
+        let guard = locks.lock(&"das-boot".to_owned()).await;
+
+        // writes to different movies run concurrently, writes to the same movie serialize
+        assert!(can_lock(&locks, "alien").await);
+        assert!(!can_lock(&locks, "das-boot").await);
+
+        // the waiting write proceeds as soon as the first write is done
+        let waiting = {
+            let locks = locks.clone();
+            tokio::spawn(async move {
+                locks.lock(&"das-boot".to_owned()).await;
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        drop(guard);
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // unused locks are removed again
+        assert!(can_lock(&locks, "heat").await);
+        assert_eq!(locks.locks.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_global_lock() {
+        let locks = MovieLocks::new(LockGranularity::Global);
+
+        let _guard = locks.lock(&"das-boot".to_owned()).await;
+        assert!(!can_lock(&locks, "alien").await);
+    }
+}
@@ -1,16 +1,28 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
-use log::{debug, error, info, trace};
+use log::{debug, error, info, trace, warn};
+use prometheus::IntGauge;
 use tokio::{
     io::AsyncWriteExt,
     sync::{mpsc, RwLock},
 };
 
+use super::activity_log::{ActivityKind, ActivityLog};
+use super::image_hash::store_screenshot_hash;
+use super::screenshot_cache::ScreenshotCache;
 use crate::{
-    ffmpeg::FFMpeg, MovieDataType, MovieId, MovieSearchQuery, MovieStorage, MoviesIndex,
-    ScreenshotInfo,
+    ffmpeg::{check_screenshot_fraction, FFMpeg},
+    AnimatedPreviewInfo, Error, MovieDataType, MovieId, MovieSearchQuery, MovieStorage,
+    MoviesIndex, PreviewState, ScreenshotInfo, ScreenshotMargin,
 };
 
+/// The duration of the animated preview in seconds.
+const ANIMATED_PREVIEW_DURATION: f64 = 3.0;
+
 /// The request to generate a preview.
 #[derive(Clone, Debug)]
 pub struct ScreenshotRequest {
@@ -19,12 +31,188 @@ pub struct ScreenshotRequest {
     pub ext: String,
 }
 
+/// Returns the time stamps in seconds at which the screenshots of a movie with the given
+/// duration are taken, ordered by their index. The first time stamp, which is used for the main
+/// screenshot, lies at the given position within the movie without the skipped intro and outro.
+/// The other time stamps are evenly spaced within that range, e.g., at 10%, 30%, 70% and 90% of
+/// the range for five screenshots, where the main screenshot replaces the one closest to the
+/// middle. If the margins leave no range, they are ignored. The time stamps always lie within
+/// [0, duration). If the duration is unknown, zero or invalid, only a single time stamp for the
+/// first frame is returned.
+///
+/// # Arguments
+/// * `duration` - The duration of the movie in seconds, if known.
+/// * `count` - The number of screenshots to take, at least one screenshot is always taken.
+/// * `position` - The position of the main screenshot as fraction of the range, e.g., 0.5.
+/// * `skip_intro` - The margin at the beginning of the movie to skip.
+/// * `skip_outro` - The margin at the end of the movie to skip.
+fn get_screenshot_time_stamps(
+    duration: Option<f64>,
+    count: usize,
+    position: f64,
+    skip_intro: ScreenshotMargin,
+    skip_outro: ScreenshotMargin,
+) -> Vec<f64> {
+    let duration = match duration {
+        Some(duration) if duration.is_finite() && duration > 0.0 => duration,
+        _ => return vec![0.0],
+    };
+
+    // the range of the movie the screenshots are taken from
+    let start = skip_intro.to_seconds(duration);
+    let end = duration - skip_outro.to_seconds(duration);
+    let (start, end) = if start.is_finite() && end.is_finite() && 0.0 <= start && start < end {
+        (start, end.min(duration))
+    } else {
+        (0.0, duration)
+    };
+
+    let count = count.max(1);
+    let time_stamp = |position: f64| {
+        let time_stamp = start + (end - start) * position;
+        if (0.0..duration).contains(&time_stamp) {
+            time_stamp
+        } else {
+            0.0
+        }
+    };
+
+    let middle = count / 2;
+    std::iter::once(position)
+        .chain(
+            (0..count)
+                .filter(|i| *i != middle)
+                .map(|i| (2 * i + 1) as f64 / (2 * count) as f64),
+        )
+        .map(time_stamp)
+        .collect()
+}
+
+/// Returns the time stamp in seconds at which the animated preview of a movie with the given
+/// duration starts. The preview starts at the main screenshot, but is moved towards the start of
+/// the movie if it would otherwise end after the movie.
+///
+/// # Arguments
+/// * `main_time_stamp` - The time stamp of the main screenshot in seconds.
+/// * `duration` - The duration of the movie in seconds, if known.
+fn get_animated_preview_start(main_time_stamp: f64, duration: Option<f64>) -> f64 {
+    match duration {
+        Some(duration) if duration.is_finite() => main_time_stamp
+            .min(duration - ANIMATED_PREVIEW_DURATION)
+            .max(0.0),
+        _ => main_time_stamp,
+    }
+}
+
+/// Moves the current main screenshot of the given movie into its screenshot history before it
+/// is replaced and returns whether it has been archived. Failing to archive the screenshot
+/// doesn't prevent replacing it.
+///
+/// # Arguments
+/// * `index` - The movie index.
+/// * `storage` - The movie storage.
+/// * `movie_id` - The id of the movie whose screenshot is replaced.
+/// * `history_size` - The maximal number of screenshots in the history, 0 disables the history.
+pub async fn archive_screenshot<I: MoviesIndex, S: MovieStorage>(
+    index: &Arc<RwLock<I>>,
+    storage: &Arc<RwLock<S>>,
+    movie_id: &MovieId,
+    history_size: usize,
+) -> bool {
+    if history_size == 0 {
+        return false;
+    }
+
+    let ext = match index.read().await.get_movie(movie_id).await {
+        Ok(movie) => match movie.screenshot_file_info {
+            Some(screenshot_info) => screenshot_info.extension,
+            None => return false,
+        },
+        Err(err) => {
+            warn!("Failed to get screenshot of movie '{}': {}", movie_id, err);
+            return false;
+        }
+    };
+
+    match storage
+        .read()
+        .await
+        .archive_screenshot(movie_id.clone(), &ext, history_size)
+        .await
+    {
+        Ok(()) => true,
+        Err(err) => {
+            warn!(
+                "Failed to archive screenshot of movie '{}': {}",
+                movie_id, err
+            );
+            false
+        }
+    }
+}
+
+/// The movies with queued or in-flight preview requests, shared between the senders of preview
+/// requests and the preview generator.
+#[derive(Clone, Debug, Default)]
+pub struct PendingPreviews {
+    /// The number of queued or in-flight preview requests per movie.
+    requests: Arc<Mutex<HashMap<MovieId, usize>>>,
+}
+
+impl PendingPreviews {
+    /// Marks a preview request for the given movie as pending.
+    ///
+    /// # Arguments
+    /// * `movie_id` - The id of the movie whose preview has been requested.
+    pub fn add(&self, movie_id: &MovieId) {
+        *self
+            .requests
+            .lock()
+            .unwrap()
+            .entry(movie_id.clone())
+            .or_insert(0) += 1;
+    }
+
+    /// Marks a preview request for the given movie as done, i.e., it is no longer pending.
+    ///
+    /// # Arguments
+    /// * `movie_id` - The id of the movie whose preview request is done.
+    pub fn remove(&self, movie_id: &MovieId) {
+        let mut requests = self.requests.lock().unwrap();
+        if let Some(count) = requests.get_mut(movie_id) {
+            *count -= 1;
+            if *count == 0 {
+                requests.remove(movie_id);
+            }
+        }
+    }
+
+    /// Returns true if a preview request for the given movie is queued or in-flight.
+    ///
+    /// # Arguments
+    /// * `movie_id` - The id of the movie to check.
+    pub fn contains(&self, movie_id: &MovieId) -> bool {
+        self.requests.lock().unwrap().contains_key(movie_id)
+    }
+}
+
 pub struct PreviewGenerator<I: MoviesIndex, S: MovieStorage> {
     ffmpeg: FFMpeg,
     index: Arc<RwLock<I>>,
     storage: Arc<RwLock<S>>,
-    recv_preview: mpsc::UnboundedReceiver<ScreenshotRequest>,
-    send_preview: mpsc::UnboundedSender<ScreenshotRequest>,
+    recv_preview: mpsc::Receiver<ScreenshotRequest>,
+    send_preview: mpsc::Sender<ScreenshotRequest>,
+    pending_previews: PendingPreviews,
+    activity_log: ActivityLog,
+    compute_screenshot_hashes: bool,
+    num_previews: usize,
+    screenshot_skip_intro: ScreenshotMargin,
+    screenshot_skip_outro: ScreenshotMargin,
+    preview_position: f64,
+    screenshot_history_size: usize,
+    animated_preview: bool,
+    queue_length: Option<IntGauge>,
+    screenshot_cache: ScreenshotCache,
 }
 
 impl<I: MoviesIndex, S: MovieStorage> PreviewGenerator<I, S> {
@@ -34,8 +222,16 @@ impl<I: MoviesIndex, S: MovieStorage> PreviewGenerator<I, S> {
     /// * `ffmpeg` - The ffmpeg instance.
     /// * `index` - The movie index.
     /// * `storage` - The movie storage.
-    pub fn new(ffmpeg: FFMpeg, index: Arc<RwLock<I>>, storage: Arc<RwLock<S>>) -> Self {
-        let (send_preview, recv_preview) = mpsc::unbounded_channel();
+    /// * `capacity` - The maximal number of queued preview requests.
+    /// * `activity_log` - The log for recording the generated previews.
+    pub fn new(
+        ffmpeg: FFMpeg,
+        index: Arc<RwLock<I>>,
+        storage: Arc<RwLock<S>>,
+        capacity: usize,
+        activity_log: ActivityLog,
+    ) -> Self {
+        let (send_preview, recv_preview) = mpsc::channel(capacity.max(1));
 
         Self {
             ffmpeg,
@@ -43,165 +239,1083 @@ impl<I: MoviesIndex, S: MovieStorage> PreviewGenerator<I, S> {
             storage,
             recv_preview,
             send_preview: send_preview.clone(),
+            pending_previews: PendingPreviews::default(),
+            activity_log,
+            compute_screenshot_hashes: false,
+            num_previews: 1,
+            screenshot_skip_intro: ScreenshotMargin::default(),
+            screenshot_skip_outro: ScreenshotMargin::default(),
+            preview_position: 0.5,
+            screenshot_history_size: 0,
+            animated_preview: false,
+            queue_length: None,
+            screenshot_cache: ScreenshotCache::default(),
         }
     }
 
+    /// Enables or disables computing the perceptual hashes of the generated screenshots.
+    ///
+    /// # Arguments
+    /// * `compute_screenshot_hashes` - If true, the hash of each generated screenshot is stored.
+    pub fn with_screenshot_hashes(mut self, compute_screenshot_hashes: bool) -> Self {
+        self.compute_screenshot_hashes = compute_screenshot_hashes;
+        self
+    }
+
+    /// Sets the number of screenshots taken of each movie at evenly spaced time stamps.
+    ///
+    /// # Arguments
+    /// * `num_previews` - The number of screenshots, at least one screenshot is always taken.
+    pub fn with_num_previews(mut self, num_previews: usize) -> Self {
+        self.num_previews = num_previews.max(1);
+        self
+    }
+
+    /// Sets the margins at the beginning and the end of each movie, which are skipped when
+    /// taking screenshots.
+    ///
+    /// # Arguments
+    /// * `skip_intro` - The margin at the beginning of each movie.
+    /// * `skip_outro` - The margin at the end of each movie.
+    pub fn with_screenshot_margins(
+        mut self,
+        skip_intro: ScreenshotMargin,
+        skip_outro: ScreenshotMargin,
+    ) -> Self {
+        self.screenshot_skip_intro = skip_intro;
+        self.screenshot_skip_outro = skip_outro;
+        self
+    }
+
+    /// Sets the position of the main screenshot as fraction of the duration of each movie, e.g.,
+    /// 0.25 to avoid black intro frames. Fails if the position doesn't lie within (0, 1).
+    ///
+    /// # Arguments
+    /// * `preview_position` - The position of the main screenshot.
+    pub fn with_preview_position(mut self, preview_position: f64) -> Result<Self, Error> {
+        check_screenshot_fraction(preview_position)?;
+
+        self.preview_position = preview_position;
+        Ok(self)
+    }
+
+    /// Sets the number of replaced screenshots kept per movie, s.t. they can be restored.
+    ///
+    /// # Arguments
+    /// * `history_size` - The number of previous screenshots, 0 disables the history.
+    pub fn with_screenshot_history(mut self, history_size: usize) -> Self {
+        self.screenshot_history_size = history_size;
+        self
+    }
+
+    /// Enables or disables generating an animated preview of each movie.
+    ///
+    /// # Arguments
+    /// * `animated_preview` - If true, an animated preview is generated besides the screenshots.
+    pub fn with_animated_preview(mut self, animated_preview: bool) -> Self {
+        self.animated_preview = animated_preview;
+        self
+    }
+
+    /// Sets the gauge, which is updated with the number of queued or in-flight preview requests
+    /// whenever a request is received or completed.
+    ///
+    /// # Arguments
+    /// * `queue_length` - The gauge for the length of the preview queue.
+    pub fn with_queue_gauge(mut self, queue_length: IntGauge) -> Self {
+        self.queue_length = Some(queue_length);
+        self
+    }
+
+    /// Sets the in-memory screenshot cache, which is updated with each generated screenshot.
+    ///
+    /// # Arguments
+    /// * `screenshot_cache` - The screenshot cache shared with the service handler.
+    pub fn with_screenshot_cache(mut self, screenshot_cache: ScreenshotCache) -> Self {
+        self.screenshot_cache = screenshot_cache;
+        self
+    }
+
     /// Returns the sender for preview requests.
-    pub fn get_preview_request_sender(&self) -> mpsc::UnboundedSender<ScreenshotRequest> {
+    pub fn get_preview_request_sender(&self) -> mpsc::Sender<ScreenshotRequest> {
         self.send_preview.clone()
     }
 
+    /// Returns the movies with queued or in-flight preview requests. Senders of preview requests
+    /// must mark their requests as pending before sending them.
+    pub fn get_pending_previews(&self) -> PendingPreviews {
+        self.pending_previews.clone()
+    }
+
     /// Runs the preview generator loop.
     pub async fn run(&mut self) {
-        self.trigger_all_missing_previews().await;
+        // as the queue of preview requests is bounded, the missing previews are requested
+        // concurrently to processing the requests
+        let index = self.index.clone();
+        let preview_request_sender = self.get_preview_request_sender();
+        let pending_previews = self.get_pending_previews();
+
+        tokio::join!(
+            Self::trigger_all_missing_previews(index, preview_request_sender, pending_previews),
+            self.process_preview_requests()
+        );
+    }
 
+    /// Processes the incoming preview requests.
+    async fn process_preview_requests(&mut self) {
         info!("Starting preview generator loop...");
 
-        while let Some(r) = self.recv_preview.recv().await {
-            debug!("Generating preview for request '{:?}'", r);
+        while let Some(request) = self.recv_preview.recv().await {
+            self.update_queue_length(1);
+            let preview_state = self.generate_preview(&request).await;
 
-            let file_path = match self
-                .storage
-                .read()
+            match &preview_state {
+                PreviewState::Ready => self.activity_log.record(
+                    ActivityKind::PreviewGenerated,
+                    Some(&request.movie_id),
+                    format!("Generated the preview of movie {}", request.movie_id),
+                ),
+                PreviewState::Failed { reason } => self.activity_log.record(
+                    ActivityKind::Error,
+                    Some(&request.movie_id),
+                    format!(
+                        "Failed to generate the preview of movie {}: {}",
+                        request.movie_id, reason
+                    ),
+                ),
+                PreviewState::Pending | PreviewState::Unsupported => (),
+            }
+
+            if let Err(err) = self
+                .index
+                .write()
                 .await
-                .get_file_path(
-                    r.movie_id.clone(),
-                    MovieDataType::MovieData { ext: r.ext.clone() },
-                )
+                .update_preview_state(&request.movie_id, preview_state)
                 .await
             {
-                Err(err) => {
-                    error!("Failed to get movie file path for movie '{}'", r.movie_id);
-                    error!("Error: {}", err);
-                    continue;
-                }
-                Ok(file_path) => match file_path {
-                    None => {
-                        error!("File paths are not supported by backend");
-                        continue;
-                    }
-                    Some(file_path) => file_path,
-                },
-            };
+                error!(
+                    "Failed to update preview state for movie '{}'",
+                    request.movie_id
+                );
+                error!("Error: {}", err);
+            }
 
-            debug!("Movie file path: {}", file_path.display());
+            self.pending_previews.remove(&request.movie_id);
+            self.update_queue_length(0);
+        }
 
-            // determine the total duration of the movie
-            trace!("Getting movie duration...");
-            let duration = match self.ffmpeg.get_movie_duration(&file_path).await {
-                Err(err) => {
-                    error!("Failed to get movie duration for movie '{}'", r.movie_id);
-                    error!("Error: {}", err);
-                    continue;
-                }
-                Ok(duration) => duration,
-            };
+        info!("Preview generator loop stopped");
+    }
 
-            // we make the screenshot in the middle of the movie
-            let time_stamp = duration / 2.0;
-            let screenshot_data = match self.ffmpeg.create_screenshot(&file_path, time_stamp).await
-            {
-                Ok(data) => data,
-                Err(err) => {
-                    error!("Failed to create screenshot for movie '{}'", r.movie_id);
-                    error!("Error: {}", err);
-                    continue;
+    /// Updates the gauge for the length of the preview queue, if any.
+    ///
+    /// # Arguments
+    /// * `in_flight` - The number of received requests, which are still being processed.
+    fn update_queue_length(&self, in_flight: usize) {
+        if let Some(queue_length) = &self.queue_length {
+            queue_length.set((self.recv_preview.len() + in_flight) as i64);
+        }
+    }
+
+    /// Generates the preview for the given request and updates the movie index accordingly.
+    /// Returns the resulting state of the preview.
+    ///
+    /// # Arguments
+    /// * `request` - The preview request to process.
+    async fn generate_preview(&self, request: &ScreenshotRequest) -> PreviewState {
+        debug!("Generating preview for request '{:?}'", request);
+
+        let failed = |message: &str, err: Error| {
+            error!("{} for movie '{}'", message, request.movie_id);
+            error!("Error: {}", err);
+
+            PreviewState::Failed {
+                reason: format!("{}: {}", message, err),
+            }
+        };
+
+        let file_path = match self
+            .storage
+            .read()
+            .await
+            .get_file_path(
+                request.movie_id.clone(),
+                MovieDataType::MovieData {
+                    ext: request.ext.clone(),
+                },
+            )
+            .await
+        {
+            Err(err) => return failed("Failed to get movie file path", err),
+            Ok(file_path) => match file_path {
+                None => {
+                    error!("File paths are not supported by backend");
+                    return PreviewState::Unsupported;
                 }
-            };
+                Some(file_path) => file_path,
+            },
+        };
+
+        debug!("Movie file path: {}", file_path.display());
+
+        // audio-only files have no frame to take a screenshot of
+        match self.ffmpeg.has_video_stream(&file_path).await {
+            Ok(true) => (),
+            Ok(false) => {
+                warn!("Movie '{}' has no video stream", request.movie_id);
+                return PreviewState::Unsupported;
+            }
+            Err(err) => {
+                warn!(
+                    "Failed to probe video streams of movie '{}'",
+                    request.movie_id
+                );
+                warn!("Error: {}", err);
+            }
+        }
+
+        // determine the total duration of the movie
+        trace!("Getting movie duration...");
+        let duration = match self.ffmpeg.get_movie_duration(&file_path).await {
+            Err(err) => {
+                warn!(
+                    "Failed to get movie duration for movie '{}'",
+                    request.movie_id
+                );
+                warn!("Error: {}", err);
+                warn!("Falling back to the first frame");
+                None
+            }
+            Ok(duration) => Some(duration),
+        };
 
-            // write screenshot data
+        // keep the replaced main screenshot, s.t. it can be restored
+        archive_screenshot(
+            &self.index,
+            &self.storage,
+            &request.movie_id,
+            self.screenshot_history_size,
+        )
+        .await;
+
+        // the main screenshot is taken at the preview position
+        let time_stamps = get_screenshot_time_stamps(
+            duration,
+            self.num_previews,
+            self.preview_position,
+            self.screenshot_skip_intro,
+            self.screenshot_skip_outro,
+        );
+        for (index, time_stamp) in time_stamps.iter().enumerate() {
+            trace!("Screenshot {} time stamp: {}", index, time_stamp);
+
+            // stream the screenshot data directly into the storage
             trace!("Write screenshot data...");
+            let data_type = || MovieDataType::ScreenshotData {
+                ext: "png".to_owned(),
+                index: index as u32,
+            };
             let mut writer = match self
                 .storage
                 .read()
                 .await
-                .write_movie_data(
-                    r.movie_id.clone(),
-                    MovieDataType::ScreenshotData {
-                        ext: "png".to_owned(),
-                    },
-                )
+                .write_movie_data(request.movie_id.clone(), data_type())
                 .await
             {
                 Ok(writer) => writer,
+                Err(err) => return failed("Failed to write screenshot data", err),
+            };
+
+            let ret = self
+                .ffmpeg
+                .write_screenshot(&file_path, *time_stamp, &mut writer)
+                .await;
+            drop(writer);
+
+            if let Err(err) = ret {
+                // a truncated screenshot must not be served as screenshot of the movie
+                if let Err(err) = self
+                    .storage
+                    .read()
+                    .await
+                    .remove_movie_file(request.movie_id.clone(), data_type())
+                    .await
+                {
+                    warn!(
+                        "Failed to remove incomplete screenshot of movie '{}': {}",
+                        request.movie_id, err
+                    );
+                }
+
+                return failed("Failed to create screenshot", err);
+            }
+        }
+
+        // update movie index about the new screenshot
+        trace!("Update movie index...");
+        if let Err(err) = self
+            .index
+            .write()
+            .await
+            .update_screenshot_info(
+                &request.movie_id,
+                ScreenshotInfo {
+                    extension: "png".to_owned(),
+                    mime_type: "image/png".to_owned(),
+                    count: time_stamps.len() as u32,
+                },
+            )
+            .await
+        {
+            return failed("Failed to update movie index", err);
+        }
+
+        // the cached screenshot is replaced by the new one, s.t. the cache stays up to date
+        if self.screenshot_cache.is_enabled() {
+            match self.read_main_screenshot(&request.movie_id).await {
+                Ok(data) => {
+                    self.screenshot_cache
+                        .insert(request.movie_id.clone(), "png", data.into())
+                }
                 Err(err) => {
-                    error!("Failed to write screenshot data for movie '{}'", r.movie_id);
-                    error!("Error: {}", err);
-                    continue;
+                    warn!(
+                        "Failed to cache screenshot of movie '{}': {}",
+                        request.movie_id, err
+                    );
+                    self.screenshot_cache.remove(&request.movie_id);
                 }
-            };
+            }
+        }
 
-            if let Err(err) = writer.write_all(&screenshot_data).await {
-                error!("Failed to write screenshot data for movie '{}'", r.movie_id);
-                error!("Error: {}", err);
-                continue;
+        // missing video metadata only hides the details of the movie file
+        match self.ffmpeg.get_movie_metadata(&file_path).await {
+            Ok(video_metadata) => {
+                if let Err(err) = self
+                    .index
+                    .write()
+                    .await
+                    .update_video_metadata(&request.movie_id, video_metadata)
+                    .await
+                {
+                    warn!(
+                        "Failed to store video metadata of movie '{}': {}",
+                        request.movie_id, err
+                    );
+                }
             }
+            Err(err) => warn!(
+                "Failed to get video metadata of movie '{}': {}",
+                request.movie_id, err
+            ),
+        }
 
-            // update movie index about the new screenshot
-            trace!("Update movie index...");
-            match self
-                .index
-                .write()
-                .await
-                .update_screenshot_info(
-                    &r.movie_id,
-                    ScreenshotInfo {
-                        extension: "png".to_owned(),
-                        mime_type: "image/png".to_owned(),
-                    },
-                )
+        // a missing animated preview only hides the preview, the screenshots are kept
+        if self.animated_preview {
+            let start = get_animated_preview_start(time_stamps[0], duration);
+            if let Err(err) = self
+                .store_animated_preview(&request.movie_id, &file_path, start)
                 .await
             {
-                Ok(_) => {}
-                Err(err) => {
-                    error!("Failed to update movie index for movie '{}'", r.movie_id);
-                    error!("Error: {}", err);
-                    continue;
-                }
+                warn!(
+                    "Failed to create animated preview of movie '{}': {}",
+                    request.movie_id, err
+                );
             }
         }
 
-        info!("Preview generator loop stopped");
-    }
+        // a missing hash only excludes the movie from the similarity search
+        if self.compute_screenshot_hashes {
+            if let Err(err) = self.store_screenshot_hash(&request.movie_id).await {
+                warn!(
+                    "Failed to compute screenshot hash for movie '{}': {}",
+                    request.movie_id, err
+                );
+            }
+        }
 
-    async fn trigger_all_missing_previews(&self) {
-        info!("Triggering all missing previews...");
-        let index = self.index.read().await;
+        PreviewState::Ready
+    }
 
-        let preview_request_sender = self.get_preview_request_sender();
+    /// Creates the animated preview of the given movie and stores it together with its info.
+    ///
+    /// # Arguments
+    /// * `movie_id` - The id of the movie.
+    /// * `file_path` - The path to the movie file.
+    /// * `start` - The time stamp in seconds at which the animated preview starts.
+    async fn store_animated_preview(
+        &self,
+        movie_id: &MovieId,
+        file_path: &Path,
+        start: f64,
+    ) -> Result<(), Error> {
+        trace!("Create animated preview at {}...", start);
+        let data = self
+            .ffmpeg
+            .create_animated_preview(file_path, start, ANIMATED_PREVIEW_DURATION)
+            .await?;
 
-        let query: MovieSearchQuery = Default::default();
-        let movie_ids = match index.search_movies(query).await {
-            Ok(movie_ids) => movie_ids,
-            Err(err) => {
-                error!("Failed to search movies");
-                error!("Error: {}", err);
-                return;
-            }
+        let animated_preview_info = AnimatedPreviewInfo {
+            extension: "gif".to_owned(),
+            mime_type: "image/gif".to_owned(),
         };
 
-        for movie_id in movie_ids.iter() {
-            let movie = match index.get_movie(movie_id).await {
-                Ok(movie) => movie,
+        let mut writer = self
+            .storage
+            .read()
+            .await
+            .write_movie_data(
+                movie_id.clone(),
+                MovieDataType::AnimatedPreviewData {
+                    ext: animated_preview_info.extension.clone(),
+                },
+            )
+            .await?;
+        writer.write_all(&data).await?;
+        writer.flush().await?;
+
+        self.index
+            .write()
+            .await
+            .update_animated_preview_info(movie_id, animated_preview_info)
+            .await
+    }
+
+    /// Computes the perceptual hash of the generated screenshot of the given movie and stores it
+    /// in the index.
+    ///
+    /// # Arguments
+    /// * `movie_id` - The id of the movie.
+    async fn store_screenshot_hash(&self, movie_id: &MovieId) -> Result<(), Error> {
+        let data = self.read_main_screenshot(movie_id).await?;
+
+        store_screenshot_hash(&self.index, movie_id, data).await
+    }
+
+    /// Reads the generated main screenshot of the given movie. The file is read directly, s.t.
+    /// the generator doesn't hold a reader of the storage, which may not be sendable.
+    ///
+    /// # Arguments
+    /// * `movie_id` - The id of the movie.
+    async fn read_main_screenshot(&self, movie_id: &MovieId) -> Result<Vec<u8>, Error> {
+        let file_path = self
+            .storage
+            .read()
+            .await
+            .get_file_path(
+                movie_id.clone(),
+                MovieDataType::ScreenshotData {
+                    ext: "png".to_owned(),
+                    index: 0,
+                },
+            )
+            .await?
+            .ok_or_else(|| Error::Internal("File paths are not supported by backend".into()))?;
+
+        Ok(tokio::fs::read(&file_path).await?)
+    }
+
+    /// Sends preview requests for all movies with a movie file, but without a preview.
+    ///
+    /// # Arguments
+    /// * `index` - The movie index.
+    /// * `preview_request_sender` - The sender for the preview requests.
+    /// * `pending_previews` - The movies with pending preview requests.
+    async fn trigger_all_missing_previews(
+        index: Arc<RwLock<I>>,
+        preview_request_sender: mpsc::Sender<ScreenshotRequest>,
+        pending_previews: PendingPreviews,
+    ) {
+        info!("Triggering all missing previews...");
+
+        // collect the requests first, s.t. the index isn't locked while waiting for the queue
+        let mut requests: Vec<ScreenshotRequest> = Vec::new();
+        {
+            let index = index.read().await;
+
+            let query: MovieSearchQuery = Default::default();
+            let movie_ids = match index.search_movies(query).await {
+                Ok(movie_ids) => movie_ids,
                 Err(err) => {
-                    error!("Failed to get movie '{}'", movie_id);
+                    error!("Failed to search movies");
                     error!("Error: {}", err);
-                    continue;
+                    return;
                 }
             };
 
-            if let Some(movie_file_info) = movie.movie_file_info {
-                if movie.screenshot_file_info.is_none() {
-                    info!("Movie '{}' is missing a preview", movie_id);
-                    if let Err(err) = preview_request_sender.send(ScreenshotRequest {
-                        movie_id: movie_id.clone(),
-                        ext: movie_file_info.extension,
-                    }) {
-                        error!("Failed to send preview request for movie '{}'", movie_id);
+            for movie_id in movie_ids.iter() {
+                let movie = match index.get_movie(movie_id).await {
+                    Ok(movie) => movie,
+                    Err(err) => {
+                        error!("Failed to get movie '{}'", movie_id);
                         error!("Error: {}", err);
+                        continue;
+                    }
+                };
+
+                // failed previews would most likely fail again, e.g., as the movie is corrupt
+                if let Some(PreviewState::Failed { .. }) = movie.preview_state {
+                    info!("Skipping movie '{}' with failed preview", movie_id);
+                    continue;
+                }
+
+                if let Some(movie_file_info) = movie.movie_file_info {
+                    if movie.screenshot_file_info.is_none() {
+                        info!("Movie '{}' is missing a preview", movie_id);
+                        requests.push(ScreenshotRequest {
+                            movie_id: movie_id.clone(),
+                            ext: movie_file_info.extension,
+                        });
+                    }
+                }
+            }
+        }
+
+        for request in requests {
+            let movie_id = request.movie_id.clone();
+            pending_previews.add(&movie_id);
+            if let Err(err) = preview_request_sender.send(request).await {
+                error!("Failed to send preview request for movie '{}'", movie_id);
+                error!("Error: {}", err);
+                pending_previews.remove(&movie_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{os::unix::fs::PermissionsExt, path::Path, time::Duration};
+
+    use tempdir::TempDir;
+
+    use crate::{file_storage::FileStorage, Movie, MovieFileInfo, Options, SimpleMoviesIndex};
+
+    use super::*;
+
+    /// The name of the file the fake ffmpeg binary appends the screenshot time stamps to.
+    const TIME_STAMP_FILE_NAME: &str = "time_stamp";
+
+    /// Writes fake ffmpeg and ffprobe binaries into the given directory. The fake ffprobe runs
+    /// the given script to report the duration and the fake ffmpeg records the time stamps of the
+    /// requested screenshots.
+    ///
+    /// # Arguments
+    /// * `dir` - The directory to write the binaries to.
+    /// * `ffprobe_script` - The script reporting the movie duration.
+    fn write_fake_binaries(dir: &Path, ffprobe_script: &str) {
+        let version_check = "if [ \"$1\" = \"-version\" ]; then echo \"version test\"; exit 0; fi";
+        let ffmpeg_script = format!(
+            "echo \"$2\" >> '{}'\nprintf 'screenshot'",
+            dir.join(TIME_STAMP_FILE_NAME).display()
+        );
+
+        for (name, script) in [
+            ("ffmpeg", ffmpeg_script.as_str()),
+            ("ffprobe", ffprobe_script),
+        ] {
+            write_fake_binary(dir, name, &format!("{}\n{}", version_check, script));
+        }
+    }
+
+    /// Writes a fake binary with the given name running the given shell script.
+    ///
+    /// # Arguments
+    /// * `dir` - The directory to write the binary to.
+    /// * `name` - The name of the binary.
+    /// * `script` - The shell script run by the binary.
+    fn write_fake_binary(dir: &Path, name: &str, script: &str) {
+        let file_path = dir.join(name);
+        std::fs::write(&file_path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    /// Adds a movie with a movie file to the given index and storage and returns its id.
+    ///
+    /// # Arguments
+    /// * `index` - The movies index to add the movie to.
+    /// * `storage` - The storage to write the movie file to.
+    async fn add_movie_with_file(
+        index: &RwLock<SimpleMoviesIndex>,
+        storage: &RwLock<FileStorage>,
+    ) -> MovieId {
+        let id = index
+            .write()
+            .await
+            .add_movie(Movie {
+                title: "Short Clip".to_owned(),
+                description: String::new(),
+                tags: Vec::new(),
+            })
+            .await
+            .unwrap();
+        let ext = "mp4".to_owned();
+        let mut writer = storage
+            .read()
+            .await
+            .write_movie_data(id.clone(), MovieDataType::MovieData { ext: ext.clone() })
+            .await
+            .unwrap();
+        writer.write_all(b"movie").await.unwrap();
+        index
+            .write()
+            .await
+            .update_movie_file_info(
+                &id,
+                MovieFileInfo {
+                    extension: ext,
+                    mime_type: "video/mp4".to_owned(),
+                    original_filename: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        id
+    }
+
+    /// Generates a preview for a single movie using fake ffmpeg binaries and returns the time
+    /// stamp at which the screenshot has been taken.
+    ///
+    /// # Arguments
+    /// * `ffprobe_script` - The script reporting the movie duration.
+    async fn generate_preview(ffprobe_script: &str) -> String {
+        let time_stamps = generate_previews(ffprobe_script, 1).await;
+        assert_eq!(time_stamps.len(), 1);
+
+        time_stamps[0].clone()
+    }
+
+    /// Generates the given number of screenshots for a single movie using fake ffmpeg binaries
+    /// and returns the time stamps at which the screenshots have been taken, ordered by their
+    /// index.
+    ///
+    /// # Arguments
+    /// * `ffprobe_script` - The script reporting the movie duration.
+    /// * `num_previews` - The number of screenshots to take.
+    async fn generate_previews(ffprobe_script: &str, num_previews: usize) -> Vec<String> {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let bin_dir = root_dir.path().join("bin");
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        write_fake_binaries(&bin_dir, ffprobe_script);
+
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().join("data");
+
+        let index = Arc::new(RwLock::new(SimpleMoviesIndex::new(&options).unwrap()));
+        let storage = Arc::new(RwLock::new(FileStorage::new(&options).unwrap()));
+        let id = add_movie_with_file(&index, &storage).await;
+
+        // the preview generator picks up the movie with the missing preview
+        let ffmpeg = FFMpeg::new(&bin_dir).await.unwrap();
+        let mut preview_generator = PreviewGenerator::new(
+            ffmpeg,
+            index.clone(),
+            storage.clone(),
+            16,
+            ActivityLog::new(16),
+        )
+        .with_num_previews(num_previews);
+        let handle = tokio::spawn(async move { preview_generator.run().await });
+
+        let mut screenshot_info = None;
+        for _ in 0..100 {
+            screenshot_info = index
+                .read()
+                .await
+                .get_movie(&id)
+                .await
+                .unwrap()
+                .screenshot_file_info;
+            if screenshot_info.is_some() {
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        handle.abort();
+
+        let screenshot_info = screenshot_info.expect("no screenshot has been created");
+
+        // every screenshot is stored
+        for index in 0..screenshot_info.count {
+            let file_path = storage
+                .read()
+                .await
+                .get_file_path(
+                    id.clone(),
+                    MovieDataType::ScreenshotData {
+                        ext: screenshot_info.extension.clone(),
+                        index,
+                    },
+                )
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(file_path.exists(), "screenshot {} is missing", index);
+        }
+
+        let time_stamps = std::fs::read_to_string(bin_dir.join(TIME_STAMP_FILE_NAME)).unwrap();
+        let time_stamps: Vec<String> = time_stamps.lines().map(str::to_owned).collect();
+        assert_eq!(time_stamps.len(), screenshot_info.count as usize);
+
+        time_stamps
+    }
+
+    /// Returns the screenshot time stamps for the given duration without skipping any margins.
+    ///
+    /// # Arguments
+    /// * `duration` - The duration of the movie in seconds, if known.
+    /// * `count` - The number of screenshots to take.
+    fn time_stamps(duration: Option<f64>, count: usize) -> Vec<f64> {
+        get_screenshot_time_stamps(
+            duration,
+            count,
+            0.5,
+            ScreenshotMargin::default(),
+            ScreenshotMargin::default(),
+        )
+    }
+
+    #[test]
+    fn test_get_screenshot_time_stamps() {
+        assert_eq!(time_stamps(Some(100.0), 1), vec![50.0]);
+        assert_eq!(time_stamps(Some(0.4), 1), vec![0.2]);
+        assert_eq!(time_stamps(Some(0.0), 1), vec![0.0]);
+        assert_eq!(time_stamps(Some(-1.0), 1), vec![0.0]);
+        assert_eq!(time_stamps(Some(f64::NAN), 1), vec![0.0]);
+        assert_eq!(time_stamps(Some(f64::INFINITY), 1), vec![0.0]);
+        assert_eq!(time_stamps(None, 1), vec![0.0]);
+
+        // the main screenshot is taken in the middle, followed by the others in chronological order
+        assert_eq!(
+            time_stamps(Some(100.0), 5),
+            vec![50.0, 10.0, 30.0, 70.0, 90.0]
+        );
+        assert_eq!(time_stamps(Some(100.0), 4), vec![50.0, 12.5, 37.5, 87.5]);
+        assert_eq!(time_stamps(Some(100.0), 0), vec![50.0]);
+
+        // without a duration, only the first frame is taken
+        assert_eq!(time_stamps(None, 5), vec![0.0]);
+    }
+
+    #[test]
+    fn test_screenshot_margins() {
+        let seconds = ScreenshotMargin::Seconds;
+        let fraction = ScreenshotMargin::Fraction;
+
+        // the time stamps are evenly spaced within the trimmed range
+        assert_eq!(
+            get_screenshot_time_stamps(Some(100.0), 1, 0.5, seconds(10.0), seconds(30.0)),
+            vec![40.0]
+        );
+        assert_eq!(
+            get_screenshot_time_stamps(Some(100.0), 3, 0.5, seconds(10.0), seconds(30.0)),
+            vec![40.0, 20.0, 60.0]
+        );
+        assert_eq!(
+            get_screenshot_time_stamps(Some(100.0), 2, 0.5, fraction(0.1), fraction(0.1)),
+            vec![50.0, 30.0]
+        );
+
+        // the main screenshot is taken at the preview position within the trimmed range
+        assert_eq!(
+            get_screenshot_time_stamps(Some(100.0), 1, 0.25, seconds(0.0), seconds(0.0)),
+            vec![25.0]
+        );
+        assert_eq!(
+            get_screenshot_time_stamps(Some(100.0), 5, 0.25, seconds(0.0), seconds(0.0)),
+            vec![25.0, 10.0, 30.0, 70.0, 90.0]
+        );
+        assert_eq!(
+            get_screenshot_time_stamps(Some(100.0), 1, 0.25, seconds(10.0), seconds(30.0)),
+            vec![25.0]
+        );
+
+        // margins leaving no range are ignored
+        assert_eq!(
+            get_screenshot_time_stamps(Some(100.0), 1, 0.5, seconds(90.0), seconds(10.0)),
+            vec![50.0]
+        );
+        assert_eq!(
+            get_screenshot_time_stamps(Some(100.0), 1, 0.5, fraction(0.6), fraction(0.6)),
+            vec![50.0]
+        );
+
+        // without a duration, the first frame is taken regardless of the margins
+        assert_eq!(
+            get_screenshot_time_stamps(None, 3, 0.5, seconds(10.0), seconds(10.0)),
+            vec![0.0]
+        );
+
+        // the time stamps always stay within the trimmed range
+        for duration in [0.5, 30.0, 600.0, 5400.0, 10800.0] {
+            for (skip_intro, skip_outro) in [
+                (seconds(0.0), seconds(0.0)),
+                (seconds(90.0), seconds(300.0)),
+                (fraction(0.05), fraction(0.1)),
+                (seconds(60.0), fraction(0.2)),
+            ] {
+                let start = skip_intro.to_seconds(duration);
+                let end = duration - skip_outro.to_seconds(duration);
+                let (start, end) = if start < end {
+                    (start, end)
+                } else {
+                    (0.0, duration)
+                };
+
+                for count in 1..=5 {
+                    for time_stamp in get_screenshot_time_stamps(
+                        Some(duration),
+                        count,
+                        0.5,
+                        skip_intro,
+                        skip_outro,
+                    ) {
+                        assert!(
+                            start <= time_stamp && time_stamp < end,
+                            "time stamp {} outside of [{}, {}) for duration {}",
+                            time_stamp,
+                            start,
+                            end,
+                            duration
+                        );
                     }
                 }
             }
         }
     }
+
+    #[test]
+    fn test_get_animated_preview_start() {
+        assert_eq!(get_animated_preview_start(50.0, Some(100.0)), 50.0);
+
+        // the preview ends with the movie at the latest
+        assert_eq!(get_animated_preview_start(9.0, Some(10.0)), 7.0);
+        assert_eq!(get_animated_preview_start(1.0, Some(2.0)), 0.0);
+
+        // without a duration, the preview starts at the main screenshot
+        assert_eq!(get_animated_preview_start(0.0, None), 0.0);
+        assert_eq!(get_animated_preview_start(5.0, Some(f64::NAN)), 5.0);
+    }
+
+    #[tokio::test]
+    async fn test_animated_preview() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let bin_dir = root_dir.path().join("bin");
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        write_fake_binaries(&bin_dir, "echo 10.0");
+
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().join("data");
+
+        let index = Arc::new(RwLock::new(SimpleMoviesIndex::new(&options).unwrap()));
+        let storage = Arc::new(RwLock::new(FileStorage::new(&options).unwrap()));
+        let id = add_movie_with_file(&index, &storage).await;
+
+        let ffmpeg = FFMpeg::new(&bin_dir).await.unwrap();
+        let mut preview_generator = PreviewGenerator::new(
+            ffmpeg,
+            index.clone(),
+            storage.clone(),
+            16,
+            ActivityLog::new(16),
+        )
+        .with_animated_preview(true);
+        let handle = tokio::spawn(async move { preview_generator.run().await });
+
+        let mut movie = None;
+        for _ in 0..100 {
+            let current = index.read().await.get_movie(&id).await.unwrap();
+            if current.animated_preview_info.is_some() {
+                movie = Some(current);
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        handle.abort();
+
+        // the animated preview is stored in addition to the screenshot
+        let movie = movie.expect("no animated preview has been created");
+        assert!(movie.screenshot_file_info.is_some());
+        let animated_preview_info = movie.animated_preview_info.unwrap();
+        assert_eq!(animated_preview_info.mime_type, "image/gif");
+
+        let file_path = storage
+            .read()
+            .await
+            .get_file_path(
+                id.clone(),
+                MovieDataType::AnimatedPreviewData {
+                    ext: animated_preview_info.extension,
+                },
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(std::fs::read(file_path).unwrap(), b"screenshot");
+
+        // both the screenshot and the animated preview start in the middle of the movie
+        let time_stamps = std::fs::read_to_string(bin_dir.join(TIME_STAMP_FILE_NAME)).unwrap();
+        assert_eq!(time_stamps.lines().collect::<Vec<_>>(), ["5", "5"]);
+    }
+
+    #[tokio::test]
+    async fn test_multiple_previews() {
+        assert_eq!(
+            generate_previews("echo 100.000000", 5).await,
+            vec!["50", "10", "30", "70", "90"]
+        );
+
+        // a movie without a duration only gets a single screenshot of the first frame
+        assert_eq!(
+            generate_previews("echo 'invalid data' >&2; exit 1", 5).await,
+            vec!["0"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_preview_of_short_clips() {
+        // sub-second clip
+        assert_eq!(generate_preview("echo 0.400000").await, "0.2");
+
+        // zero-duration clip
+        assert_eq!(generate_preview("echo 0.000000").await, "0");
+
+        // probing the duration fails
+        assert_eq!(
+            generate_preview("echo 'invalid data' >&2; exit 1").await,
+            "0"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_preview_of_corrupt_file() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let bin_dir = root_dir.path().join("bin");
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        let corrupt = "echo 'Invalid data found when processing input' >&2; exit 1";
+        write_fake_binaries(&bin_dir, corrupt);
+        write_fake_binary(
+            &bin_dir,
+            "ffmpeg",
+            &format!("if [ \"$1\" = \"-version\" ]; then exit 0; fi\n{}", corrupt),
+        );
+
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().join("data");
+
+        let index = Arc::new(RwLock::new(SimpleMoviesIndex::new(&options).unwrap()));
+        let storage = Arc::new(RwLock::new(FileStorage::new(&options).unwrap()));
+        let id = add_movie_with_file(&index, &storage).await;
+
+        let ffmpeg = FFMpeg::new(&bin_dir).await.unwrap();
+        let mut preview_generator = PreviewGenerator::new(
+            ffmpeg,
+            index.clone(),
+            storage.clone(),
+            16,
+            ActivityLog::new(16),
+        );
+        let handle = tokio::spawn(async move { preview_generator.run().await });
+
+        let mut preview_state = None;
+        for _ in 0..100 {
+            preview_state = index
+                .read()
+                .await
+                .get_movie(&id)
+                .await
+                .unwrap()
+                .preview_state;
+            if preview_state.is_some() {
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        handle.abort();
+
+        match preview_state {
+            Some(PreviewState::Failed { reason }) => {
+                assert!(reason.contains("Invalid data found"), "{}", reason)
+            }
+            state => panic!("Unexpected preview state {:?}", state),
+        }
+
+        // no incomplete screenshot is left behind
+        let ret = storage
+            .read()
+            .await
+            .read_movie_data(
+                id.clone(),
+                MovieDataType::ScreenshotData {
+                    ext: "png".to_owned(),
+                    index: 0,
+                },
+            )
+            .await;
+        assert!(ret.is_err());
+
+        // the failed preview isn't requested again on the next startup
+        let (sender, mut receiver) = mpsc::channel(16);
+        PreviewGenerator::<SimpleMoviesIndex, FileStorage>::trigger_all_missing_previews(
+            index.clone(),
+            sender,
+            PendingPreviews::default(),
+        )
+        .await;
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_preview_of_audio_only_file() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let bin_dir = root_dir.path().join("bin");
+        std::fs::create_dir_all(&bin_dir).unwrap();
+
+        // the fake ffprobe reports the duration, but no video stream
+        write_fake_binaries(
+            &bin_dir,
+            "case \"$*\" in *-select_streams*) exit 0;; esac\necho 10.0",
+        );
+
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().join("data");
+
+        let index = Arc::new(RwLock::new(SimpleMoviesIndex::new(&options).unwrap()));
+        let storage = Arc::new(RwLock::new(FileStorage::new(&options).unwrap()));
+        let id = add_movie_with_file(&index, &storage).await;
+
+        let ffmpeg = FFMpeg::new(&bin_dir).await.unwrap();
+        let mut preview_generator = PreviewGenerator::new(
+            ffmpeg,
+            index.clone(),
+            storage.clone(),
+            16,
+            ActivityLog::new(16),
+        );
+        let handle = tokio::spawn(async move { preview_generator.run().await });
+
+        let mut preview_state = None;
+        for _ in 0..100 {
+            preview_state = index
+                .read()
+                .await
+                .get_movie(&id)
+                .await
+                .unwrap()
+                .preview_state;
+            if preview_state.is_some() {
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        handle.abort();
+
+        // no screenshot has been attempted
+        assert_eq!(preview_state, Some(PreviewState::Unsupported));
+        assert!(!bin_dir.join(TIME_STAMP_FILE_NAME).exists());
+    }
 }
@@ -0,0 +1,160 @@
+use std::sync::{Arc, Mutex};
+
+use actix_web::web::Bytes;
+use tokio::{io::AsyncReadExt, sync::RwLock};
+
+use crate::{lru::Lru, Error, MovieDataType, MovieId, MovieStorage};
+
+/// A cached main screenshot of a movie.
+struct CachedScreenshot {
+    /// The file extension of the screenshot.
+    extension: String,
+
+    /// The data of the screenshot.
+    data: Bytes,
+}
+
+/// An in-memory cache for the main screenshots of the movies, s.t. e.g. the initial grid of the
+/// most recent movies is rendered without reading the storage. The cache evicts the least
+/// recently used screenshots once the total size of the screenshots exceeds its maximal size,
+/// where a maximal size of zero disables the cache. All clones share the same cache.
+#[derive(Clone)]
+pub struct ScreenshotCache {
+    lru: Arc<Mutex<Lru<MovieId, CachedScreenshot>>>,
+}
+
+impl Default for ScreenshotCache {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl ScreenshotCache {
+    /// Creates a new empty cache.
+    ///
+    /// # Arguments
+    /// * `max_size` - The maximal total size of the cached screenshots in bytes.
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            lru: Arc::new(Mutex::new(Lru::new(max_size))),
+        }
+    }
+
+    /// Returns true if screenshots are cached at all.
+    pub fn is_enabled(&self) -> bool {
+        self.lru.lock().unwrap().max_weight() > 0
+    }
+
+    /// Returns the cached screenshot of the given movie, if any, and marks it as recently used.
+    /// A screenshot with another extension than the given one is outdated and not returned.
+    ///
+    /// # Arguments
+    /// * `id` - The ID of the movie.
+    /// * `extension` - The file extension of the current screenshot of the movie.
+    pub fn get(&self, id: &MovieId, extension: &str) -> Option<Bytes> {
+        let mut lru = self.lru.lock().unwrap();
+        let screenshot = lru.get(id)?;
+
+        (screenshot.extension == extension).then(|| screenshot.data.clone())
+    }
+
+    /// Inserts the screenshot of the given movie, replacing any previous screenshot of the movie,
+    /// and evicts the least recently used screenshots until the cache fits. Screenshots exceeding
+    /// the maximal size on their own are not cached.
+    ///
+    /// # Arguments
+    /// * `id` - The ID of the movie.
+    /// * `extension` - The file extension of the screenshot.
+    /// * `data` - The data of the screenshot.
+    pub fn insert(&self, id: MovieId, extension: &str, data: Bytes) {
+        // each screenshot weighs its size, s.t. the cache is bounded by the total size
+        let size = data.len();
+        self.lru.lock().unwrap().insert(
+            id,
+            CachedScreenshot {
+                extension: extension.to_owned(),
+                data,
+            },
+            size,
+        );
+    }
+
+    /// Removes the screenshot of the given movie, e.g., after it has been replaced.
+    ///
+    /// # Arguments
+    /// * `id` - The ID of the movie.
+    pub fn remove(&self, id: &MovieId) {
+        self.lru.lock().unwrap().remove(id);
+    }
+}
+
+/// Reads the main screenshot of the given movie from the storage.
+///
+/// # Arguments
+/// * `storage` - The movie storage.
+/// * `id` - The ID of the movie.
+/// * `extension` - The file extension of the screenshot.
+pub async fn read_screenshot<S: MovieStorage>(
+    storage: &RwLock<S>,
+    id: &MovieId,
+    extension: &str,
+) -> Result<Bytes, Error> {
+    let mut reader = storage
+        .read()
+        .await
+        .read_movie_data(
+            id.clone(),
+            MovieDataType::ScreenshotData {
+                ext: extension.to_owned(),
+                index: 0,
+            },
+        )
+        .await?;
+
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data).await?;
+
+    Ok(data.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_screenshot_cache() {
+        let cache = ScreenshotCache::new(10);
+        assert!(cache.is_enabled());
+
+        cache.insert("a".to_owned(), "png", Bytes::from_static(b"aaaa"));
+        cache.insert("b".to_owned(), "png", Bytes::from_static(b"bbbb"));
+        assert_eq!(cache.get(&"a".to_owned(), "png").unwrap(), "aaaa");
+        assert!(cache.get(&"a".to_owned(), "jpg").is_none());
+
+        // inserting c evicts b, which has been used least recently
+        cache.insert("c".to_owned(), "png", Bytes::from_static(b"cccc"));
+        assert!(cache.get(&"b".to_owned(), "png").is_none());
+        assert_eq!(cache.get(&"a".to_owned(), "png").unwrap(), "aaaa");
+        assert_eq!(cache.get(&"c".to_owned(), "png").unwrap(), "cccc");
+
+        // replacing a screenshot updates the total size
+        cache.insert("a".to_owned(), "jpg", Bytes::from_static(b"aa"));
+        assert_eq!(cache.get(&"a".to_owned(), "jpg").unwrap(), "aa");
+        cache.insert("d".to_owned(), "png", Bytes::from_static(b"dddd"));
+        assert!(cache.get(&"a".to_owned(), "jpg").is_some());
+        assert!(cache.get(&"c".to_owned(), "png").is_some());
+
+        // too large screenshots are not cached
+        cache.insert("e".to_owned(), "png", Bytes::from_static(b"eeeeeeeeeee"));
+        assert!(cache.get(&"e".to_owned(), "png").is_none());
+
+        cache.remove(&"d".to_owned());
+        assert!(cache.get(&"d".to_owned(), "png").is_none());
+
+        // a cache without size caches nothing
+        let cache = ScreenshotCache::new(0);
+        assert!(!cache.is_enabled());
+        cache.insert("a".to_owned(), "png", Bytes::from_static(b"a"));
+        assert!(cache.get(&"a".to_owned(), "png").is_none());
+    }
+}
@@ -0,0 +1,229 @@
+use std::io::Cursor;
+
+use image::{imageops::FilterType, DynamicImage, ImageOutputFormat, RgbImage};
+use log::debug;
+
+use crate::Error;
+
+/// The maximal width and height of a resized screenshot.
+pub const MAX_RESIZED_SCREENSHOT_SIZE: u32 = 1920;
+
+/// The file extension of resized screenshots.
+pub const RESIZED_SCREENSHOT_EXT: &str = "png";
+
+/// The mime type of resized screenshots.
+pub const RESIZED_SCREENSHOT_MIME_TYPE: &str = "image/png";
+
+/// The sizes the bounds of resized screenshots are quantized to, s.t. only a few resized
+/// variants of each screenshot are cached, no matter which sizes are requested.
+pub const RESIZED_SCREENSHOT_SIZES: [u32; 12] = [
+    64,
+    90,
+    128,
+    180,
+    240,
+    320,
+    360,
+    480,
+    640,
+    720,
+    1280,
+    MAX_RESIZED_SCREENSHOT_SIZE,
+];
+
+/// Returns the bounds for a resized screenshot based on the requested width and height.
+/// Missing dimensions are unbounded and all dimensions are rounded down to the next size of
+/// `RESIZED_SCREENSHOT_SIZES`, but to at least the smallest size.
+///
+/// # Arguments
+/// * `width` - Optionally, the requested width.
+/// * `height` - Optionally, the requested height.
+pub fn get_resize_bounds(width: Option<u32>, height: Option<u32>) -> (u32, u32) {
+    let quantize = |size: Option<u32>| {
+        let size = size.unwrap_or(MAX_RESIZED_SCREENSHOT_SIZE);
+        RESIZED_SCREENSHOT_SIZES
+            .iter()
+            .rev()
+            .copied()
+            .find(|&quantized| quantized <= size)
+            .unwrap_or(RESIZED_SCREENSHOT_SIZES[0])
+    };
+
+    (quantize(width), quantize(height))
+}
+
+/// Decodes the given screenshot, resizes it s.t. it fits into the given bounds while preserving
+/// its aspect ratio and returns the resized screenshot encoded as PNG.
+/// Screenshots which already fit into the bounds are not scaled up.
+///
+/// # Arguments
+/// * `data` - The encoded screenshot data.
+/// * `width` - The maximal width of the resized screenshot.
+/// * `height` - The maximal height of the resized screenshot.
+pub fn resize_screenshot(data: &[u8], width: u32, height: u32) -> Result<Vec<u8>, Error> {
+    let image = image::load_from_memory(data)?;
+
+    let image = if image.width() <= width && image.height() <= height {
+        image
+    } else {
+        image.resize(width, height, FilterType::Triangle)
+    };
+
+    debug!(
+        "Resized screenshot to {}x{} for bounds {}x{}",
+        image.width(),
+        image.height(),
+        width,
+        height
+    );
+
+    let mut resized_data = Cursor::new(Vec::new());
+    image.write_to(&mut resized_data, ImageOutputFormat::Png)?;
+
+    Ok(resized_data.into_inner())
+}
+
+/// The width of each screenshot on a contact sheet.
+pub const CONTACT_SHEET_CELL_WIDTH: u32 = 320;
+
+/// The maximal height of each screenshot on a contact sheet, s.t. screenshots with an extreme
+/// aspect ratio can't blow up the size of the sheet.
+pub const CONTACT_SHEET_MAX_CELL_HEIGHT: u32 = 2 * CONTACT_SHEET_CELL_WIDTH;
+
+/// Decodes the given screenshots and composites them into a grid with the given number of
+/// columns, i.e., a contact sheet, which is returned encoded as PNG. Each screenshot is scaled to
+/// fit into `CONTACT_SHEET_CELL_WIDTH` x `CONTACT_SHEET_MAX_CELL_HEIGHT` while preserving its
+/// aspect ratio and all rows are as high as the highest scaled screenshot. A grid with more
+/// columns than screenshots is reduced to a single row.
+///
+/// # Arguments
+/// * `screenshots` - The encoded screenshots in the order of the grid.
+/// * `columns` - The number of columns of the grid.
+pub fn create_contact_sheet(screenshots: &[Vec<u8>], columns: u32) -> Result<Vec<u8>, Error> {
+    if screenshots.is_empty() || columns == 0 {
+        return Err(Error::InvalidArgument(
+            "A contact sheet needs at least one screenshot and one column".to_owned(),
+        ));
+    }
+
+    let mut cells = Vec::with_capacity(screenshots.len());
+    for data in screenshots.iter() {
+        let image = image::load_from_memory(data)?;
+        cells.push(image.resize(
+            CONTACT_SHEET_CELL_WIDTH,
+            CONTACT_SHEET_MAX_CELL_HEIGHT,
+            FilterType::Triangle,
+        ));
+    }
+
+    let columns = columns.min(cells.len() as u32);
+    let rows = (cells.len() as u32).div_ceil(columns);
+    let cell_height = cells.iter().map(|cell| cell.height()).max().unwrap_or(1);
+
+    let mut sheet = RgbImage::new(columns * CONTACT_SHEET_CELL_WIDTH, rows * cell_height);
+    for (i, cell) in cells.iter().enumerate() {
+        let i = i as u32;
+        image::imageops::overlay(
+            &mut sheet,
+            &cell.to_rgb8(),
+            ((i % columns) * CONTACT_SHEET_CELL_WIDTH) as i64,
+            ((i / columns) * cell_height) as i64,
+        );
+    }
+
+    debug!(
+        "Created contact sheet of {} screenshots with {}x{} cells",
+        cells.len(),
+        columns,
+        rows
+    );
+
+    let mut sheet_data = Cursor::new(Vec::new());
+    DynamicImage::ImageRgb8(sheet).write_to(&mut sheet_data, ImageOutputFormat::Png)?;
+
+    Ok(sheet_data.into_inner())
+}
+
+#[cfg(test)]
+mod test {
+    use image::{DynamicImage, GenericImageView};
+
+    use super::*;
+
+    fn create_test_image(width: u32, height: u32) -> Vec<u8> {
+        let image = DynamicImage::new_rgb8(width, height);
+        let mut data = Cursor::new(Vec::new());
+        image.write_to(&mut data, ImageOutputFormat::Png).unwrap();
+
+        data.into_inner()
+    }
+
+    #[test]
+    fn test_get_resize_bounds() {
+        assert_eq!(get_resize_bounds(Some(320), Some(180)), (320, 180));
+        assert_eq!(
+            get_resize_bounds(Some(320), None),
+            (320, MAX_RESIZED_SCREENSHOT_SIZE)
+        );
+        assert_eq!(
+            get_resize_bounds(Some(0), Some(100000)),
+            (64, MAX_RESIZED_SCREENSHOT_SIZE)
+        );
+
+        // sizes in between are rounded down, s.t. the screenshot still fits
+        assert_eq!(get_resize_bounds(Some(100), Some(1000)), (90, 720));
+        assert_eq!(get_resize_bounds(Some(321), Some(179)), (320, 128));
+    }
+
+    #[test]
+    fn test_resize_screenshot() {
+        let data = create_test_image(640, 360);
+
+        let resized = resize_screenshot(&data, 320, 320).unwrap();
+        let image = image::load_from_memory(&resized).unwrap();
+        assert_eq!(image.dimensions(), (320, 180));
+
+        // no upscaling
+        let resized = resize_screenshot(&data, 1000, 1000).unwrap();
+        let image = image::load_from_memory(&resized).unwrap();
+        assert_eq!(image.dimensions(), (640, 360));
+
+        assert!(resize_screenshot(b"no image", 320, 180).is_err());
+    }
+
+    #[test]
+    fn test_create_contact_sheet() {
+        let screenshots = vec![
+            create_test_image(640, 360),
+            create_test_image(640, 360),
+            create_test_image(640, 480),
+        ];
+
+        // the rows are as high as the highest screenshot
+        let sheet = create_contact_sheet(&screenshots, 2).unwrap();
+        let image = image::load_from_memory(&sheet).unwrap();
+        assert_eq!(image.dimensions(), (640, 480));
+
+        // too many columns are reduced to a single row
+        let sheet = create_contact_sheet(&screenshots, 5).unwrap();
+        let image = image::load_from_memory(&sheet).unwrap();
+        assert_eq!(image.dimensions(), (960, 240));
+
+        assert!(create_contact_sheet(&screenshots, 0).is_err());
+        assert!(create_contact_sheet(&[], 2).is_err());
+        assert!(create_contact_sheet(&[b"no image".to_vec()], 2).is_err());
+    }
+
+    #[test]
+    fn test_create_contact_sheet_extreme_aspect_ratio() {
+        let screenshots = vec![create_test_image(1, 100_000), create_test_image(640, 360)];
+
+        // the tall screenshot is fit into the maximal cell height
+        let sheet = create_contact_sheet(&screenshots, 2).unwrap();
+        let image = image::load_from_memory(&sheet).unwrap();
+        assert_eq!(
+            image.dimensions(),
+            (2 * CONTACT_SHEET_CELL_WIDTH, CONTACT_SHEET_MAX_CELL_HEIGHT)
+        );
+    }
+}
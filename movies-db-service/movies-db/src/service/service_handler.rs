@@ -1,34 +1,438 @@
 use crate::{
-    Error, Movie, MovieDataType, MovieId, MovieSearchQuery, MovieStorage, MoviesIndex,
-    ReadResource, ScreenshotInfo,
+    generate_movie_id, lru::Lru, DownloadOffload, Error, FilenameTagsPolicy, JobKind, Movie,
+    MovieDataType, MovieDetailed, MovieFileInfo, MovieId, MovieSearchQuery, MovieStorage,
+    MoviesIndex, NoVideoStreamPolicy, Options, PreviewBackpressurePolicy, PreviewState,
+    ReadResource, ScreenshotInfo, SearchFacets, SortingField, SortingOrder, SubtitleInfo,
 };
 
 use actix_multipart::Multipart;
 use actix_web::body::SizedStream;
-use actix_web::http::header::{self, ByteRangeSpec};
+use actix_web::http::header::{
+    self, ByteRangeSpec, CacheControl, CacheDirective, EntityTag, HttpDate, IfNoneMatch, IfRange,
+};
 use actix_web::HttpResponse;
-use actix_web::{web, Responder, Result};
-use futures::{StreamExt, TryStreamExt};
-use log::{debug, error, info};
+use actix_web::{web, Either, Responder, Result};
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt, TryStreamExt};
+use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
 use std::io::SeekFrom;
-use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::io::{AsyncSeekExt, AsyncWriteExt};
-use tokio::sync::{mpsc, RwLock};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{
+    AsyncBufRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWriteExt, BufReader, BufWriter,
+};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{broadcast, mpsc, RwLock};
 
 use tokio_util::io::ReaderStream;
+use tokio_util::sync::CancellationToken;
+
+use super::active_jobs::ActiveJobs;
+use super::activity_log::{ActivityKind, ActivityLog};
+use super::authorizer::{Action, AllowAll, Authorizer, RequestContext};
+use super::bundle::{self, BundleCompression};
+use super::feed::create_rss_feed;
+use super::ffmpeg::FFMpeg;
+use super::filename_tags::extract_filename_tags;
+use super::fuzzy_tags::correct_tag;
+use super::idle_timeout::IdleTimeoutStream;
+use super::image_hash::{cluster_similar, store_screenshot_hash};
+use super::movie_locks::MovieLocks;
+use super::preview_generator::{archive_screenshot, PendingPreviews, ScreenshotRequest};
+use super::screenshot_cache::{read_screenshot, ScreenshotCache};
+use super::screenshot_resizer;
+use super::share_token::{ShareSigner, ShareToken};
+use super::upload_limits::{UploadLimits, UploadPermit};
+
+/// The maximal length of the extension of an uploaded file.
+const MAX_EXTENSION_LENGTH: usize = 8;
+
+/// The maximal number of characters of the original filename kept in the movie file info.
+const MAX_ORIGINAL_FILENAME_LENGTH: usize = 255;
+
+/// The duration in seconds for which clients may cache the placeholder screenshot, which is kept
+/// short, s.t. the actual screenshot is shown soon after it has been generated.
+const PLACEHOLDER_SCREENSHOT_MAX_AGE: u32 = 60;
+
+/// The header marking a response as the placeholder screenshot.
+const PLACEHOLDER_HEADER: &str = "X-Placeholder";
+
+/// The header marking a list as truncated by a server-side limit.
+const TRUNCATED_HEADER: &str = "X-Truncated";
+
+/// The hint attached to truncated tag lists.
+const TRUNCATED_TAGS_WARNING: &str =
+    "199 - \"The tag list is truncated, use /tags/top or /tags/suggest instead\"";
+
+/// The format into which movies are transcoded on the fly.
+const TRANSCODING_FORMAT: &str = "mp4";
+
+/// The mime type of movies transcoded on the fly.
+const TRANSCODING_MIME_TYPE: &str = "video/mp4";
+
+/// The number of movies in a feed if the query doesn't limit the number of results.
+const DEFAULT_FEED_ITEMS: usize = 50;
+
+/// The mime type of the movie feeds.
+const FEED_MIME_TYPE: &str = "application/rss+xml; charset=utf-8";
+
+/// Returns true if the given extension can be safely used in the name of a stored file, i.e., if
+/// it consists of at most `MAX_EXTENSION_LENGTH` ASCII alphanumeric characters.
+///
+/// # Arguments
+/// * `extension` - The extension to check.
+fn is_valid_extension(extension: &str) -> bool {
+    !extension.is_empty()
+        && extension.len() <= MAX_EXTENSION_LENGTH
+        && extension.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Returns true if the given content type denotes a subtitle, i.e., a text format like WebVTT or
+/// SubRip, which some clients report as `application/x-subrip`.
+///
+/// # Arguments
+/// * `content_type` - The content type of the upload.
+fn is_subtitle_content_type(content_type: &str) -> bool {
+    content_type.starts_with("text/") || content_type == "application/x-subrip"
+}
+
+/// Returns the lower case extension of the given filename of an uploaded file. The stored file
+/// is named by the service, s.t. the extension is the only part of the filename that is used.
+/// Filenames with path components, control characters or an unsafe extension are rejected.
+///
+/// # Arguments
+/// * `filename` - The filename provided by the client.
+fn get_upload_extension(filename: &str) -> Result<String, Error> {
+    if filename.contains(['/', '\\']) || filename.chars().any(char::is_control) {
+        return Err(Error::InvalidArgument(format!(
+            "Invalid filename {:?}",
+            filename
+        )));
+    }
+
+    match filename.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() && is_valid_extension(extension) => {
+            Ok(extension.to_lowercase())
+        }
+        _ => Err(Error::InvalidArgument(format!(
+            "Invalid extension of filename {:?}",
+            filename
+        ))),
+    }
+}
+
+/// Returns the mime type of an image with the given lower case file extension.
+///
+/// # Arguments
+/// * `ext` - The file extension of the image, e.g., "png".
+fn get_image_mime_type(ext: &str) -> String {
+    match ext {
+        "jpg" | "jpeg" => "image/jpeg".to_owned(),
+        "svg" => "image/svg+xml".to_owned(),
+        ext => format!("image/{}", ext),
+    }
+}
+
+/// Checks that an upload request doesn't consist of more fields than allowed, s.t. a request
+/// with thousands of tiny fields can't tie up a worker.
+///
+/// # Arguments
+/// * `num_fields` - The number of fields received so far, including the current one.
+/// * `max_fields` - The maximal number of fields per upload request.
+fn check_multipart_field_count(num_fields: usize, max_fields: usize) -> Result<(), Error> {
+    if num_fields > max_fields {
+        return Err(Error::InvalidArgument(format!(
+            "The upload consists of more than {} fields",
+            max_fields
+        )));
+    }
+
+    Ok(())
+}
+
+/// Returns the given filename of an uploaded file truncated to `MAX_ORIGINAL_FILENAME_LENGTH`
+/// characters.
+///
+/// # Arguments
+/// * `filename` - The filename provided by the client.
+fn bound_original_filename(filename: &str) -> String {
+    filename
+        .chars()
+        .take(MAX_ORIGINAL_FILENAME_LENGTH)
+        .collect()
+}
 
-use super::preview_generator::ScreenshotRequest;
+/// Returns the ETag of the stored movie data, which is derived from its size and its time of
+/// last modification, s.t. a replaced movie file gets a new ETag.
+///
+/// # Arguments
+/// * `size` - The size of the movie data in bytes.
+/// * `last_modified` - The time of the last modification of the movie data.
+fn movie_data_etag(size: u64, last_modified: SystemTime) -> EntityTag {
+    let modified = last_modified
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    EntityTag::new_strong(format!("{:x}-{:x}", size, modified))
+}
+
+/// Returns true if the validator of the given `If-Range` header still matches the movie data,
+/// i.e., if a partial response can be safely stitched together with the data the client already
+/// has. Entity tags must match strongly and dates must match the time of last modification with
+/// the precision of an HTTP date, i.e., in seconds.
+///
+/// # Arguments
+/// * `if_range` - The `If-Range` header sent by the client.
+/// * `etag` - The ETag of the movie data, if known.
+/// * `last_modified` - The time of the last modification of the movie data, if known.
+fn if_range_matches(
+    if_range: &IfRange,
+    etag: Option<&EntityTag>,
+    last_modified: Option<SystemTime>,
+) -> bool {
+    match if_range {
+        IfRange::EntityTag(tag) => etag.is_some_and(|etag| tag.strong_eq(etag)),
+        IfRange::Date(date) => {
+            last_modified.is_some_and(|m| HttpDate::from(m).to_string() == date.to_string())
+        }
+    }
+}
+
+/// The time after which an upload token expires, unless its upload has been started.
+const UPLOAD_TOKEN_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The maximal number of upload tokens, s.t. clients can't exhaust the memory by starting
+/// uploads without ever uploading anything.
+const MAX_UPLOAD_TOKENS: usize = 1024;
+
+/// The size of the in-memory pipe used for streaming bundles to the client.
+const BUNDLE_PIPE_SIZE: usize = 64 * 1024;
+
+/// The number of columns of a contact sheet, unless requested otherwise.
+const DEFAULT_CONTACT_SHEET_COLUMNS: u32 = 4;
+
+/// The maximal total size of the cached contact sheets in bytes.
+const CONTACT_SHEET_CACHE_SIZE: usize = 32 * 1024 * 1024;
 
 pub struct ServiceHandler<I, S>
 where
     I: MoviesIndex,
     S: MovieStorage,
 {
+    options: Options,
     index: Arc<RwLock<I>>,
     storage: Arc<RwLock<S>>,
-    preview_requests: mpsc::UnboundedSender<ScreenshotRequest>,
+    preview_requests: mpsc::Sender<ScreenshotRequest>,
+
+    /// The movies with queued or in-flight preview requests.
+    pending_previews: PendingPreviews,
+
+    /// The jobs currently processing movies apart from the previews, e.g., uploads.
+    active_jobs: ActiveJobs,
+
+    /// The log of the recent activity.
+    activity_log: ActivityLog,
+
+    /// The uploads in progress, which can be cancelled by their upload token.
+    uploads: Mutex<HashMap<String, InFlightUpload>>,
+
+    /// The locks serializing write operations on the same movie.
+    movie_locks: MovieLocks,
+
+    /// The signer for the URLs of shared movies, if sharing is enabled.
+    share_signer: Option<ShareSigner>,
+
+    /// The ffmpeg instance for transcoding movies on the fly, if available.
+    ffmpeg: Option<FFMpeg>,
+
+    /// The hook deciding whether a request may operate on a movie.
+    authorizer: Arc<dyn Authorizer>,
+
+    /// The flag marking the service as read-only, which is shared by all libraries.
+    read_only: Arc<AtomicBool>,
+
+    /// The limits of the concurrent uploads per client, which are shared by all libraries.
+    upload_limits: Arc<UploadLimits>,
+
+    /// The in-memory cache of the main screenshots.
+    screenshot_cache: ScreenshotCache,
+
+    /// The in-memory cache of the contact sheets, keyed by the hash of their screenshots and
+    /// their number of columns.
+    contact_sheets: Mutex<Lru<u64, web::Bytes>>,
+}
+
+/// An upload in progress, which can be cancelled by its upload token.
+struct InFlightUpload {
+    /// The id of the movie the upload belongs to.
+    movie_id: MovieId,
+
+    /// The token for cancelling the upload.
+    cancellation: CancellationToken,
+
+    /// The time at which the upload token expires, unless the upload has been started.
+    expires: Instant,
+
+    /// True once the upload has been started, s.t. the upload token no longer expires.
+    started: bool,
+}
+
+impl InFlightUpload {
+    /// Returns true if the upload token has expired without the upload being started.
+    fn is_expired(&self) -> bool {
+        !self.started && self.expires <= Instant::now()
+    }
+}
+
+/// The status of the preview of a movie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PreviewStatus {
+    /// The movie does not exist.
+    NotFound,
+
+    /// The preview generation is queued or in progress.
+    Pending,
+
+    /// The preview has been generated.
+    Ready,
+
+    /// There is no preview and none is being generated, e.g., as the movie has no movie file.
+    Missing,
+}
+
+/// The outcome of queueing a preview request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PreviewRequestOutcome {
+    /// The request has been queued.
+    Queued,
+
+    /// The request has been dropped, as the queue is full.
+    QueueFull,
+
+    /// The request could not be queued, e.g., as previews are not generated at all.
+    Failed,
+}
+
+/// The response body if the preview of a movie has been deferred, as the queue is full.
+#[derive(Debug, Serialize, Deserialize)]
+struct DeferredPreview {
+    id: MovieId,
+
+    /// The number of queued preview requests.
+    queue_depth: usize,
+
+    /// The number of seconds after which the preview should be requested again.
+    retry_after: u32,
+}
+
+/// The response body for a shared movie.
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedMovie {
+    /// The signed URL for downloading the movie.
+    url: String,
+
+    /// The time at which the URL expires.
+    expires: DateTime<Utc>,
+}
+
+/// The data derived from a movie, which can be regenerated by the reprocess endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DerivedArtifact {
+    /// The screenshot generated from the movie file.
+    Preview,
+
+    /// The resized variants of the screenshot, which are discarded and rebuilt lazily from the
+    /// current screenshot when they are requested the next time.
+    Thumbnails,
+
+    /// The perceptual hash of the screenshot.
+    ScreenshotHash,
+}
+
+/// The status of regenerating a derived artifact of a movie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ReprocessStatus {
+    /// The artifact has been regenerated.
+    Done,
+
+    /// The regeneration has been queued, e.g., the preview generation.
+    Queued,
+
+    /// The artifact has been discarded and is regenerated lazily when it is requested the next
+    /// time, e.g., the resized screenshots.
+    Invalidated,
+
+    /// The artifact could not be regenerated.
+    Failed,
+}
+
+/// The result of regenerating a single derived artifact of a movie.
+#[derive(Debug, Serialize, Deserialize)]
+struct ArtifactResult {
+    artifact: DerivedArtifact,
+    status: ReprocessStatus,
+
+    /// The reason why the artifact could not be regenerated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// The results of regenerating the derived artifacts of a movie.
+#[derive(Debug, Serialize, Deserialize)]
+struct ReprocessResult {
+    id: MovieId,
+    artifacts: Vec<ArtifactResult>,
+}
+
+/// The result of backfilling the video metadata of the movies.
+#[derive(Debug, Serialize, Deserialize)]
+struct ProbeAllResult {
+    /// The number of movies having a movie file, but no video metadata.
+    movies: usize,
+
+    /// The number of movies whose video metadata has been backfilled.
+    probed: usize,
+
+    /// The number of movies whose movie file could not be probed.
+    failed: usize,
+}
+
+/// The status of a movie as reported by the batch status endpoint.
+#[derive(Debug, Serialize, Deserialize)]
+struct MovieStatus {
+    id: MovieId,
+
+    /// Whether a movie file has been uploaded.
+    has_file: bool,
+
+    /// Whether the movie has a screenshot.
+    has_screenshot: bool,
+
+    /// The status of the preview generation.
+    preview: PreviewStatus,
+}
+
+/// The response for starting a cancelable upload.
+#[derive(Debug, Serialize, Deserialize)]
+struct UploadToken {
+    token: String,
+}
+
+/// The response of an upload if the tags extracted from the filename are suggested.
+#[derive(Debug, Serialize, Deserialize)]
+struct UploadedMovie {
+    /// The tags extracted from the filename, which the movie doesn't have yet.
+    suggested_tags: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -37,6 +441,63 @@ struct MovieListEntry {
     title: String,
 }
 
+/// The read-only mode of the service.
+#[derive(Debug, Serialize, Deserialize)]
+struct ReadOnlyMode {
+    read_only: bool,
+}
+
+/// The offset of the first movie whose title starts with a given letter.
+#[derive(Debug, Serialize, Deserialize)]
+struct LetterOffset {
+    offset: usize,
+}
+
+/// A previous screenshot of a movie, which can be restored.
+#[derive(Debug, Serialize, Deserialize)]
+struct ScreenshotVersion {
+    /// The version of the screenshot, where greater versions have been replaced more recently.
+    version: u32,
+
+    /// The file extension of the screenshot.
+    extension: String,
+}
+
+/// The number of movies whose tag has been renamed.
+#[derive(Debug, Serialize, Deserialize)]
+struct RenamedTag {
+    movies: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MovieSizeEntry {
+    id: MovieId,
+    title: String,
+
+    /// The size of the stored movie file in bytes.
+    size: u64,
+}
+
+/// A page of the movies matching a search together with the total number of matches.
+#[derive(Debug, Serialize, Deserialize)]
+struct SearchResult {
+    /// The number of all matching movies regardless of the pagination.
+    total: usize,
+
+    /// The index of the first returned movie among all matching movies.
+    start_index: usize,
+
+    /// The returned movies.
+    results: Vec<MovieListEntry>,
+}
+
+/// The result of a faceted search, i.e., the matching movies and the facets of all matches.
+#[derive(Debug, Serialize, Deserialize)]
+struct FacetedSearchResult {
+    items: Vec<MovieListEntry>,
+    facets: SearchFacets,
+}
+
 impl<I, S> ServiceHandler<I, S>
 where
     I: MoviesIndex,
@@ -45,27 +506,173 @@ where
     /// Creates a new instance of the service handler.
     ///
     /// # Arguments
+    /// * `options` - The options for the service.
     /// * `index` - The movies index.
     /// * `storage` - The movie storage.
     /// * `preview_requests` - The channel for sending preview requests.
+    /// * `pending_previews` - The movies with queued or in-flight preview requests.
+    /// * `activity_log` - The log of the recent activity.
     pub async fn new(
+        options: &Options,
         index: Arc<RwLock<I>>,
         storage: Arc<RwLock<S>>,
-        preview_requests: mpsc::UnboundedSender<ScreenshotRequest>,
+        preview_requests: mpsc::Sender<ScreenshotRequest>,
+        pending_previews: PendingPreviews,
+        activity_log: ActivityLog,
     ) -> Result<Self, Error> {
         Ok(Self {
+            options: options.clone(),
             index,
             storage,
             preview_requests,
+            pending_previews,
+            active_jobs: ActiveJobs::default(),
+            activity_log,
+            uploads: Mutex::new(HashMap::new()),
+            movie_locks: MovieLocks::new(options.lock_granularity),
+            share_signer: options.share_secret.as_deref().map(ShareSigner::new),
+            ffmpeg: None,
+            authorizer: Arc::new(AllowAll),
+            read_only: Arc::new(AtomicBool::new(false)),
+            upload_limits: Arc::new(UploadLimits::new(options.max_uploads_per_client)),
+            screenshot_cache: ScreenshotCache::new(options.screenshot_memory_cache_size),
+            contact_sheets: Mutex::new(Lru::new(CONTACT_SHEET_CACHE_SIZE)),
         })
     }
 
+    /// Returns the handler with the given ffmpeg instance for transcoding movies on the fly.
+    ///
+    /// # Arguments
+    /// * `ffmpeg` - The ffmpeg instance.
+    pub fn with_ffmpeg(mut self, ffmpeg: FFMpeg) -> Self {
+        self.ffmpeg = Some(ffmpeg);
+        self
+    }
+
+    /// Returns the handler with the given authorizer for the operations on single movies.
+    ///
+    /// # Arguments
+    /// * `authorizer` - The authorizer, which replaces the default of allowing every request.
+    pub fn with_authorizer(mut self, authorizer: Arc<dyn Authorizer>) -> Self {
+        self.authorizer = authorizer;
+        self
+    }
+
+    /// Returns the handler with the given flag marking the service as read-only, s.t. the
+    /// read-only mode can be shared with the handlers of other libraries.
+    ///
+    /// # Arguments
+    /// * `read_only` - The shared read-only flag.
+    pub fn with_read_only_flag(mut self, read_only: Arc<AtomicBool>) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Returns the handler with the given limits of the concurrent uploads per client, s.t. the
+    /// limits can be shared with the handlers of other libraries.
+    ///
+    /// # Arguments
+    /// * `upload_limits` - The shared upload limits.
+    pub fn with_upload_limits(mut self, upload_limits: Arc<UploadLimits>) -> Self {
+        self.upload_limits = upload_limits;
+        self
+    }
+
+    /// Returns the handler with the given in-memory screenshot cache, s.t. the cache can be kept
+    /// up to date by the preview generator.
+    ///
+    /// # Arguments
+    /// * `screenshot_cache` - The shared screenshot cache.
+    pub fn with_screenshot_cache(mut self, screenshot_cache: ScreenshotCache) -> Self {
+        self.screenshot_cache = screenshot_cache;
+        self
+    }
+
+    /// Loads the screenshots of the most recently added movies into the in-memory screenshot
+    /// cache, s.t. the first page of movies is served without reading the storage. The number of
+    /// movies is given by `Options::screenshot_warm_up_count`. Does nothing if the cache is
+    /// disabled. Returns the number of loaded screenshots.
+    pub async fn warm_up_screenshot_cache(&self) -> usize {
+        if !self.screenshot_cache.is_enabled() || self.options.screenshot_warm_up_count == 0 {
+            return 0;
+        }
+
+        info!("Warming up the screenshot cache ...");
+
+        let query = MovieSearchQuery {
+            sorting_field: SortingField::Date,
+            sorting_order: SortingOrder::Descending,
+            num_results: Some(self.options.screenshot_warm_up_count),
+            ..Default::default()
+        };
+
+        let index = self.index.read().await;
+        let ids = match index.search_movies(query).await {
+            Ok(ids) => ids,
+            Err(err) => {
+                warn!(
+                    "Failed to find the recent movies for the screenshot cache: {}",
+                    err
+                );
+                return 0;
+            }
+        };
+
+        // the most recent screenshots are inserted last, s.t. they are evicted last
+        let mut num_loaded = 0;
+        for id in ids.into_iter().rev() {
+            let extension = match index.get_movie(&id).await {
+                Ok(movie) => match movie.screenshot_file_info {
+                    Some(screenshot_info) => screenshot_info.extension,
+                    None => continue,
+                },
+                Err(err) => {
+                    warn!(
+                        "Failed to get movie {} for the screenshot cache: {}",
+                        id, err
+                    );
+                    continue;
+                }
+            };
+
+            match read_screenshot(&self.storage, &id, &extension).await {
+                Ok(data) => {
+                    self.screenshot_cache.insert(id, &extension, data);
+                    num_loaded += 1;
+                }
+                Err(err) => warn!("Failed to read screenshot of movie {}: {}", id, err),
+            }
+        }
+
+        info!(
+            "Warming up the screenshot cache ... DONE, loaded {} screenshots",
+            num_loaded
+        );
+
+        num_loaded
+    }
+
     /// Handles the request to add a new movie.
     ///
     /// # Arguments
     /// * `movie` - The movie to add.
-    pub async fn handle_add_movie(&self, movie: Movie) -> Result<impl Responder> {
-        match self.index.write().await.add_movie(movie).await {
+    /// * `id` - Optionally, the id for the new movie. If not provided, a new id is generated.
+    pub async fn handle_add_movie(
+        &self,
+        mut movie: Movie,
+        id: Option<MovieId>,
+    ) -> Result<impl Responder> {
+        if let Err(err) = self.limit_description(&mut movie) {
+            return Self::handle_error(err);
+        }
+
+        let title = movie.title.clone();
+        let ret = match id {
+            Some(id) => self.index.write().await.add_movie_with_id(id, movie).await,
+            None => self.index.write().await.add_movie(movie).await,
+        };
+
+        match ret {
             Ok(movie_id) => match self
                 .storage
                 .read()
@@ -73,206 +680,487 @@ where
                 .allocate_movie_data(movie_id.clone())
                 .await
             {
-                Ok(()) => Ok(movie_id),
-                Err(err) => Self::handle_error(err),
+                Ok(()) => {
+                    self.activity_log.record(
+                        ActivityKind::MovieAdded,
+                        Some(&movie_id),
+                        format!("Added movie '{}'", title),
+                    );
+
+                    Ok(movie_id)
+                }
+                Err(err) => {
+                    // the movie is unusable without its data directory, e.g., if the disk is full
+                    if let Err(err) = self.index.write().await.remove_movie(&movie_id).await {
+                        error!("Failed to roll back movie {}: {}", movie_id, err);
+                    }
+
+                    Self::handle_error(err)
+                }
             },
             Err(err) => Self::handle_error(err),
         }
     }
 
-    /// Handles the request to get a new movie.
+    /// Handles the request to add multiple new movies at once, e.g., for a bulk import. Returns
+    /// the ids of the new movies in the order of the given movies.
     ///
     /// # Arguments
-    /// * `movie` - The movie to get.
-    pub async fn handle_get_movie(&self, id: MovieId) -> Result<impl Responder> {
-        match self.index.read().await.get_movie(&id).await {
-            Ok(movie) => Ok(web::Json(movie)),
-            Err(err) => Self::handle_error(err),
+    /// * `movies` - The movies to add.
+    pub async fn handle_add_movies(&self, mut movies: Vec<Movie>) -> Result<impl Responder> {
+        for movie in movies.iter_mut() {
+            if let Err(err) = self.limit_description(movie) {
+                return Self::handle_error(err);
+            }
         }
-    }
 
-    /// Handles the request to delete a new movie.
-    ///
-    /// # Arguments
-    /// * `movie` - The movie to get.
-    pub async fn handle_delete_movie(&self, id: MovieId) -> Result<impl Responder> {
-        match self.index.write().await.remove_movie(&id).await {
-            Ok(()) => match self.storage.read().await.remove_movie_data(id).await {
-                Ok(_) => Ok(actix_web::HttpResponse::Ok()),
-                Err(err) => {
-                    error!("Error deleting movie: {}", err);
-                    Err(actix_web::error::ErrorInternalServerError(err))
+        let titles: Vec<String> = movies.iter().map(|movie| movie.title.clone()).collect();
+        let ids = match self.index.write().await.add_movies(movies).await {
+            Ok(ids) => ids,
+            Err(err) => return Self::handle_error(err),
+        };
+
+        if let Err(err) = self.storage.read().await.allocate_movies_data(&ids).await {
+            // the movies are unusable without their data directories, e.g., if the disk is full
+            let mut index = self.index.write().await;
+            for id in ids.iter() {
+                if let Err(err) = index.remove_movie(id).await {
+                    error!("Failed to roll back movie {}: {}", id, err);
                 }
-            },
-            Err(err) => Self::handle_error(err),
+            }
+
+            return Self::handle_error(err);
+        }
+
+        for (id, title) in ids.iter().zip(titles) {
+            self.activity_log.record(
+                ActivityKind::MovieAdded,
+                Some(id),
+                format!("Added movie '{}'", title),
+            );
         }
+
+        Ok(web::Json(ids))
     }
 
-    /// Handles the request to upload a movie.
+    /// Handles the request to share the given movie, i.e., to create a signed URL for downloading
+    /// the movie without further credentials until the given time-to-live has passed.
     ///
     /// # Arguments
-    /// * `id` - The id of the movie to upload.
-    /// * `multipart` - The multipart data of the movie.
-    pub async fn handle_upload_movie(
+    /// * `id` - The id of the movie to share.
+    /// * `ttl` - The time-to-live of the URL in seconds.
+    /// * `base_path` - The path of the movie endpoints, e.g., `/api/v1/movie`.
+    pub async fn handle_share_movie(
         &self,
         id: MovieId,
-        mut multipart: Multipart,
+        ttl: u32,
+        base_path: &str,
     ) -> Result<impl Responder> {
-        info!("Uploading movie {} ...", id);
+        let signer = match &self.share_signer {
+            Some(signer) => signer,
+            None => {
+                return Self::handle_error(Error::InvalidArgument(
+                    "Sharing movies is not enabled".to_owned(),
+                ))
+            }
+        };
 
-        // iterate over multipart stream
-        while let Ok(Some(mut field)) = multipart.try_next().await {
-            // extract the filename
-            let content_type = field.content_disposition();
-            let filename: PathBuf = match content_type.get_filename() {
-                Some(filename) => PathBuf::from(filename),
-                None => {
-                    error!("Invalid filename");
-                    return Err(actix_web::error::ErrorBadRequest("Invalid filename"));
-                }
-            };
+        self.check_movie_exists(&id).await?;
 
-            // extract content type information
-            let content_type: String = match field.headers().get(header::CONTENT_TYPE) {
-                Some(content_type) => content_type.to_str().unwrap().to_string(),
-                None => {
-                    error!("Invalid content type");
-                    return Err(actix_web::error::ErrorBadRequest("Invalid content type"));
-                }
-            };
+        let expires = Utc::now() + chrono::Duration::seconds(ttl.into());
+        let token = signer.sign(&id, expires);
+        let url = format!(
+            "{}/file?id={}&expires={}&signature={}",
+            base_path, id, token.expires, token.signature
+        );
 
-            // check if the content type is a video
-            if !content_type.starts_with("video") {
-                error!("Invalid content type");
-                return Err(actix_web::error::ErrorUnsupportedMediaType(
-                    "Invalid content type",
-                ));
-            }
+        info!("Shared movie {} until {}", id, expires);
 
-            info!(
-                "Uploading file {:?} with mime-type {}",
-                filename, content_type
-            );
+        Ok(web::Json(SharedMovie { url, expires }))
+    }
 
-            // extract the extension
-            let ext = match filename.extension() {
-                Some(ext) => match ext.to_str() {
-                    Some(ext) => ext.to_string(),
-                    None => {
-                        error!("Invalid extension");
-                        return Err(actix_web::error::ErrorBadRequest("Invalid extension"));
-                    }
-                },
-                None => {
-                    error!("Invalid extension");
-                    return Err(actix_web::error::ErrorBadRequest("Invalid extension"));
-                }
-            };
+    /// Checks the given share token, which grants access to the downloads of the given movie.
+    /// Expired or tampered tokens and tokens of other movies are rejected.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to download.
+    /// * `token` - The share token provided by the client.
+    pub fn handle_check_share_token(&self, id: &MovieId, token: &ShareToken) -> Result<()> {
+        let ret = match &self.share_signer {
+            Some(signer) => signer.verify(id, token, Utc::now()),
+            None => Err(Error::PermissionDenied(
+                "Sharing movies is not enabled".to_owned(),
+            )),
+        };
 
-            debug!("Uploading file with extension: {:?}", ext);
+        match ret {
+            Ok(()) => Ok(()),
+            Err(err) => Self::handle_error(err),
+        }
+    }
 
-            // open writer for storing movie data
-            let mut writer = match self
+    /// Checks whether the request of the given context may perform the given action on the
+    /// given movie. Denied requests are rejected with `403 Forbidden`.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context of the request.
+    /// * `id` - The id of the movie.
+    /// * `action` - The action to perform.
+    pub fn handle_check_access(
+        &self,
+        ctx: &RequestContext,
+        id: &MovieId,
+        action: Action,
+    ) -> Result<()> {
+        if self.authorizer.can_access(ctx, id, action) {
+            return Ok(());
+        }
+
+        Self::handle_error(Error::PermissionDenied(format!(
+            "{:?} access to movie {} denied",
+            action, id
+        )))
+    }
+
+    /// Checks that the service accepts writes, i.e., that it isn't in read-only mode. Otherwise,
+    /// mutating requests are rejected with 503 Service Unavailable while reads continue.
+    pub fn handle_check_writable(&self) -> Result<()> {
+        if !self.read_only.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        warn!("Rejected a mutating request in read-only mode");
+        Err(actix_web::error::ErrorServiceUnavailable(
+            "The service is in read-only mode for maintenance",
+        ))
+    }
+
+    /// Starts an upload of the client of the given request. If the client already runs the
+    /// maximal number of concurrent uploads, the upload is rejected with 429 Too Many Requests.
+    /// The returned permit must be held until the upload finishes or aborts.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context of the request.
+    pub fn handle_acquire_upload_permit(&self, ctx: &RequestContext) -> Result<UploadPermit> {
+        let client = UploadLimits::client_key(ctx);
+        match self.upload_limits.try_acquire(&client) {
+            Some(permit) => Ok(permit),
+            None => {
+                warn!("Rejected an upload of client {}, too many uploads", client);
+                Err(actix_web::error::ErrorTooManyRequests(
+                    "Too many concurrent uploads",
+                ))
+            }
+        }
+    }
+
+    /// Handles the request to switch the read-only mode of the service on or off. The mode
+    /// applies to all libraries of the service.
+    ///
+    /// # Arguments
+    /// * `read_only` - True to reject all mutating requests, false to accept them again.
+    pub fn handle_set_read_only(&self, read_only: bool) -> Result<impl Responder> {
+        self.read_only.store(read_only, Ordering::SeqCst);
+        info!(
+            "Service switched to {} mode",
+            if read_only { "read-only" } else { "read-write" }
+        );
+
+        Ok(web::Json(ReadOnlyMode { read_only }))
+    }
+
+    /// Handles the request to get a new movie. The response reports whether the movie is
+    /// currently being processed.
+    ///
+    /// # Arguments
+    /// * `movie` - The movie to get.
+    pub async fn handle_get_movie(&self, id: MovieId) -> Result<impl Responder> {
+        match self.index.read().await.get_movie(&id).await {
+            Ok(mut movie) => {
+                movie.active_jobs = self.get_active_jobs(&id);
+                movie.processing = !movie.active_jobs.is_empty();
+
+                Ok(Self::create_metadata_response(&movie))
+            }
+            Err(err) => Self::handle_error(err),
+        }
+    }
+
+    /// Returns the kinds of the jobs currently processing the given movie, i.e., its queued or
+    /// in-flight preview request and the other tracked jobs, ordered by the kind.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    fn get_active_jobs(&self, id: &MovieId) -> Vec<JobKind> {
+        let mut kinds = self.active_jobs.get(id);
+        if self.pending_previews.contains(id) {
+            kinds.push(JobKind::Preview);
+            kinds.sort();
+        }
+
+        kinds
+    }
+
+    /// Handles the request to delete a new movie.
+    ///
+    /// # Arguments
+    /// * `movie` - The movie to get.
+    pub async fn handle_delete_movie(&self, id: MovieId) -> Result<impl Responder> {
+        let _movie_lock = self.movie_locks.lock(&id).await;
+
+        match self.index.write().await.remove_movie(&id).await {
+            Ok(()) => match self
                 .storage
                 .read()
                 .await
-                .write_movie_data(id.clone(), MovieDataType::MovieData { ext: ext.clone() })
+                .remove_movie_data(id.clone())
                 .await
             {
-                Ok(writer) => writer,
+                Ok(_) => {
+                    self.screenshot_cache.remove(&id);
+                    self.activity_log.record(
+                        ActivityKind::MovieDeleted,
+                        Some(&id),
+                        format!("Deleted movie {}", id),
+                    );
+
+                    Ok(actix_web::HttpResponse::Ok())
+                }
                 Err(err) => {
-                    return Self::handle_error(err);
+                    error!("Error deleting movie: {}", err);
+                    Err(actix_web::error::ErrorInternalServerError(err))
                 }
-            };
+            },
+            Err(err) => Self::handle_error(err),
+        }
+    }
 
-            // Field in turn is stream of *Bytes* object
-            while let Some(chunk) = field.next().await {
-                let data = match chunk {
-                    Ok(data) => data,
-                    Err(err) => {
-                        error!("Error reading chunk: {}", err);
-                        return Err(actix_web::error::ErrorInternalServerError(err));
-                    }
-                };
+    /// Handles the request to update the title, the description and the tags of a movie.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to update.
+    /// * `movie` - The new title, description and tags of the movie.
+    pub async fn handle_update_movie(
+        &self,
+        id: MovieId,
+        mut movie: Movie,
+    ) -> Result<impl Responder> {
+        if let Err(err) = self.limit_description(&mut movie) {
+            return Self::handle_error(err);
+        }
 
-                match writer.write_all(&data).await {
-                    Ok(_) => (),
-                    Err(err) => {
-                        error!("Error writing chunk: {}", err);
-                        return Err(actix_web::error::ErrorInternalServerError(err));
-                    }
-                }
-            }
+        match self.index.write().await.update_movie(&id, movie).await {
+            Ok(()) => Ok(actix_web::HttpResponse::Ok()),
+            Err(err) => Self::handle_error(err),
+        }
+    }
 
-            // update the movie file info
-            match self
-                .index
-                .write()
-                .await
-                .update_movie_file_info(
-                    &id,
-                    crate::MovieFileInfo {
-                        extension: ext.clone(),
-                        mime_type: content_type,
-                    },
-                )
-                .await
-            {
-                Ok(()) => (),
-                Err(err) => {
-                    error!("Error updating movie file info: {}", err);
-                    return Err(actix_web::error::ErrorInternalServerError(err));
-                }
+    /// Handles the request to touch a movie, i.e., to move it to the front of the recently added
+    /// movies.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to touch.
+    pub async fn handle_touch_movie(&self, id: MovieId) -> Result<impl Responder> {
+        match self.index.write().await.touch_movie(&id).await {
+            Ok(()) => Ok(actix_web::HttpResponse::Ok()),
+            Err(err) => Self::handle_error(err),
+        }
+    }
+
+    /// Handles the request to feature a movie or to remove it from the featured movies, which can
+    /// be pinned to the top of the search results.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    /// * `featured` - True if the movie is featured.
+    pub async fn handle_set_featured(&self, id: MovieId, featured: bool) -> Result<impl Responder> {
+        match self.index.write().await.set_featured(&id, featured).await {
+            Ok(()) => Ok(actix_web::HttpResponse::Ok()),
+            Err(err) => Self::handle_error(err),
+        }
+    }
+
+    /// Checks that the movie with the given id exists without looking up its details.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to check.
+    async fn check_movie_exists(&self, id: &MovieId) -> Result<()> {
+        match self.index.read().await.movie_exists(id).await {
+            Ok(true) => Ok(()),
+            Ok(false) => {
+                error!("Movie with id {} not found", id);
+                Self::handle_error(Error::NotFound(format!("Movie with id {} not found", id)))
             }
+            Err(err) => Self::handle_error(err),
+        }
+    }
 
-            if let Err(err) = self.preview_requests.send(ScreenshotRequest {
-                movie_id: id.clone(),
-                ext: ext.clone(),
-            }) {
-                error!("Error sending preview request: {}", err);
+    /// Handles the request to start a cancelable upload of a movie and returns the upload token
+    /// for the upload. The token expires if the upload isn't started within an hour. If too many
+    /// tokens are pending, the request is rejected with 429 Too Many Requests.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to upload.
+    pub async fn handle_start_upload(&self, id: MovieId) -> Result<impl Responder> {
+        self.check_movie_exists(&id).await?;
+
+        let mut uploads = self.uploads.lock().unwrap();
+        uploads.retain(|_, upload| !upload.is_expired());
+        if uploads.len() >= MAX_UPLOAD_TOKENS {
+            warn!(
+                "Rejected an upload of movie {}, too many pending uploads",
+                id
+            );
+            return Err(actix_web::error::ErrorTooManyRequests(
+                "Too many pending uploads",
+            ));
+        }
+
+        let token = uuid::Uuid::new_v4().to_string();
+        info!("Started upload {} for movie {}", token, id);
+
+        uploads.insert(
+            token.clone(),
+            InFlightUpload {
+                movie_id: id,
+                cancellation: CancellationToken::new(),
+                expires: Instant::now() + UPLOAD_TOKEN_TTL,
+                started: false,
+            },
+        );
+        drop(uploads);
+
+        Ok(HttpResponse::Ok().json(UploadToken { token }))
+    }
+
+    /// Handles the request to cancel the upload with the given upload token.
+    ///
+    /// # Arguments
+    /// * `token` - The upload token of the upload to cancel.
+    pub async fn handle_cancel_upload(&self, token: &str) -> Result<impl Responder> {
+        match self.uploads.lock().unwrap().remove(token) {
+            Some(upload) => {
+                info!("Cancel upload {} for movie {}", token, upload.movie_id);
+                upload.cancellation.cancel();
+
+                Ok(HttpResponse::Ok())
             }
+            None => Self::handle_error(Error::NotFound(format!("Unknown upload token {}", token))),
         }
+    }
 
-        info!("Uploading movie {} ... DONE", id);
+    /// Handles the request to upload a movie.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to upload.
+    /// * `token` - Optionally, the upload token for cancelling the upload.
+    /// * `multipart` - The multipart data of the movie.
+    pub async fn handle_upload_movie(
+        &self,
+        id: MovieId,
+        token: Option<String>,
+        multipart: Multipart,
+    ) -> Result<impl Responder> {
+        let token = match token {
+            Some(token) => token,
+            None => {
+                return self
+                    .upload_movie(id, CancellationToken::new(), multipart)
+                    .await
+            }
+        };
 
-        Ok(actix_web::HttpResponse::Ok())
+        let cancellation = {
+            let mut uploads = self.uploads.lock().unwrap();
+            uploads.retain(|_, upload| !upload.is_expired());
+            match uploads.get_mut(&token) {
+                Some(upload) if upload.movie_id == id => {
+                    upload.started = true;
+                    upload.cancellation.clone()
+                }
+                Some(_) => {
+                    return Err(actix_web::error::ErrorBadRequest(
+                        "Upload token belongs to another movie",
+                    ))
+                }
+                None => {
+                    return Self::handle_error(Error::NotFound(format!(
+                        "Unknown upload token {}",
+                        token
+                    )))
+                }
+            }
+        };
+
+        let result = self.upload_movie(id, cancellation, multipart).await;
+        self.uploads.lock().unwrap().remove(&token);
+
+        result
     }
 
-    /// Handles the request to upload a screenshot.
+    /// Uploads the movie file of the given multipart data, unless the upload gets cancelled.
+    /// If the preview of the movie gets deferred, the response is 503 Service Unavailable,
+    /// although the movie file has been stored. If the tags extracted from the filename are
+    /// suggested, they are returned in the response.
     ///
     /// # Arguments
-    /// * `id` - The id of the movie to upload a screenshot.
-    /// * `multipart` - The multipart data of the screenshot.
-    pub async fn handle_upload_screenshot(
+    /// * `id` - The id of the movie to upload.
+    /// * `cancellation` - The token for cancelling the upload.
+    /// * `multipart` - The multipart data of the movie.
+    async fn upload_movie(
         &self,
         id: MovieId,
+        cancellation: CancellationToken,
         mut multipart: Multipart,
-    ) -> Result<impl Responder> {
-        info!("Uploading screenshot {} ...", id);
+    ) -> Result<HttpResponse> {
+        // concurrent uploads of the same movie would write to the same file
+        let _movie_lock = self.movie_locks.lock(&id).await;
+
+        // uploads of unknown movies are rejected before any data is stored
+        self.check_movie_exists(&id).await?;
+
+        info!("Uploading movie {} ...", id);
+        let _upload_job = self.active_jobs.start(&id, JobKind::Upload);
+
+        let mut preview_outcome = None;
+        let mut suggested_tags: Vec<String> = Vec::new();
 
         // iterate over multipart stream
+        let mut num_fields = 0;
         while let Ok(Some(mut field)) = multipart.try_next().await {
+            num_fields += 1;
+            if let Err(err) =
+                check_multipart_field_count(num_fields, self.options.max_multipart_fields)
+            {
+                return Self::handle_error(err);
+            }
+
             // extract the filename
             let content_type = field.content_disposition();
-            let filename: PathBuf = match content_type.get_filename() {
-                Some(filename) => PathBuf::from(filename),
-                None => {
-                    error!("Invalid filename");
-                    return Err(actix_web::error::ErrorBadRequest("Invalid filename"));
-                }
-            };
+            let filename: String =
+                match content_type.and_then(|content_type| content_type.get_filename()) {
+                    Some(filename) => filename.to_owned(),
+                    None => {
+                        error!("Invalid filename");
+                        return Err(actix_web::error::ErrorBadRequest("Invalid filename"));
+                    }
+                };
 
             // extract content type information
-            let content_type: String = match field.headers().get(header::CONTENT_TYPE) {
-                Some(content_type) => content_type.to_str().unwrap().to_string(),
-                None => {
+            let content_type: String = match field
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .map(|content_type| content_type.to_str())
+            {
+                Some(Ok(content_type)) => content_type.to_string(),
+                Some(Err(_)) | None => {
                     error!("Invalid content type");
                     return Err(actix_web::error::ErrorBadRequest("Invalid content type"));
                 }
             };
 
-            // check if the content type is an image
-            if !content_type.starts_with("image") {
+            // check if the content type is a video
+            if !content_type.starts_with("video") {
                 error!("Invalid content type");
                 return Err(actix_web::error::ErrorUnsupportedMediaType(
                     "Invalid content type",
@@ -280,36 +1168,42 @@ where
             }
 
             info!(
-                "Uploading screenshot {:?} with mime-type {}",
+                "Uploading file {:?} with mime-type {}",
                 filename, content_type
             );
 
             // extract the extension
-            let ext = match filename.extension() {
-                Some(ext) => match ext.to_str() {
-                    Some(ext) => ext.to_string(),
-                    None => {
-                        error!("Invalid extension");
-                        return Err(actix_web::error::ErrorBadRequest("Invalid extension"));
-                    }
-                },
-                None => {
-                    error!("Invalid extension");
-                    return Err(actix_web::error::ErrorBadRequest("Invalid extension"));
+            let ext = match get_upload_extension(&filename) {
+                Ok(ext) => ext,
+                Err(err) => {
+                    error!("{}", err);
+                    return Self::handle_error(err);
                 }
             };
 
-            debug!("Uploading screenshot with extension: {:?}", ext);
+            debug!("Uploading file with extension: {:?}", ext);
 
-            // open writer for storing screenshot data
-            let mut writer = match self
+            // a movie file is final once the index refers to it, whereas the leftovers of failed
+            // uploads may still be replaced
+            if self.options.immutable_files {
+                match self.index.read().await.get_movie(&id).await {
+                    Ok(movie) if movie.movie_file_info.is_some() => {
+                        return Self::handle_error(Error::AlreadyExists(format!(
+                            "Movie file for movie '{}' already exists and must not be overwritten",
+                            id
+                        )));
+                    }
+                    Ok(_) => {}
+                    Err(err) => return Self::handle_error(err),
+                }
+            }
+
+            // open writer for storing movie data
+            let writer = match self
                 .storage
                 .read()
                 .await
-                .write_movie_data(
-                    id.clone(),
-                    MovieDataType::ScreenshotData { ext: ext.clone() },
-                )
+                .write_movie_data(id.clone(), MovieDataType::MovieData { ext: ext.clone() })
                 .await
             {
                 Ok(writer) => writer,
@@ -317,249 +1211,7113 @@ where
                     return Self::handle_error(err);
                 }
             };
+            let mut writer = BufWriter::with_capacity(self.options.upload_buffer_size, writer);
+            let mut num_bytes: u64 = 0;
 
             // Field in turn is stream of *Bytes* object
-            while let Some(chunk) = field.next().await {
+            while let Some(chunk) = tokio::select! {
+                biased;
+                _ = cancellation.cancelled() => None,
+                chunk = field.next() => chunk,
+            } {
                 let data = match chunk {
                     Ok(data) => data,
                     Err(err) => {
                         error!("Error reading chunk: {}", err);
-                        return Err(actix_web::error::ErrorInternalServerError(err));
+                        drop(writer);
+                        let err = Error::Internal(format!("Error reading chunk: {}", err));
+                        return self.rollback_failed_upload(&id, &ext, err).await;
                     }
                 };
 
-                match writer.write_all(&data).await {
-                    Ok(_) => (),
-                    Err(err) => {
-                        error!("Error writing chunk: {}", err);
-                        return Err(actix_web::error::ErrorInternalServerError(err));
-                    }
+                if let Err(err) = writer.write_all(&data).await {
+                    error!("Error writing chunk: {}", err);
+                    drop(writer);
+                    return self.rollback_failed_upload(&id, &ext, err.into()).await;
                 }
+                num_bytes += data.len() as u64;
+            }
+
+            if cancellation.is_cancelled() {
+                drop(writer);
+                return self.rollback_cancelled_upload(&id, ext).await;
+            }
+
+            // an empty movie file can neither be played nor previewed
+            if num_bytes == 0 {
+                error!("Uploaded movie file of {} is empty", id);
+                drop(writer);
+                self.remove_uploaded_movie_file(&id, &ext).await;
+                return Err(actix_web::error::ErrorBadRequest("The movie file is empty"));
+            }
+
+            // make sure all buffered data has been written before updating the index
+            if let Err(err) = writer.flush().await {
+                error!("Error flushing data: {}", err);
+                drop(writer);
+                return self.rollback_failed_upload(&id, &ext, err.into()).await;
+            }
+            drop(writer);
+
+            if self.options.no_video_stream_policy == NoVideoStreamPolicy::Reject
+                && !self.has_video_stream(&id, &ext).await
+            {
+                error!("Uploaded movie file of {} has no video stream", id);
+                self.remove_uploaded_movie_file(&id, &ext).await;
+                return Err(actix_web::error::ErrorUnsupportedMediaType(
+                    "The movie file has no video stream",
+                ));
             }
 
-            // update the movie screenshot info
+            // update the movie file info
             match self
                 .index
                 .write()
                 .await
-                .update_screenshot_info(
+                .update_movie_file_info(
                     &id,
-                    ScreenshotInfo {
+                    crate::MovieFileInfo {
                         extension: ext.clone(),
                         mime_type: content_type,
+                        original_filename: Some(bound_original_filename(&filename)),
                     },
                 )
                 .await
             {
                 Ok(()) => (),
                 Err(err) => {
-                    error!("Error updating screenshot info: {}", err);
+                    error!("Error updating movie file info: {}", err);
                     return Err(actix_web::error::ErrorInternalServerError(err));
                 }
             }
-        }
 
-        info!("Uploading screenshot {} ... DONE", id);
+            for tag in self.handle_filename_tags(&id, &filename).await {
+                if !suggested_tags.contains(&tag) {
+                    suggested_tags.push(tag);
+                }
+            }
 
-        Ok(actix_web::HttpResponse::Ok())
-    }
+            preview_outcome = Some(
+                self.request_preview(ScreenshotRequest {
+                    movie_id: id.clone(),
+                    ext: ext.clone(),
+                })
+                .await,
+            );
+        }
 
-    /// Handles the request to download a movie.
-    ///
+        info!("Uploading movie {} ... DONE", id);
+        self.activity_log.record(
+            ActivityKind::MovieUploaded,
+            Some(&id),
+            format!("Uploaded the movie file of movie {}", id),
+        );
+
+        if preview_outcome == Some(PreviewRequestOutcome::QueueFull) {
+            if let Some(response) = self.create_deferred_preview_response(&id) {
+                return Ok(response);
+            }
+        }
+
+        if self.options.filename_tags_policy == FilenameTagsPolicy::Suggest {
+            return Ok(HttpResponse::Ok().json(UploadedMovie { suggested_tags }));
+        }
+
+        Ok(actix_web::HttpResponse::Ok().finish())
+    }
+
+    /// Extracts the tags from the filename of an uploaded movie file and handles them according
+    /// to the configured policy. Failing to handle the tags doesn't fail the upload.
+    /// Returns the tags to suggest, i.e., the extracted tags the movie doesn't have yet, which
+    /// are only non-empty if the tags are suggested.
+    ///
     /// # Arguments
-    /// * `id` - The id of the movie to download.
-    pub async fn handle_download_movie(
-        &self,
-        id: MovieId,
-        ranges: &[ByteRangeSpec],
-    ) -> Result<impl Responder> {
-        info!("Downloading movie {} ...", id);
+    /// * `id` - The id of the uploaded movie.
+    /// * `filename` - The filename of the uploaded movie file.
+    async fn handle_filename_tags(&self, id: &MovieId, filename: &str) -> Vec<String> {
+        let policy = self.options.filename_tags_policy;
+        if policy == FilenameTagsPolicy::Disabled {
+            return Vec::new();
+        }
 
-        // get the movie file info, needed for requesting the movie data
-        let movie_file_info = match self.index.read().await.get_movie(&id).await {
-            Ok(movie) => match movie.movie_file_info {
-                Some(movie_file_info) => movie_file_info,
-                None => {
-                    error!("Movie {} has no movie file info", id);
-                    return Err(actix_web::error::ErrorConflict(format!(
-                        "Movie {} is not yet ready",
-                        id
-                    )));
+        let tags = extract_filename_tags(filename, &self.options.filename_tag_tokens).into_tags();
+        if tags.is_empty() {
+            return Vec::new();
+        }
+
+        debug!("Extracted tags {:?} from filename {:?}", tags, filename);
+
+        match policy {
+            FilenameTagsPolicy::Disabled => Vec::new(),
+            FilenameTagsPolicy::Suggest => match self.index.read().await.get_movie(id).await {
+                Ok(movie) => tags
+                    .into_iter()
+                    .filter(|tag| {
+                        !movie
+                            .movie
+                            .tags
+                            .iter()
+                            .any(|movie_tag| movie_tag.to_lowercase() == *tag)
+                    })
+                    .collect(),
+                Err(err) => {
+                    warn!("Failed to get the tags of movie {}: {}", id, err);
+                    Vec::new()
                 }
             },
-            Err(err) => {
-                error!("Error getting movie info: {}", err);
-                return Self::handle_error(err);
+            FilenameTagsPolicy::Apply => {
+                if let Err(err) = self.index.write().await.add_tags(id, &tags).await {
+                    warn!(
+                        "Failed to add the tags of the filename to movie {}: {}",
+                        id, err
+                    );
+                }
+
+                Vec::new()
             }
+        }
+    }
+
+    /// Handles the request to generate the preview of a movie, e.g., after it has been deferred.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to generate the preview for.
+    pub async fn handle_request_preview(&self, id: MovieId) -> Result<impl Responder> {
+        let movie = match self.index.read().await.get_movie(&id).await {
+            Ok(movie) => movie,
+            Err(err) => return Self::handle_error(err),
         };
 
-        // create reader onto the movie data
-        let mut movie_data = match self
-            .storage
-            .read()
-            .await
-            .read_movie_data(
-                id,
-                MovieDataType::MovieData {
-                    ext: movie_file_info.extension.clone(),
-                },
-            )
-            .await
-        {
-            Ok(movie_data) => movie_data,
-            Err(err) => {
-                error!("Error reading movie data: {}", err);
-                return Self::handle_error(err);
+        let ext = match movie.movie_file_info {
+            Some(movie_file_info) => movie_file_info.extension,
+            None => {
+                return Self::handle_error(Error::NotFound(format!(
+                    "Movie {} has no movie file",
+                    id
+                )))
             }
         };
 
-        // get total length and create satisfiable range
-        let full_length = movie_data.get_size().await as u64;
-        let range = match ranges.first() {
-            Some(range) => range.to_satisfiable_range(full_length),
-            None => None,
-        };
+        let outcome = self
+            .request_preview(ScreenshotRequest {
+                movie_id: id.clone(),
+                ext,
+            })
+            .await;
 
-        let mut response = if range.is_none() {
-            HttpResponse::Ok()
-        } else {
-            HttpResponse::PartialContent()
-        };
+        match outcome {
+            PreviewRequestOutcome::Queued => Ok(HttpResponse::Accepted().finish()),
+            PreviewRequestOutcome::QueueFull => Ok(self
+                .create_deferred_preview_response(&id)
+                .unwrap_or_else(|| HttpResponse::ServiceUnavailable().finish())),
+            PreviewRequestOutcome::Failed => Err(actix_web::error::ErrorServiceUnavailable(
+                "Previews are not available",
+            )),
+        }
+    }
 
-        let mut length = full_length;
-        if let Some(r) = range {
-            debug!("Seek to new range: {:?}", r);
-            movie_data.seek(SeekFrom::Start(r.0)).await?;
-            debug_assert_eq!(movie_data.stream_position().await?, r.0);
-            length = r.1 - r.0 + 1;
+    /// Returns the number of queued preview requests.
+    fn get_preview_queue_depth(&self) -> usize {
+        self.preview_requests.max_capacity() - self.preview_requests.capacity()
+    }
 
-            response.append_header((
-                header::CONTENT_RANGE,
-                format!("bytes {}-{}/{}", r.0, r.1, full_length),
-            ));
+    /// Creates the 503 Service Unavailable response telling the client to request the preview of
+    /// the given movie again later. Returns None if previews are not deferred.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie whose preview has been deferred.
+    fn create_deferred_preview_response(&self, id: &MovieId) -> Option<HttpResponse> {
+        if self.options.preview_backpressure != PreviewBackpressurePolicy::Defer {
+            return None;
         }
 
-        let reader_stream = ReaderStream::new(movie_data);
-        let sized_stream = SizedStream::new(length, reader_stream);
+        let retry_after = self.options.preview_retry_after;
+        warn!(
+            "Preview of movie {} deferred, retry after {} seconds",
+            id, retry_after
+        );
 
-        response
-            .content_type(movie_file_info.mime_type)
-            .append_header((header::ACCEPT_RANGES, "bytes"))
-            .message_body(sized_stream)
+        Some(
+            HttpResponse::ServiceUnavailable()
+                .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                .json(DeferredPreview {
+                    id: id.clone(),
+                    queue_depth: self.get_preview_queue_depth(),
+                    retry_after,
+                }),
+        )
     }
 
-    /// Handles the request to download a screenshot.
+    /// Removes the partially written movie file of a cancelled upload. If the partial file has
+    /// replaced a previously uploaded movie file, the file info of the movie is removed as well.
     ///
     /// # Arguments
-    /// * `id` - The id of the movie whose screenshot will be downloaded.
-    pub async fn handle_download_screenshot(&self, id: MovieId) -> Result<impl Responder> {
-        info!("Downloading screenshot {} ...", id);
+    /// * `id` - The id of the movie whose upload has been cancelled.
+    /// * `ext` - The extension of the partially written movie file.
+    async fn rollback_cancelled_upload(&self, id: &MovieId, ext: String) -> Result<HttpResponse> {
+        info!("Upload of movie {} has been cancelled", id);
 
-        // get the movie screenshot info, needed for requesting the data
-        let screenshot_info = match self.index.read().await.get_movie(&id).await {
-            Ok(movie) => match movie.screenshot_file_info {
-                Some(screenshot_info) => screenshot_info,
-                None => {
-                    error!("Movie {} has no screenshot info", id);
-                    return Err(actix_web::error::ErrorConflict(format!(
-                        "Movie {} is not yet ready",
-                        id
-                    )));
-                }
-            },
-            Err(err) => {
-                error!("Error getting screenshot info: {}", err);
-                return Self::handle_error(err);
+        self.remove_uploaded_movie_file(id, &ext).await;
+
+        Err(actix_web::error::ErrorConflict("Upload has been cancelled"))
+    }
+
+    /// Removes the partially written movie file of an upload, which failed with the given error,
+    /// and responds with the error, e.g., 507 Insufficient Storage if the disk is full.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie whose upload has failed.
+    /// * `ext` - The extension of the partially written movie file.
+    /// * `err` - The error of the upload.
+    async fn rollback_failed_upload(
+        &self,
+        id: &MovieId,
+        ext: &str,
+        err: Error,
+    ) -> Result<HttpResponse> {
+        self.remove_uploaded_movie_file(id, ext).await;
+
+        Self::handle_error(err)
+    }
+
+    /// Removes the uploaded movie file with the given extension and the movie file info, if it
+    /// refers to the removed file.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie whose movie file is removed.
+    /// * `ext` - The extension of the uploaded movie file.
+    async fn remove_uploaded_movie_file(&self, id: &MovieId, ext: &str) {
+        if let Err(err) = self
+            .storage
+            .read()
+            .await
+            .remove_movie_file(
+                id.clone(),
+                MovieDataType::MovieData {
+                    ext: ext.to_owned(),
+                },
+            )
+            .await
+        {
+            error!("Failed to remove uploaded movie file of {}: {}", id, err);
+        }
+
+        let mut index = self.index.write().await;
+        let is_replaced = match index.get_movie(id).await {
+            Ok(movie) => movie
+                .movie_file_info
+                .is_some_and(|info| info.extension == ext),
+            Err(_) => false,
+        };
+        if is_replaced {
+            if let Err(err) = index.remove_movie_file_info(id).await {
+                error!("Failed to remove movie file info of {}: {}", id, err);
             }
+        }
+    }
+
+    /// Returns false if the stored movie file of the given movie is known to have no video
+    /// stream. Files which can't be probed, e.g., as ffmpeg isn't available, are assumed to have
+    /// a video stream.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    /// * `ext` - The extension of the movie file.
+    async fn has_video_stream(&self, id: &MovieId, ext: &str) -> bool {
+        let ffmpeg = match &self.ffmpeg {
+            Some(ffmpeg) => ffmpeg,
+            None => return true,
         };
 
-        // create reader onto the screenshot data
-        let screenshot_data = match self
+        let file_path = match self
             .storage
             .read()
             .await
-            .read_movie_data(
-                id,
-                MovieDataType::ScreenshotData {
-                    ext: screenshot_info.extension.clone(),
+            .get_file_path(
+                id.clone(),
+                MovieDataType::MovieData {
+                    ext: ext.to_owned(),
                 },
             )
             .await
         {
-            Ok(screenshot_data) => screenshot_data,
+            Ok(Some(file_path)) => file_path,
+            Ok(None) => return true,
             Err(err) => {
-                error!("Error reading screenshot data: {}", err);
-                return Self::handle_error(err);
+                warn!("Failed to get movie file path of {}: {}", id, err);
+                return true;
             }
         };
 
-        // create response
-        let length = screenshot_data.get_size().await as u64;
-        let reader_stream = ReaderStream::new(screenshot_data);
-        let sized_stream = SizedStream::new(length, reader_stream);
-
-        HttpResponse::Ok()
-            .content_type(screenshot_info.mime_type)
-            .message_body(sized_stream)
+        match ffmpeg.has_video_stream(&file_path).await {
+            Ok(has_video_stream) => has_video_stream,
+            Err(err) => {
+                warn!("Failed to probe video streams of movie {}: {}", id, err);
+                true
+            }
+        }
     }
 
-    /// Handles the request to show the list of all movies.
+    /// Handles the request to upload a screenshot.
     ///
     /// # Arguments
-    /// * `query` - The query to search for.
-    pub async fn handle_search_movies(&self, query: MovieSearchQuery) -> Result<impl Responder> {
-        let movie_ids = match self.index.read().await.search_movies(query).await {
-            Ok(movie_ids) => movie_ids,
-            Err(err) => {
-                error!("Error searching: {}", err);
+    /// * `id` - The id of the movie to upload a screenshot.
+    /// * `multipart` - The multipart data of the screenshot.
+    pub async fn handle_upload_screenshot(
+        &self,
+        id: MovieId,
+        mut multipart: Multipart,
+    ) -> Result<impl Responder> {
+        let _movie_lock = self.movie_locks.lock(&id).await;
+        self.check_movie_exists(&id).await?;
+
+        info!("Uploading screenshot {} ...", id);
+
+        // iterate over multipart stream
+        let mut num_fields = 0;
+        while let Ok(Some(mut field)) = multipart.try_next().await {
+            num_fields += 1;
+            if let Err(err) =
+                check_multipart_field_count(num_fields, self.options.max_multipart_fields)
+            {
                 return Self::handle_error(err);
             }
-        };
 
-        let mut movies: Vec<MovieListEntry> = Vec::with_capacity(movie_ids.len());
-        for movie_id in movie_ids.iter() {
-            let movie = self.index.read().await.get_movie(movie_id).await.unwrap();
-            movies.push(MovieListEntry {
-                id: movie_id.clone(),
-                title: movie.movie.title,
-            });
-        }
+            // extract the filename
+            let content_type = field.content_disposition();
+            let filename: String =
+                match content_type.and_then(|content_type| content_type.get_filename()) {
+                    Some(filename) => filename.to_owned(),
+                    None => {
+                        error!("Invalid filename");
+                        return Err(actix_web::error::ErrorBadRequest("Invalid filename"));
+                    }
+                };
 
-        Ok(web::Json(movies))
-    }
+            // extract content type information
+            let content_type: String = match field
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .map(|content_type| content_type.to_str())
+            {
+                Some(Ok(content_type)) => content_type.to_string(),
+                Some(Err(_)) | None => {
+                    error!("Invalid content type");
+                    return Err(actix_web::error::ErrorBadRequest("Invalid content type"));
+                }
+            };
 
-    /// Handles the request to get a list of all tags with the number of movies associated with
-    /// each tag.
-    pub async fn handle_get_tags(&self) -> Result<impl Responder> {
-        let tags = match self.index.read().await.get_tag_list_with_count().await {
-            Ok(tags) => tags,
-            Err(err) => {
-                error!("Error getting tags: {}", err);
-                return Self::handle_error(err);
+            // check if the content type is an image
+            if !content_type.starts_with("image") {
+                error!("Invalid content type");
+                return Err(actix_web::error::ErrorUnsupportedMediaType(
+                    "Invalid content type",
+                ));
             }
-        };
 
-        Ok(web::Json(tags))
-    }
+            info!(
+                "Uploading screenshot {:?} with mime-type {}",
+                filename, content_type
+            );
 
-    /// Handles the given error by translating it into an actix-web error response.
-    ///
-    /// # Arguments
-    /// * `err` - The error to handle.
-    fn handle_error<T: Responder>(err: Error) -> Result<T> {
-        match err {
-            Error::InvalidArgument(e) => {
-                error!("Invalid argument: {}", e);
-                Err(actix_web::error::ErrorBadRequest(e))
+            // extract the extension
+            let ext = match get_upload_extension(&filename) {
+                Ok(ext) => ext,
+                Err(err) => {
+                    error!("{}", err);
+                    return Self::handle_error(err);
+                }
+            };
+
+            debug!("Uploading screenshot with extension: {:?}", ext);
+
+            // keep the replaced screenshot, s.t. it can be restored
+            let is_archived = archive_screenshot(
+                &self.index,
+                &self.storage,
+                &id,
+                self.options.screenshot_history_size,
+            )
+            .await;
+
+            // open writer for storing screenshot data
+            let writer = match self
+                .storage
+                .read()
+                .await
+                .write_movie_data(
+                    id.clone(),
+                    MovieDataType::ScreenshotData {
+                        ext: ext.clone(),
+                        index: 0,
+                    },
+                )
+                .await
+            {
+                Ok(writer) => writer,
+                Err(err) => {
+                    if is_archived {
+                        self.restore_archived_screenshot(&id).await;
+                    }
+                    return Self::handle_error(err);
+                }
+            };
+            let mut writer = BufWriter::with_capacity(self.options.upload_buffer_size, writer);
+
+            // keep a copy of the screenshot for computing its perceptual hash
+            let mut screenshot_data: Vec<u8> = Vec::new();
+
+            // Field in turn is stream of *Bytes* object
+            while let Some(chunk) = field.next().await {
+                let data = match chunk {
+                    Ok(data) => data,
+                    Err(err) => {
+                        error!("Error reading chunk: {}", err);
+                        drop(writer);
+                        let err = Error::Internal(format!("Error reading chunk: {}", err));
+                        return self
+                            .rollback_failed_screenshot_upload(&id, &ext, is_archived, err)
+                            .await;
+                    }
+                };
+
+                if let Err(err) = writer.write_all(&data).await {
+                    error!("Error writing chunk: {}", err);
+                    drop(writer);
+                    return self
+                        .rollback_failed_screenshot_upload(&id, &ext, is_archived, err.into())
+                        .await;
+                }
+
+                if self.options.compute_screenshot_hashes {
+                    screenshot_data.extend_from_slice(&data);
+                }
             }
-            Error::NotFound(e) => {
-                error!("Not found: {}", e);
-                Err(actix_web::error::ErrorNotFound(e))
+
+            // make sure all buffered data has been written before updating the index
+            if let Err(err) = writer.flush().await {
+                error!("Error flushing data: {}", err);
+                drop(writer);
+                return self
+                    .rollback_failed_screenshot_upload(&id, &ext, is_archived, err.into())
+                    .await;
             }
-            _ => {
-                error!("Internal error: {}", err);
-                Err(actix_web::error::ErrorInternalServerError(err))
+
+            // update the movie screenshot info, which also replaces any failed preview
+            let mut index = self.index.write().await;
+            let result = match index
+                .update_screenshot_info(
+                    &id,
+                    ScreenshotInfo {
+                        extension: ext.clone(),
+                        mime_type: content_type,
+                        count: 1,
+                    },
+                )
+                .await
+            {
+                Ok(()) => index.update_preview_state(&id, PreviewState::Ready).await,
+                Err(err) => Err(err),
+            };
+
+            if let Err(err) = result {
+                error!("Error updating screenshot info: {}", err);
+                return Err(actix_web::error::ErrorInternalServerError(err));
+            }
+            drop(index);
+            self.screenshot_cache.remove(&id);
+
+            // a missing hash only excludes the movie from the similarity search
+            if self.options.compute_screenshot_hashes {
+                if let Err(err) = store_screenshot_hash(&self.index, &id, screenshot_data).await {
+                    warn!(
+                        "Failed to compute screenshot hash for movie {}: {}",
+                        id, err
+                    );
+                }
+            }
+        }
+
+        info!("Uploading screenshot {} ... DONE", id);
+
+        Ok(actix_web::HttpResponse::Ok().finish())
+    }
+
+    /// Removes the partially written screenshot of an upload, which failed with the given error,
+    /// restores the screenshot it was about to replace and responds with the error.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie whose screenshot upload has failed.
+    /// * `ext` - The extension of the partially written screenshot.
+    /// * `is_archived` - Whether the replaced screenshot has been moved into the history.
+    /// * `err` - The error of the upload.
+    async fn rollback_failed_screenshot_upload(
+        &self,
+        id: &MovieId,
+        ext: &str,
+        is_archived: bool,
+        err: Error,
+    ) -> Result<HttpResponse> {
+        if let Err(err) = self
+            .storage
+            .read()
+            .await
+            .remove_movie_file(
+                id.clone(),
+                MovieDataType::ScreenshotData {
+                    ext: ext.to_owned(),
+                    index: 0,
+                },
+            )
+            .await
+        {
+            error!("Failed to remove uploaded screenshot of {}: {}", id, err);
+        }
+
+        if is_archived {
+            self.restore_archived_screenshot(id).await;
+        }
+
+        Self::handle_error(err)
+    }
+
+    /// Moves the most recently archived screenshot of the given movie back to its main
+    /// screenshot, i.e., undoes the archiving before a failed screenshot upload.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie whose screenshot is restored.
+    async fn restore_archived_screenshot(&self, id: &MovieId) {
+        let storage = self.storage.read().await;
+        let result = match storage.get_screenshot_history(id.clone()).await {
+            Ok(history) => match history.first() {
+                Some((version, _)) => storage
+                    .restore_screenshot(
+                        id.clone(),
+                        *version,
+                        None,
+                        self.options.screenshot_history_size,
+                    )
+                    .await
+                    .map(|_| ()),
+                None => Ok(()),
+            },
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = result {
+            error!(
+                "Failed to restore the replaced screenshot of {}: {}",
+                id, err
+            );
+        }
+        drop(storage);
+        self.screenshot_cache.remove(id);
+    }
+
+    /// Handles the request for the screenshot history of a movie, i.e., its previous screenshots
+    /// which can be restored, the most recently replaced screenshot first.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    pub async fn handle_get_screenshot_history(&self, id: MovieId) -> Result<impl Responder> {
+        self.check_movie_exists(&id).await?;
+
+        let history = match self
+            .storage
+            .read()
+            .await
+            .get_screenshot_history(id.clone())
+            .await
+        {
+            Ok(history) => history,
+            Err(err) => {
+                error!("Error getting the screenshot history of {}: {}", id, err);
+                return Self::handle_error(err);
+            }
+        };
+
+        let versions: Vec<ScreenshotVersion> = history
+            .into_iter()
+            .map(|(version, extension)| ScreenshotVersion { version, extension })
+            .collect();
+
+        Ok(Self::create_metadata_response(&versions))
+    }
+
+    /// Handles the request to restore the given version of the screenshot history of a movie as
+    /// its screenshot. The replaced screenshot becomes part of the history.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    /// * `version` - The version of the previous screenshot to restore.
+    pub async fn handle_revert_screenshot(
+        &self,
+        id: MovieId,
+        version: u32,
+    ) -> Result<impl Responder> {
+        let _movie_lock = self.movie_locks.lock(&id).await;
+
+        let screenshot_info = match self.index.read().await.get_movie(&id).await {
+            Ok(movie) => movie.screenshot_file_info,
+            Err(err) => {
+                error!("Error getting movie {}: {}", id, err);
+                return Self::handle_error(err);
+            }
+        };
+
+        info!("Reverting screenshot of {} to version {} ...", id, version);
+
+        let storage = self.storage.read().await;
+        let ext = match storage
+            .restore_screenshot(
+                id.clone(),
+                version,
+                screenshot_info.as_ref().map(|info| info.extension.as_str()),
+                self.options.screenshot_history_size,
+            )
+            .await
+        {
+            Ok(ext) => ext,
+            Err(err) => {
+                error!("Error restoring screenshot of {}: {}", id, err);
+                return Self::handle_error(err);
+            }
+        };
+
+        // the other screenshots of the movie are kept
+        let mut index = self.index.write().await;
+        let result = match index
+            .update_screenshot_info(
+                &id,
+                ScreenshotInfo {
+                    mime_type: get_image_mime_type(&ext),
+                    extension: ext.clone(),
+                    count: screenshot_info.map_or(1, |info| info.count),
+                },
+            )
+            .await
+        {
+            Ok(()) => index.update_preview_state(&id, PreviewState::Ready).await,
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = result {
+            error!("Error updating screenshot info: {}", err);
+            return Self::handle_error(err);
+        }
+        drop(index);
+        self.screenshot_cache.remove(&id);
+
+        // a missing hash only excludes the movie from the similarity search
+        if self.options.compute_screenshot_hashes {
+            let mut screenshot_data: Vec<u8> = Vec::new();
+            let result = match storage
+                .read_movie_data(id.clone(), MovieDataType::ScreenshotData { ext, index: 0 })
+                .await
+            {
+                Ok(mut reader) => reader
+                    .read_to_end(&mut screenshot_data)
+                    .await
+                    .map_err(Error::from),
+                Err(err) => Err(err),
+            };
+
+            let result = match result {
+                Ok(_) => store_screenshot_hash(&self.index, &id, screenshot_data).await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result {
+                warn!(
+                    "Failed to compute screenshot hash for movie {}: {}",
+                    id, err
+                );
+            }
+        }
+
+        info!(
+            "Reverting screenshot of {} to version {} ... DONE",
+            id, version
+        );
+
+        Ok(actix_web::HttpResponse::Ok())
+    }
+
+    /// Handles the request to download a movie. The requested range is only served if the
+    /// optional `If-Range` validator still matches the movie data, otherwise the whole movie is
+    /// sent, s.t. a resumed download of a replaced movie doesn't mix the data of both files.
+    /// A range beyond the end of the movie is rejected with `416 Range Not Satisfiable`.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to download.
+    /// * `ranges` - The requested byte ranges.
+    /// * `if_range` - Optionally, the `If-Range` header sent by the client.
+    pub async fn handle_download_movie(
+        &self,
+        id: MovieId,
+        ranges: &[ByteRangeSpec],
+        if_range: Option<IfRange>,
+    ) -> Result<Either<impl Responder, HttpResponse>> {
+        info!("Downloading movie {} ...", id);
+
+        // get the movie file info, needed for requesting the movie data
+        let movie_file_info = self.get_movie_file_info(&id).await?;
+
+        // let the reverse proxy serve the movie file, which also handles the ranges
+        if let Some(response) = self
+            .create_offloaded_download(&id, &movie_file_info)
+            .await?
+        {
+            return Ok(Either::Right(response));
+        }
+
+        // create reader onto the movie data
+        let mut movie_data = match self
+            .storage
+            .read()
+            .await
+            .read_movie_data(
+                id.clone(),
+                MovieDataType::MovieData {
+                    ext: movie_file_info.extension.clone(),
+                },
+            )
+            .await
+        {
+            Ok(movie_data) => movie_data,
+            Err(err) => {
+                error!("Error reading movie data: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        // get total length and the validators of the movie data
+        let full_length = movie_data.get_size().await as u64;
+        let last_modified = movie_data.get_last_modified().await;
+        let etag = last_modified.map(|modified| movie_data_etag(full_length, modified));
+
+        // ignore the range if the client's copy is outdated
+        let ranges: &[ByteRangeSpec] = match if_range {
+            Some(if_range) if !if_range_matches(&if_range, etag.as_ref(), last_modified) => {
+                debug!(
+                    "If-Range doesn't match movie {}, sending the whole movie",
+                    id
+                );
+                &[]
+            }
+            _ => ranges,
+        };
+
+        // create satisfiable range
+        let range = match ranges.first() {
+            Some(range) => match range.to_satisfiable_range(full_length) {
+                Some(range) => Some(range),
+                None => {
+                    debug!("Range {:?} of movie {} is not satisfiable", range, id);
+                    return Ok(Either::Right(
+                        HttpResponse::RangeNotSatisfiable()
+                            .append_header((
+                                header::CONTENT_RANGE,
+                                format!("bytes */{}", full_length),
+                            ))
+                            .append_header((header::ACCEPT_RANGES, "bytes"))
+                            .finish(),
+                    ));
+                }
+            },
+            None => None,
+        };
+
+        let mut response = if range.is_none() {
+            HttpResponse::Ok()
+        } else {
+            HttpResponse::PartialContent()
+        };
+
+        let mut length = full_length;
+        if let Some(r) = range {
+            debug!("Seek to new range: {:?}", r);
+            movie_data.seek(SeekFrom::Start(r.0)).await?;
+            debug_assert_eq!(movie_data.stream_position().await?, r.0);
+            length = r.1 - r.0 + 1;
+
+            response.append_header((
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{}", r.0, r.1, full_length),
+            ));
+        }
+
+        if let Some(etag) = etag {
+            response.insert_header(header::ETag(etag));
+        }
+        if let Some(modified) = last_modified {
+            response.insert_header(header::LastModified(modified.into()));
+        }
+
+        // only the requested range is streamed, s.t. the body matches its length
+        let reader_stream =
+            self.with_download_idle_timeout(ReaderStream::new(movie_data.take(length)));
+        let sized_stream = SizedStream::new(length, reader_stream);
+
+        Ok(Either::Left(
+            response
+                .content_type(movie_file_info.mime_type)
+                .append_header((header::ACCEPT_RANGES, "bytes"))
+                .message_body(sized_stream)?,
+        ))
+    }
+
+    /// Creates the response offloading the download of the given movie file onto the reverse
+    /// proxy, which serves the file given by the header of the response. Returns none if the
+    /// offloading is disabled or if the storage provides no file path, s.t. the movie file is
+    /// streamed as usual.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to download.
+    /// * `movie_file_info` - The file info of the movie to download.
+    async fn create_offloaded_download(
+        &self,
+        id: &MovieId,
+        movie_file_info: &MovieFileInfo,
+    ) -> Result<Option<HttpResponse>> {
+        if self.options.download_offload == DownloadOffload::Disabled {
+            return Ok(None);
+        }
+
+        let file_path = match self
+            .storage
+            .read()
+            .await
+            .get_file_path(
+                id.clone(),
+                MovieDataType::MovieData {
+                    ext: movie_file_info.extension.clone(),
+                },
+            )
+            .await
+        {
+            Ok(Some(file_path)) => file_path,
+            Ok(None) => {
+                debug!("The storage provides no file paths, streaming movie {}", id);
+                return Ok(None);
+            }
+            Err(err) => {
+                error!("Error getting the file path of movie {}: {}", id, err);
+                return Self::handle_error(err);
+            }
+        };
+
+        let (header_name, header_value) = match &self.options.download_offload {
+            DownloadOffload::Disabled => return Ok(None),
+            DownloadOffload::XAccelRedirect { location } => {
+                let relative_path = match file_path.strip_prefix(&self.options.root_dir) {
+                    Ok(relative_path) => relative_path,
+                    Err(_) => {
+                        warn!(
+                            "File '{}' is outside of the root directory, streaming movie {}",
+                            file_path.display(),
+                            id
+                        );
+                        return Ok(None);
+                    }
+                };
+
+                let mut internal_path = location.trim_end_matches('/').to_owned();
+                for component in relative_path.iter() {
+                    internal_path.push('/');
+                    internal_path.push_str(&component.to_string_lossy());
+                }
+
+                ("X-Accel-Redirect", internal_path)
+            }
+            DownloadOffload::XSendfile => ("X-Sendfile", file_path.display().to_string()),
+        };
+
+        debug!("Offloading download of movie {} by {}", id, header_name);
+
+        Ok(Some(
+            HttpResponse::Ok()
+                .content_type(movie_file_info.mime_type.clone())
+                .insert_header((header_name, header_value))
+                .finish(),
+        ))
+    }
+
+    /// Handles the request to download the movie in the given format, e.g., "mp4". Movies in
+    /// another format are transcoded into a fragmented MP4 while they are streamed, if enabled.
+    /// Transcoding trades CPU for storage and doesn't support range requests, i.e., the whole
+    /// transcoded movie is always streamed.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to download.
+    /// * `format` - The requested format of the movie.
+    /// * `ranges` - The requested byte ranges, only supported if no transcoding is needed.
+    /// * `if_range` - Optionally, the `If-Range` header sent by the client.
+    pub async fn handle_download_movie_as(
+        &self,
+        id: MovieId,
+        format: &str,
+        ranges: &[ByteRangeSpec],
+        if_range: Option<IfRange>,
+    ) -> Result<Either<impl Responder, HttpResponse>> {
+        let format = format.to_lowercase();
+        let movie_file_info = self.get_movie_file_info(&id).await?;
+        if movie_file_info.extension == format {
+            return Ok(Either::Left(
+                self.handle_download_movie(id, ranges, if_range).await?,
+            ));
+        }
+
+        if format != TRANSCODING_FORMAT {
+            return Self::handle_error(Error::InvalidArgument(format!(
+                "Movies can't be transcoded into '{}'",
+                format
+            )));
+        }
+
+        let ffmpeg = match (&self.ffmpeg, self.options.transcode_on_the_fly) {
+            (Some(ffmpeg), true) => ffmpeg,
+            (None, true) => {
+                return Self::handle_error(Error::Internal(
+                    "ffmpeg is not available for transcoding".to_owned(),
+                ))
+            }
+            (_, false) => {
+                return Self::handle_error(Error::InvalidArgument(
+                    "Transcoding on the fly is not enabled".to_owned(),
+                ))
+            }
+        };
+
+        let file_path = match self
+            .storage
+            .read()
+            .await
+            .get_file_path(
+                id.clone(),
+                MovieDataType::MovieData {
+                    ext: movie_file_info.extension.clone(),
+                },
+            )
+            .await
+        {
+            Ok(Some(file_path)) => file_path,
+            Ok(None) => {
+                return Self::handle_error(Error::Internal(
+                    "The storage provides no file paths for transcoding".to_owned(),
+                ))
+            }
+            Err(err) => {
+                error!("Error getting movie file path: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        info!(
+            "Transcoding movie {} from '{}' into '{}' ...",
+            id, movie_file_info.extension, format
+        );
+        let stream = match ffmpeg.transcode_to_mp4(&file_path).await {
+            Ok(stream) => stream,
+            Err(err) => {
+                error!("Error transcoding movie {}: {}", id, err);
+                return Self::handle_error(err);
+            }
+        };
+
+        // the transcode is tracked until the stream is dropped, i.e., finished or aborted
+        let transcode_job = self.active_jobs.start(&id, JobKind::Transcode);
+        let stream = self.with_download_idle_timeout(stream.map(move |chunk| {
+            let _transcode_job = &transcode_job;
+            chunk
+        }));
+
+        Ok(Either::Right(
+            HttpResponse::Ok()
+                .content_type(TRANSCODING_MIME_TYPE)
+                .append_header((header::ACCEPT_RANGES, "none"))
+                .streaming(stream),
+        ))
+    }
+
+    /// Wraps the given download stream, s.t. it is aborted if the client pulls no data within the
+    /// configured idle timeout.
+    ///
+    /// # Arguments
+    /// * `stream` - The download stream to wrap.
+    fn with_download_idle_timeout<T: Stream + 'static>(&self, stream: T) -> IdleTimeoutStream<T> {
+        let timeout = match self.options.download_idle_timeout {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+
+        IdleTimeoutStream::new(stream, timeout)
+    }
+
+    /// Returns the movie file info of the given movie, which is only available once the movie
+    /// file has been uploaded.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    async fn get_movie_file_info(&self, id: &MovieId) -> Result<MovieFileInfo> {
+        match self.index.read().await.get_movie(id).await {
+            Ok(movie) => match movie.movie_file_info {
+                Some(movie_file_info) => Ok(movie_file_info),
+                None => {
+                    error!("Movie {} has no movie file info", id);
+                    Err(actix_web::error::ErrorConflict(format!(
+                        "Movie {} is not yet ready",
+                        id
+                    )))
+                }
+            },
+            Err(err) => {
+                error!("Error getting movie info: {}", err);
+                Self::handle_error(err)
+            }
+        }
+    }
+
+    /// Handles the request to correct the movie file info of a movie without re-uploading the
+    /// movie file. If the extension changes, the stored movie file is renamed accordingly, unless
+    /// the movie files are immutable, which only allows correcting the mime type.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie whose movie file info will be updated.
+    /// * `mime_type` - The new mime type of the movie file, must be a video mime type.
+    /// * `extension` - Optionally, the new extension of the movie file.
+    pub async fn handle_update_movie_file_info(
+        &self,
+        id: MovieId,
+        mime_type: String,
+        extension: Option<String>,
+    ) -> Result<impl Responder> {
+        info!("Updating movie file info of movie {} ...", id);
+
+        match self.update_movie_file_info(&id, mime_type, extension).await {
+            Ok(movie_file_info) => Ok(web::Json(movie_file_info)),
+            Err(err) => {
+                error!("Error updating movie file info of movie {}: {}", id, err);
+                Self::handle_error(err)
+            }
+        }
+    }
+
+    /// Handles the request to download a screenshot.
+    /// If a width and/or height is provided, the screenshot is resized to fit into the given
+    /// dimensions. Resized screenshots are cached in the storage.
+    /// The response can be cached by clients and is validated by a content based ETag.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie whose screenshot will be downloaded.
+    /// * `width` - Optionally, the maximal width of the screenshot.
+    /// * `height` - Optionally, the maximal height of the screenshot.
+    /// * `if_none_match` - Optionally, the ETags of the screenshot cached by the client.
+    pub async fn handle_download_screenshot(
+        &self,
+        id: MovieId,
+        width: Option<u32>,
+        height: Option<u32>,
+        if_none_match: Option<IfNoneMatch>,
+    ) -> Result<HttpResponse> {
+        info!("Downloading screenshot {} ...", id);
+
+        // get the movie screenshot info, needed for requesting the data
+        let screenshot_info = match self.index.read().await.get_movie(&id).await {
+            Ok(movie) => match movie.screenshot_file_info {
+                Some(screenshot_info) => screenshot_info,
+                None => {
+                    if let Some(placeholder) = &self.options.placeholder_screenshot {
+                        info!("Movie {} has no screenshot, serve placeholder", id);
+                        return Self::create_placeholder_screenshot_response(placeholder).await;
+                    }
+
+                    error!("Movie {} has no screenshot info", id);
+                    return Err(actix_web::error::ErrorConflict(format!(
+                        "Movie {} is not yet ready",
+                        id
+                    )));
+                }
+            },
+            Err(err) => {
+                error!("Error getting screenshot info: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        if width.is_some() || height.is_some() {
+            let (width, height) = screenshot_resizer::get_resize_bounds(width, height);
+
+            return match self
+                .get_resized_screenshot(id, &screenshot_info, width, height)
+                .await
+            {
+                Ok(data) => Ok(self.create_screenshot_response(
+                    data,
+                    screenshot_resizer::RESIZED_SCREENSHOT_MIME_TYPE,
+                    if_none_match,
+                )),
+                Err(err) => {
+                    error!("Error resizing screenshot: {}", err);
+                    Self::handle_error(err)
+                }
+            };
+        }
+
+        // read the screenshot data, which is needed as a whole for computing the ETag
+        let screenshot_data = match self.screenshot_cache.get(&id, &screenshot_info.extension) {
+            Some(screenshot_data) => screenshot_data,
+            None => match read_screenshot(&self.storage, &id, &screenshot_info.extension).await {
+                Ok(screenshot_data) => {
+                    self.screenshot_cache.insert(
+                        id,
+                        &screenshot_info.extension,
+                        screenshot_data.clone(),
+                    );
+                    screenshot_data
+                }
+                Err(err) => {
+                    error!("Error reading screenshot data: {}", err);
+                    return Self::handle_error(err);
+                }
+            },
+        };
+
+        Ok(self.create_screenshot_response(
+            screenshot_data,
+            &screenshot_info.mime_type,
+            if_none_match,
+        ))
+    }
+
+    /// Handles the request to download all screenshots of a movie composited into a single grid
+    /// image, i.e., a contact sheet. The contact sheets are cached by the set of their
+    /// screenshots, s.t. regenerated screenshots result in a new contact sheet.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    /// * `columns` - Optionally, the number of columns of the grid.
+    /// * `if_none_match` - Optionally, the ETags of the contact sheet cached by the client.
+    pub async fn handle_download_contact_sheet(
+        &self,
+        id: MovieId,
+        columns: Option<u32>,
+        if_none_match: Option<IfNoneMatch>,
+    ) -> Result<HttpResponse> {
+        info!("Downloading contact sheet {} ...", id);
+
+        let columns = columns.unwrap_or(DEFAULT_CONTACT_SHEET_COLUMNS);
+        if columns == 0 {
+            return Self::handle_error(Error::InvalidArgument(
+                "A contact sheet needs at least one column".to_owned(),
+            ));
+        }
+
+        let screenshot_info = match self.index.read().await.get_movie(&id).await {
+            Ok(movie) => match movie.screenshot_file_info {
+                Some(screenshot_info) => screenshot_info,
+                None => {
+                    error!("Movie {} has no screenshot info", id);
+                    return Err(actix_web::error::ErrorConflict(format!(
+                        "Movie {} is not yet ready",
+                        id
+                    )));
+                }
+            },
+            Err(err) => {
+                error!("Error getting screenshot info: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        match self.get_contact_sheet(id, &screenshot_info, columns).await {
+            Ok(data) => Ok(self.create_screenshot_response(
+                data,
+                screenshot_resizer::RESIZED_SCREENSHOT_MIME_TYPE,
+                if_none_match,
+            )),
+            Err(err) => {
+                error!("Error creating contact sheet: {}", err);
+                Self::handle_error(err)
+            }
+        }
+    }
+
+    /// Handles the request to download the animated preview of a movie, which is cached by
+    /// clients like the screenshots.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    /// * `if_none_match` - Optionally, the ETags of the animated preview cached by the client.
+    pub async fn handle_download_animated_preview(
+        &self,
+        id: MovieId,
+        if_none_match: Option<IfNoneMatch>,
+    ) -> Result<HttpResponse> {
+        info!("Downloading animated preview {} ...", id);
+
+        let animated_preview_info = match self.index.read().await.get_movie(&id).await {
+            Ok(movie) => match movie.animated_preview_info {
+                Some(animated_preview_info) => animated_preview_info,
+                None => {
+                    error!("Movie {} has no animated preview", id);
+                    return Err(actix_web::error::ErrorNotFound(format!(
+                        "Movie {} has no animated preview",
+                        id
+                    )));
+                }
+            },
+            Err(err) => {
+                error!("Error getting animated preview info: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        let mut data = Vec::new();
+        let result = match self
+            .storage
+            .read()
+            .await
+            .read_movie_data(
+                id,
+                MovieDataType::AnimatedPreviewData {
+                    ext: animated_preview_info.extension.clone(),
+                },
+            )
+            .await
+        {
+            Ok(mut reader) => reader.read_to_end(&mut data).await.map_err(Error::from),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            error!("Error reading animated preview data: {}", err);
+            return Self::handle_error(err);
+        }
+
+        Ok(self.create_screenshot_response(data, &animated_preview_info.mime_type, if_none_match))
+    }
+
+    /// Handles the upload of a subtitle file of the given language for a movie. An existing
+    /// subtitle of the same language is replaced.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    /// * `language` - The language of the subtitle, e.g., "en".
+    /// * `multipart` - The multipart stream containing the subtitle file.
+    pub async fn handle_upload_subtitle(
+        &self,
+        id: MovieId,
+        language: String,
+        mut multipart: Multipart,
+    ) -> Result<impl Responder> {
+        let _movie_lock = self.movie_locks.lock(&id).await;
+        self.check_movie_exists(&id).await?;
+
+        info!("Uploading subtitle {} [{}] ...", id, language);
+
+        // iterate over multipart stream
+        let mut num_fields = 0;
+        while let Ok(Some(mut field)) = multipart.try_next().await {
+            num_fields += 1;
+            if let Err(err) =
+                check_multipart_field_count(num_fields, self.options.max_multipart_fields)
+            {
+                return Self::handle_error(err);
+            }
+
+            // extract the filename
+            let content_type = field.content_disposition();
+            let filename: String =
+                match content_type.and_then(|content_type| content_type.get_filename()) {
+                    Some(filename) => filename.to_owned(),
+                    None => {
+                        error!("Invalid filename");
+                        return Err(actix_web::error::ErrorBadRequest("Invalid filename"));
+                    }
+                };
+
+            // extract content type information
+            let content_type: String = match field
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .map(|content_type| content_type.to_str())
+            {
+                Some(Ok(content_type)) => content_type.to_string(),
+                Some(Err(_)) | None => {
+                    error!("Invalid content type");
+                    return Err(actix_web::error::ErrorBadRequest("Invalid content type"));
+                }
+            };
+
+            // check if the content type is a subtitle, e.g., text/vtt or application/x-subrip
+            if !is_subtitle_content_type(&content_type) {
+                error!("Invalid content type");
+                return Err(actix_web::error::ErrorUnsupportedMediaType(
+                    "Invalid content type",
+                ));
+            }
+
+            info!(
+                "Uploading subtitle {:?} with mime-type {}",
+                filename, content_type
+            );
+
+            // extract the extension
+            let ext = match get_upload_extension(&filename) {
+                Ok(ext) => ext,
+                Err(err) => {
+                    error!("{}", err);
+                    return Self::handle_error(err);
+                }
+            };
+
+            debug!("Uploading subtitle with extension: {:?}", ext);
+
+            // open writer for storing subtitle data
+            let writer = match self
+                .storage
+                .read()
+                .await
+                .write_movie_data(
+                    id.clone(),
+                    MovieDataType::SubtitleData {
+                        ext: ext.clone(),
+                        language: language.clone(),
+                    },
+                )
+                .await
+            {
+                Ok(writer) => writer,
+                Err(err) => {
+                    return Self::handle_error(err);
+                }
+            };
+            let mut writer = BufWriter::with_capacity(self.options.upload_buffer_size, writer);
+
+            // Field in turn is stream of *Bytes* object
+            while let Some(chunk) = field.next().await {
+                let data = match chunk {
+                    Ok(data) => data,
+                    Err(err) => {
+                        error!("Error reading chunk: {}", err);
+                        return Err(actix_web::error::ErrorInternalServerError(err));
+                    }
+                };
+
+                match writer.write_all(&data).await {
+                    Ok(_) => (),
+                    Err(err) => {
+                        error!("Error writing chunk: {}", err);
+                        return Err(actix_web::error::ErrorInternalServerError(err));
+                    }
+                }
+            }
+
+            // make sure all buffered data has been written before updating the index
+            if let Err(err) = writer.flush().await {
+                error!("Error flushing data: {}", err);
+                return Err(actix_web::error::ErrorInternalServerError(err));
+            }
+
+            let mut index = self.index.write().await;
+            let previous = match index.get_subtitles(&id).await {
+                Ok(subtitles) => subtitles
+                    .into_iter()
+                    .find(|subtitle| subtitle.language == language),
+                Err(err) => {
+                    error!("Error getting subtitles: {}", err);
+                    return Self::handle_error(err);
+                }
+            };
+
+            if let Err(err) = index
+                .add_subtitle_info(
+                    &id,
+                    SubtitleInfo {
+                        language: language.clone(),
+                        mime_type: content_type,
+                        extension: ext.clone(),
+                    },
+                )
+                .await
+            {
+                error!("Error updating subtitle info: {}", err);
+                return Err(actix_web::error::ErrorInternalServerError(err));
+            }
+            drop(index);
+
+            // the replaced subtitle is only left behind if it had a different extension
+            if let Some(previous) = previous.filter(|previous| previous.extension != ext) {
+                if let Err(err) = self
+                    .storage
+                    .read()
+                    .await
+                    .remove_movie_file(
+                        id.clone(),
+                        MovieDataType::SubtitleData {
+                            ext: previous.extension,
+                            language: language.clone(),
+                        },
+                    )
+                    .await
+                {
+                    warn!("Failed to remove replaced subtitle of {}: {}", id, err);
+                }
+            }
+        }
+
+        info!("Uploading subtitle {} [{}] ... DONE", id, language);
+
+        Ok(actix_web::HttpResponse::Ok())
+    }
+
+    /// Handles the request to download the subtitle of the given language of a movie.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    /// * `language` - The language of the subtitle, e.g., "en".
+    pub async fn handle_download_subtitle(
+        &self,
+        id: MovieId,
+        language: String,
+    ) -> Result<HttpResponse> {
+        info!("Downloading subtitle {} [{}] ...", id, language);
+
+        let subtitle_info = match self.index.read().await.get_subtitles(&id).await {
+            Ok(subtitles) => match subtitles
+                .into_iter()
+                .find(|subtitle| subtitle.language == language)
+            {
+                Some(subtitle_info) => subtitle_info,
+                None => {
+                    error!("Movie {} has no subtitle for language {}", id, language);
+                    return Err(actix_web::error::ErrorNotFound(format!(
+                        "Movie {} has no subtitle for language {}",
+                        id, language
+                    )));
+                }
+            },
+            Err(err) => {
+                error!("Error getting subtitles: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        let mut data = Vec::new();
+        let result = match self
+            .storage
+            .read()
+            .await
+            .read_movie_data(
+                id,
+                MovieDataType::SubtitleData {
+                    ext: subtitle_info.extension.clone(),
+                    language,
+                },
+            )
+            .await
+        {
+            Ok(mut reader) => reader.read_to_end(&mut data).await.map_err(Error::from),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            error!("Error reading subtitle data: {}", err);
+            return Self::handle_error(err);
+        }
+
+        Ok(HttpResponse::Ok()
+            .content_type(subtitle_info.mime_type)
+            .body(data))
+    }
+
+    /// Creates the response for the given screenshot data, which can be cached by clients for
+    /// the configured duration. The ETag of the response is based on the screenshot data, s.t. a
+    /// regenerated screenshot gets a new ETag. If the client already has the screenshot, the
+    /// response is `304 Not Modified` without any content.
+    ///
+    /// # Arguments
+    /// * `data` - The screenshot data.
+    /// * `mime_type` - The mime type of the screenshot.
+    /// * `if_none_match` - Optionally, the ETags of the screenshot cached by the client.
+    fn create_screenshot_response(
+        &self,
+        data: impl Into<web::Bytes>,
+        mime_type: &str,
+        if_none_match: Option<IfNoneMatch>,
+    ) -> HttpResponse {
+        let data: web::Bytes = data.into();
+        let mut hasher = DefaultHasher::new();
+        data[..].hash(&mut hasher);
+        let etag = EntityTag::new_strong(format!("{:016x}", hasher.finish()));
+
+        let cache_control = CacheControl(vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(self.options.screenshot_cache_max_age),
+        ]);
+
+        let is_cached = match if_none_match {
+            Some(IfNoneMatch::Any) => true,
+            Some(IfNoneMatch::Items(etags)) => etags.iter().any(|tag| tag.weak_eq(&etag)),
+            None => false,
+        };
+
+        if is_cached {
+            HttpResponse::NotModified()
+                .insert_header(header::ETag(etag))
+                .insert_header(cache_control)
+                .finish()
+        } else {
+            HttpResponse::Ok()
+                .content_type(mime_type)
+                .insert_header(header::ETag(etag))
+                .insert_header(cache_control)
+                .body(data)
+        }
+    }
+
+    /// Creates the response with the placeholder screenshot at the given path. The response is
+    /// marked as placeholder and may only be cached briefly.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the placeholder screenshot.
+    async fn create_placeholder_screenshot_response(path: &Path) -> Result<HttpResponse> {
+        let data = match tokio::fs::read(path).await {
+            Ok(data) => data,
+            Err(err) => {
+                error!(
+                    "Error reading placeholder screenshot {}: {}",
+                    path.display(),
+                    err
+                );
+                return Self::handle_error(err.into());
+            }
+        };
+
+        let mime_type = image::ImageFormat::from_path(path)
+            .map(|format| format.to_mime_type())
+            .unwrap_or("application/octet-stream");
+
+        Ok(HttpResponse::Ok()
+            .content_type(mime_type)
+            .insert_header((PLACEHOLDER_HEADER, "true"))
+            .insert_header(CacheControl(vec![
+                CacheDirective::Public,
+                CacheDirective::MaxAge(PLACEHOLDER_SCREENSHOT_MAX_AGE),
+            ]))
+            .body(data))
+    }
+
+    /// Creates the JSON response for the given movie metadata. Metadata changes frequently, s.t.
+    /// clients must revalidate it before using a cached copy.
+    ///
+    /// # Arguments
+    /// * `metadata` - The metadata to respond with.
+    fn create_metadata_response<T: Serialize>(metadata: &T) -> HttpResponse {
+        HttpResponse::Ok()
+            .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+            .json(metadata)
+    }
+
+    /// Returns the contact sheet of the given screenshots of the given movie with the given
+    /// number of columns, which is either taken from the cache or created and cached.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    /// * `screenshot_info` - The info of the screenshots of the movie.
+    /// * `columns` - The number of columns of the contact sheet.
+    async fn get_contact_sheet(
+        &self,
+        id: MovieId,
+        screenshot_info: &ScreenshotInfo,
+        columns: u32,
+    ) -> Result<web::Bytes, Error> {
+        let mut screenshots = Vec::with_capacity(screenshot_info.count as usize);
+        {
+            let storage = self.storage.read().await;
+            for index in 0..screenshot_info.count {
+                let mut screenshot_data = Vec::new();
+                storage
+                    .read_movie_data(
+                        id.clone(),
+                        MovieDataType::ScreenshotData {
+                            ext: screenshot_info.extension.clone(),
+                            index,
+                        },
+                    )
+                    .await?
+                    .read_to_end(&mut screenshot_data)
+                    .await?;
+                screenshots.push(screenshot_data);
+            }
+        }
+
+        // the screenshots are read anyway, s.t. hashing them keeps the cache valid without
+        // tracking when the screenshots are regenerated
+        let mut hasher = DefaultHasher::new();
+        columns.hash(&mut hasher);
+        screenshots.hash(&mut hasher);
+        let key = hasher.finish();
+
+        if let Some(data) = self.contact_sheets.lock().unwrap().get(&key) {
+            debug!("Use cached contact sheet for {}", id);
+            return Ok(data.clone());
+        }
+
+        // compositing is CPU heavy, so it is done outside of the async runtime
+        let data: web::Bytes = tokio::task::spawn_blocking(move || {
+            screenshot_resizer::create_contact_sheet(&screenshots, columns)
+        })
+        .await
+        .map_err(|err| Error::Internal(format!("Failed to create contact sheet: {}", err)))??
+        .into();
+
+        let size = data.len();
+        self.contact_sheets
+            .lock()
+            .unwrap()
+            .insert(key, data.clone(), size);
+
+        Ok(data)
+    }
+
+    /// Returns the screenshot resized to the given bounds. If a resized variant with the given
+    /// bounds has been cached before, the cached variant is returned. Otherwise, the resized
+    /// screenshot is created and cached.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie whose screenshot will be resized.
+    /// * `screenshot_info` - The info of the original screenshot.
+    /// * `width` - The maximal width of the resized screenshot.
+    /// * `height` - The maximal height of the resized screenshot.
+    async fn get_resized_screenshot(
+        &self,
+        id: MovieId,
+        screenshot_info: &ScreenshotInfo,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, Error> {
+        let storage = self.storage.read().await;
+
+        let resized_data_type = || MovieDataType::ResizedScreenshotData {
+            ext: screenshot_resizer::RESIZED_SCREENSHOT_EXT.to_owned(),
+            width,
+            height,
+        };
+
+        // check if the resized screenshot is already cached
+        if let Ok(mut cached_reader) = storage
+            .read_movie_data(id.clone(), resized_data_type())
+            .await
+        {
+            debug!("Use cached screenshot {}x{} for {}", width, height, id);
+            let mut cached_data = Vec::new();
+            cached_reader.read_to_end(&mut cached_data).await?;
+
+            return Ok(cached_data);
+        }
+
+        // read the original screenshot
+        let mut screenshot_data = Vec::new();
+        storage
+            .read_movie_data(
+                id.clone(),
+                MovieDataType::ScreenshotData {
+                    ext: screenshot_info.extension.clone(),
+                    index: 0,
+                },
+            )
+            .await?
+            .read_to_end(&mut screenshot_data)
+            .await?;
+
+        // resizing is CPU heavy, so it is done outside of the async runtime
+        let resized_data = tokio::task::spawn_blocking(move || {
+            screenshot_resizer::resize_screenshot(&screenshot_data, width, height)
+        })
+        .await
+        .map_err(|err| Error::Internal(format!("Failed to resize screenshot: {}", err)))??;
+
+        // cache the resized screenshot by writing it into a temporary file, which is renamed
+        // afterwards, s.t. concurrent requests never read a partially written variant
+        let temp_ext = format!(
+            "{}.{}.tmp",
+            screenshot_resizer::RESIZED_SCREENSHOT_EXT,
+            generate_movie_id()
+        );
+        let temp_data_type = || MovieDataType::ResizedScreenshotData {
+            ext: temp_ext.clone(),
+            width,
+            height,
+        };
+        let ret = async {
+            let mut writer = storage
+                .write_movie_data(id.clone(), temp_data_type())
+                .await?;
+            writer.write_all(&resized_data).await?;
+            writer.flush().await?;
+            drop(writer);
+
+            storage
+                .rename_movie_data(id.clone(), temp_data_type(), resized_data_type())
+                .await
+        }
+        .await;
+
+        // the resized screenshot is returned anyway, even if it couldn't be cached
+        if let Err(err) = ret {
+            // a concurrent request may have cached the same variant in the meantime
+            if !matches!(err, Error::AlreadyExists(_)) {
+                warn!(
+                    "Failed to cache screenshot {}x{} for {}: {}",
+                    width, height, id, err
+                );
+            }
+
+            let _ = storage.remove_movie_file(id, temp_data_type()).await;
+        }
+
+        Ok(resized_data)
+    }
+
+    /// Updates the movie file info of the given movie and renames the stored movie file if the
+    /// extension changes. Returns the new movie file info.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie whose movie file info will be updated.
+    /// * `mime_type` - The new mime type of the movie file, must be a video mime type.
+    /// * `extension` - Optionally, the new extension of the movie file.
+    async fn update_movie_file_info(
+        &self,
+        id: &MovieId,
+        mime_type: String,
+        extension: Option<String>,
+    ) -> Result<MovieFileInfo, Error> {
+        if !mime_type.starts_with("video/") {
+            return Err(Error::InvalidArgument(format!(
+                "Mime type '{}' is not a video mime type",
+                mime_type
+            )));
+        }
+
+        if let Some(extension) = &extension {
+            if !is_valid_extension(extension) {
+                return Err(Error::InvalidArgument(format!(
+                    "Invalid extension '{}'",
+                    extension
+                )));
+            }
+        }
+
+        // keep the movie locked, s.t. the movie file info can't change in the meantime
+        let _movie_lock = self.movie_locks.lock(id).await;
+
+        let old_info = match self.index.read().await.get_movie(id).await?.movie_file_info {
+            Some(movie_file_info) => movie_file_info,
+            None => {
+                return Err(Error::NotFound(format!("Movie {} has no movie file", id)));
+            }
+        };
+
+        let new_info = MovieFileInfo {
+            extension: extension
+                .map(|extension| extension.to_lowercase())
+                .unwrap_or_else(|| old_info.extension.clone()),
+            mime_type,
+            original_filename: old_info.original_filename.clone(),
+        };
+
+        let data_type = |movie_file_info: &MovieFileInfo| MovieDataType::MovieData {
+            ext: movie_file_info.extension.clone(),
+        };
+
+        let rename = new_info.extension != old_info.extension;
+        if rename && self.options.immutable_files {
+            return Err(Error::AlreadyExists(format!(
+                "Movie file for movie '{}' is immutable and must not be renamed",
+                id
+            )));
+        }
+
+        if rename {
+            self.storage
+                .read()
+                .await
+                .rename_movie_data(id.clone(), data_type(&old_info), data_type(&new_info))
+                .await?;
+        }
+
+        if let Err(err) = self
+            .index
+            .write()
+            .await
+            .update_movie_file_info(id, new_info.clone())
+            .await
+        {
+            // undo the renaming to keep the storage consistent with the index
+            if rename {
+                if let Err(err) = self
+                    .storage
+                    .read()
+                    .await
+                    .rename_movie_data(id.clone(), data_type(&new_info), data_type(&old_info))
+                    .await
+                {
+                    error!("Failed to restore movie file of movie {}: {}", id, err);
+                }
+            }
+
+            return Err(err);
+        }
+
+        Ok(new_info)
+    }
+
+    /// Queues the given preview request. If the queue is full, the request is either delayed or
+    /// dropped according to the configured backpressure policy.
+    ///
+    /// # Arguments
+    /// * `request` - The preview request to queue.
+    async fn request_preview(&self, request: ScreenshotRequest) -> PreviewRequestOutcome {
+        // the request is marked as pending before sending, as it may be processed immediately
+        let movie_id = request.movie_id.clone();
+        self.pending_previews.add(&movie_id);
+        self.set_preview_state(&movie_id, PreviewState::Pending)
+            .await;
+
+        let outcome = match self.options.preview_backpressure {
+            PreviewBackpressurePolicy::Wait => match self.preview_requests.send(request).await {
+                Ok(()) => PreviewRequestOutcome::Queued,
+                Err(err) => {
+                    error!("Error sending preview request: {}", err);
+                    PreviewRequestOutcome::Failed
+                }
+            },
+            PreviewBackpressurePolicy::Drop | PreviewBackpressurePolicy::Defer => {
+                match self.preview_requests.try_send(request) {
+                    Ok(()) => PreviewRequestOutcome::Queued,
+                    Err(TrySendError::Full(request)) => {
+                        warn!(
+                            "Preview queue is full, dropping preview request for movie {}",
+                            request.movie_id
+                        );
+                        PreviewRequestOutcome::QueueFull
+                    }
+                    Err(err) => {
+                        error!("Error sending preview request: {}", err);
+                        PreviewRequestOutcome::Failed
+                    }
+                }
+            }
+        };
+
+        if outcome != PreviewRequestOutcome::Queued {
+            self.pending_previews.remove(&movie_id);
+        }
+
+        // dropped requests stay pending, as they are requested again on the next startup
+        if outcome == PreviewRequestOutcome::Failed {
+            self.set_preview_state(&movie_id, PreviewState::Unsupported)
+                .await;
+        }
+
+        outcome
+    }
+
+    /// Updates the preview state of the given movie. Failures are only logged, as the preview
+    /// state is informational.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to update.
+    /// * `preview_state` - The new state of the preview generation.
+    async fn set_preview_state(&self, id: &MovieId, preview_state: PreviewState) {
+        if let Err(err) = self
+            .index
+            .write()
+            .await
+            .update_preview_state(id, preview_state)
+            .await
+        {
+            error!("Error updating preview state of movie {}: {}", id, err);
+        }
+    }
+
+    /// Handles the request to report the status of the given movies, i.e., whether they have a
+    /// movie file and a screenshot and whether the generation of their preview is pending.
+    ///
+    /// # Arguments
+    /// * `ids` - The ids of the movies to report the status for.
+    pub async fn handle_get_movies_status(&self, ids: Vec<MovieId>) -> Result<impl Responder> {
+        let index = self.index.read().await;
+
+        let mut statuses: Vec<MovieStatus> = Vec::with_capacity(ids.len());
+        for id in ids {
+            let movie = match index.get_movie(&id).await {
+                Ok(movie) => Some(movie),
+                Err(Error::NotFound(_)) => None,
+                Err(err) => {
+                    error!("Error getting movie {}: {}", id, err);
+                    return Self::handle_error(err);
+                }
+            };
+
+            let has_file = movie
+                .as_ref()
+                .is_some_and(|movie| movie.movie_file_info.is_some());
+            let has_screenshot = movie
+                .as_ref()
+                .is_some_and(|movie| movie.screenshot_file_info.is_some());
+
+            let preview = if movie.is_none() {
+                PreviewStatus::NotFound
+            } else if self.pending_previews.contains(&id) {
+                PreviewStatus::Pending
+            } else if has_screenshot {
+                PreviewStatus::Ready
+            } else {
+                PreviewStatus::Missing
+            };
+
+            statuses.push(MovieStatus {
+                id,
+                has_file,
+                has_screenshot,
+                preview,
+            });
+        }
+
+        Ok(web::Json(statuses))
+    }
+
+    /// Handles the request to download a movie including its metadata, movie file and screenshot
+    /// as a single ZIP bundle, which is optionally gzip-compressed.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to export.
+    /// * `compression` - The compression of the bundle.
+    pub async fn handle_download_bundle(
+        &self,
+        id: MovieId,
+        compression: BundleCompression,
+    ) -> Result<impl Responder> {
+        info!("Downloading bundle {} ...", id);
+
+        let movie = match self.index.read().await.get_movie(&id).await {
+            Ok(movie) => movie,
+            Err(err) => {
+                error!("Error getting movie info: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        // create readers onto the movie and screenshot data, if available
+        let storage = self.storage.read().await;
+        let movie_data = match &movie.movie_file_info {
+            Some(movie_file_info) => match storage
+                .read_movie_data(
+                    id.clone(),
+                    MovieDataType::MovieData {
+                        ext: movie_file_info.extension.clone(),
+                    },
+                )
+                .await
+            {
+                Ok(movie_data) => Some(movie_data),
+                Err(err) => {
+                    error!("Error reading movie data: {}", err);
+                    return Self::handle_error(err);
+                }
+            },
+            None => None,
+        };
+
+        let screenshot_data = match &movie.screenshot_file_info {
+            Some(screenshot_info) => match storage
+                .read_movie_data(
+                    id.clone(),
+                    MovieDataType::ScreenshotData {
+                        ext: screenshot_info.extension.clone(),
+                        index: 0,
+                    },
+                )
+                .await
+            {
+                Ok(screenshot_data) => Some(screenshot_data),
+                Err(err) => {
+                    error!("Error reading screenshot data: {}", err);
+                    return Self::handle_error(err);
+                }
+            },
+            None => None,
+        };
+        drop(storage);
+
+        let mut filename = bundle::create_bundle_filename(&movie.movie.title);
+
+        // the bundle is written by a separate task into a pipe whose other end is streamed to the
+        // client, s.t. the movie data is never fully loaded into memory
+        let (writer, reader) = tokio::io::duplex(BUNDLE_PIPE_SIZE);
+        actix_web::rt::spawn(async move {
+            let ret = match compression {
+                BundleCompression::None => {
+                    bundle::write_bundle(writer, &movie, movie_data, screenshot_data).await
+                }
+                BundleCompression::GzipFile | BundleCompression::GzipEncoding => {
+                    bundle::write_compressed_bundle(writer, &movie, movie_data, screenshot_data)
+                        .await
+                }
+            };
+
+            match ret {
+                Ok(_) => info!("Downloading bundle {} ... DONE", id),
+                Err(err) => error!("Error writing bundle for movie {}: {}", id, err),
+            }
+        });
+
+        let mut response = HttpResponse::Ok();
+        match compression {
+            BundleCompression::None => {
+                response.content_type("application/zip");
+            }
+            BundleCompression::GzipFile => {
+                response.content_type("application/gzip");
+                filename.push_str(".gz");
+            }
+            BundleCompression::GzipEncoding => {
+                response
+                    .content_type("application/zip")
+                    .insert_header((header::CONTENT_ENCODING, "gzip"))
+                    .insert_header((header::VARY, "Accept-Encoding"));
+            }
+        }
+
+        Ok(response
+            .append_header(header::ContentDisposition {
+                disposition: header::DispositionType::Attachment,
+                parameters: vec![header::DispositionParam::Filename(filename)],
+            })
+            .streaming(self.with_download_idle_timeout(ReaderStream::new(reader))))
+    }
+
+    /// Handles the request to import a movie from a ZIP bundle as produced by the bundle export.
+    /// Gzip-compressed bundles are decompressed transparently. Returns the id of the newly
+    /// created movie.
+    ///
+    /// # Arguments
+    /// * `multipart` - The multipart data containing the bundle.
+    pub async fn handle_upload_bundle(&self, mut multipart: Multipart) -> Result<impl Responder> {
+        info!("Uploading bundle ...");
+
+        let mut field = match multipart.try_next().await {
+            Ok(Some(field)) => field,
+            Ok(None) => {
+                error!("No bundle provided");
+                return Err(actix_web::error::ErrorBadRequest("No bundle provided"));
+            }
+            Err(err) => {
+                error!("Error reading multipart data: {}", err);
+                return Err(actix_web::error::ErrorBadRequest(err));
+            }
+        };
+
+        // the bundle needs to be seekable for reading it, so it is spooled to a temporary file
+        let bundle_path =
+            std::env::temp_dir().join(format!("movies-db-{}.zip", generate_movie_id()));
+        debug!("Spool bundle to {}", bundle_path.display());
+
+        let bundle_file = tokio::fs::File::create(&bundle_path).await?;
+        let mut bundle_file =
+            BufWriter::with_capacity(self.options.upload_buffer_size, bundle_file);
+        let mut bundle_size: u64 = 0;
+        while let Some(chunk) = field.next().await {
+            let data = match chunk {
+                Ok(data) => data,
+                Err(err) => {
+                    error!("Error reading chunk: {}", err);
+                    Self::remove_temp_file(&bundle_path).await;
+                    return Err(actix_web::error::ErrorInternalServerError(err));
+                }
+            };
+
+            // the spooled bundle must not fill up the temporary directory
+            bundle_size += data.len() as u64;
+            let max_bundle_size = self.options.max_bundle_size;
+            if max_bundle_size > 0 && bundle_size > max_bundle_size {
+                error!(
+                    "Bundle exceeds the maximal size of {} bytes",
+                    max_bundle_size
+                );
+                Self::remove_temp_file(&bundle_path).await;
+                return Err(actix_web::error::ErrorPayloadTooLarge(format!(
+                    "Bundle exceeds the maximal size of {} bytes",
+                    max_bundle_size
+                )));
+            }
+
+            if let Err(err) = bundle_file.write_all(&data).await {
+                error!("Error writing chunk: {}", err);
+                Self::remove_temp_file(&bundle_path).await;
+                return Err(actix_web::error::ErrorInternalServerError(err));
+            }
+        }
+
+        if let Err(err) = bundle_file.flush().await {
+            error!("Error flushing bundle: {}", err);
+            Self::remove_temp_file(&bundle_path).await;
+            return Err(actix_web::error::ErrorInternalServerError(err));
+        }
+        drop(bundle_file);
+
+        // compressed bundles are decompressed into a separate temporary file before the import
+        let ret = match bundle::is_gzip_file(&bundle_path).await {
+            Ok(true) => {
+                let unpacked_path = bundle_path.with_extension("unpacked.zip");
+                debug!("Decompress bundle to {}", unpacked_path.display());
+
+                let ret = match bundle::decompress_file(
+                    &bundle_path,
+                    &unpacked_path,
+                    self.options.max_bundle_size,
+                )
+                .await
+                {
+                    Ok(()) => self.import_bundle(&unpacked_path).await,
+                    Err(err) => Err(err),
+                };
+                Self::remove_temp_file(&unpacked_path).await;
+
+                ret
+            }
+            Ok(false) => self.import_bundle(&bundle_path).await,
+            Err(err) => Err(err),
+        };
+        Self::remove_temp_file(&bundle_path).await;
+
+        match ret {
+            Ok(id) => {
+                info!("Uploading bundle ... DONE, created movie {}", id);
+                Ok(id)
+            }
+            Err(err) => Self::handle_error(err),
+        }
+    }
+
+    /// Imports the bundle stored at the given path and returns the id of the newly created movie.
+    /// If the import fails, all data of the partially imported movie is removed again.
+    ///
+    /// # Arguments
+    /// * `bundle_path` - The path to the bundle file.
+    async fn import_bundle(&self, bundle_path: &Path) -> Result<MovieId, Error> {
+        let bundle_file = tokio::fs::File::open(bundle_path).await?;
+        let mut bundle = bundle::BundleReader::new(BufReader::new(bundle_file))
+            .await?
+            .with_max_entry_size(self.options.max_bundle_size);
+        let mut metadata = bundle.metadata().clone();
+        self.limit_description(&mut metadata.movie)?;
+
+        // the extensions are used in the names of the stored files, which are passed to ffmpeg
+        let extensions = metadata
+            .movie_file_info
+            .iter()
+            .map(|info| &info.extension)
+            .chain(
+                metadata
+                    .screenshot_file_info
+                    .iter()
+                    .map(|info| &info.extension),
+            );
+        for extension in extensions {
+            if !is_valid_extension(extension) {
+                error!("Bundle has unsafe file extension '{}'", extension);
+                return Err(Error::InvalidArgument(format!(
+                    "Invalid file extension '{}' in bundle",
+                    extension
+                )));
+            }
+        }
+
+        let id = self.index.write().await.add_movie(metadata.movie).await?;
+
+        let ret = self
+            .import_bundle_files(
+                &id,
+                &mut bundle,
+                metadata.movie_file_info,
+                metadata.screenshot_file_info,
+            )
+            .await;
+
+        if let Err(err) = ret {
+            error!("Failed to import bundle, remove movie {}", id);
+            if let Err(err) = self.index.write().await.remove_movie(&id).await {
+                error!("Failed to remove movie {}: {}", id, err);
+            }
+            if let Err(err) = self
+                .storage
+                .read()
+                .await
+                .remove_movie_data(id.clone())
+                .await
+            {
+                error!("Failed to remove movie data {}: {}", id, err);
+            }
+
+            return Err(err);
+        }
+
+        Ok(id)
+    }
+
+    /// Stores the movie file and the screenshot of the given bundle for the given movie and
+    /// updates the index accordingly.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to store the files for.
+    /// * `bundle` - The bundle to read the files from.
+    /// * `movie_file_info` - The movie file info of the bundle.
+    /// * `screenshot_info` - The screenshot info of the bundle.
+    async fn import_bundle_files<R>(
+        &self,
+        id: &MovieId,
+        bundle: &mut bundle::BundleReader<R>,
+        movie_file_info: Option<MovieFileInfo>,
+        screenshot_info: Option<ScreenshotInfo>,
+    ) -> Result<(), Error>
+    where
+        R: AsyncBufRead + AsyncSeek + Unpin,
+    {
+        let storage = self.storage.read().await;
+        storage.allocate_movie_data(id.clone()).await?;
+
+        if let Some(movie_file_info) = movie_file_info {
+            let writer = storage
+                .write_movie_data(
+                    id.clone(),
+                    MovieDataType::MovieData {
+                        ext: movie_file_info.extension.clone(),
+                    },
+                )
+                .await?;
+            let mut writer = BufWriter::with_capacity(self.options.upload_buffer_size, writer);
+            bundle.copy_movie_data(&mut writer).await?;
+            writer.flush().await?;
+
+            let ext = movie_file_info.extension.clone();
+            self.index
+                .write()
+                .await
+                .update_movie_file_info(id, movie_file_info)
+                .await?;
+
+            // only generate a preview if the bundle does not provide a screenshot
+            if screenshot_info.is_none() {
+                self.request_preview(ScreenshotRequest {
+                    movie_id: id.clone(),
+                    ext,
+                })
+                .await;
+            }
+        }
+
+        if let Some(screenshot_info) = screenshot_info {
+            let writer = storage
+                .write_movie_data(
+                    id.clone(),
+                    MovieDataType::ScreenshotData {
+                        ext: screenshot_info.extension.clone(),
+                        index: 0,
+                    },
+                )
+                .await?;
+            let mut writer = BufWriter::with_capacity(self.options.upload_buffer_size, writer);
+            bundle.copy_screenshot_data(&mut writer).await?;
+            writer.flush().await?;
+
+            self.index
+                .write()
+                .await
+                .update_screenshot_info(id, screenshot_info)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies the configured maximal description length to the given movie.
+    ///
+    /// # Arguments
+    /// * `movie` - The movie whose description will be checked.
+    fn limit_description(&self, movie: &mut Movie) -> Result<(), Error> {
+        match self.options.max_description_length {
+            Some(max_length) => {
+                movie.limit_description(max_length, self.options.description_length_policy)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Removes the given temporary file and logs an error if this fails.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the temporary file.
+    async fn remove_temp_file(path: &Path) {
+        if let Err(err) = tokio::fs::remove_file(path).await {
+            error!(
+                "Failed to remove temporary file {}: {}",
+                path.display(),
+                err
+            );
+        }
+    }
+
+    /// Prepares the given query for searching the index, i.e., resolves its relative time window
+    /// into an absolute one and corrects its tags.
+    ///
+    /// # Arguments
+    /// * `query` - The query to prepare.
+    async fn prepare_query(&self, mut query: MovieSearchQuery) -> Result<MovieSearchQuery> {
+        if let Err(err) = query.resolve_added_within(Utc::now()) {
+            error!("Invalid time window: {}", err);
+            return Self::handle_error(err);
+        }
+
+        self.correct_query_tags(query).await
+    }
+
+    /// Replaces the tags of the given query matching no known tag by the closest known tags, if
+    /// fuzzy tag matching is requested. Tags without a close known tag are kept, i.e., they still
+    /// match no movie.
+    ///
+    /// # Arguments
+    /// * `query` - The query whose tags to correct.
+    async fn correct_query_tags(&self, mut query: MovieSearchQuery) -> Result<MovieSearchQuery> {
+        if !query.tag_fuzzy_match || query.tags.is_empty() {
+            return Ok(query);
+        }
+
+        let known_tags: Vec<(String, usize)> =
+            match self.index.read().await.get_tag_list_with_count().await {
+                Ok(tags) => tags
+                    .into_iter()
+                    .map(|(tag, count)| (tag.to_lowercase(), count))
+                    .collect(),
+                Err(err) => {
+                    error!("Error getting the tags: {}", err);
+                    return Self::handle_error(err);
+                }
+            };
+
+        for tag in query.tags.iter_mut() {
+            let lower_tag = tag.to_lowercase();
+            let is_known = known_tags.iter().any(|(known_tag, _)| {
+                if query.tag_prefix_match {
+                    known_tag.starts_with(&lower_tag)
+                } else {
+                    *known_tag == lower_tag
+                }
+            });
+
+            if !is_known {
+                if let Some(corrected_tag) = correct_tag(&lower_tag, &known_tags) {
+                    debug!("Corrected tag '{}' to '{}'", tag, corrected_tag);
+                    *tag = corrected_tag.to_owned();
+                }
+            }
+        }
+
+        // the tags of a query are sorted, s.t. corrected tags must be sorted again
+        query.tags.sort();
+
+        Ok(query)
+    }
+
+    /// Handles the request to show the list of all movies. Besides the requested page of movies,
+    /// the response contains the total number of matches for building pagination controls.
+    ///
+    /// # Arguments
+    /// * `query` - The query to search for.
+    pub async fn handle_search_movies(&self, query: MovieSearchQuery) -> Result<impl Responder> {
+        let query = self.prepare_query(query).await?;
+        let start_index = query.start_index.unwrap_or(0);
+
+        let index = self.index.read().await;
+        let total = match index.search_movies_count(query.clone()).await {
+            Ok(total) => total,
+            Err(err) => {
+                error!("Error counting the search results: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        let movie_ids = match index.search_movies(query).await {
+            Ok(movie_ids) => movie_ids,
+            Err(err) => {
+                error!("Error searching: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        let mut movies: Vec<MovieListEntry> = Vec::with_capacity(movie_ids.len());
+        for movie_id in movie_ids.into_iter() {
+            match index.get_movie(&movie_id).await {
+                Ok(movie) => movies.push(MovieListEntry {
+                    id: movie_id,
+                    title: movie.movie.title,
+                }),
+                Err(err) => {
+                    error!("Error getting movie {}: {}", movie_id, err);
+                    return Self::handle_error(err);
+                }
+            }
+        }
+
+        Ok(Self::create_metadata_response(&SearchResult {
+            total,
+            start_index,
+            results: movies,
+        }))
+    }
+
+    /// Handles the request to search for movies, but only returns the ids of the matching
+    /// movies. In contrast to `handle_search_movies`, the individual movies are not looked up.
+    ///
+    /// # Arguments
+    /// * `query` - The query to search for.
+    pub async fn handle_search_movie_ids(&self, query: MovieSearchQuery) -> Result<impl Responder> {
+        let query = self.prepare_query(query).await?;
+        let movie_ids = match self.index.read().await.search_movies(query).await {
+            Ok(movie_ids) => movie_ids,
+            Err(err) => {
+                error!("Error searching: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        Ok(Self::create_metadata_response(&movie_ids))
+    }
+
+    /// Handles the request for the offset of the first movie whose title starts with the given
+    /// letter, s.t. a client browsing the movies sorted by their title can jump directly to it.
+    /// The pagination of the query is ignored.
+    ///
+    /// # Arguments
+    /// * `query` - The query defining the filtered and sorted movies.
+    /// * `letter` - The letter to jump to.
+    pub async fn handle_get_letter_offset(
+        &self,
+        query: MovieSearchQuery,
+        letter: char,
+    ) -> Result<impl Responder> {
+        if query.sorting_field != SortingField::Title {
+            error!("Jumping to a letter requires sorting by title");
+            return Self::handle_error(Error::InvalidArgument(
+                "Jumping to a letter requires sorting by title".to_owned(),
+            ));
+        }
+
+        let query = self.prepare_query(query).await?;
+        let offset = match self
+            .index
+            .read()
+            .await
+            .get_letter_offset(query, letter)
+            .await
+        {
+            Ok(offset) => offset,
+            Err(err) => {
+                error!("Error getting the offset of letter {}: {}", letter, err);
+                return Self::handle_error(err);
+            }
+        };
+
+        Ok(Self::create_metadata_response(&LetterOffset { offset }))
+    }
+
+    /// Handles the request to search for movies and to compute the facets of all matching movies,
+    /// i.e., the counts of the tags, years and formats. The facets ignore the pagination of the
+    /// query and thus describe the whole filtered set of movies.
+    ///
+    /// # Arguments
+    /// * `query` - The query to search for.
+    pub async fn handle_search_movies_faceted(
+        &self,
+        query: MovieSearchQuery,
+    ) -> Result<impl Responder> {
+        let query = self.prepare_query(query).await?;
+
+        let index = self.index.read().await;
+        let facets = match index.get_search_facets(query.clone()).await {
+            Ok(facets) => facets,
+            Err(err) => {
+                error!("Error computing the search facets: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        let movie_ids = match index.search_movies(query).await {
+            Ok(movie_ids) => movie_ids,
+            Err(err) => {
+                error!("Error searching: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        // only the movies of the requested page are looked up
+        let mut items: Vec<MovieListEntry> = Vec::with_capacity(movie_ids.len());
+        for movie_id in movie_ids.into_iter() {
+            match index.get_movie(&movie_id).await {
+                Ok(movie) => items.push(MovieListEntry {
+                    id: movie_id,
+                    title: movie.movie.title,
+                }),
+                Err(err) => {
+                    error!("Error getting movie {}: {}", movie_id, err);
+                    return Self::handle_error(err);
+                }
+            }
+        }
+
+        Ok(Self::create_metadata_response(&FacetedSearchResult {
+            items,
+            facets,
+        }))
+    }
+
+    /// Handles the request for an RSS feed of the movies matching the given query, where the most
+    /// recently added movies come first regardless of the sorting of the query.
+    ///
+    /// # Arguments
+    /// * `query` - The query to search for.
+    /// * `movies_url` - The URL of the movie endpoints, e.g.,
+    ///   `http://localhost:3030/api/v1/movie`.
+    pub async fn handle_get_movie_feed(
+        &self,
+        query: MovieSearchQuery,
+        movies_url: &str,
+    ) -> Result<impl Responder> {
+        let query = self.prepare_query(query).await?;
+        let query = MovieSearchQuery {
+            sorting_field: SortingField::Date,
+            sorting_order: SortingOrder::Descending,
+            featured_first: false,
+            num_results: Some(query.num_results.unwrap_or(DEFAULT_FEED_ITEMS)),
+            ..query
+        };
+
+        let index = self.index.read().await;
+        let movie_ids = match index.search_movies(query).await {
+            Ok(movie_ids) => movie_ids,
+            Err(err) => {
+                error!("Error searching: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        let mut movies: Vec<(MovieId, MovieDetailed)> = Vec::with_capacity(movie_ids.len());
+        for movie_id in movie_ids.into_iter() {
+            match index.get_movie(&movie_id).await {
+                Ok(movie) => movies.push((movie_id, movie)),
+                Err(err) => {
+                    error!("Error getting movie {}: {}", movie_id, err);
+                    return Self::handle_error(err);
+                }
+            }
+        }
+
+        Ok(HttpResponse::Ok()
+            .content_type(FEED_MIME_TYPE)
+            .body(create_rss_feed(movies_url, &movies)))
+    }
+
+    /// Handles the request to get the movies with the largest stored movie files, ordered
+    /// descending by their size.
+    ///
+    /// # Arguments
+    /// * `limit` - The maximal number of movies to return.
+    pub async fn handle_get_largest_movies(&self, limit: usize) -> Result<impl Responder> {
+        let movie_ids = match self
+            .index
+            .read()
+            .await
+            .search_movies(Default::default())
+            .await
+        {
+            Ok(movie_ids) => movie_ids,
+            Err(err) => {
+                error!("Error searching: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        let mut movies: Vec<MovieSizeEntry> = Vec::with_capacity(movie_ids.len());
+        for movie_id in movie_ids.iter() {
+            let movie = match self.index.read().await.get_movie(movie_id).await {
+                Ok(movie) => movie,
+                Err(err) => {
+                    error!("Error getting movie {}: {}", movie_id, err);
+                    return Self::handle_error(err);
+                }
+            };
+
+            // movies without a movie file do not occupy any relevant space
+            let movie_file_info = match movie.movie_file_info {
+                Some(movie_file_info) => movie_file_info,
+                None => continue,
+            };
+
+            // the size is taken from the file system, s.t. no movie file needs to be opened
+            let file_path = match self
+                .storage
+                .read()
+                .await
+                .get_file_path(
+                    movie_id.clone(),
+                    MovieDataType::MovieData {
+                        ext: movie_file_info.extension,
+                    },
+                )
+                .await
+            {
+                Ok(Some(file_path)) => file_path,
+                Ok(None) => {
+                    return Self::handle_error(Error::Internal(
+                        "File paths are not supported by backend".into(),
+                    ));
+                }
+                Err(err) => {
+                    error!("Error getting movie file path {}: {}", movie_id, err);
+                    continue;
+                }
+            };
+
+            let size = match tokio::fs::metadata(&file_path).await {
+                Ok(metadata) => metadata.len(),
+                Err(err) => {
+                    error!("Error reading movie file size {}: {}", movie_id, err);
+                    continue;
+                }
+            };
+
+            movies.push(MovieSizeEntry {
+                id: movie_id.clone(),
+                title: movie.movie.title,
+                size,
+            });
+        }
+
+        movies.sort_by_key(|movie| Reverse(movie.size));
+        movies.truncate(limit);
+
+        Ok(web::Json(movies))
+    }
+
+    /// Handles the request to get tag suggestions for the given tags, i.e., the tags that
+    /// co-occur most often with the given tags.
+    ///
+    /// # Arguments
+    /// * `tags` - The tags to get suggestions for.
+    pub async fn handle_suggest_tags(&self, tags: &[String]) -> Result<impl Responder> {
+        let suggestions = match self.index.read().await.suggest_tags(tags).await {
+            Ok(suggestions) => suggestions,
+            Err(err) => {
+                error!("Error getting tag suggestions: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        Ok(web::Json(suggestions))
+    }
+
+    /// Handles the request to re-probe the ffmpeg and ffprobe binaries. Allows to pick up updated
+    /// binaries without restarting the service and returns their current version info.
+    /// The binaries are probed by the ffmpeg instance of the handler, s.t. the probe shares the
+    /// bounded pool of ffmpeg processes and the timeout. Only if ffmpeg wasn't available at
+    /// startup, the binaries are probed by a new instance.
+    pub async fn handle_recheck_ffmpeg(&self) -> Result<impl Responder> {
+        let versions = match &self.ffmpeg {
+            Some(ffmpeg) => ffmpeg.get_versions().await,
+            None => FFMpeg::recheck(&self.options.ffmpeg).await,
+        };
+
+        let versions = match versions {
+            Ok(versions) => versions,
+            Err(err) => {
+                error!("Error rechecking ffmpeg: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        Ok(web::Json(versions))
+    }
+
+    /// Handles the request to find movies with near-duplicate screenshots. Returns the clusters
+    /// of movies whose screenshot hashes are within the given Hamming distance. Only movies
+    /// whose screenshot hash has been computed are considered.
+    ///
+    /// # Arguments
+    /// * `threshold` - The maximal Hamming distance of similar screenshot hashes.
+    pub async fn handle_get_similar_images(&self, threshold: u32) -> Result<impl Responder> {
+        let hashes = match self.index.read().await.get_screenshot_hashes().await {
+            Ok(hashes) => hashes,
+            Err(err) => {
+                error!("Error getting screenshot hashes: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        Ok(web::Json(cluster_similar(&hashes, threshold)))
+    }
+
+    /// Handles the request to regenerate the given derived artifacts of the given movies and of
+    /// the movies matching the optional query. Previews are queued for the preview generator,
+    /// s.t. they share the bounded pool of ffmpeg processes, and thumbnails are discarded, s.t.
+    /// they are rebuilt on their next request, while the other artifacts are regenerated
+    /// immediately. The response reports the result for each movie and artifact.
+    /// The request is rejected if the context may not update any of the selected movies.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context of the request.
+    /// * `ids` - The ids of the movies to reprocess.
+    /// * `query` - Optionally, the query selecting additional movies to reprocess.
+    /// * `artifacts` - The derived artifacts to regenerate.
+    pub async fn handle_reprocess(
+        &self,
+        ctx: &RequestContext,
+        mut ids: Vec<MovieId>,
+        query: Option<MovieSearchQuery>,
+        mut artifacts: Vec<DerivedArtifact>,
+    ) -> Result<impl Responder> {
+        if ids.is_empty() && query.is_none() {
+            return Self::handle_error(Error::InvalidArgument(
+                "Either ids or a query must be given".to_owned(),
+            ));
+        }
+
+        artifacts.sort();
+        artifacts.dedup();
+        if artifacts.is_empty() {
+            return Self::handle_error(Error::InvalidArgument(
+                "No artifacts to regenerate given".to_owned(),
+            ));
+        }
+
+        if let Some(query) = query {
+            let query = self.prepare_query(query).await?;
+            match self.index.read().await.search_movies(query).await {
+                Ok(movie_ids) => ids.extend(movie_ids),
+                Err(err) => {
+                    error!("Error searching: {}", err);
+                    return Self::handle_error(err);
+                }
+            }
+        }
+
+        for id in ids.iter() {
+            self.handle_check_access(ctx, id, Action::Update)?;
+        }
+
+        let mut results: Vec<ReprocessResult> = Vec::with_capacity(ids.len());
+        for id in ids {
+            if results.iter().any(|result| result.id == id) {
+                continue;
+            }
+
+            info!("Reprocessing movie {} ...", id);
+
+            let mut artifact_results = Vec::with_capacity(artifacts.len());
+            for artifact in artifacts.iter() {
+                let (status, error) = match self.reprocess_artifact(&id, *artifact).await {
+                    Ok(status) => (status, None),
+                    Err(err) => {
+                        warn!(
+                            "Failed to regenerate {:?} of movie {}: {}",
+                            artifact, id, err
+                        );
+                        (ReprocessStatus::Failed, Some(err.to_string()))
+                    }
+                };
+
+                artifact_results.push(ArtifactResult {
+                    artifact: *artifact,
+                    status,
+                    error,
+                });
+            }
+
+            results.push(ReprocessResult {
+                id,
+                artifacts: artifact_results,
+            });
+        }
+
+        Ok(web::Json(results))
+    }
+
+    /// Regenerates the given derived artifact of the given movie and returns whether it has been
+    /// regenerated, queued for regeneration or invalidated for a lazy regeneration.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    /// * `artifact` - The derived artifact to regenerate.
+    async fn reprocess_artifact(
+        &self,
+        id: &MovieId,
+        artifact: DerivedArtifact,
+    ) -> Result<ReprocessStatus, Error> {
+        let movie = self.index.read().await.get_movie(id).await?;
+        let _reprocess_job = self.active_jobs.start(id, JobKind::Reprocess);
+
+        match artifact {
+            DerivedArtifact::Preview => {
+                let ext = match movie.movie_file_info {
+                    Some(movie_file_info) => movie_file_info.extension,
+                    None => return Err(Error::NotFound(format!("Movie {} has no movie file", id))),
+                };
+
+                let outcome = self
+                    .request_preview(ScreenshotRequest {
+                        movie_id: id.clone(),
+                        ext,
+                    })
+                    .await;
+
+                match outcome {
+                    PreviewRequestOutcome::Queued => Ok(ReprocessStatus::Queued),
+                    PreviewRequestOutcome::QueueFull => {
+                        Err(Error::Internal("The preview queue is full".to_owned()))
+                    }
+                    PreviewRequestOutcome::Failed => {
+                        Err(Error::Internal("Previews are not available".to_owned()))
+                    }
+                }
+            }
+            DerivedArtifact::Thumbnails => {
+                let _movie_lock = self.movie_locks.lock(id).await;
+
+                self.storage
+                    .read()
+                    .await
+                    .invalidate_resized_screenshots(id.clone())
+                    .await?;
+
+                Ok(ReprocessStatus::Invalidated)
+            }
+            DerivedArtifact::ScreenshotHash => {
+                let screenshot_info = match movie.screenshot_file_info {
+                    Some(screenshot_info) => screenshot_info,
+                    None => return Err(Error::NotFound(format!("Movie {} has no screenshot", id))),
+                };
+
+                let _movie_lock = self.movie_locks.lock(id).await;
+
+                let mut screenshot_data = Vec::new();
+                self.storage
+                    .read()
+                    .await
+                    .read_movie_data(
+                        id.clone(),
+                        MovieDataType::ScreenshotData {
+                            ext: screenshot_info.extension,
+                            index: 0,
+                        },
+                    )
+                    .await?
+                    .read_to_end(&mut screenshot_data)
+                    .await?;
+
+                store_screenshot_hash(&self.index, id, screenshot_data).await?;
+
+                Ok(ReprocessStatus::Done)
+            }
+        }
+    }
+
+    /// Handles the request to backfill the video metadata of all movies having a movie file, but
+    /// no video metadata, e.g., as they have been uploaded before the metadata was stored. The
+    /// movie files are probed concurrently, where ffprobe shares the bounded pool of ffmpeg
+    /// processes with the preview generator. A dry run only counts the movies to probe.
+    ///
+    /// # Arguments
+    /// * `dry_run` - If true, the movies are counted, but not probed.
+    pub async fn handle_probe_all(&self, dry_run: bool) -> Result<impl Responder> {
+        let ffmpeg = match &self.ffmpeg {
+            Some(ffmpeg) => ffmpeg,
+            None => {
+                return Self::handle_error(Error::Internal(
+                    "ffprobe is not available for probing".to_owned(),
+                ))
+            }
+        };
+
+        let mut movies: Vec<(MovieId, String)> = Vec::new();
+        {
+            let index = self.index.read().await;
+            let movie_ids = match index.search_movies(MovieSearchQuery::default()).await {
+                Ok(movie_ids) => movie_ids,
+                Err(err) => {
+                    error!("Error searching: {}", err);
+                    return Self::handle_error(err);
+                }
+            };
+
+            for id in movie_ids {
+                let movie = match index.get_movie(&id).await {
+                    Ok(movie) => movie,
+                    Err(err) => {
+                        error!("Error getting movie {}: {}", id, err);
+                        return Self::handle_error(err);
+                    }
+                };
+
+                if let (Some(movie_file_info), None) = (movie.movie_file_info, movie.video_metadata)
+                {
+                    movies.push((id, movie_file_info.extension));
+                }
+            }
+        }
+
+        info!("Found {} movies without video metadata", movies.len());
+        if dry_run {
+            return Ok(web::Json(ProbeAllResult {
+                movies: movies.len(),
+                probed: 0,
+                failed: 0,
+            }));
+        }
+
+        let outcomes: Vec<Result<(), Error>> = futures::stream::iter(movies.iter())
+            .map(|(id, ext)| self.probe_movie(ffmpeg, id, ext))
+            .buffer_unordered(self.options.max_ffmpeg_processes.max(1))
+            .collect()
+            .await;
+
+        let failed = outcomes.iter().filter(|outcome| outcome.is_err()).count();
+
+        Ok(web::Json(ProbeAllResult {
+            movies: movies.len(),
+            probed: movies.len() - failed,
+            failed,
+        }))
+    }
+
+    /// Probes the movie file of the given movie and stores its video metadata.
+    ///
+    /// # Arguments
+    /// * `ffmpeg` - The ffmpeg instance for probing the movie file.
+    /// * `id` - The id of the movie.
+    /// * `ext` - The extension of the movie file.
+    async fn probe_movie(&self, ffmpeg: &FFMpeg, id: &MovieId, ext: &str) -> Result<(), Error> {
+        let _reprocess_job = self.active_jobs.start(id, JobKind::Reprocess);
+
+        let file_path = self
+            .storage
+            .read()
+            .await
+            .get_file_path(
+                id.clone(),
+                MovieDataType::MovieData {
+                    ext: ext.to_owned(),
+                },
+            )
+            .await?;
+        let file_path = match file_path {
+            Some(file_path) => file_path,
+            None => {
+                warn!("The movie file of {} is not stored locally", id);
+                return Err(Error::NotFound(format!(
+                    "The movie file of {} is not stored locally",
+                    id
+                )));
+            }
+        };
+
+        let video_metadata = match ffmpeg.get_movie_metadata(&file_path).await {
+            Ok(video_metadata) => video_metadata,
+            Err(err) => {
+                warn!("Failed to get video metadata of movie {}: {}", id, err);
+                return Err(err);
+            }
+        };
+
+        self.index
+            .write()
+            .await
+            .update_video_metadata(id, video_metadata)
+            .await
+    }
+
+    /// Handles the request to get a list of all tags with the number of movies associated with
+    /// each tag.
+    pub async fn handle_get_tags(&self) -> Result<impl Responder> {
+        // the tags are ordered like the top tags, s.t. only one more tag than the limit is loaded
+        let limit = self.options.max_tag_list_size;
+        let tags = if limit == 0 {
+            self.index.read().await.get_tag_list_with_count().await
+        } else {
+            self.index
+                .read()
+                .await
+                .get_top_tags(limit.saturating_add(1))
+                .await
+        };
+        let mut tags = match tags {
+            Ok(tags) => tags,
+            Err(err) => {
+                error!("Error getting tags: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        let mut response = HttpResponse::Ok();
+        if limit > 0 && tags.len() > limit {
+            warn!("Truncated the tag list to {} tags", limit);
+            tags.truncate(limit);
+            response
+                .insert_header((TRUNCATED_HEADER, "true"))
+                .insert_header((header::WARNING, TRUNCATED_TAGS_WARNING));
+        }
+
+        Ok(response.json(tags))
+    }
+
+    /// Handles the request to rename a tag of all movies and returns the number of movies having
+    /// the tag before the rename.
+    ///
+    /// # Arguments
+    /// * `from` - The tag to rename.
+    /// * `to` - The new name of the tag.
+    pub async fn handle_rename_tag(&self, from: &str, to: &str) -> Result<impl Responder> {
+        info!("Renaming tag '{}' to '{}' ...", from, to);
+
+        match self.index.write().await.rename_tag(from, to).await {
+            Ok(movies) => Ok(web::Json(RenamedTag { movies })),
+            Err(err) => {
+                error!("Error renaming tag '{}': {}", from, err);
+                Self::handle_error(err)
+            }
+        }
+    }
+
+    /// Handles the request to rename tags in bulk and returns the number of movies having each
+    /// renamed tag before the renames.
+    ///
+    /// # Arguments
+    /// * `renames` - The renames mapping the old tags onto the new tags.
+    pub async fn handle_rename_tags(
+        &self,
+        renames: &BTreeMap<String, String>,
+    ) -> Result<impl Responder> {
+        info!("Renaming {} tags ...", renames.len());
+
+        match self.index.write().await.rename_tags(renames).await {
+            Ok(counts) => Ok(web::Json(counts)),
+            Err(err) => {
+                error!("Error renaming tags: {}", err);
+                Self::handle_error(err)
+            }
+        }
+    }
+
+    /// Handles the request to get the most common tags.
+    ///
+    /// # Arguments
+    /// * `limit` - The maximal number of tags to return.
+    pub async fn handle_get_top_tags(&self, limit: usize) -> Result<impl Responder> {
+        let tags = match self.index.read().await.get_top_tags(limit).await {
+            Ok(tags) => tags,
+            Err(err) => {
+                error!("Error getting top tags: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        Ok(web::Json(tags))
+    }
+
+    /// Handles the request to autocomplete a tag, i.e., to get the most common tags starting
+    /// with the given prefix. The number of tags is bounded by the maximal size of the tag list.
+    ///
+    /// # Arguments
+    /// * `prefix` - The prefix of the tags, which is compared case-insensitively.
+    /// * `limit` - The maximal number of tags to return.
+    pub async fn handle_search_tags(&self, prefix: &str, limit: usize) -> Result<impl Responder> {
+        let limit = match self.options.max_tag_list_size {
+            0 => limit,
+            max_limit => limit.min(max_limit),
+        };
+
+        let tags = match self.index.read().await.search_tags(prefix, limit).await {
+            Ok(tags) => tags,
+            Err(err) => {
+                error!("Error searching tags with prefix '{}': {}", prefix, err);
+                return Self::handle_error(err);
+            }
+        };
+
+        Ok(web::Json(tags))
+    }
+
+    /// Handles the request to get the most recent activity events, starting with the newest one.
+    ///
+    /// # Arguments
+    /// * `limit` - The maximal number of events to return.
+    pub async fn handle_get_activity(&self, limit: usize) -> Result<impl Responder> {
+        Ok(web::Json(self.activity_log.get_recent(limit)))
+    }
+
+    /// Handles the request to stream all activity events from now on as server-sent events.
+    pub async fn handle_stream_activity(&self) -> Result<impl Responder> {
+        let receiver = self.activity_log.subscribe();
+
+        let events = futures::stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => {
+                        let data = serde_json::to_string(&event).unwrap_or_default();
+                        let data = web::Bytes::from(format!("data: {}\n\n", data));
+
+                        return Some((Ok::<_, actix_web::Error>(data), receiver));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(num_skipped)) => {
+                        warn!("Activity stream skipped {} events", num_skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+
+        Ok(HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+            .streaming(events))
+    }
+
+    /// Handles the request to get the number of movies added per year.
+    pub async fn handle_get_year_histogram(&self) -> Result<impl Responder> {
+        let histogram = match self.index.read().await.get_year_histogram().await {
+            Ok(histogram) => histogram,
+            Err(err) => {
+                error!("Error getting year histogram: {}", err);
+                return Self::handle_error(err);
+            }
+        };
+
+        Ok(web::Json(histogram))
+    }
+
+    /// Handles the request to get the aggregated execution times of the index queries. Fails
+    /// with 404 Not Found if query timing is disabled.
+    pub async fn handle_get_query_stats(&self) -> Result<impl Responder> {
+        match self.index.read().await.get_query_stats() {
+            Some(stats) => Ok(web::Json(stats)),
+            None => Self::handle_error(Error::NotFound("Query timing is disabled".to_owned())),
+        }
+    }
+
+    /// Handles the given error by translating it into an actix-web error response.
+    ///
+    /// # Arguments
+    /// * `err` - The error to handle.
+    fn handle_error<T>(err: Error) -> Result<T> {
+        match err {
+            Error::InvalidArgument(e) => {
+                error!("Invalid argument: {}", e);
+                Err(actix_web::error::ErrorBadRequest(e))
+            }
+            Error::NotFound(e) => {
+                error!("Not found: {}", e);
+                Err(actix_web::error::ErrorNotFound(e))
+            }
+            Error::AlreadyExists(e) => {
+                error!("Already exists: {}", e);
+                Err(actix_web::error::ErrorConflict(e))
+            }
+            Error::PermissionDenied(e) => {
+                error!("Permission denied: {}", e);
+                Err(actix_web::error::ErrorForbidden(e))
+            }
+            Error::InsufficientStorage(e) => {
+                error!("Insufficient storage: {}", e);
+                Err(actix_web::error::ErrorInsufficientStorage(e))
+            }
+            Error::PayloadTooLarge(e) => {
+                error!("Payload too large: {}", e);
+                Err(actix_web::error::ErrorPayloadTooLarge(e))
+            }
+            _ => {
+                error!("Internal error: {}", err);
+                Err(actix_web::error::ErrorInternalServerError(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::http::header::HeaderMap;
+    use actix_web::web::Bytes;
+    use chrono::Utc;
+    use image::GenericImageView;
+    use std::time::Duration;
+    use tempdir::TempDir;
+
+    use crate::service::activity_log::ActivityEvent;
+    use crate::{
+        file_storage::FileStorage, AnimatedPreviewInfo, SimpleMoviesIndex, SortingOrder,
+        VideoMetadata,
+    };
+
+    use super::*;
+
+    const BOUNDARY: &str = "movies-db-test-boundary";
+
+    /// Creates a new service handler based on the given options.
+    async fn create_handler(
+        options: &Options,
+    ) -> (
+        ServiceHandler<SimpleMoviesIndex, FileStorage>,
+        mpsc::Receiver<ScreenshotRequest>,
+    ) {
+        let index = Arc::new(RwLock::new(SimpleMoviesIndex::new(options).unwrap()));
+        let storage = Arc::new(RwLock::new(FileStorage::new(options).unwrap()));
+        let (preview_requests, recv_preview) = mpsc::channel(options.preview_queue_capacity);
+
+        let handler = ServiceHandler::new(
+            options,
+            index,
+            storage,
+            preview_requests,
+            PendingPreviews::default(),
+            ActivityLog::new(options.activity_log_capacity),
+        )
+        .await
+        .unwrap();
+
+        (handler, recv_preview)
+    }
+
+    /// Creates a new handler like `create_handler` and adds a single movie without a file.
+    async fn create_handler_with_movie(
+        options: &Options,
+    ) -> (
+        ServiceHandler<SimpleMoviesIndex, FileStorage>,
+        mpsc::Receiver<ScreenshotRequest>,
+        MovieId,
+    ) {
+        let (handler, recv_preview) = create_handler(options).await;
+
+        let id = handler
+            .index
+            .write()
+            .await
+            .add_movie(Movie {
+                title: "Das Boot".to_owned(),
+                description: String::new(),
+                tags: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        (handler, recv_preview, id)
+    }
+
+    /// Creates a multipart payload for the given file, split into chunks of the given size.
+    fn create_multipart(
+        filename: &str,
+        content_type: &str,
+        data: &[u8],
+        chunk_size: usize,
+    ) -> Multipart {
+        create_multipart_fields(&[(filename, content_type, data)], chunk_size)
+    }
+
+    /// Creates a multipart stream with a file field for each of the given filenames, content
+    /// types and data.
+    fn create_multipart_fields(fields: &[(&str, &str, &[u8])], chunk_size: usize) -> Multipart {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", BOUNDARY)
+                .parse()
+                .unwrap(),
+        );
+
+        let mut body: Vec<u8> = Vec::new();
+        for (filename, content_type, data) in fields {
+            body.extend_from_slice(
+                format!(
+                    "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                    BOUNDARY, filename, content_type
+                )
+                .as_bytes(),
+            );
+            body.extend_from_slice(data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", BOUNDARY).as_bytes());
+
+        let chunks: Vec<Result<Bytes, actix_web::error::PayloadError>> = body
+            .chunks(chunk_size)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+
+        Multipart::new(&headers, futures::stream::iter(chunks))
+    }
+
+    #[actix_web::test]
+    async fn test_add_movie_with_id() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        let movie = Movie {
+            title: "Das Boot".to_owned(),
+            description: String::new(),
+            tags: Vec::new(),
+        };
+
+        assert!(handler
+            .handle_add_movie(movie.clone(), Some("das-boot".to_owned()))
+            .await
+            .is_ok());
+
+        let err = match handler
+            .handle_add_movie(movie, Some("das-boot".to_owned()))
+            .await
+        {
+            Ok(_) => panic!("Adding a movie with a colliding id must fail"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::CONFLICT
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_upload_large_movie() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.upload_buffer_size = 64 * 1024;
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+
+        // create 8 MiB of data which doesn't contain the multipart boundary
+        let data: Vec<u8> = (0..8 * 1024 * 1024).map(|i| (i * 7 % 251) as u8).collect();
+
+        // upload in small fragmented chunks
+        let multipart = create_multipart("movie.mp4", "video/mp4", &data, 4093);
+        assert!(handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+            .is_ok());
+
+        let movie = handler.index.read().await.get_movie(&id).await.unwrap();
+        assert_eq!(
+            movie.movie_file_info,
+            Some(MovieFileInfo {
+                extension: "mp4".to_owned(),
+                mime_type: "video/mp4".to_owned(),
+                original_filename: Some("movie.mp4".to_owned()),
+            })
+        );
+
+        let mut reader = handler
+            .storage
+            .read()
+            .await
+            .read_movie_data(
+                id,
+                MovieDataType::MovieData {
+                    ext: "mp4".to_owned(),
+                },
+            )
+            .await
+            .unwrap();
+
+        let mut stored_data = Vec::new();
+        reader.read_to_end(&mut stored_data).await.unwrap();
+        assert!(stored_data == data);
+    }
+
+    #[actix_web::test]
+    async fn test_upload_unknown_movie() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        // uploads of unknown movies are rejected without storing any data
+        let id = generate_movie_id();
+        assert!(handler.handle_start_upload(id.clone()).await.is_err());
+
+        let multipart = create_multipart("movie.mp4", "video/mp4", &[42u8; 1024], 256);
+        let err = match handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+        {
+            Ok(_) => panic!("Uploading an unknown movie must fail"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::NOT_FOUND
+        );
+        assert!(!root_dir.path().join(&id).exists());
+    }
+
+    #[actix_web::test]
+    async fn test_upload_empty_movie() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, mut recv_preview, id) = create_handler_with_movie(&options).await;
+
+        let multipart = create_multipart("movie.mp4", "video/mp4", &[], 64);
+        let err = match handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+        {
+            Ok(_) => panic!("Uploading an empty movie file must fail"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+
+        // neither the empty file nor its file info is kept and no preview is requested
+        let movie = handler.index.read().await.get_movie(&id).await.unwrap();
+        assert!(movie.movie_file_info.is_none());
+        assert!(!root_dir.path().join(&id).join("movie.mp4").exists());
+        assert!(recv_preview.try_recv().is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_upload_too_many_fields() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.max_multipart_fields = 4;
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+
+        // thousands of tiny fields are rejected for both movies and screenshots
+        let movie_fields = vec![("movie.mp4", "video/mp4", b"m".as_slice()); 1000];
+        let err = match handler
+            .handle_upload_movie(
+                id.clone(),
+                None,
+                create_multipart_fields(&movie_fields, 1024),
+            )
+            .await
+        {
+            Ok(_) => panic!("Uploading too many fields must fail"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+
+        let screenshot_fields = vec![("screenshot.png", "image/png", b"s".as_slice()); 1000];
+        let err = match handler
+            .handle_upload_screenshot(
+                id.clone(),
+                create_multipart_fields(&screenshot_fields, 1024),
+            )
+            .await
+        {
+            Ok(_) => panic!("Uploading too many fields must fail"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+
+        // uploads within the limit are accepted
+        assert!(handler
+            .handle_upload_screenshot(
+                id.clone(),
+                create_multipart_fields(&screenshot_fields[..4], 1024)
+            )
+            .await
+            .is_ok());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[actix_web::test]
+    async fn test_upload_movie_disk_full() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.upload_buffer_size = 16;
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+        handler
+            .storage
+            .read()
+            .await
+            .allocate_movie_data(id.clone())
+            .await
+            .unwrap();
+
+        // writing to /dev/full fails in the same way as writing to a full disk
+        let movie_file = root_dir.path().join(&id).join("movie.mp4");
+        std::os::unix::fs::symlink("/dev/full", &movie_file).unwrap();
+
+        let multipart = create_multipart("movie.mp4", "video/mp4", &[42u8; 1024], 64);
+        let err = match handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+        {
+            Ok(_) => panic!("Uploading to a full disk must fail"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::INSUFFICIENT_STORAGE
+        );
+
+        // the partially written file is removed and the movie has no movie file
+        assert!(std::fs::symlink_metadata(&movie_file).is_err());
+        let movie = handler.index.read().await.get_movie(&id).await.unwrap();
+        assert!(movie.movie_file_info.is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_upload_movie_read_error() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+
+        // the connection breaks after the first part of the movie file
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", BOUNDARY)
+                .parse()
+                .unwrap(),
+        );
+        let head = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"movie.mp4\"\r\nContent-Type: video/mp4\r\n\r\n",
+            BOUNDARY
+        );
+        let chunks: Vec<Result<Bytes, actix_web::error::PayloadError>> =
+            vec![Ok(Bytes::from(head)), Ok(Bytes::from_static(&[42u8; 1024]))];
+        let broken = futures::stream::once(async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Err(actix_web::error::PayloadError::Incomplete(None))
+        });
+        let multipart = Multipart::new(&headers, futures::stream::iter(chunks).chain(broken));
+
+        assert!(handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+            .is_err());
+
+        // the partially written file is removed and the movie has no movie file
+        assert!(!root_dir.path().join(&id).join("movie.mp4").exists());
+        let movie = handler.index.read().await.get_movie(&id).await.unwrap();
+        assert!(movie.movie_file_info.is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_upload_movie_immutable() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.immutable_files = true;
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+
+        // the leftover of a failed upload isn't final, s.t. the upload can be retried
+        handler
+            .storage
+            .read()
+            .await
+            .allocate_movie_data(id.clone())
+            .await
+            .unwrap();
+        std::fs::write(root_dir.path().join(&id).join("movie.mkv"), b"partial").unwrap();
+
+        let multipart = create_multipart("movie.mp4", "video/mp4", b"Movie Data!", 64);
+        assert!(handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+            .is_ok());
+
+        // overwriting the movie file is rejected, also with a different extension
+        for filename in ["movie.mp4", "movie.mkv"] {
+            let multipart = create_multipart(filename, "video/mp4", b"Other Data!", 64);
+            let err = match handler
+                .handle_upload_movie(id.clone(), None, multipart)
+                .await
+            {
+                Ok(_) => panic!("Overwriting an immutable movie file must fail"),
+                Err(err) => err,
+            };
+            assert_eq!(
+                err.as_response_error().status_code(),
+                actix_web::http::StatusCode::CONFLICT
+            );
+        }
+
+        // the movie file is untouched
+        let data = std::fs::read(root_dir.path().join(&id).join("movie.mp4")).unwrap();
+        assert_eq!(data, b"Movie Data!");
+    }
+
+    #[actix_web::test]
+    async fn test_upload_unsafe_filenames() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+
+        let long_extension = format!("movie.{}", "a".repeat(50));
+        for filename in [
+            "../movie.mp4",
+            "movie.\u{1F3AC}",
+            ".mp4",
+            long_extension.as_str(),
+        ] {
+            let multipart = create_multipart(filename, "video/mp4", b"movie", 1024);
+            let err = match handler
+                .handle_upload_movie(id.clone(), None, multipart)
+                .await
+            {
+                Ok(_) => panic!("Uploading {:?} must fail", filename),
+                Err(err) => err,
+            };
+            assert_eq!(
+                err.as_response_error().status_code(),
+                actix_web::http::StatusCode::BAD_REQUEST
+            );
+        }
+
+        let movie = handler.index.read().await.get_movie(&id).await.unwrap();
+        assert!(movie.movie_file_info.is_none());
+
+        // unicode outside of the extension only ends up in the metadata
+        let filename = "\u{1F3AC} Das Boot.MP4";
+        let multipart = create_multipart(filename, "video/mp4", b"movie", 1024);
+        assert!(handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+            .is_ok());
+
+        let movie = handler.index.read().await.get_movie(&id).await.unwrap();
+        assert_eq!(
+            movie.movie_file_info,
+            Some(MovieFileInfo {
+                extension: "mp4".to_owned(),
+                mime_type: "video/mp4".to_owned(),
+                original_filename: Some(filename.to_owned()),
+            })
+        );
+
+        let file_path = handler
+            .storage
+            .read()
+            .await
+            .get_file_path(
+                id,
+                MovieDataType::MovieData {
+                    ext: "mp4".to_owned(),
+                },
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(file_path.file_name().unwrap(), "movie.mp4");
+    }
+
+    #[actix_web::test]
+    async fn test_upload_movie_filename_tags() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.filename_tags_policy = FilenameTagsPolicy::Apply;
+        options.filename_tag_tokens = vec!["bluray".to_owned()];
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        let id = handler
+            .index
+            .write()
+            .await
+            .add_movie(Movie {
+                title: "Alien".to_owned(),
+                description: String::new(),
+                tags: vec!["Sci-Fi".to_owned(), "1979".to_owned()],
+            })
+            .await
+            .unwrap();
+
+        // the extracted tags are added to the movie, skipping the tags it already has
+        let multipart = create_multipart(
+            "Alien.1979.BluRay.1080p.mkv",
+            "video/x-matroska",
+            b"movie",
+            1024,
+        );
+        assert!(handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+            .is_ok());
+
+        let movie = handler.index.read().await.get_movie(&id).await.unwrap();
+        assert_eq!(movie.movie.tags, ["1080p", "1979", "bluray", "sci-fi"]);
+
+        // the extracted tags are only suggested
+        options.filename_tags_policy = FilenameTagsPolicy::Suggest;
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        let id = handler
+            .index
+            .write()
+            .await
+            .add_movie(Movie {
+                title: "Heat".to_owned(),
+                description: String::new(),
+                tags: vec!["1995".to_owned()],
+            })
+            .await
+            .unwrap();
+
+        let multipart =
+            create_multipart("Heat (1995) [720p BluRay].mp4", "video/mp4", b"movie", 1024);
+        let response = handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+            .unwrap()
+            .respond_to(&actix_web::test::TestRequest::default().to_http_request())
+            .map_into_boxed_body();
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let uploaded: UploadedMovie = serde_json::from_slice(&body).unwrap();
+        assert_eq!(uploaded.suggested_tags, ["720p", "bluray"]);
+
+        let movie = handler.index.read().await.get_movie(&id).await.unwrap();
+        assert_eq!(movie.movie.tags, ["1995"]);
+    }
+
+    #[actix_web::test]
+    async fn test_import_bundle_unsafe_extension() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        // the extension would end up in the name of the stored file passed to ffmpeg
+        let movie = MovieDetailed {
+            movie: Movie {
+                title: "Das Boot".to_owned(),
+                description: String::new(),
+                tags: Vec::new(),
+            },
+            movie_file_info: Some(MovieFileInfo {
+                extension: "mp4 -f null; rm -rf ~".to_owned(),
+                mime_type: "video/mp4".to_owned(),
+                original_filename: None,
+            }),
+            screenshot_file_info: None,
+            date: Utc::now(),
+            preview_state: None,
+            featured: false,
+            processing: false,
+            active_jobs: Vec::new(),
+            video_metadata: None,
+            animated_preview_info: None,
+            subtitle_languages: Vec::new(),
+        };
+        let bundle = bundle::write_bundle(
+            Vec::new(),
+            &movie,
+            Some(std::io::Cursor::new(b"movie".to_vec())),
+            None,
+        )
+        .await
+        .unwrap();
+        let bundle_path = root_dir.path().join("bundle.zip");
+        std::fs::write(&bundle_path, bundle).unwrap();
+
+        let ret = handler.import_bundle(&bundle_path).await;
+        assert!(matches!(ret, Err(Error::InvalidArgument(_))));
+
+        let query = MovieSearchQuery::default();
+        let movies = handler.index.read().await.search_movies(query).await;
+        assert!(movies.unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_upload_bundle_too_large() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.max_bundle_size = 1024;
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        let multipart = create_multipart("Das Boot.zip", "application/zip", &[0u8; 2048], 256);
+        let err = match handler.handle_upload_bundle(multipart).await {
+            Ok(_) => panic!("too large bundle has been accepted"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        // a small compressed bundle must not expand beyond the maximal size either
+        let mut encoder = async_compression::tokio::write::GzipEncoder::new(Vec::new());
+        encoder.write_all(&[0u8; 64 * 1024]).await.unwrap();
+        encoder.shutdown().await.unwrap();
+        let bundle = encoder.into_inner();
+        assert!(bundle.len() < 1024);
+
+        let multipart = create_multipart("Das Boot.zip.gz", "application/gzip", &bundle, 256);
+        let err = match handler.handle_upload_bundle(multipart).await {
+            Ok(_) => panic!("too large decompressed bundle has been accepted"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        let query = MovieSearchQuery::default();
+        let movies = handler.index.read().await.search_movies(query).await;
+        assert!(movies.unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_compressed_bundle_round_trip() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().join("source");
+        let (source, _recv_preview) = create_handler(&options).await;
+        options.root_dir = root_dir.path().join("target");
+        let (target, _recv_preview) = create_handler(&options).await;
+
+        let id = source
+            .index
+            .write()
+            .await
+            .add_movie(Movie {
+                title: "Das Boot".to_owned(),
+                description: "A German U-boat stalks the frigid waters of the North Atlantic."
+                    .to_owned(),
+                tags: vec!["drama".to_owned(), "war".to_owned()],
+            })
+            .await
+            .unwrap();
+
+        // store a movie file and a screenshot, s.t. no preview is requested on import
+        let data_types = || {
+            [
+                MovieDataType::MovieData {
+                    ext: "mp4".to_owned(),
+                },
+                MovieDataType::ScreenshotData {
+                    ext: "png".to_owned(),
+                    index: 0,
+                },
+            ]
+        };
+        let files = [vec![42u8; 64 * 1024], b"Screenshot Data!".to_vec()];
+        for (data_type, data) in data_types().into_iter().zip(files.iter()) {
+            let mut writer = source
+                .storage
+                .read()
+                .await
+                .write_movie_data(id.clone(), data_type)
+                .await
+                .unwrap();
+            writer.write_all(data).await.unwrap();
+            writer.flush().await.unwrap();
+        }
+
+        let mut index = source.index.write().await;
+        index
+            .update_movie_file_info(
+                &id,
+                MovieFileInfo {
+                    extension: "mp4".to_owned(),
+                    mime_type: "video/mp4".to_owned(),
+                    original_filename: None,
+                },
+            )
+            .await
+            .unwrap();
+        index
+            .update_screenshot_info(
+                &id,
+                ScreenshotInfo {
+                    extension: "png".to_owned(),
+                    mime_type: "image/png".to_owned(),
+                    count: 1,
+                },
+            )
+            .await
+            .unwrap();
+        drop(index);
+
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let response = source
+            .handle_download_bundle(id.clone(), BundleCompression::GzipFile)
+            .await
+            .unwrap()
+            .respond_to(&request)
+            .map_into_boxed_body();
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/gzip"
+        );
+        let bundle = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+
+        // the compressed bundle is much smaller than the movie file it contains
+        assert_eq!(bundle[..2], [0x1f, 0x8b]);
+        assert!(bundle.len() < files[0].len() / 4);
+
+        let response = target
+            .handle_upload_bundle(create_multipart(
+                "Das Boot.zip.gz",
+                "application/gzip",
+                &bundle,
+                4096,
+            ))
+            .await
+            .unwrap()
+            .respond_to(&request)
+            .map_into_boxed_body();
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        let imported_id = String::from_utf8(
+            actix_web::body::to_bytes(response.into_body())
+                .await
+                .unwrap()
+                .to_vec(),
+        )
+        .unwrap();
+
+        let movie = source.index.read().await.get_movie(&id).await.unwrap();
+        let imported = target
+            .index
+            .read()
+            .await
+            .get_movie(&imported_id)
+            .await
+            .unwrap();
+        assert_eq!(imported.movie.title, movie.movie.title);
+        assert_eq!(imported.movie.description, movie.movie.description);
+        assert_eq!(imported.movie.tags, movie.movie.tags);
+        assert_eq!(imported.movie_file_info, movie.movie_file_info);
+        assert_eq!(imported.screenshot_file_info, movie.screenshot_file_info);
+
+        for (data_type, data) in data_types().into_iter().zip(files.iter()) {
+            let mut imported_data = Vec::new();
+            target
+                .storage
+                .read()
+                .await
+                .read_movie_data(imported_id.clone(), data_type)
+                .await
+                .unwrap()
+                .read_to_end(&mut imported_data)
+                .await
+                .unwrap();
+            assert_eq!(&imported_data, data);
+        }
+
+        // the import created exactly one movie
+        let query = MovieSearchQuery::default();
+        let movies = target.index.read().await.search_movies(query).await;
+        assert_eq!(movies.unwrap(), [imported_id]);
+    }
+
+    #[actix_web::test]
+    async fn test_cancel_upload() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+
+        let response = handler
+            .handle_start_upload(id.clone())
+            .await
+            .unwrap()
+            .respond_to(&actix_web::test::TestRequest::default().to_http_request())
+            .map_into_boxed_body();
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let token: UploadToken = serde_json::from_slice(&body).unwrap();
+
+        // stream the upload s.t. it stays in progress until it gets cancelled
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", BOUNDARY)
+                .parse()
+                .unwrap(),
+        );
+        let (sender, receiver) =
+            futures::channel::mpsc::unbounded::<Result<Bytes, actix_web::error::PayloadError>>();
+        let multipart = Multipart::new(&headers, receiver);
+
+        let movie_file = root_dir.path().join(&id).join("movie.mp4");
+        let cancel = async {
+            let head = format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"movie.mp4\"\r\nContent-Type: video/mp4\r\n\r\n",
+                BOUNDARY
+            );
+            sender.unbounded_send(Ok(Bytes::from(head))).unwrap();
+            sender
+                .unbounded_send(Ok(Bytes::from(vec![42u8; 64 * 1024])))
+                .unwrap();
+
+            while !movie_file.exists() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+
+            assert!(handler.handle_cancel_upload(&token.token).await.is_ok());
+        };
+
+        let (result, _) = tokio::join!(
+            handler.handle_upload_movie(id.clone(), Some(token.token.clone()), multipart),
+            cancel
+        );
+        let err = match result {
+            Ok(_) => panic!("A cancelled upload must fail"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::CONFLICT
+        );
+
+        assert!(!movie_file.exists());
+        let movie = handler.index.read().await.get_movie(&id).await.unwrap();
+        assert!(movie.movie_file_info.is_none());
+        assert!(handler.uploads.lock().unwrap().is_empty());
+
+        // the token is no longer valid
+        assert!(handler.handle_cancel_upload(&token.token).await.is_err());
+        drop(sender);
+    }
+
+    #[actix_web::test]
+    async fn test_concurrent_writes() {
+        for granularity in [
+            crate::LockGranularity::PerMovie,
+            crate::LockGranularity::Global,
+        ] {
+            let root_dir = TempDir::new("movies-db").unwrap();
+            let mut options = Options::default();
+            options.root_dir = root_dir.path().to_path_buf();
+            options.lock_granularity = granularity;
+
+            let (handler, _recv_preview) = create_handler(&options).await;
+
+            let mut ids = Vec::new();
+            for title in ["Das Boot", "Alien"] {
+                let id = handler
+                    .index
+                    .write()
+                    .await
+                    .add_movie(Movie {
+                        title: title.to_owned(),
+                        description: String::new(),
+                        tags: Vec::new(),
+                    })
+                    .await
+                    .unwrap();
+                ids.push(id);
+            }
+
+            // stream the upload of the first movie s.t. it stays in progress
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", BOUNDARY)
+                    .parse()
+                    .unwrap(),
+            );
+            let (sender, receiver) = futures::channel::mpsc::unbounded::<
+                Result<Bytes, actix_web::error::PayloadError>,
+            >();
+            let multipart = Multipart::new(&headers, receiver);
+
+            let movie_file = root_dir.path().join(&ids[0]).join("movie.mp4");
+            let writes = async {
+                let head = format!(
+                    "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"movie.mp4\"\r\nContent-Type: video/mp4\r\n\r\n",
+                    BOUNDARY
+                );
+                sender.unbounded_send(Ok(Bytes::from(head))).unwrap();
+                sender
+                    .unbounded_send(Ok(Bytes::from(vec![42u8; 1024])))
+                    .unwrap();
+
+                while !movie_file.exists() {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+
+                // an upload of another movie only waits with a global lock
+                let multipart = create_multipart("movie.mp4", "video/mp4", b"Movie Data!", 64);
+                let upload = handler.handle_upload_movie(ids[1].clone(), None, multipart);
+                let finished = tokio::time::timeout(Duration::from_millis(200), upload)
+                    .await
+                    .is_ok();
+                assert_eq!(finished, granularity == crate::LockGranularity::PerMovie);
+
+                // deleting the same movie waits until the upload is done
+                let delete = handler.handle_delete_movie(ids[0].clone());
+                assert!(tokio::time::timeout(Duration::from_millis(200), delete)
+                    .await
+                    .is_err());
+
+                let tail = format!("\r\n--{}--\r\n", BOUNDARY);
+                sender.unbounded_send(Ok(Bytes::from(tail))).unwrap();
+                sender.close_channel();
+            };
+
+            let (result, _) = tokio::join!(
+                handler.handle_upload_movie(ids[0].clone(), None, multipart),
+                writes
+            );
+            assert!(result.is_ok());
+
+            let movie = handler.index.read().await.get_movie(&ids[0]).await.unwrap();
+            assert!(movie.movie_file_info.is_some());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_upload_token_expiry() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+
+        for _ in 0..MAX_UPLOAD_TOKENS {
+            assert!(handler.handle_start_upload(id.clone()).await.is_ok());
+        }
+
+        // the number of pending tokens is bounded
+        let err = match handler.handle_start_upload(id.clone()).await {
+            Ok(_) => panic!("Starting too many uploads must fail"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::TOO_MANY_REQUESTS
+        );
+
+        // expired tokens are removed, unless their upload has been started
+        let tokens: Vec<String> = {
+            let mut uploads = handler.uploads.lock().unwrap();
+            for (index, upload) in uploads.values_mut().enumerate() {
+                upload.expires = Instant::now();
+                upload.started = index == 0;
+            }
+            uploads
+                .iter()
+                .filter(|(_, upload)| !upload.started)
+                .map(|(token, _)| token.clone())
+                .take(1)
+                .collect()
+        };
+        assert!(handler.handle_start_upload(id.clone()).await.is_ok());
+        assert_eq!(handler.uploads.lock().unwrap().len(), 2);
+
+        // an expired token can't be used anymore
+        let multipart = create_multipart("movie.mp4", "video/mp4", b"Movie Data!", 64);
+        let err = match handler
+            .handle_upload_movie(id.clone(), Some(tokens[0].clone()), multipart)
+            .await
+        {
+            Ok(_) => panic!("Uploading with an expired token must fail"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::NOT_FOUND
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_download_movie_range() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+        let multipart = create_multipart("movie.mp4", "video/mp4", b"movie data", 1024);
+        assert!(handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+            .is_ok());
+
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let download = |ranges: Vec<ByteRangeSpec>| {
+            let id = id.clone();
+            let handler = &handler;
+            let request = request.clone();
+            async move {
+                let response = handler
+                    .handle_download_movie(id, &ranges, None)
+                    .await
+                    .unwrap()
+                    .respond_to(&request)
+                    .map_into_boxed_body();
+                let status = response.status();
+                let headers = response.headers().clone();
+                let data = actix_web::body::to_bytes(response.into_body())
+                    .await
+                    .unwrap();
+
+                (status, headers, data)
+            }
+        };
+
+        // only the requested part is streamed
+        let (status, headers, data) = download(vec![ByteRangeSpec::FromTo(0, 4)]).await;
+        assert_eq!(status, actix_web::http::StatusCode::PARTIAL_CONTENT);
+        assert_eq!(headers.get(header::CONTENT_RANGE).unwrap(), "bytes 0-4/10");
+        assert_eq!(headers.get(header::ACCEPT_RANGES).unwrap(), "bytes");
+        assert_eq!(data, "movie");
+
+        let (status, headers, data) = download(vec![ByteRangeSpec::Last(4)]).await;
+        assert_eq!(status, actix_web::http::StatusCode::PARTIAL_CONTENT);
+        assert_eq!(headers.get(header::CONTENT_RANGE).unwrap(), "bytes 6-9/10");
+        assert_eq!(data, "data");
+
+        // without a range the whole movie is streamed
+        let (status, headers, data) = download(Vec::new()).await;
+        assert_eq!(status, actix_web::http::StatusCode::OK);
+        assert!(!headers.contains_key(header::CONTENT_RANGE));
+        assert_eq!(headers.get(header::ACCEPT_RANGES).unwrap(), "bytes");
+        assert_eq!(data, "movie data");
+
+        // a range beyond the end of the movie is rejected
+        let (status, headers, data) = download(vec![ByteRangeSpec::From(10)]).await;
+        assert_eq!(status, actix_web::http::StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(headers.get(header::CONTENT_RANGE).unwrap(), "bytes */10");
+        assert!(data.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_download_movie_offloaded() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.download_offload = DownloadOffload::XAccelRedirect {
+            location: "/protected/".to_owned(),
+        };
+
+        let (mut handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+        handler
+            .index
+            .write()
+            .await
+            .update_movie_file_info(
+                &id,
+                MovieFileInfo {
+                    extension: "mkv".to_owned(),
+                    mime_type: "video/x-matroska".to_owned(),
+                    original_filename: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let response = handler
+            .handle_download_movie(id.clone(), &[ByteRangeSpec::FromTo(0, 4)], None)
+            .await
+            .unwrap()
+            .respond_to(&request)
+            .map_into_boxed_body();
+
+        // the proxy serves the file including the range, s.t. the body is empty
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        assert_eq!(
+            response.headers().get("X-Accel-Redirect").unwrap(),
+            format!("/protected/{}/movie.mkv", id).as_str()
+        );
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "video/x-matroska"
+        );
+        let data = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert!(data.is_empty());
+
+        // X-Sendfile refers to the absolute path of the movie file
+        handler.options.download_offload = DownloadOffload::XSendfile;
+        let response = handler
+            .handle_download_movie(id.clone(), &[], None)
+            .await
+            .unwrap()
+            .respond_to(&request)
+            .map_into_boxed_body();
+        let file_path = root_dir.path().join(&id).join("movie.mkv");
+        assert_eq!(
+            response.headers().get("X-Sendfile").unwrap(),
+            file_path.display().to_string().as_str()
+        );
+        assert!(!response.headers().contains_key("X-Accel-Redirect"));
+    }
+
+    #[actix_web::test]
+    async fn test_download_movie_if_range() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+        let multipart = create_multipart("movie.mp4", "video/mp4", b"movie data", 1024);
+        assert!(handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+            .is_ok());
+
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let download = |if_range: Option<IfRange>| {
+            let id = id.clone();
+            let handler = &handler;
+            let request = request.clone();
+            async move {
+                let response = handler
+                    .handle_download_movie(id, &[ByteRangeSpec::From(6)], if_range)
+                    .await
+                    .unwrap()
+                    .respond_to(&request)
+                    .map_into_boxed_body();
+                let status = response.status();
+                let headers = response.headers().clone();
+                let data = actix_web::body::to_bytes(response.into_body())
+                    .await
+                    .unwrap();
+
+                (status, headers, data)
+            }
+        };
+
+        // without a validator the range is always served
+        let (status, headers, data) = download(None).await;
+        assert_eq!(status, actix_web::http::StatusCode::PARTIAL_CONTENT);
+        assert_eq!(data, "data");
+        let etag: EntityTag = headers
+            .get(header::ETAG)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let last_modified: HttpDate = headers
+            .get(header::LAST_MODIFIED)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        // the validators still match
+        let (status, _, data) = download(Some(IfRange::EntityTag(etag.clone()))).await;
+        assert_eq!(status, actix_web::http::StatusCode::PARTIAL_CONTENT);
+        assert_eq!(data, "data");
+        let (status, _, data) = download(Some(IfRange::Date(last_modified))).await;
+        assert_eq!(status, actix_web::http::StatusCode::PARTIAL_CONTENT);
+        assert_eq!(data, "data");
+
+        // a weak ETag never matches
+        let weak_etag = EntityTag::new_weak(etag.tag().to_owned());
+        let (status, _, data) = download(Some(IfRange::EntityTag(weak_etag))).await;
+        assert_eq!(status, actix_web::http::StatusCode::OK);
+        assert_eq!(data, "movie data");
+
+        // the movie file is replaced, s.t. the stale validator triggers a full response
+        let multipart = create_multipart("movie.mp4", "video/mp4", b"new movie data", 1024);
+        assert!(handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+            .is_ok());
+        let (status, headers, data) = download(Some(IfRange::EntityTag(etag.clone()))).await;
+        assert_eq!(status, actix_web::http::StatusCode::OK);
+        assert!(!headers.contains_key(header::CONTENT_RANGE));
+        assert_eq!(data, "new movie data");
+        assert_ne!(
+            headers.get(header::ETAG).unwrap().to_str().unwrap(),
+            etag.to_string()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_update_movie_file_info() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+
+        // upload a wmv file with a wrong mime type
+        let data = b"movie data".to_vec();
+        let multipart = create_multipart("movie.mp4", "video/mp4", &data, data.len());
+        assert!(handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+            .is_ok());
+
+        // only video mime types and plain extensions are accepted
+        for (mime_type, extension) in [("image/png", None), ("video/x-ms-wmv", Some("../wmv"))] {
+            let response = handler
+                .handle_update_movie_file_info(
+                    id.clone(),
+                    mime_type.to_owned(),
+                    extension.map(|e| e.to_owned()),
+                )
+                .await;
+            assert_eq!(
+                response.err().unwrap().as_response_error().status_code(),
+                actix_web::http::StatusCode::BAD_REQUEST
+            );
+        }
+
+        // correct the mime type and the extension
+        assert!(handler
+            .handle_update_movie_file_info(
+                id.clone(),
+                "video/x-ms-wmv".to_owned(),
+                Some("WMV".to_owned())
+            )
+            .await
+            .is_ok());
+
+        let movie = handler.index.read().await.get_movie(&id).await.unwrap();
+        assert_eq!(
+            movie.movie_file_info,
+            Some(MovieFileInfo {
+                extension: "wmv".to_owned(),
+                mime_type: "video/x-ms-wmv".to_owned(),
+                original_filename: Some("movie.mp4".to_owned()),
+            })
+        );
+
+        // the download serves the renamed file with the corrected content type
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let response = handler
+            .handle_download_movie(id.clone(), &[], None)
+            .await
+            .unwrap()
+            .respond_to(&request)
+            .map_into_boxed_body();
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "video/x-ms-wmv"
+        );
+        let downloaded = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(downloaded.as_ref(), data.as_slice());
+
+        // the old file is gone
+        assert!(handler
+            .storage
+            .read()
+            .await
+            .read_movie_data(
+                id,
+                MovieDataType::MovieData {
+                    ext: "mp4".to_owned()
+                }
+            )
+            .await
+            .is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_update_movie_file_info_immutable() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.immutable_files = true;
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+
+        let data = b"movie data".to_vec();
+        let multipart = create_multipart("movie.mp4", "video/mp4", &data, data.len());
+        assert!(handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+            .is_ok());
+
+        // the immutable movie file must not be renamed
+        let response = handler
+            .handle_update_movie_file_info(
+                id.clone(),
+                "video/x-ms-wmv".to_owned(),
+                Some("wmv".to_owned()),
+            )
+            .await;
+        assert_eq!(
+            response.err().unwrap().as_response_error().status_code(),
+            actix_web::http::StatusCode::CONFLICT
+        );
+
+        // correcting the mime type is still allowed
+        assert!(handler
+            .handle_update_movie_file_info(
+                id.clone(),
+                "video/mpeg".to_owned(),
+                Some("MP4".to_owned())
+            )
+            .await
+            .is_ok());
+
+        let movie = handler.index.read().await.get_movie(&id).await.unwrap();
+        assert_eq!(
+            movie.movie_file_info,
+            Some(MovieFileInfo {
+                extension: "mp4".to_owned(),
+                mime_type: "video/mpeg".to_owned(),
+                original_filename: Some("movie.mp4".to_owned()),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_movies_faceted() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        for (title, tags, ext) in [
+            ("Alien", vec!["sci-fi", "horror"], Some("mkv")),
+            ("Interstellar", vec!["sci-fi", "space"], Some("mp4")),
+            ("Galaxy Quest", vec!["sci-fi", "comedy"], Some("mp4")),
+            ("Arrival", vec!["sci-fi"], None),
+            ("Das Boot", vec!["drama", "war"], Some("avi")),
+        ] {
+            let id = handler
+                .index
+                .write()
+                .await
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: String::new(),
+                    tags: tags.iter().map(|t| t.to_string()).collect(),
+                })
+                .await
+                .unwrap();
+
+            if let Some(ext) = ext {
+                handler
+                    .index
+                    .write()
+                    .await
+                    .update_movie_file_info(
+                        &id,
+                        MovieFileInfo {
+                            extension: ext.to_owned(),
+                            mime_type: format!("video/{}", ext),
+                            original_filename: None,
+                        },
+                    )
+                    .await
+                    .unwrap();
+            }
+        }
+
+        // the facets describe all sci-fi movies, while the items are paginated
+        let query = MovieSearchQuery {
+            sorting_field: SortingField::Title,
+            sorting_order: SortingOrder::Ascending,
+            tags: vec!["sci-fi".to_owned()],
+            start_index: Some(1),
+            num_results: Some(2),
+            ..Default::default()
+        };
+
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let response = handler
+            .handle_search_movies_faceted(query)
+            .await
+            .unwrap()
+            .respond_to(&request)
+            .map_into_boxed_body();
+        let data = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let result: FacetedSearchResult = serde_json::from_slice(&data).unwrap();
+
+        let titles: Vec<&str> = result.items.iter().map(|m| m.title.as_str()).collect();
+        assert_eq!(titles, ["Arrival", "Galaxy Quest"]);
+
+        let tag = |tag: &str, count: usize| (tag.to_owned(), count);
+        assert_eq!(
+            result.facets.tags,
+            [
+                tag("sci-fi", 4),
+                tag("comedy", 1),
+                tag("horror", 1),
+                tag("space", 1)
+            ]
+        );
+        assert_eq!(result.facets.formats, [tag("mp4", 2), tag("mkv", 1)]);
+
+        // the years themselves are checked with fixed dates by the tests of the indices
+        let years: usize = result.facets.years.iter().map(|(_, count)| count).sum();
+        assert_eq!(years, 4);
+    }
+
+    /// Checks that the given XML document is well-formed, i.e., that all elements are properly
+    /// nested and that the text contains no unescaped markup, and returns the names of all
+    /// elements in document order.
+    fn parse_xml_elements(xml: &str) -> Vec<String> {
+        let xml = xml.trim_start();
+        let mut rest = match xml.strip_prefix("<?xml") {
+            Some(rest) => &rest[rest.find("?>").unwrap() + 2..],
+            None => xml,
+        };
+
+        let check_text = |text: &str| {
+            assert!(!text.contains('>'), "Unescaped markup in {:?}", text);
+            for (pos, _) in text.match_indices('&') {
+                let entity = &text[pos..pos + text[pos..].find(';').unwrap() + 1];
+                assert!(
+                    ["&amp;", "&lt;", "&gt;", "&quot;", "&apos;"].contains(&entity),
+                    "Unknown entity {}",
+                    entity
+                );
+            }
+        };
+
+        let mut open_elements: Vec<String> = Vec::new();
+        let mut elements = Vec::new();
+        while let Some(start) = rest.find('<') {
+            check_text(&rest[..start]);
+            let end = start + rest[start..].find('>').unwrap();
+            let tag = &rest[start + 1..end];
+            rest = &rest[end + 1..];
+
+            if let Some(name) = tag.strip_prefix('/') {
+                assert_eq!(open_elements.pop().as_deref(), Some(name));
+                continue;
+            }
+
+            let self_closing = tag.ends_with('/');
+            let tag = tag.trim_end_matches('/');
+            let (name, attributes) = tag.split_once(' ').unwrap_or((tag, ""));
+            for attribute in attributes.split_whitespace() {
+                let (_, value) = attribute.split_once('=').unwrap();
+                assert!(value.len() >= 2 && value.starts_with('"') && value.ends_with('"'));
+                check_text(&value[1..value.len() - 1]);
+            }
+
+            elements.push(name.to_owned());
+            if !self_closing {
+                open_elements.push(name.to_owned());
+            }
+        }
+
+        check_text(rest);
+        assert!(open_elements.is_empty(), "Unclosed {:?}", open_elements);
+        assert_eq!(elements.first().map(|s| s.as_str()), Some("rss"));
+
+        elements
+    }
+
+    #[tokio::test]
+    async fn test_get_movie_feed() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        let mut ids = Vec::new();
+        for (title, description, tags) in [
+            (
+                "Alien",
+                "In space no one can hear you scream.",
+                vec!["sci-fi"],
+            ),
+            ("Das Boot", "A German U-boat", vec!["war"]),
+            (
+                "Tom & Jerry <Pilot>",
+                "\"Cat\" & 'mouse' > dog",
+                vec!["sci-fi", "cartoon"],
+            ),
+        ] {
+            let id = handler
+                .index
+                .write()
+                .await
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: description.to_owned(),
+                    tags: tags.iter().map(|t| t.to_string()).collect(),
+                })
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        handler
+            .index
+            .write()
+            .await
+            .update_screenshot_info(
+                &ids[0],
+                ScreenshotInfo {
+                    extension: "png".to_owned(),
+                    mime_type: "image/png".to_owned(),
+                    count: 1,
+                },
+            )
+            .await
+            .unwrap();
+
+        let query = MovieSearchQuery {
+            tags: vec!["sci-fi".to_owned()],
+            ..Default::default()
+        };
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let response = handler
+            .handle_get_movie_feed(query, "http://localhost/api/v1/movie")
+            .await
+            .unwrap()
+            .respond_to(&request)
+            .map_into_boxed_body();
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            FEED_MIME_TYPE
+        );
+        let data = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let xml = String::from_utf8(data.to_vec()).unwrap();
+
+        // one item per matching movie, where only the movie with a screenshot has an enclosure
+        let elements = parse_xml_elements(&xml);
+        let count = |name: &str| elements.iter().filter(|e| e.as_str() == name).count();
+        assert_eq!(count("channel"), 1);
+        assert_eq!(count("item"), 2);
+        assert_eq!(count("pubDate"), 2);
+        assert_eq!(count("enclosure"), 1);
+
+        assert!(xml.contains("<title>Tom &amp; Jerry &lt;Pilot&gt;</title>"));
+        assert!(xml.contains(&format!(
+            "<link>http://localhost/api/v1/movie?id={}</link>",
+            ids[0]
+        )));
+        assert!(xml.contains(&format!(
+            "<enclosure url=\"http://localhost/api/v1/movie/screenshot?id={}\"",
+            ids[0]
+        )));
+        assert!(!xml.contains("Das Boot"));
+    }
+
+    /// Creates the given number of preview requests for distinct movies.
+    fn create_preview_requests(num: usize) -> Vec<ScreenshotRequest> {
+        (0..num)
+            .map(|i| ScreenshotRequest {
+                movie_id: format!("movie-{}", i),
+                ext: "mp4".to_owned(),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_rename_tags() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        for (title, tags) in [
+            ("Alien", vec!["sci-fi", "horror"]),
+            ("Interstellar", vec!["scifi", "science fiction"]),
+        ] {
+            handler
+                .index
+                .write()
+                .await
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: String::new(),
+                    tags: tags.iter().map(|t| t.to_string()).collect(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let renames: BTreeMap<String, String> =
+            [("Sci-Fi", "science fiction"), ("scifi", "sci-fi")]
+                .iter()
+                .map(|(old_tag, new_tag)| (old_tag.to_string(), new_tag.to_string()))
+                .collect();
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let response = handler
+            .handle_rename_tags(&renames)
+            .await
+            .unwrap()
+            .respond_to(&request)
+            .map_into_boxed_body();
+        let data = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let counts: BTreeMap<String, usize> = serde_json::from_slice(&data).unwrap();
+        assert_eq!(
+            counts,
+            BTreeMap::from([("sci-fi".to_owned(), 1), ("scifi".to_owned(), 1)])
+        );
+
+        let tags = handler
+            .index
+            .read()
+            .await
+            .get_tag_list_with_count()
+            .await
+            .unwrap();
+        assert_eq!(
+            tags,
+            [("science fiction".to_owned(), 2), ("horror".to_owned(), 1)]
+        );
+
+        // cycles are rejected as bad requests
+        let renames: BTreeMap<String, String> = [("horror", "thriller"), ("thriller", "horror")]
+            .iter()
+            .map(|(old_tag, new_tag)| (old_tag.to_string(), new_tag.to_string()))
+            .collect();
+        let err = match handler.handle_rename_tags(&renames).await {
+            Ok(_) => panic!("Renaming tags in a cycle must fail"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_movies_fuzzy_tags() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        for (title, tags) in [
+            ("Alien", vec!["science fiction", "horror"]),
+            ("Interstellar", vec!["science fiction", "space"]),
+            ("Das Boot", vec!["germany", "war"]),
+            ("Heat", vec!["usa", "crime"]),
+        ] {
+            handler
+                .index
+                .write()
+                .await
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: String::new(),
+                    tags: tags.iter().map(|t| t.to_string()).collect(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let search = |tags: &[&str], tag_fuzzy_match: bool| {
+            let query = MovieSearchQuery {
+                sorting_field: SortingField::Title,
+                sorting_order: SortingOrder::Ascending,
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                tag_fuzzy_match,
+                ..Default::default()
+            };
+            let handler = &handler;
+
+            async move {
+                let request = actix_web::test::TestRequest::default().to_http_request();
+                let response = handler
+                    .handle_search_movies(query)
+                    .await
+                    .unwrap()
+                    .respond_to(&request)
+                    .map_into_boxed_body();
+                let data = actix_web::body::to_bytes(response.into_body())
+                    .await
+                    .unwrap();
+                let result: SearchResult = serde_json::from_slice(&data).unwrap();
+
+                result
+                    .results
+                    .into_iter()
+                    .map(|movie| movie.title)
+                    .collect::<Vec<String>>()
+            }
+        };
+
+        // near-miss tags only match if fuzzy matching is requested
+        assert!(search(&["sciencefiction"], false).await.is_empty());
+        assert_eq!(
+            search(&["sciencefiction"], true).await,
+            ["Alien", "Interstellar"]
+        );
+        assert_eq!(search(&["Germeny"], true).await, ["Das Boot"]);
+
+        // known tags are kept and tags too far from any known tag match nothing
+        assert_eq!(search(&["war"], true).await, ["Das Boot"]);
+        assert!(search(&["wat"], true).await.is_empty());
+        assert!(search(&["western"], true).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_movies_added_within() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        // the first movie is older than the window of the search below
+        for title in ["Das Boot", "Alien"] {
+            if title == "Alien" {
+                tokio::time::sleep(std::time::Duration::from_millis(2100)).await;
+            }
+
+            handler
+                .index
+                .write()
+                .await
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: String::new(),
+                    tags: Vec::new(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let search = |added_within: &str| {
+            let query = MovieSearchQuery {
+                added_within: Some(added_within.to_owned()),
+                ..Default::default()
+            };
+            let handler = &handler;
+
+            async move {
+                let request = actix_web::test::TestRequest::default().to_http_request();
+                let response = handler
+                    .handle_search_movies(query)
+                    .await?
+                    .respond_to(&request)
+                    .map_into_boxed_body();
+                let data = actix_web::body::to_bytes(response.into_body())
+                    .await
+                    .unwrap();
+                let result: SearchResult = serde_json::from_slice(&data).unwrap();
+
+                Ok::<Vec<String>, actix_web::Error>(
+                    result
+                        .results
+                        .into_iter()
+                        .map(|movie| movie.title)
+                        .collect(),
+                )
+            }
+        };
+
+        assert_eq!(search("2s").await.unwrap(), ["Alien"]);
+        assert_eq!(search("1h").await.unwrap().len(), 2);
+        assert_eq!(search("7d").await.unwrap().len(), 2);
+
+        // malformed windows are rejected
+        for added_within in ["", "7", "seven days", "-1d", "7y"] {
+            let err = search(added_within).await.unwrap_err();
+            assert_eq!(
+                err.as_response_error().status_code(),
+                actix_web::http::StatusCode::BAD_REQUEST
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preview_backpressure_drop() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.preview_queue_capacity = 2;
+        options.preview_backpressure = PreviewBackpressurePolicy::Drop;
+
+        let (handler, mut recv_preview) = create_handler(&options).await;
+
+        // flooding the queue doesn't block, the surplus requests are dropped
+        for request in create_preview_requests(10) {
+            tokio::time::timeout(Duration::from_secs(1), handler.request_preview(request))
+                .await
+                .unwrap();
+        }
+
+        let mut movie_ids = Vec::new();
+        while let Ok(request) = recv_preview.try_recv() {
+            movie_ids.push(request.movie_id);
+        }
+        assert_eq!(movie_ids, ["movie-0", "movie-1"]);
+    }
+
+    #[actix_web::test]
+    async fn test_preview_backpressure_defer() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.preview_queue_capacity = 1;
+        options.preview_backpressure = PreviewBackpressurePolicy::Defer;
+        options.preview_retry_after = 30;
+
+        let (handler, mut recv_preview) = create_handler(&options).await;
+
+        let mut ids = Vec::new();
+        for title in ["Das Boot", "Alien"] {
+            let id = handler
+                .index
+                .write()
+                .await
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: String::new(),
+                    tags: Vec::new(),
+                })
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+
+        // the first upload saturates the queue, s.t. the preview of the second one is deferred
+        let mut statuses = Vec::new();
+        for id in ids.iter() {
+            let multipart = create_multipart("movie.mp4", "video/mp4", b"movie", 1024);
+            let response = handler
+                .handle_upload_movie(id.clone(), None, multipart)
+                .await
+                .unwrap()
+                .respond_to(&actix_web::test::TestRequest::default().to_http_request())
+                .map_into_boxed_body();
+            statuses.push(response.status());
+
+            if response.status() == actix_web::http::StatusCode::SERVICE_UNAVAILABLE {
+                assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "30");
+
+                let data = actix_web::body::to_bytes(response.into_body())
+                    .await
+                    .unwrap();
+                let deferred: DeferredPreview = serde_json::from_slice(&data).unwrap();
+                assert_eq!(deferred.id, *id);
+                assert_eq!(deferred.queue_depth, 1);
+            }
+        }
+        assert_eq!(
+            statuses,
+            [
+                actix_web::http::StatusCode::OK,
+                actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+            ]
+        );
+
+        // the movie file has been stored nevertheless, but no preview is pending
+        let movie = handler.index.read().await.get_movie(&ids[1]).await.unwrap();
+        assert!(movie.movie_file_info.is_some());
+        assert!(!handler.pending_previews.contains(&ids[1]));
+
+        // once the queue has space again, the preview can be requested again
+        assert_eq!(recv_preview.recv().await.unwrap().movie_id, ids[0]);
+        let response = handler
+            .handle_request_preview(ids[1].clone())
+            .await
+            .unwrap()
+            .respond_to(&actix_web::test::TestRequest::default().to_http_request());
+        assert_eq!(response.status(), actix_web::http::StatusCode::ACCEPTED);
+        assert_eq!(recv_preview.recv().await.unwrap().movie_id, ids[1]);
+    }
+
+    #[tokio::test]
+    async fn test_preview_backpressure_wait() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.preview_queue_capacity = 2;
+        options.preview_backpressure = PreviewBackpressurePolicy::Wait;
+
+        let (handler, mut recv_preview) = create_handler(&options).await;
+        let mut requests = create_preview_requests(3).into_iter();
+
+        handler.request_preview(requests.next().unwrap()).await;
+        handler.request_preview(requests.next().unwrap()).await;
+
+        // the queue is full, s.t. the next request has to wait until there is space again
+        let request = requests.next().unwrap();
+        let mut pending = Box::pin(handler.request_preview(request));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), &mut pending)
+                .await
+                .is_err()
+        );
+
+        assert_eq!(recv_preview.recv().await.unwrap().movie_id, "movie-0");
+        tokio::time::timeout(Duration::from_secs(1), pending)
+            .await
+            .unwrap();
+
+        let mut movie_ids = Vec::new();
+        while let Ok(request) = recv_preview.try_recv() {
+            movie_ids.push(request.movie_id);
+        }
+        assert_eq!(movie_ids, ["movie-1", "movie-2"]);
+    }
+
+    /// Returns the reported status of the given movies.
+    async fn get_movies_status(
+        handler: &ServiceHandler<SimpleMoviesIndex, FileStorage>,
+        ids: &[&MovieId],
+    ) -> Vec<MovieStatus> {
+        let ids = ids.iter().map(|id| id.to_string()).collect();
+        let response = handler
+            .handle_get_movies_status(ids)
+            .await
+            .unwrap()
+            .respond_to(&actix_web::test::TestRequest::default().to_http_request())
+            .map_into_boxed_body();
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[actix_web::test]
+    async fn test_movies_status() {
+        use crate::service::preview_generator::PreviewGenerator;
+        use std::os::unix::fs::PermissionsExt;
+
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().join("data");
+
+        // fake ffmpeg binaries, s.t. the preview generation works without ffmpeg
+        let bin_dir = root_dir.path().join("bin");
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        for (name, output) in [("ffmpeg", "printf 'screenshot'"), ("ffprobe", "echo 10.0")] {
+            let file_path = bin_dir.join(name);
+            std::fs::write(&file_path, format!("#!/bin/sh\n{}\n", output)).unwrap();
+            std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let index = Arc::new(RwLock::new(SimpleMoviesIndex::new(&options).unwrap()));
+        let storage = Arc::new(RwLock::new(FileStorage::new(&options).unwrap()));
+        let ffmpeg = FFMpeg::new(&bin_dir).await.unwrap();
+        let activity_log = ActivityLog::new(options.activity_log_capacity);
+        let mut preview_generator = PreviewGenerator::new(
+            ffmpeg,
+            index.clone(),
+            storage.clone(),
+            16,
+            activity_log.clone(),
+        );
+        let handler = ServiceHandler::new(
+            &options,
+            index.clone(),
+            storage.clone(),
+            preview_generator.get_preview_request_sender(),
+            preview_generator.get_pending_previews(),
+            activity_log,
+        )
+        .await
+        .unwrap();
+
+        let add_movie = |title: &str| {
+            let index = index.clone();
+            let title = title.to_owned();
+            async move {
+                index
+                    .write()
+                    .await
+                    .add_movie(Movie {
+                        title,
+                        description: String::new(),
+                        tags: Vec::new(),
+                    })
+                    .await
+                    .unwrap()
+            }
+        };
+        let id = add_movie("Das Boot").await;
+        let no_file_id = add_movie("Alien").await;
+        let unknown_id = "unknown".to_owned();
+
+        let mut writer = storage
+            .read()
+            .await
+            .write_movie_data(
+                id.clone(),
+                MovieDataType::MovieData {
+                    ext: "mp4".to_owned(),
+                },
+            )
+            .await
+            .unwrap();
+        writer.write_all(b"movie").await.unwrap();
+        index
+            .write()
+            .await
+            .update_movie_file_info(
+                &id,
+                MovieFileInfo {
+                    extension: "mp4".to_owned(),
+                    mime_type: "video/mp4".to_owned(),
+                    original_filename: None,
+                },
+            )
+            .await
+            .unwrap();
+        handler
+            .request_preview(ScreenshotRequest {
+                movie_id: id.clone(),
+                ext: "mp4".to_owned(),
+            })
+            .await;
+
+        // the preview generator isn't running yet, s.t. the request is still queued
+        let statuses = get_movies_status(&handler, &[&id, &no_file_id, &unknown_id]).await;
+        assert_eq!(statuses.len(), 3);
+        assert!(statuses[0].has_file);
+        assert!(!statuses[0].has_screenshot);
+        assert_eq!(statuses[0].preview, PreviewStatus::Pending);
+        assert!(!statuses[1].has_file);
+        assert_eq!(statuses[1].preview, PreviewStatus::Missing);
+        assert_eq!(statuses[2].id, unknown_id);
+        assert_eq!(statuses[2].preview, PreviewStatus::NotFound);
+
+        let generator = tokio::spawn(async move { preview_generator.run().await });
+
+        let mut status = None;
+        for _ in 0..100 {
+            status = get_movies_status(&handler, &[&id]).await.pop();
+            if status
+                .as_ref()
+                .is_some_and(|status| status.preview == PreviewStatus::Ready)
+            {
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        generator.abort();
+
+        let status = status.unwrap();
+        assert_eq!(status.preview, PreviewStatus::Ready);
+        assert!(status.has_file);
+        assert!(status.has_screenshot);
+    }
+
+    /// Returns the movie as reported by the service.
+    async fn get_movie(
+        handler: &ServiceHandler<SimpleMoviesIndex, FileStorage>,
+        id: &MovieId,
+    ) -> MovieDetailed {
+        let response = handler
+            .handle_get_movie(id.clone())
+            .await
+            .unwrap()
+            .respond_to(&actix_web::test::TestRequest::default().to_http_request())
+            .map_into_boxed_body();
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_movie_processing() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, mut recv_preview, id) = create_handler_with_movie(&options).await;
+
+        let movie = get_movie(&handler, &id).await;
+        assert!(!movie.processing);
+        assert!(movie.active_jobs.is_empty());
+
+        // the movie is processed while its preview is queued
+        handler
+            .request_preview(ScreenshotRequest {
+                movie_id: id.clone(),
+                ext: "mp4".to_owned(),
+            })
+            .await;
+
+        let movie = get_movie(&handler, &id).await;
+        assert!(movie.processing);
+        assert_eq!(movie.active_jobs, [JobKind::Preview]);
+
+        // further jobs are reported alongside the preview
+        let transcode_job = handler.active_jobs.start(&id, JobKind::Transcode);
+        let movie = get_movie(&handler, &id).await;
+        assert_eq!(movie.active_jobs, [JobKind::Preview, JobKind::Transcode]);
+        drop(transcode_job);
+
+        // the preview generator marks the request as done once the preview has been generated
+        assert_eq!(recv_preview.recv().await.unwrap().movie_id, id);
+        handler.pending_previews.remove(&id);
+
+        let movie = get_movie(&handler, &id).await;
+        assert!(!movie.processing);
+        assert!(movie.active_jobs.is_empty());
+    }
+
+    /// Writes a PNG image with the given dimensions as the given data type of the given movie.
+    async fn write_image(
+        handler: &ServiceHandler<SimpleMoviesIndex, FileStorage>,
+        id: &MovieId,
+        data_type: MovieDataType,
+        width: u32,
+        height: u32,
+    ) {
+        let mut data = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::new_rgb8(width, height)
+            .write_to(&mut data, image::ImageOutputFormat::Png)
+            .unwrap();
+
+        let mut writer = handler
+            .storage
+            .read()
+            .await
+            .write_movie_data(id.clone(), data_type)
+            .await
+            .unwrap();
+        writer.write_all(data.get_ref()).await.unwrap();
+        writer.flush().await.unwrap();
+    }
+
+    /// Downloads the screenshot with the given bounds and returns its dimensions.
+    async fn download_screenshot_dimensions(
+        handler: &ServiceHandler<SimpleMoviesIndex, FileStorage>,
+        id: &MovieId,
+        width: Option<u32>,
+        height: Option<u32>,
+    ) -> (u32, u32) {
+        let response = handler
+            .handle_download_screenshot(id.clone(), width, height, None)
+            .await
+            .unwrap();
+
+        let data = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+
+        image::load_from_memory(&data).unwrap().dimensions()
+    }
+
+    #[actix_web::test]
+    async fn test_reprocess_thumbnails() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        let screenshot = || MovieDataType::ScreenshotData {
+            ext: "png".to_owned(),
+            index: 0,
+        };
+        let resized_screenshot = || MovieDataType::ResizedScreenshotData {
+            ext: "png".to_owned(),
+            width: 320,
+            height: 180,
+        };
+
+        let mut ids = Vec::new();
+        for title in ["Das Boot", "Alien", "Heat"] {
+            let id = handler
+                .index
+                .write()
+                .await
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: String::new(),
+                    tags: Vec::new(),
+                })
+                .await
+                .unwrap();
+            handler
+                .storage
+                .read()
+                .await
+                .allocate_movie_data(id.clone())
+                .await
+                .unwrap();
+            write_image(&handler, &id, screenshot(), 640, 360).await;
+            handler
+                .index
+                .write()
+                .await
+                .update_screenshot_info(
+                    &id,
+                    ScreenshotInfo {
+                        extension: "png".to_owned(),
+                        mime_type: "image/png".to_owned(),
+                        count: 1,
+                    },
+                )
+                .await
+                .unwrap();
+
+            // mark the cached thumbnail, s.t. a regenerated thumbnail can be told apart
+            write_image(&handler, &id, resized_screenshot(), 10, 10).await;
+            ids.push(id);
+        }
+
+        let ctx = RequestContext {
+            headers: HeaderMap::new(),
+            peer_addr: None,
+        };
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let response = handler
+            .handle_reprocess(
+                &ctx,
+                vec![ids[0].clone(), ids[2].clone(), ids[0].clone()],
+                None,
+                vec![DerivedArtifact::Thumbnails, DerivedArtifact::ScreenshotHash],
+            )
+            .await
+            .unwrap()
+            .respond_to(&request)
+            .map_into_boxed_body();
+        let data = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let results: Vec<ReprocessResult> = serde_json::from_slice(&data).unwrap();
+
+        // each movie is reported once with the result of each artifact
+        let results: Vec<(MovieId, Vec<(DerivedArtifact, ReprocessStatus)>)> = results
+            .into_iter()
+            .map(|result| {
+                let artifacts = result
+                    .artifacts
+                    .iter()
+                    .map(|a| (a.artifact, a.status))
+                    .collect();
+                (result.id, artifacts)
+            })
+            .collect();
+        let expected = vec![
+            (DerivedArtifact::Thumbnails, ReprocessStatus::Invalidated),
+            (DerivedArtifact::ScreenshotHash, ReprocessStatus::Done),
+        ];
+        assert_eq!(
+            results,
+            vec![
+                (ids[0].clone(), expected.clone()),
+                (ids[2].clone(), expected)
+            ]
+        );
+
+        // only the thumbnails of the reprocessed movies are regenerated on their next request
+        for (id, dimensions) in ids.iter().zip([(320, 180), (10, 10), (320, 180)]) {
+            assert_eq!(
+                download_screenshot_dimensions(&handler, id, Some(320), Some(180)).await,
+                dimensions
+            );
+        }
+
+        // the preview can't be regenerated without a movie file
+        let response = handler
+            .handle_reprocess(
+                &ctx,
+                vec![ids[1].clone()],
+                None,
+                vec![DerivedArtifact::Preview],
+            )
+            .await
+            .unwrap()
+            .respond_to(&request)
+            .map_into_boxed_body();
+        let data = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let results: Vec<ReprocessResult> = serde_json::from_slice(&data).unwrap();
+        assert_eq!(results[0].artifacts[0].status, ReprocessStatus::Failed);
+        assert!(results[0].artifacts[0].error.is_some());
+
+        // neither movies nor artifacts given
+        for (ids, artifacts) in [
+            (Vec::new(), vec![DerivedArtifact::Thumbnails]),
+            (vec![ids[0].clone()], Vec::new()),
+        ] {
+            let response = handler.handle_reprocess(&ctx, ids, None, artifacts).await;
+            assert_eq!(
+                response.err().unwrap().as_response_error().status_code(),
+                actix_web::http::StatusCode::BAD_REQUEST
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[actix_web::test]
+    async fn test_probe_all() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().join("data");
+
+        // the fake ffprobe reports a full HD video stream for every file
+        let bin_dir = root_dir.path().join("bin");
+        std::fs::create_dir(&bin_dir).unwrap();
+        let version_check = "if [ \"$1\" = \"-version\" ]; then echo \"version test\"; exit 0; fi";
+        let probe_output = r#"{"streams":[{"codec_type":"video","codec_name":"h264","width":1920,"height":1080}],"format":{"duration":"5400.0"}}"#;
+        for (name, script) in [
+            ("ffmpeg", String::new()),
+            ("ffprobe", format!("echo '{}'", probe_output)),
+        ] {
+            let file_path = bin_dir.join(name);
+            let script = format!("#!/bin/sh\n{}\n{}\n", version_check, script);
+            std::fs::write(&file_path, script).unwrap();
+            std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        let mut ids = Vec::new();
+        for title in ["Das Boot", "Alien", "Heat"] {
+            let id = handler
+                .index
+                .write()
+                .await
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: String::new(),
+                    tags: Vec::new(),
+                })
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+
+        // the first two movies get a movie file, but only the second one has been probed
+        for id in ids[..2].iter() {
+            let multipart = create_multipart("movie.mp4", "video/mp4", b"movie", 1024);
+            handler
+                .handle_upload_movie(id.clone(), None, multipart)
+                .await
+                .unwrap();
+        }
+        let probed = VideoMetadata {
+            width: 640,
+            height: 480,
+            codec: "mpeg4".to_owned(),
+            bitrate: None,
+            frame_rate: None,
+            duration: Some(60.0),
+        };
+        handler
+            .index
+            .write()
+            .await
+            .update_video_metadata(&ids[1], probed.clone())
+            .await
+            .unwrap();
+
+        let handler = handler.with_ffmpeg(FFMpeg::new(&bin_dir).await.unwrap());
+        let probe_all = |dry_run: bool| {
+            let handler = &handler;
+            async move {
+                let request = actix_web::test::TestRequest::default().to_http_request();
+                let response = handler
+                    .handle_probe_all(dry_run)
+                    .await
+                    .unwrap()
+                    .respond_to(&request)
+                    .map_into_boxed_body();
+                let data = actix_web::body::to_bytes(response.into_body())
+                    .await
+                    .unwrap();
+                let result: ProbeAllResult = serde_json::from_slice(&data).unwrap();
+                (result.movies, result.probed, result.failed)
+            }
+        };
+
+        // a dry run only counts the movie to probe
+        assert_eq!(probe_all(true).await, (1, 0, 0));
+        let movie = handler.index.read().await.get_movie(&ids[0]).await.unwrap();
+        assert!(movie.video_metadata.is_none());
+
+        assert_eq!(probe_all(false).await, (1, 1, 0));
+        let movie = handler.index.read().await.get_movie(&ids[0]).await.unwrap();
+        let video_metadata = movie.video_metadata.unwrap();
+        assert_eq!((video_metadata.width, video_metadata.height), (1920, 1080));
+        assert_eq!(video_metadata.duration, Some(5400.0));
+
+        // the already probed movie and the movie without a file are left untouched
+        let movie = handler.index.read().await.get_movie(&ids[1]).await.unwrap();
+        assert_eq!(movie.video_metadata, Some(probed));
+        let movie = handler.index.read().await.get_movie(&ids[2]).await.unwrap();
+        assert!(movie.video_metadata.is_none());
+
+        assert_eq!(probe_all(false).await, (0, 0, 0));
+    }
+
+    #[cfg(unix)]
+    #[actix_web::test]
+    async fn test_recheck_ffmpeg() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().join("data");
+        options.ffmpeg = root_dir.path().join("missing");
+
+        let bin_dir = root_dir.path().join("bin");
+        std::fs::create_dir(&bin_dir).unwrap();
+        for name in ["ffmpeg", "ffprobe"] {
+            let file_path = bin_dir.join(name);
+            let script = format!("#!/bin/sh\necho \"{} version test\"\n", name);
+            std::fs::write(&file_path, script).unwrap();
+            std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        // without ffmpeg at startup, the configured directory is probed again
+        let (handler, _recv_preview) = create_handler(&options).await;
+        let response = handler.handle_recheck_ffmpeg().await;
+        assert!(response.is_err());
+
+        // otherwise, the binaries are probed by the ffmpeg instance of the handler
+        let handler = handler.with_ffmpeg(FFMpeg::new(&bin_dir).await.unwrap());
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let response = handler
+            .handle_recheck_ffmpeg()
+            .await
+            .unwrap()
+            .respond_to(&request)
+            .map_into_boxed_body();
+        let data = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let versions: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        assert_eq!(versions["ffmpeg"], "ffmpeg version test");
+        assert_eq!(versions["ffprobe"], "ffprobe version test");
+    }
+
+    #[actix_web::test]
+    async fn test_download_resized_screenshot() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+        handler
+            .storage
+            .read()
+            .await
+            .allocate_movie_data(id.clone())
+            .await
+            .unwrap();
+
+        let screenshot = || MovieDataType::ScreenshotData {
+            ext: "png".to_owned(),
+            index: 0,
+        };
+        let resized_screenshot = || MovieDataType::ResizedScreenshotData {
+            ext: "png".to_owned(),
+            width: 320,
+            height: 180,
+        };
+
+        write_image(&handler, &id, screenshot(), 640, 360).await;
+        handler
+            .index
+            .write()
+            .await
+            .update_screenshot_info(
+                &id,
+                ScreenshotInfo {
+                    extension: "png".to_owned(),
+                    mime_type: "image/png".to_owned(),
+                    count: 1,
+                },
+            )
+            .await
+            .unwrap();
+
+        // request the original and two different sizes
+        assert_eq!(
+            download_screenshot_dimensions(&handler, &id, None, None).await,
+            (640, 360)
+        );
+        assert_eq!(
+            download_screenshot_dimensions(&handler, &id, Some(320), Some(180)).await,
+            (320, 180)
+        );
+        assert_eq!(
+            download_screenshot_dimensions(&handler, &id, Some(100), None).await,
+            (90, 51)
+        );
+
+        // replace the cached variant by a marker image to check that the cache is reused
+        write_image(&handler, &id, resized_screenshot(), 10, 10).await;
+        assert_eq!(
+            download_screenshot_dimensions(&handler, &id, Some(320), Some(180)).await,
+            (10, 10)
+        );
+
+        // a new screenshot invalidates the cached variants
+        write_image(&handler, &id, screenshot(), 1280, 720).await;
+        assert_eq!(
+            download_screenshot_dimensions(&handler, &id, Some(320), Some(180)).await,
+            (320, 180)
+        );
+
+        // concurrent requests of a new variant all get the complete screenshot
+        let downloads =
+            (0..8).map(|_| download_screenshot_dimensions(&handler, &id, Some(640), Some(640)));
+        for dimensions in futures::future::join_all(downloads).await {
+            assert_eq!(dimensions, (640, 360));
+        }
+
+        // only the variants remain, but no temporary files
+        let movie_dir = handler
+            .storage
+            .read()
+            .await
+            .get_file_path(id.clone(), screenshot())
+            .await
+            .unwrap()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .to_path_buf();
+        let temp_files = std::fs::read_dir(movie_dir)
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().ends_with(".tmp")
+            })
+            .count();
+        assert_eq!(temp_files, 0);
+    }
+
+    #[actix_web::test]
+    async fn test_download_contact_sheet() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+        handler
+            .storage
+            .read()
+            .await
+            .allocate_movie_data(id.clone())
+            .await
+            .unwrap();
+
+        // the movie is not ready without screenshots
+        let response = handler
+            .handle_download_contact_sheet(id.clone(), None, None)
+            .await;
+        assert_eq!(
+            response.err().unwrap().as_response_error().status_code(),
+            actix_web::http::StatusCode::CONFLICT
+        );
+
+        let screenshot = |index: u32| MovieDataType::ScreenshotData {
+            ext: "png".to_owned(),
+            index,
+        };
+        for index in 0..3 {
+            write_image(&handler, &id, screenshot(index), 640, 360).await;
+        }
+        handler
+            .index
+            .write()
+            .await
+            .update_screenshot_info(
+                &id,
+                ScreenshotInfo {
+                    extension: "png".to_owned(),
+                    mime_type: "image/png".to_owned(),
+                    count: 3,
+                },
+            )
+            .await
+            .unwrap();
+
+        let contact_sheet_dimensions = |columns: Option<u32>| {
+            let handler = &handler;
+            let id = id.clone();
+
+            async move {
+                let response = handler
+                    .handle_download_contact_sheet(id, columns, None)
+                    .await
+                    .unwrap();
+                let data = actix_web::body::to_bytes(response.into_body())
+                    .await
+                    .unwrap();
+
+                image::load_from_memory(&data).unwrap().dimensions()
+            }
+        };
+
+        // the three screenshots are scaled to the width of a cell and laid out in a grid
+        let (cell_width, cell_height) = (screenshot_resizer::CONTACT_SHEET_CELL_WIDTH, 180);
+        assert_eq!(
+            contact_sheet_dimensions(Some(2)).await,
+            (2 * cell_width, 2 * cell_height)
+        );
+        assert_eq!(
+            contact_sheet_dimensions(Some(1)).await,
+            (cell_width, 3 * cell_height)
+        );
+        assert_eq!(
+            contact_sheet_dimensions(None).await,
+            (3 * cell_width, cell_height)
+        );
+
+        // a new screenshot results in a new contact sheet instead of the cached one
+        write_image(&handler, &id, screenshot(2), 640, 480).await;
+        assert_eq!(
+            contact_sheet_dimensions(Some(2)).await,
+            (2 * cell_width, 2 * 240)
+        );
+
+        let response = handler
+            .handle_download_contact_sheet(id.clone(), Some(0), None)
+            .await;
+        assert_eq!(
+            response.err().unwrap().as_response_error().status_code(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_revert_screenshot() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.screenshot_history_size = 2;
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+
+        let get_history = || {
+            let handler = &handler;
+            let id = id.clone();
+
+            async move {
+                let request = actix_web::test::TestRequest::default().to_http_request();
+                let response = handler
+                    .handle_get_screenshot_history(id)
+                    .await
+                    .unwrap()
+                    .respond_to(&request)
+                    .map_into_boxed_body();
+                let data = actix_web::body::to_bytes(response.into_body())
+                    .await
+                    .unwrap();
+                let history: Vec<ScreenshotVersion> = serde_json::from_slice(&data).unwrap();
+
+                history
+                    .into_iter()
+                    .map(|v| (v.version, v.extension))
+                    .collect::<Vec<(u32, String)>>()
+            }
+        };
+        assert!(get_history().await.is_empty());
+
+        // only the two most recently replaced screenshots are kept
+        for (filename, content_type, data) in [
+            ("first.png", "image/png", "first"),
+            ("second.jpg", "image/jpeg", "second"),
+            ("third.png", "image/png", "third"),
+            ("fourth.png", "image/png", "fourth"),
+        ] {
+            let multipart = create_multipart(filename, content_type, data.as_bytes(), 64);
+            assert!(handler
+                .handle_upload_screenshot(id.clone(), multipart)
+                .await
+                .is_ok());
+        }
+        assert_eq!(
+            get_history().await,
+            [(3, "png".to_owned()), (2, "jpg".to_owned())]
+        );
+
+        // reverting to a previous screenshot archives the replaced one
+        assert!(handler
+            .handle_revert_screenshot(id.clone(), 2)
+            .await
+            .is_ok());
+        assert_eq!(
+            get_history().await,
+            [(4, "png".to_owned()), (3, "png".to_owned())]
+        );
+
+        let movie = handler.index.read().await.get_movie(&id).await.unwrap();
+        let screenshot_info = movie.screenshot_file_info.unwrap();
+        assert_eq!(screenshot_info.extension, "jpg");
+        assert_eq!(screenshot_info.mime_type, "image/jpeg");
+
+        let mut reader = handler
+            .storage
+            .read()
+            .await
+            .read_movie_data(
+                id.clone(),
+                MovieDataType::ScreenshotData {
+                    ext: "jpg".to_owned(),
+                    index: 0,
+                },
+            )
+            .await
+            .unwrap();
+        let mut data = String::new();
+        reader.read_to_string(&mut data).await.unwrap();
+        assert_eq!(data, "second");
+
+        // unknown versions can't be restored
+        let err = match handler.handle_revert_screenshot(id.clone(), 2).await {
+            Ok(_) => panic!("Reverting to an unknown version must fail"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::NOT_FOUND
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_upload_screenshot_read_error() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.screenshot_history_size = 2;
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+
+        let multipart = create_multipart("first.png", "image/png", b"first", 64);
+        assert!(handler
+            .handle_upload_screenshot(id.clone(), multipart)
+            .await
+            .is_ok());
+
+        // the connection breaks after the first part of the replacing screenshot
+        for (filename, content_type) in [("second.png", "image/png"), ("third.jpg", "image/jpeg")] {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", BOUNDARY)
+                    .parse()
+                    .unwrap(),
+            );
+            let head = format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                BOUNDARY, filename, content_type
+            );
+            let chunks: Vec<Result<Bytes, actix_web::error::PayloadError>> =
+                vec![Ok(Bytes::from(head)), Ok(Bytes::from_static(b"partial"))];
+            let broken = futures::stream::once(async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Err(actix_web::error::PayloadError::Incomplete(None))
+            });
+            let multipart = Multipart::new(&headers, futures::stream::iter(chunks).chain(broken));
+
+            assert!(handler
+                .handle_upload_screenshot(id.clone(), multipart)
+                .await
+                .is_err());
+
+            // the replaced screenshot is restored and the partially written one is removed
+            let movie = handler.index.read().await.get_movie(&id).await.unwrap();
+            assert_eq!(movie.screenshot_file_info.unwrap().extension, "png");
+            assert_eq!(
+                std::fs::read(root_dir.path().join(&id).join("preview.png")).unwrap(),
+                b"first"
+            );
+            assert!(!root_dir.path().join(&id).join("preview.jpg").exists());
+            assert!(handler
+                .storage
+                .read()
+                .await
+                .get_screenshot_history(id.clone())
+                .await
+                .unwrap()
+                .is_empty());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_screenshot_cache_headers() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.screenshot_cache_max_age = 3600;
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+        handler
+            .storage
+            .read()
+            .await
+            .allocate_movie_data(id.clone())
+            .await
+            .unwrap();
+
+        let screenshot = || MovieDataType::ScreenshotData {
+            ext: "png".to_owned(),
+            index: 0,
+        };
+        write_image(&handler, &id, screenshot(), 640, 360).await;
+        handler
+            .index
+            .write()
+            .await
+            .update_screenshot_info(
+                &id,
+                ScreenshotInfo {
+                    extension: "png".to_owned(),
+                    mime_type: "image/png".to_owned(),
+                    count: 1,
+                },
+            )
+            .await
+            .unwrap();
+
+        let download = |width: Option<u32>, if_none_match: Option<IfNoneMatch>| {
+            handler.handle_download_screenshot(id.clone(), width, None, if_none_match)
+        };
+        let get_etag = |response: &HttpResponse| -> EntityTag {
+            response
+                .headers()
+                .get(header::ETAG)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .parse()
+                .unwrap()
+        };
+
+        let response = download(None, None).await.unwrap();
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CACHE_CONTROL).unwrap(),
+            "public, max-age=3600"
+        );
+        let etag = get_etag(&response);
+
+        // the resized variant has its own ETag
+        let response = download(Some(320), None).await.unwrap();
+        assert!(response.headers().contains_key(header::CACHE_CONTROL));
+        assert_ne!(get_etag(&response), etag);
+
+        // the client already has the screenshot
+        let response = download(None, Some(IfNoneMatch::Items(vec![etag.clone()])))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), actix_web::http::StatusCode::NOT_MODIFIED);
+
+        // a regenerated screenshot has a new ETag
+        write_image(&handler, &id, screenshot(), 1280, 720).await;
+        let response = download(None, Some(IfNoneMatch::Items(vec![etag.clone()])))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        assert_ne!(get_etag(&response), etag);
+
+        // metadata must be revalidated
+        let response = handler
+            .handle_get_movie(id.clone())
+            .await
+            .unwrap()
+            .respond_to(&actix_web::test::TestRequest::default().to_http_request());
+        assert_eq!(
+            response.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-cache"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_placeholder_screenshot() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+
+        // without a placeholder, the screenshot is not yet ready
+        let err = handler
+            .handle_download_screenshot(id.clone(), None, None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::CONFLICT
+        );
+
+        let placeholder = root_dir.path().join("placeholder.png");
+        std::fs::write(&placeholder, b"placeholder").unwrap();
+        options.placeholder_screenshot = Some(placeholder);
+        let handler = ServiceHandler { options, ..handler };
+
+        let response = handler
+            .handle_download_screenshot(id.clone(), None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "image/png"
+        );
+        assert_eq!(response.headers().get(PLACEHOLDER_HEADER).unwrap(), "true");
+        assert_eq!(
+            response.headers().get(header::CACHE_CONTROL).unwrap(),
+            "public, max-age=60"
+        );
+
+        let data = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(data, "placeholder");
+    }
+
+    #[actix_web::test]
+    async fn test_download_animated_preview() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+
+        // the animated preview hasn't been generated
+        let err = handler
+            .handle_download_animated_preview(id.clone(), None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::NOT_FOUND
+        );
+
+        let mut writer = handler
+            .storage
+            .read()
+            .await
+            .write_movie_data(
+                id.clone(),
+                MovieDataType::AnimatedPreviewData {
+                    ext: "gif".to_owned(),
+                },
+            )
+            .await
+            .unwrap();
+        writer.write_all(b"GIF89a").await.unwrap();
+        writer.flush().await.unwrap();
+        handler
+            .index
+            .write()
+            .await
+            .update_animated_preview_info(
+                &id,
+                AnimatedPreviewInfo {
+                    extension: "gif".to_owned(),
+                    mime_type: "image/gif".to_owned(),
+                },
+            )
+            .await
+            .unwrap();
+
+        let response = handler
+            .handle_download_animated_preview(id.clone(), None)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "image/gif"
+        );
+        assert!(response.headers().get(header::ETAG).is_some());
+
+        let data = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(data, "GIF89a");
+    }
+
+    #[actix_web::test]
+    async fn test_upload_subtitle() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+
+        let err = handler
+            .handle_download_subtitle(id.clone(), "en".to_owned())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::NOT_FOUND
+        );
+
+        // only text formats are accepted as subtitles
+        let err = match handler
+            .handle_upload_subtitle(
+                id.clone(),
+                "en".to_owned(),
+                create_multipart("subtitle.png", "image/png", b"png", 1024),
+            )
+            .await
+        {
+            Ok(_) => panic!("Uploading an image as subtitle must fail"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+
+        let srt_data = b"1\n00:00:01,000 --> 00:00:02,000\nHello\n";
+        let vtt_data = b"WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nHello\n";
+        for (filename, content_type, data) in [
+            ("subtitle.srt", "application/x-subrip", srt_data.as_slice()),
+            ("subtitle.vtt", "text/vtt", vtt_data.as_slice()),
+        ] {
+            assert!(handler
+                .handle_upload_subtitle(
+                    id.clone(),
+                    "en".to_owned(),
+                    create_multipart(filename, content_type, data, 16),
+                )
+                .await
+                .is_ok());
+        }
+
+        // the WebVTT subtitle replaced the SubRip one
+        assert_eq!(
+            handler
+                .index
+                .read()
+                .await
+                .get_movie(&id)
+                .await
+                .unwrap()
+                .subtitle_languages,
+            vec!["en".to_owned()]
+        );
+
+        let response = handler
+            .handle_download_subtitle(id.clone(), "en".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/vtt"
+        );
+
+        let data = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(data, vtt_data.as_slice());
+
+        // invalid languages are rejected
+        let err = match handler
+            .handle_upload_subtitle(
+                id.clone(),
+                "../en".to_owned(),
+                create_multipart("subtitle.vtt", "text/vtt", vtt_data, 1024),
+            )
+            .await
+        {
+            Ok(_) => panic!("Uploading a subtitle with an invalid language must fail"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+
+        // content types that are not visible ASCII are rejected instead of panicking
+        let err = match handler
+            .handle_upload_subtitle(
+                id.clone(),
+                "en".to_owned(),
+                create_multipart("subtitle.vtt", "text/vtt\u{e9}", vtt_data, 1024),
+            )
+            .await
+        {
+            Ok(_) => panic!("Uploading a subtitle with a non-ASCII content type must fail"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_get_largest_movies() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        // add movies with files of different sizes and one movie without a file
+        let sizes: [(&str, Option<usize>); 4] = [
+            ("Doctor Who", Some(300)),
+            ("The X-Files", Some(1000)),
+            ("E.T. the Extra-Terrestrial", None),
+            ("Das Boot", Some(10)),
+        ];
+
+        for (title, size) in sizes.iter() {
+            let id = handler
+                .index
+                .write()
+                .await
+                .add_movie(Movie {
+                    title: title.to_string(),
+                    description: String::new(),
+                    tags: Vec::new(),
+                })
+                .await
+                .unwrap();
+
+            if let Some(size) = size {
+                let data = vec![0u8; *size];
+                let multipart = create_multipart("movie.mp4", "video/mp4", &data, 1024);
+                assert!(handler
+                    .handle_upload_movie(id, None, multipart)
+                    .await
+                    .is_ok());
+            }
+        }
+
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let response = handler
+            .handle_get_largest_movies(2)
+            .await
+            .unwrap()
+            .respond_to(&request)
+            .map_into_boxed_body();
+        let data = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let movies: Vec<MovieSizeEntry> = serde_json::from_slice(&data).unwrap();
+
+        let movies: Vec<(&str, u64)> = movies.iter().map(|m| (m.title.as_str(), m.size)).collect();
+        assert_eq!(movies, [("The X-Files", 1000), ("Doctor Who", 300)]);
+    }
+
+    #[actix_web::test]
+    async fn test_get_tags_truncated() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.max_tag_list_size = 2;
+
+        let (mut handler, _recv_preview) = create_handler(&options).await;
+
+        /// Returns the listed tags and whether they are marked as truncated.
+        async fn get_tags(
+            handler: &ServiceHandler<SimpleMoviesIndex, FileStorage>,
+        ) -> (Vec<(String, usize)>, bool) {
+            let request = actix_web::test::TestRequest::default().to_http_request();
+            let response = handler
+                .handle_get_tags()
+                .await
+                .unwrap()
+                .respond_to(&request)
+                .map_into_boxed_body();
+            let truncated = response.headers().get(TRUNCATED_HEADER).is_some();
+            let data = actix_web::body::to_bytes(response.into_body())
+                .await
+                .unwrap();
+
+            (serde_json::from_slice(&data).unwrap(), truncated)
+        }
+
+        for tags in [vec!["drama", "war"], vec!["drama"]] {
+            handler
+                .index
+                .write()
+                .await
+                .add_movie(Movie {
+                    title: "Das Boot".to_owned(),
+                    description: String::new(),
+                    tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                })
+                .await
+                .unwrap();
+        }
+
+        // the list within the limit is complete
+        let (tags, truncated) = get_tags(&handler).await;
+        assert_eq!(tags, [("drama".to_owned(), 2), ("war".to_owned(), 1)]);
+        assert!(!truncated);
+
+        // the most frequent tags are kept, once the limit is exceeded
+        handler
+            .index
+            .write()
+            .await
+            .add_movie(Movie {
+                title: "Doctor Who".to_owned(),
+                description: String::new(),
+                tags: vec!["sci-fi".to_owned()],
+            })
+            .await
+            .unwrap();
+        let (tags, truncated) = get_tags(&handler).await;
+        assert_eq!(tags, [("drama".to_owned(), 2), ("sci-fi".to_owned(), 1)]);
+        assert!(truncated);
+
+        // the largest limit doesn't overflow when loading one more tag
+        handler.options.max_tag_list_size = usize::MAX;
+        let (tags, truncated) = get_tags(&handler).await;
+        assert_eq!(tags.len(), 3);
+        assert!(!truncated);
+    }
+
+    #[actix_web::test]
+    async fn test_search_tags_limit() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.max_tag_list_size = 1;
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+        handler
+            .index
+            .write()
+            .await
+            .add_movie(Movie {
+                title: "Alien".to_owned(),
+                description: String::new(),
+                tags: vec!["sci-fi".to_owned(), "scary".to_owned()],
+            })
+            .await
+            .unwrap();
+
+        // the requested limit is bounded by the maximal size of the tag list
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let response = handler
+            .handle_search_tags("sc", 10)
+            .await
+            .unwrap()
+            .respond_to(&request)
+            .map_into_boxed_body();
+        let data = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let tags: Vec<(String, usize)> = serde_json::from_slice(&data).unwrap();
+        assert_eq!(tags.len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_add_movies() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        let movies: Vec<Movie> = (0..300)
+            .map(|i| Movie {
+                title: format!("Movie {}", i),
+                description: String::new(),
+                tags: vec![format!("tag{}", i % 10)],
+            })
+            .collect();
+
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let response = handler
+            .handle_add_movies(movies)
+            .await
+            .unwrap()
+            .respond_to(&request)
+            .map_into_boxed_body();
+        let data = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let ids: Vec<MovieId> = serde_json::from_slice(&data).unwrap();
+
+        // the ids are returned in the order of the movies
+        assert_eq!(ids.len(), 300);
+        for (i, id) in ids.iter().enumerate() {
+            let movie = handler.index.read().await.get_movie(id).await.unwrap();
+            assert_eq!(movie.movie.title, format!("Movie {}", i));
+            assert!(root_dir.path().join(id).is_dir());
+        }
+
+        let query = MovieSearchQuery::default();
+        let found = handler.index.read().await.search_movies(query).await;
+        assert_eq!(found.unwrap().len(), 300);
+    }
+
+    #[cfg(unix)]
+    #[actix_web::test]
+    async fn test_upload_movie_without_video_stream() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().join("data");
+        options.no_video_stream_policy = NoVideoStreamPolicy::Reject;
+
+        // the fake ffprobe reports video streams only for files containing "video"
+        let bin_dir = root_dir.path().join("bin");
+        std::fs::create_dir(&bin_dir).unwrap();
+        let version_check = "if [ \"$1\" = \"-version\" ]; then echo \"version test\"; exit 0; fi";
+        for (name, script) in [
+            ("ffmpeg", ""),
+            (
+                "ffprobe",
+                "for arg; do file=\"$arg\"; done\ngrep -q video \"$file\" && echo 0\nexit 0",
+            ),
+        ] {
+            let file_path = bin_dir.join(name);
+            let script = format!("#!/bin/sh\n{}\n{}\n", version_check, script);
+            std::fs::write(&file_path, script).unwrap();
+            std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+        let handler = handler.with_ffmpeg(FFMpeg::new(&bin_dir).await.unwrap());
+
+        // the audio-only file is rejected and not kept
+        let multipart = create_multipart("soundtrack.mp4", "video/mp4", b"audio only", 1024);
+        let response = handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await;
+        assert_eq!(
+            response.err().unwrap().as_response_error().status_code(),
+            actix_web::http::StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+
+        let movie = handler.index.read().await.get_movie(&id).await.unwrap();
+        assert!(movie.movie_file_info.is_none());
+        assert!(handler
+            .storage
+            .read()
+            .await
+            .read_movie_data(
+                id.clone(),
+                MovieDataType::MovieData {
+                    ext: "mp4".to_owned()
+                }
+            )
+            .await
+            .is_err());
+
+        // a file with a video stream is accepted
+        let multipart = create_multipart("movie.mp4", "video/mp4", b"video data", 1024);
+        assert!(handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+            .is_ok());
+
+        let movie = handler.index.read().await.get_movie(&id).await.unwrap();
+        assert!(movie.movie_file_info.is_some());
+    }
+
+    #[cfg(unix)]
+    #[actix_web::test]
+    async fn test_download_movie_transcoded() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().join("data");
+        options.transcode_on_the_fly = true;
+
+        // the fake ffmpeg writes the header of an MP4 file, i.e., its 'ftyp' box
+        let bin_dir = root_dir.path().join("bin");
+        std::fs::create_dir(&bin_dir).unwrap();
+        let version_check = "if [ \"$1\" = \"-version\" ]; then echo \"version test\"; exit 0; fi";
+        for (name, script) in [
+            (
+                "ffmpeg",
+                "printf '\\000\\000\\000\\020ftypisom\\000\\000\\002\\000'",
+            ),
+            ("ffprobe", "echo 10.0"),
+        ] {
+            let file_path = bin_dir.join(name);
+            let script = format!("#!/bin/sh\n{}\n{}\n", version_check, script);
+            std::fs::write(&file_path, script).unwrap();
+            std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let (handler, _recv_preview, id) = create_handler_with_movie(&options).await;
+        let handler = handler.with_ffmpeg(FFMpeg::new(&bin_dir).await.unwrap());
+        let multipart = create_multipart("movie.wmv", "video/x-ms-wmv", b"wmv movie", 1024);
+        assert!(handler
+            .handle_upload_movie(id.clone(), None, multipart)
+            .await
+            .is_ok());
+
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let download = |format: &'static str| {
+            let id = id.clone();
+            let handler = &handler;
+            let request = request.clone();
+            async move {
+                let response = handler
+                    .handle_download_movie_as(id, format, &[], None)
+                    .await?
+                    .respond_to(&request)
+                    .map_into_boxed_body();
+                let content_type = response.headers().get(header::CONTENT_TYPE).cloned();
+                let data = actix_web::body::to_bytes(response.into_body())
+                    .await
+                    .unwrap();
+
+                Ok::<_, actix_web::Error>((content_type.unwrap(), data))
+            }
+        };
+
+        // the WMV movie is streamed as MP4
+        let (content_type, data) = download("mp4").await.unwrap();
+        assert_eq!(content_type, "video/mp4");
+        assert_eq!(&data[4..12], b"ftypisom");
+
+        // the stored format is streamed as is
+        let (content_type, data) = download("WMV").await.unwrap();
+        assert_eq!(content_type, "video/x-ms-wmv");
+        assert_eq!(data, "wmv movie");
+
+        // other formats can't be transcoded into
+        let err = download("webm").await.unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_timestamps_in_utc() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.display_timezone = Some(chrono::FixedOffset::east_opt(2 * 3600).unwrap());
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        let movie = Movie {
+            title: "Das Boot".to_owned(),
+            description: String::new(),
+            tags: Vec::new(),
+        };
+        assert!(handler
+            .handle_add_movie(movie, Some("das-boot".to_owned()))
+            .await
+            .is_ok());
+
+        // the display timezone doesn't affect the timestamps of the API
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        for response in [
+            handler
+                .handle_get_movie("das-boot".to_owned())
+                .await
+                .unwrap()
+                .respond_to(&request)
+                .map_into_boxed_body(),
+            handler
+                .handle_get_activity(1)
+                .await
+                .unwrap()
+                .respond_to(&request)
+                .map_into_boxed_body(),
+        ] {
+            let data = actix_web::body::to_bytes(response.into_body())
+                .await
+                .unwrap();
+            let value: serde_json::Value = serde_json::from_slice(&data).unwrap();
+            let date = match &value {
+                serde_json::Value::Array(events) => &events[0]["time"],
+                _ => &value["date"],
+            };
+            assert!(date.as_str().unwrap().ends_with('Z'), "{}", date);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_activity_feed() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+        let mut live_events = handler.activity_log.subscribe();
+
+        let movie = Movie {
+            title: "Das Boot".to_owned(),
+            description: String::new(),
+            tags: Vec::new(),
+        };
+        assert!(handler
+            .handle_add_movie(movie, Some("das-boot".to_owned()))
+            .await
+            .is_ok());
+        assert!(handler
+            .handle_delete_movie("das-boot".to_owned())
+            .await
+            .is_ok());
+
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let response = handler
+            .handle_get_activity(10)
+            .await
+            .unwrap()
+            .respond_to(&request)
+            .map_into_boxed_body();
+        let data = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let events: Vec<ActivityEvent> = serde_json::from_slice(&data).unwrap();
+
+        // the most recent event comes first
+        let kinds: Vec<ActivityKind> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            [ActivityKind::MovieDeleted, ActivityKind::MovieAdded]
+        );
+        assert_eq!(events[1].movie_id, Some("das-boot".to_owned()));
+        assert!(events[1].message.contains("Das Boot"));
+
+        // live subscribers receive the events in the order of their occurrence
+        let event = live_events.recv().await.unwrap();
+        assert_eq!(event.kind, ActivityKind::MovieAdded);
+    }
+
+    #[actix_web::test]
+    async fn test_similar_images() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.compute_screenshot_hashes = true;
+
+        let (handler, _recv_preview) = create_handler(&options).await;
+
+        // the same frame as lossless png and as low quality jpeg, and a different frame
+        let frame = image::RgbImage::from_fn(320, 180, |x, y| {
+            let value = ((x + y) * 255 / 500) as u8;
+            image::Rgb([value, 255 - value, value / 2])
+        });
+        let mut png = std::io::Cursor::new(Vec::new());
+        frame
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+        let mut jpeg = std::io::Cursor::new(Vec::new());
+        frame
+            .write_to(&mut jpeg, image::ImageOutputFormat::Jpeg(40))
+            .unwrap();
+        let mut other = std::io::Cursor::new(Vec::new());
+        image::imageops::flip_horizontal(&frame)
+            .write_to(&mut other, image::ImageOutputFormat::Png)
+            .unwrap();
+
+        let mut movie_ids = Vec::new();
+        for (filename, content_type, data) in [
+            ("frame.png", "image/png", png.get_ref()),
+            ("frame.jpg", "image/jpeg", jpeg.get_ref()),
+            ("other.png", "image/png", other.get_ref()),
+        ] {
+            let id = handler
+                .index
+                .write()
+                .await
+                .add_movie(Movie {
+                    title: filename.to_owned(),
+                    description: String::new(),
+                    tags: Vec::new(),
+                })
+                .await
+                .unwrap();
+
+            handler
+                .handle_upload_screenshot(
+                    id.clone(),
+                    create_multipart(filename, content_type, data, 4096),
+                )
+                .await
+                .unwrap();
+
+            movie_ids.push(id);
+        }
+
+        assert_eq!(
+            handler
+                .index
+                .read()
+                .await
+                .get_screenshot_hashes()
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+
+        let response = handler
+            .handle_get_similar_images(10)
+            .await
+            .unwrap()
+            .respond_to(&actix_web::test::TestRequest::default().to_http_request())
+            .map_into_boxed_body();
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let clusters: Vec<Vec<MovieId>> = serde_json::from_slice(&body).unwrap();
+
+        let mut expected = vec![movie_ids[0].clone(), movie_ids[1].clone()];
+        expected.sort();
+        assert_eq!(clusters, vec![expected]);
+    }
+
+    /// The number of screenshot reads of `CountingStorage`.
+    static SCREENSHOT_READS: std::sync::atomic::AtomicUsize =
+        std::sync::atomic::AtomicUsize::new(0);
+
+    /// A movie storage counting the reads of main screenshots.
+    struct CountingStorage {
+        storage: FileStorage,
+    }
+
+    #[async_trait::async_trait]
+    impl MovieStorage for CountingStorage {
+        type W = <FileStorage as MovieStorage>::W;
+        type R = <FileStorage as MovieStorage>::R;
+
+        fn new(options: &Options) -> Result<Self, Error> {
+            Ok(Self {
+                storage: FileStorage::new(options)?,
+            })
+        }
+
+        async fn allocate_movie_data(&self, id: MovieId) -> Result<(), Error> {
+            self.storage.allocate_movie_data(id).await
+        }
+
+        async fn allocate_movies_data(&self, ids: &[MovieId]) -> Result<(), Error> {
+            self.storage.allocate_movies_data(ids).await
+        }
+
+        async fn write_movie_data(
+            &self,
+            id: MovieId,
+            data_type: MovieDataType,
+        ) -> Result<Self::W, Error> {
+            self.storage.write_movie_data(id, data_type).await
+        }
+
+        async fn rename_movie_data(
+            &self,
+            id: MovieId,
+            from: MovieDataType,
+            to: MovieDataType,
+        ) -> Result<(), Error> {
+            self.storage.rename_movie_data(id, from, to).await
+        }
+
+        async fn remove_movie_file(
+            &self,
+            id: MovieId,
+            data_type: MovieDataType,
+        ) -> Result<(), Error> {
+            self.storage.remove_movie_file(id, data_type).await
+        }
+
+        async fn invalidate_resized_screenshots(&self, id: MovieId) -> Result<(), Error> {
+            self.storage.invalidate_resized_screenshots(id).await
+        }
+
+        async fn archive_screenshot(
+            &self,
+            id: MovieId,
+            ext: &str,
+            history_size: usize,
+        ) -> Result<(), Error> {
+            self.storage.archive_screenshot(id, ext, history_size).await
+        }
+
+        async fn get_screenshot_history(&self, id: MovieId) -> Result<Vec<(u32, String)>, Error> {
+            self.storage.get_screenshot_history(id).await
+        }
+
+        async fn restore_screenshot(
+            &self,
+            id: MovieId,
+            version: u32,
+            ext: Option<&str>,
+            history_size: usize,
+        ) -> Result<String, Error> {
+            self.storage
+                .restore_screenshot(id, version, ext, history_size)
+                .await
+        }
+
+        async fn remove_movie_data(&self, id: MovieId) -> Result<(), Error> {
+            self.storage.remove_movie_data(id).await
+        }
+
+        async fn read_movie_data(
+            &self,
+            id: MovieId,
+            data_type: MovieDataType,
+        ) -> Result<Self::R, Error> {
+            if matches!(data_type, MovieDataType::ScreenshotData { index: 0, .. }) {
+                SCREENSHOT_READS.fetch_add(1, Ordering::SeqCst);
             }
+
+            self.storage.read_movie_data(id, data_type).await
+        }
+
+        async fn get_file_path(
+            &self,
+            id: MovieId,
+            data_type: MovieDataType,
+        ) -> Result<Option<std::path::PathBuf>, Error> {
+            self.storage.get_file_path(id, data_type).await
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_screenshot_cache_warm_up() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.screenshot_memory_cache_size = 1024 * 1024;
+        options.screenshot_warm_up_count = 2;
+
+        let index = Arc::new(RwLock::new(SimpleMoviesIndex::new(&options).unwrap()));
+        let storage = Arc::new(RwLock::new(CountingStorage::new(&options).unwrap()));
+        let (preview_requests, _recv_preview) = mpsc::channel(1);
+        let handler = ServiceHandler::new(
+            &options,
+            index,
+            storage,
+            preview_requests,
+            PendingPreviews::default(),
+            ActivityLog::new(options.activity_log_capacity),
+        )
+        .await
+        .unwrap();
+
+        // the movies are added one after the other, the last one being the most recent
+        let mut ids = Vec::new();
+        for title in ["Das Boot", "Doctor Who", "The X-Files"] {
+            let id = handler
+                .index
+                .write()
+                .await
+                .add_movie(Movie {
+                    title: title.to_owned(),
+                    description: String::new(),
+                    tags: Vec::new(),
+                })
+                .await
+                .unwrap();
+
+            let storage = handler.storage.read().await;
+            storage.allocate_movie_data(id.clone()).await.unwrap();
+            let mut writer = storage
+                .write_movie_data(
+                    id.clone(),
+                    MovieDataType::ScreenshotData {
+                        ext: "png".to_owned(),
+                        index: 0,
+                    },
+                )
+                .await
+                .unwrap();
+            writer.write_all(title.as_bytes()).await.unwrap();
+            writer.flush().await.unwrap();
+            drop(storage);
+
+            handler
+                .index
+                .write()
+                .await
+                .update_screenshot_info(
+                    &id,
+                    ScreenshotInfo {
+                        extension: "png".to_owned(),
+                        mime_type: "image/png".to_owned(),
+                        count: 1,
+                    },
+                )
+                .await
+                .unwrap();
+
+            ids.push(id);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // only the screenshots of the two most recent movies are loaded
+        assert_eq!(handler.warm_up_screenshot_cache().await, 2);
+        assert_eq!(SCREENSHOT_READS.load(Ordering::SeqCst), 2);
+
+        let download =
+            |id: &MovieId| handler.handle_download_screenshot(id.clone(), None, None, None);
+        let body = |response: HttpResponse| async move {
+            actix_web::body::to_bytes(response.into_body())
+                .await
+                .unwrap()
+        };
+
+        // the recent movies are served from the cache
+        let response = download(&ids[2]).await.unwrap();
+        assert_eq!(body(response).await, "The X-Files");
+        let response = download(&ids[1]).await.unwrap();
+        assert_eq!(body(response).await, "Doctor Who");
+        assert_eq!(SCREENSHOT_READS.load(Ordering::SeqCst), 2);
+
+        // the older movie is read once and cached afterwards
+        for _ in 0..2 {
+            let response = download(&ids[0]).await.unwrap();
+            assert_eq!(body(response).await, "Das Boot");
         }
+        assert_eq!(SCREENSHOT_READS.load(Ordering::SeqCst), 3);
+
+        // a replaced screenshot is read again
+        let multipart = create_multipart("new.png", "image/png", b"new screenshot", 4);
+        handler
+            .handle_upload_screenshot(ids[2].clone(), multipart)
+            .await
+            .unwrap();
+        let response = download(&ids[2]).await.unwrap();
+        assert_eq!(body(response).await, "new screenshot");
+        assert_eq!(SCREENSHOT_READS.load(Ordering::SeqCst), 4);
     }
 }
@@ -1,21 +1,53 @@
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    marker::PhantomData,
+    net::TcpListener,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
 
 use actix_cors::Cors;
 use actix_multipart::Multipart;
-use actix_web::{http::header, web, App, HttpServer, Responder, Result};
+use actix_web::{
+    http::{header, Method},
+    middleware::from_fn,
+    web, App, Either, FromRequest, Handler, HttpRequest, HttpResponse, HttpServer, Responder,
+    Result, Scope,
+};
+use itertools::Itertools;
 
-use log::{debug, error, info, trace};
+use log::{debug, error, info, trace, warn};
 use serde_qs::actix::QsQuery;
 use tokio::sync::{mpsc, RwLock};
+use tokio_util::sync::CancellationToken;
 
 use crate::{
     ffmpeg::FFMpeg, service::preview_generator::PreviewGenerator, Error, Movie, MovieId,
-    MovieSearchQuery, MovieStorage, MoviesIndex, Options,
+    MovieSearchQuery, MovieStorage, MoviesIndex, Options, DEFAULT_LIBRARY,
 };
 
 pub type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
 
-use super::{preview_generator::ScreenshotRequest, service_handler::ServiceHandler};
+/// The shared service handler of a library as passed to the request handlers.
+type HandlerData<I, S> = web::Data<RwLock<ServiceHandler<I, S>>>;
+
+use super::{
+    activity_log::ActivityLog,
+    api_keys::{require_api_key, ApiKeys},
+    authorizer::{Action, AllowAll, Authorizer, RequestContext},
+    bundle::{accepts_gzip, BundleCompression},
+    flush_registry::FlushRegistry,
+    metrics::{record_metrics, Metrics},
+    preview_generator::{PendingPreviews, ScreenshotRequest},
+    screenshot_cache::ScreenshotCache,
+    service_handler::{DerivedArtifact, ServiceHandler},
+    share_token::ShareToken,
+    startup_report::{
+        check_root_dir_writable, StartupReport, CHECK_FFMPEG, CHECK_HTTP_ADDRESS, CHECK_INDEX,
+        CHECK_ROOT_DIR, CHECK_STORAGE,
+    },
+    upload_limits::UploadLimits,
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -26,6 +58,27 @@ where
 {
     options: Options,
     phantom: PhantomData<(I, S)>,
+
+    /// The subsystems with buffered writes, which are flushed before the service stops.
+    flush_registry: FlushRegistry,
+
+    /// The token for stopping the periodic flushes.
+    flush_cancellation: CancellationToken,
+
+    /// The hook deciding whether a request may operate on a movie.
+    authorizer: Arc<dyn Authorizer>,
+
+    /// The flag marking all libraries as read-only, e.g., during a backup.
+    read_only: Arc<AtomicBool>,
+
+    /// The limits of the concurrent uploads per client across all libraries.
+    upload_limits: Arc<UploadLimits>,
+
+    /// The metrics of all libraries, which are exposed for monitoring.
+    metrics: web::Data<Metrics>,
+
+    /// The API keys granting access to the mutating endpoints of all libraries.
+    api_keys: web::Data<ApiKeys>,
 }
 
 /// The query for the GET /api/v1/movie endpoint.
@@ -34,6 +87,331 @@ struct MovieIdQuery {
     id: MovieId,
 }
 
+/// The options of the GET /api/v1/movie/search endpoint, which are passed alongside the
+/// search query.
+#[derive(Debug, Deserialize, Serialize)]
+struct SearchOptionsQuery {
+    /// If true, only the ids of the matching movies are returned.
+    #[serde(default)]
+    ids_only: bool,
+}
+
+/// The query for the GET /api/v1/movie/first-at endpoint, which is passed alongside the
+/// search query.
+#[derive(Debug, Deserialize, Serialize)]
+struct FirstAtQuery {
+    /// The letter with which the title of the movie to jump to starts.
+    letter: char,
+}
+
+/// The query for the GET /api/v1/movie/file endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct DownloadMovieQuery {
+    id: MovieId,
+
+    /// Optionally, the requested format of the movie, e.g., "mp4".
+    #[serde(default)]
+    format: Option<String>,
+}
+
+/// The query for the POST /api/v1/movie/share endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct ShareQuery {
+    id: MovieId,
+
+    /// The time-to-live of the shared URL in seconds.
+    ttl: u32,
+}
+
+/// The optional share token in the query of the download endpoints, e.g.,
+/// GET /api/v1/movie/file. A given token is checked instead of the `Authorizer`.
+#[derive(Debug, Deserialize, Serialize)]
+struct ShareTokenQuery {
+    #[serde(default)]
+    expires: Option<i64>,
+
+    #[serde(default)]
+    signature: Option<String>,
+}
+
+impl ShareTokenQuery {
+    /// Returns the share token of the query, if any.
+    fn into_token(self) -> Option<ShareToken> {
+        match (self.expires, self.signature) {
+            (Some(expires), Some(signature)) => Some(ShareToken { expires, signature }),
+            _ => None,
+        }
+    }
+}
+
+/// The body for the POST /api/v1/movie endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct NewMovie {
+    /// Optionally, the id for the new movie. If not provided, a new id is generated.
+    #[serde(default)]
+    id: Option<MovieId>,
+
+    #[serde(flatten)]
+    movie: Movie,
+}
+
+/// The query for the GET /api/v1/movie/screenshot endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct ScreenshotQuery {
+    id: MovieId,
+
+    /// Optionally, the maximal width of the screenshot.
+    w: Option<u32>,
+
+    /// Optionally, the maximal height of the screenshot.
+    h: Option<u32>,
+}
+
+/// The query for the GET /api/v1/movie/contact-sheet endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct ContactSheetQuery {
+    id: MovieId,
+
+    /// Optionally, the number of columns of the contact sheet.
+    columns: Option<u32>,
+}
+
+/// The query for the GET /api/v1/movie/bundle endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct BundleQuery {
+    id: MovieId,
+
+    /// If true, the bundle is sent as gzip-compressed file.
+    #[serde(default)]
+    compress: bool,
+}
+
+/// The query for the POST and GET /api/v1/movie/subtitle endpoints.
+#[derive(Debug, Deserialize, Serialize)]
+struct SubtitleQuery {
+    id: MovieId,
+
+    /// The language of the subtitle, e.g., "en".
+    lang: String,
+}
+
+/// The query for the POST /api/v1/movie/screenshot/revert endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct ScreenshotVersionQuery {
+    id: MovieId,
+
+    /// The version of the previous screenshot to restore.
+    version: u32,
+}
+
+/// The query for the endpoints returning a limited number of results, e.g.,
+/// GET /api/v1/stats/largest.
+#[derive(Debug, Deserialize, Serialize)]
+struct LimitQuery {
+    /// The maximal number of results to return.
+    #[serde(default = "LimitQuery::default_limit")]
+    limit: usize,
+}
+
+impl LimitQuery {
+    fn default_limit() -> usize {
+        10
+    }
+}
+
+/// The query for the GET /api/v1/maintenance/similar-images endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct SimilarImagesQuery {
+    /// The maximal Hamming distance of the screenshot hashes of similar movies.
+    #[serde(default = "SimilarImagesQuery::default_threshold")]
+    threshold: u32,
+}
+
+impl SimilarImagesQuery {
+    fn default_threshold() -> u32 {
+        10
+    }
+}
+
+/// The query for the POST /api/v1/maintenance/probe-all endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct ProbeAllQuery {
+    /// If true, the movies without video metadata are only counted.
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// The body for the POST /api/v1/maintenance/reprocess endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct ReprocessRequest {
+    /// The ids of the movies to reprocess.
+    #[serde(default)]
+    ids: Vec<MovieId>,
+
+    /// Optionally, the query selecting additional movies to reprocess.
+    #[serde(default)]
+    query: Option<MovieSearchQuery>,
+
+    /// The derived artifacts to regenerate.
+    artifacts: Vec<DerivedArtifact>,
+}
+
+/// The body for the PATCH /api/v1/movie/file/info endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct MovieFileInfoUpdate {
+    /// The new mime type of the movie file.
+    mime_type: String,
+
+    /// Optionally, the new extension of the movie file.
+    #[serde(default)]
+    extension: Option<String>,
+}
+
+/// The query for the GET /api/v1/health endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct HealthQuery {
+    /// Whether to include the report of the startup checks.
+    #[serde(default)]
+    detailed: bool,
+}
+
+/// The response of the GET /api/v1/health endpoint.
+#[derive(Debug, Serialize)]
+struct HealthResponse {
+    /// Either "ok" or "degraded" if any of the startup checks failed.
+    status: &'static str,
+
+    /// The report of the startup checks, only included in the detailed mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    startup_report: Option<StartupReport>,
+}
+
+/// The query for the POST /api/v1/movie/file endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct UploadMovieQuery {
+    id: MovieId,
+
+    /// Optionally, the upload token for cancelling the upload.
+    #[serde(default)]
+    token: Option<String>,
+}
+
+/// The query for the POST /api/v1/movie/upload/cancel endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct UploadTokenQuery {
+    token: String,
+}
+
+/// The body for the POST /api/v1/tags/rename endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct TagRename {
+    /// The tag to rename.
+    from: String,
+
+    /// The new name of the tag.
+    to: String,
+}
+
+/// The query for the GET /api/v1/tags/suggest endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct TagsQuery {
+    /// The comma-separated list of tags to get suggestions for.
+    tags: String,
+}
+
+/// The query for the GET /api/v1/movie/tags endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+struct TagSearchQuery {
+    /// Optionally, the prefix of the tags to autocomplete. Without a prefix, all tags are
+    /// returned.
+    prefix: Option<String>,
+
+    /// The maximal number of tags to return for a prefix.
+    #[serde(default = "LimitQuery::default_limit")]
+    limit: usize,
+}
+
+impl TagsQuery {
+    /// Returns the list of non-empty tags of the query.
+    fn tag_list(&self) -> Vec<String> {
+        self.tags
+            .split(',')
+            .map(|tag| tag.trim())
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| tag.to_owned())
+            .collect()
+    }
+}
+
+/// Registers the routes of a scope and keeps track of the methods supported by each path, s.t.
+/// requests to a known path with an unsupported method are answered with
+/// `405 Method Not Allowed` and the supported methods in the `Allow` header instead of `404`.
+struct RouteRegistry {
+    scope: Scope,
+
+    /// The paths of the registered routes together with their supported methods.
+    allowed_methods: Vec<(&'static str, Vec<Method>)>,
+}
+
+impl RouteRegistry {
+    /// Creates a new registry for the routes of the given scope.
+    ///
+    /// # Arguments
+    /// * `scope` - The scope to register the routes in.
+    fn new(scope: Scope) -> Self {
+        Self {
+            scope,
+            allowed_methods: Vec::new(),
+        }
+    }
+
+    /// Registers the given handler for the given path and method.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the route.
+    /// * `method` - The method of the route.
+    /// * `handler` - The handler of the route.
+    fn route<F, Args>(mut self, path: &'static str, method: Method, handler: F) -> Self
+    where
+        F: Handler<Args>,
+        Args: FromRequest + 'static,
+        F::Output: Responder + 'static,
+    {
+        self.scope = self
+            .scope
+            .route(path, web::method(method.clone()).to(handler));
+
+        match self.allowed_methods.iter_mut().find(|(p, _)| *p == path) {
+            Some((_, methods)) => methods.push(method),
+            None => self.allowed_methods.push((path, vec![method])),
+        }
+
+        self
+    }
+
+    /// Returns the scope with all registered routes. Each path additionally gets a fallback
+    /// route, which rejects all requests not matching any of the methods of the path.
+    fn finish(self) -> Scope {
+        let mut scope = self.scope;
+        for (path, methods) in self.allowed_methods {
+            let allow = methods.iter().map(|method| method.as_str()).join(", ");
+            scope = scope.route(
+                path,
+                web::route().to(move || {
+                    let allow = allow.clone();
+                    async move {
+                        HttpResponse::MethodNotAllowed()
+                            .insert_header((header::ALLOW, allow))
+                            .finish()
+                    }
+                }),
+            );
+        }
+
+        scope
+    }
+}
+
 impl<I, S> Service<I, S>
 where
     I: MoviesIndex,
@@ -47,14 +425,73 @@ where
         let options = options.clone();
         let phantom = PhantomData {};
 
-        Ok(Self { phantom, options })
+        Ok(Self {
+            phantom,
+            flush_registry: FlushRegistry::default(),
+            flush_cancellation: CancellationToken::new(),
+            authorizer: Arc::new(AllowAll),
+            read_only: Arc::new(AtomicBool::new(false)),
+            upload_limits: Arc::new(UploadLimits::new(options.max_uploads_per_client)),
+            metrics: web::Data::new(Metrics::new()?),
+            api_keys: web::Data::new(ApiKeys::new(&options.api_keys)),
+            options,
+        })
+    }
+
+    /// Returns the service with the given authorizer, which is invoked before each operation on
+    /// a single movie in any of the libraries. By default, every request is allowed.
+    ///
+    /// # Arguments
+    /// * `authorizer` - The authorizer for the operations on single movies.
+    pub fn with_authorizer(mut self, authorizer: Arc<dyn Authorizer>) -> Self {
+        self.authorizer = authorizer;
+        self
+    }
+
+    /// Returns the registry, at which subsystems with buffered writes register for being
+    /// flushed periodically and during the shutdown.
+    pub fn get_flush_registry(&self) -> FlushRegistry {
+        self.flush_registry.clone()
+    }
+
+    /// Stops the periodic flushes and flushes all buffered writes one last time. Is invoked
+    /// when the HTTP server has stopped, e.g., after receiving a termination signal.
+    pub async fn shutdown(&self) -> Result<(), Error> {
+        info!("Flushing buffered writes...");
+
+        self.flush_cancellation.cancel();
+        match self.flush_registry.flush_all().await {
+            Err(err) => {
+                error!("Flushing buffered writes...FAILED");
+                Err(err)
+            }
+            Ok(()) => {
+                info!("Flushing buffered writes...DONE");
+                Ok(())
+            }
+        }
     }
 
     /// Runs the service.
     pub async fn run(&self) -> Result<(), Error> {
         info!("Running the service...");
 
-        match self.run_http_server().await {
+        if self.options.flush_interval > 0 {
+            let flush_registry = self.flush_registry.clone();
+            let interval = Duration::from_secs(self.options.flush_interval);
+            let cancellation = self.flush_cancellation.clone();
+            tokio::spawn(async move {
+                flush_registry
+                    .run_periodic_flush(interval, cancellation)
+                    .await
+            });
+        }
+
+        // the buffered writes are flushed even if the server failed
+        let result = self.run_http_server().await;
+        let flushed = self.shutdown().await;
+
+        match result {
             Err(err) => {
                 error!("Running the service...FAILED");
                 error!("Error: {}", err);
@@ -65,34 +502,26 @@ where
             }
         }
 
-        Ok(())
+        flushed
     }
 
     /// Runs the HTTP server.
     async fn run_http_server(&self) -> Result<(), Error> {
-        let index = Arc::new(RwLock::new(I::new(&self.options)?));
-        let storage = Arc::new(RwLock::new(S::new(&self.options)?));
-
-        // create preview generator
-        let ffmpeg = FFMpeg::new(&self.options.ffmpeg).await?;
-        let preview_generator = PreviewGenerator::new(ffmpeg, index.clone(), storage.clone());
-        let preview_requests = preview_generator.get_preview_request_sender();
-
-        // spawn preview generator
-        tokio::spawn(async move {
-            let mut p = preview_generator;
-            p.run().await;
-        });
-
-        // create handler
-        let handler = self
-            .create_service_handler(index.clone(), storage.clone(), preview_requests)
-            .await?;
-        let handler = RwLock::new(handler);
-        let handler = web::Data::new(handler);
+        let mut report = StartupReport::default();
+        let startup = self.startup(&mut report).await;
+        report.log();
+        let (libraries, listener) = startup?;
+
+        let libraries: Vec<(String, HandlerData<I, S>)> = libraries
+            .into_iter()
+            .map(|(name, handler)| (name, web::Data::new(RwLock::new(handler))))
+            .collect();
+        let report = web::Data::new(report);
+        let metrics = self.metrics.clone();
+        let api_keys = self.api_keys.clone();
 
         info!("Running the HTTP server...");
-        info!("Listening on {}", self.options.http_address);
+        info!("Listening on {}", listener.local_addr()?);
 
         match HttpServer::new(move || {
             let cors = Cors::default()
@@ -100,29 +529,18 @@ where
                 .allow_any_method()
                 .allow_any_origin();
 
-            let api_v1 = web::scope("/api/v1")
-                .route("/movie", web::post().to(Self::handle_post_movie))
-                .route("/movie", web::get().to(Self::handle_get_movie))
-                .route("/movie", web::delete().to(Self::handle_delete_movie))
-                .route("/movie/search", web::get().to(Self::handle_search_movie))
-                .route("/movie/tags", web::get().to(Self::handle_get_tags))
-                .route("/movie/file", web::post().to(Self::handle_upload_movie))
-                .route("/movie/file", web::get().to(Self::handle_download_movie))
-                .route(
-                    "/movie/screenshot",
-                    web::post().to(Self::handle_upload_screenshot),
-                )
-                .route(
-                    "/movie/screenshot",
-                    web::get().to(Self::handle_download_screenshot),
-                );
-
+            // the rejected requests are recorded in the metrics and carry the CORS headers
             App::new()
+                .wrap(from_fn(require_api_key))
+                .wrap(from_fn(record_metrics))
                 .wrap(cors)
-                .app_data(handler.clone())
-                .service(api_v1)
+                .app_data(report.clone())
+                .app_data(metrics.clone())
+                .app_data(api_keys.clone())
+                .route("/metrics", web::get().to(Self::handle_get_metrics))
+                .service(Self::create_api_scope(&libraries))
         })
-        .bind(self.options.http_address)?
+        .listen(listener)?
         .run()
         .await
         {
@@ -139,20 +557,359 @@ where
         Ok(())
     }
 
+    /// Creates the scope of the API. The routes of the default library, i.e., the first of the
+    /// given libraries, are served directly under /api/v1, while every library is served
+    /// under /api/v1/library/{name}.
+    ///
+    /// # Arguments
+    /// * `libraries` - The names and service handlers of the libraries.
+    fn create_api_scope(libraries: &[(String, HandlerData<I, S>)]) -> Scope {
+        let mut api_v1 = web::scope("/api/v1");
+
+        for (name, handler) in libraries.iter() {
+            let library_scope = web::scope(&format!("/library/{}", name)).app_data(handler.clone());
+            let library_routes = Self::register_routes(RouteRegistry::new(library_scope));
+            api_v1 = api_v1.service(library_routes.finish());
+        }
+
+        if let Some((_, handler)) = libraries.first() {
+            api_v1 = api_v1.app_data(handler.clone());
+        }
+
+        let routes = RouteRegistry::new(api_v1).route("/health", Method::GET, Self::handle_health);
+        Self::register_routes(routes).finish()
+    }
+
+    /// Registers the routes operating on a single library.
+    ///
+    /// # Arguments
+    /// * `routes` - The registry of the scope to register the routes in.
+    fn register_routes(routes: RouteRegistry) -> RouteRegistry {
+        routes
+            .route("/movie", Method::POST, Self::handle_post_movie)
+            .route("/movie", Method::GET, Self::handle_get_movie)
+            .route("/movie", Method::DELETE, Self::handle_delete_movie)
+            .route("/movie", Method::PUT, Self::handle_put_movie)
+            .route("/movie/touch", Method::POST, Self::handle_touch_movie)
+            .route("/movie/feature", Method::POST, Self::handle_feature_movie)
+            .route(
+                "/movie/feature",
+                Method::DELETE,
+                Self::handle_unfeature_movie,
+            )
+            .route("/movie/share", Method::POST, Self::handle_share_movie)
+            .route("/movies", Method::POST, Self::handle_post_movies)
+            .route("/movie/preview", Method::POST, Self::handle_request_preview)
+            .route(
+                "/movie/preview",
+                Method::GET,
+                Self::handle_download_animated_preview,
+            )
+            .route("/movie/search", Method::GET, Self::handle_search_movie)
+            .route("/movie/first-at", Method::GET, Self::handle_get_first_at)
+            .route(
+                "/movie/search/faceted",
+                Method::GET,
+                Self::handle_search_movie_faceted,
+            )
+            .route("/movie/feed", Method::GET, Self::handle_get_movie_feed)
+            .route("/movie/tags", Method::GET, Self::handle_get_tags)
+            .route("/tags/suggest", Method::GET, Self::handle_suggest_tags)
+            .route("/tags/top", Method::GET, Self::handle_get_top_tags)
+            .route("/tags/rename", Method::POST, Self::handle_rename_tag)
+            .route("/tags/rename-batch", Method::POST, Self::handle_rename_tags)
+            .route(
+                "/movie/upload/start",
+                Method::POST,
+                Self::handle_start_upload,
+            )
+            .route(
+                "/movie/upload/cancel",
+                Method::POST,
+                Self::handle_cancel_upload,
+            )
+            .route("/movie/file", Method::POST, Self::handle_upload_movie)
+            .route("/movie/file", Method::GET, Self::handle_download_movie)
+            .route(
+                "/movie/file/info",
+                Method::PATCH,
+                Self::handle_update_movie_file_info,
+            )
+            .route(
+                "/movie/screenshot",
+                Method::POST,
+                Self::handle_upload_screenshot,
+            )
+            .route(
+                "/movie/screenshot",
+                Method::GET,
+                Self::handle_download_screenshot,
+            )
+            .route(
+                "/movie/contact-sheet",
+                Method::GET,
+                Self::handle_download_contact_sheet,
+            )
+            .route(
+                "/movie/screenshot/history",
+                Method::GET,
+                Self::handle_get_screenshot_history,
+            )
+            .route(
+                "/movie/screenshot/revert",
+                Method::POST,
+                Self::handle_revert_screenshot,
+            )
+            .route(
+                "/movie/subtitle",
+                Method::POST,
+                Self::handle_upload_subtitle,
+            )
+            .route(
+                "/movie/subtitle",
+                Method::GET,
+                Self::handle_download_subtitle,
+            )
+            .route("/movie/bundle", Method::GET, Self::handle_download_bundle)
+            .route("/movie/bundle", Method::POST, Self::handle_upload_bundle)
+            .route(
+                "/movies/status",
+                Method::POST,
+                Self::handle_get_movies_status,
+            )
+            .route("/activity", Method::GET, Self::handle_get_activity)
+            .route(
+                "/activity/stream",
+                Method::GET,
+                Self::handle_stream_activity,
+            )
+            .route("/stats/years", Method::GET, Self::handle_get_year_histogram)
+            .route("/stats/queries", Method::GET, Self::handle_get_query_stats)
+            .route(
+                "/stats/largest",
+                Method::GET,
+                Self::handle_get_largest_movies,
+            )
+            .route(
+                "/maintenance/ffmpeg/recheck",
+                Method::POST,
+                Self::handle_recheck_ffmpeg,
+            )
+            .route(
+                "/maintenance/similar-images",
+                Method::GET,
+                Self::handle_get_similar_images,
+            )
+            .route(
+                "/maintenance/reprocess",
+                Method::POST,
+                Self::handle_reprocess,
+            )
+            .route(
+                "/maintenance/probe-all",
+                Method::POST,
+                Self::handle_probe_all,
+            )
+            .route(
+                "/maintenance/readonly",
+                Method::POST,
+                Self::handle_enable_read_only,
+            )
+            .route(
+                "/maintenance/readonly",
+                Method::DELETE,
+                Self::handle_disable_read_only,
+            )
+    }
+
+    /// Starts the components of the service and records the outcome of each step in the given
+    /// startup report. Returns the names and service handlers of all libraries, starting with the
+    /// default library, and the listener bound to the HTTP address.
+    ///
+    /// # Arguments
+    /// * `report` - The report to record the startup checks in.
+    async fn startup(
+        &self,
+        report: &mut StartupReport,
+    ) -> Result<(Vec<(String, ServiceHandler<I, S>)>, TcpListener), Error> {
+        let root_dir = check_root_dir_writable(&self.options.root_dir).await;
+        report.add_check(CHECK_ROOT_DIR, root_dir.as_ref());
+        root_dir?;
+
+        let ffmpeg = FFMpeg::new_with_versions(&self.options.ffmpeg).await;
+        report.add_check(
+            CHECK_FFMPEG,
+            ffmpeg
+                .as_ref()
+                .map(|(_, versions)| format!("{}; {}", versions.ffmpeg, versions.ffprobe)),
+        );
+
+        // all libraries share the same budget of ffmpeg processes
+        let ffmpeg = match ffmpeg {
+            Ok((ffmpeg, _)) => Some(
+                ffmpeg
+                    .with_max_processes(self.options.max_ffmpeg_processes)
+                    .with_timeout(match self.options.ffmpeg_timeout {
+                        0 => None,
+                        secs => Some(Duration::from_secs(secs)),
+                    }),
+            ),
+            Err(err) if self.options.require_ffmpeg => return Err(err),
+            Err(_) => {
+                warn!("ffmpeg is not available, no previews will be generated");
+                None
+            }
+        };
+
+        let mut library_names = vec![DEFAULT_LIBRARY.to_owned()];
+        for name in self.options.libraries.iter() {
+            if library_names.contains(name) {
+                return Err(Error::InvalidArgument(format!(
+                    "Library '{}' is configured multiple times",
+                    name
+                )));
+            }
+
+            library_names.push(name.clone());
+        }
+
+        let mut libraries = Vec::with_capacity(library_names.len());
+        for name in library_names {
+            let handler = self.start_library(&name, ffmpeg.as_ref(), report).await?;
+            libraries.push((name, handler));
+        }
+
+        let listener = TcpListener::bind(self.options.http_address).map_err(Error::from);
+        report.add_check(
+            CHECK_HTTP_ADDRESS,
+            listener
+                .as_ref()
+                .map(|_| format!("bound to {}", self.options.http_address)),
+        );
+
+        Ok((libraries, listener?))
+    }
+
+    /// Starts the index, storage and preview generator of the given library and returns the
+    /// service handler for the library.
+    ///
+    /// # Arguments
+    /// * `library` - The name of the library.
+    /// * `ffmpeg` - The ffmpeg instance for generating previews and transcoding, if available.
+    /// * `report` - The report to record the startup checks in.
+    async fn start_library(
+        &self,
+        library: &str,
+        ffmpeg: Option<&FFMpeg>,
+        report: &mut StartupReport,
+    ) -> Result<ServiceHandler<I, S>, Error> {
+        let options = self.options.for_library(library)?;
+
+        // the checks of additional libraries are qualified by the library name
+        let check_name = |name: &str| {
+            if library == DEFAULT_LIBRARY {
+                name.to_owned()
+            } else {
+                format!("{} ({})", name, library)
+            }
+        };
+
+        let index = I::new(&options);
+        report.add_check(&check_name(CHECK_INDEX), index.as_ref().map(|_| "opened"));
+        let index = Arc::new(RwLock::new(index?));
+
+        let storage = S::new(&options);
+        report.add_check(
+            &check_name(CHECK_STORAGE),
+            storage.as_ref().map(|_| "reachable"),
+        );
+        let storage = Arc::new(RwLock::new(storage?));
+
+        let activity_log = ActivityLog::new(options.activity_log_capacity);
+        let screenshot_cache = ScreenshotCache::new(options.screenshot_memory_cache_size);
+        let (preview_requests, pending_previews) = match ffmpeg {
+            Some(ffmpeg) => {
+                // create and spawn preview generator
+                let preview_generator = PreviewGenerator::new(
+                    ffmpeg.clone(),
+                    index.clone(),
+                    storage.clone(),
+                    options.preview_queue_capacity,
+                    activity_log.clone(),
+                )
+                .with_screenshot_hashes(options.compute_screenshot_hashes)
+                .with_num_previews(options.num_previews)
+                .with_screenshot_margins(
+                    options.screenshot_skip_intro,
+                    options.screenshot_skip_outro,
+                )
+                .with_preview_position(options.preview_position)?
+                .with_screenshot_history(options.screenshot_history_size)
+                .with_animated_preview(options.animated_preview)
+                .with_queue_gauge(self.metrics.preview_queue_length(library))
+                .with_screenshot_cache(screenshot_cache.clone());
+                let preview_requests = preview_generator.get_preview_request_sender();
+                let pending_previews = preview_generator.get_pending_previews();
+
+                tokio::spawn(async move {
+                    let mut p = preview_generator;
+                    p.run().await;
+                });
+
+                (preview_requests, pending_previews)
+            }
+            None => (mpsc::channel(1).0, PendingPreviews::default()),
+        };
+
+        let handler = Self::create_service_handler(
+            &options,
+            index,
+            storage,
+            preview_requests,
+            pending_previews,
+            activity_log,
+        )
+        .await?
+        .with_authorizer(self.authorizer.clone())
+        .with_read_only_flag(self.read_only.clone())
+        .with_upload_limits(self.upload_limits.clone())
+        .with_screenshot_cache(screenshot_cache);
+        handler.warm_up_screenshot_cache().await;
+
+        Ok(match ffmpeg {
+            Some(ffmpeg) => handler.with_ffmpeg(ffmpeg.clone()),
+            None => handler,
+        })
+    }
+
     /// Creates a new instance of the service handler.
     ///
     /// # Arguments
+    /// * `options` - The options of the library served by the handler.
     /// * `index` - The movies index.
     /// * `storage` - The movie storage.
     /// * `preview_requests` - The channel to send preview requests to.
+    /// * `pending_previews` - The movies with queued or in-flight preview requests.
+    /// * `activity_log` - The log of the recent activity.
     async fn create_service_handler(
-        &self,
+        options: &Options,
         index: Arc<RwLock<I>>,
         storage: Arc<RwLock<S>>,
-        preview_requests: mpsc::UnboundedSender<ScreenshotRequest>,
+        preview_requests: mpsc::Sender<ScreenshotRequest>,
+        pending_previews: PendingPreviews,
+        activity_log: ActivityLog,
     ) -> Result<ServiceHandler<I, S>, Error> {
         info!("Creating the service handler...");
-        match ServiceHandler::new(index, storage, preview_requests).await {
+        match ServiceHandler::new(
+            options,
+            index,
+            storage,
+            preview_requests,
+            pending_previews,
+            activity_log,
+        )
+        .await
+        {
             Err(err) => {
                 error!("Creating the service handler...FAILED");
                 error!("Error: {}", err);
@@ -169,18 +926,38 @@ where
     ///
     /// # Arguments
     /// * `handler` - The service handler.
-    /// * `movie` - The movie to add.
+    /// * `new_movie` - The movie to add and optionally its id.
     async fn handle_post_movie(
         handler: web::Data<RwLock<ServiceHandler<I, S>>>,
-        movie: web::Json<Movie>,
+        new_movie: web::Json<NewMovie>,
     ) -> Result<impl Responder> {
         debug!("Handling POST /api/v1/movie");
-        trace!("Request body: {:?}", movie);
+        trace!("Request body: {:?}", new_movie);
+
+        let new_movie: NewMovie = new_movie.into_inner();
+
+        let handler = handler.read().await;
+        handler.handle_check_writable()?;
+        handler
+            .handle_add_movie(new_movie.movie, new_movie.id)
+            .await
+    }
 
-        let movie: Movie = movie.into_inner();
+    /// Handles the POST /api/v1/movies endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `movies` - The movies to add.
+    async fn handle_post_movies(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        movies: web::Json<Vec<Movie>>,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/movies");
+        trace!("Request body: {} movies", movies.len());
 
         let handler = handler.read().await;
-        handler.handle_add_movie(movie).await
+        handler.handle_check_writable()?;
+        handler.handle_add_movies(movies.into_inner()).await
     }
 
     /// Handles the GET /api/v1/movie endpoint.
@@ -188,9 +965,11 @@ where
     /// # Arguments
     /// * `handler` - The service handler.
     /// * `query` - The query parameters.
+    /// * `search_options` - The options of the search.
     async fn handle_search_movie(
         handler: web::Data<RwLock<ServiceHandler<I, S>>>,
         query: QsQuery<MovieSearchQuery>,
+        search_options: QsQuery<SearchOptionsQuery>,
     ) -> Result<impl Responder> {
         debug!("Handling GET /api/v1/movie/search");
         trace!("Request query: {:?}", query);
@@ -199,154 +978,2031 @@ where
 
         let handler = handler.read().await;
 
-        handler.handle_search_movies(query).await
+        if search_options.ids_only {
+            Ok(Either::Left(handler.handle_search_movie_ids(query).await?))
+        } else {
+            Ok(Either::Right(handler.handle_search_movies(query).await?))
+        }
     }
 
-    /// Handles the GET /api/v1/tags endpoint.
+    /// Handles the GET /api/v1/movie/first-at endpoint.
     ///
     /// # Arguments
     /// * `handler` - The service handler.
-    async fn handle_get_tags(
+    /// * `query` - The query parameters.
+    /// * `first_at` - The letter to jump to.
+    async fn handle_get_first_at(
         handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        query: QsQuery<MovieSearchQuery>,
+        first_at: QsQuery<FirstAtQuery>,
     ) -> Result<impl Responder> {
-        debug!("Handling GET /api/v1/movie/tags");
+        debug!("Handling GET /api/v1/movie/first-at");
+        trace!("Request query: {:?}, letter: {:?}", query, first_at);
 
         let handler = handler.read().await;
-
-        handler.handle_get_tags().await
+        handler
+            .handle_get_letter_offset(query.into_inner(), first_at.letter)
+            .await
     }
 
-    /// Handles the GET /api/v1/movie endpoint.
+    /// Handles the GET /api/v1/movie/search/faceted endpoint.
     ///
     /// # Arguments
     /// * `handler` - The service handler.
     /// * `query` - The query parameters.
-    async fn handle_get_movie(
+    async fn handle_search_movie_faceted(
         handler: web::Data<RwLock<ServiceHandler<I, S>>>,
-        query: web::Query<MovieIdQuery>,
+        query: QsQuery<MovieSearchQuery>,
     ) -> Result<impl Responder> {
-        debug!("Handling GET /api/v1/movie");
+        debug!("Handling GET /api/v1/movie/search/faceted");
         trace!("Request query: {:?}", query);
 
-        let id: MovieId = query.into_inner().id;
+        let query: MovieSearchQuery = query.into_inner();
 
         let handler = handler.read().await;
 
-        handler.handle_get_movie(id).await
+        handler.handle_search_movies_faceted(query).await
     }
 
-    /// Handles the DELETE /api/v1/movie endpoint.
+    /// Handles the GET /api/v1/movie/feed endpoint.
     ///
     /// # Arguments
     /// * `handler` - The service handler.
+    /// * `request` - The request, whose URL the links of the feed are based on.
     /// * `query` - The query parameters.
-    async fn handle_delete_movie(
+    async fn handle_get_movie_feed(
         handler: web::Data<RwLock<ServiceHandler<I, S>>>,
-        query: web::Query<MovieIdQuery>,
+        request: HttpRequest,
+        query: QsQuery<MovieSearchQuery>,
     ) -> Result<impl Responder> {
-        debug!("Handling DELETE /api/v1/movie");
+        debug!("Handling GET /api/v1/movie/feed");
         trace!("Request query: {:?}", query);
 
-        let id: MovieId = query.into_inner().id;
+        let query: MovieSearchQuery = query.into_inner();
+        let movies_url = {
+            let connection_info = request.connection_info();
+            format!(
+                "{}://{}{}",
+                connection_info.scheme(),
+                connection_info.host(),
+                request.path().trim_end_matches("/feed")
+            )
+        };
 
         let handler = handler.read().await;
-
-        handler.handle_delete_movie(id).await
+        handler.handle_get_movie_feed(query, &movies_url).await
     }
 
-    /// Handles the POST /api/v1/movie/file endpoint.
+    /// Handles the GET /api/v1/movie/tags endpoint.
     ///
     /// # Arguments
     /// * `handler` - The service handler.
     /// * `query` - The query parameters.
-    /// * `multipart` - The multipart data.
-    async fn handle_upload_movie(
+    async fn handle_get_tags(
         handler: web::Data<RwLock<ServiceHandler<I, S>>>,
-        query: web::Query<MovieIdQuery>,
-        multipart: Multipart,
+        query: web::Query<TagSearchQuery>,
     ) -> Result<impl Responder> {
-        debug!("Handling POST /api/v1/movie/file");
+        debug!("Handling GET /api/v1/movie/tags");
+        trace!("Request query: {:?}", query);
+
+        let handler = handler.read().await;
+
+        match &query.prefix {
+            Some(prefix) => Ok(Either::Left(
+                handler.handle_search_tags(prefix, query.limit).await?,
+            )),
+            None => Ok(Either::Right(handler.handle_get_tags().await?)),
+        }
+    }
+
+    /// Handles the GET /api/v1/tags/top endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `query` - The query parameters.
+    async fn handle_get_top_tags(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        query: web::Query<LimitQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/tags/top");
+        trace!("Request query: {:?}", query);
+
+        let handler = handler.read().await;
+
+        handler.handle_get_top_tags(query.limit).await
+    }
+
+    /// Handles the POST /api/v1/tags/rename endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `rename` - The tag to rename and its new name.
+    async fn handle_rename_tag(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        rename: web::Json<TagRename>,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/tags/rename");
+        trace!("Request body: {:?}", rename);
+
+        let handler = handler.read().await;
+        handler.handle_check_writable()?;
+        handler.handle_rename_tag(&rename.from, &rename.to).await
+    }
+
+    /// Handles the POST /api/v1/tags/rename-batch endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `renames` - The renames mapping the old tags onto the new tags.
+    async fn handle_rename_tags(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        renames: web::Json<BTreeMap<String, String>>,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/tags/rename-batch");
+        trace!("Request body: {:?}", renames);
+
+        let handler = handler.read().await;
+        handler.handle_check_writable()?;
+        handler.handle_rename_tags(&renames).await
+    }
+
+    /// Handles the GET /api/v1/tags/suggest endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `query` - The query parameters.
+    async fn handle_suggest_tags(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        query: web::Query<TagsQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/tags/suggest");
+        trace!("Request query: {:?}", query);
+
+        let handler = handler.read().await;
+
+        handler.handle_suggest_tags(&query.tag_list()).await
+    }
+
+    /// Handles the POST /api/v1/movies/status endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `ids` - The ids of the movies to report the status for.
+    async fn handle_get_movies_status(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        ids: web::Json<Vec<MovieId>>,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/movies/status");
+        trace!("Request body: {:?}", ids);
+
+        let handler = handler.read().await;
+        for id in ids.iter() {
+            handler.handle_check_access(&ctx, id, Action::Read)?;
+        }
+
+        handler.handle_get_movies_status(ids.into_inner()).await
+    }
+
+    /// Handles the GET /api/v1/stats/largest endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `query` - The query parameters.
+    async fn handle_get_largest_movies(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        query: web::Query<LimitQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/stats/largest");
+        trace!("Request query: {:?}", query);
+
+        let handler = handler.read().await;
+
+        handler.handle_get_largest_movies(query.limit).await
+    }
+
+    /// Handles the GET /api/v1/activity endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `query` - The query parameters.
+    async fn handle_get_activity(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        query: web::Query<LimitQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/activity");
+        trace!("Request query: {:?}", query);
+
+        let handler = handler.read().await;
+
+        handler.handle_get_activity(query.limit).await
+    }
+
+    /// Handles the GET /api/v1/activity/stream endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    async fn handle_stream_activity(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/activity/stream");
+
+        let handler = handler.read().await;
+
+        handler.handle_stream_activity().await
+    }
+
+    /// Handles the GET /api/v1/stats/years endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    async fn handle_get_year_histogram(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/stats/years");
+
+        let handler = handler.read().await;
+
+        handler.handle_get_year_histogram().await
+    }
+
+    /// Handles the GET /api/v1/stats/queries endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    async fn handle_get_query_stats(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/stats/queries");
+
+        let handler = handler.read().await;
+
+        handler.handle_get_query_stats().await
+    }
+
+    /// Handles the GET /api/v1/health endpoint.
+    ///
+    /// # Arguments
+    /// * `report` - The report of the startup checks.
+    /// * `query` - The query parameters.
+    async fn handle_health(
+        report: web::Data<StartupReport>,
+        query: web::Query<HealthQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/health");
+
+        let status = if report.is_ok() { "ok" } else { "degraded" };
+        let startup_report = query.detailed.then(|| report.get_ref().clone());
+
+        Ok(web::Json(HealthResponse {
+            status,
+            startup_report,
+        }))
+    }
+
+    /// Handles the GET /metrics endpoint, which exposes the metrics in the Prometheus text
+    /// format.
+    ///
+    /// # Arguments
+    /// * `metrics` - The metrics of the service.
+    async fn handle_get_metrics(metrics: web::Data<Metrics>) -> Result<impl Responder> {
+        debug!("Handling GET /metrics");
+
+        match metrics.encode() {
+            Ok(text) => Ok(HttpResponse::Ok()
+                .content_type(prometheus::TEXT_FORMAT)
+                .body(text)),
+            Err(err) => {
+                error!("Error encoding metrics: {}", err);
+                Err(actix_web::error::ErrorInternalServerError(err))
+            }
+        }
+    }
+
+    /// Handles the POST /api/v1/maintenance/ffmpeg/recheck endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    async fn handle_recheck_ffmpeg(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/maintenance/ffmpeg/recheck");
+
+        let handler = handler.read().await;
+
+        handler.handle_recheck_ffmpeg().await
+    }
+
+    /// Handles the POST /api/v1/maintenance/readonly endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    async fn handle_enable_read_only(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/maintenance/readonly");
+
+        let handler = handler.read().await;
+
+        handler.handle_set_read_only(true)
+    }
+
+    /// Handles the DELETE /api/v1/maintenance/readonly endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    async fn handle_disable_read_only(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+    ) -> Result<impl Responder> {
+        debug!("Handling DELETE /api/v1/maintenance/readonly");
+
+        let handler = handler.read().await;
+
+        handler.handle_set_read_only(false)
+    }
+
+    /// Handles the GET /api/v1/maintenance/similar-images endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `query` - The query parameters.
+    async fn handle_get_similar_images(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        query: web::Query<SimilarImagesQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/maintenance/similar-images");
+        trace!("Request query: {:?}", query);
+
+        let handler = handler.read().await;
+
+        handler.handle_get_similar_images(query.threshold).await
+    }
+
+    /// Handles the POST /api/v1/maintenance/reprocess endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `request` - The movies to reprocess and the artifacts to regenerate.
+    async fn handle_reprocess(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        request: web::Json<ReprocessRequest>,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/maintenance/reprocess");
+        trace!("Request body: {:?}", request);
+
+        let request: ReprocessRequest = request.into_inner();
+
+        let handler = handler.read().await;
+        handler.handle_check_writable()?;
+
+        handler
+            .handle_reprocess(&ctx, request.ids, request.query, request.artifacts)
+            .await
+    }
+
+    /// Handles the POST /api/v1/maintenance/probe-all endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `query` - The query parameters.
+    async fn handle_probe_all(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        query: web::Query<ProbeAllQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/maintenance/probe-all");
+        trace!("Request query: {:?}", query);
+
+        let handler = handler.read().await;
+        if !query.dry_run {
+            handler.handle_check_writable()?;
+        }
+
+        handler.handle_probe_all(query.dry_run).await
+    }
+
+    /// Handles the GET /api/v1/movie endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `query` - The query parameters.
+    async fn handle_get_movie(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        query: web::Query<MovieIdQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/movie");
         trace!("Request query: {:?}", query);
 
         let id: MovieId = query.into_inner().id;
 
         let handler = handler.read().await;
+        handler.handle_check_access(&ctx, &id, Action::Read)?;
 
-        handler.handle_upload_movie(id, multipart).await
+        handler.handle_get_movie(id).await
     }
 
-    /// Handles the GET /api/v1/movie/file endpoint.
+    /// Handles the DELETE /api/v1/movie endpoint.
     ///
     /// # Arguments
     /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
     /// * `query` - The query parameters.
-    async fn handle_download_movie(
+    async fn handle_delete_movie(
         handler: web::Data<RwLock<ServiceHandler<I, S>>>,
-        ranges: web::Header<header::Range>,
+        ctx: RequestContext,
         query: web::Query<MovieIdQuery>,
     ) -> Result<impl Responder> {
-        debug!("Handling GET /api/v1/movie/file");
+        debug!("Handling DELETE /api/v1/movie");
         trace!("Request query: {:?}", query);
 
-        let ranges: header::Range = ranges.0;
+        let id: MovieId = query.into_inner().id;
 
-        let ranges = match ranges {
-            header::Range::Bytes(ranges) => {
-                trace!("Request ranges: {:?}", ranges);
-                ranges
-            }
-            _ => {
-                error!("Invalid range header");
-                return Err(actix_web::error::ErrorRangeNotSatisfiable(
-                    "Invalid range header",
-                ));
-            }
-        };
+        let handler = handler.read().await;
+        handler.handle_check_writable()?;
+        handler.handle_check_access(&ctx, &id, Action::Delete)?;
+
+        handler.handle_delete_movie(id).await
+    }
+
+    /// Handles the PUT /api/v1/movie endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `query` - The query parameters.
+    /// * `movie` - The new title, description and tags of the movie.
+    async fn handle_put_movie(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        query: web::Query<MovieIdQuery>,
+        movie: web::Json<Movie>,
+    ) -> Result<impl Responder> {
+        debug!("Handling PUT /api/v1/movie");
+        trace!("Request query: {:?}", query);
+        trace!("Request body: {:?}", movie);
 
         let id: MovieId = query.into_inner().id;
 
         let handler = handler.read().await;
+        handler.handle_check_writable()?;
+        handler.handle_check_access(&ctx, &id, Action::Update)?;
 
-        handler.handle_download_movie(id, &ranges).await
+        handler.handle_update_movie(id, movie.into_inner()).await
     }
 
-    /// Handles the POST /api/v1/movie/screenshot endpoint.
+    /// Handles the POST /api/v1/movie/touch endpoint.
     ///
     /// # Arguments
     /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
     /// * `query` - The query parameters.
-    /// * `multipart` - The multipart data.
-    async fn handle_upload_screenshot(
+    async fn handle_touch_movie(
         handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
         query: web::Query<MovieIdQuery>,
-        multipart: Multipart,
     ) -> Result<impl Responder> {
-        debug!("Handling POST /api/v1/movie/screenshot");
+        debug!("Handling POST /api/v1/movie/touch");
         trace!("Request query: {:?}", query);
 
         let id: MovieId = query.into_inner().id;
 
         let handler = handler.read().await;
+        handler.handle_check_writable()?;
+        handler.handle_check_access(&ctx, &id, Action::Update)?;
 
-        handler.handle_upload_screenshot(id, multipart).await
+        handler.handle_touch_movie(id).await
     }
 
-    /// Handles the GET /api/v1/movie/screenshot endpoint.
+    /// Handles the POST /api/v1/movie/feature endpoint.
     ///
     /// # Arguments
     /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
     /// * `query` - The query parameters.
-    async fn handle_download_screenshot(
+    async fn handle_feature_movie(
         handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
         query: web::Query<MovieIdQuery>,
     ) -> Result<impl Responder> {
-        debug!("Handling GET /api/v1/movie/screenshot");
+        debug!("Handling POST /api/v1/movie/feature");
         trace!("Request query: {:?}", query);
 
         let id: MovieId = query.into_inner().id;
 
         let handler = handler.read().await;
+        handler.handle_check_writable()?;
+        handler.handle_check_access(&ctx, &id, Action::Update)?;
+
+        handler.handle_set_featured(id, true).await
+    }
+
+    /// Handles the DELETE /api/v1/movie/feature endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `query` - The query parameters.
+    async fn handle_unfeature_movie(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        query: web::Query<MovieIdQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling DELETE /api/v1/movie/feature");
+        trace!("Request query: {:?}", query);
+
+        let id: MovieId = query.into_inner().id;
+
+        let handler = handler.read().await;
+        handler.handle_check_writable()?;
+        handler.handle_check_access(&ctx, &id, Action::Update)?;
+
+        handler.handle_set_featured(id, false).await
+    }
+
+    /// Handles the POST /api/v1/movie/preview endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `query` - The query parameters.
+    async fn handle_request_preview(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        query: web::Query<MovieIdQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/movie/preview");
+        trace!("Request query: {:?}", query);
+
+        let id: MovieId = query.into_inner().id;
+
+        let handler = handler.read().await;
+        handler.handle_check_writable()?;
+        handler.handle_check_access(&ctx, &id, Action::Update)?;
+
+        handler.handle_request_preview(id).await
+    }
+
+    /// Handles the POST /api/v1/movie/upload/start endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `query` - The query parameters.
+    async fn handle_start_upload(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        query: web::Query<MovieIdQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/movie/upload/start");
+        trace!("Request query: {:?}", query);
+
+        let id: MovieId = query.into_inner().id;
+
+        let handler = handler.read().await;
+        handler.handle_check_writable()?;
+        handler.handle_check_access(&ctx, &id, Action::Update)?;
+
+        handler.handle_start_upload(id).await
+    }
+
+    /// Handles the POST /api/v1/movie/upload/cancel endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `query` - The query parameters.
+    async fn handle_cancel_upload(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        query: web::Query<UploadTokenQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/movie/upload/cancel");
+        trace!("Request query: {:?}", query);
+
+        let handler = handler.read().await;
+
+        handler.handle_cancel_upload(&query.token).await
+    }
+
+    /// Handles the POST /api/v1/movie/file endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `query` - The query parameters.
+    /// * `multipart` - The multipart data.
+    async fn handle_upload_movie(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        query: web::Query<UploadMovieQuery>,
+        multipart: Multipart,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/movie/file");
+        trace!("Request query: {:?}", query);
+
+        let UploadMovieQuery { id, token } = query.into_inner();
+
+        let handler = handler.read().await;
+        handler.handle_check_writable()?;
+        handler.handle_check_access(&ctx, &id, Action::Update)?;
+
+        let _upload_permit = handler.handle_acquire_upload_permit(&ctx)?;
+        handler.handle_upload_movie(id, token, multipart).await
+    }
+
+    /// Handles the PATCH /api/v1/movie/file/info endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `query` - The query parameters.
+    /// * `update` - The new movie file info.
+    async fn handle_update_movie_file_info(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        query: web::Query<MovieIdQuery>,
+        update: web::Json<MovieFileInfoUpdate>,
+    ) -> Result<impl Responder> {
+        debug!("Handling PATCH /api/v1/movie/file/info");
+        trace!("Request query: {:?}", query);
+        trace!("Request body: {:?}", update);
+
+        let id: MovieId = query.into_inner().id;
+        let update = update.into_inner();
+
+        let handler = handler.read().await;
+        handler.handle_check_writable()?;
+        handler.handle_check_access(&ctx, &id, Action::Update)?;
+
+        handler
+            .handle_update_movie_file_info(id, update.mime_type, update.extension)
+            .await
+    }
+
+    /// Handles the POST /api/v1/movie/share endpoint. The shared URL only bypasses the
+    /// `Authorizer`, as the downloads don't require an API key anyway.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `request` - The request, whose path the shared URL is based on.
+    /// * `query` - The query parameters.
+    async fn handle_share_movie(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        request: HttpRequest,
+        query: web::Query<ShareQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/movie/share");
+        trace!("Request query: {:?}", query);
+
+        let query: ShareQuery = query.into_inner();
+        let base_path = request.path().trim_end_matches("/share");
+
+        let handler = handler.read().await;
+        handler.handle_check_access(&ctx, &query.id, Action::Read)?;
+
+        handler
+            .handle_share_movie(query.id, query.ttl, base_path)
+            .await
+    }
+
+    /// Handles the GET /api/v1/movie/file endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ranges` - The requested byte ranges.
+    /// * `if_range` - The validator under which the ranges are requested, if any.
+    /// * `query` - The query parameters.
+    /// * `share_token` - The optional share token.
+    /// * `ctx` - The context of the request, which is authorized if no share token is given.
+    async fn handle_download_movie(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ranges: Option<web::Header<header::Range>>,
+        if_range: Option<web::Header<header::IfRange>>,
+        query: web::Query<DownloadMovieQuery>,
+        share_token: web::Query<ShareTokenQuery>,
+        ctx: RequestContext,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/movie/file");
+        trace!("Request query: {:?}", query);
+
+        // without a range header, the whole movie is downloaded
+        let ranges = match ranges.map(|ranges| ranges.into_inner()) {
+            Some(header::Range::Bytes(ranges)) => {
+                trace!("Request ranges: {:?}", ranges);
+                ranges
+            }
+            None => Vec::new(),
+            Some(_) => {
+                error!("Invalid range header");
+                return Err(actix_web::error::ErrorRangeNotSatisfiable(
+                    "Invalid range header",
+                ));
+            }
+        };
+
+        let query: DownloadMovieQuery = query.into_inner();
+        let if_range = if_range.map(|header| header.into_inner());
+
+        let handler = handler.read().await;
+
+        match share_token.into_inner().into_token() {
+            Some(token) => handler.handle_check_share_token(&query.id, &token)?,
+            None => handler.handle_check_access(&ctx, &query.id, Action::Read)?,
+        }
+
+        match query.format {
+            Some(format) => {
+                let response = handler
+                    .handle_download_movie_as(query.id, &format, &ranges, if_range)
+                    .await?;
+                Ok(Either::Left(response))
+            }
+            None => Ok(Either::Right(
+                handler
+                    .handle_download_movie(query.id, &ranges, if_range)
+                    .await?,
+            )),
+        }
+    }
+
+    /// Handles the POST /api/v1/movie/screenshot endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `query` - The query parameters.
+    /// * `multipart` - The multipart data.
+    async fn handle_upload_screenshot(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        query: web::Query<MovieIdQuery>,
+        multipart: Multipart,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/movie/screenshot");
+        trace!("Request query: {:?}", query);
+
+        let id: MovieId = query.into_inner().id;
+
+        let handler = handler.read().await;
+        handler.handle_check_writable()?;
+        handler.handle_check_access(&ctx, &id, Action::Update)?;
+
+        let _upload_permit = handler.handle_acquire_upload_permit(&ctx)?;
+        handler.handle_upload_screenshot(id, multipart).await
+    }
+
+    /// Handles the GET /api/v1/movie/screenshot endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `query` - The query parameters.
+    /// * `if_none_match` - The ETags of the screenshot cached by the client, if any.
+    /// * `share_token` - The optional share token.
+    /// * `ctx` - The context of the request, which is authorized if no share token is given.
+    async fn handle_download_screenshot(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        query: web::Query<ScreenshotQuery>,
+        if_none_match: Option<web::Header<header::IfNoneMatch>>,
+        share_token: web::Query<ShareTokenQuery>,
+        ctx: RequestContext,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/movie/screenshot");
+        trace!("Request query: {:?}", query);
+
+        let query: ScreenshotQuery = query.into_inner();
+        let if_none_match = if_none_match.map(|header| header.into_inner());
+
+        let handler = handler.read().await;
+
+        match share_token.into_inner().into_token() {
+            Some(token) => handler.handle_check_share_token(&query.id, &token)?,
+            None => handler.handle_check_access(&ctx, &query.id, Action::Read)?,
+        }
+
+        handler
+            .handle_download_screenshot(query.id, query.w, query.h, if_none_match)
+            .await
+    }
+
+    /// Handles the GET /api/v1/movie/contact-sheet endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `query` - The query parameters.
+    /// * `if_none_match` - The ETags of the contact sheet cached by the client, if any.
+    /// * `share_token` - The share token granting access to the movie, if any.
+    /// * `ctx` - The context of the request.
+    async fn handle_download_contact_sheet(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        query: web::Query<ContactSheetQuery>,
+        if_none_match: Option<web::Header<header::IfNoneMatch>>,
+        share_token: web::Query<ShareTokenQuery>,
+        ctx: RequestContext,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/movie/contact-sheet");
+        trace!("Request query: {:?}", query);
+
+        let query: ContactSheetQuery = query.into_inner();
+        let if_none_match = if_none_match.map(|header| header.into_inner());
+
+        let handler = handler.read().await;
+
+        match share_token.into_inner().into_token() {
+            Some(token) => handler.handle_check_share_token(&query.id, &token)?,
+            None => handler.handle_check_access(&ctx, &query.id, Action::Read)?,
+        }
+
+        handler
+            .handle_download_contact_sheet(query.id, query.columns, if_none_match)
+            .await
+    }
+
+    /// Handles the GET /api/v1/movie/preview endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `query` - The query parameters.
+    /// * `if_none_match` - The ETags of the animated preview cached by the client, if any.
+    /// * `share_token` - The share token granting access to the movie, if any.
+    /// * `ctx` - The context of the request.
+    async fn handle_download_animated_preview(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        query: web::Query<MovieIdQuery>,
+        if_none_match: Option<web::Header<header::IfNoneMatch>>,
+        share_token: web::Query<ShareTokenQuery>,
+        ctx: RequestContext,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/movie/preview");
+        trace!("Request query: {:?}", query);
+
+        let id: MovieId = query.into_inner().id;
+        let if_none_match = if_none_match.map(|header| header.into_inner());
+
+        let handler = handler.read().await;
+
+        match share_token.into_inner().into_token() {
+            Some(token) => handler.handle_check_share_token(&id, &token)?,
+            None => handler.handle_check_access(&ctx, &id, Action::Read)?,
+        }
+
+        handler
+            .handle_download_animated_preview(id, if_none_match)
+            .await
+    }
+
+    /// Handles the POST /api/v1/movie/subtitle endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `query` - The query parameters.
+    /// * `multipart` - The multipart data.
+    async fn handle_upload_subtitle(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        query: web::Query<SubtitleQuery>,
+        multipart: Multipart,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/movie/subtitle");
+        trace!("Request query: {:?}", query);
+
+        let query: SubtitleQuery = query.into_inner();
+
+        let handler = handler.read().await;
+        handler.handle_check_writable()?;
+        handler.handle_check_access(&ctx, &query.id, Action::Update)?;
+
+        let _upload_permit = handler.handle_acquire_upload_permit(&ctx)?;
+        handler
+            .handle_upload_subtitle(query.id, query.lang, multipart)
+            .await
+    }
+
+    /// Handles the GET /api/v1/movie/subtitle endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `query` - The query parameters.
+    async fn handle_download_subtitle(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        query: web::Query<SubtitleQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/movie/subtitle");
+        trace!("Request query: {:?}", query);
+
+        let query: SubtitleQuery = query.into_inner();
+
+        let handler = handler.read().await;
+        handler.handle_check_access(&ctx, &query.id, Action::Read)?;
+
+        handler.handle_download_subtitle(query.id, query.lang).await
+    }
+
+    /// Handles the GET /api/v1/movie/screenshot/history endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `query` - The query parameters.
+    async fn handle_get_screenshot_history(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        query: web::Query<MovieIdQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/movie/screenshot/history");
+        trace!("Request query: {:?}", query);
+
+        let id: MovieId = query.into_inner().id;
+
+        let handler = handler.read().await;
+        handler.handle_check_access(&ctx, &id, Action::Read)?;
+
+        handler.handle_get_screenshot_history(id).await
+    }
+
+    /// Handles the POST /api/v1/movie/screenshot/revert endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `query` - The query parameters.
+    async fn handle_revert_screenshot(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        query: web::Query<ScreenshotVersionQuery>,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/movie/screenshot/revert");
+        trace!("Request query: {:?}", query);
+
+        let query: ScreenshotVersionQuery = query.into_inner();
+
+        let handler = handler.read().await;
+        handler.handle_check_writable()?;
+        handler.handle_check_access(&ctx, &query.id, Action::Update)?;
+
+        handler
+            .handle_revert_screenshot(query.id, query.version)
+            .await
+    }
+
+    /// Handles the GET /api/v1/movie/bundle endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `request` - The request, whose accepted encodings determine the compression.
+    /// * `query` - The query parameters.
+    /// * `share_token` - The optional share token.
+    /// * `ctx` - The context of the request, which is authorized if no share token is given.
+    async fn handle_download_bundle(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        request: HttpRequest,
+        query: web::Query<BundleQuery>,
+        share_token: web::Query<ShareTokenQuery>,
+        ctx: RequestContext,
+    ) -> Result<impl Responder> {
+        debug!("Handling GET /api/v1/movie/bundle");
+        trace!("Request query: {:?}", query);
+
+        let query: BundleQuery = query.into_inner();
+        let id: MovieId = query.id;
+
+        // an explicitly requested compressed file takes precedence over the content encoding
+        let accepts_gzip = request
+            .headers()
+            .get(header::ACCEPT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(accepts_gzip);
+        let compression = if query.compress {
+            BundleCompression::GzipFile
+        } else if accepts_gzip {
+            BundleCompression::GzipEncoding
+        } else {
+            BundleCompression::None
+        };
+
+        let handler = handler.read().await;
+
+        match share_token.into_inner().into_token() {
+            Some(token) => handler.handle_check_share_token(&id, &token)?,
+            None => handler.handle_check_access(&ctx, &id, Action::Read)?,
+        }
+
+        handler.handle_download_bundle(id, compression).await
+    }
+
+    /// Handles the POST /api/v1/movie/bundle endpoint.
+    ///
+    /// # Arguments
+    /// * `handler` - The service handler.
+    /// * `ctx` - The context of the request.
+    /// * `multipart` - The multipart data.
+    async fn handle_upload_bundle(
+        handler: web::Data<RwLock<ServiceHandler<I, S>>>,
+        ctx: RequestContext,
+        multipart: Multipart,
+    ) -> Result<impl Responder> {
+        debug!("Handling POST /api/v1/movie/bundle");
+
+        let handler = handler.read().await;
+        handler.handle_check_writable()?;
+
+        let _upload_permit = handler.handle_acquire_upload_permit(&ctx)?;
+
+        handler.handle_upload_bundle(multipart).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tempdir::TempDir;
+
+    use crate::{file_storage::FileStorage, SimpleMoviesIndex};
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+
+    use super::super::api_keys::API_KEY_HEADER;
+    use super::super::flush_registry::test::BatchedCounter;
+    use super::super::share_token::ShareSigner;
+    use super::*;
+    use crate::{
+        AnimatedPreviewInfo, IndexBatch, MovieDetailed, MovieFileInfo, PreviewState, QueryStats,
+        ScreenshotInfo, SearchFacets, SubtitleInfo, VideoMetadata,
+    };
+
+    /// The number of calls of `CountingMoviesIndex::get_movie`.
+    static GET_MOVIE_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    /// A movies index counting the lookups of individual movies.
+    struct CountingMoviesIndex {
+        index: SimpleMoviesIndex,
+    }
+
+    #[async_trait]
+    impl MoviesIndex for CountingMoviesIndex {
+        fn new(options: &Options) -> Result<Self, Error> {
+            Ok(Self {
+                index: SimpleMoviesIndex::new(options)?,
+            })
+        }
+
+        async fn add_movie(&mut self, movie: Movie) -> Result<MovieId, Error> {
+            self.index.add_movie(movie).await
+        }
+
+        async fn add_movie_with_id(&mut self, id: MovieId, movie: Movie) -> Result<MovieId, Error> {
+            self.index.add_movie_with_id(id, movie).await
+        }
+
+        async fn add_movies(&mut self, movies: Vec<Movie>) -> Result<Vec<MovieId>, Error> {
+            self.index.add_movies(movies).await
+        }
+
+        async fn get_movie(&self, id: &MovieId) -> Result<MovieDetailed, Error> {
+            GET_MOVIE_CALLS.fetch_add(1, Ordering::SeqCst);
+            self.index.get_movie(id).await
+        }
+
+        async fn movie_exists(&self, id: &MovieId) -> Result<bool, Error> {
+            self.index.movie_exists(id).await
+        }
+
+        async fn update_movie_file_info(
+            &mut self,
+            id: &MovieId,
+            movie_file_info: MovieFileInfo,
+        ) -> Result<(), Error> {
+            self.index.update_movie_file_info(id, movie_file_info).await
+        }
+
+        async fn remove_movie_file_info(&mut self, id: &MovieId) -> Result<(), Error> {
+            self.index.remove_movie_file_info(id).await
+        }
+
+        async fn update_screenshot_info(
+            &mut self,
+            id: &MovieId,
+            screenshot_info: ScreenshotInfo,
+        ) -> Result<(), Error> {
+            self.index.update_screenshot_info(id, screenshot_info).await
+        }
+
+        async fn update_video_metadata(
+            &mut self,
+            id: &MovieId,
+            video_metadata: VideoMetadata,
+        ) -> Result<(), Error> {
+            self.index.update_video_metadata(id, video_metadata).await
+        }
+
+        async fn update_animated_preview_info(
+            &mut self,
+            id: &MovieId,
+            animated_preview_info: AnimatedPreviewInfo,
+        ) -> Result<(), Error> {
+            self.index
+                .update_animated_preview_info(id, animated_preview_info)
+                .await
+        }
+
+        async fn add_subtitle_info(
+            &mut self,
+            id: &MovieId,
+            subtitle_info: SubtitleInfo,
+        ) -> Result<(), Error> {
+            self.index.add_subtitle_info(id, subtitle_info).await
+        }
+
+        async fn get_subtitles(&self, id: &MovieId) -> Result<Vec<SubtitleInfo>, Error> {
+            self.index.get_subtitles(id).await
+        }
+
+        async fn update_preview_state(
+            &mut self,
+            id: &MovieId,
+            preview_state: PreviewState,
+        ) -> Result<(), Error> {
+            self.index.update_preview_state(id, preview_state).await
+        }
+
+        async fn touch_movie(&mut self, id: &MovieId) -> Result<(), Error> {
+            self.index.touch_movie(id).await
+        }
+
+        async fn set_featured(&mut self, id: &MovieId, featured: bool) -> Result<(), Error> {
+            self.index.set_featured(id, featured).await
+        }
+
+        async fn add_tags(&mut self, id: &MovieId, tags: &[String]) -> Result<(), Error> {
+            self.index.add_tags(id, tags).await
+        }
+
+        async fn update_movie(&mut self, id: &MovieId, movie: Movie) -> Result<(), Error> {
+            self.index.update_movie(id, movie).await
+        }
+
+        async fn apply_batch(&mut self, batch: IndexBatch) -> Result<(), Error> {
+            self.index.apply_batch(batch).await
+        }
+
+        async fn rename_tag(&mut self, from: &str, to: &str) -> Result<usize, Error> {
+            self.index.rename_tag(from, to).await
+        }
+
+        async fn rename_tags(
+            &mut self,
+            renames: &BTreeMap<String, String>,
+        ) -> Result<BTreeMap<String, usize>, Error> {
+            self.index.rename_tags(renames).await
+        }
+
+        async fn remove_movie(&mut self, id: &MovieId) -> Result<(), Error> {
+            self.index.remove_movie(id).await
+        }
+
+        async fn search_movies(&self, query: MovieSearchQuery) -> Result<Vec<MovieId>, Error> {
+            self.index.search_movies(query).await
+        }
+
+        async fn search_movies_count(&self, query: MovieSearchQuery) -> Result<usize, Error> {
+            self.index.search_movies_count(query).await
+        }
+
+        async fn get_tag_list_with_count(&self) -> Result<Vec<(String, usize)>, Error> {
+            self.index.get_tag_list_with_count().await
+        }
+
+        async fn get_top_tags(&self, limit: usize) -> Result<Vec<(String, usize)>, Error> {
+            self.index.get_top_tags(limit).await
+        }
+
+        async fn search_tags(
+            &self,
+            prefix: &str,
+            limit: usize,
+        ) -> Result<Vec<(String, usize)>, Error> {
+            self.index.search_tags(prefix, limit).await
+        }
+
+        async fn suggest_tags(&self, tags: &[String]) -> Result<Vec<(String, usize)>, Error> {
+            self.index.suggest_tags(tags).await
+        }
+
+        async fn get_year_histogram(&self) -> Result<Vec<(u16, usize)>, Error> {
+            self.index.get_year_histogram().await
+        }
+
+        async fn get_search_facets(&self, query: MovieSearchQuery) -> Result<SearchFacets, Error> {
+            self.index.get_search_facets(query).await
+        }
+
+        async fn update_screenshot_hash(&mut self, id: &MovieId, hash: u64) -> Result<(), Error> {
+            self.index.update_screenshot_hash(id, hash).await
+        }
+
+        async fn get_screenshot_hashes(&self) -> Result<Vec<(MovieId, u64)>, Error> {
+            self.index.get_screenshot_hashes().await
+        }
+
+        fn get_query_stats(&self) -> Option<BTreeMap<String, QueryStats>> {
+            self.index.get_query_stats()
+        }
+
+        async fn get_letter_offset(
+            &self,
+            query: MovieSearchQuery,
+            letter: char,
+        ) -> Result<usize, Error> {
+            self.index.get_letter_offset(query, letter).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.ffmpeg = root_dir.path().join("missing");
+        options.http_address = "127.0.0.1:0".parse().unwrap();
+        options.require_ffmpeg = false;
+
+        let service: Service<SimpleMoviesIndex, FileStorage> = Service::new(&options).unwrap();
+        let mut report = StartupReport::default();
+        let (libraries, _listener) = service.startup(&mut report).await.unwrap();
+
+        let libraries: Vec<(String, HandlerData<SimpleMoviesIndex, FileStorage>)> = libraries
+            .into_iter()
+            .map(|(name, handler)| (name, web::Data::new(RwLock::new(handler))))
+            .collect();
+        let api = Service::<SimpleMoviesIndex, FileStorage>::create_api_scope(&libraries);
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(from_fn(record_metrics))
+                .app_data(service.metrics.clone())
+                .route(
+                    "/metrics",
+                    web::get().to(Service::<SimpleMoviesIndex, FileStorage>::handle_get_metrics),
+                )
+                .service(api),
+        )
+        .await;
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/api/v1/movie")
+            .set_json(Movie {
+                title: "Das Boot".to_owned(),
+                description: String::new(),
+                tags: Vec::new(),
+            })
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert!(response.status().is_success());
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/api/v1/movie?id=unknown")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::NOT_FOUND);
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/metrics")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert!(response.status().is_success());
+        let body = actix_web::test::read_body(response).await;
+        let text = std::str::from_utf8(&body).unwrap();
+
+        assert!(text
+            .contains("movies_db_http_requests_total{method=\"POST\",route=\"/api/v1/movie\"} 1"));
+        assert!(text
+            .contains("movies_db_http_requests_total{method=\"GET\",route=\"/api/v1/movie\"} 1"));
+        assert!(
+            text.contains("movies_db_http_errors_total{method=\"GET\",route=\"/api/v1/movie\"} 1")
+        );
+        assert!(
+            !text.contains("movies_db_http_errors_total{method=\"POST\",route=\"/api/v1/movie\"}")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_keys() {
+        for api_keys in [vec!["secret".to_owned()], Vec::new()] {
+            let root_dir = TempDir::new("movies-db").unwrap();
+            let mut options = Options::default();
+            options.root_dir = root_dir.path().to_path_buf();
+            options.ffmpeg = root_dir.path().join("missing");
+            options.http_address = "127.0.0.1:0".parse().unwrap();
+            options.require_ffmpeg = false;
+            options.api_keys = api_keys.clone();
+
+            let service: Service<SimpleMoviesIndex, FileStorage> = Service::new(&options).unwrap();
+            let mut report = StartupReport::default();
+            let (libraries, _listener) = service.startup(&mut report).await.unwrap();
+
+            let libraries: Vec<(String, HandlerData<SimpleMoviesIndex, FileStorage>)> = libraries
+                .into_iter()
+                .map(|(name, handler)| (name, web::Data::new(RwLock::new(handler))))
+                .collect();
+            let api = Service::<SimpleMoviesIndex, FileStorage>::create_api_scope(&libraries);
+            let app = actix_web::test::init_service(
+                App::new()
+                    .wrap(from_fn(require_api_key))
+                    .app_data(service.api_keys.clone())
+                    .service(api),
+            )
+            .await;
+
+            let post_movie = |api_key: Option<&str>| {
+                let mut request = actix_web::test::TestRequest::post()
+                    .uri("/api/v1/movie")
+                    .set_json(Movie {
+                        title: "Das Boot".to_owned(),
+                        description: String::new(),
+                        tags: Vec::new(),
+                    });
+                if let Some(api_key) = api_key {
+                    request = request.insert_header((API_KEY_HEADER, api_key));
+                }
+
+                request.to_request()
+            };
+
+            if api_keys.is_empty() {
+                // without keys, the authentication is disabled
+                let response = actix_web::test::call_service(&app, post_movie(None)).await;
+                assert!(response.status().is_success());
+                continue;
+            }
+
+            let response = actix_web::test::call_service(&app, post_movie(Some("secret"))).await;
+            assert!(response.status().is_success());
+            let body = actix_web::test::read_body(response).await;
+            let id = String::from_utf8(body.to_vec()).unwrap();
+
+            for api_key in [None, Some("wrong")] {
+                let response = actix_web::test::call_service(&app, post_movie(api_key)).await;
+                assert_eq!(response.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+                let body: serde_json::Value = actix_web::test::read_body_json(response).await;
+                assert!(body["error"].is_string());
+            }
+
+            let request = actix_web::test::TestRequest::delete()
+                .uri(&format!("/api/v1/movie?id={}", id))
+                .insert_header((API_KEY_HEADER, "wrong"))
+                .to_request();
+            let response = actix_web::test::call_service(&app, request).await;
+            assert_eq!(response.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+
+            // the file info of a movie can't be changed without a key either
+            let request = actix_web::test::TestRequest::patch()
+                .uri(&format!("/api/v1/movie/file/info?id={}", id))
+                .set_json(serde_json::json!({ "extension": "mkv" }))
+                .to_request();
+            let response = actix_web::test::call_service(&app, request).await;
+            assert_eq!(response.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+
+            // reading endpoints stay open
+            let request = actix_web::test::TestRequest::get()
+                .uri(&format!("/api/v1/movie?id={}", id))
+                .to_request();
+            let response = actix_web::test::call_service(&app, request).await;
+            assert!(response.status().is_success());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_ids_only() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.ffmpeg = root_dir.path().join("missing");
+        options.http_address = "127.0.0.1:0".parse().unwrap();
+        options.require_ffmpeg = false;
+
+        let service: Service<CountingMoviesIndex, FileStorage> = Service::new(&options).unwrap();
+        let mut report = StartupReport::default();
+        let (libraries, _listener) = service.startup(&mut report).await.unwrap();
+
+        let libraries: Vec<(String, HandlerData<CountingMoviesIndex, FileStorage>)> = libraries
+            .into_iter()
+            .map(|(name, handler)| (name, web::Data::new(RwLock::new(handler))))
+            .collect();
+        let api = Service::<CountingMoviesIndex, FileStorage>::create_api_scope(&libraries);
+        let app = actix_web::test::init_service(App::new().service(api)).await;
+
+        let movies: Vec<Movie> = ["Das Boot", "Alien", "Heat"]
+            .iter()
+            .map(|title| Movie {
+                title: title.to_string(),
+                description: String::new(),
+                tags: Vec::new(),
+            })
+            .collect();
+        let request = actix_web::test::TestRequest::post()
+            .uri("/api/v1/movies")
+            .set_json(&movies)
+            .to_request();
+        let ids: Vec<MovieId> = actix_web::test::call_and_read_body_json(&app, request).await;
+
+        // the ids are returned as a bare array without looking up the individual movies
+        let calls = GET_MOVIE_CALLS.load(Ordering::SeqCst);
+        let request = actix_web::test::TestRequest::get()
+            .uri("/api/v1/movie/search?ids_only=true&sorting_field=title&sorting_order=ascending")
+            .to_request();
+        let response: serde_json::Value =
+            actix_web::test::call_and_read_body_json(&app, request).await;
+        assert_eq!(
+            response,
+            serde_json::json!([ids[1].clone(), ids[0].clone(), ids[2].clone()])
+        );
+        assert_eq!(GET_MOVIE_CALLS.load(Ordering::SeqCst), calls);
+
+        // the regular search looks up the titles
+        let request = actix_web::test::TestRequest::get()
+            .uri("/api/v1/movie/search?ids_only=false")
+            .to_request();
+        let response: serde_json::Value =
+            actix_web::test::call_and_read_body_json(&app, request).await;
+        assert_eq!(response["total"], 3);
+        assert_eq!(response["results"].as_array().unwrap().len(), 3);
+        assert!(response["results"][0]["title"].is_string());
+        assert_eq!(GET_MOVIE_CALLS.load(Ordering::SeqCst), calls + 3);
+    }
+
+    #[tokio::test]
+    async fn test_search_tags() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.ffmpeg = root_dir.path().join("missing");
+        options.http_address = "127.0.0.1:0".parse().unwrap();
+        options.require_ffmpeg = false;
+
+        let service: Service<CountingMoviesIndex, FileStorage> = Service::new(&options).unwrap();
+        let mut report = StartupReport::default();
+        let (libraries, _listener) = service.startup(&mut report).await.unwrap();
+
+        let libraries: Vec<(String, HandlerData<CountingMoviesIndex, FileStorage>)> = libraries
+            .into_iter()
+            .map(|(name, handler)| (name, web::Data::new(RwLock::new(handler))))
+            .collect();
+        let api = Service::<CountingMoviesIndex, FileStorage>::create_api_scope(&libraries);
+        let app = actix_web::test::init_service(App::new().service(api)).await;
+
+        let movies: Vec<Movie> = [
+            ("Alien", vec!["Sci-Fi", "space"]),
+            ("Interstellar", vec!["sci-fi", "space", "drama"]),
+            ("Scream", vec!["horror", "scary"]),
+        ]
+        .into_iter()
+        .map(|(title, tags)| Movie {
+            title: title.to_owned(),
+            description: String::new(),
+            tags: tags.into_iter().map(|tag| tag.to_owned()).collect(),
+        })
+        .collect();
+        let request = actix_web::test::TestRequest::post()
+            .uri("/api/v1/movies")
+            .set_json(&movies)
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert!(response.status().is_success());
+
+        let get_tags = |uri: &str| actix_web::test::TestRequest::get().uri(uri).to_request();
+
+        let response: serde_json::Value = actix_web::test::call_and_read_body_json(
+            &app,
+            get_tags("/api/v1/movie/tags?prefix=SC"),
+        )
+        .await;
+        assert_eq!(response, serde_json::json!([["sci-fi", 2], ["scary", 1]]));
+
+        let response: serde_json::Value = actix_web::test::call_and_read_body_json(
+            &app,
+            get_tags("/api/v1/movie/tags?prefix=sc&limit=1"),
+        )
+        .await;
+        assert_eq!(response, serde_json::json!([["sci-fi", 2]]));
+
+        // without a prefix, all tags are returned regardless of the limit
+        let response: serde_json::Value =
+            actix_web::test::call_and_read_body_json(&app, get_tags("/api/v1/movie/tags?limit=1"))
+                .await;
+        assert_eq!(response.as_array().unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_flushes_buffered_writes() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let service: Service<SimpleMoviesIndex, FileStorage> = Service::new(&options).unwrap();
+        let counter = Arc::new(BatchedCounter::default());
+        service.get_flush_registry().register(counter.clone());
+
+        // the increments are only buffered until the shutdown
+        counter.pending.fetch_add(5, Ordering::SeqCst);
+        assert_eq!(counter.persisted.load(Ordering::SeqCst), 0);
+
+        service.shutdown().await.unwrap();
+        assert_eq!(counter.persisted.load(Ordering::SeqCst), 5);
+        assert_eq!(counter.pending.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_startup_without_ffmpeg() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().join("data");
+        options.ffmpeg = root_dir.path().join("missing");
+        options.http_address = "127.0.0.1:0".parse().unwrap();
+        options.require_ffmpeg = false;
+
+        // a missing ffmpeg is reported, but does not prevent the startup
+        let service: Service<SimpleMoviesIndex, FileStorage> = Service::new(&options).unwrap();
+        let mut report = StartupReport::default();
+        assert!(service.startup(&mut report).await.is_ok());
+
+        assert!(!report.is_ok());
+        assert!(!report.get_check(CHECK_FFMPEG).unwrap().ok);
+        for name in [
+            CHECK_ROOT_DIR,
+            CHECK_INDEX,
+            CHECK_STORAGE,
+            CHECK_HTTP_ADDRESS,
+        ] {
+            assert!(report.get_check(name).unwrap().ok, "check {} failed", name);
+        }
+
+        // if ffmpeg is required, the startup fails
+        options.require_ffmpeg = true;
+        let service: Service<SimpleMoviesIndex, FileStorage> = Service::new(&options).unwrap();
+        let mut report = StartupReport::default();
+        assert!(service.startup(&mut report).await.is_err());
+        assert!(!report.get_check(CHECK_FFMPEG).unwrap().ok);
+    }
+
+    #[actix_web::test]
+    async fn test_libraries() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.ffmpeg = root_dir.path().join("missing");
+        options.http_address = "127.0.0.1:0".parse().unwrap();
+        options.require_ffmpeg = false;
+        options.libraries = vec!["home-videos".to_owned()];
+
+        let service: Service<SimpleMoviesIndex, FileStorage> = Service::new(&options).unwrap();
+        let mut report = StartupReport::default();
+        let (libraries, _listener) = service.startup(&mut report).await.unwrap();
+        assert!(report.get_check("index (home-videos)").unwrap().ok);
+
+        let libraries: Vec<(String, HandlerData<SimpleMoviesIndex, FileStorage>)> = libraries
+            .into_iter()
+            .map(|(name, handler)| (name, web::Data::new(RwLock::new(handler))))
+            .collect();
+        let api = Service::<SimpleMoviesIndex, FileStorage>::create_api_scope(&libraries);
+        let app = actix_web::test::init_service(App::new().service(api)).await;
+
+        // add a movie to the default and to the additional library
+        for (path, title) in [
+            ("/api/v1/movie", "Das Boot"),
+            ("/api/v1/library/home-videos/movie", "Birthday Party"),
+        ] {
+            let request = actix_web::test::TestRequest::post()
+                .uri(path)
+                .set_json(serde_json::json!({
+                    "title": title,
+                    "description": "",
+                    "tags": []
+                }))
+                .to_request();
+            let response = actix_web::test::call_service(&app, request).await;
+            assert!(response.status().is_success());
+        }
+
+        // the movies of one library don't appear in the search of another library
+        for (path, title) in [
+            ("/api/v1/movie/search", "Das Boot"),
+            ("/api/v1/library/default/movie/search", "Das Boot"),
+            ("/api/v1/library/home-videos/movie/search", "Birthday Party"),
+        ] {
+            let request = actix_web::test::TestRequest::get().uri(path).to_request();
+            let response: serde_json::Value =
+                actix_web::test::call_and_read_body_json(&app, request).await;
+            let movies = response["results"].as_array().unwrap();
+
+            assert_eq!(movies.len(), 1, "unexpected search result for {}", path);
+            assert_eq!(movies[0]["title"], title);
+        }
+
+        // unknown libraries are not served
+        let request = actix_web::test::TestRequest::get()
+            .uri("/api/v1/library/unknown/movie/search")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::NOT_FOUND);
+
+        // the library data is stored separately from the default library
+        assert!(root_dir
+            .path()
+            .join("libraries.d")
+            .join("home-videos")
+            .is_dir());
+    }
+
+    #[actix_web::test]
+    async fn test_share_movie() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.ffmpeg = root_dir.path().join("missing");
+        options.http_address = "127.0.0.1:0".parse().unwrap();
+        options.require_ffmpeg = false;
+        options.share_secret = Some("secret".to_owned());
+
+        let service: Service<SimpleMoviesIndex, FileStorage> = Service::new(&options).unwrap();
+        let mut report = StartupReport::default();
+        let (libraries, _listener) = service.startup(&mut report).await.unwrap();
+
+        let libraries: Vec<(String, HandlerData<SimpleMoviesIndex, FileStorage>)> = libraries
+            .into_iter()
+            .map(|(name, handler)| (name, web::Data::new(RwLock::new(handler))))
+            .collect();
+        let api = Service::<SimpleMoviesIndex, FileStorage>::create_api_scope(&libraries);
+        let app = actix_web::test::init_service(App::new().service(api)).await;
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/api/v1/movie")
+            .set_json(serde_json::json!({
+                "id": "das-boot",
+                "title": "Das Boot",
+                "description": "",
+                "tags": []
+            }))
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert!(response.status().is_success());
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/api/v1/movie/share?id=das-boot&ttl=3600")
+            .to_request();
+        let shared: serde_json::Value =
+            actix_web::test::call_and_read_body_json(&app, request).await;
+        let url = shared["url"].as_str().unwrap();
+        assert!(url.starts_with("/api/v1/movie/file?id=das-boot&expires="));
+
+        // the token grants access to all downloads of the movie, e.g., its bundle
+        let download = |url: String| {
+            let request = actix_web::test::TestRequest::get().uri(&url).to_request();
+            actix_web::test::call_service(&app, request)
+        };
+
+        let response = download(url.replace("/movie/file?", "/movie/bundle?")).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+
+        // a tampered signature
+        let (prefix, signature) = url.split_once("signature=").unwrap();
+        let flipped = if signature.starts_with('0') { '1' } else { '0' };
+        let tampered = format!("{}signature={}{}", prefix, flipped, &signature[1..]);
+        let response = download(tampered.replace("/movie/file?", "/movie/bundle?")).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::FORBIDDEN);
+
+        // an expired token
+        let token = ShareSigner::new("secret").sign(
+            &"das-boot".to_owned(),
+            chrono::Utc::now() - chrono::Duration::hours(1),
+        );
+        let expired = format!(
+            "/api/v1/movie/bundle?id=das-boot&expires={}&signature={}",
+            token.expires, token.signature
+        );
+        let response = download(expired).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn test_method_not_allowed() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.ffmpeg = root_dir.path().join("missing");
+        options.http_address = "127.0.0.1:0".parse().unwrap();
+        options.require_ffmpeg = false;
+        options.libraries = vec!["home-videos".to_owned()];
+
+        let service: Service<SimpleMoviesIndex, FileStorage> = Service::new(&options).unwrap();
+        let mut report = StartupReport::default();
+        let (libraries, _listener) = service.startup(&mut report).await.unwrap();
+
+        let libraries: Vec<(String, HandlerData<SimpleMoviesIndex, FileStorage>)> = libraries
+            .into_iter()
+            .map(|(name, handler)| (name, web::Data::new(RwLock::new(handler))))
+            .collect();
+        let api = Service::<SimpleMoviesIndex, FileStorage>::create_api_scope(&libraries);
+        let app = actix_web::test::init_service(App::new().service(api)).await;
+
+        for (method, path, allow) in [
+            (Method::PUT, "/api/v1/movie/search", "GET"),
+            (Method::POST, "/api/v1/movie/tags", "GET"),
+            (Method::PATCH, "/api/v1/movie", "POST, GET, DELETE, PUT"),
+            (Method::GET, "/api/v1/movie/file/info", "PATCH"),
+            (Method::DELETE, "/api/v1/health", "GET"),
+            (
+                Method::PUT,
+                "/api/v1/library/home-videos/movie/search",
+                "GET",
+            ),
+        ] {
+            let request = actix_web::test::TestRequest::default()
+                .method(method.clone())
+                .uri(path)
+                .to_request();
+            let response = actix_web::test::call_service(&app, request).await;
+            assert_eq!(
+                response.status(),
+                actix_web::http::StatusCode::METHOD_NOT_ALLOWED,
+                "unexpected status for {} {}",
+                method,
+                path
+            );
+            assert_eq!(response.headers().get(header::ALLOW).unwrap(), allow);
+        }
+
+        // the supported methods are still served and unknown paths are not found
+        let request = actix_web::test::TestRequest::get()
+            .uri("/api/v1/movie/search")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+
+        let request = actix_web::test::TestRequest::put()
+            .uri("/api/v1/unknown")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::NOT_FOUND);
+    }
+
+    /// An authorizer allowing to read movies, but denying to delete them.
+    struct ReadOnlyAuthorizer;
+
+    impl Authorizer for ReadOnlyAuthorizer {
+        fn can_access(&self, _ctx: &RequestContext, _id: &MovieId, action: Action) -> bool {
+            action != Action::Delete
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_authorizer() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.ffmpeg = root_dir.path().join("missing");
+        options.http_address = "127.0.0.1:0".parse().unwrap();
+        options.require_ffmpeg = false;
+
+        let service: Service<SimpleMoviesIndex, FileStorage> = Service::new(&options)
+            .unwrap()
+            .with_authorizer(Arc::new(ReadOnlyAuthorizer));
+        let mut report = StartupReport::default();
+        let (libraries, _listener) = service.startup(&mut report).await.unwrap();
+
+        let libraries: Vec<(String, HandlerData<SimpleMoviesIndex, FileStorage>)> = libraries
+            .into_iter()
+            .map(|(name, handler)| (name, web::Data::new(RwLock::new(handler))))
+            .collect();
+        let api = Service::<SimpleMoviesIndex, FileStorage>::create_api_scope(&libraries);
+        let app = actix_web::test::init_service(App::new().service(api)).await;
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/api/v1/movie")
+            .set_json(serde_json::json!({
+                "id": "das-boot",
+                "title": "Das Boot",
+                "description": "",
+                "tags": []
+            }))
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert!(response.status().is_success());
+
+        // reading the movie is allowed
+        let request = actix_web::test::TestRequest::get()
+            .uri("/api/v1/movie?id=das-boot")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+
+        // deleting the movie is denied
+        let request = actix_web::test::TestRequest::delete()
+            .uri("/api/v1/movie?id=das-boot")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::FORBIDDEN);
+
+        // the movie still exists
+        let request = actix_web::test::TestRequest::get()
+            .uri("/api/v1/movie?id=das-boot")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_max_uploads_per_client() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.ffmpeg = root_dir.path().join("missing");
+        options.http_address = "127.0.0.1:0".parse().unwrap();
+        options.require_ffmpeg = false;
+        options.libraries = vec!["home-videos".to_owned()];
+        options.max_uploads_per_client = 1;
+
+        let service: Service<SimpleMoviesIndex, FileStorage> = Service::new(&options).unwrap();
+        let mut report = StartupReport::default();
+        let (libraries, _listener) = service.startup(&mut report).await.unwrap();
+
+        let libraries: Vec<(String, HandlerData<SimpleMoviesIndex, FileStorage>)> = libraries
+            .into_iter()
+            .map(|(name, handler)| (name, web::Data::new(RwLock::new(handler))))
+            .collect();
+        let api = Service::<SimpleMoviesIndex, FileStorage>::create_api_scope(&libraries);
+        let app = actix_web::test::init_service(App::new().service(api)).await;
+
+        for uri in ["/api/v1/movie", "/api/v1/library/home-videos/movie"] {
+            let request = actix_web::test::TestRequest::post()
+                .uri(uri)
+                .set_json(serde_json::json!({
+                    "id": "das-boot",
+                    "title": "Das Boot",
+                    "description": "",
+                    "tags": []
+                }))
+                .to_request();
+            let response = actix_web::test::call_service(&app, request).await;
+            assert!(response.status().is_success());
+        }
+
+        let upload = |uri: &str, client: &str| {
+            let boundary = "movies-db-test-boundary";
+            let body = format!(
+                "--{boundary}\r\n\
+                 Content-Disposition: form-data; name=\"file\"; filename=\"movie.mp4\"\r\n\
+                 Content-Type: video/mp4\r\n\r\n\
+                 movie\r\n\
+                 --{boundary}--\r\n"
+            );
+
+            actix_web::test::TestRequest::post()
+                .uri(uri)
+                .peer_addr(format!("{}:4711", client).parse().unwrap())
+                .insert_header((
+                    header::CONTENT_TYPE,
+                    format!("multipart/form-data; boundary={}", boundary),
+                ))
+                .set_payload(body)
+                .to_request()
+        };
+
+        // the client already runs as many uploads as allowed in any library
+        let running_upload = service.upload_limits.try_acquire("10.0.0.1").unwrap();
+        for uri in [
+            "/api/v1/movie/file?id=das-boot",
+            "/api/v1/library/home-videos/movie/file?id=das-boot",
+        ] {
+            let response = actix_web::test::call_service(&app, upload(uri, "10.0.0.1")).await;
+            assert_eq!(
+                response.status(),
+                actix_web::http::StatusCode::TOO_MANY_REQUESTS
+            );
+        }
+
+        // other clients are not affected
+        let response = actix_web::test::call_service(
+            &app,
+            upload("/api/v1/movie/file?id=das-boot", "10.0.0.2"),
+        )
+        .await;
+        assert_ne!(
+            response.status(),
+            actix_web::http::StatusCode::TOO_MANY_REQUESTS
+        );
+
+        // the permit is released as soon as the running upload is done
+        drop(running_upload);
+        let response = actix_web::test::call_service(
+            &app,
+            upload("/api/v1/movie/file?id=das-boot", "10.0.0.1"),
+        )
+        .await;
+        assert_ne!(
+            response.status(),
+            actix_web::http::StatusCode::TOO_MANY_REQUESTS
+        );
+
+        // the finished upload has released its permit again
+        assert!(service.upload_limits.try_acquire("10.0.0.1").is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_read_only_mode() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options = Options::default();
+        options.root_dir = root_dir.path().to_path_buf();
+        options.ffmpeg = root_dir.path().join("missing");
+        options.http_address = "127.0.0.1:0".parse().unwrap();
+        options.require_ffmpeg = false;
+        options.libraries = vec!["home-videos".to_owned()];
+
+        let service: Service<SimpleMoviesIndex, FileStorage> = Service::new(&options).unwrap();
+        let mut report = StartupReport::default();
+        let (libraries, _listener) = service.startup(&mut report).await.unwrap();
+
+        let libraries: Vec<(String, HandlerData<SimpleMoviesIndex, FileStorage>)> = libraries
+            .into_iter()
+            .map(|(name, handler)| (name, web::Data::new(RwLock::new(handler))))
+            .collect();
+        let api = Service::<SimpleMoviesIndex, FileStorage>::create_api_scope(&libraries);
+        let app = actix_web::test::init_service(App::new().service(api)).await;
+
+        let post_movie = |uri: &str, id: &str| {
+            actix_web::test::TestRequest::post()
+                .uri(uri)
+                .set_json(serde_json::json!({
+                    "id": id,
+                    "title": "Das Boot",
+                    "description": "",
+                    "tags": []
+                }))
+                .to_request()
+        };
+
+        let response =
+            actix_web::test::call_service(&app, post_movie("/api/v1/movie", "das-boot")).await;
+        assert!(response.status().is_success());
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/api/v1/maintenance/readonly")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+
+        // writes are rejected in all libraries, while reads continue
+        let response =
+            actix_web::test::call_service(&app, post_movie("/api/v1/movie", "alien")).await;
+        assert_eq!(
+            response.status(),
+            actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+        let response = actix_web::test::call_service(
+            &app,
+            post_movie("/api/v1/library/home-videos/movie", "alien"),
+        )
+        .await;
+        assert_eq!(
+            response.status(),
+            actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let request = actix_web::test::TestRequest::delete()
+            .uri("/api/v1/movie?id=das-boot")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(
+            response.status(),
+            actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/api/v1/movie?id=das-boot")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+
+        // writes resume once the read-only mode is switched off
+        let request = actix_web::test::TestRequest::delete()
+            .uri("/api/v1/maintenance/readonly")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+
+        let response =
+            actix_web::test::call_service(&app, post_movie("/api/v1/movie", "alien")).await;
+        assert!(response.status().is_success());
 
-        handler.handle_download_screenshot(id).await
+        let request = actix_web::test::TestRequest::get()
+            .uri("/api/v1/movie?id=alien")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
     }
 }
@@ -0,0 +1,165 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{Error, MovieId};
+
+/// A token granting access to the downloads of a single movie until it expires.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareToken {
+    /// The time at which the token expires as seconds since the Unix epoch.
+    pub expires: i64,
+
+    /// The hex encoded HMAC signature of the movie id and the expiry.
+    pub signature: String,
+}
+
+/// Signs and verifies share tokens with a secret.
+pub struct ShareSigner {
+    secret: Vec<u8>,
+}
+
+/// Computes the HMAC-SHA256 of the given message.
+///
+/// # Arguments
+/// * `key` - The secret key.
+/// * `message` - The message to authenticate.
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Compares the given byte strings in constant time, s.t. the comparison does not reveal how
+/// many leading bytes of a forged signature are correct.
+///
+/// # Arguments
+/// * `lhs` - The first byte string.
+/// * `rhs` - The second byte string.
+pub(super) fn constant_time_eq(lhs: &[u8], rhs: &[u8]) -> bool {
+    lhs.len() == rhs.len() && lhs.iter().zip(rhs).fold(0, |acc, (l, r)| acc | (l ^ r)) == 0
+}
+
+impl ShareSigner {
+    /// Creates a new signer with the given secret.
+    ///
+    /// # Arguments
+    /// * `secret` - The secret to sign the tokens with.
+    pub fn new(secret: &str) -> Self {
+        Self {
+            secret: secret.as_bytes().to_vec(),
+        }
+    }
+
+    /// Creates a token for the given movie, which expires at the given time.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to share.
+    /// * `expires` - The time at which the token expires.
+    pub fn sign(&self, id: &MovieId, expires: DateTime<Utc>) -> ShareToken {
+        let expires = expires.timestamp();
+
+        ShareToken {
+            expires,
+            signature: self.create_signature(id, expires),
+        }
+    }
+
+    /// Checks that the given token has been signed for the given movie and has not yet expired.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie to access.
+    /// * `token` - The token to check.
+    /// * `now` - The current time.
+    pub fn verify(
+        &self,
+        id: &MovieId,
+        token: &ShareToken,
+        now: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let signature = self.create_signature(id, token.expires);
+        if !constant_time_eq(signature.as_bytes(), token.signature.as_bytes()) {
+            return Err(Error::PermissionDenied(format!(
+                "Invalid share token for movie {}",
+                id
+            )));
+        }
+
+        if token.expires < now.timestamp() {
+            return Err(Error::PermissionDenied(format!(
+                "Share token for movie {} has expired",
+                id
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the hex encoded signature of the given movie id and expiry.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the movie.
+    /// * `expires` - The expiry as seconds since the Unix epoch.
+    fn create_signature(&self, id: &MovieId, expires: i64) -> String {
+        // movie ids never contain a colon, s.t. the message is unambiguous
+        let message = format!("{}:{}", id, expires);
+
+        hmac_sha256(&self.secret, message.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256() {
+        // test case 2 of RFC 4231
+        let signature = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        let signature: String = signature.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(
+            signature,
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_share_token() {
+        let signer = ShareSigner::new("secret");
+        let id = "das-boot".to_owned();
+        let now = Utc::now();
+
+        // a valid token
+        let token = signer.sign(&id, now + Duration::hours(1));
+        assert!(signer.verify(&id, &token, now).is_ok());
+
+        // an expired token
+        let ret = signer.verify(&id, &token, now + Duration::hours(2));
+        assert!(matches!(ret, Err(Error::PermissionDenied(_))));
+
+        // a tampered signature or expiry
+        let mut tampered = token.clone();
+        tampered.signature.replace_range(0..1, "x");
+        let ret = signer.verify(&id, &tampered, now);
+        assert!(matches!(ret, Err(Error::PermissionDenied(_))));
+
+        let mut tampered = token.clone();
+        tampered.expires += 3600;
+        let ret = signer.verify(&id, &tampered, now);
+        assert!(matches!(ret, Err(Error::PermissionDenied(_))));
+
+        // a token for another movie or signed with another secret
+        let ret = signer.verify(&"alien".to_owned(), &token, now);
+        assert!(matches!(ret, Err(Error::PermissionDenied(_))));
+
+        let ret = ShareSigner::new("other").verify(&id, &token, now);
+        assert!(matches!(ret, Err(Error::PermissionDenied(_))));
+    }
+}
@@ -0,0 +1,146 @@
+use std::{fmt::Display, path::Path};
+
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use tokio::fs as tokio_fs;
+
+use crate::Error;
+
+/// The name of the check if the root directory is writable.
+pub const CHECK_ROOT_DIR: &str = "root_dir";
+
+/// The name of the check if the movies index could be opened.
+pub const CHECK_INDEX: &str = "index";
+
+/// The name of the check if the movie storage is reachable.
+pub const CHECK_STORAGE: &str = "storage";
+
+/// The name of the check if the ffmpeg and ffprobe binaries are present.
+pub const CHECK_FFMPEG: &str = "ffmpeg";
+
+/// The name of the check if the configured HTTP address could be bound.
+pub const CHECK_HTTP_ADDRESS: &str = "http_address";
+
+/// The name of the file written to check if the root directory is writable.
+const WRITE_CHECK_FILE_NAME: &str = ".movies-db-write-check";
+
+/// The result of a single check performed during startup.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StartupCheck {
+    /// The name of the check.
+    pub name: String,
+
+    /// Whether the check has been successful.
+    pub ok: bool,
+
+    /// The details of the check, e.g., a version info or an error message.
+    pub message: String,
+}
+
+/// The aggregated results of all checks performed during startup.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StartupReport {
+    pub checks: Vec<StartupCheck>,
+}
+
+impl StartupReport {
+    /// Adds the result of a check to the report.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the check.
+    /// * `result` - The result of the check, where the success value describes the outcome.
+    pub fn add_check<M: Display>(&mut self, name: &str, result: Result<M, &Error>) {
+        let (ok, message) = match result {
+            Ok(message) => (true, message.to_string()),
+            Err(err) => (false, err.to_string()),
+        };
+
+        self.checks.push(StartupCheck {
+            name: name.to_owned(),
+            ok,
+            message,
+        });
+    }
+
+    /// Returns the check with the given name, if any.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the check to return.
+    pub fn get_check(&self, name: &str) -> Option<&StartupCheck> {
+        self.checks.iter().find(|check| check.name == name)
+    }
+
+    /// Returns true if all checks have been successful.
+    pub fn is_ok(&self) -> bool {
+        self.checks.iter().all(|check| check.ok)
+    }
+
+    /// Logs a concise summary of the report.
+    pub fn log(&self) {
+        info!("Startup self-check:");
+        for check in self.checks.iter() {
+            if check.ok {
+                info!("  [OK]     {}: {}", check.name, check.message);
+            } else {
+                error!("  [FAILED] {}: {}", check.name, check.message);
+            }
+        }
+    }
+}
+
+/// Checks if the given root directory is writable by creating it, if needed, and writing and
+/// removing a small file in it.
+///
+/// # Arguments
+/// * `root_dir` - The root directory to check.
+pub async fn check_root_dir_writable(root_dir: &Path) -> Result<String, Error> {
+    tokio_fs::create_dir_all(root_dir).await.map_err(|e| {
+        Error::Internal(format!(
+            "Failed to create root directory '{}': {}",
+            root_dir.display(),
+            e
+        ))
+    })?;
+
+    let check_file = root_dir.join(WRITE_CHECK_FILE_NAME);
+    tokio_fs::write(&check_file, b"ok").await.map_err(|e| {
+        Error::Internal(format!(
+            "Root directory '{}' is not writable: {}",
+            root_dir.display(),
+            e
+        ))
+    })?;
+    tokio_fs::remove_file(&check_file).await?;
+
+    Ok(format!("'{}' is writable", root_dir.display()))
+}
+
+#[cfg(test)]
+mod test {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_startup_report() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+
+        let mut report = StartupReport::default();
+        report.add_check(
+            CHECK_ROOT_DIR,
+            check_root_dir_writable(root_dir.path()).await.as_ref(),
+        );
+        assert!(report.is_ok());
+        assert!(!root_dir.path().join(WRITE_CHECK_FILE_NAME).exists());
+
+        let err = Error::Internal("binary not found".to_owned());
+        report.add_check::<String>(CHECK_FFMPEG, Err(&err));
+        assert!(!report.is_ok());
+
+        let check = report.get_check(CHECK_FFMPEG).unwrap();
+        assert!(!check.ok);
+        assert!(check.message.contains("binary not found"));
+        assert!(report.get_check(CHECK_ROOT_DIR).unwrap().ok);
+        assert!(report.get_check("unknown").is_none());
+    }
+}
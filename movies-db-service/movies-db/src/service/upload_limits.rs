@@ -0,0 +1,114 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::authorizer::RequestContext;
+
+/// The key of the clients whose address is unknown, which share a single limit.
+const UNKNOWN_CLIENT_KEY: &str = "unknown";
+
+/// Limits the number of concurrent uploads per client, s.t. a single client can't exhaust the
+/// disk bandwidth by opening many uploads at once. Clients are identified by their IP address,
+/// i.e., all clients behind the same reverse proxy share a single limit.
+pub struct UploadLimits {
+    /// The maximal number of concurrent uploads per client, 0 disables the limit.
+    max_uploads: usize,
+
+    /// The semaphores of the clients. Semaphores without running uploads are removed lazily.
+    uploads: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+/// The permit for a single running upload, which is released as soon as it is dropped, i.e.,
+/// once the upload finishes or aborts.
+pub struct UploadPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl UploadLimits {
+    /// Creates new upload limits without any running uploads.
+    ///
+    /// # Arguments
+    /// * `max_uploads` - The maximal number of concurrent uploads per client, 0 disables the
+    ///   limit.
+    pub fn new(max_uploads: usize) -> Self {
+        Self {
+            max_uploads,
+            uploads: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the key identifying the client of the given request.
+    ///
+    /// # Arguments
+    /// * `ctx` - The context of the request.
+    pub fn client_key(ctx: &RequestContext) -> String {
+        match ctx.peer_addr {
+            Some(addr) => addr.ip().to_string(),
+            None => UNKNOWN_CLIENT_KEY.to_owned(),
+        }
+    }
+
+    /// Tries to start an upload of the given client without waiting. Returns none if the client
+    /// already runs the maximal number of concurrent uploads.
+    ///
+    /// # Arguments
+    /// * `client` - The key identifying the client.
+    pub fn try_acquire(&self, client: &str) -> Option<UploadPermit> {
+        if self.max_uploads == 0 {
+            return Some(UploadPermit { _permit: None });
+        }
+
+        let semaphore = {
+            let mut uploads = self.uploads.lock().unwrap();
+            match uploads.get(client) {
+                Some(semaphore) => semaphore.clone(),
+                None => {
+                    // only the map itself refers to semaphores without running uploads
+                    uploads.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+
+                    let semaphore = Arc::new(Semaphore::new(self.max_uploads));
+                    uploads.insert(client.to_owned(), semaphore.clone());
+                    semaphore
+                }
+            }
+        };
+
+        semaphore
+            .try_acquire_owned()
+            .ok()
+            .map(|permit| UploadPermit {
+                _permit: Some(permit),
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_upload_limits() {
+        let limits = UploadLimits::new(2);
+
+        let first = limits.try_acquire("10.0.0.1").unwrap();
+        let _second = limits.try_acquire("10.0.0.1").unwrap();
+        assert!(limits.try_acquire("10.0.0.1").is_none());
+
+        // other clients have their own limit
+        let _other = limits.try_acquire("10.0.0.2").unwrap();
+
+        // a finished upload releases its permit
+        drop(first);
+        assert!(limits.try_acquire("10.0.0.1").is_some());
+
+        // without a limit, every upload is accepted
+        let limits = UploadLimits::new(0);
+        let permits: Vec<UploadPermit> = (0..16)
+            .map(|_| limits.try_acquire("10.0.0.1").unwrap())
+            .collect();
+        assert_eq!(permits.len(), 16);
+    }
+}
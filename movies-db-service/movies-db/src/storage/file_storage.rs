@@ -1,4 +1,4 @@
-use std::{fs, path::PathBuf};
+use std::{cmp::Reverse, fs, io, path::PathBuf, time::SystemTime};
 
 use async_trait::async_trait;
 use log::{info, trace};
@@ -8,6 +8,41 @@ use crate::{Error, MovieId, Options, ReadResource};
 
 use super::movies_storage::{MovieDataType, MovieStorage};
 
+/// The file name prefix of resized screenshots.
+const RESIZED_SCREENSHOT_PREFIX: &str = "preview_";
+
+/// The file name prefix of the previous screenshots in the screenshot history.
+const SCREENSHOT_HISTORY_PREFIX: &str = "screenshot_history_";
+
+/// The file name prefix of movie files.
+const MOVIE_FILE_PREFIX: &str = "movie.";
+
+/// Creates the error for a failed file system operation with the given description. Running out
+/// of disk space is reported as insufficient storage, all other failures as internal errors.
+///
+/// # Arguments
+/// * `description` - The description of the failed operation.
+/// * `err` - The error of the operation.
+fn storage_error(description: String, err: io::Error) -> Error {
+    if err.kind() == io::ErrorKind::StorageFull {
+        Error::InsufficientStorage(format!("{}: {}", description, err))
+    } else {
+        Error::Internal(format!("{}: {}", description, err))
+    }
+}
+
+/// Returns true if the given subtitle language is safe to use in a file name, i.e., it consists
+/// of ASCII alphanumeric characters and dashes only, e.g., "pt-BR".
+///
+/// # Arguments
+/// * `language` - The language to check.
+fn is_valid_language(language: &str) -> bool {
+    !language.is_empty()
+        && language
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
 pub struct FileStorage {
     root_dir: PathBuf,
 }
@@ -17,6 +52,10 @@ impl ReadResource for tokio_fs::File {
     async fn get_size(&self) -> usize {
         self.metadata().await.map(|m| m.len() as usize).unwrap_or(0)
     }
+
+    async fn get_last_modified(&self) -> Option<SystemTime> {
+        self.metadata().await.and_then(|m| m.modified()).ok()
+    }
 }
 
 #[async_trait]
@@ -49,11 +88,13 @@ impl MovieStorage for FileStorage {
         tokio_fs::create_dir_all(&movie_data_path)
             .await
             .map_err(|e| {
-                Error::Internal(format!(
-                    "Failed to create movie data directory '{}': {}",
-                    movie_data_path.display(),
-                    e
-                ))
+                storage_error(
+                    format!(
+                        "Failed to create movie data directory '{}'",
+                        movie_data_path.display()
+                    ),
+                    e,
+                )
             })?;
 
         info!("Created movie data directory '{}'", id);
@@ -61,20 +102,49 @@ impl MovieStorage for FileStorage {
         Ok(())
     }
 
+    async fn allocate_movies_data(&self, ids: &[MovieId]) -> Result<(), Error> {
+        // the root directory exists, s.t. each directory can be created without checking its
+        // ancestors
+        for id in ids {
+            let movie_data_path = self.get_movie_data_path(id);
+            match tokio_fs::create_dir(&movie_data_path).await {
+                Err(e) if e.kind() != io::ErrorKind::AlreadyExists => {
+                    return Err(storage_error(
+                        format!(
+                            "Failed to create movie data directory '{}'",
+                            movie_data_path.display()
+                        ),
+                        e,
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        info!("Created {} movie data directories", ids.len());
+
+        Ok(())
+    }
+
     async fn write_movie_data(
         &self,
         id: MovieId,
         data_type: MovieDataType,
     ) -> Result<Self::W, Error> {
+        let is_screenshot = matches!(data_type, MovieDataType::ScreenshotData { index: 0, .. });
         let file_path = self.get_file_path(&id, data_type, true).await?;
         trace!("Writing movie data to '{}'", file_path.display());
 
+        // resized screenshots are derived from the screenshot and thus are outdated now
+        if is_screenshot {
+            self.remove_resized_screenshots(&id).await?;
+        }
+
         let file = tokio_fs::File::create(&file_path).await.map_err(|e| {
-            Error::Internal(format!(
-                "Failed to create file '{}': {}",
-                file_path.display(),
-                e
-            ))
+            storage_error(
+                format!("Failed to create file '{}'", file_path.display()),
+                e,
+            )
         })?;
 
         Ok(file)
@@ -99,6 +169,133 @@ impl MovieStorage for FileStorage {
         Ok(file)
     }
 
+    async fn rename_movie_data(
+        &self,
+        id: MovieId,
+        from: MovieDataType,
+        to: MovieDataType,
+    ) -> Result<(), Error> {
+        let from_path = self.get_file_path(&id, from, false).await?;
+        let to_path = self.get_file_path(&id, to, false).await?;
+        trace!(
+            "Rename movie data '{}' to '{}'",
+            from_path.display(),
+            to_path.display()
+        );
+
+        if tokio_fs::metadata(&from_path).await.is_err() {
+            return Err(Error::NotFound(format!(
+                "File '{}' does not exist",
+                from_path.display()
+            )));
+        }
+
+        if tokio_fs::metadata(&to_path).await.is_ok() {
+            return Err(Error::AlreadyExists(format!(
+                "File '{}' already exists",
+                to_path.display()
+            )));
+        }
+
+        tokio_fs::rename(&from_path, &to_path).await.map_err(|e| {
+            Error::Internal(format!(
+                "Failed to rename file '{}' to '{}': {}",
+                from_path.display(),
+                to_path.display(),
+                e
+            ))
+        })?;
+
+        Ok(())
+    }
+
+    async fn remove_movie_file(&self, id: MovieId, data_type: MovieDataType) -> Result<(), Error> {
+        let file_path = self.get_file_path(&id, data_type, false).await?;
+        trace!("Remove movie file '{}'", file_path.display());
+
+        tokio_fs::remove_file(&file_path).await.map_err(|e| {
+            Error::Internal(format!(
+                "Failed to remove file '{}': {}",
+                file_path.display(),
+                e
+            ))
+        })?;
+
+        Ok(())
+    }
+
+    async fn invalidate_resized_screenshots(&self, id: MovieId) -> Result<(), Error> {
+        self.remove_resized_screenshots(&id).await
+    }
+
+    async fn archive_screenshot(
+        &self,
+        id: MovieId,
+        ext: &str,
+        history_size: usize,
+    ) -> Result<(), Error> {
+        if history_size == 0 {
+            return Ok(());
+        }
+
+        self.move_screenshot_to_history(&id, ext).await?;
+        self.prune_screenshot_history(&id, history_size).await
+    }
+
+    async fn get_screenshot_history(&self, id: MovieId) -> Result<Vec<(u32, String)>, Error> {
+        self.list_screenshot_history(&id).await
+    }
+
+    async fn restore_screenshot(
+        &self,
+        id: MovieId,
+        version: u32,
+        ext: Option<&str>,
+        history_size: usize,
+    ) -> Result<String, Error> {
+        let history = self.list_screenshot_history(&id).await?;
+        let restored_ext = match history.into_iter().find(|(v, _)| *v == version) {
+            Some((_, ext)) => ext,
+            None => {
+                return Err(Error::NotFound(format!(
+                    "Screenshot version {} of movie '{}' not found",
+                    version, id
+                )));
+            }
+        };
+
+        if let Some(ext) = ext {
+            self.move_screenshot_to_history(&id, ext).await?;
+        }
+
+        // a stale screenshot with the extension of the restored one would block the rename
+        let screenshot = |ext: &str| MovieDataType::ScreenshotData {
+            ext: ext.to_owned(),
+            index: 0,
+        };
+        let screenshot_path = self
+            .get_file_path(&id, screenshot(&restored_ext), false)
+            .await?;
+        if tokio_fs::metadata(&screenshot_path).await.is_ok() {
+            tokio_fs::remove_file(&screenshot_path).await?;
+        }
+
+        self.rename_movie_data(
+            id.clone(),
+            MovieDataType::ScreenshotHistoryData {
+                ext: restored_ext.clone(),
+                version,
+            },
+            screenshot(&restored_ext),
+        )
+        .await?;
+
+        self.remove_resized_screenshots(&id).await?;
+        self.prune_screenshot_history(&id, history_size).await?;
+
+        Ok(restored_ext)
+    }
+
     async fn remove_movie_data(&self, id: MovieId) -> Result<(), Error> {
         let movie_data_path = self.get_movie_data_path(&id);
         trace!("Remove movie data '{}'", movie_data_path.display());
@@ -140,7 +337,8 @@ impl FileStorage {
         file_path
     }
 
-    /// Returns the file path for the given movie id and data type.
+    /// Returns the file path for the given movie id and data type. Each data type is mapped to a
+    /// distinct file name, s.t. the artifacts of a movie never collide.
     ///
     /// # Arguments
     /// * `id` - The movie id for which to return the file path.
@@ -157,25 +355,211 @@ impl FileStorage {
         // make sure the root directory exists
         if create_dir {
             tokio_fs::create_dir_all(&file_path).await.map_err(|e| {
-                Error::Internal(format!(
-                    "Failed to create root directory '{}': {}",
-                    file_path.display(),
-                    e
-                ))
+                storage_error(
+                    format!("Failed to create root directory '{}'", file_path.display()),
+                    e,
+                )
             })?;
         }
 
         match data_type {
             MovieDataType::MovieData { ext } => {
-                file_path.push(format!("movie.{}", ext));
+                file_path.push(format!("{}{}", MOVIE_FILE_PREFIX, ext));
             }
-            MovieDataType::ScreenshotData { ext } => {
+            MovieDataType::ScreenshotData { ext, index: 0 } => {
                 file_path.push(format!("preview.{}", ext));
             }
+            MovieDataType::ScreenshotData { ext, index } => {
+                file_path.push(format!("screenshot_{}.{}", index, ext));
+            }
+            MovieDataType::ScreenshotHistoryData { ext, version } => {
+                file_path.push(format!("{}{}.{}", SCREENSHOT_HISTORY_PREFIX, version, ext));
+            }
+            MovieDataType::ResizedScreenshotData { ext, width, height } => {
+                file_path.push(format!(
+                    "{}{}x{}.{}",
+                    RESIZED_SCREENSHOT_PREFIX, width, height, ext
+                ));
+            }
+            MovieDataType::ThumbnailData { ext, index } => {
+                file_path.push(format!("thumbnail_{}.{}", index, ext));
+            }
+            MovieDataType::PosterData { ext } => {
+                file_path.push(format!("poster.{}", ext));
+            }
+            MovieDataType::AnimatedPreviewData { ext } => {
+                file_path.push(format!("animated_preview.{}", ext));
+            }
+            MovieDataType::SpriteSheetData { ext } => {
+                file_path.push(format!("sprites.{}", ext));
+            }
+            MovieDataType::SubtitleData { ext, language } => {
+                // the language ends up in the file name and thus must not contain a separator
+                if !is_valid_language(&language) {
+                    return Err(Error::InvalidArgument(format!(
+                        "Invalid subtitle language '{}'",
+                        language
+                    )));
+                }
+
+                file_path.push(format!("subtitle_{}.{}", language, ext));
+            }
+            MovieDataType::TranscodedData { ext } => {
+                file_path.push(format!("transcoded.{}", ext));
+            }
         }
 
         Ok(file_path)
     }
+
+    /// Returns the versions and file extensions of the screenshots in the screenshot history of
+    /// the given movie id, the most recent version first.
+    ///
+    /// # Arguments
+    /// * `id` - The movie id for which to list the screenshot history.
+    async fn list_screenshot_history(&self, id: &MovieId) -> Result<Vec<(u32, String)>, Error> {
+        let movie_data_path = self.get_movie_data_path(id);
+
+        // movies without any stored data have no history
+        let mut entries = match tokio_fs::read_dir(&movie_data_path).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(Error::Internal(format!(
+                    "Failed to read movie data directory '{}': {}",
+                    movie_data_path.display(),
+                    e
+                )));
+            }
+        };
+
+        let mut history: Vec<(u32, String)> = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let file_name = entry.file_name();
+            let version_and_ext = file_name
+                .to_str()
+                .and_then(|name| name.strip_prefix(SCREENSHOT_HISTORY_PREFIX))
+                .and_then(|name| name.split_once('.'));
+
+            if let Some((version, ext)) = version_and_ext {
+                if let Ok(version) = version.parse::<u32>() {
+                    history.push((version, ext.to_owned()));
+                }
+            }
+        }
+
+        history.sort_by_key(|(version, _)| Reverse(*version));
+
+        Ok(history)
+    }
+
+    /// Moves the main screenshot with the given extension of the given movie id into the
+    /// screenshot history as its most recent version, if the screenshot exists.
+    ///
+    /// # Arguments
+    /// * `id` - The movie id for which to move the screenshot.
+    /// * `ext` - The file extension of the main screenshot.
+    async fn move_screenshot_to_history(&self, id: &MovieId, ext: &str) -> Result<(), Error> {
+        let screenshot = MovieDataType::ScreenshotData {
+            ext: ext.to_owned(),
+            index: 0,
+        };
+        let screenshot_path = self.get_file_path(id, screenshot, false).await?;
+        if tokio_fs::metadata(&screenshot_path).await.is_err() {
+            return Ok(());
+        }
+
+        let version = match self.list_screenshot_history(id).await?.first() {
+            Some((version, _)) => version + 1,
+            None => 1,
+        };
+        let history_path = self
+            .get_file_path(
+                id,
+                MovieDataType::ScreenshotHistoryData {
+                    ext: ext.to_owned(),
+                    version,
+                },
+                false,
+            )
+            .await?;
+        trace!(
+            "Archive screenshot '{}' as '{}'",
+            screenshot_path.display(),
+            history_path.display()
+        );
+
+        tokio_fs::rename(&screenshot_path, &history_path)
+            .await
+            .map_err(|e| {
+                storage_error(
+                    format!(
+                        "Failed to archive screenshot '{}'",
+                        screenshot_path.display()
+                    ),
+                    e,
+                )
+            })?;
+
+        Ok(())
+    }
+
+    /// Removes the oldest screenshots of the screenshot history of the given movie id, s.t. at
+    /// most the given number of screenshots remain.
+    ///
+    /// # Arguments
+    /// * `id` - The movie id for which to prune the screenshot history.
+    /// * `history_size` - The maximal number of screenshots in the history.
+    async fn prune_screenshot_history(
+        &self,
+        id: &MovieId,
+        history_size: usize,
+    ) -> Result<(), Error> {
+        let history = self.list_screenshot_history(id).await?;
+        for (version, ext) in history.into_iter().skip(history_size) {
+            let file_path = self
+                .get_file_path(
+                    id,
+                    MovieDataType::ScreenshotHistoryData { ext, version },
+                    false,
+                )
+                .await?;
+            trace!("Remove previous screenshot '{}'", file_path.display());
+            tokio_fs::remove_file(&file_path).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes all resized screenshots of the given movie id.
+    ///
+    /// # Arguments
+    /// * `id` - The movie id for which to remove the resized screenshots.
+    async fn remove_resized_screenshots(&self, id: &MovieId) -> Result<(), Error> {
+        let movie_data_path = self.get_movie_data_path(id);
+
+        let mut entries = tokio_fs::read_dir(&movie_data_path).await.map_err(|e| {
+            Error::Internal(format!(
+                "Failed to read movie data directory '{}': {}",
+                movie_data_path.display(),
+                e
+            ))
+        })?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let is_resized_screenshot = entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with(RESIZED_SCREENSHOT_PREFIX));
+
+            if is_resized_screenshot {
+                trace!("Remove resized screenshot '{}'", entry.path().display());
+                tokio_fs::remove_file(entry.path()).await?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -263,6 +647,7 @@ mod test {
                     id0.clone(),
                     MovieDataType::ScreenshotData {
                         ext: "png".to_string(),
+                        index: 0,
                     },
                 )
                 .await
@@ -312,6 +697,7 @@ mod test {
                     id0.clone(),
                     MovieDataType::ScreenshotData {
                         ext: "png".to_string(),
+                        index: 0,
                     },
                 )
                 .await
@@ -342,9 +728,179 @@ mod test {
                 id0.clone(),
                 MovieDataType::ScreenshotData {
                     ext: "png".to_string(),
+                    index: 0,
                 }
             )
             .await
             .is_err());
     }
+
+    #[tokio::test]
+    async fn test_write_all_data_types() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options: Options = Default::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let storage = FileStorage::new(&options).unwrap();
+
+        let id0 = generate_movie_id();
+        let data_types = || {
+            let ext = |ext: &str| ext.to_string();
+            vec![
+                MovieDataType::MovieData { ext: ext("mp4") },
+                MovieDataType::ScreenshotData {
+                    ext: ext("png"),
+                    index: 0,
+                },
+                MovieDataType::ScreenshotHistoryData {
+                    ext: ext("png"),
+                    version: 1,
+                },
+                MovieDataType::ResizedScreenshotData {
+                    ext: ext("png"),
+                    width: 320,
+                    height: 200,
+                },
+                MovieDataType::ThumbnailData {
+                    ext: ext("png"),
+                    index: 0,
+                },
+                MovieDataType::ThumbnailData {
+                    ext: ext("png"),
+                    index: 1,
+                },
+                MovieDataType::PosterData { ext: ext("png") },
+                MovieDataType::AnimatedPreviewData { ext: ext("webp") },
+                MovieDataType::SpriteSheetData { ext: ext("png") },
+                MovieDataType::SubtitleData {
+                    ext: ext("vtt"),
+                    language: ext("en"),
+                },
+                MovieDataType::SubtitleData {
+                    ext: ext("vtt"),
+                    language: ext("pt-BR"),
+                },
+                MovieDataType::TranscodedData { ext: ext("mp4") },
+            ]
+        };
+
+        // the resized screenshot is written after the screenshot, which would remove it
+        for (i, data_type) in data_types().into_iter().enumerate() {
+            let mut w = storage
+                .write_movie_data(id0.clone(), data_type)
+                .await
+                .unwrap();
+            w.write_all(format!("data {}", i).as_bytes()).await.unwrap();
+        }
+
+        // each data type is stored in its own file, s.t. none has been overwritten
+        for (i, data_type) in data_types().into_iter().enumerate() {
+            let mut r = storage
+                .read_movie_data(id0.clone(), data_type)
+                .await
+                .unwrap();
+            let mut s = String::new();
+            r.read_to_string(&mut s).await.unwrap();
+            assert_eq!(s, format!("data {}", i));
+        }
+
+        let num_files = std::fs::read_dir(root_dir.path().join(&id0))
+            .unwrap()
+            .count();
+        assert_eq!(num_files, data_types().len());
+
+        // the languages of subtitles must not escape the movie data directory
+        for language in ["../en", "", "en/us"] {
+            assert!(matches!(
+                storage
+                    .write_movie_data(
+                        id0.clone(),
+                        MovieDataType::SubtitleData {
+                            ext: "vtt".to_string(),
+                            language: language.to_string(),
+                        },
+                    )
+                    .await,
+                Err(Error::InvalidArgument(_))
+            ));
+        }
+    }
+
+    /// Reads the main screenshot of the given movie id as string.
+    async fn read_screenshot(storage: &FileStorage, id: &MovieId) -> String {
+        let mut r = storage
+            .read_movie_data(
+                id.clone(),
+                MovieDataType::ScreenshotData {
+                    ext: "png".to_string(),
+                    index: 0,
+                },
+            )
+            .await
+            .unwrap();
+        let mut s = String::new();
+        r.read_to_string(&mut s).await.unwrap();
+        s
+    }
+
+    #[tokio::test]
+    async fn test_screenshot_history() {
+        let root_dir = TempDir::new("movies-db").unwrap();
+        let mut options: Options = Default::default();
+        options.root_dir = root_dir.path().to_path_buf();
+
+        let storage = FileStorage::new(&options).unwrap();
+
+        let id0 = generate_movie_id();
+        let screenshot = || MovieDataType::ScreenshotData {
+            ext: "png".to_string(),
+            index: 0,
+        };
+
+        // without any screenshot, the history is empty
+        assert!(storage
+            .get_screenshot_history(id0.clone())
+            .await
+            .unwrap()
+            .is_empty());
+
+        // only the two most recently replaced screenshots are kept
+        for i in 1..=4 {
+            storage
+                .archive_screenshot(id0.clone(), "png", 2)
+                .await
+                .unwrap();
+            let mut w = storage
+                .write_movie_data(id0.clone(), screenshot())
+                .await
+                .unwrap();
+            w.write_all(format!("screenshot {}", i).as_bytes())
+                .await
+                .unwrap();
+            w.flush().await.unwrap();
+        }
+
+        let history = storage.get_screenshot_history(id0.clone()).await.unwrap();
+        assert_eq!(history, [(3, "png".to_string()), (2, "png".to_string())]);
+
+        // restoring a screenshot archives the replaced one
+        let ext = storage
+            .restore_screenshot(id0.clone(), 2, Some("png"), 2)
+            .await
+            .unwrap();
+        assert_eq!(ext, "png");
+        assert_eq!(read_screenshot(&storage, &id0).await, "screenshot 2");
+
+        let history = storage.get_screenshot_history(id0.clone()).await.unwrap();
+        assert_eq!(history, [(4, "png".to_string()), (3, "png".to_string())]);
+
+        // unknown versions can't be restored
+        assert!(matches!(
+            storage
+                .restore_screenshot(id0.clone(), 2, Some("png"), 2)
+                .await,
+            Err(Error::NotFound(_))
+        ));
+        assert_eq!(read_screenshot(&storage, &id0).await, "screenshot 2");
+    }
 }
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::SystemTime};
 
 use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite};
 
@@ -15,6 +15,58 @@ pub enum MovieDataType {
     ScreenshotData {
         /// The file extension of the screenshot data.
         ext: String,
+
+        /// The index of the screenshot among the screenshots of the movie, where index 0 is the
+        /// main screenshot.
+        index: u32,
+    },
+    ScreenshotHistoryData {
+        /// The file extension of the previous screenshot data.
+        ext: String,
+
+        /// The version of the previous screenshot, where greater versions have been replaced
+        /// more recently.
+        version: u32,
+    },
+    ResizedScreenshotData {
+        /// The file extension of the resized screenshot data.
+        ext: String,
+
+        /// The maximal width of the resized screenshot.
+        width: u32,
+
+        /// The maximal height of the resized screenshot.
+        height: u32,
+    },
+    ThumbnailData {
+        /// The file extension of the thumbnail data.
+        ext: String,
+
+        /// The index of the thumbnail among the thumbnails of the movie.
+        index: u32,
+    },
+    PosterData {
+        /// The file extension of the poster data.
+        ext: String,
+    },
+    AnimatedPreviewData {
+        /// The file extension of the animated preview data.
+        ext: String,
+    },
+    SpriteSheetData {
+        /// The file extension of the sprite sheet data.
+        ext: String,
+    },
+    SubtitleData {
+        /// The file extension of the subtitle data, e.g., "vtt".
+        ext: String,
+
+        /// The language of the subtitles, e.g., "en" or "pt-BR".
+        language: String,
+    },
+    TranscodedData {
+        /// The file extension of the transcoded movie data, i.e., the target format.
+        ext: String,
     },
 }
 
@@ -22,6 +74,9 @@ pub enum MovieDataType {
 #[async_trait]
 pub trait ReadResource: AsyncRead + AsyncSeek + Unpin + 'static {
     async fn get_size(&self) -> usize;
+
+    /// Returns the time of the last modification of the data, if known.
+    async fn get_last_modified(&self) -> Option<SystemTime>;
 }
 
 /// The trait for storing movie data.
@@ -44,7 +99,14 @@ pub trait MovieStorage: Send + Sync {
     /// * `id` - The movie id for which to allocate the data.
     async fn allocate_movie_data(&self, id: MovieId) -> Result<(), Error>;
 
+    /// Allocates space for the given new movies at once.
+    ///
+    /// # Arguments
+    /// * `ids` - The movie ids for which to allocate the data.
+    async fn allocate_movies_data(&self, ids: &[MovieId]) -> Result<(), Error>;
+
     /// Returns a writer for the given movie id and data type to store the data.
+    /// Writing new screenshot data invalidates all resized variants of the screenshot.
     ///
     /// # Arguments
     /// * `id` - The movie id for which to store the data.
@@ -55,6 +117,75 @@ pub trait MovieStorage: Send + Sync {
         data_type: MovieDataType,
     ) -> Result<Self::W, Error>;
 
+    /// Renames the data of the given movie id from one data type to another, e.g., to change the
+    /// file extension of the movie data without rewriting it.
+    /// Fails if the source data does not exist or if the destination data already exists.
+    ///
+    /// # Arguments
+    /// * `id` - The movie id for which to rename the data.
+    /// * `from` - The current type of the data.
+    /// * `to` - The new type of the data.
+    async fn rename_movie_data(
+        &self,
+        id: MovieId,
+        from: MovieDataType,
+        to: MovieDataType,
+    ) -> Result<(), Error>;
+
+    /// Removes a single file of the data for the given movie id and data type.
+    ///
+    /// # Arguments
+    /// * `id` - The movie id for which to remove the file.
+    /// * `data_type` - The type of data to remove.
+    async fn remove_movie_file(&self, id: MovieId, data_type: MovieDataType) -> Result<(), Error>;
+
+    /// Removes all resized variants of the screenshot of the given movie id, s.t. they are
+    /// regenerated from the current screenshot when they are requested the next time.
+    ///
+    /// # Arguments
+    /// * `id` - The movie id for which to remove the resized screenshots.
+    async fn invalidate_resized_screenshots(&self, id: MovieId) -> Result<(), Error>;
+
+    /// Moves the main screenshot with the given extension of the given movie id into its
+    /// screenshot history, s.t. it can be restored after it has been replaced. The history keeps
+    /// the given number of most recent screenshots and drops the older ones. Does nothing if
+    /// there is no such screenshot.
+    ///
+    /// # Arguments
+    /// * `id` - The movie id for which to archive the screenshot.
+    /// * `ext` - The file extension of the main screenshot.
+    /// * `history_size` - The maximal number of screenshots in the history.
+    async fn archive_screenshot(
+        &self,
+        id: MovieId,
+        ext: &str,
+        history_size: usize,
+    ) -> Result<(), Error>;
+
+    /// Returns the versions and file extensions of the screenshots in the screenshot history of
+    /// the given movie id, the most recently replaced screenshot first.
+    ///
+    /// # Arguments
+    /// * `id` - The movie id for which to return the screenshot history.
+    async fn get_screenshot_history(&self, id: MovieId) -> Result<Vec<(u32, String)>, Error>;
+
+    /// Replaces the main screenshot of the given movie id by the given version of its screenshot
+    /// history and returns the file extension of the restored screenshot. The replaced main
+    /// screenshot, if any, is archived.
+    ///
+    /// # Arguments
+    /// * `id` - The movie id for which to restore the screenshot.
+    /// * `version` - The version of the screenshot in the history to restore.
+    /// * `ext` - The file extension of the current main screenshot, if any.
+    /// * `history_size` - The maximal number of screenshots in the history.
+    async fn restore_screenshot(
+        &self,
+        id: MovieId,
+        version: u32,
+        ext: Option<&str>,
+        history_size: usize,
+    ) -> Result<String, Error>;
+
     /// Removes the data for the given movie id.
     ///
     /// # Arguments
@@ -6,7 +6,7 @@ mod test {
         path::{Path, PathBuf},
     };
 
-    use movies_db::ffmpeg::FFMpeg;
+    use movies_db::ffmpeg::{FFMpeg, FFMpegVersions};
     use tempdir::TempDir;
 
     fn write_file_to_temp_dir(temp_dir: &TempDir, file_name: &str, data: &[u8]) {
@@ -15,10 +15,54 @@ mod test {
         File::create(&file_path).unwrap().write_all(data).unwrap();
     }
 
+    #[tokio::test]
+    async fn test_ffmpeg_write_screenshot() {
+        let temp_dir = TempDir::new("test_ffmpeg_write_screenshot").unwrap();
+
+        // copy mp4 test file into temporary directory
+        let mp4_data = include_bytes!("data/file_example_MP4_480_1_5MG.mp4");
+
+        write_file_to_temp_dir(&temp_dir, "movie.mp4", mp4_data);
+
+        // test only works if ffmpeg and ffprobe are located in /usr/bin
+        let ffmpeg = FFMpeg::new(Path::new("/usr/bin")).await.unwrap();
+
+        // stream the screenshot into a file
+        let screenshot_path = temp_dir.path().join("screenshot.png");
+        let mut file = tokio::fs::File::create(&screenshot_path).await.unwrap();
+        let written = ffmpeg
+            .write_screenshot(&temp_dir.path().join("movie.mp4"), 15f64, &mut file)
+            .await
+            .unwrap();
+        drop(file);
+
+        let screenshot = std::fs::read(&screenshot_path).unwrap();
+        assert_eq!(screenshot.len() as u64, written);
+
+        // the streamed screenshot is a valid png file
+        let image =
+            image::load_from_memory_with_format(&screenshot, image::ImageFormat::Png).unwrap();
+        assert!(image.width() > 0 && image.height() > 0);
+    }
+
+    /// Writes fake ffmpeg and ffprobe binaries reporting the given version into the temp dir.
+    #[cfg(unix)]
+    fn write_fake_binaries(temp_dir: &TempDir, version: &str) {
+        use std::os::unix::fs::PermissionsExt;
+
+        for name in ["ffmpeg", "ffprobe"] {
+            let script = format!("#!/bin/sh\necho \"{} version {}\"\n", name, version);
+            write_file_to_temp_dir(temp_dir, name, script.as_bytes());
+
+            let file_path = temp_dir.path().join(name);
+            std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+
     #[tokio::test]
     async fn test_ffmpeg_init() {
         // test only works if ffmpeg and ffprobe are located in /usr/bin
-        FFMpeg::new(&Path::new("/usr/bin")).await.unwrap();
+        FFMpeg::new(Path::new("/usr/bin")).await.unwrap();
     }
 
     #[tokio::test]
@@ -31,7 +75,7 @@ mod test {
         write_file_to_temp_dir(&temp_dir, "movie.mp4", mp4_data);
 
         // test only works if ffmpeg and ffprobe are located in /usr/bin
-        let ffmpeg = FFMpeg::new(&Path::new("/usr/bin")).await.unwrap();
+        let ffmpeg = FFMpeg::new(Path::new("/usr/bin")).await.unwrap();
         let duration = ffmpeg
             .get_movie_duration(&temp_dir.path().join("movie.mp4"))
             .await
@@ -50,7 +94,7 @@ mod test {
         write_file_to_temp_dir(&temp_dir, "movie.mp4", mp4_data);
 
         // test only works if ffmpeg and ffprobe are located in /usr/bin
-        let ffmpeg = FFMpeg::new(&Path::new("/usr/bin")).await.unwrap();
+        let ffmpeg = FFMpeg::new(Path::new("/usr/bin")).await.unwrap();
         let screenshot = ffmpeg
             .create_screenshot(&temp_dir.path().join("movie.mp4"), 15f64)
             .await
@@ -59,4 +103,30 @@ mod test {
         println!("{}", screenshot.len());
         println!("{:?}", String::from_utf8_lossy(&screenshot[..20]));
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_ffmpeg_recheck() {
+        let temp_dir = TempDir::new("test_ffmpeg_recheck").unwrap();
+
+        write_fake_binaries(&temp_dir, "6.0");
+        let ffmpeg = FFMpeg::new(temp_dir.path()).await.unwrap();
+        assert_eq!(
+            ffmpeg.get_versions().await.unwrap(),
+            FFMpegVersions {
+                ffmpeg: "ffmpeg version 6.0".to_owned(),
+                ffprobe: "ffprobe version 6.0".to_owned(),
+            }
+        );
+
+        // update the binaries and check that the recheck reports the new version
+        write_fake_binaries(&temp_dir, "6.1");
+        let versions = FFMpeg::recheck(temp_dir.path()).await.unwrap();
+        assert_eq!(versions.ffmpeg, "ffmpeg version 6.1");
+        assert_eq!(versions.ffprobe, "ffprobe version 6.1");
+
+        // removing the binaries must be reported as error
+        std::fs::remove_file(temp_dir.path().join("ffprobe")).unwrap();
+        assert!(FFMpeg::recheck(temp_dir.path()).await.is_err());
+    }
 }
@@ -17,7 +17,7 @@ export default function VideosList(): JSX.Element {
     // update list of movies
     const updateList = async () => {
         const movies = await service.searchMovies();
-        const movieIds = movies.map((movie) => {
+        const movieIds = movies.results.map((movie) => {
             return movie.id;
         });
 
@@ -95,7 +95,8 @@ export class Service {
      * 
      * @param query - The query to use for searching movies.
      * 
-     * @returns a list of movie ids with title matching the given query in the given order.
+     * @returns a page of movie ids with title matching the given query in the given order
+     * together with the total number of matching movies.
      **/
     public async searchMovies(): Promise<MovieSearchResult> {
         const query = this.query;
@@ -143,6 +144,20 @@ export class Service {
         return `${this.endpoint}/movie/screenshot?id=${id}`;
     }
 
+    /**
+     * @returns the resource url for the animated preview of the video with the given id.
+     */
+    public getAnimatedPreviewUrl(id: MovieId): string {
+        return `${this.endpoint}/movie/preview?id=${id}`;
+    }
+
+    /**
+     * @returns the resource url for the subtitle of the given language of the video with the given id.
+     */
+    public getSubtitleUrl(id: MovieId, language: string): string {
+        return `${this.endpoint}/movie/subtitle?id=${id}&lang=${encodeURIComponent(language)}`;
+    }
+
     /**
      * Registers a callback to be called when the video list has been updated.
      * 
@@ -31,6 +31,38 @@ export interface ScreenshotInfo {
     mime_type: string,
 }
 
+/**
+ * The interface for infos about the animated preview data.
+ */
+export interface AnimatedPreviewInfo {
+    /// the extension of the animated preview, e.g., "gif"
+    extension: string,
+
+    // the mime type of the animated preview, e.g., "image/gif"
+    mime_type: string,
+}
+
+/**
+ * The interface for the metadata of the video stream of the movie file.
+ */
+export interface VideoMetadata {
+    /// the resolution of the video in pixels
+    width: number,
+    height: number,
+
+    /// the codec of the video, e.g., "h264"
+    codec: string,
+
+    /// the bitrate in bits per second
+    bitrate?: number,
+
+    /// the frame rate in frames per second
+    frame_rate?: number,
+
+    /// the duration in seconds
+    duration?: number,
+}
+
 /**
  * A detailed movie with additional information.
  */
@@ -38,6 +70,9 @@ export interface MovieDetailed {
     movie: MovieSubmit;
     movie_file_info?: MovieFileInfo;
     screenshot_file_info?: ScreenshotInfo;
+    video_metadata?: VideoMetadata;
+    animated_preview_info?: AnimatedPreviewInfo;
+    subtitle_languages?: string[];
     date: string;
 }
 
@@ -87,4 +122,8 @@ export interface MovieSearchResultEntry {
     title: string;
 }
 
-export type MovieSearchResult = MovieSearchResultEntry[];
\ No newline at end of file
+export interface MovieSearchResult {
+    total: number;
+    start_index: number;
+    results: MovieSearchResultEntry[];
+}
\ No newline at end of file